@@ -0,0 +1,39 @@
+//! Embed build information for `--version` and `GET /version`
+
+use std::env;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+
+    let commit = command_output("git", &["rev-parse", "HEAD"]).unwrap_or_else(|| "unknown".into());
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+    let rustc_version = command_output(&rustc, &["--version"]).unwrap_or_else(|| "unknown".into());
+    let features: Vec<_> = env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(str::to_lowercase))
+        .collect();
+
+    println!("cargo:rustc-env=ZEPHYR_GIT_COMMIT={commit}");
+    println!(
+        "cargo:rustc-env=ZEPHYR_BUILD_TIMESTAMP={}",
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+    );
+    println!("cargo:rustc-env=ZEPHYR_RUSTC_VERSION={rustc_version}");
+    let features = if features.is_empty() {
+        "none".to_string()
+    } else {
+        features.join(",")
+    };
+    println!("cargo:rustc-env=ZEPHYR_FEATURES={features}");
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(output.stdout).ok()?;
+    Some(text.trim().to_string())
+}
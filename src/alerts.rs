@@ -0,0 +1,520 @@
+//! Threshold alerts from `[alerts]`, evaluated by the proxy every 15 seconds.
+//!
+//! A rule is tracked per endpoint, or once for `no_healthy_endpoint`. Its
+//! alert fires once when the condition starts to hold and resolves once the
+//! condition has stayed clear for `clear_seconds`, so an endpoint flapping
+//! around the threshold does not fire on every sample.
+
+use crate::config::{AlertMetric, AlertRule};
+use crate::connection_tracker::EventSender;
+use crate::endpoint::{EndpointId, EndpointIndex};
+use crate::events::{Alert, ProxyEvent};
+use crate::lock::LockExt;
+use crate::logging::{log_alert_fired, log_alert_resolved};
+use crate::metrics::CounterSnapshot;
+use crate::proxy::{ProxyState, SharedState};
+use crate::stats::percentile;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::time::Duration;
+
+/// How often the rules are evaluated, also the shortest `for_seconds`
+pub const EVALUATION_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Why `rule` cannot be evaluated, checked at config load
+pub fn validate_rule(rule: &AlertRule, index: &EndpointIndex) -> Result<(), String> {
+    if rule.name.trim().is_empty() {
+        return Err("the name is empty".to_string());
+    }
+    let interval = EVALUATION_INTERVAL.as_secs();
+    if rule.for_seconds < interval {
+        return Err(format!(
+            "for_seconds must be at least {interval}, the evaluation interval"
+        ));
+    }
+    match rule.metric {
+        AlertMetric::NoHealthyEndpoint => {
+            if rule.endpoint.is_some() {
+                return Err(
+                    "no_healthy_endpoint watches every endpoint, remove endpoint".to_string(),
+                );
+            }
+            if rule.above.is_some() {
+                return Err("no_healthy_endpoint takes no threshold, remove above".to_string());
+            }
+        }
+        AlertMetric::ErrorRate | AlertMetric::LatencyP90 => {
+            let Some(above) = rule.above else {
+                return Err(format!("{} needs a threshold in above", rule.metric));
+            };
+            if !above.is_finite() || above < 0.0 {
+                return Err(format!("above must be a positive number, got {above}"));
+            }
+            if rule.metric == AlertMetric::ErrorRate && above >= 100.0 {
+                return Err(
+                    "above is a percentage, an error rate above 100 never fires".to_string()
+                );
+            }
+        }
+    }
+    if let Some(endpoint) = &rule.endpoint {
+        if index.get(&EndpointId::new(endpoint)).is_none() {
+            return Err(format!("no endpoint has the id '{endpoint}'"));
+        }
+    }
+    Ok(())
+}
+
+/// What the rules are evaluated against, taken from the proxy state
+#[derive(Debug, Clone, Default)]
+pub struct Observation {
+    pub at: DateTime<Utc>,
+    /// Ids of the configured endpoints
+    pub endpoints: Vec<String>,
+    /// Request counters by endpoint id
+    pub counters: BTreeMap<String, CounterSnapshot>,
+    /// Successful health check latencies by endpoint id, with when each was measured
+    pub latencies: BTreeMap<String, Vec<(DateTime<Utc>, u64)>>,
+    /// Endpoints that passed their last health check and are not in maintenance
+    pub healthy_endpoints: usize,
+}
+
+impl Observation {
+    pub fn of(state: &ProxyState) -> Self {
+        let endpoints: Vec<String> = state
+            .config
+            .endpoint_index()
+            .iter()
+            .map(|endpoint| endpoint.id.to_string())
+            .collect();
+        let latencies = state
+            .endpoint_status
+            .iter()
+            .map(|(id, status)| {
+                let measured = status
+                    .latency_history
+                    .get_measurements()
+                    .iter()
+                    .filter_map(|m| m.latency.map(|latency| (m.timestamp, latency)))
+                    .collect();
+                (id.to_string(), measured)
+            })
+            .collect();
+        let healthy_endpoints = state
+            .endpoint_status
+            .values()
+            .filter(|status| status.is_available() && status.maintenance_until.is_none())
+            .count();
+
+        Self {
+            at: Utc::now(),
+            endpoints,
+            counters: state.metrics.snapshots_by_id(),
+            latencies,
+            healthy_endpoints,
+        }
+    }
+}
+
+/// An alert starting or ending
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertChange {
+    Fired(Alert),
+    Resolved(Alert),
+}
+
+/// State of one rule for one endpoint
+#[derive(Debug, Default)]
+struct Track {
+    /// Since when the condition holds, while it does
+    breaching_since: Option<DateTime<Utc>>,
+    /// Since when the condition is clear, while the alert is firing
+    clear_since: Option<DateTime<Utc>>,
+    firing: Option<Alert>,
+}
+
+impl Track {
+    /// Fold in one evaluation: `measured` is whether the condition holds and
+    /// the value behind it, `None` without data, which counts as clear
+    fn update(
+        &mut self,
+        rule: &AlertRule,
+        endpoint: Option<&str>,
+        measured: Option<(bool, f64)>,
+        now: DateTime<Utc>,
+    ) -> Option<AlertChange> {
+        let value = measured.map(|(_, value)| value);
+        if measured.is_some_and(|(breaching, _)| breaching) {
+            self.clear_since = None;
+            let since = *self.breaching_since.get_or_insert(now);
+            if let Some(alert) = &mut self.firing {
+                alert.value = value;
+                return None;
+            }
+            // Rates and percentiles already cover `for_seconds`, a state has
+            // to last that long
+            let hold = match rule.metric {
+                AlertMetric::NoHealthyEndpoint => rule.for_seconds,
+                AlertMetric::ErrorRate | AlertMetric::LatencyP90 => 0,
+            };
+            if (now - since).num_seconds() < hold as i64 {
+                return None;
+            }
+            let alert = Alert {
+                rule: rule.name.clone(),
+                metric: rule.metric,
+                endpoint: endpoint.map(str::to_string),
+                value,
+                threshold: rule.above,
+                for_seconds: rule.for_seconds,
+                since,
+            };
+            self.firing = Some(alert.clone());
+            Some(AlertChange::Fired(alert))
+        } else {
+            self.breaching_since = None;
+            self.firing.as_mut()?.value = value;
+            let clear_since = *self.clear_since.get_or_insert(now);
+            if (now - clear_since).num_seconds() < rule.clear_seconds as i64 {
+                return None;
+            }
+            self.clear_since = None;
+            self.firing.take().map(AlertChange::Resolved)
+        }
+    }
+}
+
+/// Evaluates the rules against successive observations, remembering enough
+/// counter history for the longest window
+#[derive(Debug, Default)]
+pub struct AlertEvaluator {
+    /// Counters of earlier evaluations, oldest first
+    samples: VecDeque<(DateTime<Utc>, BTreeMap<String, CounterSnapshot>)>,
+    /// By rule name and endpoint id
+    tracks: BTreeMap<(String, Option<String>), Track>,
+}
+
+impl AlertEvaluator {
+    /// Evaluate `rules` against `observation`, returning the alerts that
+    /// fired or resolved. Alerts of rules no longer configured resolve.
+    pub fn evaluate(&mut self, rules: &[AlertRule], observation: Observation) -> Vec<AlertChange> {
+        let now = observation.at;
+        let mut changes = Vec::new();
+        let mut evaluated = HashSet::new();
+
+        for rule in rules {
+            let endpoints: Vec<Option<&str>> = match (rule.metric, &rule.endpoint) {
+                (AlertMetric::NoHealthyEndpoint, _) => vec![None],
+                (_, Some(endpoint)) => vec![Some(endpoint.as_str())],
+                (_, None) => observation
+                    .endpoints
+                    .iter()
+                    .map(|id| Some(id.as_str()))
+                    .collect(),
+            };
+            for endpoint in endpoints {
+                let measured = self.measure(rule, endpoint, &observation);
+                let key = (rule.name.clone(), endpoint.map(str::to_string));
+                let track = self.tracks.entry(key.clone()).or_default();
+                changes.extend(track.update(rule, endpoint, measured, now));
+                evaluated.insert(key);
+            }
+        }
+
+        self.tracks.retain(|key, track| {
+            if evaluated.contains(key) {
+                return true;
+            }
+            if let Some(alert) = track.firing.take() {
+                changes.push(AlertChange::Resolved(alert));
+            }
+            false
+        });
+
+        // Keep the newest sample at or before the start of the longest window
+        let longest = rules.iter().map(|rule| rule.for_seconds).max().unwrap_or(0);
+        let horizon = now - chrono::Duration::seconds(longest as i64);
+        self.samples.push_back((now, observation.counters));
+        while self.samples.get(1).is_some_and(|(at, _)| *at <= horizon) {
+            self.samples.pop_front();
+        }
+
+        changes
+    }
+
+    /// Alerts firing as of the last evaluation
+    pub fn active(&self) -> Vec<Alert> {
+        self.tracks
+            .values()
+            .filter_map(|track| track.firing.clone())
+            .collect()
+    }
+
+    /// Whether the rule's condition holds, and the value behind it
+    fn measure(
+        &self,
+        rule: &AlertRule,
+        endpoint: Option<&str>,
+        observation: &Observation,
+    ) -> Option<(bool, f64)> {
+        let window_start = observation.at - chrono::Duration::seconds(rule.for_seconds as i64);
+        let above = rule.above.unwrap_or_default();
+        match rule.metric {
+            AlertMetric::NoHealthyEndpoint => Some((
+                observation.healthy_endpoints == 0,
+                observation.healthy_endpoints as f64,
+            )),
+            AlertMetric::ErrorRate => {
+                let endpoint = endpoint?;
+                let current = observation.counters.get(endpoint)?;
+                // Only a full window is evaluated, a few requests after
+                // startup must not decide the rate
+                let (_, earlier) = self
+                    .samples
+                    .iter()
+                    .rev()
+                    .find(|(at, _)| *at <= window_start)?;
+                let window = current.since(&earlier.get(endpoint).copied().unwrap_or_default());
+                if window.requests == 0 {
+                    return None;
+                }
+                let failed = window.endpoint_errors.min(window.requests);
+                let rate = failed as f64 * 100.0 / window.requests as f64;
+                Some((rate > above, rate))
+            }
+            AlertMetric::LatencyP90 => {
+                let mut latencies: Vec<u64> = observation
+                    .latencies
+                    .get(endpoint?)?
+                    .iter()
+                    .filter(|(at, _)| *at >= window_start)
+                    .map(|(_, latency)| *latency)
+                    .collect();
+                if latencies.is_empty() {
+                    return None;
+                }
+                latencies.sort_unstable();
+                let p90 = percentile(&latencies, 90) as f64;
+                Some((p90 > above, p90))
+            }
+        }
+    }
+}
+
+/// Evaluate the configured rules every [`EVALUATION_INTERVAL`], publishing
+/// the firing alerts in the proxy state and each change as an event
+pub async fn run_alert_evaluator(state: SharedState, event_sender: EventSender, silent: bool) {
+    let mut evaluator = AlertEvaluator::default();
+    let mut interval = tokio::time::interval(EVALUATION_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+        let (rules, observation) = {
+            let state_guard = state.lock_or_recover();
+            (
+                state_guard.config.alerts.rules.clone(),
+                Observation::of(&state_guard),
+            )
+        };
+        let changes = evaluator.evaluate(&rules, observation);
+        state.lock_or_recover().active_alerts = evaluator.active();
+
+        for change in changes {
+            let (alert, firing) = match change {
+                AlertChange::Fired(alert) => (alert, true),
+                AlertChange::Resolved(alert) => (alert, false),
+            };
+            if !silent {
+                if firing {
+                    log_alert_fired(&alert);
+                } else {
+                    log_alert_resolved(&alert);
+                }
+            }
+            let _ = event_sender.send(ProxyEvent::AlertChanged { alert, firing });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn rule(metric: AlertMetric, above: Option<f64>, for_seconds: u64) -> AlertRule {
+        AlertRule {
+            name: "rule".to_string(),
+            metric,
+            endpoint: None,
+            above,
+            for_seconds,
+            clear_seconds: 60,
+        }
+    }
+
+    /// `seconds` after a fixed start
+    fn at(seconds: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap() + chrono::Duration::seconds(seconds)
+    }
+
+    fn healthy(seconds: i64, healthy_endpoints: usize) -> Observation {
+        Observation {
+            at: at(seconds),
+            healthy_endpoints,
+            ..Observation::default()
+        }
+    }
+
+    /// Endpoint `a` having answered `requests` requests, `errors` of them failed
+    fn counters(seconds: i64, requests: u64, errors: u64) -> Observation {
+        let snapshot = CounterSnapshot {
+            requests,
+            endpoint_errors: errors,
+            ..CounterSnapshot::default()
+        };
+        Observation {
+            at: at(seconds),
+            endpoints: vec!["a".to_string()],
+            counters: BTreeMap::from([("a".to_string(), snapshot)]),
+            ..Observation::default()
+        }
+    }
+
+    /// Endpoint `a` with one health check latency measured at each sample
+    fn latency(seconds: i64, latency_ms: u64) -> Observation {
+        Observation {
+            at: at(seconds),
+            endpoints: vec!["a".to_string()],
+            latencies: BTreeMap::from([("a".to_string(), vec![(at(seconds), latency_ms)])]),
+            ..Observation::default()
+        }
+    }
+
+    /// Feed `observations` one per evaluation, returning the changes with the
+    /// second of the evaluation that produced each
+    fn run(
+        rules: &[AlertRule],
+        observations: impl IntoIterator<Item = Observation>,
+    ) -> Vec<(i64, AlertChange)> {
+        let mut evaluator = AlertEvaluator::default();
+        let start = at(0);
+        observations
+            .into_iter()
+            .flat_map(|observation| {
+                let second = (observation.at - start).num_seconds();
+                evaluator
+                    .evaluate(rules, observation)
+                    .into_iter()
+                    .map(move |change| (second, change))
+            })
+            .collect()
+    }
+
+    fn fired_at(changes: &[(i64, AlertChange)]) -> Vec<i64> {
+        changes
+            .iter()
+            .filter(|(_, change)| matches!(change, AlertChange::Fired(_)))
+            .map(|(second, _)| *second)
+            .collect()
+    }
+
+    fn resolved_at(changes: &[(i64, AlertChange)]) -> Vec<i64> {
+        changes
+            .iter()
+            .filter(|(_, change)| matches!(change, AlertChange::Resolved(_)))
+            .map(|(second, _)| *second)
+            .collect()
+    }
+
+    #[test]
+    fn no_healthy_endpoint_fires_once_the_state_held_for_the_window() {
+        let rules = [rule(AlertMetric::NoHealthyEndpoint, None, 60)];
+        let changes = run(&rules, (0..10).map(|i| healthy(i * 15, 0)));
+        assert_eq!(fired_at(&changes), [60]);
+        assert!(resolved_at(&changes).is_empty());
+
+        // A healthy sample inside the window starts the hold over
+        let samples = [0, 0, 0, 1, 0, 0, 0, 0, 0];
+        let changes = run(
+            &rules,
+            samples
+                .iter()
+                .enumerate()
+                .map(|(i, &up)| healthy(i as i64 * 15, up)),
+        );
+        assert_eq!(fired_at(&changes), [120]);
+    }
+
+    #[test]
+    fn alert_resolves_after_staying_clear_for_clear_seconds() {
+        let rules = [rule(AlertMetric::NoHealthyEndpoint, None, 15)];
+        // Down until 30s, healthy from 45s on
+        let changes = run(
+            &rules,
+            (0..12).map(|i| healthy(i * 15, usize::from(i >= 3))),
+        );
+        assert_eq!(fired_at(&changes), [15]);
+        assert_eq!(resolved_at(&changes), [105]);
+
+        let AlertChange::Resolved(alert) = &changes[1].1 else {
+            panic!("{changes:?}");
+        };
+        assert_eq!(alert.since, at(0));
+        assert_eq!(alert.value, Some(1.0));
+    }
+
+    #[test]
+    fn flapping_condition_fires_and_resolves_once() {
+        let rules = [rule(AlertMetric::LatencyP90, Some(2000.0), 15)];
+        // Crossing the threshold on every other sample for five minutes, then
+        // staying below it
+        let samples = (0..20)
+            .map(|i| if i % 2 == 0 { 2500 } else { 500 })
+            .chain([500; 6]);
+        let changes = run(
+            &rules,
+            samples
+                .enumerate()
+                .map(|(i, ms)| latency(i as i64 * 15, ms)),
+        );
+        assert_eq!(fired_at(&changes), [0]);
+        // Clear from 285s on, resolved 60s later
+        assert_eq!(resolved_at(&changes), [345]);
+    }
+
+    #[test]
+    fn error_rate_waits_for_a_full_window() {
+        let mut rule = rule(AlertMetric::ErrorRate, Some(20.0), 60);
+        rule.endpoint = Some("a".to_string());
+        // Half of the requests fail from the start
+        let changes = run(
+            &[rule.clone()],
+            (0..8).map(|i| counters(i * 15, i as u64 * 10, i as u64 * 5)),
+        );
+        assert_eq!(fired_at(&changes), [60]);
+        let AlertChange::Fired(alert) = &changes[0].1 else {
+            panic!("{changes:?}");
+        };
+        assert_eq!(alert.endpoint.as_deref(), Some("a"));
+        assert_eq!(alert.value, Some(50.0));
+        assert_eq!(alert.threshold, Some(20.0));
+
+        // No requests in the window counts as clear
+        let changes = run(&[rule], (0..8).map(|i| counters(i * 15, 0, 0)));
+        assert!(changes.is_empty(), "{changes:?}");
+    }
+
+    #[test]
+    fn alerts_of_a_removed_rule_resolve() {
+        let rules = [rule(AlertMetric::NoHealthyEndpoint, None, 15)];
+        let mut evaluator = AlertEvaluator::default();
+        evaluator.evaluate(&rules, healthy(0, 0));
+        evaluator.evaluate(&rules, healthy(15, 0));
+        assert_eq!(evaluator.active().len(), 1);
+
+        let changes = evaluator.evaluate(&[], healthy(30, 0));
+        assert!(matches!(changes[..], [AlertChange::Resolved(_)]));
+        assert!(evaluator.active().is_empty());
+    }
+}
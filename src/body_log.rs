@@ -0,0 +1,213 @@
+//! Request and response bodies at debug level, for finding out what a client
+//! sent and an endpoint answered. Bodies are printed inline up to
+//! `logging.body_max_bytes`, or written whole to one file each under
+//! `logging.body_to_file`, which is pruned by count and age.
+
+use crate::config::LoggingConfig;
+use crate::connection_tracker::ConnectionId;
+use crate::logging::{log_body, log_body_file, log_body_file_error};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Extension of body files; pruning leaves anything else alone
+const BODY_FILE_EXTENSION: &str = "body";
+
+/// Which body of a proxied request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BodyKind {
+    Request,
+    Response,
+}
+
+impl BodyKind {
+    /// Part of the body file name
+    fn as_str(self) -> &'static str {
+        match self {
+            BodyKind::Request => "request",
+            BodyKind::Response => "response",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            BodyKind::Request => "Request",
+            BodyKind::Response => "Response",
+        }
+    }
+}
+
+/// Whether bodies are logged: only while debug logs are on
+pub(crate) fn enabled() -> bool {
+    tracing::enabled!(tracing::Level::DEBUG)
+}
+
+/// Log the `kind` body of `connection_id` inline, or to its file when
+/// `logging.body_to_file` is set
+pub(crate) fn record(
+    config: &LoggingConfig,
+    connection_id: &ConnectionId,
+    kind: BodyKind,
+    body: &[u8],
+) {
+    let Some(dir) = &config.body_to_file else {
+        let shown = &body[..body.len().min(config.body_max_bytes)];
+        log_body(
+            connection_id,
+            kind.label(),
+            body.len(),
+            &String::from_utf8_lossy(shown),
+        );
+        return;
+    };
+    let written = write_body_file(dir, connection_id, kind, body).and_then(|path| {
+        prune(dir, config.body_files_kept, max_age(config))?;
+        Ok(path)
+    });
+    match written {
+        Ok(path) => log_body_file(connection_id, kind.label(), body.len(), &path),
+        Err(e) => log_body_file_error(dir, &e),
+    }
+}
+
+fn max_age(config: &LoggingConfig) -> Option<Duration> {
+    (config.body_files_max_age_hours > 0)
+        .then(|| Duration::from_secs(config.body_files_max_age_hours * 3600))
+}
+
+fn write_body_file(
+    dir: &Path,
+    connection_id: &ConnectionId,
+    kind: BodyKind,
+    body: &[u8],
+) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!(
+        "{connection_id}.{}.{BODY_FILE_EXTENSION}",
+        kind.as_str()
+    ));
+    fs::write(&path, body)?;
+    Ok(path)
+}
+
+/// Delete the body files in `dir` beyond the newest `kept` (0 keeps all) and
+/// those older than `max_age`
+fn prune(dir: &Path, kept: usize, max_age: Option<Duration>) -> io::Result<()> {
+    let mut files: Vec<(SystemTime, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == BODY_FILE_EXTENSION)
+        })
+        .filter_map(|path| Some((fs::metadata(&path).ok()?.modified().ok()?, path)))
+        .collect();
+    // Newest first; ids start with the time, so names break ties
+    files.sort_by(|a, b| b.cmp(a));
+
+    let now = SystemTime::now();
+    for (index, (modified, path)) in files.iter().enumerate() {
+        let too_many = kept > 0 && index >= kept;
+        let too_old = max_age
+            .is_some_and(|max_age| now.duration_since(*modified).unwrap_or_default() > max_age);
+        if too_many || too_old {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zephyr-bodies-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn config(dir: &Path, kept: usize) -> LoggingConfig {
+        LoggingConfig {
+            body_to_file: Some(dir.to_path_buf()),
+            body_files_kept: kept,
+            ..LoggingConfig::default()
+        }
+    }
+
+    #[test]
+    fn bodies_are_written_whole_to_a_file_per_connection() {
+        let dir = body_dir("whole");
+        let config = LoggingConfig {
+            body_max_bytes: 4,
+            ..config(&dir, 0)
+        };
+        let id = crate::connection_tracker::generate_connection_id();
+        record(&config, &id, BodyKind::Request, b"{\"prompt\": \"long\"}");
+        record(&config, &id, BodyKind::Response, b"answer");
+
+        assert_eq!(
+            names(&dir),
+            [format!("{id}.request.body"), format!("{id}.response.body")]
+        );
+        let request = fs::read(dir.join(format!("{id}.request.body"))).unwrap();
+        assert_eq!(request, b"{\"prompt\": \"long\"}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pruning_keeps_the_newest_files() {
+        let dir = body_dir("count");
+        let config = config(&dir, 2);
+        let ids: Vec<ConnectionId> = (0..3)
+            .map(|_| crate::connection_tracker::generate_connection_id())
+            .collect();
+        for id in &ids {
+            record(&config, id, BodyKind::Request, b"{}");
+        }
+
+        assert_eq!(
+            names(&dir),
+            [
+                format!("{}.request.body", ids[1]),
+                format!("{}.request.body", ids[2])
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pruning_deletes_old_files_and_leaves_other_files_alone() {
+        let dir = body_dir("age");
+        fs::create_dir_all(&dir).unwrap();
+        let old = dir.join("req_1_000001.request.body");
+        fs::write(&old, "{}").unwrap();
+        let two_days_ago = SystemTime::now() - Duration::from_secs(48 * 3600);
+        fs::File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(two_days_ago)
+            .unwrap();
+        fs::write(dir.join("notes.txt"), "mine").unwrap();
+
+        let id = crate::connection_tracker::generate_connection_id();
+        record(&config(&dir, 0), &id, BodyKind::Request, b"{}");
+
+        assert_eq!(
+            names(&dir),
+            ["notes.txt".to_string(), format!("{id}.request.body")]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,36 @@
+//! Build information embedded by `build.rs`
+
+/// Full git commit hash of the build, `unknown` outside a git checkout
+pub const GIT_COMMIT: &str = env!("ZEPHYR_GIT_COMMIT");
+/// When the binary was built, RFC 3339 in UTC
+pub const BUILD_TIMESTAMP: &str = env!("ZEPHYR_BUILD_TIMESTAMP");
+/// Output of `rustc --version` for the compiler that built the binary
+pub const RUSTC_VERSION: &str = env!("ZEPHYR_RUSTC_VERSION");
+/// Comma-separated cargo features the binary was built with, or `none`
+const FEATURES: &str = env!("ZEPHYR_FEATURES");
+
+/// Text printed by `--version`
+pub const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "\ncommit:   ",
+    env!("ZEPHYR_GIT_COMMIT"),
+    "\nbuilt:    ",
+    env!("ZEPHYR_BUILD_TIMESTAMP"),
+    "\nrustc:    ",
+    env!("ZEPHYR_RUSTC_VERSION"),
+    "\nfeatures: ",
+    env!("ZEPHYR_FEATURES"),
+);
+
+/// Abbreviated commit hash as shown by `git log --oneline`
+pub fn short_commit() -> &'static str {
+    GIT_COMMIT.get(..7).unwrap_or(GIT_COMMIT)
+}
+
+/// Enabled cargo features
+pub fn features() -> Vec<&'static str> {
+    if FEATURES == "none" {
+        return Vec::new();
+    }
+    FEATURES.split(',').collect()
+}
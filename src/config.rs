@@ -1,10 +1,19 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-use crate::i18n::Language;
+use crate::alerts;
+use crate::endpoint::{normalize_url, EndpointIndex, EndpointRef};
+use crate::health::{
+    HealthProbe, HEALTH_CHECK_MODEL, HEALTH_CHECK_SYSTEM_PROMPT, MINIMAL_HEALTH_PROMPT,
+};
+use crate::i18n::{self, I18n, Language};
+use crate::logging::console_warning;
+use crate::maintenance::{self, MaintenanceTimeZone};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -16,6 +25,41 @@ pub struct Config {
     /// UI and display settings
     #[serde(default)]
     pub ui: UiConfig,
+    /// Request retry and fallback settings
+    #[serde(default)]
+    pub retry: RetryConfig,
+    /// Console log formatting
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// Requests forwarded without retries, fallback or connection tracking
+    #[serde(default)]
+    pub routing: RoutingConfig,
+    /// Responses to GET requests kept in memory and served without an upstream call
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// Completed request records kept in a local SQLite file
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// Threshold alerts evaluated by the proxy
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+    /// File the group tokens are read from, relative to the config file;
+    /// `--env-file` overrides it (default: `.env`)
+    #[serde(default)]
+    pub env_file: Option<PathBuf>,
+    /// File the configuration was loaded from
+    #[serde(skip)]
+    pub source_path: Option<PathBuf>,
+    /// When the configuration was loaded or last reloaded
+    #[serde(skip)]
+    pub loaded_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Configured endpoints by id and URL, built once when the file is parsed
+    #[serde(skip)]
+    pub(crate) endpoint_index: EndpointIndex,
+    /// A group's Claude CLI was not found and `http_fallback` or
+    /// `allow_missing_claude_binary` let the proxy start anyway
+    #[serde(skip)]
+    pub(crate) claude_binary_missing: bool,
 }
 
 /// Group of endpoints sharing the same auth token
@@ -25,14 +69,83 @@ pub struct Group {
     pub name: String,
     /// Environment variable name containing the auth token
     pub auth_token_env: String,
+    /// Which Authorization header this group's endpoints receive
+    #[serde(default)]
+    pub auth_mode: AuthMode,
     /// Endpoints in this group (simplified format)
     pub endpoints: Vec<SimpleEndpoint>,
     /// Whether this is the default group
     #[serde(default)]
     pub default: Option<bool>,
+    /// Failover tier, lower is preferred. Auto mode uses the most preferred
+    /// tier with a healthy endpoint and never leaves it for latency.
+    #[serde(default = "default_group_priority")]
+    pub priority: u8,
+    /// How long requests to this group's endpoints wait for response headers,
+    /// overriding `server.response_header_timeout_seconds`
+    #[serde(default)]
+    pub request_timeout_seconds: Option<u64>,
+    /// How long this group's endpoints may take to stream a response body,
+    /// overriding `server.response_body_timeout_seconds`
+    #[serde(default)]
+    pub response_body_timeout_seconds: Option<u64>,
     /// Optional group-specific health check settings
     #[serde(default)]
     pub health_check: Option<HealthCheckConfig>,
+    /// Claude CLI (or a wrapper script) for this group's health checks,
+    /// overriding `health_check.claude_binary_path`
+    #[serde(default)]
+    pub claude_binary_path: Option<String>,
+    /// Health check model for this group's endpoints, overriding
+    /// `health_check.model`, for relays that do not offer the default one
+    #[serde(default)]
+    pub health_check_model: Option<String>,
+    /// Overrides `health_check.prompt` for this group
+    #[serde(default)]
+    pub health_check_prompt: Option<String>,
+    /// Overrides `health_check.system_prompt` for this group
+    #[serde(default)]
+    pub health_check_system_prompt: Option<String>,
+    /// Price per million input tokens, 0 disables cost estimation
+    #[serde(default)]
+    pub input_price_per_mtok: f64,
+    /// Price per million output tokens, 0 disables cost estimation
+    #[serde(default)]
+    pub output_price_per_mtok: f64,
+}
+
+impl Group {
+    /// Token from `auth_token_env`, as the env file last provided it or else
+    /// from the environment, `None` while the variable is unset or empty.
+    /// Groups without one stay configured but are never selected or checked.
+    pub fn auth_token(&self) -> Option<String> {
+        crate::env_file::var(&self.auth_token_env).filter(|token| !token.trim().is_empty())
+    }
+}
+
+/// Which Authorization header requests to a group's endpoints carry.
+/// Health checks always use the group token.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMode {
+    /// Replace the client's header with the group token
+    #[default]
+    Inject,
+    /// Forward the client's header untouched; requests without one are
+    /// answered with 401
+    Passthrough,
+    /// Forward the client's header when present, the group token otherwise
+    PreferClient,
+}
+
+impl AuthMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthMode::Inject => "inject",
+            AuthMode::Passthrough => "passthrough",
+            AuthMode::PreferClient => "prefer_client",
+        }
+    }
 }
 
 /// Individual endpoint configuration (legacy compatibility)
@@ -54,6 +167,97 @@ pub struct SimpleEndpoint {
     pub url: String,
     /// Display name for this endpoint
     pub name: String,
+    /// Stable identifier that recorded history is kept under, so the URL can
+    /// change without losing it (default: the name)
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Labels such as region or provider, used by the runtime tag filter
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Recurring windows during which the endpoint is taken out of rotation
+    /// and not health checked
+    #[serde(default)]
+    pub maintenance: Vec<MaintenanceWindow>,
+    /// Whether the proxy starts on this endpoint; only allowed in the default
+    /// group, whose first endpoint is used when none is marked
+    #[serde(default)]
+    pub default: Option<bool>,
+    /// How long requests to this endpoint wait for response headers,
+    /// overriding the group's value
+    #[serde(default)]
+    pub request_timeout_seconds: Option<u64>,
+    /// How long this endpoint may take to stream a response body, overriding
+    /// the group's value
+    #[serde(default)]
+    pub response_body_timeout_seconds: Option<u64>,
+}
+
+/// A recurring maintenance window, in `[server] maintenance_timezone`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    /// Start and end time such as "03:00-03:15"; a window ending before it
+    /// starts runs past midnight
+    pub window: String,
+    /// Days the window starts on, such as "mon"; every day when empty
+    #[serde(default)]
+    pub days: Vec<String>,
+}
+
+impl SimpleEndpoint {
+    pub fn id(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Request timeouts above this are accepted with a warning
+const LONG_REQUEST_TIMEOUT_SECONDS: u64 = 1800;
+
+/// Health check prompts longer than this are accepted with a warning, every
+/// check pays for them
+const LONG_HEALTH_PROMPT_CHARS: usize = 50;
+
+/// How long a request to one endpoint waits for response headers, or for its
+/// body with [`Config::body_timeout_for`], and which setting decided it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestTimeout {
+    pub seconds: u64,
+    pub source: TimeoutSource,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutSource {
+    /// The endpoint's override
+    Endpoint,
+    /// The group's override
+    Group,
+    /// The `[server]` setting
+    Server,
+}
+
+impl RequestTimeout {
+    pub fn duration(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.seconds)
+    }
+}
+
+impl Default for RequestTimeout {
+    fn default() -> Self {
+        Self {
+            seconds: default_response_header_timeout(),
+            source: TimeoutSource::Server,
+        }
+    }
+}
+
+impl std::fmt::Display for RequestTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let source = match self.source {
+            TimeoutSource::Endpoint => "endpoint override",
+            TimeoutSource::Group => "group override",
+            TimeoutSource::Server => "server default",
+        };
+        write!(f, "{}s ({source})", self.seconds)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,13 +266,154 @@ pub struct ServerConfig {
     /// Minimum improvement in milliseconds to trigger endpoint switch
     #[serde(default = "default_switch_threshold")]
     pub switch_threshold_ms: u64,
-    /// Maximum time to wait for graceful endpoint switch
+    /// How long the endpoint left by a switch drains: it finishes its in-flight
+    /// requests but receives no new ones, including fallback
     #[serde(default = "default_graceful_timeout")]
     pub graceful_switch_timeout_ms: u64,
+    /// How long after a switch failed requests on the new endpoint need to
+    /// repeat before it is marked failed or left (0 disables)
+    #[serde(default = "default_switch_grace")]
+    pub switch_grace_seconds: u64,
+    /// How long an upstream attempt waits for the response headers before it
+    /// fails with 504; only this phase is retried
+    #[serde(default = "default_response_header_timeout")]
+    pub response_header_timeout_seconds: u64,
+    /// Upper bound for streaming a response body once its headers arrived
+    #[serde(default = "default_response_body_timeout")]
+    pub response_body_timeout_seconds: u64,
+    /// Abort a response body when no chunk arrives for this long (0 disables)
+    #[serde(default = "default_stream_idle_timeout")]
+    pub stream_idle_timeout_seconds: u64,
+    /// What the client receives when a stalled stream is aborted
+    #[serde(default)]
+    pub stream_stall_action: StreamStallAction,
+    /// Environment variable holding the bearer token required by admin routes
+    #[serde(default)]
+    pub admin_token_env: Option<String>,
+    /// Endpoints the health checks may switch to in Auto mode
+    #[serde(default)]
+    pub auto_switch_scope: AutoSwitchScope,
+    /// Between endpoints measuring within the switch threshold of each other,
+    /// prefer the one that is usually faster at this hour of day
+    #[serde(default)]
+    pub prefer_hourly_profile: bool,
+    /// Close a local connection that has not sent complete request headers
+    /// within this long (0 disables)
+    #[serde(default = "default_header_read_timeout")]
+    pub http1_header_read_timeout_ms: u64,
+    /// Close a local keep-alive connection with no request in flight and no
+    /// traffic for this long (0 disables)
+    #[serde(default = "default_idle_connection_timeout")]
+    pub idle_connection_timeout_seconds: u64,
+    /// Local connections accepted at once; further ones are closed right away
+    /// (0 means no limit)
+    #[serde(default = "default_max_local_connections")]
+    pub max_concurrent_local_connections: usize,
+    /// Proxied requests handled at once; further ones are answered with 503
+    /// right away (0 means no limit)
+    #[serde(default = "default_max_in_flight_requests")]
+    pub max_in_flight_requests: usize,
+    /// Time zone of endpoint maintenance windows: "local", "utc" or an
+    /// offset such as "+08:00"
+    #[serde(default = "default_maintenance_timezone")]
+    pub maintenance_timezone: String,
+    /// Keep a pooled connection open to the endpoint fallback would try
+    /// first, so failing over skips the TCP and TLS handshake
+    #[serde(default)]
+    pub warm_standby: bool,
+    /// Extra listeners besides `port`, each optionally limited to one group
+    #[serde(default)]
+    pub listeners: Vec<ListenerConfig>,
+}
+
+/// An extra local port the proxy serves on, from `[[server.listeners]]`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ListenerConfig {
+    pub port: u16,
+    #[serde(default = "default_bind_address")]
+    pub bind_address: IpAddr,
+    /// Only this group's endpoints serve requests arriving here, for both
+    /// endpoint selection and fallback. Unset means every group.
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+impl ListenerConfig {
+    /// The listener on `server.port`, which serves every group
+    pub fn primary(port: u16) -> Self {
+        Self {
+            port,
+            bind_address: default_bind_address(),
+            group: None,
+        }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", SocketAddr::new(self.bind_address, self.port))
+    }
+}
+
+/// Groups automatic switching may move the current endpoint into
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoSwitchScope {
+    /// Every configured endpoint
+    #[default]
+    All,
+    /// Only the default group, other groups stay manual and fallback only
+    DefaultGroup,
+    /// Only the group of the current endpoint
+    CurrentGroup,
+}
+
+/// How a stalled streaming response is ended for the client
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamStallAction {
+    /// Send a terminal SSE `error` event (plain truncation for non-SSE bodies)
+    #[default]
+    ErrorEvent,
+    /// End the body without further data
+    Truncate,
+}
+
+/// What health checks run to judge an endpoint
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthCheckMode {
+    /// The Claude CLI when it is found, otherwise what `http_fallback` and
+    /// `allow_missing_claude_binary` say
+    #[default]
+    Auto,
+    /// Only the Claude CLI; a missing CLI refuses to start
+    ClaudeCli,
+    /// A `count_tokens` request, which needs no CLI and costs no tokens
+    Http,
+}
+
+/// Which HTTP check latency feeds switch decisions
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthCheckMeasure {
+    /// Checks that reused an open connection, free of connection setup
+    #[default]
+    Warm,
+    /// Checks that had to open a connection, as the first request after an
+    /// idle period does
+    Cold,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HealthCheckConfig {
+    /// What the checks run, the Claude CLI unless set otherwise
+    #[serde(default)]
+    pub mode: HealthCheckMode,
+    /// In `auto` mode, check over HTTP when a Claude CLI is not found
+    #[serde(default)]
+    pub http_fallback: bool,
+    /// In HTTP mode, whether cold or warm latencies drive switching
+    #[serde(default)]
+    pub measure: HealthCheckMeasure,
     /// Base health check interval in seconds
     pub interval_seconds: u64,
     /// Minimum health check interval (for dynamic scaling)
@@ -84,156 +429,1128 @@ pub struct HealthCheckConfig {
     pub timeout_seconds: u64,
     /// Path to Claude CLI binary
     pub claude_binary_path: String,
+    /// Start without health checks when a Claude CLI is not found, serving
+    /// the default endpoint, instead of refusing to start
+    #[serde(default)]
+    pub allow_missing_claude_binary: bool,
+    /// Maximum number of health checks running at the same time
+    #[serde(default = "default_max_concurrent_checks")]
+    pub max_concurrent_checks: usize,
+    /// Verify the starting endpoint before the proxy accepts traffic
+    #[serde(default = "default_startup_check")]
+    pub startup_check: bool,
+    /// Before serving, send one `count_tokens` request per group to find
+    /// tokens its endpoints reject; checked again whenever a token changes
+    #[serde(default)]
+    pub validate_tokens: bool,
+    /// Refuse to start when `validate_tokens` finds a rejected token, set by
+    /// `--strict`
+    #[serde(default)]
+    pub strict_token_validation: bool,
+    /// Text the CLI output must contain (case-insensitive) for a check to pass;
+    /// empty accepts any output
+    #[serde(default = "default_expected_output")]
+    pub expected_output: String,
+    /// Model the CLI is asked to answer with, the cheapest by default
+    #[serde(default = "default_health_check_model")]
+    pub model: String,
+    /// Prompt of every check, kept minimal for token spend
+    #[serde(default = "default_health_check_prompt")]
+    pub prompt: String,
+    /// Appended to the CLI's system prompt to force a short reply
+    #[serde(default = "default_health_check_system_prompt")]
+    pub system_prompt: String,
+}
+
+impl HealthCheckConfig {
+    /// Model, prompt and system prompt set here, without group overrides
+    pub fn probe(&self) -> HealthProbe {
+        HealthProbe {
+            model: self.model.clone(),
+            prompt: self.prompt.clone(),
+            system_prompt: self.system_prompt.clone(),
+        }
+    }
 }
 
 /// UI configuration
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UiConfig {
     /// Language setting for the interface
     #[serde(default)]
     pub language: Language,
+    /// Show per-endpoint retry counters in the endpoints table
+    #[serde(default)]
+    pub show_retry_column: bool,
+    /// Currency symbol shown in front of estimated costs
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    /// Connections open this long are marked yellow in the dashboard
+    #[serde(default = "default_connection_warn")]
+    pub connection_warn_seconds: u64,
+    /// Connections open this long are marked red in the dashboard
+    #[serde(default = "default_connection_alert")]
+    pub connection_alert_seconds: u64,
+    /// `"auto"` scales each trend sparkline to its own range, `"fixed(800)"`
+    /// draws every endpoint from 0 to 800ms
+    #[serde(default)]
+    pub sparkline_scale: SparklineScale,
+    /// Completed requests kept for the recent requests panel and
+    /// `/connections/recent`, 0 keeps none
+    #[serde(default = "default_recent_requests_size")]
+    pub recent_requests_size: usize,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            language: Language::default(),
+            show_retry_column: false,
+            currency: default_currency(),
+            connection_warn_seconds: default_connection_warn(),
+            connection_alert_seconds: default_connection_alert(),
+            sparkline_scale: SparklineScale::default(),
+            recent_requests_size: default_recent_requests_size(),
+        }
+    }
+}
+
+/// Latency range the dashboard trend sparklines are drawn against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum SparklineScale {
+    /// Between each endpoint's own fastest and slowest recent check
+    #[default]
+    Auto,
+    /// From 0 to this many milliseconds for every endpoint, so they compare
+    Fixed(u64),
+}
+
+impl TryFrom<String> for SparklineScale {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value == "auto" {
+            return Ok(Self::Auto);
+        }
+        value
+            .strip_prefix("fixed(")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .and_then(|max_ms| max_ms.trim().parse().ok())
+            .filter(|max_ms| *max_ms > 0)
+            .map(Self::Fixed)
+            .ok_or_else(|| format!("expected \"auto\" or \"fixed(<max_ms>)\", got \"{value}\""))
+    }
+}
+
+impl From<SparklineScale> for String {
+    fn from(scale: SparklineScale) -> Self {
+        match scale {
+            SparklineScale::Auto => "auto".to_string(),
+            SparklineScale::Fixed(max_ms) => format!("fixed({max_ms})"),
+        }
+    }
+}
+
+/// Requests that bypass retries, fallback and connection tracking
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutingConfig {
+    /// Rules checked in order; a request matching any of them is passed through
+    #[serde(default)]
+    pub passthrough: Vec<PassthroughRule>,
+}
+
+impl RoutingConfig {
+    /// Whether a request with this method and path matches a passthrough rule
+    pub fn is_passthrough(&self, method: &str, path: &str) -> bool {
+        self.passthrough
+            .iter()
+            .any(|rule| rule.matches(method, path))
+    }
+}
+
+/// Method and path glob of requests forwarded with a single attempt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PassthroughRule {
+    /// HTTP method, case-insensitive; `*` matches any method
+    #[serde(default = "default_passthrough_method")]
+    pub method: String,
+    /// Path glob where `*` matches any run of characters, e.g. `/v1/models*`
+    pub path: String,
+}
+
+impl PassthroughRule {
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        (self.method == "*" || self.method.eq_ignore_ascii_case(method))
+            && glob_matches(&self.path, path)
+    }
+}
+
+/// Responses cached for GET requests on `paths`, off while `paths` is empty
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// Path globs as in passthrough rules, e.g. `/v1/models*`
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// How long a cached response is served before it is revalidated upstream
+    #[serde(default = "default_cache_ttl")]
+    pub ttl_seconds: u64,
+    /// Larger response bodies are forwarded without being cached
+    #[serde(default = "default_cache_max_body_bytes")]
+    pub max_body_bytes: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            ttl_seconds: default_cache_ttl(),
+            max_body_bytes: default_cache_max_body_bytes(),
+        }
+    }
+}
+
+impl CacheConfig {
+    /// Whether a response to this request may come from the cache
+    pub fn is_cacheable(&self, method: &str, path: &str) -> bool {
+        method.eq_ignore_ascii_case("GET")
+            && self.paths.iter().any(|pattern| glob_matches(pattern, path))
+    }
+}
+
+/// Match `text` against `pattern`, where `*` matches any run of characters
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*` in the pattern
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Console log formatting, used without the dashboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    /// Show endpoint display names, padded to equal width, instead of URLs
+    #[serde(default)]
+    pub use_endpoint_names: bool,
+    /// Collapse identical failures for an endpoint within this window (0 disables)
+    #[serde(default = "default_duplicate_window")]
+    pub duplicate_window_seconds: u64,
+    /// Log one summary line per health check cycle, with per-endpoint
+    /// successes demoted to debug
+    #[serde(default = "default_cycle_summary")]
+    pub cycle_summary: bool,
+    /// Emoji and symbols in console output; off prints plain ASCII tags
+    /// such as `[HEALTH]` for log pipelines that mangle anything else
+    #[serde(default = "default_emoji")]
+    pub emoji: bool,
+    /// Bytes of each request and response body printed at debug level
+    #[serde(default = "default_body_max_bytes")]
+    pub body_max_bytes: usize,
+    /// Directory debug level writes whole bodies to instead, one file per
+    /// body named by connection id, logging only the path and size
+    #[serde(default)]
+    pub body_to_file: Option<PathBuf>,
+    /// Body files kept in `body_to_file`, the oldest are deleted (0 keeps all)
+    #[serde(default = "default_body_files_kept")]
+    pub body_files_kept: usize,
+    /// Body files older than this are deleted (0 keeps them)
+    #[serde(default = "default_body_files_max_age_hours")]
+    pub body_files_max_age_hours: u64,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            use_endpoint_names: false,
+            duplicate_window_seconds: default_duplicate_window(),
+            cycle_summary: default_cycle_summary(),
+            emoji: default_emoji(),
+            body_max_bytes: default_body_max_bytes(),
+            body_to_file: None,
+            body_files_kept: default_body_files_kept(),
+            body_files_max_age_hours: default_body_files_max_age_hours(),
+        }
+    }
+}
+
+/// Where completed requests are recorded, needs the `sqlite` cargo feature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// SQLite file, relative to the working directory
+    #[serde(default = "default_storage_path")]
+    pub path: PathBuf,
+    /// Delete records older than this many days (0 keeps them forever)
+    #[serde(default = "default_storage_retention")]
+    pub retention_days: u64,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_storage_path(),
+            retention_days: default_storage_retention(),
+        }
+    }
+}
+
+/// Threshold alerts, each firing once when its condition starts to hold and
+/// resolving once when it stops
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertsConfig {
+    #[serde(default)]
+    pub rules: Vec<AlertRule>,
+}
+
+/// One `[[alerts.rules]]` entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    /// Unique name shown when the alert fires
+    pub name: String,
+    pub metric: AlertMetric,
+    /// Id of the endpoint to watch; without one every endpoint is watched
+    /// separately. Not used by `no_healthy_endpoint`.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Fires above this value: percent for `error_rate`, milliseconds for
+    /// `latency_p90`. Not used by `no_healthy_endpoint`.
+    #[serde(default)]
+    pub above: Option<f64>,
+    /// Window the error rate and p90 are taken over, or how long no endpoint
+    /// may be healthy
+    pub for_seconds: u64,
+    /// How long the condition must stay clear before the alert resolves, so a
+    /// flapping condition fires once
+    #[serde(default = "default_alert_clear_seconds")]
+    pub clear_seconds: u64,
+}
+
+/// What an alert rule watches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    /// Share of proxied requests the endpoint failed
+    ErrorRate,
+    /// 90th percentile of the endpoint's successful health check latencies
+    LatencyP90,
+    /// No endpoint passes its health check
+    NoHealthyEndpoint,
+}
+
+impl std::fmt::Display for AlertMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlertMetric::ErrorRate => write!(f, "error_rate"),
+            AlertMetric::LatencyP90 => write!(f, "latency_p90"),
+            AlertMetric::NoHealthyEndpoint => write!(f, "no_healthy_endpoint"),
+        }
+    }
+}
+
+/// Retry and fallback configuration for proxied requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryConfig {
+    /// Additional attempts on the same endpoint after the first one fails
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each following retry
+    #[serde(default = "default_retry_base_delay")]
+    pub base_delay_ms: u64,
+    /// Upper bound for the delay between retries
+    #[serde(default = "default_retry_max_delay")]
+    pub max_delay_ms: u64,
+    /// Which other endpoints may serve a request once retries are exhausted
+    #[serde(default)]
+    pub fallback: FallbackPolicy,
+    /// Answer requests with 503 right away, skipping retries and fallback,
+    /// while the last full health check cycle found every endpoint down
+    #[serde(default = "default_fail_fast")]
+    pub fail_fast_when_all_down: bool,
+    /// Upstream statuses retried on the same endpoint before falling back.
+    /// Unset means 408, 429 and every server error.
+    #[serde(default)]
+    pub retry_on_status: Option<Vec<u16>>,
+    /// Upstream statuses that skip the remaining retries and go straight to
+    /// the fallback endpoints. These win over `retry_on_status`.
+    #[serde(default)]
+    pub fallback_on_status: Vec<u16>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+            base_delay_ms: default_retry_base_delay(),
+            max_delay_ms: default_retry_max_delay(),
+            fallback: FallbackPolicy::default(),
+            fail_fast_when_all_down: default_fail_fast(),
+            retry_on_status: None,
+            fallback_on_status: Vec::new(),
+        }
+    }
+}
+
+/// Endpoints eligible for fallback after the primary endpoint gives up
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FallbackPolicy {
+    /// Return the primary endpoint's error without trying other endpoints
+    Off,
+    /// Only endpoints in the same group as the failed one
+    SameGroup,
+    /// Every other configured endpoint
+    #[default]
+    All,
+}
+
+impl FallbackPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FallbackPolicy::Off => "off",
+            FallbackPolicy::SameGroup => "same_group",
+            FallbackPolicy::All => "all",
+        }
+    }
 }
 
 // Default values
+fn default_max_retries() -> u32 {
+    2
+}
+fn default_retry_base_delay() -> u64 {
+    500
+}
+fn default_retry_max_delay() -> u64 {
+    5000
+}
+fn default_fail_fast() -> bool {
+    true
+}
+fn default_recent_requests_size() -> usize {
+    100
+}
+fn default_currency() -> String {
+    "$".to_string()
+}
+fn default_cache_ttl() -> u64 {
+    300
+}
+fn default_cache_max_body_bytes() -> usize {
+    1024 * 1024
+}
+fn default_passthrough_method() -> String {
+    "*".to_string()
+}
+fn default_connection_warn() -> u64 {
+    60
+}
+fn default_connection_alert() -> u64 {
+    300
+}
+/// Where the Claude CLI is commonly installed, searched after PATH
+pub const CLAUDE_INSTALL_DIRS: &[&str] = &[
+    "~/.claude/local",
+    "~/.local/bin",
+    "~/.npm-global/bin",
+    "/usr/local/bin",
+    "/opt/homebrew/bin",
+];
+
+/// Absolute path of the Claude CLI for a configured path: the path itself if it
+/// is an executable file, else a file of the same name on PATH, else `claude` in
+/// one of [`CLAUDE_INSTALL_DIRS`]
+pub fn resolve_claude_binary(configured: &str) -> Option<PathBuf> {
+    let configured = Path::new(configured);
+    let name = configured.file_name()?;
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let path_dirs: Vec<PathBuf> = env::var_os("PATH")
+        .map(|path| env::split_paths(&path).collect())
+        .unwrap_or_default();
+    let install_dirs = CLAUDE_INSTALL_DIRS
+        .iter()
+        .filter_map(|dir| match dir.strip_prefix("~/") {
+            Some(relative) => home.as_ref().map(|home| home.join(relative)),
+            None => Some(PathBuf::from(dir)),
+        });
+
+    // A bare name like "claude" means a PATH lookup, not a file in the working directory
+    let as_given = configured
+        .parent()
+        .is_some_and(|parent| !parent.as_os_str().is_empty())
+        .then(|| configured.to_path_buf());
+    as_given
+        .into_iter()
+        .chain(path_dirs.into_iter().map(|dir| dir.join(name)))
+        .chain(install_dirs.map(|dir| dir.join("claude")))
+        .find(|candidate| is_executable(candidate))
+        .map(|found| std::path::absolute(&found).unwrap_or(found))
+}
+
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = path.metadata() else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+fn default_duplicate_window() -> u64 {
+    30
+}
+fn default_cycle_summary() -> bool {
+    true
+}
+fn default_emoji() -> bool {
+    true
+}
+fn default_body_max_bytes() -> usize {
+    4096
+}
+fn default_body_files_kept() -> usize {
+    200
+}
+fn default_body_files_max_age_hours() -> u64 {
+    24
+}
+fn default_storage_path() -> PathBuf {
+    PathBuf::from(crate::storage::DEFAULT_STORAGE_FILE)
+}
+fn default_storage_retention() -> u64 {
+    90
+}
+fn default_alert_clear_seconds() -> u64 {
+    60
+}
 fn default_switch_threshold() -> u64 {
     50
 }
 fn default_graceful_timeout() -> u64 {
     30000
 }
+fn default_switch_grace() -> u64 {
+    30
+}
+fn default_response_header_timeout() -> u64 {
+    30
+}
+fn default_response_body_timeout() -> u64 {
+    600
+}
+fn default_stream_idle_timeout() -> u64 {
+    60
+}
+fn default_header_read_timeout() -> u64 {
+    10_000
+}
+fn default_idle_connection_timeout() -> u64 {
+    300
+}
+fn default_max_local_connections() -> usize {
+    1024
+}
+fn default_group_priority() -> u8 {
+    1
+}
+fn default_max_in_flight_requests() -> usize {
+    256
+}
+fn default_bind_address() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::LOCALHOST)
+}
+fn default_maintenance_timezone() -> String {
+    "local".to_string()
+}
+fn default_max_concurrent_checks() -> usize {
+    4
+}
+fn default_startup_check() -> bool {
+    true
+}
+fn default_expected_output() -> String {
+    "ok".to_string()
+}
+fn default_health_check_model() -> String {
+    HEALTH_CHECK_MODEL.to_string()
+}
+fn default_health_check_prompt() -> String {
+    MINIMAL_HEALTH_PROMPT.to_string()
+}
+fn default_health_check_system_prompt() -> String {
+    HEALTH_CHECK_SYSTEM_PROMPT.to_string()
+}
+
+/// Endpoint ids that differ between two configurations, see
+/// [`Config::diff_endpoints`], and the listeners a reload opened or closed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ConfigDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    /// Listener URLs the reload started serving on
+    pub listeners_opened: Vec<String>,
+    /// Listener URLs the reload stopped accepting on
+    pub listeners_closed: Vec<String>,
+    /// Why the listener changes were not applied; the running listeners are kept
+    pub listener_error: Option<String>,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.listeners_opened.is_empty()
+            && self.listeners_closed.is_empty()
+    }
+}
+
+impl std::fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "+{} endpoints, -{}, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        )?;
+        if !self.listeners_opened.is_empty() {
+            write!(f, ", listening on {}", self.listeners_opened.join(", "))?;
+        }
+        if !self.listeners_closed.is_empty() {
+            write!(f, ", closed {}", self.listeners_closed.join(", "))?;
+        }
+        if let Some(error) = &self.listener_error {
+            write!(f, ", listeners unchanged: cannot bind {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        Self::load_from_file_with_env(path, None)
+    }
+
+    /// Load `path`, reading tokens from `env_file` instead of the `env_file`
+    /// setting or `.env`
+    pub fn load_from_file_with_env<P: AsRef<Path>>(
+        path: P,
+        env_file: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let mut config = Self::parse_file(path)?;
+
+        let env_path = config.resolve_env_file(path, env_file)?;
+        if !env_path.is_file() {
+            // Watched anyway, so a file created later still provides tokens
+            crate::env_file::watch(&env_path);
+        } else {
+            let provided = crate::env_file::load(&env_path)?;
+            console_warning(
+                &config
+                    .text()
+                    .loading_env_file(&env_path.display().to_string(), &provided),
+            );
+        }
+
+        config.validate()?;
+        config.claude_binary_missing = config.resolve_claude_binaries()?;
+        config.source_path = Some(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+        config.loaded_at = Some(chrono::Utc::now());
+        Ok(config)
+    }
+
+    /// Read `path` again for a running proxy. No env file is loaded and nothing
+    /// is printed; tokens come from the environment as it is now.
+    pub fn reload_from_file(path: &Path) -> anyhow::Result<Self> {
+        let mut config = Self::parse_file(path)?;
+        config.validate()?;
+        config.claude_binary_missing = config.resolve_claude_binaries()?;
+        config.source_path = Some(path.to_path_buf());
+        config.loaded_at = Some(chrono::Utc::now());
+        Ok(config)
+    }
+
+    fn parse_file(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("{}", i18n::console().config_read_failed(&e)))?;
+        Self::parse(&content)
+    }
+
+    /// Parse the contents of a config file, without validating them
+    pub(crate) fn parse(content: &str) -> anyhow::Result<Self> {
+        let mut config: Self = toml::from_str(content)
+            .map_err(|e| anyhow::anyhow!("{}", i18n::console().config_parse_failed(&e)))?;
+        config.endpoint_index = EndpointIndex::new(&config.groups).map_err(|duplicate| {
+            anyhow::anyhow!(
+                "{}",
+                config.text().duplicate_endpoint_id(duplicate.as_str())
+            )
+        })?;
+        // Before validation prints anything; `--no-emoji` is never overridden
+        if !config.logging.emoji {
+            crate::logging::set_console_emoji(false);
+        }
+        Ok(config)
+    }
+
+    pub fn load_default() -> anyhow::Result<Self> {
+        Self::load_default_with_env(None)
+    }
+
+    /// Find and load the config file, reading tokens from `env_file` instead of
+    /// the `env_file` setting or `.env`
+    pub fn load_default_with_env(env_file: Option<&Path>) -> anyhow::Result<Self> {
+        let config_paths = ["config.toml", "config.toml.template"];
+
+        for path in &config_paths {
+            if Path::new(path).exists() {
+                console_warning(&i18n::console().loading_config(path));
+                return Self::load_from_file_with_env(path, env_file);
+            }
+        }
+
+        Err(anyhow::anyhow!("{}", i18n::console().config_not_found()))
+    }
+
+    /// Env file to load: `explicit` as given, else `env_file` relative to the
+    /// config file, else `.env` in the working directory or next to the config.
+    /// Only the default may be missing.
+    fn resolve_env_file(
+        &self,
+        config_path: &Path,
+        explicit: Option<&Path>,
+    ) -> anyhow::Result<PathBuf> {
+        let config_dir = config_path.parent().unwrap_or(Path::new(""));
+        let configured = explicit
+            .map(Path::to_path_buf)
+            .or_else(|| self.env_file.as_ref().map(|path| config_dir.join(path)));
+        if let Some(path) = configured {
+            if !path.is_file() {
+                return Err(anyhow::anyhow!(
+                    "{}",
+                    self.text().env_file_not_found(&path.display().to_string())
+                ));
+            }
+            return Ok(path);
+        }
+
+        let in_working_dir = PathBuf::from(crate::env_file::DEFAULT_ENV_FILE);
+        let next_to_config = config_dir.join(crate::env_file::DEFAULT_ENV_FILE);
+        if !in_working_dir.is_file() && next_to_config.is_file() {
+            Ok(next_to_config)
+        } else {
+            Ok(in_working_dir)
+        }
+    }
+
+    /// Console text in this config's `ui.language`
+    fn text(&self) -> I18n {
+        I18n::new(self.ui.language.clone())
+    }
+
+    /// Find every group's Claude CLI, printing the one it uses, unless checks
+    /// run over HTTP. A missing CLI is an error when `claude_cli` mode is
+    /// forced, or in `auto` mode without `http_fallback` or
+    /// `allow_missing_claude_binary`; otherwise it is a warning and `true` is
+    /// returned.
+    fn resolve_claude_binaries(&self) -> anyhow::Result<bool> {
+        self.resolve_claude_binaries_with(resolve_claude_binary)
+    }
+
+    /// [`Self::resolve_claude_binaries`], finding a configured path with `resolve`
+    fn resolve_claude_binaries_with(
+        &self,
+        resolve: impl Fn(&str) -> Option<PathBuf>,
+    ) -> anyhow::Result<bool> {
+        let text = self.text();
+        let mode = self.health_check.mode;
+        if mode == HealthCheckMode::Http {
+            return Ok(false);
+        }
+        let mut missing = false;
+        for group in &self.groups {
+            let configured = self.configured_claude_binary(group);
+            let Some(resolved) = resolve(configured) else {
+                let searched = CLAUDE_INSTALL_DIRS.join(", ");
+                if mode == HealthCheckMode::ClaudeCli {
+                    return Err(anyhow::anyhow!(
+                        "{}",
+                        text.claude_binary_required(configured, &group.name, &searched)
+                    ));
+                }
+                if !self.health_check.http_fallback
+                    && !self.health_check.allow_missing_claude_binary
+                {
+                    return Err(anyhow::anyhow!(
+                        "{}",
+                        text.claude_binary_not_found(configured, &group.name, &searched)
+                    ));
+                }
+                console_warning(&text.claude_binary_missing_allowed(
+                    configured,
+                    &group.name,
+                    &searched,
+                ));
+                missing = true;
+                continue;
+            };
+            console_warning(&text.claude_binary_used(&group.name, &resolved.display().to_string()));
+        }
+        if missing && self.health_check.http_fallback {
+            console_warning(text.http_health_checks_at_startup());
+        } else if missing {
+            console_warning(text.health_checks_disabled_at_startup());
+        }
+        Ok(missing)
+    }
+
+    /// Whether health checks send a `count_tokens` request instead of running
+    /// the Claude CLI: in `http` mode, or in `auto` mode with `http_fallback`
+    /// once a CLI was not found
+    pub fn http_health_checks(&self) -> bool {
+        match self.health_check.mode {
+            HealthCheckMode::Http => true,
+            HealthCheckMode::ClaudeCli => false,
+            HealthCheckMode::Auto => self.claude_binary_missing && self.health_check.http_fallback,
+        }
+    }
+
+    /// Health checks are off because a Claude CLI is missing, see
+    /// `allow_missing_claude_binary`
+    pub fn health_checks_disabled(&self) -> bool {
+        self.claude_binary_missing && !self.http_health_checks()
+    }
+
+    /// Health check mode as `/status` reports it
+    pub fn health_check_mode_name(&self) -> &'static str {
+        if self.health_checks_disabled() {
+            "disabled (claude binary missing)"
+        } else if self.http_health_checks() {
+            "http"
+        } else {
+            "claude_cli"
+        }
+    }
+
+    /// Validate configuration - modern format only
+    fn validate(&self) -> anyhow::Result<()> {
+        let text = self.text();
+
+        // Ensure we have at least one group
+        if self.groups.is_empty() {
+            return Err(anyhow::anyhow!("{}", text.no_groups()));
+        }
+
+        // Validate each group
+        for group in &self.groups {
+            if group.endpoints.is_empty() {
+                return Err(anyhow::anyhow!("{}", text.group_no_endpoints(&group.name)));
+            }
+
+            if group.auth_token_env.trim().is_empty() {
+                return Err(anyhow::anyhow!(
+                    "{}",
+                    text.group_missing_token_env(&group.name)
+                ));
+            }
 
-impl Config {
-    pub fn load_from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
-        // Load .env file if it exists
-        if Path::new(".env").exists() {
-            dotenv::dotenv().ok();
-            println!("📋 Loading environment variables from .env file");
-        }
+            // A missing token only sidelines the group, see `groups_without_token`
+            let token_value = group.auth_token().unwrap_or_default();
+            if token_value.contains("your-claude-auth-token-here")
+                || token_value.contains("your-anthropic-auth-token-here")
+            {
+                return Err(anyhow::anyhow!(
+                    "{}",
+                    text.placeholder_token(&group.auth_token_env)
+                ));
+            }
 
-        let content = fs::read_to_string(&path).map_err(|e| {
-            anyhow::anyhow!(
-                "❌ Failed to read config file: {}\n💡 Make sure the file exists and is readable",
-                e
-            )
-        })?;
+            for (field, price) in [
+                ("input_price_per_mtok", group.input_price_per_mtok),
+                ("output_price_per_mtok", group.output_price_per_mtok),
+            ] {
+                if !price.is_finite() || price < 0.0 {
+                    return Err(anyhow::anyhow!(
+                        "{}",
+                        text.invalid_price(&group.name, field, price)
+                    ));
+                }
+            }
+        }
 
-        let config: Config = toml::from_str(&content).map_err(|e| {
-            anyhow::anyhow!("❌ Failed to parse config file: {}\n💡 Check your TOML syntax. Common issues:\n  • Missing quotes around strings\n  • Invalid endpoint_groups structure\n  • See config.toml.template for examples", e)
-        })?;
+        if self.groups_without_token().len() == self.groups.len() {
+            let checked = self
+                .groups
+                .iter()
+                .map(|group| group.auth_token_env.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(anyhow::anyhow!("{}", text.no_group_token(&checked)));
+        }
 
-        config.validate()?;
-        Ok(config)
-    }
+        let mut urls = HashSet::new();
+        for endpoint in self.groups.iter().flat_map(|group| &group.endpoints) {
+            if !urls.insert(normalize_url(&endpoint.url)) {
+                return Err(anyhow::anyhow!(
+                    "{}",
+                    text.duplicate_endpoint_url(&endpoint.url)
+                ));
+            }
+        }
 
-    pub fn load_default() -> anyhow::Result<Self> {
-        // Load .env file if it exists
-        if Path::new(".env").exists() {
-            dotenv::dotenv().ok();
-            println!("📋 Loading environment variables from .env file");
+        let mut ports = HashSet::from([self.server.port]);
+        for listener in &self.server.listeners {
+            if !ports.insert(listener.port) {
+                return Err(anyhow::anyhow!(
+                    "{}",
+                    text.duplicate_listener_port(listener.port)
+                ));
+            }
+            if let Some(group) = &listener.group {
+                if !self.groups.iter().any(|g| &g.name == group) {
+                    return Err(anyhow::anyhow!(
+                        "{}",
+                        text.unknown_listener_group(listener.port, group)
+                    ));
+                }
+            }
         }
 
-        let config_paths = ["config.toml", "config.toml.template"];
+        for (field, seconds) in [
+            (
+                "response_header_timeout_seconds",
+                self.server.response_header_timeout_seconds,
+            ),
+            (
+                "response_body_timeout_seconds",
+                self.server.response_body_timeout_seconds,
+            ),
+        ] {
+            if seconds == 0 {
+                return Err(anyhow::anyhow!("{}", text.response_timeout_zero(field)));
+            }
+        }
 
-        for path in &config_paths {
-            if Path::new(path).exists() {
-                println!("📋 Loading configuration from: {path}");
-                return Self::load_from_file(path);
+        for group in &self.groups {
+            let owners = std::iter::once((
+                text.health_check_context_group(&group.name),
+                group.request_timeout_seconds,
+                group.response_body_timeout_seconds,
+            ))
+            .chain(group.endpoints.iter().map(|endpoint| {
+                (
+                    text.endpoint_context(&endpoint.name),
+                    endpoint.request_timeout_seconds,
+                    endpoint.response_body_timeout_seconds,
+                )
+            }));
+            for (owner, seconds, body_seconds) in owners {
+                if body_seconds == Some(0) {
+                    return Err(anyhow::anyhow!(
+                        "{}",
+                        text.timeout_override_zero("response_body_timeout_seconds", &owner)
+                    ));
+                }
+                match seconds {
+                    Some(0) => {
+                        return Err(anyhow::anyhow!(
+                            "{}",
+                            text.timeout_override_zero("request_timeout_seconds", &owner)
+                        ));
+                    }
+                    Some(seconds) if seconds > LONG_REQUEST_TIMEOUT_SECONDS => {
+                        console_warning(&text.long_request_timeout(&owner, seconds));
+                    }
+                    _ => {}
+                }
             }
         }
 
-        Err(anyhow::anyhow!(
-            "❌ No configuration file found!\n\
-             💡 Please create a config.toml file. You can:\n\
-             🔧 Copy config.toml.template to config.toml\n\
-             📝 Update the auth token and Claude binary path\n\
-             ⚡ The old 'endpoints' array format is no longer supported"
+        let probes = std::iter::once((
+            text.health_check_context_global().to_string(),
+            Some(&self.health_check.model),
+            Some(&self.health_check.prompt),
+            Some(&self.health_check.system_prompt),
         ))
-    }
+        .chain(self.groups.iter().map(|group| {
+            (
+                text.health_check_context_group(&group.name),
+                group.health_check_model.as_ref(),
+                group.health_check_prompt.as_ref(),
+                group.health_check_system_prompt.as_ref(),
+            )
+        }));
+        for (owner, model, prompt, system_prompt) in probes {
+            for (field, value) in [
+                ("model", model),
+                ("prompt", prompt),
+                ("system_prompt", system_prompt),
+            ] {
+                if value.is_some_and(|value| value.trim().is_empty()) {
+                    return Err(anyhow::anyhow!(
+                        "{}",
+                        text.health_check_text_empty(field, &owner)
+                    ));
+                }
+            }
+            if let Some(length) = prompt
+                .map(|prompt| prompt.chars().count())
+                .filter(|&length| length > LONG_HEALTH_PROMPT_CHARS)
+            {
+                console_warning(&text.long_health_prompt(&owner, length));
+            }
+        }
 
-    /// Validate configuration - modern format only
-    fn validate(&self) -> anyhow::Result<()> {
-        // Ensure we have at least one group
-        if self.groups.is_empty() {
+        if MaintenanceTimeZone::parse(&self.server.maintenance_timezone).is_none() {
             return Err(anyhow::anyhow!(
-                "❌ No endpoint groups configured!\n\
-                 💡 Please use the modern groups format in your config.toml.\n\
-                 📖 See config.toml.example for examples.\n\
-                 🔗 Copy config.toml.example to config.toml and update AUTH_TOKEN in .env"
+                "{}",
+                text.invalid_maintenance_timezone(&self.server.maintenance_timezone)
             ));
         }
-
-        // Validate each group
-        for group in &self.groups {
-            if group.endpoints.is_empty() {
-                return Err(anyhow::anyhow!(
-                    "❌ Group '{}' has no endpoints configured",
-                    group.name
-                ));
+        for endpoint in self.groups.iter().flat_map(|group| &group.endpoints) {
+            for window in &endpoint.maintenance {
+                if let Err(problem) = maintenance::validate_window(window) {
+                    return Err(anyhow::anyhow!(
+                        "{}",
+                        text.invalid_maintenance_window(&endpoint.name, &window.window, &problem)
+                    ));
+                }
             }
+        }
 
-            if group.auth_token_env.trim().is_empty() {
+        let retry_statuses = self.retry.retry_on_status.iter().flatten();
+        for (field, status) in retry_statuses
+            .map(|status| ("retry_on_status", status))
+            .chain(
+                self.retry
+                    .fallback_on_status
+                    .iter()
+                    .map(|status| ("fallback_on_status", status)),
+            )
+        {
+            if !(400..=599).contains(status) {
                 return Err(anyhow::anyhow!(
-                    "❌ Group '{}' is missing auth_token_env reference",
-                    group.name
+                    "{}",
+                    text.invalid_retry_status(*status, field)
                 ));
             }
+        }
+
+        if !self.cache.paths.is_empty() && self.cache.ttl_seconds == 0 {
+            return Err(anyhow::anyhow!(
+                "{}",
+                text.invalid_cache("ttl_seconds must be at least 1")
+            ));
+        }
+        if let Some(path) = self.cache.paths.iter().find(|path| !path.starts_with('/')) {
+            return Err(anyhow::anyhow!(
+                "{}",
+                text.invalid_cache(&format!("path '{path}' must start with /"))
+            ));
+        }
 
-            // Check if the environment variable exists
-            if env::var(&group.auth_token_env).is_err() {
+        let mut alert_names = HashSet::new();
+        for rule in &self.alerts.rules {
+            let problem = if !alert_names.insert(rule.name.as_str()) {
+                Some("another rule has the same name".to_string())
+            } else {
+                alerts::validate_rule(rule, self.endpoint_index()).err()
+            };
+            if let Some(problem) = problem {
                 return Err(anyhow::anyhow!(
-                    "❌ Environment variable '{}' for group '{}' is not set.\n💡 Please check your .env file or set the environment variable",
-                    group.auth_token_env, group.name
+                    "{}",
+                    text.invalid_alert_rule(&rule.name, &problem)
                 ));
             }
+        }
 
-            // Get the actual token value to validate it
-            let token_value = env::var(&group.auth_token_env).unwrap_or_default();
-            if token_value.contains("your-claude-auth-token-here")
-                || token_value.contains("your-anthropic-auth-token-here")
-            {
+        if let Some(admin_token_env) = &self.server.admin_token_env {
+            if crate::env_file::var(admin_token_env).is_none_or(|token| token.trim().is_empty()) {
                 return Err(anyhow::anyhow!(
-                    "❌ Please replace the placeholder auth token in '{}' environment variable with your real Claude auth token", 
-                    group.auth_token_env
+                    "{}",
+                    text.admin_token_not_set(admin_token_env)
                 ));
             }
         }
 
-        // Validate that we have at least one default group
-        let has_default = self
+        // Validate that exactly one group is the default, warning when none is
+        let default_groups: Vec<&str> = self
             .groups
             .iter()
-            .any(|group| group.default.unwrap_or(false));
+            .filter(|group| group.default.unwrap_or(false))
+            .map(|group| group.name.as_str())
+            .collect();
 
-        if !has_default {
-            println!("⚠️  No default group specified, using first group as default");
+        match default_groups.as_slice() {
+            [] => console_warning(text.no_default_group()),
+            [_] => {}
+            groups => {
+                return Err(anyhow::anyhow!(
+                    "{}",
+                    text.multiple_default_groups(&groups.join("', '"))
+                ));
+            }
         }
 
-        // Validate unique endpoint names across all groups
-        let mut names = std::collections::HashSet::new();
+        // At most one default endpoint, and only in the default group
         for group in &self.groups {
-            for endpoint in &group.endpoints {
-                if !names.insert(&endpoint.name) {
-                    return Err(anyhow::anyhow!(
-                        "❌ Duplicate endpoint name '{}' found.\n💡 Each endpoint must have a unique name across all groups.", 
-                        endpoint.name
-                    ));
-                }
+            let default_endpoints: Vec<&str> = group
+                .endpoints
+                .iter()
+                .filter(|endpoint| endpoint.default.unwrap_or(false))
+                .map(|endpoint| endpoint.name.as_str())
+                .collect();
+            if default_endpoints.is_empty() {
+                continue;
+            }
+            if !group.default.unwrap_or(false) {
+                return Err(anyhow::anyhow!(
+                    "{}",
+                    text.default_endpoint_outside_default_group(
+                        &default_endpoints.join("', '"),
+                        &group.name
+                    )
+                ));
+            }
+            if default_endpoints.len() > 1 {
+                return Err(anyhow::anyhow!(
+                    "{}",
+                    text.multiple_default_endpoints(&group.name, &default_endpoints.join("', '"))
+                ));
             }
         }
 
-        // Validate Claude binary paths
+        // Validate unique endpoint names across all groups
+        let mut names = std::collections::HashSet::new();
         for group in &self.groups {
-            let health_config = group.health_check.as_ref().unwrap_or(&self.health_check);
-            if !Path::new(&health_config.claude_binary_path).exists() {
-                // Try to find claude in PATH if the specified path doesn't exist
-                if std::process::Command::new(&health_config.claude_binary_path)
-                    .arg("--version")
-                    .output()
-                    .is_err()
-                {
+            for endpoint in &group.endpoints {
+                if !names.insert(&endpoint.name) {
                     return Err(anyhow::anyhow!(
-                        "❌ Claude binary not found at: {}\n💡 Please install Claude CLI or update the path in your config",
-                        health_config.claude_binary_path
+                        "{}",
+                        text.duplicate_endpoint_name(&endpoint.name)
                     ));
                 }
             }
@@ -242,27 +1559,35 @@ impl Config {
         // Validate health check intervals
         self.validate_health_check_intervals()?;
 
-        println!("✅ Configuration validated successfully!");
-        println!(
-            "🚀 Found {} groups with {} total endpoints",
+        if self.storage.enabled && !cfg!(feature = "sqlite") {
+            return Err(anyhow::anyhow!("{}", text.storage_without_sqlite()));
+        }
+
+        console_warning(text.config_validated());
+        console_warning(&text.config_summary(
             self.groups.len(),
-            self.groups.iter().map(|g| g.endpoints.len()).sum::<usize>()
-        );
+            self.groups.iter().map(|g| g.endpoints.len()).sum::<usize>(),
+        ));
 
         Ok(())
     }
 
     /// Validate health check interval constraints
     fn validate_health_check_intervals(&self) -> anyhow::Result<()> {
+        let text = self.text();
+
         // Check global health check config
-        self.validate_single_health_check_config(&self.health_check, "global")?;
+        self.validate_single_health_check_config(
+            &self.health_check,
+            text.health_check_context_global(),
+        )?;
 
         // Check group-specific health check configs
         for group in &self.groups {
             if let Some(group_health_config) = &group.health_check {
                 self.validate_single_health_check_config(
                     group_health_config,
-                    &format!("group '{}'", group.name),
+                    &text.health_check_context_group(&group.name),
                 )?;
             }
         }
@@ -275,27 +1600,32 @@ impl Config {
         config: &HealthCheckConfig,
         context: &str,
     ) -> anyhow::Result<()> {
+        let text = self.text();
+
         // Validate basic intervals
         if config.interval_seconds == 0 {
-            return Err(anyhow::anyhow!(
-                "Health check interval cannot be 0 for {}",
-                context
-            ));
+            return Err(anyhow::anyhow!("{}", text.interval_zero(context)));
         }
 
         if config.timeout_seconds == 0 {
+            return Err(anyhow::anyhow!("{}", text.timeout_zero(context)));
+        }
+
+        if config.max_concurrent_checks == 0 {
             return Err(anyhow::anyhow!(
-                "Health check timeout cannot be 0 for {}",
-                context
+                "{}",
+                text.max_concurrent_checks_zero(context)
             ));
         }
 
         if config.timeout_seconds >= config.interval_seconds {
             return Err(anyhow::anyhow!(
-                "Health check timeout ({}s) should be less than interval ({}s) for {}",
-                config.timeout_seconds,
-                config.interval_seconds,
-                context
+                "{}",
+                text.timeout_not_below_interval(
+                    config.timeout_seconds,
+                    config.interval_seconds,
+                    context
+                )
             ));
         }
 
@@ -303,25 +1633,28 @@ impl Config {
         if config.dynamic_scaling {
             if let Some(min_interval) = config.min_interval_seconds {
                 if min_interval == 0 {
-                    return Err(anyhow::anyhow!(
-                        "Minimum interval cannot be 0 when dynamic scaling is enabled for {}",
-                        context
-                    ));
+                    return Err(anyhow::anyhow!("{}", text.min_interval_zero(context)));
                 }
 
                 if min_interval > config.interval_seconds {
                     return Err(anyhow::anyhow!(
-                        "Minimum interval ({}s) cannot be greater than base interval ({}s) for {}",
-                        min_interval,
-                        config.interval_seconds,
-                        context
+                        "{}",
+                        text.min_interval_above_interval(
+                            min_interval,
+                            config.interval_seconds,
+                            context
+                        )
                     ));
                 }
 
                 if config.timeout_seconds >= min_interval {
                     return Err(anyhow::anyhow!(
-                        "Health check timeout ({}s) should be less than minimum interval ({}s) for {}",
-                        config.timeout_seconds, min_interval, context
+                        "{}",
+                        text.timeout_not_below_min_interval(
+                            config.timeout_seconds,
+                            min_interval,
+                            context
+                        )
                     ));
                 }
             }
@@ -329,10 +1662,12 @@ impl Config {
             if let Some(max_interval) = config.max_interval_seconds {
                 if max_interval < config.interval_seconds {
                     return Err(anyhow::anyhow!(
-                        "Maximum interval ({}s) cannot be less than base interval ({}s) for {}",
-                        max_interval,
-                        config.interval_seconds,
-                        context
+                        "{}",
+                        text.max_interval_below_interval(
+                            max_interval,
+                            config.interval_seconds,
+                            context
+                        )
                     ));
                 }
             }
@@ -355,10 +1690,64 @@ impl Config {
         )
     }
 
+    /// Upper bound for a full health check cycle over `endpoint_count` endpoints.
+    ///
+    /// Checks run in batches of `max_concurrent_checks`, so the wall time grows with
+    /// the number of batches rather than staying at a single check timeout.
+    pub fn health_check_cycle_timeout(&self, endpoint_count: usize) -> Duration {
+        let concurrency = self.health_check.max_concurrent_checks.max(1);
+        let batches = endpoint_count.div_ceil(concurrency).max(1) as u64;
+        Duration::from_secs(self.health_check.timeout_seconds * batches + 5)
+    }
+
     pub fn is_dynamic_scaling_enabled(&self) -> bool {
         self.health_check.dynamic_scaling
     }
 
+    /// Claude CLI path a group's health checks are configured with: the group's
+    /// own `claude_binary_path`, then its `health_check` section, then the global one
+    pub fn configured_claude_binary<'a>(&'a self, group: &'a Group) -> &'a str {
+        group
+            .claude_binary_path
+            .as_deref()
+            .or(group
+                .health_check
+                .as_ref()
+                .map(|health_check| health_check.claude_binary_path.as_str()))
+            .unwrap_or(&self.health_check.claude_binary_path)
+    }
+
+    /// Claude CLI that health checks for `group_name` run, found with
+    /// [`resolve_claude_binary`], or the configured path when nothing is found
+    /// so the spawn error names it
+    pub fn claude_binary(&self, group_name: &str) -> PathBuf {
+        let configured = self
+            .groups
+            .iter()
+            .find(|group| group.name == group_name)
+            .map(|group| self.configured_claude_binary(group))
+            .unwrap_or(&self.health_check.claude_binary_path);
+        resolve_claude_binary(configured).unwrap_or_else(|| PathBuf::from(configured))
+    }
+
+    /// Model, prompt and system prompt of the health checks for `group_name`:
+    /// the group's own settings, then `[health_check]`
+    pub fn health_probe(&self, group_name: &str) -> HealthProbe {
+        let mut probe = self.health_check.probe();
+        if let Some(group) = self.groups.iter().find(|group| group.name == group_name) {
+            if let Some(model) = &group.health_check_model {
+                probe.model = model.clone();
+            }
+            if let Some(prompt) = &group.health_check_prompt {
+                probe.prompt = prompt.clone();
+            }
+            if let Some(system_prompt) = &group.health_check_system_prompt {
+                probe.system_prompt = system_prompt.clone();
+            }
+        }
+        probe
+    }
+
     /// Get the configured default group, if any
     pub fn get_default_group(&self) -> Option<&Group> {
         self.groups
@@ -366,25 +1755,278 @@ impl Config {
             .find(|group| group.default.unwrap_or(false))
     }
 
-    /// Get the configured default endpoint from default group, if any
+    /// Get the configured default endpoint from default group, if any: the
+    /// endpoint marked `default = true`, otherwise the group's first
     pub fn get_default_endpoint(&self) -> Option<(String, SimpleEndpoint)> {
         if let Some(default_group) = self.get_default_group() {
-            if let Some(first_endpoint) = default_group.endpoints.first() {
-                if let Ok(auth_token) = env::var(&default_group.auth_token_env) {
-                    return Some((auth_token, first_endpoint.clone()));
+            let endpoint = default_group
+                .endpoints
+                .iter()
+                .find(|endpoint| endpoint.default.unwrap_or(false))
+                .or(default_group.endpoints.first());
+            if let Some(endpoint) = endpoint {
+                if let Some(auth_token) = default_group.auth_token() {
+                    return Some((auth_token, endpoint.clone()));
                 }
             }
         }
         None
     }
 
+    /// Endpoints automatic switching may move to from `current_endpoint`.
+    /// Without a default group the first group is the default.
+    pub fn auto_switch_endpoints(&self, current_endpoint: &str) -> Vec<String> {
+        let allowed_group = match self.server.auto_switch_scope {
+            AutoSwitchScope::All => None,
+            AutoSwitchScope::DefaultGroup => self.get_default_group().or(self.groups.first()),
+            AutoSwitchScope::CurrentGroup => self.groups.iter().find(|group| {
+                group
+                    .endpoints
+                    .iter()
+                    .any(|endpoint| endpoint.url == current_endpoint)
+            }),
+        };
+
+        self.get_all_endpoints()
+            .into_iter()
+            .filter(|(_, _, group)| allowed_group.is_none_or(|allowed| &allowed.name == group))
+            .map(|(_, endpoint, _)| endpoint.url)
+            .collect()
+    }
+
+    /// Every tag used by a configured endpoint, sorted
+    pub fn all_tags(&self) -> Vec<String> {
+        let tags: std::collections::BTreeSet<&String> = self
+            .groups
+            .iter()
+            .flat_map(|group| &group.endpoints)
+            .flat_map(|endpoint| &endpoint.tags)
+            .collect();
+        tags.into_iter().cloned().collect()
+    }
+
+    /// Whether the endpoint at `url` carries at least one of `tags`
+    pub fn endpoint_has_any_tag(&self, url: &str, tags: &[String]) -> bool {
+        self.find_endpoint(url)
+            .is_some_and(|(_, endpoint)| endpoint.tags.iter().any(|tag| tags.contains(tag)))
+    }
+
+    /// Time zone maintenance windows are written in
+    pub fn maintenance_timezone(&self) -> MaintenanceTimeZone {
+        MaintenanceTimeZone::parse(&self.server.maintenance_timezone)
+            .unwrap_or(MaintenanceTimeZone::Local)
+    }
+
+    /// Endpoints inside a maintenance window at `now`, by URL, with when
+    /// their window ends
+    pub fn maintenance_due(
+        &self,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> HashMap<String, chrono::DateTime<chrono::Utc>> {
+        let time_zone = self.maintenance_timezone();
+        self.groups
+            .iter()
+            .flat_map(|group| &group.endpoints)
+            .filter_map(|endpoint| {
+                maintenance::active_until(&endpoint.maintenance, time_zone, now)
+                    .map(|until| (endpoint.url.clone(), until))
+            })
+            .collect()
+    }
+
+    /// Token required by admin routes, if one is configured
+    pub fn get_admin_token(&self) -> Option<String> {
+        self.server
+            .admin_token_env
+            .as_ref()
+            .and_then(|name| crate::env_file::var(name))
+            .filter(|token| !token.trim().is_empty())
+    }
+
+    /// Groups whose `auth_token_env` is unset or empty right now
+    pub fn groups_without_token(&self) -> Vec<&Group> {
+        self.groups
+            .iter()
+            .filter(|group| group.auth_token().is_none())
+            .collect()
+    }
+
+    /// Stable id of the endpoint at `url`, see [`SimpleEndpoint::id`]
+    pub fn endpoint_id(&self, url: &str) -> Option<&str> {
+        self.endpoint_index.id_of(url).map(|id| id.as_str())
+    }
+
+    /// Every configured endpoint by id and URL
+    pub fn endpoint_index(&self) -> &EndpointIndex {
+        &self.endpoint_index
+    }
+
+    /// The configured endpoint at `url`, however the URL is spelled
+    pub fn endpoint_ref(&self, url: &str) -> Option<&EndpointRef> {
+        self.endpoint_index.resolve(url)
+    }
+
+    /// Group and configuration of the endpoint at `url`
+    fn find_endpoint(&self, url: &str) -> Option<(&Group, &SimpleEndpoint)> {
+        let endpoint = self.endpoint_ref(url)?;
+        self.groups
+            .iter()
+            .filter(|group| group.name == endpoint.group)
+            .flat_map(|group| group.endpoints.iter().map(move |ep| (group, ep)))
+            .find(|(_, ep)| ep.id() == endpoint.id.as_str())
+    }
+
+    /// Endpoints added, removed or changed in `new`, matched by id. An endpoint
+    /// counts as changed when its URL, name, tags or group differ.
+    pub fn diff_endpoints(&self, new: &Config) -> ConfigDiff {
+        fn by_id(config: &Config) -> BTreeMap<&str, (&Group, &SimpleEndpoint)> {
+            config
+                .groups
+                .iter()
+                .flat_map(|group| group.endpoints.iter().map(move |ep| (ep.id(), (group, ep))))
+                .collect()
+        }
+        let old = by_id(self);
+        let new = by_id(new);
+
+        let mut diff = ConfigDiff::default();
+        for (id, (new_group, new_endpoint)) in &new {
+            match old.get(id) {
+                None => diff.added.push(id.to_string()),
+                Some((old_group, old_endpoint)) => {
+                    let same = old_endpoint.url == new_endpoint.url
+                        && old_endpoint.name == new_endpoint.name
+                        && old_endpoint.tags == new_endpoint.tags
+                        && old_endpoint.maintenance == new_endpoint.maintenance
+                        && old_group.name == new_group.name
+                        && old_group.auth_token_env == new_group.auth_token_env;
+                    if !same {
+                        diff.changed.push(id.to_string());
+                    }
+                }
+            }
+        }
+        diff.removed = old
+            .keys()
+            .filter(|id| !new.contains_key(*id))
+            .map(|id| id.to_string())
+            .collect();
+        diff
+    }
+
+    /// Id of every configured endpoint, keyed by URL
+    pub fn endpoint_ids(&self) -> HashMap<String, String> {
+        self.groups
+            .iter()
+            .flat_map(|group| &group.endpoints)
+            .map(|endpoint| (endpoint.url.clone(), endpoint.id().to_string()))
+            .collect()
+    }
+
+    /// Environment variable the endpoint at `url` is waiting for, if its group
+    /// has no token
+    pub fn missing_token_env(&self, url: &str) -> Option<&str> {
+        self.group_of(url)
+            .filter(|group| group.auth_token().is_none())
+            .map(|group| group.auth_token_env.as_str())
+    }
+
+    /// Every configured endpoint with its group name, including groups without
+    /// a token
+    pub fn get_configured_endpoints_legacy(&self) -> Vec<(EndpointConfig, String)> {
+        self.groups
+            .iter()
+            .flat_map(|group| {
+                group
+                    .endpoints
+                    .iter()
+                    .map(|endpoint| (endpoint.clone().into(), group.name.clone()))
+            })
+            .collect()
+    }
+
+    /// Every local listener: `server.port` first, then `[[server.listeners]]`
+    pub fn listeners(&self) -> Vec<ListenerConfig> {
+        std::iter::once(ListenerConfig::primary(self.server.port))
+            .chain(self.server.listeners.iter().cloned())
+            .collect()
+    }
+
+    /// Group containing `endpoint_url`
+    pub fn group_of(&self, endpoint_url: &str) -> Option<&Group> {
+        self.find_endpoint(endpoint_url).map(|(group, _)| group)
+    }
+
+    /// Failover tier of the group containing `endpoint_url`, the default
+    /// priority for an unknown endpoint
+    pub fn tier_of(&self, endpoint_url: &str) -> u8 {
+        self.group_of(endpoint_url)
+            .map_or_else(default_group_priority, |group| group.priority)
+    }
+
+    /// How long a request to `endpoint_url` waits for response headers: the
+    /// endpoint's override, else its group's, else the server setting
+    pub fn request_timeout_for(&self, endpoint_url: &str) -> RequestTimeout {
+        self.timeout_for(
+            endpoint_url,
+            |endpoint| endpoint.request_timeout_seconds,
+            |group| group.request_timeout_seconds,
+            self.server.response_header_timeout_seconds,
+        )
+    }
+
+    /// How long a response from `endpoint_url` may take to stream its body:
+    /// the endpoint's override, else its group's, else the server setting
+    pub fn body_timeout_for(&self, endpoint_url: &str) -> RequestTimeout {
+        self.timeout_for(
+            endpoint_url,
+            |endpoint| endpoint.response_body_timeout_seconds,
+            |group| group.response_body_timeout_seconds,
+            self.server.response_body_timeout_seconds,
+        )
+    }
+
+    fn timeout_for(
+        &self,
+        endpoint_url: &str,
+        endpoint_override: impl Fn(&SimpleEndpoint) -> Option<u64>,
+        group_override: impl Fn(&Group) -> Option<u64>,
+        server_seconds: u64,
+    ) -> RequestTimeout {
+        let found = self.find_endpoint(endpoint_url);
+        let endpoint = found.and_then(|(_, endpoint)| endpoint_override(endpoint));
+        let group = found.and_then(|(group, _)| group_override(group));
+        match (endpoint, group) {
+            (Some(seconds), _) => RequestTimeout {
+                seconds,
+                source: TimeoutSource::Endpoint,
+            },
+            (None, Some(seconds)) => RequestTimeout {
+                seconds,
+                source: TimeoutSource::Group,
+            },
+            (None, None) => RequestTimeout {
+                seconds: server_seconds,
+                source: TimeoutSource::Server,
+            },
+        }
+    }
+
+    /// Auth mode of the group containing `endpoint_url`, the default for an
+    /// unknown endpoint
+    pub fn auth_mode_for(&self, endpoint_url: &str) -> AuthMode {
+        self.group_of(endpoint_url)
+            .map(|group| group.auth_mode)
+            .unwrap_or_default()
+    }
+
     /// Get all endpoints with their auth tokens and group names (legacy compatibility)
     /// Returns: Vec<(auth_token, endpoint_config, group_name)>
     pub fn get_all_endpoints_legacy(&self) -> Vec<(String, EndpointConfig, String)> {
         let mut all_endpoints = Vec::new();
 
         for group in &self.groups {
-            if let Ok(auth_token) = env::var(&group.auth_token_env) {
+            if let Some(auth_token) = group.auth_token() {
                 for endpoint in &group.endpoints {
                     all_endpoints.push((
                         auth_token.clone(),
@@ -404,7 +2046,7 @@ impl Config {
         let mut all_endpoints = Vec::new();
 
         for group in &self.groups {
-            if let Ok(auth_token) = env::var(&group.auth_token_env) {
+            if let Some(auth_token) = group.auth_token() {
                 for endpoint in &group.endpoints {
                     all_endpoints.push((auth_token.clone(), endpoint.clone(), group.name.clone()));
                 }
@@ -424,3 +2066,287 @@ impl From<SimpleEndpoint> for EndpointConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{config, TWO_GROUPS};
+
+    /// `TWO_GROUPS` with `from` replaced by `to`
+    fn changed(from: &str, to: &str) -> Config {
+        assert!(TWO_GROUPS.contains(from), "{from}");
+        config(&TWO_GROUPS.replace(from, to))
+    }
+
+    #[test]
+    fn diff_of_an_unchanged_config_is_empty() {
+        let diff = config(TWO_GROUPS).diff_endpoints(&config(TWO_GROUPS));
+        assert!(diff.is_empty(), "{diff:?}");
+    }
+
+    #[test]
+    fn diff_lists_added_and_removed_endpoints() {
+        let old = config(TWO_GROUPS);
+        let new = changed(
+            r#"endpoints = [{ url = "http://127.0.0.1:19002", name = "beta" }]"#,
+            r#"endpoints = [{ url = "http://127.0.0.1:19003", name = "gamma" }, { url = "http://127.0.0.1:19004", name = "delta" }]"#,
+        );
+
+        let diff = old.diff_endpoints(&new);
+        assert_eq!(diff.added, ["delta", "gamma"]);
+        assert_eq!(diff.removed, ["beta"]);
+        assert!(diff.changed.is_empty());
+
+        let diff = new.diff_endpoints(&old);
+        assert_eq!(diff.added, ["beta"]);
+        assert_eq!(diff.removed, ["delta", "gamma"]);
+    }
+
+    #[test]
+    fn diff_lists_changed_endpoints() {
+        let old = config(TWO_GROUPS);
+        let changes = [
+            // URL
+            (
+                r#"url = "http://127.0.0.1:19002""#,
+                r#"url = "http://127.0.0.1:29002""#,
+            ),
+            // Tags
+            (r#"name = "beta" }"#, r#"name = "beta", tags = ["eu"] }"#),
+            // Token
+            (
+                r#"auth_token_env = "ZEPHYR_TEST_TOKEN_BACKUP""#,
+                r#"auth_token_env = "ZEPHYR_TEST_TOKEN_MAIN""#,
+            ),
+            // Group
+            (r#"name = "backup""#, r#"name = "fallback""#),
+        ];
+        for (from, to) in changes {
+            let diff = old.diff_endpoints(&changed(from, to));
+            assert_eq!(diff.changed, ["beta"], "{to}");
+            assert!(diff.added.is_empty() && diff.removed.is_empty(), "{to}");
+        }
+    }
+
+    #[test]
+    fn diff_matches_endpoints_by_id() {
+        // A renamed endpoint with a fixed id is changed, not replaced
+        let old = changed(r#"name = "beta""#, r#"name = "beta", id = "b""#);
+        let new = changed(r#"name = "beta""#, r#"name = "Beta EU", id = "b""#);
+        let diff = old.diff_endpoints(&new);
+        assert_eq!(diff.changed, ["b"]);
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+
+        // Without one the id is the name, so a rename replaces the endpoint
+        let diff =
+            config(TWO_GROUPS).diff_endpoints(&changed(r#"name = "beta""#, r#"name = "gamma""#));
+        assert_eq!(diff.added, ["gamma"]);
+        assert_eq!(diff.removed, ["beta"]);
+    }
+
+    #[test]
+    fn diff_ignores_group_order() {
+        let main = TWO_GROUPS.find("[[groups]]").unwrap();
+        let backup = TWO_GROUPS.rfind("[[groups]]").unwrap();
+        let health = TWO_GROUPS.find("[health_check]").unwrap();
+        let reordered = format!(
+            "{}{}{}{}",
+            &TWO_GROUPS[..main],
+            &TWO_GROUPS[backup..health],
+            &TWO_GROUPS[main..backup],
+            &TWO_GROUPS[health..]
+        );
+        let diff = config(TWO_GROUPS).diff_endpoints(&config(&reordered));
+        assert!(diff.is_empty(), "{diff:?}");
+    }
+
+    /// Two groups of two endpoints each, `main` holding `a1` and `a2` and
+    /// `backup` holding `b1` and `b2`, with `default = true` on the groups
+    /// and endpoints listed
+    fn with_defaults(groups: &[&str], endpoints: &[&str]) -> Config {
+        let default = |name: &str, list: &[&str]| {
+            if list.contains(&name) {
+                ", default = true"
+            } else {
+                ""
+            }
+        };
+        let mut toml = "[server]\nport = 18080\n".to_string();
+        for (group, token, ids) in [
+            ("main", "ZEPHYR_TEST_TOKEN_MAIN", ["a1", "a2"]),
+            ("backup", "ZEPHYR_TEST_TOKEN_BACKUP", ["b1", "b2"]),
+        ] {
+            let endpoints: Vec<String> = ids
+                .iter()
+                .enumerate()
+                .map(|(i, id)| {
+                    format!(
+                        r#"{{ url = "http://127.0.0.1:1900{}", name = "{id}"{} }}"#,
+                        i + usize::from(group == "backup") * 2,
+                        default(id, endpoints)
+                    )
+                })
+                .collect();
+            toml.push_str(&format!(
+                "\n[[groups]]\nname = \"{group}\"\nauth_token_env = \"{token}\"\n{}endpoints = [{}]\n",
+                if groups.contains(&group) { "default = true\n" } else { "" },
+                endpoints.join(", ")
+            ));
+        }
+        toml.push_str(&TWO_GROUPS[TWO_GROUPS.find("[health_check]").unwrap()..]);
+        config(&toml)
+    }
+
+    fn default_endpoint(config: &Config) -> Option<String> {
+        config
+            .get_default_endpoint()
+            .map(|(_, endpoint)| endpoint.name)
+    }
+
+    #[test]
+    fn without_a_default_group_there_is_no_default_endpoint() {
+        let config = with_defaults(&[], &[]);
+        config.validate().unwrap();
+        assert!(config.get_default_group().is_none());
+        assert_eq!(default_endpoint(&config), None);
+    }
+
+    #[test]
+    fn one_default_group_starts_on_its_first_endpoint() {
+        let config = with_defaults(&["backup"], &[]);
+        config.validate().unwrap();
+        assert_eq!(config.get_default_group().unwrap().name, "backup");
+        assert_eq!(default_endpoint(&config).as_deref(), Some("b1"));
+    }
+
+    #[test]
+    fn two_default_groups_are_rejected() {
+        let error = with_defaults(&["main", "backup"], &[])
+            .validate()
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("'main', 'backup'"), "{error}");
+    }
+
+    #[test]
+    fn default_endpoint_of_the_default_group_is_the_start() {
+        let config = with_defaults(&["main"], &["a2"]);
+        config.validate().unwrap();
+        assert_eq!(default_endpoint(&config).as_deref(), Some("a2"));
+    }
+
+    #[test]
+    fn two_default_endpoints_are_rejected() {
+        let error = with_defaults(&["main"], &["a1", "a2"])
+            .validate()
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("'a1', 'a2'"), "{error}");
+        assert!(error.contains("'main'"), "{error}");
+    }
+
+    #[test]
+    fn default_endpoint_outside_the_default_group_is_rejected() {
+        let error = with_defaults(&["main"], &["b2"])
+            .validate()
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("'b2'") && error.contains("'backup'"),
+            "{error}"
+        );
+
+        // Likewise when no group is the default
+        let error = with_defaults(&[], &["a2"])
+            .validate()
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("'a2'") && error.contains("'main'"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn validation_errors_are_in_the_configured_language() {
+        let mut config = with_defaults(&["main", "backup"], &[]);
+        let error = config.validate().unwrap_err().to_string();
+        assert!(
+            error.contains("Mark only one group as the default"),
+            "{error}"
+        );
+
+        config.ui.language = Language::Zh;
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("只能将一个分组设为默认组"), "{error}");
+        assert!(error.contains("'main', 'backup'"), "{error}");
+    }
+
+    /// `TWO_GROUPS` with `settings` in place of its `[health_check]` options
+    /// for a missing CLI, the binaries resolved when `cli_found`
+    fn start_with(settings: &str, cli_found: bool) -> (Config, anyhow::Result<bool>) {
+        let mut config = changed("allow_missing_claude_binary = true", settings);
+        let resolved =
+            config.resolve_claude_binaries_with(|path| cli_found.then(|| PathBuf::from(path)));
+        if let Ok(missing) = resolved {
+            config.claude_binary_missing = missing;
+        }
+        (config, resolved)
+    }
+
+    #[test]
+    fn missing_cli_falls_back_to_http_checks_when_configured() {
+        let (config, resolved) = start_with("http_fallback = true", false);
+        assert!(resolved.unwrap());
+        assert!(config.http_health_checks());
+        assert!(!config.health_checks_disabled());
+        assert_eq!(config.health_check_mode_name(), "http");
+    }
+
+    #[test]
+    fn missing_cli_disables_health_checks_when_allowed() {
+        let (config, resolved) = start_with("allow_missing_claude_binary = true", false);
+        assert!(resolved.unwrap());
+        assert!(!config.http_health_checks());
+        assert!(config.health_checks_disabled());
+        assert_eq!(
+            config.health_check_mode_name(),
+            "disabled (claude binary missing)"
+        );
+    }
+
+    #[test]
+    fn missing_cli_fails_the_start_when_cli_mode_is_forced() {
+        let settings =
+            "mode = \"claude_cli\"\nhttp_fallback = true\nallow_missing_claude_binary = true";
+        let error = start_with(settings, false).1.unwrap_err().to_string();
+        assert!(error.contains("/nonexistent/claude"), "{error}");
+        assert!(
+            error.contains("health_check.mode = \"claude_cli\""),
+            "{error}"
+        );
+
+        // A CLI that is found is used as usual
+        let (config, resolved) = start_with(settings, true);
+        assert!(!resolved.unwrap());
+        assert_eq!(config.health_check_mode_name(), "claude_cli");
+    }
+
+    #[test]
+    fn missing_cli_without_a_fallback_names_the_options() {
+        let error = start_with("", false).1.unwrap_err().to_string();
+        assert!(error.contains("http_fallback"), "{error}");
+        assert!(error.contains("allow_missing_claude_binary"), "{error}");
+    }
+
+    #[test]
+    fn http_mode_does_not_look_for_the_cli() {
+        let mut config = changed("allow_missing_claude_binary = true", "mode = \"http\"");
+        let missing = config
+            .resolve_claude_binaries_with(|path| panic!("looked for {path}"))
+            .unwrap();
+        config.claude_binary_missing = missing;
+        assert!(config.http_health_checks());
+        assert_eq!(config.health_check_mode_name(), "http");
+    }
+}
@@ -1,5 +1,12 @@
-use crate::events::{ActiveConnection, ConnectionStatus};
-use std::collections::HashMap;
+use crate::endpoint::EndpointId;
+use crate::events::{
+    ActiveConnection, CompletedConnection, CompletionReason, ConnectionStatus, PhaseDurations,
+    RequestInfo, RequestTimings,
+};
+use crate::stats::{percentile, Summary};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
@@ -8,41 +15,173 @@ use tokio::sync::mpsc;
 /// Global counter for unique connection IDs
 static CONNECTION_COUNTER: AtomicU64 = AtomicU64::new(0);
 
+/// Characters of the counter shown by [`ConnectionId::short_display`]
+const SHORT_ID_LEN: usize = 6;
+
+/// Identifier of a proxied request, `req_<unix millis>_<counter>` with the
+/// process-wide counter in base36, zero-padded to six characters
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ConnectionId(String);
+
+impl ConnectionId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Last characters of the counter, enough to tell apart the connections
+    /// on screen; the full id is used everywhere else
+    pub fn short_display(&self) -> &str {
+        let counter = self.0.rsplit('_').next().unwrap_or(&self.0);
+        counter
+            .char_indices()
+            .rev()
+            .nth(SHORT_ID_LEN - 1)
+            .map_or(counter, |(start, _)| &counter[start..])
+    }
+}
+
+impl fmt::Display for ConnectionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Request timings kept per endpoint for the averages and percentiles
+const TIMING_SAMPLES: usize = 500;
+
 /// Diagnostic information about connection tracker state
 #[derive(Debug, Clone)]
 pub struct ConnectionDiagnostics {
     pub total_active: u32,
     pub endpoint_counts: HashMap<String, u32>,
-    pub duration_stats: Vec<u64>,
+    /// Ages of the active connections in seconds, overall and by endpoint
+    pub durations: Summary,
+    pub endpoint_durations: HashMap<String, Summary>,
     pub completed_count: u64,
     pub peak_concurrent: u32,
+    pub phase_averages: HashMap<String, PhaseAverages>,
+    pub request_timings: HashMap<String, TimingSummary>,
+    /// 90th percentile of the proxy's own share of recent requests, across
+    /// every endpoint
+    pub overhead_p90_us: Option<u64>,
+}
+
+/// Average time an endpoint's completed connections spent in each status
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct PhaseAverages {
+    /// Connections the averages are taken over
+    pub completed: u64,
+    #[serde(flatten)]
+    pub average: PhaseDurations,
+}
+
+/// Recent request timings of an endpoint, in microseconds
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct TimingSummary {
+    /// Requests the figures are taken over, the most recent ones
+    pub samples: usize,
+    pub preprocessing: TimingStats,
+    pub upstream_headers: TimingStats,
+    pub body_transfer: TimingStats,
+    pub response_write: TimingStats,
+    /// Pre-processing plus response write, the time spent in the proxy
+    pub overhead: TimingStats,
+}
+
+impl TimingSummary {
+    fn of(timings: &VecDeque<RequestTimings>) -> Self {
+        let stats = |part: fn(&RequestTimings) -> u64| TimingStats::of(timings.iter().map(part));
+        Self {
+            samples: timings.len(),
+            preprocessing: stats(|t| t.preprocessing_us),
+            upstream_headers: stats(|t| t.upstream_headers_us),
+            body_transfer: stats(|t| t.body_transfer_us),
+            response_write: stats(|t| t.response_write_us),
+            overhead: stats(RequestTimings::overhead_us),
+        }
+    }
+}
+
+/// Average and percentiles of one part of the request timings
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TimingStats {
+    pub average: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+}
+
+impl TimingStats {
+    fn of(values: impl Iterator<Item = u64>) -> Self {
+        let mut values: Vec<u64> = values.collect();
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_unstable();
+        Self {
+            average: values.iter().sum::<u64>() / values.len() as u64,
+            p50: percentile(&values, 50),
+            p90: percentile(&values, 90),
+            p99: percentile(&values, 99),
+        }
+    }
 }
 
 /// Tracks active connections and provides statistics
 #[derive(Debug)]
 pub struct ConnectionTracker {
-    active: HashMap<String, ActiveConnection>,
+    active: HashMap<ConnectionId, ActiveConnection>,
     completed_count: u64,
     peak_concurrent: u32,
-    endpoint_distribution: HashMap<String, u32>,
+    /// Active connections per endpoint
+    endpoint_distribution: HashMap<EndpointId, u32>,
+    /// Last URL each endpoint's requests were sent to, which diagnostics are
+    /// reported under
+    endpoint_urls: HashMap<EndpointId, String>,
+    /// Most recently completed connections, oldest first
+    recent: VecDeque<CompletedConnection>,
+    /// Completed connections kept in `recent`, 0 keeps none
+    recent_capacity: usize,
+    /// Per endpoint, connections completed normally and their summed phases
+    phase_totals: HashMap<EndpointId, (u64, PhaseDurations)>,
+    /// Per endpoint, the timings of its last `TIMING_SAMPLES` requests
+    request_timings: HashMap<EndpointId, VecDeque<RequestTimings>>,
 }
 
 impl ConnectionTracker {
-    pub fn new() -> Self {
+    /// Tracker keeping the last `recent_capacity` completed connections
+    pub fn new(recent_capacity: usize) -> Self {
         Self {
             active: HashMap::new(),
             completed_count: 0,
             peak_concurrent: 0,
             endpoint_distribution: HashMap::new(),
+            endpoint_urls: HashMap::new(),
+            recent: VecDeque::with_capacity(recent_capacity),
+            recent_capacity,
+            phase_totals: HashMap::new(),
+            request_timings: HashMap::new(),
         }
     }
 
-    pub fn start_connection(&mut self, id: String, endpoint: String) -> ActiveConnection {
-        let connection = ActiveConnection::new(id.clone(), endpoint.clone());
+    pub fn start_connection(
+        &mut self,
+        id: ConnectionId,
+        endpoint: String,
+        endpoint_id: EndpointId,
+        request_info: Option<RequestInfo>,
+    ) -> ActiveConnection {
+        self.endpoint_urls
+            .insert(endpoint_id.clone(), endpoint.clone());
+        *self
+            .endpoint_distribution
+            .entry(endpoint_id.clone())
+            .or_insert(0) += 1;
+        let connection = ActiveConnection::new(id.clone(), endpoint, endpoint_id, request_info);
 
         // Update statistics
         self.active.insert(id, connection.clone());
-        *self.endpoint_distribution.entry(endpoint).or_insert(0) += 1;
 
         // Track peak concurrent connections
         if self.active.len() as u32 > self.peak_concurrent {
@@ -54,7 +193,7 @@ impl ConnectionTracker {
 
     pub fn update_connection_status(
         &mut self,
-        id: &str,
+        id: &ConnectionId,
         status: ConnectionStatus,
     ) -> Option<ActiveConnection> {
         if let Some(connection) = self.active.get_mut(id) {
@@ -65,17 +204,82 @@ impl ConnectionTracker {
         }
     }
 
-    pub fn complete_connection(&mut self, id: &str) -> Option<ActiveConnection> {
-        if let Some(connection) = self.active.remove(id) {
-            self.completed_count += 1;
+    /// Record how much of the response body has been forwarded
+    pub fn update_bytes_received(&mut self, id: &ConnectionId, bytes_received: u64) {
+        if let Some(connection) = self.active.get_mut(id) {
+            connection.bytes_received = bytes_received;
+        }
+    }
+
+    /// Keep a finished request's timings for the endpoint that served it
+    pub fn record_timings(&mut self, endpoint: &EndpointId, timings: RequestTimings) {
+        let samples = self
+            .request_timings
+            .entry(endpoint.clone())
+            .or_insert_with(|| VecDeque::with_capacity(TIMING_SAMPLES));
+        if samples.len() == TIMING_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(timings);
+    }
 
-            // Update endpoint distribution
-            if let Some(count) = self.endpoint_distribution.get_mut(&connection.endpoint) {
-                *count = count.saturating_sub(1);
-                if *count == 0 {
-                    self.endpoint_distribution.remove(&connection.endpoint);
-                }
+    /// Attribute an active connection to a different endpoint (e.g. after fallback)
+    pub fn reassign_connection(
+        &mut self,
+        id: &ConnectionId,
+        endpoint: &str,
+        endpoint_id: &EndpointId,
+    ) -> Option<ActiveConnection> {
+        let connection = self.active.get_mut(id)?;
+        if connection.endpoint_id == *endpoint_id {
+            return Some(connection.clone());
+        }
+
+        let previous = std::mem::replace(&mut connection.endpoint_id, endpoint_id.clone());
+        connection.endpoint = endpoint.to_string();
+        let connection = connection.clone();
+        self.release_endpoint(&previous);
+        self.endpoint_urls
+            .insert(endpoint_id.clone(), endpoint.to_string());
+        *self
+            .endpoint_distribution
+            .entry(endpoint_id.clone())
+            .or_insert(0) += 1;
+        Some(connection)
+    }
+
+    /// Take a finished or moved connection off its endpoint's count
+    fn release_endpoint(&mut self, endpoint_id: &EndpointId) {
+        if let Some(count) = self.endpoint_distribution.get_mut(endpoint_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                self.endpoint_distribution.remove(endpoint_id);
             }
+        }
+    }
+
+    /// Stop tracking a connection and add it to the recent history, with the
+    /// status returned to the client
+    pub fn complete_connection(
+        &mut self,
+        id: &ConnectionId,
+        reason: CompletionReason,
+        status: Option<u16>,
+    ) -> Option<ActiveConnection> {
+        if let Some(connection) = self.active.remove(id) {
+            self.completed_count += 1;
+            let completed = connection.completed(reason, status);
+            let (count, totals) = self
+                .phase_totals
+                .entry(connection.endpoint_id.clone())
+                .or_default();
+            *count += 1;
+            totals.connecting_ms += completed.phases.connecting_ms;
+            totals.processing_ms += completed.phases.processing_ms;
+            totals.finishing_ms += completed.phases.finishing_ms;
+            self.remember(completed);
+
+            self.release_endpoint(&connection.endpoint_id);
 
             Some(connection)
         } else {
@@ -83,7 +287,26 @@ impl ConnectionTracker {
         }
     }
 
-    pub fn get_active_connections(&self) -> &HashMap<String, ActiveConnection> {
+    fn remember(&mut self, completed: CompletedConnection) {
+        if self.recent_capacity == 0 {
+            return;
+        }
+        while self.recent.len() >= self.recent_capacity {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(completed);
+    }
+
+    /// Recently completed connections, newest first
+    pub fn recent_connections(&self) -> impl Iterator<Item = &CompletedConnection> {
+        self.recent.iter().rev()
+    }
+
+    pub fn recent_capacity(&self) -> usize {
+        self.recent_capacity
+    }
+
+    pub fn get_active_connections(&self) -> &HashMap<ConnectionId, ActiveConnection> {
         &self.active
     }
 
@@ -99,11 +322,27 @@ impl ConnectionTracker {
         self.peak_concurrent
     }
 
-    #[allow(dead_code)]
-    pub fn get_endpoint_distribution(&self) -> &HashMap<String, u32> {
+    pub fn get_endpoint_distribution(&self) -> &HashMap<EndpointId, u32> {
         &self.endpoint_distribution
     }
 
+    /// Active connections per endpoint, by the URL they were sent to
+    pub fn connections_by_url(&self) -> BTreeMap<String, u32> {
+        let mut counts = BTreeMap::new();
+        for conn in self.active.values() {
+            *counts.entry(conn.endpoint.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// URL an endpoint's diagnostics are reported under
+    fn endpoint_url(&self, endpoint_id: &EndpointId) -> String {
+        self.endpoint_urls
+            .get(endpoint_id)
+            .cloned()
+            .unwrap_or_else(|| endpoint_id.to_string())
+    }
+
     #[allow(dead_code)]
     pub fn get_connections_for_endpoint(&self, endpoint: &str) -> Vec<&ActiveConnection> {
         self.active
@@ -112,11 +351,16 @@ impl ConnectionTracker {
             .collect()
     }
 
+    /// The connection that has been open the longest
+    pub fn oldest_connection(&self) -> Option<&ActiveConnection> {
+        self.active.values().min_by_key(|conn| conn.start_time)
+    }
+
     /// Get diagnostic information about connections
     pub fn get_connection_diagnostics(&self) -> ConnectionDiagnostics {
         let current_time = chrono::Utc::now();
         let mut endpoint_counts = HashMap::new();
-        let mut duration_stats = Vec::new();
+        let mut durations: HashMap<String, Vec<u64>> = HashMap::new();
 
         for conn in self.active.values() {
             // Count connections per endpoint
@@ -124,53 +368,63 @@ impl ConnectionTracker {
 
             // Calculate connection duration
             let duration_seconds = (current_time - conn.start_time).num_seconds() as u64;
-            duration_stats.push(duration_seconds);
+            durations
+                .entry(conn.endpoint.clone())
+                .or_default()
+                .push(duration_seconds);
         }
 
         ConnectionDiagnostics {
             total_active: self.active.len() as u32,
             endpoint_counts,
-            duration_stats,
+            durations: Summary::of(durations.values().flatten().copied()),
+            endpoint_durations: durations
+                .into_iter()
+                .map(|(endpoint, seconds)| (endpoint, Summary::of(seconds)))
+                .collect(),
             completed_count: self.completed_count,
             peak_concurrent: self.peak_concurrent,
+            phase_averages: self
+                .phase_totals
+                .iter()
+                .map(|(endpoint, (count, totals))| {
+                    let average = PhaseDurations {
+                        connecting_ms: totals.connecting_ms / count,
+                        processing_ms: totals.processing_ms / count,
+                        finishing_ms: totals.finishing_ms / count,
+                    };
+                    (
+                        self.endpoint_url(endpoint),
+                        PhaseAverages {
+                            completed: *count,
+                            average,
+                        },
+                    )
+                })
+                .collect(),
+            request_timings: self
+                .request_timings
+                .iter()
+                .map(|(endpoint, timings)| {
+                    (self.endpoint_url(endpoint), TimingSummary::of(timings))
+                })
+                .collect(),
+            overhead_p90_us: {
+                let overheads = self.request_timings.values().flatten();
+                let overhead = TimingStats::of(overheads.map(RequestTimings::overhead_us));
+                (!self.request_timings.is_empty()).then_some(overhead.p90)
+            },
         }
     }
 
-    /// Clean up connections for endpoints that are no longer active
-    pub fn cleanup_orphaned_connections(&mut self, current_active_endpoint: &str) -> Vec<String> {
-        let mut orphaned_connections = Vec::new();
-
-        // Find connections to endpoints that don't match the current active endpoint
-        let orphaned_ids: Vec<String> = self
-            .active
-            .iter()
-            .filter(|(_, conn)| conn.endpoint != current_active_endpoint)
-            .map(|(id, _)| id.clone())
-            .collect();
-
-        // Remove orphaned connections
-        for id in orphaned_ids {
-            if let Some(connection) = self.active.remove(&id) {
-                // Update endpoint distribution
-                if let Some(count) = self.endpoint_distribution.get_mut(&connection.endpoint) {
-                    *count = count.saturating_sub(1);
-                    if *count == 0 {
-                        self.endpoint_distribution.remove(&connection.endpoint);
-                    }
-                }
-                orphaned_connections.push(id);
-            }
-        }
-
-        orphaned_connections
-    }
-
     /// Force cleanup all active connections (for shutdown scenarios)
-    pub fn force_cleanup_all_connections(&mut self) -> Vec<String> {
-        let connection_ids: Vec<String> = self.active.keys().cloned().collect();
+    pub fn force_cleanup_all_connections(&mut self) -> Vec<ConnectionId> {
+        let connection_ids: Vec<ConnectionId> = self.active.keys().cloned().collect();
 
         for id in &connection_ids {
-            self.active.remove(id);
+            if let Some(connection) = self.active.remove(id) {
+                self.remember(connection.completed(CompletionReason::Shutdown, None));
+            }
         }
 
         // Clear endpoint distribution
@@ -180,11 +434,11 @@ impl ConnectionTracker {
     }
 
     /// Check for connections that should be considered abandoned (longer idle time)
-    pub fn cleanup_abandoned_connections(&mut self, max_idle_seconds: u64) -> Vec<String> {
+    pub fn cleanup_abandoned_connections(&mut self, max_idle_seconds: u64) -> Vec<ConnectionId> {
         let current_time = chrono::Utc::now();
         let mut abandoned = Vec::new();
 
-        let abandoned_ids: Vec<String> = self
+        let abandoned_ids: Vec<ConnectionId> = self
             .active
             .iter()
             .filter(|(_, conn)| {
@@ -196,13 +450,8 @@ impl ConnectionTracker {
 
         for id in abandoned_ids {
             if let Some(connection) = self.active.remove(&id) {
-                // Update endpoint distribution
-                if let Some(count) = self.endpoint_distribution.get_mut(&connection.endpoint) {
-                    *count = count.saturating_sub(1);
-                    if *count == 0 {
-                        self.endpoint_distribution.remove(&connection.endpoint);
-                    }
-                }
+                self.remember(connection.completed(CompletionReason::Abandoned, None));
+                self.release_endpoint(&connection.endpoint_id);
                 abandoned.push(id);
             }
         }
@@ -211,12 +460,12 @@ impl ConnectionTracker {
     }
 
     /// Clean up connections that have been running for too long (safety mechanism)
-    pub fn cleanup_stale_connections(&mut self, max_duration_seconds: u64) -> Vec<String> {
+    pub fn cleanup_stale_connections(&mut self, max_duration_seconds: u64) -> Vec<ConnectionId> {
         let mut stale_connections = Vec::new();
         let current_time = chrono::Utc::now();
 
         // Find connections that have been running longer than max_duration_seconds
-        let stale_ids: Vec<String> = self
+        let stale_ids: Vec<ConnectionId> = self
             .active
             .iter()
             .filter(|(_, conn)| {
@@ -229,13 +478,8 @@ impl ConnectionTracker {
         // Remove stale connections
         for id in stale_ids {
             if let Some(connection) = self.active.remove(&id) {
-                // Update endpoint distribution
-                if let Some(count) = self.endpoint_distribution.get_mut(&connection.endpoint) {
-                    *count = count.saturating_sub(1);
-                    if *count == 0 {
-                        self.endpoint_distribution.remove(&connection.endpoint);
-                    }
-                }
+                self.remember(connection.completed(CompletionReason::Stale, None));
+                self.release_endpoint(&connection.endpoint_id);
                 stale_connections.push(id);
             }
         }
@@ -248,15 +492,95 @@ impl ConnectionTracker {
 pub type SharedConnectionTracker = Arc<Mutex<ConnectionTracker>>;
 
 /// Utility to generate unique connection IDs
-pub fn generate_connection_id() -> String {
+pub fn generate_connection_id() -> ConnectionId {
     let counter = CONNECTION_COUNTER.fetch_add(1, Ordering::Relaxed);
     let timestamp = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap()
         .as_millis();
-    format!("req_{timestamp}_{counter}")
+    ConnectionId(format!(
+        "req_{timestamp}_{:0>width$}",
+        base36(counter),
+        width = SHORT_ID_LEN
+    ))
+}
+
+fn base36(mut value: u64) -> String {
+    const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let mut digits = Vec::new();
+    loop {
+        digits.push(DIGITS[(value % 36) as usize]);
+        value /= 36;
+        if value == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap_or_default()
 }
 
 /// Event sender for dashboard communication
 pub type EventSender = mpsc::UnboundedSender<crate::events::ProxyEvent>;
 pub type EventReceiver = mpsc::UnboundedReceiver<crate::events::ProxyEvent>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn base36_digits() {
+        assert_eq!(base36(0), "0");
+        assert_eq!(base36(35), "z");
+        assert_eq!(base36(36), "10");
+        assert_eq!(base36(36u64.pow(6) - 1), "zzzzzz");
+        assert_eq!(base36(u64::MAX), "3w5e11264sgsf");
+    }
+
+    #[test]
+    fn connection_id_format() {
+        let id = generate_connection_id();
+        let parts: Vec<&str> = id.as_str().split('_').collect();
+        assert_eq!(parts.len(), 3, "{id}");
+        assert_eq!(parts[0], "req");
+        assert!(parts[1].parse::<u128>().is_ok(), "{id}");
+        assert!(parts[2].len() >= SHORT_ID_LEN, "{id}");
+        assert!(
+            parts[2]
+                .chars()
+                .all(|c| c.is_ascii_digit() || c.is_ascii_lowercase()),
+            "{id}"
+        );
+        assert_eq!(id.to_string(), id.as_str());
+    }
+
+    #[test]
+    fn short_display_is_the_end_of_the_counter() {
+        let id = |s: &str| ConnectionId(s.to_string());
+        assert_eq!(id("req_1700000000000_00002s").short_display(), "00002s");
+        assert_eq!(id("req_1700000000000_1000000").short_display(), "000000");
+        // Counters shorter than the display and ids without a counter
+        assert_eq!(id("req_1700000000000_2s").short_display(), "2s");
+        assert_eq!(id("abc").short_display(), "abc");
+    }
+
+    #[test]
+    fn connection_ids_are_unique_across_threads() {
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    (0..2000)
+                        .map(|_| generate_connection_id())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let mut ids = HashSet::new();
+        for thread in threads {
+            for id in thread.join().unwrap() {
+                assert!(ids.insert(id.clone()), "{id} generated twice");
+            }
+        }
+        assert_eq!(ids.len(), 16_000);
+    }
+}
@@ -1,40 +1,64 @@
-use crate::config::{Config, EndpointConfig};
-use crate::connection_tracker::{EventReceiver, SharedConnectionTracker};
-use crate::dynamic_health::LoadLevel;
-use crate::events::{ActiveConnection, ConnectionStatus, ProxyEvent, SelectionMode};
-use crate::health::{EndpointStatus, LatencyHistory};
+use crate::build_info;
+use crate::config::{Config, SparklineScale};
+use crate::connection_tracker::{ConnectionId, EventReceiver, SharedConnectionTracker};
+use crate::diagnose::{
+    assemble_snapshot, write_snapshot, DEFAULT_DIAGNOSTICS_DIR, EVENT_LOG_LINES,
+};
+use crate::dynamic_health::{IntervalDecision, LoadLevel};
+use crate::endpoint::{EndpointId, EndpointIndex};
+use crate::endpoint_table::{self, EndpointRow, RowState};
+use crate::events::{
+    ActiveConnection, Alert, CompletedConnection, ConnectionStatus, HealthCycleSummary, ProxyEvent,
+    SelectionMode, SwitchReason, SwitchRecord,
+};
+use crate::group_health::{self, GroupHealth};
+use crate::health::{CheckState, EndpointStatus, LatencyHistory};
+use crate::health_orchestrator::{HealthOrchestratorHandle, OrchestratorCommand, ResetSource};
 use crate::i18n::I18n;
-use crate::proxy::SharedState;
+use crate::lock::LockExt;
+use crate::logging::{format_latency, format_phases};
+use crate::metrics::CounterSnapshot;
+use crate::proxy::{diagnostics_json, status_json, switches_json, SharedState, StatusQuery};
+use crate::signal_handler::{request_suspend, stop_process, TerminalSignal, TerminalSignals};
+use crate::stats::percentile;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
-    backend::CrosstermBackend,
+    backend::{Backend, CrosstermBackend},
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::Text,
-    widgets::{Block, Borders, List, ListItem, Paragraph, Row, Table, Wrap},
+    text::{Line, Span, Text},
+    widgets::{
+        block::{Position, Title},
+        Block, Borders, Clear, List, ListItem, Paragraph, Row, Table, Wrap,
+    },
     Frame, Terminal,
 };
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
 use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 use tokio::time::interval;
 
 /// Main dashboard application state
 pub struct Dashboard {
     /// All configured endpoints (for pre-filling)
     all_endpoints: Vec<String>,
-    /// Endpoint configuration mapping (URL -> Config)
-    endpoint_configs: HashMap<String, EndpointConfig>,
-    /// Current endpoint health status
-    endpoint_health: HashMap<String, EndpointStatus>,
+    /// Configured endpoints by id and URL, for their names
+    endpoints: EndpointIndex,
+    /// Current endpoint health status, see [`Dashboard::health_of`]
+    endpoint_health: HashMap<EndpointId, EndpointStatus>,
     /// Current active endpoint
     current_endpoint: String,
     /// Active connections from tracker
     active_connections: Vec<ActiveConnection>,
+    /// Response body progress by connection id
+    connection_progress: HashMap<ConnectionId, StreamProgress>,
     /// Connection statistics
     total_connections: u32,
     peak_connections: u32,
@@ -44,6 +68,9 @@ pub struct Dashboard {
     /// Health check timing
     next_health_check: Instant,
     health_check_interval: Duration,
+    /// What the health check interval was calculated from, shown under the
+    /// endpoint table
+    interval_decision: Option<IntervalDecision>,
     /// Health check running status
     health_check_running: Option<(Instant, Duration)>, // (started_at, estimated_duration)
     /// Load status information
@@ -51,47 +78,393 @@ pub struct Dashboard {
     active_connections_count: u32,
     /// Selection mode and manual selection state
     selection_mode: SelectionMode,
-    manual_selected_index: Option<usize>, // Index in all_endpoints for manual selection
+    /// Manually selected endpoint URL, kept by URL so it survives list changes
+    manual_selected: Option<String>,
     /// Proxy server information
-    proxy_port: u16,
+    /// Local URLs the proxy serves on, with the group a listener is limited to
+    proxy_urls: Vec<String>,
+    /// Health checks run without the proxy: no URLs to show and no traffic,
+    /// so the connections panel gives way to the cursor endpoint's health
+    monitor_only: bool,
     /// UI state
     should_quit: bool,
+    /// Health checks paused, as last confirmed by the health checker
     paused: bool,
+    /// Pause (`true`) or resume sent with 'p' and not confirmed yet, with when
+    pending_pause: Option<(bool, Instant)>,
+    /// Auto-switching frozen while health checks keep running
+    switching_frozen: bool,
+    /// The last full health check cycle found every endpoint down
+    all_endpoints_down: bool,
+    /// Threshold alerts firing, oldest first
+    active_alerts: Vec<Alert>,
+    /// Requests fail fast while every endpoint is down (`retry.fail_fast_when_all_down`)
+    fail_fast_enabled: bool,
+    /// First row shown in the table, counting group summary rows
     scroll_offset: usize,
+    /// Rows that fit in the table, measured at the last render
+    table_rows: usize,
     /// Cursor position for endpoint selection (replaces direct key selection)
     cursor_index: usize,
     /// Request tracking for improved load calculation
     recent_requests: VecDeque<Instant>,
+    /// Switch history popup state (newest first)
+    show_switch_history: bool,
+    switch_history: Vec<SwitchRecord>,
+    switch_history_scroll: usize,
+    /// Endpoint counters copied from the proxy metrics on every tick
+    endpoint_counters: BTreeMap<String, CounterSnapshot>,
+    retries_last_minute: u64,
+    /// Requests are being rejected over `server.max_in_flight_requests`: the
+    /// limit is reached, or requests were rejected since the last tick
+    shedding_load: bool,
+    in_flight_rejected: u64,
+    /// Proxy overhead of the last `OVERHEAD_SAMPLES` requests, in microseconds
+    request_overheads: VecDeque<u64>,
+    overhead_p90_us: Option<u64>,
+    show_retry_column: bool,
+    /// Connection ages at which the activity dot turns yellow and red
+    connection_warn: Duration,
+    connection_alert: Duration,
+    /// Range the trend sparklines are drawn against
+    sparkline_scale: SparklineScale,
+    /// List connections oldest first instead of newest first ('o')
+    oldest_first: bool,
+    /// Show recently completed requests instead of active connections ('v')
+    show_recent: bool,
+    /// Recently completed requests from the tracker, newest first
+    recent_connections: Vec<CompletedConnection>,
+    /// Estimated cost, only shown once a group has pricing configured
+    pricing_enabled: bool,
+    currency: String,
+    /// Tag filter copied from proxy state, and the 'T' popup editing it
+    tag_filter: Vec<String>,
+    tag_picker: Option<TagPicker>,
+    /// Endpoint a first 'X' asked to reset, with when; a second 'X' on the
+    /// same row within the notice confirms it
+    pending_reset: Option<(String, Instant)>,
+    /// Endpoint that is down and waits for a second Enter to be selected
+    pending_selection: Option<(String, Instant)>,
+    /// Endpoints draining after a switch, with their active connections
+    draining: HashMap<String, u32>,
+    /// Endpoint kept warm for the next fallback, copied from proxy state
+    standby: Option<String>,
+    /// Last finished health check cycle, for the cycle counter and its notices
+    last_cycle: Option<HealthCycleSummary>,
+    /// Endpoints whose group has no token, with the env var they wait for
+    missing_tokens: HashMap<String, String>,
+    /// Health check model of every group by name, shown for the cursor row
+    health_check_models: HashMap<String, String>,
+    /// Startup check still running, the proxy is not accepting traffic yet
+    starting: bool,
+    /// Transient message shown in the status bar
+    notice: Option<Notice>,
+    /// Recent proxy events, oldest first, for diagnostic snapshots ('e')
+    event_log: VecDeque<String>,
     /// Internationalization support
     i18n: I18n,
 }
 
+/// Streams without a chunk for this long are highlighted as slow
+const STREAM_STALL_WARNING: Duration = Duration::from_secs(5);
+/// Streams without a chunk for this long are highlighted as stalled
+const STREAM_STALL_CRITICAL: Duration = Duration::from_secs(20);
+
+/// Checks shown in each trend sparkline
+const SPARKLINE_SAMPLES: usize = 6;
+
+/// Completed requests the status bar's proxy overhead p90 is taken over
+const OVERHEAD_SAMPLES: usize = 200;
+
+/// How long a status bar notice stays visible
+const NOTICE_DURATION: Duration = Duration::from_secs(8);
+
+/// How long a pause or resume waits for the health checker to confirm it.
+/// Commands queue behind a running cycle, so this allows for a slow one.
+const PAUSE_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Message shown in the status bar in place of the key hints for a while
+#[derive(Debug, Clone)]
+struct Notice {
+    text: String,
+    raised_at: Instant,
+    /// Something went wrong or was refused, rather than a confirmation
+    warning: bool,
+}
+
+impl Notice {
+    fn warning(text: String) -> Self {
+        Self {
+            text,
+            raised_at: Instant::now(),
+            warning: true,
+        }
+    }
+
+    fn info(text: String) -> Self {
+        Self {
+            text,
+            raised_at: Instant::now(),
+            warning: false,
+        }
+    }
+}
+
+/// Latest body progress reported for a connection
+#[derive(Debug, Clone, Copy)]
+struct StreamProgress {
+    bytes_received: u64,
+    last_chunk_at: Instant,
+}
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 struct SwitchInfo {
     from: String,
     to: String,
-    from_latency: u64,
-    to_latency: u64,
-    improvement: u64,
+    from_latency: Option<u64>,
+    to_latency: Option<u64>,
+    /// Latency saved by the switch, `None` when either side was never measured
+    improvement: Option<u64>,
+    reason: SwitchReason,
+    timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<SwitchRecord> for SwitchInfo {
+    fn from(record: SwitchRecord) -> Self {
+        Self {
+            improvement: latency_improvement(record.from_latency, record.to_latency),
+            from: record.from,
+            to: record.to,
+            from_latency: record.from_latency,
+            to_latency: record.to_latency,
+            reason: record.reason,
+            timestamp: record.timestamp,
+        }
+    }
+}
+
+/// Tag filter being edited in the 'T' popup
+struct TagPicker {
+    /// Every configured tag and whether it is chosen
+    tags: Vec<(String, bool)>,
+    cursor: usize,
+}
+
+impl TagPicker {
+    fn chosen_tags(&self) -> Vec<String> {
+        self.tags
+            .iter()
+            .filter(|(_, chosen)| *chosen)
+            .map(|(tag, _)| tag.clone())
+            .collect()
+    }
+}
+
+/// What the event loop does after [`Dashboard::handle_key`] updated the UI state
+#[derive(Debug, Clone)]
+enum Action {
+    None,
+    Quit,
+    SendCommand(OrchestratorCommand),
+    OpenSwitchHistory,
+    OpenTagPicker,
+    /// Set the tag filter chosen in the picker
+    ApplyTagFilter(Vec<String>),
+    ToggleSelectionMode,
+    /// Switch to the endpoint at this index (manual mode), `forced` when it is
+    /// down and the selection was confirmed
+    SelectEndpoint {
+        index: usize,
+        forced: bool,
+    },
+    /// Write a diagnostic snapshot for a problem report
+    ExportDiagnostics,
+}
+
+/// Where the dashboard reads key presses from
+pub trait InputSource {
+    /// Wait for the next key press, `None` once no more input will arrive
+    async fn next_key(&mut self) -> Option<KeyCode>;
+}
+
+/// How long the input thread waits for a terminal event before checking
+/// whether the dashboard is still listening
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Key presses read from the terminal on a dedicated thread, so a slow frame
+/// never delays input and queued keys are handled one after another
+pub struct CrosstermInput {
+    keys: mpsc::UnboundedReceiver<KeyCode>,
+}
+
+impl CrosstermInput {
+    pub fn spawn() -> Self {
+        let (sender, keys) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            while !sender.is_closed() {
+                match event::poll(INPUT_POLL_INTERVAL) {
+                    Ok(false) => {}
+                    Ok(true) => match event::read() {
+                        Ok(Event::Key(key))
+                            if key.code == KeyCode::Char('z')
+                                && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                        {
+                            request_suspend();
+                        }
+                        Ok(Event::Key(key)) => {
+                            let _ = sender.send(key.code);
+                        }
+                        Ok(_) => {}
+                        Err(_) => break,
+                    },
+                    Err(_) => break,
+                }
+            }
+        });
+        Self { keys }
+    }
+}
+
+impl InputSource for CrosstermInput {
+    async fn next_key(&mut self) -> Option<KeyCode> {
+        self.keys.recv().await
+    }
+}
+
+/// Most proxy events handled in one pass of the dashboard loop, so a burst of
+/// requests can't hold off key presses and ticks
+const MAX_EVENTS_PER_BATCH: usize = 1024;
+
+/// How often the dashboard syncs with the tracker and redraws when idle
+const TICK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Smallest terminal the full layout is drawn in; anything smaller shows a
+/// single notice instead of squeezing the panels
+const MIN_TERMINAL_WIDTH: u16 = 40;
+const MIN_TERMINAL_HEIGHT: u16 = 12;
+
+/// Put the terminal in the state the dashboard draws in
+fn enter_terminal() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)
+}
+
+/// Give the terminal back to the shell. Every step is attempted even if an
+/// earlier one fails, so raw mode is never left on.
+fn leave_terminal() -> io::Result<()> {
+    let raw_mode = disable_raw_mode();
+    let screen = execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        crossterm::cursor::Show
+    );
+    raw_mode.and(screen)
+}
+
+/// Latency saved by moving from `from` to `to`, if both are known
+fn latency_improvement(from: Option<u64>, to: Option<u64>) -> Option<u64> {
+    Some(from?.saturating_sub(to?))
+}
+
+/// A row of the endpoint table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TableLine<'a> {
+    /// Summary of the named group, which the cursor skips
+    Group(&'a str),
+    /// The endpoint at this position in `all_endpoints`
+    Endpoint(usize),
+}
+
+/// Rows of the endpoint table for `endpoints`, with a summary row above each
+/// group's first endpoint once more than one group is shown
+fn table_lines<'a>(endpoints: &[String], index: &'a EndpointIndex) -> Vec<TableLine<'a>> {
+    let groups: Vec<Option<&str>> = endpoints
+        .iter()
+        .map(|url| index.resolve(url).map(|endpoint| endpoint.group.as_str()))
+        .collect();
+    let grouped = groups.iter().flatten().collect::<HashSet<_>>().len() > 1;
+
+    let mut lines = Vec::with_capacity(endpoints.len());
+    let mut previous = None;
+    for (position, group) in groups.into_iter().enumerate() {
+        if grouped && group != previous {
+            lines.extend(group.map(TableLine::Group));
+        }
+        previous = group;
+        lines.push(TableLine::Endpoint(position));
+    }
+    lines
+}
+
+/// Env var each of `endpoints` is waiting for, for those whose group has no token
+fn missing_tokens(config: &Config, endpoints: &[String]) -> HashMap<String, String> {
+    endpoints
+        .iter()
+        .filter_map(|url| Some((url.clone(), config.missing_token_env(url)?.to_string())))
+        .collect()
+}
+
+/// Model the health checks of each group ask for, by group name
+fn health_check_models(config: &Config) -> HashMap<String, String> {
+    config
+        .groups
+        .iter()
+        .map(|group| (group.name.clone(), config.health_probe(&group.name).model))
+        .collect()
+}
+
+/// Cursor and manual selection after the endpoint list changed
+#[derive(Debug, Clone, PartialEq)]
+struct ReconciledSelection {
+    cursor_index: usize,
+    /// `None` when nothing was selected or the selected endpoint is gone
+    manual_selected: Option<String>,
+}
+
+/// Carry the cursor and manual selection over from `old` to `new` by URL.
+///
+/// The cursor stays on the same endpoint wherever it moved to, and is clamped
+/// to the new list when its endpoint was removed.
+fn reconcile_endpoint_list(
+    old: &[String],
+    new: &[String],
+    cursor_index: usize,
+    manual_selected: Option<&str>,
+) -> ReconciledSelection {
+    let cursor_index = old
+        .get(cursor_index)
+        .and_then(|url| new.iter().position(|ep| ep == url))
+        .unwrap_or_else(|| cursor_index.min(new.len().saturating_sub(1)));
+    let manual_selected = manual_selected
+        .filter(|url| new.iter().any(|ep| ep == url))
+        .map(str::to_string);
+
+    ReconciledSelection {
+        cursor_index,
+        manual_selected,
+    }
 }
 
 impl Dashboard {
     pub fn new(config: &Config, health_check_interval: Duration) -> Self {
         let mut endpoint_health = HashMap::new();
-        let mut endpoint_configs = HashMap::new();
         let mut all_endpoints = Vec::new();
 
-        // Extract all endpoints and their configs
-        for (_, endpoint_config, _) in config.get_all_endpoints_legacy() {
-            let url = endpoint_config.url.clone();
-            all_endpoints.push(url.clone());
-            endpoint_configs.insert(url.clone(), endpoint_config);
+        // All endpoints, including groups without a token
+        for endpoint in config.endpoint_index().iter() {
+            all_endpoints.push(endpoint.url.clone());
 
             // Pre-fill with checking status
-            endpoint_health.insert(url.clone(), EndpointStatus::new_checking(url));
+            endpoint_health.insert(
+                endpoint.id.clone(),
+                EndpointStatus::new_checking(endpoint.url.clone()),
+            );
         }
 
+        let missing_tokens = missing_tokens(config, &all_endpoints);
+
         // Set default current endpoint
         let default_endpoint = if let Some((_, default_endpoint)) = config.get_default_endpoint() {
             default_endpoint.url.clone()
@@ -101,154 +474,502 @@ impl Dashboard {
 
         Self {
             all_endpoints,
-            endpoint_configs,
+            endpoints: config.endpoint_index().clone(),
             endpoint_health,
             current_endpoint: default_endpoint,
             active_connections: Vec::new(),
+            connection_progress: HashMap::new(),
             total_connections: 0,
             peak_connections: 0,
             completed_connections: 0,
             last_switch: None,
             next_health_check: Instant::now(), // Will be properly set by first HealthCheckStarted event
             health_check_interval,
+            interval_decision: None,
             health_check_running: None, // No health check running initially
             current_load_level: LoadLevel::Idle,
             active_connections_count: 0,
             selection_mode: SelectionMode::Auto, // Start with auto mode
-            manual_selected_index: None,         // No manual selection initially
-            proxy_port: config.server.port,
+            manual_selected: None,               // No manual selection initially
+            proxy_urls: proxy_urls(config),
+            monitor_only: false,
             should_quit: false,
             paused: false,
+            pending_pause: None,
+            switching_frozen: false,
+            all_endpoints_down: false,
+            active_alerts: Vec::new(),
+            fail_fast_enabled: config.retry.fail_fast_when_all_down,
             scroll_offset: 0,
+            table_rows: 10,
             cursor_index: 0,
             recent_requests: VecDeque::new(),
+            show_switch_history: false,
+            switch_history: Vec::new(),
+            switch_history_scroll: 0,
+            endpoint_counters: BTreeMap::new(),
+            retries_last_minute: 0,
+            shedding_load: false,
+            in_flight_rejected: 0,
+            request_overheads: VecDeque::with_capacity(OVERHEAD_SAMPLES),
+            overhead_p90_us: None,
+            show_retry_column: config.ui.show_retry_column,
+            connection_warn: Duration::from_secs(config.ui.connection_warn_seconds),
+            connection_alert: Duration::from_secs(config.ui.connection_alert_seconds),
+            sparkline_scale: config.ui.sparkline_scale,
+            oldest_first: false,
+            show_recent: false,
+            recent_connections: Vec::new(),
+            pricing_enabled: false,
+            currency: config.ui.currency.clone(),
+            tag_filter: Vec::new(),
+            tag_picker: None,
+            pending_reset: None,
+            pending_selection: None,
+            draining: HashMap::new(),
+            standby: None,
+            last_cycle: None,
+            missing_tokens,
+            health_check_models: health_check_models(config),
+            starting: config.health_check.startup_check,
+            notice: None,
+            event_log: VecDeque::with_capacity(EVENT_LOG_LINES),
             i18n: I18n::new(config.ui.language.clone()),
         }
     }
 
+    /// Show health checks only, for `claude-zephyr monitor`
+    pub fn set_monitor_only(&mut self) {
+        self.monitor_only = true;
+        self.proxy_urls.clear();
+    }
+
     /// Run the main dashboard loop
     pub async fn run(
         &mut self,
-        mut event_receiver: EventReceiver,
+        event_receiver: EventReceiver,
         connection_tracker: SharedConnectionTracker,
         proxy_state: SharedState,
-        orchestrator_command_sender: tokio::sync::mpsc::UnboundedSender<
-            crate::health_orchestrator::OrchestratorCommand,
-        >,
+        orchestrator: HealthOrchestratorHandle,
     ) -> anyhow::Result<()> {
         // Setup terminal
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend)?;
+        enter_terminal()?;
+        let backend = CrosstermBackend::new(io::stdout());
+        let mut terminal = match Terminal::new(backend) {
+            Ok(terminal) => terminal,
+            Err(e) => {
+                let _ = leave_terminal();
+                return Err(e.into());
+            }
+        };
+
+        let mut input = CrosstermInput::spawn();
+        let result = self
+            .event_loop(
+                &mut terminal,
+                &mut input,
+                event_receiver,
+                connection_tracker,
+                proxy_state,
+                orchestrator,
+            )
+            .await;
+
+        // Cleanup terminal, even when the loop failed
+        let cleanup = leave_terminal();
+        result?;
+        cleanup?;
+        Ok(())
+    }
 
-        let mut tick_interval = interval(Duration::from_millis(250)); // 4 FPS
+    async fn event_loop<B: Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        input: &mut impl InputSource,
+        mut event_receiver: EventReceiver,
+        connection_tracker: SharedConnectionTracker,
+        proxy_state: SharedState,
+        orchestrator: HealthOrchestratorHandle,
+    ) -> anyhow::Result<()> {
+        let mut tick_interval = interval(TICK_INTERVAL); // 4 FPS
+        let mut terminal_signals = TerminalSignals::new();
+        let mut events = Vec::with_capacity(MAX_EVENTS_PER_BATCH);
+        let mut events_closed = false;
+        self.load_state(&proxy_state);
 
         loop {
-            // Handle events
+            // Proxy events only update state; the frame is drawn on the next
+            // tick, key press or terminal signal
+            let mut draw = true;
+
             tokio::select! {
-                // Handle proxy events - always process to stay in sync
-                event = event_receiver.recv() => {
-                    if let Some(event) = event {
+                // Handle proxy events in batches - always process to stay in sync
+                received = event_receiver.recv_many(&mut events, MAX_EVENTS_PER_BATCH), if !events_closed => {
+                    events_closed = received == 0;
+                    for event in events.drain(..) {
                         self.handle_proxy_event(event);
                     }
+                    draw = false;
                 }
 
                 // Update active connections from tracker - always run regardless of pause state
                 // Users expect to see real-time connection monitoring even when health checks are paused
                 _ = tick_interval.tick() => {
+                    self.sync_endpoint_list(&proxy_state);
                     self.update_from_tracker(&connection_tracker);
+                    self.update_draining(&proxy_state);
+                    self.standby = proxy_state
+                        .lock_or_recover()
+                        .standby_url()
+                        .map(str::to_string);
+                    self.update_endpoint_counters(&proxy_state);
+                    self.expire_pending_pause();
+                    if self.show_switch_history {
+                        self.update_switch_history(&proxy_state);
+                    }
                 }
 
-                // Handle keyboard input
-                _ = tokio::time::sleep(Duration::from_millis(16)) => {
-                    if event::poll(Duration::from_millis(0))? {
-                        if let Event::Key(key) = event::read()? {
-                            match key.code {
-                                KeyCode::Char('q') => break,
-                                KeyCode::Char('r') => {
-                                    // Manual refresh - trigger health check
-                                    let _ = orchestrator_command_sender.send(crate::health_orchestrator::OrchestratorCommand::ManualRefresh);
-                                    self.update_from_tracker(&connection_tracker);
-                                }
-                                KeyCode::Char('p') => {
-                                    // Toggle system pause/resume
-                                    self.paused = !self.paused;
-                                    if self.paused {
-                                        let _ = orchestrator_command_sender.send(crate::health_orchestrator::OrchestratorCommand::Pause);
-                                    } else {
-                                        let _ = orchestrator_command_sender.send(crate::health_orchestrator::OrchestratorCommand::Resume);
-                                    }
-                                }
-                                KeyCode::Char('m') => {
-                                    // Toggle selection mode
-                                    self.toggle_selection_mode(&proxy_state);
-                                }
-                                KeyCode::Up => {
-                                    // Move cursor up (with wraparound)
-                                    if self.cursor_index > 0 {
-                                        self.cursor_index -= 1;
-                                    } else {
-                                        self.cursor_index = self.all_endpoints.len().saturating_sub(1);
-                                    }
-
-                                    // Auto-adjust scroll offset to follow cursor
-                                    if self.cursor_index < self.scroll_offset {
-                                        self.scroll_offset = self.cursor_index;
-                                    }
-                                }
-                                KeyCode::Down => {
-                                    // Move cursor down (with wraparound)
-                                    if self.cursor_index < self.all_endpoints.len().saturating_sub(1) {
-                                        self.cursor_index += 1;
-                                    } else {
-                                        self.cursor_index = 0;
-                                    }
-
-                                    // Auto-adjust scroll offset to follow cursor
-                                    // Assuming ~10 visible rows, adjust as needed
-                                    if self.cursor_index >= self.scroll_offset + 10 {
-                                        self.scroll_offset = self.cursor_index.saturating_sub(9);
-                                    }
-                                }
-                                KeyCode::Enter => {
-                                    // Confirm endpoint selection (only in manual mode)
-                                    if self.selection_mode == SelectionMode::Manual {
-                                        self.handle_manual_endpoint_selection_by_index(self.cursor_index, &proxy_state);
-                                    }
-                                }
-                                _ => {}
-                            }
+                // Handle keyboard input as soon as it arrives
+                key = input.next_key() => {
+                    let Some(key) = key else {
+                        // The terminal stopped delivering input
+                        break;
+                    };
+                    match self.handle_key(key) {
+                        Action::None => {}
+                        Action::Quit => break,
+                        Action::SendCommand(command) => {
+                            orchestrator.send(command);
+                            self.update_from_tracker(&connection_tracker);
                         }
+                        Action::OpenSwitchHistory => {
+                            self.update_switch_history(&proxy_state);
+                            self.switch_history_scroll = 0;
+                            self.show_switch_history = true;
+                        }
+                        Action::OpenTagPicker => self.open_tag_picker(&proxy_state),
+                        Action::ApplyTagFilter(tags) => self.apply_tag_filter(tags, &proxy_state),
+                        Action::ToggleSelectionMode => self.toggle_selection_mode(&proxy_state),
+                        Action::SelectEndpoint { index, forced } => {
+                            self.handle_manual_endpoint_selection_by_index(
+                                index,
+                                forced,
+                                &proxy_state,
+                            );
+                        }
+                        Action::ExportDiagnostics => {
+                            self.export_diagnostics(&proxy_state, &connection_tracker);
+                        }
+                    }
+                }
+
+                // Ctrl+Z / fg: hand the screen back to the shell while stopped,
+                // then redraw everything since the screen is stale
+                signal = terminal_signals.recv() => {
+                    if signal == TerminalSignal::Suspend {
+                        leave_terminal()?;
+                        stop_process();
+                        enter_terminal()?;
                     }
+                    terminal.clear()?;
                 }
             }
 
-            // Render UI
-            terminal.draw(|f| self.render(f))?;
+            // Render UI. A panic while laying out a frame ends the dashboard
+            // with an error so the terminal is still restored.
+            if draw {
+                let drawn = panic::catch_unwind(AssertUnwindSafe(|| {
+                    terminal.draw(|f| self.render(f)).map(|_| ())
+                }));
+                match drawn {
+                    Ok(result) => result?,
+                    Err(_) => anyhow::bail!("Dashboard rendering panicked"),
+                }
+            }
 
             if self.should_quit {
                 break;
             }
         }
 
-        // Cleanup terminal
-        disable_raw_mode()?;
-        execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        )?;
-        terminal.show_cursor()?;
-
         Ok(())
     }
 
+    /// Update the UI state for a key press and return what [`Self::run`]
+    /// still has to do outside the dashboard
+    fn handle_key(&mut self, key: KeyCode) -> Action {
+        // The tag picker captures navigation keys while open
+        if let Some(picker) = &mut self.tag_picker {
+            match key {
+                KeyCode::Char('q') => return Action::Quit,
+                KeyCode::Char('t') | KeyCode::Char('T') | KeyCode::Esc => {
+                    self.tag_picker = None;
+                }
+                KeyCode::Up => {
+                    picker.cursor = picker.cursor.saturating_sub(1);
+                }
+                KeyCode::Down if picker.cursor + 1 < picker.tags.len() => {
+                    picker.cursor += 1;
+                }
+                KeyCode::Char(' ') => {
+                    if let Some((_, chosen)) = picker.tags.get_mut(picker.cursor) {
+                        *chosen = !*chosen;
+                    }
+                }
+                KeyCode::Char('c') => {
+                    for (_, chosen) in &mut picker.tags {
+                        *chosen = false;
+                    }
+                }
+                KeyCode::Enter => {
+                    let tags = picker.chosen_tags();
+                    self.tag_picker = None;
+                    return Action::ApplyTagFilter(tags);
+                }
+                _ => {}
+            }
+            return Action::None;
+        }
+
+        // The switch history popup captures navigation keys while open
+        if self.show_switch_history {
+            match key {
+                KeyCode::Char('q') => return Action::Quit,
+                KeyCode::Char('h') | KeyCode::Esc => {
+                    self.show_switch_history = false;
+                }
+                KeyCode::Up => {
+                    self.switch_history_scroll = self.switch_history_scroll.saturating_sub(1);
+                }
+                KeyCode::Down if self.switch_history_scroll + 1 < self.switch_history.len() => {
+                    self.switch_history_scroll += 1;
+                }
+                _ => {}
+            }
+            return Action::None;
+        }
+
+        // Any other key cancels a reset waiting for confirmation
+        let pending_reset = self.pending_reset.take();
+        if key == KeyCode::Char('X') {
+            return self.request_reset(pending_reset);
+        }
+        if pending_reset.is_some() {
+            self.notice = None;
+        }
+        // Likewise a forced selection of an endpoint that is down
+        let pending_selection = self.pending_selection.take();
+        if key == KeyCode::Enter && self.selection_mode == SelectionMode::Manual {
+            return self.request_selection(pending_selection);
+        }
+        if pending_selection.is_some() {
+            self.notice = None;
+        }
+
+        match key {
+            KeyCode::Char('q') => Action::Quit,
+            // Open the recent switches timeline
+            KeyCode::Char('h') => Action::OpenSwitchHistory,
+            // Manual refresh - trigger health check
+            KeyCode::Char('r') => Action::SendCommand(OrchestratorCommand::ManualRefresh),
+            // Re-check only the endpoints that are down
+            KeyCode::Char('R') => Action::SendCommand(OrchestratorCommand::RefreshFailed),
+            KeyCode::Char('p') => {
+                // Toggle system pause/resume; `paused` only changes once the
+                // health checker confirms it
+                let pause = !self.pending_pause.map_or(self.paused, |(pause, _)| pause);
+                self.pending_pause = Some((pause, Instant::now()));
+                Action::SendCommand(if pause {
+                    OrchestratorCommand::Pause
+                } else {
+                    OrchestratorCommand::Resume
+                })
+            }
+            KeyCode::Char('f') => {
+                // Keep routing on the current endpoint while checks continue
+                self.switching_frozen = !self.switching_frozen;
+                Action::SendCommand(if self.switching_frozen {
+                    OrchestratorCommand::FreezeSwitching
+                } else {
+                    OrchestratorCommand::UnfreezeSwitching
+                })
+            }
+            // Choose which tags auto-selection and the table consider
+            KeyCode::Char('t') | KeyCode::Char('T') => Action::OpenTagPicker,
+            KeyCode::Char('o') => {
+                // Spot stuck connections by listing the oldest first
+                self.oldest_first = !self.oldest_first;
+                self.sort_connections();
+                Action::None
+            }
+            KeyCode::Char('v') => {
+                // Confirm a change took effect on the last few requests
+                self.show_recent = !self.show_recent;
+                Action::None
+            }
+            // Toggle selection mode
+            KeyCode::Char('m') => Action::ToggleSelectionMode,
+            // Save everything a problem report needs
+            KeyCode::Char('e') => Action::ExportDiagnostics,
+            KeyCode::Char('w') => {
+                self.export_endpoint_table();
+                Action::None
+            }
+            KeyCode::Up => {
+                // Move cursor up (with wraparound)
+                if self.cursor_index > 0 {
+                    self.cursor_index -= 1;
+                } else {
+                    self.cursor_index = self.all_endpoints.len().saturating_sub(1);
+                }
+                self.keep_cursor_visible();
+                Action::None
+            }
+            KeyCode::Down => {
+                // Move cursor down (with wraparound)
+                if self.cursor_index < self.all_endpoints.len().saturating_sub(1) {
+                    self.cursor_index += 1;
+                } else {
+                    self.cursor_index = 0;
+                }
+                self.keep_cursor_visible();
+                Action::None
+            }
+            // Page and jump through long endpoint lists, without wraparound
+            KeyCode::PageUp => {
+                self.cursor_index = self.cursor_index.saturating_sub(self.table_rows.max(1));
+                self.keep_cursor_visible();
+                Action::None
+            }
+            KeyCode::PageDown => {
+                self.cursor_index = (self.cursor_index + self.table_rows.max(1))
+                    .min(self.all_endpoints.len().saturating_sub(1));
+                self.keep_cursor_visible();
+                Action::None
+            }
+            KeyCode::Home => {
+                self.cursor_index = 0;
+                self.keep_cursor_visible();
+                Action::None
+            }
+            KeyCode::End => {
+                self.cursor_index = self.all_endpoints.len().saturating_sub(1);
+                self.keep_cursor_visible();
+                Action::None
+            }
+            _ => Action::None,
+        }
+    }
+
+    /// Ask to reset the endpoint on the cursor row, or reset it when the last
+    /// key already asked for the same one
+    fn request_reset(&mut self, pending: Option<(String, Instant)>) -> Action {
+        let Some(endpoint) = self.all_endpoints.get(self.cursor_index).cloned() else {
+            return Action::None;
+        };
+        let name = self.get_endpoint_name(&endpoint);
+        let confirmed = pending.is_some_and(|(pending, asked_at)| {
+            pending == endpoint && asked_at.elapsed() < NOTICE_DURATION
+        });
+        if !confirmed {
+            self.notice = Some(Notice::warning(self.i18n.confirm_endpoint_reset(&name)));
+            self.pending_reset = Some((endpoint, Instant::now()));
+            return Action::None;
+        }
+
+        self.notice = Some(Notice::info(self.i18n.endpoint_reset(&name)));
+        Action::SendCommand(OrchestratorCommand::ResetEndpoint {
+            endpoint,
+            source: ResetSource::Dashboard,
+        })
+    }
+
+    /// Select the endpoint on the cursor row. One that is down is only
+    /// selected when the last key already asked for the same one.
+    fn request_selection(&mut self, pending: Option<(String, Instant)>) -> Action {
+        let Some(endpoint) = self.all_endpoints.get(self.cursor_index).cloned() else {
+            return Action::None;
+        };
+        // Endpoints the selection refuses anyway are not asked about
+        let down = self.health_of(&endpoint).is_some_and(|status| {
+            status.state == CheckState::Unhealthy && status.maintenance_until.is_none()
+        }) && !self.missing_tokens.contains_key(&endpoint)
+            && endpoint != self.current_endpoint;
+        if !down {
+            return Action::SelectEndpoint {
+                index: self.cursor_index,
+                forced: false,
+            };
+        }
+
+        let confirmed = pending.is_some_and(|(pending, asked_at)| {
+            pending == endpoint && asked_at.elapsed() < NOTICE_DURATION
+        });
+        if !confirmed {
+            let name = self.get_endpoint_name(&endpoint);
+            self.notice = Some(Notice::warning(self.i18n.confirm_forced_selection(&name)));
+            self.pending_selection = Some((endpoint, Instant::now()));
+            return Action::None;
+        }
+
+        self.notice = None;
+        Action::SelectEndpoint {
+            index: self.cursor_index,
+            forced: true,
+        }
+    }
+
+    /// The health checker paused or resumed. A confirmation of an earlier
+    /// press leaves a later, opposite press waiting for its own.
+    fn confirm_pause(&mut self, paused: bool) {
+        self.paused = paused;
+        if self.pending_pause.is_some_and(|(pause, _)| pause == paused) {
+            self.pending_pause = None;
+        }
+    }
+
+    /// Give up on a pause or resume the health checker never confirmed
+    fn expire_pending_pause(&mut self) {
+        let Some((pause, sent_at)) = self.pending_pause else {
+            return;
+        };
+        if sent_at.elapsed() < PAUSE_ACK_TIMEOUT {
+            return;
+        }
+        self.pending_pause = None;
+        let text = self
+            .i18n
+            .pause_not_confirmed(pause, PAUSE_ACK_TIMEOUT.as_secs());
+        self.notice = Some(Notice::warning(text));
+    }
+
     fn handle_proxy_event(&mut self, event: ProxyEvent) {
+        // Stream progress arrives per chunk and would push everything else out
+        if !matches!(event, ProxyEvent::ConnectionProgress { .. }) {
+            if self.event_log.len() == EVENT_LOG_LINES {
+                self.event_log.pop_front();
+            }
+            self.event_log.push_back(format!(
+                "{} {event:?}",
+                chrono::Local::now().format("%H:%M:%S%.3f")
+            ));
+        }
+
         match event {
             ProxyEvent::HealthUpdate(status) => {
-                self.endpoint_health.insert(status.endpoint.clone(), status);
+                let Some(endpoint) = self.endpoints.resolve(&status.endpoint) else {
+                    return;
+                };
+                let newly_rejected = status.auth_failed
+                    && status.state == CheckState::Unhealthy
+                    && !self
+                        .endpoint_health
+                        .get(&endpoint.id)
+                        .is_some_and(|previous| previous.auth_failed);
+                if newly_rejected {
+                    self.notice = Some(Notice::warning(self.i18n.auth_rejected(&endpoint.name)));
+                }
+                self.endpoint_health.insert(endpoint.id.clone(), status);
                 // Don't reset countdown for individual health updates
                 // Let the health check cycle event handle timing
             }
@@ -257,10 +978,12 @@ impl Dashboard {
                 next_check_time,
                 load_level,
                 active_connections,
+                interval_decision,
             } => {
                 // Use the actual next check time from the main loop, not calculated time
                 self.next_health_check = next_check_time;
                 self.health_check_interval = actual_interval;
+                self.interval_decision = interval_decision;
                 self.current_load_level = load_level;
                 self.active_connections_count = active_connections;
                 self.health_check_running = None; // Health check hasn't started executing yet
@@ -275,15 +998,22 @@ impl Dashboard {
                 // Health check completed, clear running status
                 self.health_check_running = None;
             }
+            ProxyEvent::HealthCycleSummary(summary) => {
+                if let Some(notice) = self.cycle_notice(&summary) {
+                    self.notice = Some(notice);
+                }
+                self.last_cycle = Some(summary);
+            }
             ProxyEvent::EndpointSwitch {
                 from,
                 to,
                 from_latency,
                 to_latency,
+                reason,
             } => {
                 self.current_endpoint = to.clone();
                 // Calculate improvement: positive when switching to faster endpoint
-                let improvement = from_latency.saturating_sub(to_latency);
+                let improvement = latency_improvement(from_latency, to_latency);
 
                 self.last_switch = Some(SwitchInfo {
                     from,
@@ -291,27 +1021,71 @@ impl Dashboard {
                     from_latency,
                     to_latency,
                     improvement,
+                    reason,
+                    timestamp: chrono::Utc::now(),
                 });
             }
             ProxyEvent::SelectionModeChanged { mode } => {
                 self.selection_mode = mode;
             }
-            ProxyEvent::ManualEndpointSelected {
-                endpoint,
-                endpoint_index,
-            } => {
+            ProxyEvent::ManualEndpointSelected { endpoint, .. } => {
+                self.manual_selected = Some(endpoint.clone());
                 self.current_endpoint = endpoint;
-                self.manual_selected_index = Some(endpoint_index);
             }
-            ProxyEvent::ServerStarted { .. } => {}
+            ProxyEvent::ServerStarted { .. } => {
+                self.starting = false;
+            }
             ProxyEvent::ConfigLoaded { .. } => {}
             ProxyEvent::SystemPaused => {
                 // System is now truly paused - health checks stopped
-                self.paused = true;
+                self.confirm_pause(true);
             }
             ProxyEvent::SystemResumed => {
                 // System is now running - health checks resumed
-                self.paused = false;
+                self.confirm_pause(false);
+            }
+            ProxyEvent::SwitchingFrozen { frozen } => {
+                self.switching_frozen = frozen;
+            }
+            ProxyEvent::AllEndpointsDown { down } => {
+                self.all_endpoints_down = down;
+            }
+            ProxyEvent::GroupDown { group, down } => {
+                self.notice = Some(if down {
+                    Notice::warning(self.i18n.group_down_notice(&group))
+                } else {
+                    Notice::info(self.i18n.group_recovered_notice(&group))
+                });
+            }
+            ProxyEvent::AlertChanged { alert, firing } => {
+                self.active_alerts.retain(|active| {
+                    (&active.rule, &active.endpoint) != (&alert.rule, &alert.endpoint)
+                });
+                if firing {
+                    self.active_alerts.push(alert);
+                } else {
+                    self.notice = Some(Notice::info(
+                        self.i18n
+                            .alert_resolved(&alert.rule, alert.endpoint.as_deref()),
+                    ));
+                }
+            }
+            ProxyEvent::ConfigReloaded { diff, current } => {
+                // The endpoint list itself is picked up on the next tick
+                self.current_endpoint = current;
+                self.notice = Some(match &diff.listener_error {
+                    Some(error) => Notice::warning(self.i18n.listeners_not_changed(error)),
+                    None => Notice::info(self.i18n.config_reloaded(
+                        diff.added.len(),
+                        diff.removed.len(),
+                        diff.changed.len(),
+                    )),
+                });
+            }
+            ProxyEvent::ConfigReloadFailed { error } => {
+                // Config errors carry a hint on further lines, the status bar has one
+                let error = error.lines().next().unwrap_or_default();
+                self.notice = Some(Notice::warning(self.i18n.config_reload_failed(error)));
             }
             ProxyEvent::ManualRefreshTriggered => {
                 // Manual refresh was triggered - no special UI action needed
@@ -334,21 +1108,52 @@ impl Dashboard {
                 // Recalculate load level based on both active connections and request rate
                 self.recalculate_load_level();
             }
+            ProxyEvent::ConnectionProgress {
+                id,
+                bytes_received,
+                last_chunk_at,
+            } => {
+                self.connection_progress.insert(
+                    id,
+                    StreamProgress {
+                        bytes_received,
+                        last_chunk_at,
+                    },
+                );
+            }
+            ProxyEvent::ConnectionCompleted { id, timings, .. } => {
+                self.connection_progress.remove(&id);
+                if let Some(timings) = timings {
+                    self.record_overhead(timings.overhead_us());
+                }
+            }
             _ => {} // Connection events are handled via tracker updates
         }
     }
 
+    /// Add a completed request's proxy overhead to the status bar's p90
+    fn record_overhead(&mut self, overhead_us: u64) {
+        if self.request_overheads.len() == OVERHEAD_SAMPLES {
+            self.request_overheads.pop_front();
+        }
+        self.request_overheads.push_back(overhead_us);
+        let mut sorted: Vec<u64> = self.request_overheads.iter().copied().collect();
+        sorted.sort_unstable();
+        self.overhead_p90_us = Some(percentile(&sorted, 90));
+    }
+
     /// Recalculate load level based on both active connections and request frequency
     fn recalculate_load_level(&mut self) {
         let now = Instant::now();
 
-        // Calculate request rate per minute
+        // Calculate request rate per minute. Timestamps arrive in order, so
+        // a binary search keeps this cheap during a burst of requests.
         let one_minute_ago = now - Duration::from_secs(60);
-        let requests_last_minute = self
-            .recent_requests
-            .iter()
-            .filter(|&&timestamp| timestamp >= one_minute_ago)
-            .count() as f64;
+        let requests_last_minute = (self.recent_requests.len()
+            - self
+                .recent_requests
+                .partition_point(|&timestamp| timestamp < one_minute_ago))
+            as f64;
 
         // Get current active connections count
         let active_connections = self.active_connections_count;
@@ -384,28 +1189,24 @@ impl Dashboard {
 
         // When switching to manual mode, set current endpoint as the manual selection
         if self.selection_mode == SelectionMode::Manual {
-            if let Some(index) = self
-                .all_endpoints
-                .iter()
-                .position(|ep| ep == &self.current_endpoint)
-            {
-                self.manual_selected_index = Some(index);
+            if self.all_endpoints.contains(&self.current_endpoint) {
+                self.manual_selected = Some(self.current_endpoint.clone());
             }
         } else {
             // When switching to auto mode, clear manual selection
-            self.manual_selected_index = None;
+            self.manual_selected = None;
         }
 
         // Store selection mode in proxy state for health orchestrator to read
-        if let Ok(mut state_guard) = proxy_state.lock() {
-            state_guard.selection_mode = self.selection_mode;
-        }
+        let mut state_guard = proxy_state.lock_or_recover();
+        state_guard.selection_mode = self.selection_mode;
     }
 
     /// Handle manual endpoint selection by index (0-based)
     fn handle_manual_endpoint_selection_by_index(
         &mut self,
         index: usize,
+        forced: bool,
         proxy_state: &SharedState,
     ) {
         // Only process in manual mode
@@ -417,79 +1218,538 @@ impl Dashboard {
         if index < self.all_endpoints.len() {
             let endpoint = &self.all_endpoints[index];
 
+            if let Some(token_env) = self.missing_tokens.get(endpoint) {
+                self.notice = Some(Notice::warning(self.i18n.no_token_selected(token_env)));
+                return;
+            }
+            if let Some(until) = self
+                .health_of(endpoint)
+                .and_then(|status| status.maintenance_until)
+            {
+                let until = until.with_timezone(&chrono::Local).format("%H:%M");
+                self.notice = Some(Notice::warning(
+                    self.i18n.maintenance_selected(&until.to_string()),
+                ));
+                return;
+            }
+
             // Only switch if it's a different endpoint
             if endpoint != &self.current_endpoint {
                 self.current_endpoint = endpoint.clone();
-                self.manual_selected_index = Some(index);
+                self.manual_selected = Some(endpoint.clone());
+
+                // Directly switch endpoint in proxy state (dashboard mode, no console log)
+                let mut state_guard = proxy_state.lock_or_recover();
+                let record = if forced {
+                    state_guard.force_switch_endpoint(endpoint.clone(), true)
+                } else {
+                    state_guard.switch_endpoint(endpoint.clone(), SwitchReason::ManualSwitch, true)
+                };
+                drop(state_guard);
 
-                // Directly switch endpoint in proxy state
-                if let Ok(mut state_guard) = proxy_state.lock() {
-                    state_guard.switch_endpoint_silent(endpoint.clone());
+                if let Some(record) = record {
+                    self.last_switch = Some(record.into());
                 }
             }
         }
     }
 
-    fn update_from_tracker(&mut self, tracker: &SharedConnectionTracker) {
-        if let Ok(tracker_guard) = tracker.lock() {
-            self.active_connections = tracker_guard
-                .get_active_connections()
-                .values()
-                .cloned()
-                .collect();
-            self.active_connections
-                .sort_by(|a, b| b.start_time.cmp(&a.start_time)); // Newest first
-
-            self.total_connections = tracker_guard.get_active_count();
-            self.peak_connections = tracker_guard.get_peak_concurrent();
-            self.completed_connections = tracker_guard.get_completed_count();
+    /// Start from what the proxy state already holds, such as endpoints
+    /// restored from a state handoff, rather than from events alone. Events
+    /// queued before this still apply on top, in order.
+    fn load_state(&mut self, proxy_state: &SharedState) {
+        self.sync_endpoint_list(proxy_state);
+        let state_guard = proxy_state.lock_or_recover();
+        for (id, status) in &state_guard.endpoint_status {
+            if self.endpoints.get(id).is_some() {
+                self.endpoint_health.insert(id.clone(), status.clone());
+            }
+        }
+        self.current_endpoint = state_guard.current_url().to_string();
+        self.selection_mode = state_guard.selection_mode;
+        if self.selection_mode == SelectionMode::Manual {
+            self.manual_selected = Some(self.current_endpoint.clone());
+        }
+        self.switching_frozen = state_guard.switching_frozen;
+        self.all_endpoints_down = state_guard.all_down_until.is_some();
+        self.active_alerts.clone_from(&state_guard.active_alerts);
+        self.last_cycle.clone_from(&state_guard.last_cycle_summary);
+        if let Some((interval, decision)) = state_guard.health_interval {
+            self.health_check_interval = interval;
+            self.interval_decision = Some(decision);
         }
     }
 
-    fn render(&self, f: &mut Frame) {
-        // Main layout: split vertically first to reserve space for status bar
-        let main_chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Min(0),    // Main content area
-                Constraint::Length(1), // Status bar at bottom
-            ])
-            .split(f.size());
+    /// Pick up endpoint list changes from the proxy config, keeping the cursor
+    /// and manual selection on the same endpoints
+    fn sync_endpoint_list(&mut self, proxy_state: &SharedState) {
+        let (new_endpoints, index) = {
+            let state_guard = proxy_state.lock_or_recover();
+            self.tag_filter.clone_from(&state_guard.tag_filter);
+            let urls: Vec<String> = state_guard
+                .config
+                .endpoint_index()
+                .iter()
+                .map(|endpoint| endpoint.url.clone())
+                .filter(|url| state_guard.matches_tag_filter(url))
+                .collect();
+            self.missing_tokens = missing_tokens(&state_guard.config, &urls);
+            self.health_check_models = health_check_models(&state_guard.config);
+            self.proxy_urls = proxy_urls(&state_guard.config);
+            if urls == self.all_endpoints {
+                return;
+            }
+            (urls, state_guard.config.endpoint_index().clone())
+        };
 
-        // Split main content area horizontally: left (health) and right (connections)
-        // Left:Right = 2.5:1 ratio for wider left panel
-        let content_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(71), Constraint::Percentage(29)]) // 71:29 ≈ 2.5:1
-            .split(main_chunks[0]);
+        let reconciled = reconcile_endpoint_list(
+            &self.all_endpoints,
+            &new_endpoints,
+            self.cursor_index,
+            self.manual_selected.as_deref(),
+        );
 
-        // Render left panel (health monitoring)
-        self.render_health_panel(f, content_chunks[0]);
+        self.endpoints = index;
+        self.endpoint_health
+            .retain(|id, _| self.endpoints.get(id).is_some());
+        for endpoint in self.endpoints.iter() {
+            let status = self
+                .endpoint_health
+                .entry(endpoint.id.clone())
+                .or_insert_with(|| EndpointStatus::new_checking(endpoint.url.clone()));
+            status.endpoint.clone_from(&endpoint.url);
+        }
+        self.all_endpoints = new_endpoints;
+        self.cursor_index = reconciled.cursor_index;
+        self.keep_cursor_visible();
+
+        // The manual choice is gone, so let automatic selection take over
+        if self.selection_mode == SelectionMode::Manual && reconciled.manual_selected.is_none() {
+            let removed = self.manual_selected.take().unwrap_or_default();
+            self.selection_mode = SelectionMode::Auto;
+            proxy_state.lock_or_recover().selection_mode = SelectionMode::Auto;
+            self.notice = Some(Notice::warning(self.i18n.manual_endpoint_removed(&removed)));
+        }
+        self.manual_selected = reconciled.manual_selected;
+    }
 
-        // Render right panel (active connections)
-        self.render_connections_panel(f, content_chunks[1]);
+    /// Write a diagnostic snapshot with the recent event log and show where
+    fn export_diagnostics(
+        &mut self,
+        proxy_state: &SharedState,
+        connection_tracker: &SharedConnectionTracker,
+    ) {
+        let config = proxy_state.lock_or_recover().config.clone();
+        let snapshot = assemble_snapshot(
+            &config,
+            status_json(
+                proxy_state,
+                Some(connection_tracker),
+                &StatusQuery::default(),
+            ),
+            diagnostics_json(connection_tracker, false),
+            switches_json(proxy_state),
+            Some(self.event_log.iter().cloned().collect()),
+        );
+        self.notice = Some(
+            match write_snapshot(&snapshot, Path::new(DEFAULT_DIAGNOSTICS_DIR)) {
+                Ok(path) => Notice::info(self.i18n.diagnostics_saved(&path.display().to_string())),
+                Err(e) => Notice::warning(self.i18n.diagnostics_failed(&format!("{e:#}"))),
+            },
+        );
+    }
 
-        // Render status bar at bottom (using the reserved space)
-        self.render_status_bar(f, main_chunks[1]);
+    /// Row of the endpoint table for `endpoint_url`, from what the dashboard
+    /// currently shows
+    fn endpoint_row(&self, endpoint_url: &str) -> Option<EndpointRow> {
+        let endpoint = self.endpoints.resolve(endpoint_url)?;
+        Some(EndpointRow::new(
+            endpoint,
+            self.health_of(endpoint_url),
+            self.endpoint_counters
+                .get(endpoint_url)
+                .copied()
+                .unwrap_or_default(),
+            self.missing_tokens.contains_key(endpoint_url),
+        ))
     }
 
-    fn render_health_panel(&self, f: &mut Frame, area: Rect) {
-        // Left panel: title with proxy info, subtitle with status info, and endpoints
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(4), // Main title with proxy info (increased height)
+    /// Write the whole endpoint table to a CSV file in the working directory
+    fn export_endpoint_table(&mut self) {
+        let rows: Vec<EndpointRow> = self
+            .all_endpoints
+            .iter()
+            .filter_map(|url| self.endpoint_row(url))
+            .collect();
+        self.notice = Some(
+            match endpoint_table::write_csv(&rows, &self.currency, Path::new(".")) {
+                Ok(path) => Notice::info(self.i18n.table_exported(&path.display().to_string())),
+                Err(e) => Notice::warning(self.i18n.table_export_failed(&format!("{e:#}"))),
+            },
+        );
+    }
+
+    fn open_tag_picker(&mut self, proxy_state: &SharedState) {
+        let tags: Vec<(String, bool)> = {
+            let state_guard = proxy_state.lock_or_recover();
+            state_guard
+                .config
+                .all_tags()
+                .into_iter()
+                .map(|tag| {
+                    let chosen = state_guard.tag_filter.contains(&tag);
+                    (tag, chosen)
+                })
+                .collect()
+        };
+
+        if tags.is_empty() {
+            self.notice = Some(Notice::warning(self.i18n.no_tags_configured().to_string()));
+            return;
+        }
+        self.tag_picker = Some(TagPicker { tags, cursor: 0 });
+    }
+
+    /// Set the tag filter in proxy state and narrow the endpoints table to it
+    fn apply_tag_filter(&mut self, tags: Vec<String>, proxy_state: &SharedState) {
+        let record = proxy_state.lock_or_recover().set_tag_filter(tags, true);
+        if let Some(record) = record {
+            self.current_endpoint = record.to.clone();
+            self.last_switch = Some(record.into());
+        }
+        self.sync_endpoint_list(proxy_state);
+    }
+
+    /// Position of the manually selected endpoint in the list
+    fn manual_selected_index(&self) -> Option<usize> {
+        let selected = self.manual_selected.as_ref()?;
+        self.all_endpoints.iter().position(|ep| ep == selected)
+    }
+
+    /// Refresh the switch history snapshot from proxy state (newest first)
+    fn update_switch_history(&mut self, proxy_state: &SharedState) {
+        self.switch_history = proxy_state
+            .lock_or_recover()
+            .switch_history
+            .iter()
+            .rev()
+            .cloned()
+            .collect();
+        self.switch_history_scroll = self
+            .switch_history_scroll
+            .min(self.switch_history.len().saturating_sub(1));
+    }
+
+    fn update_endpoint_counters(&mut self, proxy_state: &SharedState) {
+        // Clone the handle so the counters are read without holding the state lock
+        let metrics = proxy_state.lock_or_recover().metrics.clone();
+        self.endpoint_counters = metrics.endpoint_snapshots();
+        self.retries_last_minute = metrics.retries_last_minute();
+        self.pricing_enabled = metrics.pricing_enabled();
+        let in_flight = metrics.in_flight();
+        self.shedding_load = in_flight.at_limit() || in_flight.rejected > self.in_flight_rejected;
+        self.in_flight_rejected = in_flight.rejected;
+    }
+
+    /// Count the active connections of each draining endpoint, call after
+    /// `update_from_tracker`
+    fn update_draining(&mut self, proxy_state: &SharedState) {
+        let draining: Vec<(EndpointId, String)> = proxy_state
+            .lock_or_recover()
+            .draining
+            .iter()
+            .map(|(id, drain)| (id.clone(), drain.url.clone()))
+            .collect();
+        self.draining = draining
+            .into_iter()
+            .map(|(id, url)| {
+                let active = self
+                    .active_connections
+                    .iter()
+                    .filter(|conn| conn.endpoint_id == id)
+                    .count() as u32;
+                (url, active)
+            })
+            .collect();
+    }
+
+    fn update_from_tracker(&mut self, tracker: &SharedConnectionTracker) {
+        let tracker_guard = tracker.lock_or_recover();
+        self.active_connections = tracker_guard
+            .get_active_connections()
+            .values()
+            .cloned()
+            .collect();
+        self.sort_connections();
+        self.recent_connections = tracker_guard.recent_connections().cloned().collect();
+
+        // Drop progress for connections the tracker no longer knows about
+        let active = tracker_guard.get_active_connections();
+        self.connection_progress
+            .retain(|id, _| active.contains_key(id));
+
+        self.total_connections = tracker_guard.get_active_count();
+        self.peak_connections = tracker_guard.get_peak_concurrent();
+        self.completed_connections = tracker_guard.get_completed_count();
+    }
+
+    /// Newest first, or oldest first when toggled with 'o'
+    fn sort_connections(&mut self) {
+        if self.oldest_first {
+            self.active_connections.sort_by_key(|conn| conn.start_time);
+        } else {
+            self.active_connections
+                .sort_by_key(|conn| std::cmp::Reverse(conn.start_time));
+        }
+    }
+
+    /// Scroll the endpoint table so the cursor row is shown, with its group's
+    /// summary row when it is the group's first endpoint, and once the list is
+    /// scrolled no rows are left empty at the bottom
+    fn keep_cursor_visible(&mut self) {
+        let rows = self.table_rows.max(1);
+        let (cursor_line, top, line_count) = {
+            let lines = table_lines(&self.all_endpoints, &self.endpoints);
+            let cursor_line = self.cursor_line(&lines);
+            let top = match cursor_line.checked_sub(1).map(|above| lines[above]) {
+                Some(TableLine::Group(_)) if rows > 1 => cursor_line - 1,
+                _ => cursor_line,
+            };
+            (cursor_line, top, lines.len())
+        };
+        if top < self.scroll_offset {
+            self.scroll_offset = top;
+        } else if cursor_line >= self.scroll_offset + rows {
+            self.scroll_offset = cursor_line + 1 - rows;
+        }
+        self.scroll_offset = self.scroll_offset.min(line_count.saturating_sub(rows));
+    }
+
+    /// Position of the cursor's endpoint among the table `lines`
+    fn cursor_line(&self, lines: &[TableLine]) -> usize {
+        lines
+            .iter()
+            .position(|line| *line == TableLine::Endpoint(self.cursor_index))
+            .unwrap_or(0)
+    }
+
+    /// Health of every group by name, from what the dashboard currently shows
+    fn group_summaries(&self) -> HashMap<String, GroupHealth> {
+        let mut connections: HashMap<EndpointId, u32> = HashMap::new();
+        for connection in &self.active_connections {
+            *connections
+                .entry(connection.endpoint_id.clone())
+                .or_default() += 1;
+        }
+        let counters: BTreeMap<String, CounterSnapshot> = self
+            .endpoint_counters
+            .iter()
+            .filter_map(|(url, counters)| {
+                let id = self.endpoints.id_of(url)?;
+                Some((id.to_string(), *counters))
+            })
+            .collect();
+        group_health::summarize(
+            &self.endpoints,
+            &self.endpoint_health,
+            &connections,
+            &counters,
+        )
+        .into_iter()
+        .map(|group| (group.name.clone(), group))
+        .collect()
+    }
+
+    /// Bold summary row of the group `name`: healthy endpoints, best latency
+    /// and active connections, in red once the whole group is down
+    fn group_row(&self, name: &str, health: Option<&GroupHealth>) -> Row<'static> {
+        let health = health.cloned().unwrap_or_default();
+        let latency_text = if health.down {
+            self.i18n.group_down().to_string()
+        } else {
+            format_latency(health.best_latency_ms)
+        };
+        let mut cells = vec![
+            ratatui::widgets::Cell::from(format!("{}/{}", health.healthy, health.endpoints)),
+            ratatui::widgets::Cell::from(format!("[{name}]")),
+            ratatui::widgets::Cell::from(latency_text),
+            ratatui::widgets::Cell::from(self.i18n.group_connections(health.active_connections)),
+        ];
+        if self.show_retry_column {
+            cells.push(ratatui::widgets::Cell::from(""));
+        }
+        let style = Style::default().add_modifier(Modifier::BOLD);
+        Row::new(cells).style(if health.down {
+            style.fg(Color::Red)
+        } else {
+            style
+        })
+    }
+
+    fn render(&mut self, f: &mut Frame) {
+        let size = f.size();
+        if size.width < MIN_TERMINAL_WIDTH || size.height < MIN_TERMINAL_HEIGHT {
+            let notice = Paragraph::new(
+                self.i18n
+                    .terminal_too_small(MIN_TERMINAL_WIDTH, MIN_TERMINAL_HEIGHT),
+            )
+            .style(Style::default().fg(Color::Yellow))
+            .wrap(Wrap { trim: true });
+            f.render_widget(notice, size);
+            return;
+        }
+
+        // Main layout: split vertically first to reserve space for status bar
+        let main_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),    // Main content area
+                Constraint::Length(1), // Status bar at bottom
+            ])
+            .split(f.size());
+
+        // Split main content area horizontally: left (health) and right (connections)
+        // Left:Right = 2.5:1 ratio for wider left panel
+        let content_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(71), Constraint::Percentage(29)]) // 71:29 ≈ 2.5:1
+            .split(main_chunks[0]);
+
+        // Render left panel (health monitoring)
+        self.render_health_panel(f, content_chunks[0]);
+
+        // Render right panel (active connections, or recent requests with 'v')
+        if self.monitor_only {
+            self.render_health_detail_panel(f, content_chunks[1]);
+        } else if self.show_recent {
+            self.render_recent_panel(f, content_chunks[1]);
+        } else {
+            self.render_connections_panel(f, content_chunks[1]);
+        }
+
+        // Render status bar at bottom (using the reserved space)
+        self.render_status_bar(f, main_chunks[1]);
+
+        // Popups are drawn on top of everything else
+        if self.show_switch_history {
+            self.render_switch_history(f, main_chunks[0]);
+        }
+        if let Some(picker) = &self.tag_picker {
+            self.render_tag_picker(f, picker, main_chunks[0]);
+        }
+    }
+
+    fn render_switch_history(&self, f: &mut Frame, area: Rect) {
+        let popup_area = centered_rect(80, 70, area);
+        let title = self.i18n.switch_history_title(self.switch_history.len());
+        let block = Block::default().borders(Borders::ALL).title(title);
+
+        f.render_widget(Clear, popup_area);
+
+        if self.switch_history.is_empty() {
+            let empty = Paragraph::new(self.i18n.switch_history_empty())
+                .block(block)
+                .style(Style::default().fg(Color::Gray));
+            f.render_widget(empty, popup_area);
+            return;
+        }
+
+        let items: Vec<ListItem> = self
+            .switch_history
+            .iter()
+            .skip(self.switch_history_scroll)
+            .map(|record| {
+                let local_time = record.timestamp.with_timezone(&chrono::Local);
+                let from_name = if record.from.is_empty() {
+                    "-".to_string()
+                } else {
+                    self.get_endpoint_name(&record.from)
+                };
+                let to_name = if record.to.is_empty() {
+                    "-".to_string()
+                } else {
+                    self.get_endpoint_name(&record.to)
+                };
+                let forced = if record.forced {
+                    format!(", {}", self.i18n.switch_forced())
+                } else {
+                    String::new()
+                };
+                let content = format!(
+                    "{} {} ({}) → {} ({}) [{}{forced}]",
+                    local_time.format("%H:%M:%S"),
+                    from_name,
+                    format_latency(record.from_latency),
+                    to_name,
+                    format_latency(record.to_latency),
+                    self.i18n.switch_reason(record.reason)
+                );
+                let color = if record.forced {
+                    Color::Yellow
+                } else {
+                    Color::White
+                };
+                ListItem::new(content).style(Style::default().fg(color))
+            })
+            .collect();
+
+        f.render_widget(List::new(items).block(block), popup_area);
+    }
+
+    fn render_tag_picker(&self, f: &mut Frame, picker: &TagPicker, area: Rect) {
+        let popup_area = centered_rect(50, 50, area);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.i18n.tag_picker_title());
+
+        let items: Vec<ListItem> = picker
+            .tags
+            .iter()
+            .enumerate()
+            .map(|(index, (tag, chosen))| {
+                let checkbox = if *chosen { "[x]" } else { "[ ]" };
+                let style = if index == picker.cursor {
+                    Style::default()
+                        .bg(Color::Blue)
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                ListItem::new(format!("{checkbox} {tag}")).style(style)
+            })
+            .collect();
+
+        f.render_widget(Clear, popup_area);
+        f.render_widget(List::new(items).block(block), popup_area);
+    }
+
+    fn render_health_panel(&mut self, f: &mut Frame, area: Rect) {
+        // Left panel: title with proxy info, subtitle with status info, and endpoints
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(4), // Main title with proxy info (increased height)
                 Constraint::Length(3), // Enhanced subtitle with status info
                 Constraint::Min(0),    // Endpoints table - takes all remaining space
             ])
             .split(area);
 
         // Main title with proxy information
-        let proxy_url = format!("http://localhost:{}", self.proxy_port);
+        let second_line = if self.monitor_only {
+            self.i18n.monitor_only_label().to_string()
+        } else {
+            format!(
+                "{} {}",
+                self.i18n.proxy_label(),
+                self.proxy_urls.join("  •  ")
+            )
+        };
         let title_text = format!(
-            "{}\n{} {proxy_url}",
+            "{} ({})\n{}",
             self.i18n.app_title(),
-            self.i18n.proxy_label()
+            build_info::short_commit(),
+            second_line
         );
         let title = Paragraph::new(title_text)
             .block(Block::default().borders(Borders::ALL))
@@ -501,6 +1761,46 @@ impl Dashboard {
             .wrap(Wrap { trim: true });
         f.render_widget(title, chunks[0]);
 
+        // Requests are being refused, which matters more than the usual subtitle
+        if self.failing_fast() {
+            let next_check = self
+                .next_health_check
+                .saturating_duration_since(Instant::now())
+                .as_secs();
+            let banner = Paragraph::new(self.i18n.all_endpoints_down_banner(next_check))
+                .block(Block::default().borders(Borders::ALL))
+                .style(
+                    Style::default()
+                        .fg(Color::White)
+                        .bg(Color::Red)
+                        .add_modifier(Modifier::BOLD),
+                )
+                .alignment(Alignment::Center);
+            f.render_widget(banner, chunks[1]);
+            self.render_endpoints_table(f, chunks[2]);
+            return;
+        }
+
+        // Firing alerts take the subtitle's place until they resolve
+        if let Some(alert) = self.active_alerts.first() {
+            let banner = Paragraph::new(self.i18n.alert_banner(
+                &alert.rule,
+                alert.endpoint.as_deref(),
+                self.active_alerts.len() - 1,
+            ))
+            .block(Block::default().borders(Borders::ALL))
+            .style(
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .alignment(Alignment::Center);
+            f.render_widget(banner, chunks[1]);
+            self.render_endpoints_table(f, chunks[2]);
+            return;
+        }
+
         // Enhanced subtitle with timing, load, and system status
         let subtitle_text = self.build_subtitle_text();
         // Calculate available width: total width - borders (2) - padding (2) - safety margin (2)
@@ -516,61 +1816,69 @@ impl Dashboard {
         self.render_endpoints_table(f, chunks[2]);
     }
 
-    fn render_endpoints_table(&self, f: &mut Frame, area: Rect) {
+    fn render_endpoints_table(&mut self, f: &mut Frame, area: Rect) {
+        // Rows left inside the borders and below the header, measured every
+        // frame so a resize never leaves the cursor out of view
+        self.table_rows = usize::from(area.height.saturating_sub(3));
+        self.keep_cursor_visible();
+
         // Ensure we show all endpoints, even if they haven't been health-checked yet
         let mut rows: Vec<Row> = Vec::new();
-
-        for (index, endpoint_url) in self
-            .all_endpoints
-            .iter()
-            .enumerate()
-            .skip(self.scroll_offset)
-        {
-            let status = self.endpoint_health.get(endpoint_url);
+        let lines = table_lines(&self.all_endpoints, &self.endpoints);
+        let groups = self.group_summaries();
+
+        for line in lines.iter().skip(self.scroll_offset).take(self.table_rows) {
+            let index = match *line {
+                TableLine::Group(name) => {
+                    rows.push(self.group_row(name, groups.get(name)));
+                    continue;
+                }
+                TableLine::Endpoint(index) => index,
+            };
+            let endpoint_url = &self.all_endpoints[index];
+            let status = self.health_of(endpoint_url);
             let is_current = endpoint_url == &self.current_endpoint;
-            let endpoint_config = self.endpoint_configs.get(endpoint_url);
+            let endpoint_row = self.endpoint_row(endpoint_url);
 
             // Determine highlighting - cursor position takes precedence for visual feedback
             let is_cursor_position = index == self.cursor_index;
             let is_current_endpoint = endpoint_url == &self.current_endpoint;
             let is_manually_selected = match self.selection_mode {
-                SelectionMode::Manual => self.manual_selected_index == Some(index),
+                SelectionMode::Manual => self.manual_selected.as_ref() == Some(endpoint_url),
                 SelectionMode::Auto => false,
             };
 
-            let (status_char, latency_text) = if let Some(status) = status {
-                if status.available {
-                    (
-                        self.i18n.status_available(),
-                        format!("{}ms", status.latency),
-                    )
-                } else if status.error.is_none() {
-                    (
-                        self.i18n.status_checking(),
-                        self.i18n.status_checking().to_string(),
-                    )
-                } else {
+            let missing_token = self.missing_tokens.get(endpoint_url);
+
+            let state = endpoint_row
+                .as_ref()
+                .map_or(RowState::Checking, |row| row.state);
+            let (status_char, latency_text) = match state {
+                RowState::NoToken => ("-", self.i18n.no_token().to_string()),
+                RowState::Maintenance(until) => {
+                    let until = until.with_timezone(&chrono::Local).format("%H:%M");
                     (
-                        self.i18n.status_error(),
-                        status
-                            .error
-                            .as_ref()
-                            .map(|e| {
-                                if e.contains("timeout") {
-                                    self.i18n.error_timeout()
-                                } else {
-                                    self.i18n.error_generic()
-                                }
-                            })
-                            .unwrap_or(self.i18n.error_generic())
-                            .to_string(),
+                        self.i18n.status_maintenance(),
+                        self.i18n.maintenance_until(&until.to_string()),
                     )
                 }
-            } else {
-                (
-                    self.i18n.status_checking(),
-                    self.i18n.status_checking().to_string(),
-                )
+                RowState::Available => (
+                    self.i18n.status_available(),
+                    format_latency(endpoint_row.as_ref().and_then(|row| row.latency_ms)),
+                ),
+                RowState::Unchecked => ("-", self.i18n.not_checked().to_string()),
+                RowState::Checking => (self.checking_text(), self.checking_text().to_string()),
+                RowState::AuthFailed => {
+                    (self.i18n.status_error(), self.i18n.error_auth().to_string())
+                }
+                RowState::Timeout => (
+                    self.i18n.status_error(),
+                    self.i18n.error_timeout().to_string(),
+                ),
+                RowState::Error => (
+                    self.i18n.status_error(),
+                    self.i18n.error_generic().to_string(),
+                ),
             };
 
             // Build status column with status and markers only
@@ -583,8 +1891,8 @@ impl Dashboard {
                 };
 
             // Use custom name if available, otherwise fallback to generated
-            let endpoint_name = if let Some(config) = endpoint_config {
-                config.name.clone()
+            let mut endpoint_name = if let Some(row) = &endpoint_row {
+                row.name.clone()
             } else {
                 // Fallback for old format or missing config
                 endpoint_url
@@ -594,21 +1902,24 @@ impl Dashboard {
                     .unwrap_or("")
                     .to_uppercase()
             };
+            if let Some(active) = self.draining.get(endpoint_url) {
+                endpoint_name.push(' ');
+                endpoint_name.push_str(&self.i18n.draining(*active));
+            }
+            if self.standby.as_ref() == Some(endpoint_url) {
+                endpoint_name.push(' ');
+                endpoint_name.push_str(self.i18n.standby());
+            }
+            if let Some(token_env) = missing_token {
+                endpoint_name.push_str(&format!(" [{token_env}]"));
+            }
 
             // Generate proper Unicode sparkline for this endpoint
-            let raw_sparkline = if let Some(status) = status {
-                let sparkline_result = self.generate_sparkline(&status.latency_history);
-                if sparkline_result.is_empty() {
-                    "▁▁▁▁▁".to_string()
-                } else {
-                    sparkline_result
-                }
-            } else {
-                "▁▁▁▁▁".to_string() // Default when no data
+            let sparkline = match status {
+                Some(status) => self.generate_sparkline(&status.latency_history),
+                None => Line::from("▁▁▁▁▁"), // Default when no data
             };
 
-            let sparkline = raw_sparkline;
-
             // Build clean status column - only essential status info
             let mut status_content = status_char.to_string();
 
@@ -622,12 +1933,29 @@ impl Dashboard {
                 status_content.push_str(manual_marker);
             }
 
-            let row = Row::new(vec![
+            let mut cells = vec![
                 ratatui::widgets::Cell::from(status_content),
                 ratatui::widgets::Cell::from(endpoint_name),
                 ratatui::widgets::Cell::from(latency_text),
                 ratatui::widgets::Cell::from(sparkline),
-            ]);
+            ];
+            if self.show_retry_column {
+                // retries sent / requests that exhausted every retry, then
+                // request, body and fallback timeouts if there were any
+                let counters = endpoint_row
+                    .as_ref()
+                    .map(|row| row.counters)
+                    .unwrap_or_default();
+                let timeouts =
+                    counters.request_timeouts + counters.body_timeouts + counters.fallback_timeouts;
+                let mut text =
+                    format!("{}/{}", counters.retry_attempts, counters.retries_exhausted);
+                if timeouts > 0 {
+                    text.push_str(&format!(" ⏱{timeouts}"));
+                }
+                cells.push(ratatui::widgets::Cell::from(text));
+            }
+            let row = Row::new(cells);
 
             // Apply different highlight styles based on endpoint state
             let styled_row = if is_cursor_position {
@@ -638,6 +1966,13 @@ impl Dashboard {
                         .fg(Color::White)
                         .add_modifier(Modifier::BOLD),
                 )
+            } else if missing_token.is_some() {
+                // Group without a token - dimmed, it can't be used yet
+                row.style(
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::ITALIC),
+                )
             } else if is_current_endpoint {
                 // Currently active endpoint - green text
                 row.style(
@@ -661,34 +1996,251 @@ impl Dashboard {
         }
 
         // Optimized column width distribution - status column is now much cleaner
-        let constraints = [
-            Constraint::Ratio(1, 10), // Status column gets 10% (simplified)
-            Constraint::Ratio(3, 10), // Endpoint name gets 30%
-            Constraint::Ratio(2, 10), // Latency gets 20%
-            Constraint::Ratio(4, 10), // Sparkline gets 40%
+        let constraints: Vec<Constraint> = if self.show_retry_column {
+            vec![
+                Constraint::Ratio(1, 10), // Status
+                Constraint::Ratio(3, 10), // Endpoint name
+                Constraint::Ratio(2, 10), // Latency
+                Constraint::Ratio(3, 10), // Sparkline gives up 10% to retries
+                Constraint::Ratio(1, 10), // Retries
+            ]
+        } else {
+            vec![
+                Constraint::Ratio(1, 10), // Status column gets 10% (simplified)
+                Constraint::Ratio(3, 10), // Endpoint name gets 30%
+                Constraint::Ratio(2, 10), // Latency gets 20%
+                Constraint::Ratio(4, 10), // Sparkline gets 40%
+            ]
+        };
+
+        let mut header_cells = vec![
+            ratatui::widgets::Cell::from("Status"),
+            ratatui::widgets::Cell::from("Endpoint"),
+            ratatui::widgets::Cell::from("Latency"),
+            ratatui::widgets::Cell::from(
+                Line::from(match self.sparkline_scale {
+                    SparklineScale::Auto => "Trend".to_string(),
+                    SparklineScale::Fixed(max_ms) => format!("Trend (≤{max_ms}ms)"),
+                })
+                .alignment(Alignment::Center),
+            ),
         ];
+        if self.show_retry_column {
+            header_cells.push(ratatui::widgets::Cell::from("Retries"));
+        }
 
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.endpoints_table_title());
+        if let Some(model) = self.cursor_health_check_model() {
+            block = block.title(
+                Title::from(Span::styled(
+                    format!(" {} ", self.i18n.health_check_model(model)),
+                    Style::default().fg(Color::DarkGray),
+                ))
+                .position(Position::Bottom)
+                .alignment(Alignment::Left),
+            );
+        }
+        if let Some(decision) = &self.interval_decision {
+            let footer = self
+                .i18n
+                .interval_explained(self.health_check_interval.as_secs(), decision);
+            block = block.title(
+                Title::from(Span::styled(
+                    format!(" {footer} "),
+                    Style::default().fg(Color::DarkGray),
+                ))
+                .position(Position::Bottom)
+                .alignment(Alignment::Right),
+            );
+        }
         let table = Table::new(rows)
             .widths(&constraints)
-            .header(
-                Row::new(vec![
-                    ratatui::widgets::Cell::from("Status"),
-                    ratatui::widgets::Cell::from("Endpoint"),
-                    ratatui::widgets::Cell::from("Latency"),
-                    ratatui::widgets::Cell::from(
-                        ratatui::text::Line::from("Trend").alignment(Alignment::Center),
-                    ),
-                ])
-                .style(Style::default().add_modifier(Modifier::BOLD)),
-            )
+            .header(Row::new(header_cells).style(Style::default().add_modifier(Modifier::BOLD)))
             .column_spacing(1) // Minimal spacing between columns
-            .block(Block::default().borders(Borders::ALL).title("Endpoints"));
+            .block(block);
 
         f.render_widget(table, area);
     }
 
+    /// Model the cursor row's health checks ask for, so a model the relay
+    /// does not offer is easy to spot
+    fn cursor_health_check_model(&self) -> Option<&str> {
+        let url = self.all_endpoints.get(self.cursor_index)?;
+        let group = &self.endpoints.resolve(url)?.group;
+        self.health_check_models.get(group).map(String::as_str)
+    }
+
+    /// "Endpoints", with the range of endpoints shown once the list doesn't fit
+    fn endpoints_table_title(&self) -> String {
+        let lines = table_lines(&self.all_endpoints, &self.endpoints);
+        if lines.len() <= self.table_rows {
+            return "Endpoints".to_string();
+        }
+        let mut shown = lines
+            .iter()
+            .skip(self.scroll_offset)
+            .take(self.table_rows)
+            .filter_map(|line| match line {
+                TableLine::Endpoint(index) => Some(index + 1),
+                TableLine::Group(_) => None,
+            });
+        let first = shown.next().unwrap_or(0);
+        let last = shown.next_back().unwrap_or(first);
+        let total = self.all_endpoints.len();
+        format!("Endpoints ({first}–{last} of {total})")
+    }
+
+    fn render_recent_panel(&self, f: &mut Frame, area: Rect) {
+        let title = format!("🕘 Recent Requests ({})", self.recent_connections.len());
+
+        if self.recent_connections.is_empty() {
+            let no_requests = Paragraph::new("No completed requests yet")
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .style(Style::default().fg(Color::Gray));
+            f.render_widget(no_requests, area);
+            return;
+        }
+
+        let items: Vec<ListItem> = self
+            .recent_connections
+            .iter()
+            .map(|conn| {
+                let status = conn
+                    .status
+                    .map_or_else(|| conn.reason.to_string(), |status| status.to_string());
+                let color = match conn.status {
+                    Some(200..=299) => Color::Green,
+                    Some(400..=499) => Color::Yellow,
+                    Some(_) => Color::Red,
+                    None => Color::DarkGray,
+                };
+                let content = format!(
+                    "{} {} {} ({:.1}s)\n├─ {} {}\n└─ connect {:.1}s • headers {:.1}s • body {:.1}s",
+                    conn.start_time
+                        .with_timezone(&chrono::Local)
+                        .format("%H:%M:%S"),
+                    status,
+                    self.get_endpoint_name(&conn.endpoint),
+                    conn.duration_ms as f64 / 1000.0,
+                    conn.method.as_deref().unwrap_or("-"),
+                    conn.path.as_deref().unwrap_or("-"),
+                    conn.phases.connecting_ms as f64 / 1000.0,
+                    conn.phases.processing_ms as f64 / 1000.0,
+                    conn.phases.finishing_ms as f64 / 1000.0,
+                );
+                ListItem::new(Text::from(content)).style(Style::default().fg(color))
+            })
+            .collect();
+
+        let recent_list =
+            List::new(items).block(Block::default().borders(Borders::ALL).title(title));
+
+        f.render_widget(recent_list, area);
+    }
+
+    /// The cursor endpoint's checks in full: latency statistics over the
+    /// history, the last error and every recorded check, newest first
+    fn render_health_detail_panel(&self, f: &mut Frame, area: Rect) {
+        let Some(url) = self.all_endpoints.get(self.cursor_index) else {
+            let empty = Paragraph::new("No endpoints configured")
+                .block(Block::default().borders(Borders::ALL).title("🩺 Health"))
+                .style(Style::default().fg(Color::Gray));
+            f.render_widget(empty, area);
+            return;
+        };
+        let title = format!("🩺 Health: {}", self.get_endpoint_name(url));
+        let block = Block::default().borders(Borders::ALL).title(title);
+        let Some(status) = self.health_of(url) else {
+            let unknown = Paragraph::new("Not checked yet")
+                .block(block)
+                .style(Style::default().fg(Color::Gray));
+            f.render_widget(unknown, area);
+            return;
+        };
+
+        let measurements = status.latency_history.get_measurements();
+        let latencies: Vec<u64> = measurements.iter().filter_map(|m| m.latency).collect();
+        let group = self
+            .endpoints
+            .resolve(url)
+            .map_or("-", |endpoint| endpoint.group.as_str());
+        let mut lines = vec![
+            Line::from(url.clone()),
+            Line::from(format!("Group: {group} • {}", status.state.as_str())),
+            Line::from(format!(
+                "Last check: {} • {} (TTFB {})",
+                status
+                    .last_check
+                    .with_timezone(&chrono::Local)
+                    .format("%H:%M:%S"),
+                format_latency(status.latency),
+                format_latency(status.ttfb_ms)
+            )),
+            Line::from(format!(
+                "Passed: {}/{} • min {} • avg {} • max {}",
+                latencies.len(),
+                measurements.len(),
+                format_latency(latencies.iter().min().copied()),
+                format_latency(status.latency_history.average_latency()),
+                format_latency(latencies.iter().max().copied())
+            )),
+        ];
+        if status.cold_latency_ms.is_some() || status.warm_latency_ms.is_some() {
+            lines.push(Line::from(format!(
+                "Cold: {} • warm: {}",
+                format_latency(status.cold_latency_ms),
+                format_latency(status.warm_latency_ms)
+            )));
+        }
+        if let Some(error) = &status.error {
+            lines.push(Line::styled(
+                format!("Error: {error}"),
+                Style::default().fg(Color::Red),
+            ));
+        }
+        lines.push(Line::from(""));
+        for measurement in measurements.iter().rev() {
+            let time = measurement
+                .timestamp
+                .with_timezone(&chrono::Local)
+                .format("%H:%M:%S");
+            lines.push(match measurement.latency {
+                Some(latency) => Line::styled(
+                    match &measurement.phases {
+                        Some(phases) => format!("{time}  {latency}ms  {}", format_phases(phases)),
+                        None => format!("{time}  {latency}ms"),
+                    },
+                    Style::default().fg(Color::Green),
+                ),
+                None => Line::styled(
+                    format!(
+                        "{time}  failed: {}",
+                        measurement.error.as_deref().unwrap_or("-")
+                    ),
+                    Style::default().fg(Color::Red),
+                ),
+            });
+        }
+
+        let detail = Paragraph::new(lines).block(block).wrap(Wrap { trim: true });
+        f.render_widget(detail, area);
+    }
+
     fn render_connections_panel(&self, f: &mut Frame, area: Rect) {
-        let title = format!("🔗 Active Connections ({})", self.active_connections.len());
+        let mut title = format!("🔗 Active Connections ({})", self.active_connections.len());
+        if let Some(oldest) = self
+            .active_connections
+            .iter()
+            .min_by_key(|conn| conn.start_time)
+        {
+            title.push_str(&format!(
+                " • oldest: {} on {}",
+                format_age(oldest.duration() / 1000),
+                self.get_endpoint_name(&oldest.endpoint)
+            ));
+        }
 
         if self.active_connections.is_empty() {
             let no_connections = Paragraph::new("No active connections")
@@ -703,8 +2255,7 @@ impl Dashboard {
             .iter()
             .map(|conn| {
                 // Get custom name for this endpoint
-                let endpoint_name = if let Some(config) = self.endpoint_configs.get(&conn.endpoint)
-                {
+                let endpoint_name = if let Some(config) = self.endpoints.resolve(&conn.endpoint) {
                     config.name.clone()
                 } else {
                     // Fallback for old format
@@ -718,11 +2269,22 @@ impl Dashboard {
 
                 let duration = conn.duration();
 
-                // Show real connection status instead of fake progress
-                let status_indicator = match conn.status {
-                    ConnectionStatus::Connecting => "🔗 Connecting...",
-                    ConnectionStatus::Processing => "⚡ Processing...",
-                    ConnectionStatus::Finishing => "✅ Finishing...",
+                // Show real connection status, with body progress once it streams
+                let progress = self.connection_progress.get(&conn.id);
+                let status_indicator = match (progress, &conn.status) {
+                    (Some(progress), _) => format!(
+                        "↓ {}, {:.1}s since last chunk",
+                        format_bytes(progress.bytes_received),
+                        progress.last_chunk_at.elapsed().as_secs_f64()
+                    ),
+                    (None, ConnectionStatus::Connecting) => "🔗 Connecting...".to_string(),
+                    (None, ConnectionStatus::Processing) => "⚡ Processing...".to_string(),
+                    (None, ConnectionStatus::Finishing) => "✅ Finishing...".to_string(),
+                };
+                let color = match progress.map(|p| p.last_chunk_at.elapsed()) {
+                    Some(idle) if idle >= STREAM_STALL_CRITICAL => Color::Red,
+                    Some(idle) if idle >= STREAM_STALL_WARNING => Color::Yellow,
+                    _ => Color::White,
                 };
 
                 // Simple duration-based activity indicator
@@ -736,15 +2298,15 @@ impl Dashboard {
 
                 let content = format!(
                     "{} → {} ({:.1}s)\n├─ Status: {}\n└─ Active: {}{}",
-                    &conn.id[4..10], // Short ID
+                    conn.id.short_display(),
                     endpoint_name,
                     duration as f64 / 1000.0,
                     status_indicator,
-                    if duration < 60000 { "🟢" } else { "🟡" }, // Green for < 1min, yellow for longer
+                    self.age_indicator(duration),
                     activity_dots
                 );
 
-                ListItem::new(Text::from(content)).style(Style::default().fg(Color::White))
+                ListItem::new(Text::from(content)).style(Style::default().fg(color))
             })
             .collect();
 
@@ -754,12 +2316,48 @@ impl Dashboard {
         f.render_widget(connections_list, area);
     }
 
+    /// Manual mode holds traffic on an endpoint whose last check failed
+    fn pinned_to_unhealthy(&self) -> bool {
+        self.selection_mode == SelectionMode::Manual
+            && self
+                .health_of(&self.current_endpoint)
+                .is_some_and(|status| status.state == CheckState::Unhealthy)
+    }
+
+    /// Health of the endpoint at `url`, however the URL is spelled
+    fn health_of(&self, url: &str) -> Option<&EndpointStatus> {
+        self.endpoint_health.get(self.endpoints.id_of(url)?)
+    }
+
+    /// Whether the proxy is failing requests fast: the last full cycle found
+    /// every endpoint down and no health update has shown one recover since
+    fn failing_fast(&self) -> bool {
+        self.fail_fast_enabled
+            && self.all_endpoints_down
+            && !self
+                .endpoint_health
+                .values()
+                .any(|status| status.is_available())
+    }
+
+    /// Activity dot colored by connection age against the configured thresholds
+    fn age_indicator(&self, duration_ms: u64) -> &'static str {
+        let age = Duration::from_millis(duration_ms);
+        if age >= self.connection_alert {
+            "🔴"
+        } else if age >= self.connection_warn {
+            "🟡"
+        } else {
+            "🟢"
+        }
+    }
+
     fn render_status_bar(&self, f: &mut Frame, area: Rect) {
         // Build mode indicator with current selection
         let mode_indicator = match self.selection_mode {
             SelectionMode::Auto => "🤖 AUTO".to_string(),
             SelectionMode::Manual => {
-                if let Some(index) = self.manual_selected_index {
+                if let Some(index) = self.manual_selected_index() {
                     format!("🎯 MANUAL[{}]", index + 1)
                 } else {
                     "🎯 MANUAL".to_string()
@@ -767,10 +2365,60 @@ impl Dashboard {
             }
         };
 
-        let scroll_hint = if self.all_endpoints.len() > 1 {
-            self.i18n.btn_browse_endpoints()
+        let mode_indicator = if self.switching_frozen {
+            format!("{mode_indicator} ❄️ {}", self.i18n.switching_frozen())
         } else {
-            ""
+            mode_indicator
+        };
+
+        let mode_indicator = if self.shedding_load {
+            format!("{mode_indicator} 🚦 {}", self.i18n.shedding_load())
+        } else {
+            mode_indicator
+        };
+
+        let mode_indicator = match &self.last_cycle {
+            Some(cycle) => format!("{mode_indicator} 🔁#{}", cycle.sequence),
+            None => mode_indicator,
+        };
+
+        // Retry rate is an early warning for an endpoint that is degrading
+        let mode_indicator = format!(
+            "{mode_indicator} ↻{}",
+            self.i18n.retries_per_minute(self.retries_last_minute)
+        );
+        let mode_indicator = match self.overhead_p90_us {
+            Some(overhead) => format!(
+                "{mode_indicator} ⚙{}",
+                self.i18n.overhead_p90(&format_micros(overhead))
+            ),
+            None => mode_indicator,
+        };
+        let mode_indicator = if self.tag_filter.is_empty() {
+            mode_indicator
+        } else {
+            format!("{mode_indicator} 🏷{}", self.tag_filter.join(","))
+        };
+        let mode_indicator = if self.pricing_enabled {
+            let totals = self
+                .endpoint_counters
+                .values()
+                .fold(CounterSnapshot::default(), |total, s| total + *s);
+            format!(
+                "{mode_indicator} 💰{}",
+                self.i18n.estimated_cost(
+                    &format_cost(&self.currency, totals.request_cost_micros),
+                    &format_cost(&self.currency, totals.health_check_cost_micros),
+                )
+            )
+        } else {
+            mode_indicator
+        };
+
+        let scroll_hint = if self.all_endpoints.len() > 1 {
+            self.i18n.btn_browse_endpoints()
+        } else {
+            ""
         };
 
         let selection_hint = match self.selection_mode {
@@ -783,6 +2431,11 @@ impl Dashboard {
         } else {
             self.i18n.btn_pause()
         };
+        let freeze_button_text = if self.switching_frozen {
+            self.i18n.btn_unfreeze()
+        } else {
+            self.i18n.btn_freeze()
+        };
         let mode_switch_text = match self.selection_mode {
             SelectionMode::Auto => self.i18n.btn_to_manual(),
             SelectionMode::Manual => self.i18n.btn_to_auto(),
@@ -792,21 +2445,27 @@ impl Dashboard {
             match self.selection_mode {
                 SelectionMode::Auto => {
                     format!(
-                        "{} {mode_indicator} │ {} │ {} │ {} │ {}{scroll_hint}",
+                        "{} {mode_indicator} │ {} │ {} │ {} │ {} │ {} │ {} │ {}{scroll_hint}",
                         self.i18n.status_paused(),
                         self.i18n.btn_quit(),
                         self.i18n.btn_manual_check(),
+                        self.i18n.btn_history(),
+                        self.i18n.btn_tags(),
                         pause_button_text,
+                        freeze_button_text,
                         mode_switch_text
                     )
                 }
                 SelectionMode::Manual => {
                     format!(
-                        "{} {mode_indicator} │ {} │ {} │ {} │ {}{selection_hint}",
+                        "{} {mode_indicator} │ {} │ {} │ {} │ {} │ {} │ {} │ {}{selection_hint}",
                         self.i18n.status_paused(),
                         self.i18n.btn_quit(),
                         self.i18n.btn_manual_check(),
+                        self.i18n.btn_history(),
+                        self.i18n.btn_tags(),
                         pause_button_text,
+                        freeze_button_text,
                         mode_switch_text
                     )
                 }
@@ -815,94 +2474,157 @@ impl Dashboard {
             match self.selection_mode {
                 SelectionMode::Auto => {
                     format!(
-                        "{} {mode_indicator} │ {} │ {} │ {} │ {}{scroll_hint}",
-                        self.i18n.status_monitoring(),
+                        "{} {mode_indicator} │ {} │ {} │ {} │ {} │ {} │ {} │ {}{scroll_hint}",
+                        self.monitoring_text(),
                         self.i18n.btn_quit(),
                         self.i18n.btn_manual_check(),
+                        self.i18n.btn_history(),
+                        self.i18n.btn_tags(),
                         pause_button_text,
+                        freeze_button_text,
                         mode_switch_text
                     )
                 }
                 SelectionMode::Manual => {
                     format!(
-                        "{} {mode_indicator} │ {} │ {} │ {} │ {}{selection_hint}",
-                        self.i18n.status_monitoring(),
+                        "{} {mode_indicator} │ {} │ {} │ {} │ {} │ {} │ {} │ {}{selection_hint}",
+                        self.monitoring_text(),
                         self.i18n.btn_quit(),
                         self.i18n.btn_manual_check(),
+                        self.i18n.btn_history(),
+                        self.i18n.btn_tags(),
                         pause_button_text,
+                        freeze_button_text,
                         mode_switch_text
                     )
                 }
             }
         };
 
-        let status =
-            Paragraph::new(status_text).style(Style::default().bg(Color::Blue).fg(Color::White));
+        // A recent notice replaces the key hints until it expires
+        let status = match &self.notice {
+            Some(notice) if notice.raised_at.elapsed() < NOTICE_DURATION && notice.warning => {
+                Paragraph::new(format!("⚠️ {}", notice.text))
+                    .style(Style::default().bg(Color::Yellow).fg(Color::Black))
+            }
+            Some(notice) if notice.raised_at.elapsed() < NOTICE_DURATION => {
+                Paragraph::new(format!("✅ {}", notice.text))
+                    .style(Style::default().bg(Color::Green).fg(Color::Black))
+            }
+            // Manually pinned to an endpoint that is down: every request fails
+            _ if self.pinned_to_unhealthy() => Paragraph::new(format!(
+                "⚠️ {} │ {status_text}",
+                self.i18n
+                    .pinned_to_unhealthy(&self.get_endpoint_name(&self.current_endpoint))
+            ))
+            .style(
+                Style::default()
+                    .bg(Color::Red)
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            _ => {
+                Paragraph::new(status_text).style(Style::default().bg(Color::Blue).fg(Color::White))
+            }
+        };
 
         f.render_widget(status, area);
     }
 
-    /// Generate a compact Unicode sparkline showing latency trend
-    fn generate_sparkline(&self, history: &LatencyHistory) -> String {
-        let measurements = history.get_measurements();
-
-        // If we don't have enough data, show a simple waiting indicator
-        if measurements.is_empty() {
-            return "     ".to_string(); // Empty space, clean look
+    /// Status of an endpoint that has no check result yet
+    fn checking_text(&self) -> &'static str {
+        if self.starting {
+            self.i18n.status_starting()
+        } else {
+            self.i18n.status_checking()
         }
+    }
 
-        if measurements.len() < 2 {
-            return "  ▁  ".to_string(); // Simple low bar indicating loading
+    /// Status bar state, starting until the proxy accepts traffic
+    fn monitoring_text(&self) -> &'static str {
+        if self.starting {
+            self.i18n.status_starting()
+        } else {
+            self.i18n.status_monitoring()
         }
+    }
 
-        // Extract recent latency values (ignore failures for sparkline)
-        let recent_latencies: Vec<u64> = measurements
-            .iter()
-            .filter_map(|m| m.latency)
-            .rev() // Most recent first
-            .take(6) // Use last 6 measurements for sparkline
-            .collect();
+    /// Sparkline of the most recent checks, oldest first: failed checks as a
+    /// red cross, latencies as bars scaled per `ui.sparkline_scale`
+    fn generate_sparkline(&self, history: &LatencyHistory) -> Line<'static> {
+        let measurements = history.get_measurements();
 
-        if recent_latencies.len() < 2 {
-            return "  ▁  ".to_string(); // Still loading successful measurements
+        // If we don't have any data, leave the cell empty for a clean look
+        if measurements.is_empty() {
+            return Line::from("     ");
         }
 
-        // Find min and max for normalization
-        let min_latency = *recent_latencies.iter().min().unwrap_or(&0);
-        let max_latency = *recent_latencies.iter().max().unwrap_or(&100);
+        let recent: Vec<Option<u64>> = measurements
+            .iter()
+            .skip(measurements.len().saturating_sub(SPARKLINE_SAMPLES))
+            .map(|m| m.latency)
+            .collect();
 
-        // Avoid division by zero
-        let range = if max_latency > min_latency {
-            max_latency - min_latency
-        } else {
-            1
+        let (low, high) = match self.sparkline_scale {
+            SparklineScale::Fixed(max_ms) => (0, max_ms),
+            SparklineScale::Auto => {
+                let latencies = recent.iter().flatten();
+                (
+                    latencies.clone().min().copied().unwrap_or(0),
+                    latencies.max().copied().unwrap_or(0),
+                )
+            }
         };
+        // Avoid division by zero
+        let range = high.saturating_sub(low).max(1);
 
         // Unicode sparkline characters (8 levels)
         let sparkline_chars = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
 
-        let mut sparkline = String::new();
-
-        // Generate sparkline from oldest to newest (left to right)
-        for latency in recent_latencies.iter().rev() {
-            // Normalize to 0-7 range
-            let normalized = ((latency - min_latency) * 7 / range) as usize;
-            let char_index = normalized.min(7);
-            sparkline.push(sparkline_chars[char_index]);
-        }
+        let mut spans: Vec<Span<'static>> = recent
+            .iter()
+            .map(|latency| match latency {
+                Some(latency) => {
+                    let level = (latency.saturating_sub(low) * 7 / range).min(7) as usize;
+                    Span::raw(sparkline_chars[level].to_string())
+                }
+                None => Span::styled("✗", Style::default().fg(Color::Red)),
+            })
+            .collect();
 
         // Pad to consistent width
-        while sparkline.chars().count() < 5 {
-            sparkline.push('▁');
+        while spans.len() < 5 {
+            spans.push(Span::raw("▁"));
         }
 
-        sparkline
+        Line::from(spans)
+    }
+
+    /// Notice for a finished cycle that switched, or found more failed
+    /// endpoints than the one before
+    fn cycle_notice(&self, summary: &HealthCycleSummary) -> Option<Notice> {
+        if let Some(to) = &summary.switched_to {
+            return Some(Notice::info(self.i18n.cycle_switched(
+                summary.sequence,
+                &self.get_endpoint_name(to),
+                summary.healthy,
+                summary.checked,
+            )));
+        }
+        let previous_failed = self.last_cycle.as_ref().map_or(0, |last| last.failed);
+        (summary.failed > previous_failed).then(|| {
+            Notice::warning(self.i18n.cycle_failures(
+                summary.sequence,
+                summary.failed,
+                summary.checked,
+            ))
+        })
     }
 
     /// Extract endpoint display name from URL and config
     fn get_endpoint_name(&self, endpoint_url: &str) -> String {
-        self.endpoint_configs
-            .get(endpoint_url)
+        self.endpoints
+            .resolve(endpoint_url)
             .map(|c| c.name.clone())
             .unwrap_or_else(|| {
                 // Fallback: extract from URL
@@ -917,6 +2639,10 @@ impl Dashboard {
 
     /// Build the subtitle text with status, load, mode, and optional switch info
     fn build_subtitle_text(&self) -> String {
+        if let Some((pause, _)) = self.pending_pause {
+            return self.i18n.pause_pending_subtitle(pause).to_string();
+        }
+
         // If paused, show paused indicator
         if self.paused {
             return self.i18n.paused_subtitle().to_string();
@@ -951,7 +2677,7 @@ impl Dashboard {
         let mode_text = match self.selection_mode {
             SelectionMode::Auto => self.i18n.mode_auto().to_string(),
             SelectionMode::Manual => {
-                if let Some(index) = self.manual_selected_index {
+                if let Some(index) = self.manual_selected_index() {
                     self.i18n.mode_manual_indexed(index)
                 } else {
                     self.i18n.mode_manual().to_string()
@@ -964,17 +2690,19 @@ impl Dashboard {
             let from_name = self.get_endpoint_name(&switch.from);
             let to_name = self.get_endpoint_name(&switch.to);
 
-            // Don't show improvement if from_latency is a placeholder (999999)
-            let improvement_text = if switch.from_latency >= 999999 {
-                self.i18n.switch_new_connection().to_string() // Initial connection, no meaningful improvement
-            } else if switch.improvement > 0 {
-                format!("↓{}ms", switch.improvement)
-            } else {
-                "±0ms".to_string() // No improvement or got worse
+            let improvement_text = match switch.improvement {
+                // Initial connection or unmeasured endpoint, no meaningful improvement
+                None => self.i18n.switch_new_connection().to_string(),
+                Some(improvement) if improvement > 0 => format!("↓{improvement}ms"),
+                Some(_) => "±0ms".to_string(), // No improvement or got worse
             };
 
+            let reason_text = self.i18n.switch_reason(switch.reason);
+            let age_secs = (chrono::Utc::now() - switch.timestamp).num_seconds().max(0) as u64;
+            let age_text = self.i18n.time_ago(age_secs);
+
             format!(
-                "{status_text} • {load_icon}{load_text} • {mode_text} • 🔄{from_name}→{to_name} ({improvement_text})"
+                "{status_text} • {load_icon}{load_text} • {mode_text} • 🔄{from_name}→{to_name} ({reason_text}, {improvement_text}, {age_text})"
             )
         } else {
             format!("{status_text} • {load_icon}{load_text} • {mode_text}")
@@ -992,3 +2720,927 @@ impl Dashboard {
         format!("{truncated}...")
     }
 }
+
+/// Compute a rectangle centered in `area` using percentage width/height
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+/// Local URLs the proxy serves on, with the group a listener is limited to
+fn proxy_urls(config: &Config) -> Vec<String> {
+    std::iter::once(format!("http://localhost:{}", config.server.port))
+        .chain(
+            config
+                .server
+                .listeners
+                .iter()
+                .map(|listener| match &listener.group {
+                    Some(group) => format!("{} ({group})", listener.url()),
+                    None => listener.url(),
+                }),
+        )
+        .collect()
+}
+
+/// Compact byte count for the connections panel
+fn format_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{bytes}B")
+    } else if bytes < 1024 * 1024 {
+        format!("{}KB", bytes / 1024)
+    } else {
+        format!("{:.1}MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+/// Connection age in the largest fitting unit, e.g. "45s", "14m", "2h05m"
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{seconds}s"),
+        60..=3599 => format!("{}m", seconds / 60),
+        _ => format!("{}h{:02}m", seconds / 3600, (seconds % 3600) / 60),
+    }
+}
+
+/// Micro-dollar amount with enough precision for sub-cent requests
+fn format_cost(currency: &str, micros: u64) -> String {
+    format!("{currency}{:.4}", micros as f64 / 1_000_000.0)
+}
+
+/// Microseconds as milliseconds, with a decimal below 10ms, e.g. "0.4ms", "27ms"
+fn format_micros(micros: u64) -> String {
+    if micros < 10_000 {
+        format!("{:.1}ms", micros as f64 / 1000.0)
+    } else {
+        format!("{}ms", micros / 1000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection_tracker::ConnectionTracker;
+    use crate::health_orchestrator::HealthCheckOrchestrator;
+    use crate::proxy::ProxyState;
+    use crate::test_support::{config, TWO_GROUPS};
+    use ratatui::backend::TestBackend;
+    use std::sync::{Arc, Mutex};
+
+    /// Key presses replayed in order, standing in for the terminal
+    struct ScriptedInput {
+        keys: VecDeque<KeyCode>,
+        /// Wait before each key, so the event loop runs in between
+        delay: Duration,
+        /// When the next key is due, kept across the calls the event loop
+        /// drops for another branch
+        due: Option<tokio::time::Instant>,
+    }
+
+    impl ScriptedInput {
+        fn new(keys: &[KeyCode]) -> Self {
+            Self {
+                keys: keys.iter().copied().collect(),
+                delay: Duration::ZERO,
+                due: None,
+            }
+        }
+
+        fn after(mut self, delay: Duration) -> Self {
+            self.delay = delay;
+            self
+        }
+    }
+
+    impl InputSource for ScriptedInput {
+        async fn next_key(&mut self) -> Option<KeyCode> {
+            let delay = self.delay;
+            let due = *self
+                .due
+                .get_or_insert_with(|| tokio::time::Instant::now() + delay);
+            tokio::time::sleep_until(due).await;
+            self.due = None;
+            self.keys.pop_front()
+        }
+    }
+
+    fn dashboard() -> Dashboard {
+        Dashboard::new(&config(TWO_GROUPS), Duration::from_secs(60))
+    }
+
+    /// Hand every key of `keys` to the dashboard, returning the actions
+    async fn press(dashboard: &mut Dashboard, keys: &[KeyCode]) -> Vec<Action> {
+        let mut input = ScriptedInput::new(keys);
+        let mut actions = Vec::new();
+        while let Some(key) = input.next_key().await {
+            actions.push(dashboard.handle_key(key));
+        }
+        actions
+    }
+
+    #[tokio::test]
+    async fn cursor_wraps_around_the_endpoint_list() {
+        let mut dashboard = dashboard();
+
+        let actions = press(&mut dashboard, &[KeyCode::Up]).await;
+        assert!(matches!(actions[..], [Action::None]));
+        assert_eq!(dashboard.cursor_index, 1);
+
+        press(&mut dashboard, &[KeyCode::Down]).await;
+        assert_eq!(dashboard.cursor_index, 0);
+
+        press(
+            &mut dashboard,
+            &[KeyCode::Down, KeyCode::Down, KeyCode::Down],
+        )
+        .await;
+        assert_eq!(dashboard.cursor_index, 1);
+    }
+
+    #[tokio::test]
+    async fn enter_selects_the_cursor_row_only_in_manual_mode() {
+        let mut dashboard = dashboard();
+
+        let actions = press(&mut dashboard, &[KeyCode::Down, KeyCode::Enter]).await;
+        assert!(matches!(actions[1], Action::None));
+
+        let actions = press(&mut dashboard, &[KeyCode::Char('m')]).await;
+        assert!(matches!(actions[..], [Action::ToggleSelectionMode]));
+        dashboard.selection_mode = SelectionMode::Manual;
+
+        let actions = press(&mut dashboard, &[KeyCode::Enter]).await;
+        assert!(matches!(
+            actions[..],
+            [Action::SelectEndpoint {
+                index: 1,
+                forced: false
+            }]
+        ));
+    }
+
+    #[tokio::test]
+    async fn pause_key_toggles_between_pause_and_resume() {
+        let mut dashboard = dashboard();
+
+        let actions = press(&mut dashboard, &[KeyCode::Char('p')]).await;
+        assert!(matches!(
+            actions[..],
+            [Action::SendCommand(OrchestratorCommand::Pause)]
+        ));
+        // Not paused until the health checker confirms it
+        assert!(!dashboard.paused);
+
+        // A second press before the confirmation takes the first one back
+        let actions = press(&mut dashboard, &[KeyCode::Char('p')]).await;
+        assert!(matches!(
+            actions[..],
+            [Action::SendCommand(OrchestratorCommand::Resume)]
+        ));
+    }
+
+    #[tokio::test]
+    async fn pause_key_resumes_once_the_pause_is_confirmed() {
+        let mut dashboard = dashboard();
+        press(&mut dashboard, &[KeyCode::Char('p')]).await;
+        dashboard.confirm_pause(true);
+        assert!(dashboard.paused);
+        assert!(dashboard.pending_pause.is_none());
+        let actions = press(&mut dashboard, &[KeyCode::Char('p')]).await;
+        assert!(matches!(
+            actions[..],
+            [Action::SendCommand(OrchestratorCommand::Resume)]
+        ));
+        dashboard.confirm_pause(false);
+        assert!(!dashboard.paused);
+    }
+
+    #[tokio::test]
+    async fn quit_and_unbound_keys() {
+        let mut dashboard = dashboard();
+        let actions = press(&mut dashboard, &[KeyCode::Char('z'), KeyCode::Char('q')]).await;
+        assert!(matches!(actions[..], [Action::None, Action::Quit]));
+    }
+
+    /// Draw one frame at `width`x`height`, returning the screen line by line
+    fn draw(dashboard: &mut Dashboard, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| dashboard.render(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| buffer.get(x, y).symbol.as_str())
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tiny_terminal_shows_only_the_size_notice() {
+        let mut dashboard = dashboard();
+        let screen = draw(&mut dashboard, 10, 5).concat();
+        assert!(screen.contains("Terminal"), "{screen}");
+        assert!(!screen.contains("alpha"), "{screen}");
+    }
+
+    #[test]
+    fn full_layout_starts_at_the_minimum_size() {
+        for (width, height) in [(39, 12), (40, 11)] {
+            let screen = draw(&mut dashboard(), width, height).concat();
+            assert!(screen.contains("Terminal"), "{width}x{height}: {screen}");
+        }
+        for (width, height) in [(40, 12), (41, 13), (120, 40)] {
+            let screen = draw(&mut dashboard(), width, height).concat();
+            assert!(!screen.contains("Terminal too small"), "{width}x{height}");
+        }
+        let screen = draw(&mut dashboard(), 120, 40).concat();
+        assert!(screen.contains("alpha"), "{screen}");
+    }
+
+    #[test]
+    fn every_small_size_renders_without_panicking() {
+        let mut dashboard = dashboard();
+        for width in 1..=45 {
+            for height in 1..=15 {
+                draw(&mut dashboard, width, height);
+            }
+        }
+    }
+
+    /// A dashboard over `count` endpoints named `ep00`, `ep01`, ... in the
+    /// main group, followed by the backup group's `beta`
+    fn long_dashboard(count: usize) -> Dashboard {
+        let endpoints: Vec<String> = (0..count)
+            .map(|i| {
+                format!(
+                    r#"{{ url = "http://127.0.0.1:{}", name = "ep{i:02}" }}"#,
+                    20000 + i
+                )
+            })
+            .collect();
+        let toml = TWO_GROUPS.replacen(
+            r#"endpoints = [{ url = "http://127.0.0.1:19001", name = "alpha" }]"#,
+            &format!("endpoints = [{}]", endpoints.join(", ")),
+            1,
+        );
+        Dashboard::new(&config(&toml), Duration::from_secs(60))
+    }
+
+    /// Check the cursor row is drawn after a frame at `width`x`height`
+    fn assert_cursor_drawn(dashboard: &mut Dashboard, width: u16, height: u16) {
+        let screen = draw(dashboard, width, height).concat();
+        let cursor = dashboard.cursor_index;
+        let line =
+            dashboard.cursor_line(&table_lines(&dashboard.all_endpoints, &dashboard.endpoints));
+        assert!(
+            dashboard.scroll_offset <= line
+                && line < dashboard.scroll_offset + dashboard.table_rows,
+            "cursor {cursor} on row {line} outside rows {}+{} at {width}x{height}",
+            dashboard.scroll_offset,
+            dashboard.table_rows
+        );
+        let name = dashboard.get_endpoint_name(&dashboard.all_endpoints[cursor]);
+        assert!(
+            screen.contains(&name),
+            "{name} not drawn at {width}x{height}"
+        );
+    }
+
+    #[tokio::test]
+    async fn cursor_stays_in_view_across_resizes() {
+        let mut dashboard = long_dashboard(41);
+        assert_cursor_drawn(&mut dashboard, 120, 40);
+
+        press(&mut dashboard, &[KeyCode::End]).await;
+        assert_eq!(dashboard.cursor_index, dashboard.all_endpoints.len() - 1);
+        for (width, height) in [(120, 40), (120, 16), (80, 12), (120, 60), (120, 14)] {
+            assert_cursor_drawn(&mut dashboard, width, height);
+        }
+
+        // Growing the terminal scrolls back so no rows are left empty
+        draw(&mut dashboard, 120, 80);
+        // 42 endpoints and the summary rows of both groups
+        assert_eq!(
+            dashboard.scroll_offset,
+            44usize.saturating_sub(dashboard.table_rows)
+        );
+
+        press(&mut dashboard, &[KeyCode::Home]).await;
+        assert_cursor_drawn(&mut dashboard, 120, 14);
+        assert_eq!(dashboard.scroll_offset, 0);
+    }
+
+    #[tokio::test]
+    async fn long_jumps_keep_the_cursor_in_view() {
+        let mut dashboard = long_dashboard(41);
+        draw(&mut dashboard, 120, 20);
+        let rows = dashboard.table_rows;
+        let last = dashboard.all_endpoints.len() - 1;
+        assert!(rows > 1 && rows < last, "{rows} rows");
+
+        let mut expected = 0;
+        for _ in 0..10 {
+            press(&mut dashboard, &[KeyCode::PageDown]).await;
+            expected = (expected + rows).min(last);
+            assert_eq!(dashboard.cursor_index, expected);
+            assert_cursor_drawn(&mut dashboard, 120, 20);
+        }
+        for _ in 0..10 {
+            press(&mut dashboard, &[KeyCode::PageUp]).await;
+            assert_cursor_drawn(&mut dashboard, 120, 20);
+        }
+        assert_eq!(dashboard.cursor_index, 0);
+
+        // Wrapping around from the top lands on the last row
+        press(&mut dashboard, &[KeyCode::Up]).await;
+        assert_eq!(dashboard.cursor_index, last);
+        assert_cursor_drawn(&mut dashboard, 120, 20);
+        let title = dashboard.endpoints_table_title();
+        assert!(title.ends_with("–42 of 42)"), "{title}");
+    }
+
+    /// A dashboard in manual mode with the cursor on `beta`, which is down
+    /// when `beta_down`
+    fn selecting_beta(beta_down: bool) -> Dashboard {
+        let mut dashboard = dashboard();
+        dashboard.selection_mode = SelectionMode::Manual;
+        dashboard.cursor_index = 1;
+        if beta_down {
+            let beta = dashboard
+                .endpoint_health
+                .get_mut(&EndpointId::new("beta"))
+                .unwrap();
+            beta.state = CheckState::Unhealthy;
+        }
+        dashboard
+    }
+
+    #[tokio::test]
+    async fn healthy_endpoint_is_selected_on_the_first_enter() {
+        let mut dashboard = selecting_beta(false);
+        let actions = press(&mut dashboard, &[KeyCode::Enter]).await;
+        assert!(matches!(
+            actions[..],
+            [Action::SelectEndpoint {
+                index: 1,
+                forced: false
+            }]
+        ));
+        assert!(dashboard.notice.is_none());
+    }
+
+    #[tokio::test]
+    async fn down_endpoint_is_selected_on_the_second_enter() {
+        let mut dashboard = selecting_beta(true);
+
+        let actions = press(&mut dashboard, &[KeyCode::Enter]).await;
+        assert!(matches!(actions[..], [Action::None]));
+        assert!(dashboard.notice.is_some());
+        assert!(dashboard.pending_selection.is_some());
+
+        let actions = press(&mut dashboard, &[KeyCode::Enter]).await;
+        assert!(matches!(
+            actions[..],
+            [Action::SelectEndpoint {
+                index: 1,
+                forced: true
+            }]
+        ));
+        assert!(dashboard.notice.is_none());
+        assert!(dashboard.pending_selection.is_none());
+    }
+
+    #[tokio::test]
+    async fn any_other_key_cancels_a_forced_selection() {
+        for key in [KeyCode::Char('z'), KeyCode::Down, KeyCode::Char('v')] {
+            let mut dashboard = selecting_beta(true);
+            press(&mut dashboard, &[KeyCode::Enter]).await;
+
+            press(&mut dashboard, &[key]).await;
+            assert!(dashboard.pending_selection.is_none(), "{key:?}");
+            assert!(dashboard.notice.is_none(), "{key:?}");
+
+            // Asked again from the start rather than selected
+            dashboard.cursor_index = 1;
+            let actions = press(&mut dashboard, &[KeyCode::Enter]).await;
+            assert!(matches!(actions[..], [Action::None]), "{key:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn forced_selection_is_recorded_as_forced() {
+        let mut dashboard = selecting_beta(true);
+        let state: SharedState = Arc::new(Mutex::new(ProxyState::new(config(TWO_GROUPS))));
+
+        let actions = press(&mut dashboard, &[KeyCode::Enter, KeyCode::Enter]).await;
+        let Action::SelectEndpoint { index, forced } = actions[1] else {
+            panic!("{actions:?}");
+        };
+        dashboard.handle_manual_endpoint_selection_by_index(index, forced, &state);
+
+        let state = state.lock().unwrap();
+        let record = state.switch_history.back().unwrap();
+        assert_eq!(record.to, "http://127.0.0.1:19002");
+        assert_eq!(record.reason, SwitchReason::ManualSwitch);
+        assert!(record.forced);
+        assert_eq!(dashboard.current_endpoint, "http://127.0.0.1:19002");
+    }
+
+    fn shared_state() -> SharedState {
+        Arc::new(Mutex::new(ProxyState::new(config(TWO_GROUPS))))
+    }
+
+    /// Run the event loop on a test terminal until `input` quits or runs out
+    async fn run_event_loop(
+        dashboard: &mut Dashboard,
+        input: &mut ScriptedInput,
+        events: EventReceiver,
+        proxy_state: SharedState,
+    ) {
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        let tracker = Arc::new(Mutex::new(ConnectionTracker::new(10)));
+        let (orchestrator_events, _) = mpsc::unbounded_channel();
+        let (_orchestrator, handle) = HealthCheckOrchestrator::new(
+            config(TWO_GROUPS),
+            proxy_state.clone(),
+            orchestrator_events,
+            true,
+            None,
+        );
+        dashboard
+            .event_loop(&mut terminal, input, events, tracker, proxy_state, handle)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "synthetic load, run with --ignored"]
+    async fn dashboard_catches_up_with_a_burst_of_events() {
+        const EVENTS: usize = 10_000;
+        let mut dashboard = dashboard();
+        let (sender, events) = mpsc::unbounded_channel();
+        let producer = tokio::spawn(async move {
+            for i in 0..EVENTS {
+                let _ = sender.send(ProxyEvent::RequestReceived {
+                    endpoint: "http://127.0.0.1:19001".to_string(),
+                    timestamp: Instant::now(),
+                });
+                if i % 100 == 0 {
+                    tokio::task::yield_now().await;
+                }
+            }
+            let _ = sender.send(ProxyEvent::EndpointSwitch {
+                from: "http://127.0.0.1:19001".to_string(),
+                to: "http://127.0.0.1:19002".to_string(),
+                from_latency: None,
+                to_latency: None,
+                reason: SwitchReason::Failover,
+            });
+        });
+
+        // Quit after two frames: everything sent by then must be on screen
+        let mut input = ScriptedInput::new(&[KeyCode::Char('q')]).after(TICK_INTERVAL * 2);
+        let started = Instant::now();
+        run_event_loop(&mut dashboard, &mut input, events, shared_state()).await;
+        producer.await.unwrap();
+
+        assert!(
+            started.elapsed() < TICK_INTERVAL * 3,
+            "{:?}",
+            started.elapsed()
+        );
+        assert_eq!(dashboard.recent_requests.len(), EVENTS);
+        assert_eq!(dashboard.current_endpoint, "http://127.0.0.1:19002");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    #[ignore = "stress test, run with --ignored"]
+    async fn events_sent_before_the_dashboard_listens_are_kept() {
+        const PRODUCERS: usize = 32;
+        const REQUESTS: usize = 500;
+        const BETA: &str = "http://127.0.0.1:19002";
+
+        // State no event repeats: a manual pick of a healthy endpoint
+        let proxy_state = shared_state();
+        {
+            let mut state = proxy_state.lock().unwrap();
+            state.selection_mode = SelectionMode::Manual;
+            state.switch_endpoint(BETA.to_string(), SwitchReason::ManualSwitch, true);
+            let beta = state
+                .endpoint_status
+                .get_mut(&EndpointId::new("beta"))
+                .unwrap();
+            beta.state = CheckState::Healthy;
+            beta.latency = Some(120);
+        }
+
+        // Every producer finishes before the dashboard exists
+        let (sender, events) = mpsc::unbounded_channel();
+        let _ = sender.send(ProxyEvent::ConfigLoaded { endpoint_count: 2 });
+        let producers: Vec<_> = (0..PRODUCERS)
+            .map(|_| {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let _ = sender.send(ProxyEvent::ServerStarted { port: 18080 });
+                    for _ in 0..REQUESTS {
+                        let _ = sender.send(ProxyEvent::RequestReceived {
+                            endpoint: BETA.to_string(),
+                            timestamp: Instant::now(),
+                        });
+                    }
+                })
+            })
+            .collect();
+        for producer in producers {
+            producer.await.unwrap();
+        }
+        drop(sender);
+
+        let mut dashboard = dashboard();
+        assert!(dashboard.starting);
+        let mut input = ScriptedInput::new(&[KeyCode::Char('q')]).after(TICK_INTERVAL * 2);
+        run_event_loop(&mut dashboard, &mut input, events, proxy_state).await;
+
+        assert!(!dashboard.starting);
+        assert_eq!(dashboard.recent_requests.len(), PRODUCERS * REQUESTS);
+        assert_eq!(dashboard.current_endpoint, BETA);
+        assert_eq!(dashboard.selection_mode, SelectionMode::Manual);
+        assert_eq!(dashboard.manual_selected.as_deref(), Some(BETA));
+        let beta = dashboard.health_of(BETA).unwrap();
+        assert_eq!(beta.state, CheckState::Healthy);
+        assert_eq!(beta.latency, Some(120));
+    }
+
+    /// An upstream on a loopback port answering every request with `status`
+    fn upstream(status: u16) -> String {
+        use hyper::service::{make_service_fn, service_fn};
+        use hyper::{Body, Response, Server};
+        use std::convert::Infallible;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let make_service = make_service_fn(move |_| async move {
+            Ok::<_, Infallible>(service_fn(move |_| async move {
+                Ok::<_, Infallible>(
+                    Response::builder()
+                        .status(status)
+                        .body(Body::from("{}"))
+                        .unwrap(),
+                )
+            }))
+        });
+        tokio::spawn(Server::from_tcp(listener).unwrap().serve(make_service));
+        url
+    }
+
+    #[tokio::test]
+    async fn dashboard_follows_a_fallback_to_the_serving_endpoint() {
+        use crate::proxy::RunMode;
+        use crate::server::ProxyServer;
+
+        let (overloaded, backup) = (upstream(529), upstream(200));
+        let toml = TWO_GROUPS
+            .replace("http://127.0.0.1:19001", &overloaded)
+            .replace("http://127.0.0.1:19002", &backup);
+        let mut config = config(&format!(
+            "{toml}\n[retry]\nmax_retries = 2\nbase_delay_ms = 1\nfallback_on_status = [529]\n"
+        ));
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .unwrap()
+            .port();
+        config.server.port = port;
+        // Only the request moves the proxy, no health check does
+        config.claude_binary_missing = true;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let server = ProxyServer::builder(config.clone())
+            .event_hook({
+                let events = Arc::clone(&events);
+                move |event| events.lock().unwrap().push(event.clone())
+            })
+            .mode(RunMode::Dashboard)
+            .record_usage(false)
+            .build();
+        let (_stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(server.run_until(stopped));
+
+        let message = || {
+            hyper::Request::post(format!("http://127.0.0.1:{port}/v1/messages"))
+                .body(hyper::Body::from("{}"))
+                .unwrap()
+        };
+        let mut response = None;
+        for _ in 0..100 {
+            match hyper::Client::new().request(message()).await {
+                Ok(answer) => {
+                    response = Some(answer);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+        assert_eq!(response.expect("proxy answers").status(), 200);
+
+        // The hook sees the events after the response is sent
+        let switched = |events: &[ProxyEvent]| {
+            events
+                .iter()
+                .any(|event| matches!(event, ProxyEvent::EndpointSwitch { .. }))
+        };
+        for _ in 0..100 {
+            if switched(&events.lock().unwrap()) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let mut dashboard = Dashboard::new(&config, config.health_check_interval());
+        assert_eq!(dashboard.current_endpoint, overloaded);
+        let events = std::mem::take(&mut *events.lock().unwrap());
+        assert!(switched(&events), "{events:?}");
+        for event in events {
+            dashboard.handle_proxy_event(event);
+        }
+
+        assert_eq!(dashboard.current_endpoint, backup);
+        let switch = dashboard.last_switch.as_ref().unwrap();
+        assert_eq!(
+            (switch.from.as_str(), switch.to.as_str()),
+            (overloaded.as_str(), backup.as_str())
+        );
+        assert_eq!(switch.reason, SwitchReason::RequestFallback);
+        assert_eq!(dashboard.recent_requests.len(), 1);
+    }
+
+    fn urls(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| format!("https://{name}")).collect()
+    }
+
+    fn reconciled(cursor_index: usize, manual_selected: Option<&str>) -> ReconciledSelection {
+        ReconciledSelection {
+            cursor_index,
+            manual_selected: manual_selected.map(|name| format!("https://{name}")),
+        }
+    }
+
+    #[test]
+    fn reconcile_keeps_the_selection_when_the_list_shrinks() {
+        let old = urls(&["a", "b", "c", "d"]);
+        let new = urls(&["a", "c", "d"]);
+        assert_eq!(
+            reconcile_endpoint_list(&old, &new, 3, Some("https://c")),
+            reconciled(2, Some("c"))
+        );
+    }
+
+    #[test]
+    fn reconcile_clamps_the_cursor_when_its_endpoint_is_removed() {
+        let old = urls(&["a", "b", "c", "d"]);
+        // A middle row removed leaves the cursor on the row that took its place
+        assert_eq!(
+            reconcile_endpoint_list(&old, &urls(&["a", "c", "d"]), 1, None),
+            reconciled(1, None)
+        );
+        // The last rows removed move it to the new last row
+        assert_eq!(
+            reconcile_endpoint_list(&old, &urls(&["a", "b"]), 3, None),
+            reconciled(1, None)
+        );
+        // Nothing left
+        assert_eq!(
+            reconcile_endpoint_list(&old, &[], 2, None),
+            reconciled(0, None)
+        );
+    }
+
+    #[test]
+    fn reconcile_drops_a_removed_manual_selection() {
+        let old = urls(&["a", "b", "c"]);
+        let new = urls(&["a", "c"]);
+        assert_eq!(
+            reconcile_endpoint_list(&old, &new, 0, Some("https://b")),
+            reconciled(0, None)
+        );
+    }
+
+    #[test]
+    fn reconcile_follows_the_endpoints_when_the_list_grows() {
+        let old = urls(&["b", "d"]);
+        let new = urls(&["a", "b", "c", "d", "e"]);
+        assert_eq!(
+            reconcile_endpoint_list(&old, &new, 1, Some("https://b")),
+            reconciled(3, Some("b"))
+        );
+        // From an empty list the cursor starts at the top
+        assert_eq!(
+            reconcile_endpoint_list(&[], &new, 0, None),
+            reconciled(0, None)
+        );
+    }
+
+    #[test]
+    fn reconcile_follows_the_endpoints_when_the_list_is_reordered() {
+        let old = urls(&["a", "b", "c"]);
+        let new = urls(&["c", "a", "b"]);
+        for (cursor, expected) in [(0, 1), (1, 2), (2, 0)] {
+            assert_eq!(
+                reconcile_endpoint_list(&old, &new, cursor, Some("https://c")),
+                reconciled(expected, Some("c"))
+            );
+        }
+    }
+
+    #[test]
+    fn reconcile_clamps_a_cursor_already_out_of_range() {
+        let old = urls(&["a", "b"]);
+        assert_eq!(
+            reconcile_endpoint_list(&old, &old, 5, None),
+            reconciled(1, None)
+        );
+    }
+
+    /// Sparkline of checks with these latencies, `None` for a failed check,
+    /// as its text and whether each cell is drawn as a failure
+    fn sparkline(scale: SparklineScale, latencies: &[Option<u64>]) -> (String, Vec<bool>) {
+        let mut dashboard = dashboard();
+        dashboard.sparkline_scale = scale;
+        let mut history = LatencyHistory::default();
+        for &latency in latencies {
+            let error = latency.is_none().then(|| "failed".to_string());
+            history.add_measurement(latency, error);
+        }
+        let line = dashboard.generate_sparkline(&history);
+        let text = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        let failed = line
+            .spans
+            .iter()
+            .map(|span| span.style.fg == Some(Color::Red))
+            .collect();
+        (text, failed)
+    }
+
+    #[test]
+    fn sparkline_of_no_checks_is_blank() {
+        assert_eq!(sparkline(SparklineScale::Auto, &[]).0, "     ");
+    }
+
+    #[test]
+    fn sparkline_of_a_single_check_is_padded() {
+        assert_eq!(
+            sparkline(SparklineScale::Auto, &[Some(250)]),
+            ("▁▁▁▁▁".to_string(), vec![false; 5])
+        );
+        assert_eq!(
+            sparkline(SparklineScale::Auto, &[None]),
+            ("✗▁▁▁▁".to_string(), vec![true, false, false, false, false])
+        );
+    }
+
+    #[test]
+    fn sparkline_of_only_failures_is_all_crosses() {
+        assert_eq!(
+            sparkline(SparklineScale::Auto, &[None; 6]),
+            ("✗✗✗✗✗✗".to_string(), vec![true; 6])
+        );
+        assert_eq!(
+            sparkline(SparklineScale::Fixed(1000), &[None; 6]).0,
+            "✗✗✗✗✗✗"
+        );
+    }
+
+    #[test]
+    fn sparkline_keeps_failures_between_latencies() {
+        let (text, failed) = sparkline(
+            SparklineScale::Auto,
+            &[Some(100), None, Some(900), None, Some(500)],
+        );
+        assert_eq!(text, "▁✗█✗▄");
+        assert_eq!(failed, [false, true, false, true, false]);
+    }
+
+    #[test]
+    fn sparkline_auto_scale_spans_its_own_range() {
+        let latencies: Vec<_> = (1..=6).map(|i| Some(100 * i)).collect();
+        assert_eq!(sparkline(SparklineScale::Auto, &latencies).0, "▁▂▃▅▆█");
+        // A steady endpoint is flat however slow it is
+        assert_eq!(sparkline(SparklineScale::Auto, &[Some(200); 6]).0, "▁▁▁▁▁▁");
+    }
+
+    #[test]
+    fn sparkline_fixed_scale_is_shared() {
+        let scale = SparklineScale::Fixed(1000);
+        assert_eq!(sparkline(scale, &[Some(200); 6]).0, "▂▂▂▂▂▂");
+        // Beyond the top of the scale is drawn as the top
+        assert_eq!(
+            sparkline(scale, &[Some(0), Some(500), Some(1000), Some(5000)]).0,
+            "▁▄██▁"
+        );
+    }
+
+    #[test]
+    fn sparkline_shows_only_the_latest_checks() {
+        let mut latencies = vec![None; 4];
+        latencies.extend((1..=6).map(|i| Some(100 * i)));
+        let (text, failed) = sparkline(SparklineScale::Auto, &latencies);
+        assert_eq!(text.chars().count(), SPARKLINE_SAMPLES);
+        assert_eq!(text, "▁▂▃▅▆█");
+        assert!(failed.iter().all(|&failed| !failed));
+    }
+
+    #[test]
+    fn table_has_a_summary_row_above_each_group() {
+        let dashboard = dashboard();
+        assert_eq!(
+            table_lines(&dashboard.all_endpoints, &dashboard.endpoints),
+            [
+                TableLine::Group("main"),
+                TableLine::Endpoint(0),
+                TableLine::Group("backup"),
+                TableLine::Endpoint(1),
+            ]
+        );
+
+        // A single group needs no summary
+        let alpha = dashboard.all_endpoints[..1].to_vec();
+        assert_eq!(
+            table_lines(&alpha, &dashboard.endpoints),
+            [TableLine::Endpoint(0)]
+        );
+    }
+
+    #[test]
+    fn summary_rows_are_bold_and_red_for_a_group_that_is_down() {
+        let mut dashboard = dashboard();
+        for (id, state) in [
+            ("alpha", CheckState::Healthy),
+            ("beta", CheckState::Unhealthy),
+        ] {
+            let status = dashboard
+                .endpoint_health
+                .get_mut(&EndpointId::new(id))
+                .unwrap();
+            status.state = state;
+            status.latency = Some(321);
+        }
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|f| dashboard.render(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text_at = |x: u16, y: u16, width: u16| {
+            (x..(x + width).min(120))
+                .map(|x| buffer.get(x, y).symbol.as_str())
+                .collect::<String>()
+        };
+        let line = |y: u16| text_at(0, y, 120);
+        let row_of = |text: &str| {
+            (0..30)
+                .find(|&y| line(y).contains(text))
+                .unwrap_or_else(|| panic!("{text} not drawn"))
+        };
+        // The style of a row, read where `text` is drawn on it
+        let style_at = |y: u16, text: &str| {
+            let x = (0..120)
+                .find(|&x| text_at(x, y, text.len() as u16) == text)
+                .unwrap();
+            buffer.get(x, y).style()
+        };
+
+        let main = row_of("[main]");
+        assert!(line(main).contains("1/1"), "{}", line(main));
+        assert!(line(main).contains("321ms"), "{}", line(main));
+        let main_style = style_at(main, "[main]");
+        assert!(main_style.add_modifier.contains(Modifier::BOLD));
+        assert_ne!(main_style.fg, Some(Color::Red));
+
+        let backup = row_of("[backup]");
+        assert!(line(backup).contains("0/1"), "{}", line(backup));
+        assert!(line(backup).contains("all down"), "{}", line(backup));
+        let backup_style = style_at(backup, "[backup]");
+        assert!(backup_style.add_modifier.contains(Modifier::BOLD));
+        assert_eq!(backup_style.fg, Some(Color::Red));
+
+        // The cursor starts on the first endpoint, below its summary row
+        assert_eq!(dashboard.cursor_index, 0);
+        assert_eq!(row_of("alpha"), main + 1);
+    }
+}
@@ -20,7 +20,9 @@ pub async fn test_health_check_timing() -> anyhow::Result<()> {
     );
 
     // Create minimal test setup
-    let connection_tracker = Arc::new(Mutex::new(ConnectionTracker::new()));
+    let connection_tracker = Arc::new(Mutex::new(ConnectionTracker::new(
+        config.ui.recent_requests_size,
+    )));
     let (event_sender, mut event_receiver) = mpsc::unbounded_channel::<ProxyEvent>();
     let state = Arc::new(Mutex::new(ProxyState::new(config.clone())));
 
@@ -64,7 +66,8 @@ pub async fn test_health_check_timing() -> anyhow::Result<()> {
                         actual_interval,
                         next_check_time,
                         load_level,
-                        active_connections
+                        active_connections,
+                        ..
                     }) => {
                         cycle_count += 1;
                         let now = Instant::now();
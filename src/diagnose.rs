@@ -0,0 +1,164 @@
+use crate::build_info;
+use crate::config::Config;
+use anyhow::Context;
+use hyper::{Body, Client, Method, Request, StatusCode};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory snapshots are written to, relative to the working directory
+pub const DEFAULT_DIAGNOSTICS_DIR: &str = "zephyr-diagnostics";
+
+/// Dashboard events kept for the next snapshot
+pub(crate) const EVENT_LOG_LINES: usize = 200;
+
+/// Replaces every secret found in a snapshot
+const REDACTED: &str = "[REDACTED]";
+
+/// Options for the `diagnose` subcommand
+#[derive(clap::Args, Debug)]
+pub struct DiagnoseArgs {
+    /// Base URL of the running proxy (default: http://127.0.0.1:<server.port>)
+    #[arg(long)]
+    url: Option<String>,
+
+    /// Directory the snapshot is written to
+    #[arg(long, default_value = DEFAULT_DIAGNOSTICS_DIR)]
+    output: PathBuf,
+}
+
+/// Everything a problem report needs, as one JSON document: the `/status`,
+/// `/diagnostics` and `/switches` bodies, the dashboard's recent events when
+/// taken from the dashboard, and the configuration, with every token removed
+pub(crate) fn assemble_snapshot(
+    config: &Config,
+    status: serde_json::Value,
+    diagnostics: serde_json::Value,
+    switches: serde_json::Value,
+    events: Option<Vec<String>>,
+) -> serde_json::Value {
+    let mut snapshot = serde_json::json!({
+        "created_at": chrono::Utc::now(),
+        "version": env!("CARGO_PKG_VERSION"),
+        "commit": build_info::GIT_COMMIT,
+        "status": status,
+        "diagnostics": diagnostics,
+        "switches": switches,
+        "events": events,
+        "config": config,
+    });
+    redact(&mut snapshot, &secrets(config));
+    snapshot
+}
+
+/// Token values currently loaded for the configured groups and admin routes,
+/// longest first: a token containing another is then redacted whole instead
+/// of leaving the rest of it around the shorter one's marker
+pub(crate) fn secrets(config: &Config) -> Vec<String> {
+    let mut secrets: Vec<String> = config
+        .groups
+        .iter()
+        .filter_map(|group| group.auth_token())
+        .chain(config.get_admin_token())
+        .collect();
+    secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+    secrets.dedup();
+    secrets
+}
+
+/// Replace every occurrence of a secret in the string values of `value`
+pub(crate) fn redact(value: &mut serde_json::Value, secrets: &[String]) {
+    match value {
+        serde_json::Value::String(text) => {
+            for secret in secrets {
+                if text.contains(secret.as_str()) {
+                    *text = text.replace(secret.as_str(), REDACTED);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                redact(item, secrets);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for field in fields.values_mut() {
+                redact(field, secrets);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Write `snapshot` to a new timestamped file in `dir`, returning its path
+pub(crate) fn write_snapshot(snapshot: &serde_json::Value, dir: &Path) -> anyhow::Result<PathBuf> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(format!(
+        "zephyr-diagnostics-{}.json",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    fs::write(&path, serde_json::to_vec_pretty(snapshot)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Take a snapshot of the running proxy through its HTTP endpoints and write it
+/// under `args.output`. The dashboard's event log is not reachable this way.
+pub async fn run_diagnose(config: &Config, args: &DiagnoseArgs) -> anyhow::Result<()> {
+    let base_url = args
+        .url
+        .clone()
+        .unwrap_or_else(|| format!("http://127.0.0.1:{}", config.server.port));
+    let base_url = base_url.trim_end_matches('/');
+    let admin_token = config.get_admin_token();
+
+    let status = fetch_json(base_url, "/status", admin_token.as_deref()).await?;
+    let diagnostics = fetch_json(base_url, "/diagnostics", admin_token.as_deref()).await?;
+    let switches = fetch_json(base_url, "/switches", admin_token.as_deref()).await?;
+
+    let snapshot = assemble_snapshot(config, status, diagnostics, switches, None);
+    let path = write_snapshot(&snapshot, &args.output)?;
+    println!("Diagnostics saved to {}", path.display());
+    Ok(())
+}
+
+pub(crate) async fn fetch_json(
+    base_url: &str,
+    path: &str,
+    admin_token: Option<&str>,
+) -> anyhow::Result<serde_json::Value> {
+    request_json(Method::GET, base_url, path, admin_token).await
+}
+
+/// Send a `method` request to a local route of the running proxy and parse
+/// the JSON it answers with
+pub(crate) async fn request_json(
+    method: Method,
+    base_url: &str,
+    path: &str,
+    admin_token: Option<&str>,
+) -> anyhow::Result<serde_json::Value> {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(format!("{base_url}{path}"));
+    if let Some(token) = admin_token {
+        request = request.header(hyper::header::AUTHORIZATION, format!("Bearer {token}"));
+    }
+
+    let response = Client::new()
+        .request(request.body(Body::empty())?)
+        .await
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "❌ No proxy answering at {base_url}: {e}\n💡 Start claude-zephyr first, or pass --url"
+            )
+        })?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    if status != StatusCode::OK {
+        anyhow::bail!(
+            "{path} answered {status}: {}",
+            String::from_utf8_lossy(&body)
+        );
+    }
+    serde_json::from_slice(&body).with_context(|| format!("{path} did not return JSON"))
+}
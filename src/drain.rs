@@ -0,0 +1,72 @@
+use crate::connection_tracker::{EventSender, SharedConnectionTracker};
+use crate::events::ProxyEvent;
+use crate::lock::LockExt;
+use crate::logging::log_endpoint_drained;
+use crate::proxy::SharedState;
+use std::time::Duration;
+
+/// How often draining endpoints are checked for completion
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Finish the drain of each endpoint left by a switch once its last active
+/// connection completes or `graceful_switch_timeout_ms` has passed, whichever
+/// comes first, and emit [`ProxyEvent::EndpointDrained`] for it.
+///
+/// Connections still open at the timeout are not cut; they finish on the old
+/// endpoint, only the drain window closes.
+pub async fn run_drain_monitor(
+    state: SharedState,
+    connection_tracker: SharedConnectionTracker,
+    event_sender: EventSender,
+    quiet: bool,
+) {
+    let mut interval = tokio::time::interval(DRAIN_POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let (draining, timeout) = {
+            let state_guard = state.lock_or_recover();
+            if state_guard.draining.is_empty() {
+                continue;
+            }
+            (
+                state_guard.draining.clone(),
+                Duration::from_millis(state_guard.config.server.graceful_switch_timeout_ms),
+            )
+        };
+        let active = connection_tracker
+            .lock_or_recover()
+            .get_endpoint_distribution()
+            .clone();
+
+        let finished: Vec<_> = draining
+            .into_iter()
+            .filter_map(|(id, drain)| {
+                let remaining = active.get(&id).copied().unwrap_or(0);
+                let elapsed = drain.started.elapsed();
+                (remaining == 0 || elapsed >= timeout).then_some((id, drain, remaining, elapsed))
+            })
+            .collect();
+        if finished.is_empty() {
+            continue;
+        }
+
+        let mut state_guard = state.lock_or_recover();
+        for (id, drain, remaining, elapsed) in finished {
+            // A switch back to the endpoint ended or restarted this drain meanwhile
+            if state_guard.draining.get(&id).map(|current| current.started) != Some(drain.started) {
+                continue;
+            }
+            state_guard.draining.remove(&id);
+
+            if !quiet {
+                log_endpoint_drained(&drain.url, remaining, elapsed);
+            }
+            let _ = event_sender.send(ProxyEvent::EndpointDrained {
+                endpoint: drain.url,
+                remaining,
+                elapsed,
+            });
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::config::{Config, FallbackPolicy};
+use crate::diagnose::request_json;
+use hyper::Method;
+use serde::{Deserialize, Serialize};
+
+/// Options for the `drill` subcommand
+#[derive(clap::Args, Debug)]
+pub struct DrillArgs {
+    /// Base URL of the running proxy (default: http://127.0.0.1:<server.port>)
+    #[arg(long)]
+    url: Option<String>,
+
+    /// Print JSON instead of text
+    #[arg(long)]
+    json: bool,
+}
+
+/// Outcome of a failover drill, as returned by `POST /admin/drill`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DrillReport {
+    /// Endpoint treated as failed, the current one when the drill started
+    pub drilled_endpoint: String,
+    /// Endpoint that answered the drill's request, `None` when none could
+    pub served_by: Option<String>,
+    /// Status returned for the drill's request
+    pub status: u16,
+    /// Fallback endpoints the request was sent to
+    pub candidates_tried: u32,
+    /// Time until response headers arrived from a fallback endpoint
+    pub failover_ms: u64,
+    pub fallback_policy: FallbackPolicy,
+    /// Another endpoint served the request successfully
+    pub passed: bool,
+}
+
+/// Ask the running proxy to run a failover drill and report the result.
+/// Fails if no fallback endpoint could serve the drill's request.
+pub async fn run_drill(config: &Config, args: &DrillArgs) -> anyhow::Result<()> {
+    let base_url = args
+        .url
+        .clone()
+        .unwrap_or_else(|| format!("http://127.0.0.1:{}", config.server.port));
+    let admin_token = config.get_admin_token();
+    let response = request_json(
+        Method::POST,
+        base_url.trim_end_matches('/'),
+        "/admin/drill",
+        admin_token.as_deref(),
+    )
+    .await?;
+    let report: DrillReport = serde_json::from_value(response)?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(config, &report);
+    }
+    if !report.passed {
+        anyhow::bail!("Failover drill failed: no fallback endpoint served the request");
+    }
+    Ok(())
+}
+
+fn print_report(config: &Config, report: &DrillReport) {
+    let label = |url: &str| match config
+        .get_all_endpoints()
+        .into_iter()
+        .find(|(_, endpoint, _)| endpoint.url == url)
+    {
+        Some((_, endpoint, _)) => format!("{} ({url})", endpoint.name),
+        None => url.to_string(),
+    };
+
+    println!("Drilled:    {}", label(&report.drilled_endpoint));
+    match &report.served_by {
+        Some(served_by) => println!("Served by:  {}", label(served_by)),
+        None => println!(
+            "Served by:  - (fallback: {})",
+            report.fallback_policy.as_str()
+        ),
+    }
+    println!("Status:     {}", report.status);
+    println!("Failover:   {}ms", report.failover_ms);
+    println!("Candidates: {}", report.candidates_tried);
+    println!(
+        "Result:     {}",
+        if report.passed { "passed" } else { "FAILED" }
+    );
+}
@@ -1,10 +1,13 @@
 use crate::config::Config;
 use crate::connection_tracker::SharedConnectionTracker;
+use crate::lock::LockExt;
+use serde::Serialize;
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
 /// Load level classification for dynamic health check intervals
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum LoadLevel {
     Idle,   // 0 connections, system quiet
     Low,    // 1-3 connections
@@ -44,11 +47,7 @@ impl LoadMetrics {
         }
 
         // Get current active connection count
-        let active_count = if let Ok(tracker_guard) = tracker.lock() {
-            tracker_guard.get_active_count()
-        } else {
-            0
-        };
+        let active_count = tracker.lock_or_recover().get_active_count();
 
         // Determine new load level based on active connections
         // Since each connection typically represents a request, connection count is a good proxy for load
@@ -107,6 +106,34 @@ impl LoadMetrics {
     }
 }
 
+/// Bound a scaled interval was held to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntervalBound {
+    Min,
+    Max,
+}
+
+/// What a health check interval was calculated from, to explain a long one
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct IntervalDecision {
+    /// `false` when dynamic scaling is off and the base interval is used as is
+    pub dynamic: bool,
+    pub load_level: LoadLevel,
+    /// Requests in the last minute
+    pub request_rate: f64,
+    /// How long the load has been idle, only while idle
+    pub idle_seconds: Option<u64>,
+    pub base_seconds: u64,
+    /// Multiplier applied to the base interval, `None` when it was not scaled:
+    /// scaling is off or high load uses the minimum
+    pub scaling_factor: Option<f64>,
+    pub min_seconds: u64,
+    pub max_seconds: u64,
+    /// Bound the scaled interval was outside of and was held to
+    pub clamped: Option<IntervalBound>,
+}
+
 /// Dynamic health check interval calculator
 pub struct DynamicHealthChecker {
     load_metrics: LoadMetrics,
@@ -130,23 +157,54 @@ impl DynamicHealthChecker {
         }
     }
 
-    /// Calculate the optimal health check interval based on current load
-    pub fn calculate_interval(&mut self, tracker: &SharedConnectionTracker) -> Duration {
-        if !self.dynamic_enabled {
-            return self.base_interval;
+    /// Calculate the optimal health check interval based on current load,
+    /// with the inputs that gave it
+    pub fn calculate_interval(
+        &mut self,
+        tracker: &SharedConnectionTracker,
+    ) -> (Duration, IntervalDecision) {
+        if self.dynamic_enabled {
+            self.load_metrics.update(tracker);
         }
 
-        // Update load metrics
-        self.load_metrics.update(tracker);
-
         let load_level = self.load_metrics.get_load_level();
-        let request_rate = self.load_metrics.get_request_rate();
+        let idle_duration =
+            (load_level == LoadLevel::Idle).then(|| self.load_metrics.last_load_change.elapsed());
+        self.decide(
+            load_level,
+            self.load_metrics.get_request_rate(),
+            idle_duration,
+        )
+    }
+
+    /// The interval for a load level, request rate per minute and, when idle,
+    /// how long the load has been idle
+    pub fn decide(
+        &self,
+        load_level: LoadLevel,
+        request_rate: f64,
+        idle_duration: Option<Duration>,
+    ) -> (Duration, IntervalDecision) {
+        let mut decision = IntervalDecision {
+            dynamic: self.dynamic_enabled,
+            load_level,
+            request_rate,
+            idle_seconds: idle_duration.map(|idle| idle.as_secs()),
+            base_seconds: self.base_interval.as_secs(),
+            scaling_factor: None,
+            min_seconds: self.min_interval.as_secs(),
+            max_seconds: self.max_interval.as_secs(),
+            clamped: None,
+        };
+        if !self.dynamic_enabled {
+            return (self.base_interval, decision);
+        }
 
         // Calculate scaling factor based on load and request rate
         let scaling_factor = match load_level {
             LoadLevel::High => {
                 // High load: use minimum interval directly (no scaling)
-                return self.min_interval;
+                return (self.min_interval, decision);
             }
             LoadLevel::Medium => {
                 // Medium load: slight increase
@@ -166,10 +224,7 @@ impl DynamicHealthChecker {
             }
             LoadLevel::Idle => {
                 // Idle: progressive scaling from base_interval to max_interval based on idle duration
-                let idle_duration = Instant::now() - self.load_metrics.last_load_change;
-
-                // Define idle duration thresholds for progressive scaling
-                let idle_secs = idle_duration.as_secs();
+                let idle_secs = decision.idle_seconds.unwrap_or(0);
                 let base_secs = self.base_interval.as_secs() as f64;
                 let max_secs = self.max_interval.as_secs() as f64;
 
@@ -195,6 +250,7 @@ impl DynamicHealthChecker {
                 }
             }
         };
+        decision.scaling_factor = Some(scaling_factor);
 
         // Apply scaling factor
         let calculated_interval =
@@ -202,11 +258,13 @@ impl DynamicHealthChecker {
 
         // Ensure interval respects configurable minimum and maximum bounds
         if calculated_interval < self.min_interval {
-            self.min_interval
+            decision.clamped = Some(IntervalBound::Min);
+            (self.min_interval, decision)
         } else if calculated_interval > self.max_interval {
-            self.max_interval
+            decision.clamped = Some(IntervalBound::Max);
+            (self.max_interval, decision)
         } else {
-            calculated_interval
+            (calculated_interval, decision)
         }
     }
 
@@ -227,3 +285,124 @@ impl DynamicHealthChecker {
         self.load_metrics.get_request_rate()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{config, TWO_GROUPS};
+
+    /// A checker with a 60s base interval held between 30s and an hour
+    fn checker(dynamic: bool) -> DynamicHealthChecker {
+        let mut config = config(TWO_GROUPS);
+        config.health_check.interval_seconds = 60;
+        config.health_check.min_interval_seconds = Some(30);
+        config.health_check.max_interval_seconds = Some(3600);
+        config.health_check.dynamic_scaling = dynamic;
+        DynamicHealthChecker::new(&config)
+    }
+
+    fn idle_for(checker: &DynamicHealthChecker, seconds: u64) -> (u64, IntervalDecision) {
+        let (interval, decision) =
+            checker.decide(LoadLevel::Idle, 0.0, Some(Duration::from_secs(seconds)));
+        (interval.as_secs(), decision)
+    }
+
+    #[test]
+    fn idle_interval_at_each_breakpoint() {
+        let checker = checker(true);
+        // Idle seconds, interval and scaling factor at the ends and middles of
+        // every stage of the idle ramp
+        let expected = [
+            (0, 60, 1.0),
+            (60, 60, 1.0),
+            (180, 75, 1.25),
+            (300, 90, 1.5),
+            (600, 135, 2.25),
+            (900, 180, 3.0),
+            (1350, 330, 5.5),
+            (1800, 480, 8.0),
+            (2700, 2040, 34.0),
+            (3600, 3600, 60.0),
+            (7200, 3600, 60.0),
+        ];
+        for (idle, interval, factor) in expected {
+            let (actual, decision) = idle_for(&checker, idle);
+            assert_eq!(actual, interval, "after {idle}s idle");
+            assert_eq!(decision.scaling_factor, Some(factor), "after {idle}s idle");
+            assert_eq!(decision.idle_seconds, Some(idle));
+            assert_eq!(decision.load_level, LoadLevel::Idle);
+            assert_eq!(decision.clamped, None, "after {idle}s idle");
+        }
+    }
+
+    #[test]
+    fn idle_interval_never_shrinks_as_the_idle_time_grows() {
+        let checker = checker(true);
+        let mut previous = 0;
+        for idle in (0..=4000).step_by(10) {
+            let (interval, _) = idle_for(&checker, idle);
+            assert!(interval >= previous, "{interval}s after {idle}s idle");
+            previous = interval;
+        }
+    }
+
+    #[test]
+    fn idle_interval_is_held_to_the_maximum() {
+        let mut checker = checker(true);
+        checker.max_interval = Duration::from_secs(300);
+        let (interval, decision) = idle_for(&checker, 1800);
+        assert_eq!(interval, 300);
+        assert_eq!(decision.scaling_factor, Some(8.0));
+        assert_eq!(decision.clamped, Some(IntervalBound::Max));
+    }
+
+    #[test]
+    fn busy_intervals_scale_by_load_and_rate() {
+        let checker = checker(true);
+        let expected = [
+            (LoadLevel::Low, 0.0, 150, Some(2.5)),
+            (LoadLevel::Low, 3.0, 120, Some(2.0)),
+            (LoadLevel::Medium, 0.0, 90, Some(1.5)),
+            (LoadLevel::Medium, 6.0, 72, Some(1.2)),
+            (LoadLevel::High, 50.0, 30, None),
+        ];
+        for (load_level, rate, interval, factor) in expected {
+            let (actual, decision) = checker.decide(load_level, rate, None);
+            assert_eq!(actual.as_secs(), interval, "{load_level:?} at {rate}/min");
+            assert_eq!(decision.scaling_factor, factor);
+            assert_eq!(decision.request_rate, rate);
+            assert_eq!(decision.idle_seconds, None);
+        }
+    }
+
+    #[test]
+    fn scaled_interval_is_held_to_the_minimum() {
+        let mut checker = checker(true);
+        checker.base_interval = Duration::from_secs(10);
+        let (interval, decision) = checker.decide(LoadLevel::Medium, 6.0, None);
+        assert_eq!(interval, Duration::from_secs(30));
+        assert_eq!(decision.scaling_factor, Some(1.2));
+        assert_eq!(decision.clamped, Some(IntervalBound::Min));
+    }
+
+    #[test]
+    fn base_interval_is_used_as_is_without_dynamic_scaling() {
+        let checker = checker(false);
+        for load_level in [LoadLevel::Idle, LoadLevel::High] {
+            let (interval, decision) =
+                checker.decide(load_level, 0.0, Some(Duration::from_secs(7200)));
+            assert_eq!(interval, Duration::from_secs(60));
+            assert!(!decision.dynamic);
+            assert_eq!(decision.scaling_factor, None);
+            assert_eq!(decision.clamped, None);
+            assert_eq!(
+                (
+                    decision.base_seconds,
+                    decision.min_seconds,
+                    decision.max_seconds
+                ),
+                (60, 30, 3600)
+            );
+        }
+    }
+}
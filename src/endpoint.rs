@@ -0,0 +1,253 @@
+//! Endpoint identity used as the key of every per-endpoint runtime map.
+//!
+//! Endpoints are configured by URL, but the URL is neither stable (it can
+//! change on reload) nor unique in spelling (`https://Host/` and
+//! `https://host` reach the same upstream). Runtime state is keyed by the
+//! endpoint's [`EndpointId`] instead, and URLs are resolved to it once through
+//! the [`EndpointIndex`] built when the configuration is loaded.
+
+use crate::config::Group;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Stable identifier of a configured endpoint, see [`SimpleEndpoint::id`]
+///
+/// [`SimpleEndpoint::id`]: crate::config::SimpleEndpoint::id
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EndpointId(Arc<str>);
+
+impl EndpointId {
+    pub fn new(id: &str) -> Self {
+        Self(Arc::from(id))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Serialize for EndpointId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for EndpointId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|id| Self::new(&id))
+    }
+}
+
+impl fmt::Display for EndpointId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A configured endpoint: its id with the URL, name and group it has in the
+/// current configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointRef {
+    pub id: EndpointId,
+    /// URL exactly as configured, requests are sent here
+    pub url: String,
+    pub name: String,
+    pub group: String,
+}
+
+/// Every configured endpoint in config order, with lookup by id and by URL
+#[derive(Debug, Clone, Default)]
+pub struct EndpointIndex {
+    endpoints: Vec<EndpointRef>,
+    by_id: HashMap<EndpointId, usize>,
+    /// Position in `endpoints` by normalized URL
+    by_url: HashMap<String, usize>,
+}
+
+impl EndpointIndex {
+    /// Index the endpoints of `groups`, or the first id that more than one
+    /// endpoint uses, which would leave all but one of them unreachable by id
+    pub fn new(groups: &[Group]) -> Result<Self, EndpointId> {
+        let mut index = Self::default();
+        for group in groups {
+            for endpoint in &group.endpoints {
+                let position = index.endpoints.len();
+                let id = EndpointId::new(endpoint.id());
+                if index.by_id.insert(id.clone(), position).is_some() {
+                    return Err(id);
+                }
+                index
+                    .by_url
+                    .entry(normalize_url(&endpoint.url))
+                    .or_insert(position);
+                index.endpoints.push(EndpointRef {
+                    id,
+                    url: endpoint.url.clone(),
+                    name: endpoint.name.clone(),
+                    group: group.name.clone(),
+                });
+            }
+        }
+        Ok(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &EndpointRef> {
+        self.endpoints.iter()
+    }
+
+    pub fn get(&self, id: &EndpointId) -> Option<&EndpointRef> {
+        self.by_id
+            .get(id)
+            .map(|&position| &self.endpoints[position])
+    }
+
+    /// The endpoint at `url`, however the URL is spelled
+    pub fn resolve(&self, url: &str) -> Option<&EndpointRef> {
+        self.by_url
+            .get(url)
+            .or_else(|| self.by_url.get(&normalize_url(url)))
+            .map(|&position| &self.endpoints[position])
+    }
+
+    /// Id of the endpoint at `url`, however the URL is spelled
+    pub fn id_of(&self, url: &str) -> Option<&EndpointId> {
+        self.resolve(url).map(|endpoint| &endpoint.id)
+    }
+
+    /// Key for runtime state of requests sent to `url`: the endpoint's id, or
+    /// the URL itself for one no longer configured
+    pub fn key_of(&self, url: &str) -> EndpointId {
+        self.id_of(url)
+            .cloned()
+            .unwrap_or_else(|| EndpointId::new(url))
+    }
+}
+
+/// `url` with the differences that do not change where requests go removed:
+/// surrounding whitespace, trailing slashes, the case of the scheme and host,
+/// and the scheme's default port
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let scheme = scheme.to_ascii_lowercase();
+    let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let mut authority = authority.to_ascii_lowercase();
+    let default_port = match scheme.as_str() {
+        "https" => Some(":443"),
+        "http" => Some(":80"),
+        _ => None,
+    };
+    if let Some(port) = default_port {
+        if let Some(host_len) = authority.strip_suffix(port).map(str::len) {
+            authority.truncate(host_len);
+        }
+    }
+    format!("{scheme}://{authority}{path}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{config, TWO_GROUPS};
+
+    #[test]
+    fn normalize_url_drops_trailing_slashes() {
+        assert_eq!(
+            normalize_url("https://api.example.com/"),
+            "https://api.example.com"
+        );
+        assert_eq!(
+            normalize_url("https://api.example.com//"),
+            "https://api.example.com"
+        );
+        assert_eq!(
+            normalize_url(" https://api.example.com "),
+            "https://api.example.com"
+        );
+    }
+
+    #[test]
+    fn normalize_url_drops_only_the_default_port() {
+        assert_eq!(
+            normalize_url("https://api.example.com:443"),
+            "https://api.example.com"
+        );
+        assert_eq!(
+            normalize_url("http://api.example.com:80"),
+            "http://api.example.com"
+        );
+        assert_eq!(
+            normalize_url("https://api.example.com:80"),
+            "https://api.example.com:80"
+        );
+        assert_eq!(
+            normalize_url("http://127.0.0.1:8443"),
+            "http://127.0.0.1:8443"
+        );
+    }
+
+    #[test]
+    fn normalize_url_lowercases_scheme_and_host_but_not_path() {
+        assert_eq!(
+            normalize_url("HTTPS://API.Example.COM/V1/Proxy"),
+            "https://api.example.com/V1/Proxy"
+        );
+    }
+
+    #[test]
+    fn normalize_url_keeps_the_path() {
+        assert_eq!(
+            normalize_url("https://api.example.com:443/claude/"),
+            "https://api.example.com/claude"
+        );
+        assert_ne!(
+            normalize_url("https://api.example.com/a"),
+            normalize_url("https://api.example.com/b")
+        );
+    }
+
+    #[test]
+    fn index_resolves_any_spelling_of_a_url() {
+        let config = config(TWO_GROUPS);
+        let index = config.endpoint_index();
+        let alpha = index.resolve("HTTP://127.0.0.1:19001/").unwrap();
+        assert_eq!(alpha.id.as_str(), "alpha");
+        assert_eq!(alpha.url, "http://127.0.0.1:19001");
+        assert_eq!(alpha.group, "main");
+        assert!(index.resolve("http://127.0.0.1:19003").is_none());
+    }
+
+    #[test]
+    fn key_of_falls_back_to_the_url() {
+        let config = config(TWO_GROUPS);
+        let index = config.endpoint_index();
+        assert_eq!(index.key_of("http://127.0.0.1:19002").as_str(), "beta");
+        assert_eq!(
+            index.key_of("http://127.0.0.1:19003").as_str(),
+            "http://127.0.0.1:19003"
+        );
+    }
+
+    #[test]
+    fn duplicate_id_is_rejected() {
+        let mut groups = config(TWO_GROUPS).groups;
+        groups[1].endpoints[0].id = Some("alpha".to_string());
+        assert_eq!(EndpointIndex::new(&groups).unwrap_err().as_str(), "alpha");
+
+        // Ids default to the name, so a shared name is a shared id too
+        let mut groups = config(TWO_GROUPS).groups;
+        groups[1].endpoints[0].name = "alpha".to_string();
+        assert_eq!(EndpointIndex::new(&groups).unwrap_err().as_str(), "alpha");
+    }
+
+    #[test]
+    fn duplicate_id_fails_the_config_load() {
+        let toml = TWO_GROUPS.replace(r#"name = "beta""#, r#"name = "beta", id = "alpha""#);
+        let error = crate::config::Config::parse(&toml).unwrap_err();
+        assert!(error.to_string().contains("'alpha'"), "{error}");
+    }
+}
@@ -0,0 +1,186 @@
+//! The endpoint table as plain values, shared by the dashboard and the CSV
+//! export so an exported file shows the same numbers as the screen.
+
+use crate::endpoint::EndpointRef;
+use crate::health::{CheckState, EndpointStatus};
+use crate::metrics::CounterSnapshot;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What the status and latency columns show for an endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowState {
+    /// The group's auth token variable is not set
+    NoToken,
+    /// Inside a maintenance window until the given time
+    Maintenance(DateTime<Utc>),
+    Available,
+    /// Not checked since the proxy started or the endpoint was added
+    Unchecked,
+    /// A check is running
+    Checking,
+    AuthFailed,
+    Timeout,
+    Error,
+}
+
+impl RowState {
+    fn of(status: Option<&EndpointStatus>, missing_token: bool) -> Self {
+        let Some(status) = status else {
+            return if missing_token {
+                Self::NoToken
+            } else {
+                Self::Checking
+            };
+        };
+        if missing_token {
+            Self::NoToken
+        } else if let Some(until) = status.maintenance_until {
+            Self::Maintenance(until)
+        } else if status.state == CheckState::Healthy {
+            Self::Available
+        } else if status.state == CheckState::Unchecked {
+            Self::Unchecked
+        } else if status.state == CheckState::Checking {
+            Self::Checking
+        } else if status.auth_failed {
+            Self::AuthFailed
+        } else if status.error.as_ref().is_some_and(|e| e.contains("timeout")) {
+            Self::Timeout
+        } else {
+            Self::Error
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NoToken => "no_token",
+            Self::Maintenance(_) => "maintenance",
+            Self::Available => "available",
+            Self::Unchecked => "unchecked",
+            Self::Checking => "checking",
+            Self::AuthFailed => "auth_failed",
+            Self::Timeout => "timeout",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// One row of the endpoint table
+#[derive(Debug, Clone)]
+pub struct EndpointRow {
+    pub name: String,
+    pub group: String,
+    pub url: String,
+    pub state: RowState,
+    /// Latency of the last check, only while the endpoint is available
+    pub latency_ms: Option<u64>,
+    /// Average latency of the successful checks in the history
+    pub avg_latency_ms: Option<u64>,
+    /// Share of the checks in the history that succeeded, in percent
+    pub availability_pct: Option<f64>,
+    /// Share of proxied requests not failed by the endpoint, in percent
+    pub success_rate_pct: Option<f64>,
+    pub counters: CounterSnapshot,
+}
+
+impl EndpointRow {
+    pub fn new(
+        endpoint: &EndpointRef,
+        status: Option<&EndpointStatus>,
+        counters: CounterSnapshot,
+        missing_token: bool,
+    ) -> Self {
+        let state = RowState::of(status, missing_token);
+        let history = status.map(|status| status.latency_history.get_measurements());
+        let availability_pct = history.filter(|h| !h.is_empty()).map(|h| {
+            let succeeded = h.iter().filter(|m| m.latency.is_some()).count();
+            succeeded as f64 * 100.0 / h.len() as f64
+        });
+        let success_rate_pct = (counters.requests > 0).then(|| {
+            let failed = counters.endpoint_errors.min(counters.requests);
+            (counters.requests - failed) as f64 * 100.0 / counters.requests as f64
+        });
+
+        Self {
+            name: endpoint.name.clone(),
+            group: endpoint.group.clone(),
+            url: endpoint.url.clone(),
+            state,
+            latency_ms: status
+                .filter(|_| state == RowState::Available)
+                .and_then(|status| status.latency),
+            avg_latency_ms: status.and_then(|status| status.latency_history.average_latency()),
+            availability_pct,
+            success_rate_pct,
+            counters,
+        }
+    }
+}
+
+const CSV_HEADER: &str = "name,group,url,status,latency_ms,avg_latency_ms,availability_pct,\
+success_rate_pct,requests,input_tokens,output_tokens,request_cost,health_check_cost,currency";
+
+/// The rows as CSV with a header line, costs in `currency` units
+pub fn to_csv(rows: &[EndpointRow], currency: &str) -> String {
+    let mut out = String::from(CSV_HEADER);
+    out.push_str("\r\n");
+    for row in rows {
+        let fields = [
+            csv_field(&row.name),
+            csv_field(&row.group),
+            csv_field(&row.url),
+            Cow::Borrowed(row.state.as_str()),
+            optional(row.latency_ms),
+            optional(row.avg_latency_ms),
+            percent(row.availability_pct),
+            percent(row.success_rate_pct),
+            Cow::Owned(row.counters.requests.to_string()),
+            Cow::Owned(row.counters.input_tokens.to_string()),
+            Cow::Owned(row.counters.output_tokens.to_string()),
+            cost(row.counters.request_cost_micros),
+            cost(row.counters.health_check_cost_micros),
+            csv_field(currency),
+        ];
+        let _ = write!(out, "{}\r\n", fields.join(","));
+    }
+    out
+}
+
+/// Write the rows to `zephyr-export-<timestamp>.csv` in `dir`
+pub fn write_csv(rows: &[EndpointRow], currency: &str, dir: &Path) -> anyhow::Result<PathBuf> {
+    let path = dir.join(format!(
+        "zephyr-export-{}.csv",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    fs::write(&path, to_csv(rows, currency))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// A field quoted as RFC 4180 requires when it holds a comma, quote or line
+/// break, with quotes doubled
+pub fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+fn optional(value: Option<u64>) -> Cow<'static, str> {
+    value.map_or(Cow::Borrowed(""), |value| Cow::Owned(value.to_string()))
+}
+
+fn percent(value: Option<f64>) -> Cow<'static, str> {
+    value.map_or(Cow::Borrowed(""), |value| Cow::Owned(format!("{value:.1}")))
+}
+
+/// Micro-dollars with the four decimals the dashboard shows
+fn cost(micros: u64) -> Cow<'static, str> {
+    Cow::Owned(format!("{:.4}", micros as f64 / 1_000_000.0))
+}
@@ -0,0 +1,173 @@
+//! The env file holding group tokens. Its values are written to the process
+//! environment once, at startup before any thread runs; later reads only
+//! replace an in-process copy, since `set_var` races with `getenv` in libc
+//! (DNS resolution, OpenSSL) on other threads.
+
+use crate::i18n;
+use crate::lock::RwLockExt;
+use crate::logging::{console_text, log_env_file_missing, log_env_file_reloaded};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, PoisonError, RwLock};
+
+/// File read when neither `--env-file` nor `env_file` names one
+pub const DEFAULT_ENV_FILE: &str = ".env";
+
+/// The env file tokens were loaded from, kept so it can be read again
+struct LoadedEnvFile {
+    path: PathBuf,
+    /// Variables already set outside the file, which it never overrides
+    inherited: HashSet<String>,
+    /// Values last applied from the file
+    values: BTreeMap<String, String>,
+    /// The file was gone on the last reload, and that was logged
+    missing: bool,
+}
+
+static ENV_FILE: Mutex<Option<LoadedEnvFile>> = Mutex::new(None);
+
+/// Values the env file provides as of its last read, looked up before the
+/// process environment by [`var`]
+static FILE_VALUES: LazyLock<RwLock<HashMap<String, String>>> = LazyLock::new(Default::default);
+
+/// Value of `name` from the env file, which holds the latest one after a
+/// reload, else from the process environment
+pub fn var(name: &str) -> Option<String> {
+    FILE_VALUES
+        .read_or_recover()
+        .get(name)
+        .cloned()
+        .or_else(|| env::var(name).ok())
+}
+
+/// Make `loaded`'s values the ones [`var`] returns
+fn publish(loaded: &LoadedEnvFile) {
+    *FILE_VALUES.write_or_recover() = loaded
+        .values
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+}
+
+// `from_path` never overrides a set variable, which a rotated token needs
+#[allow(deprecated)]
+fn read(path: &Path) -> Result<Vec<(String, String)>, dotenv::Error> {
+    dotenv::from_path_iter(path)?.collect()
+}
+
+/// Load `path` into the environment without overriding variables that are
+/// already set, and remember it for [`reload`]. Returns the names it provided.
+///
+/// Writes the process environment, so call it only while no other thread
+/// runs: at startup, before the async runtime is built.
+pub fn load(path: &Path) -> anyhow::Result<Vec<String>> {
+    let entries = read(path).map_err(|e| {
+        let error = i18n::console().env_file_read_failed(&path.display().to_string(), &e);
+        anyhow::anyhow!("{}", console_text(&error))
+    })?;
+
+    let mut loaded = LoadedEnvFile {
+        path: path.to_path_buf(),
+        inherited: HashSet::new(),
+        values: BTreeMap::new(),
+        missing: false,
+    };
+    let mut provided = Vec::new();
+    for (name, value) in entries {
+        if env::var_os(&name).is_some() && !loaded.values.contains_key(&name) {
+            loaded.inherited.insert(name);
+            continue;
+        }
+        env::set_var(&name, &value);
+        loaded.values.insert(name.clone(), value);
+        provided.push(name);
+    }
+
+    publish(&loaded);
+    *ENV_FILE.lock().unwrap_or_else(PoisonError::into_inner) = Some(loaded);
+    Ok(provided)
+}
+
+/// Remember `path` for [`reload`] without reading it, for a default env file
+/// that does not exist yet
+pub fn watch(path: &Path) {
+    *ENV_FILE.lock().unwrap_or_else(PoisonError::into_inner) = Some(LoadedEnvFile {
+        path: path.to_path_buf(),
+        inherited: HashSet::new(),
+        values: BTreeMap::new(),
+        missing: true,
+    });
+}
+
+/// Read the loaded env file again and apply values that changed since, so a
+/// rotated token is used by the next request. Only [`var`] sees them; the
+/// process environment is left alone. Variables removed from the file, or the
+/// whole file going missing, keep their last values. `requested` also logs a
+/// reload that changed nothing.
+pub fn reload(requested: bool) {
+    let mut guard = ENV_FILE.lock().unwrap_or_else(PoisonError::into_inner);
+    let Some(loaded) = guard.as_mut() else {
+        return;
+    };
+
+    let entries = match read(&loaded.path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            if !loaded.missing {
+                log_env_file_missing(&loaded.path.display().to_string(), &e.to_string());
+                loaded.missing = true;
+            }
+            return;
+        }
+    };
+    loaded.missing = false;
+
+    let mut changed = Vec::new();
+    for (name, value) in entries {
+        if loaded.inherited.contains(&name) {
+            continue;
+        }
+        if !loaded.values.contains_key(&name) && env::var_os(&name).is_some() {
+            loaded.inherited.insert(name);
+            continue;
+        }
+        if loaded.values.get(&name) != Some(&value) {
+            loaded.values.insert(name.clone(), value);
+            changed.push(name);
+        }
+    }
+    if !changed.is_empty() {
+        publish(loaded);
+    }
+
+    if requested || !changed.is_empty() {
+        log_env_file_reloaded(&loaded.path.display().to_string(), &changed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reloaded_values_are_seen_without_writing_the_environment() {
+        const NAME: &str = "ZEPHYR_ENV_FILE_TEST_TOKEN";
+        let path = env::temp_dir().join(format!("zephyr-env-{}", std::process::id()));
+        std::fs::write(&path, format!("{NAME}=first\n")).unwrap();
+
+        assert_eq!(load(&path).unwrap(), [NAME]);
+        assert_eq!(var(NAME).as_deref(), Some("first"));
+
+        std::fs::write(&path, format!("{NAME}=rotated\n")).unwrap();
+        reload(false);
+        assert_eq!(var(NAME).as_deref(), Some("rotated"));
+        // Only the load at startup wrote the process environment
+        assert_eq!(env::var(NAME).as_deref(), Ok("first"));
+
+        // A file that goes missing keeps the last values
+        std::fs::remove_file(&path).unwrap();
+        reload(false);
+        assert_eq!(var(NAME).as_deref(), Some("rotated"));
+    }
+}
@@ -1,4 +1,7 @@
-use crate::dynamic_health::LoadLevel;
+use crate::config::{AlertMetric, ConfigDiff};
+use crate::connection_tracker::ConnectionId;
+use crate::dynamic_health::{IntervalDecision, LoadLevel};
+use crate::endpoint::EndpointId;
 use crate::health::EndpointStatus;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -23,6 +26,165 @@ impl std::fmt::Display for SelectionMode {
     }
 }
 
+/// Why the active endpoint changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwitchReason {
+    /// A faster endpoint won the health check race
+    AutoRaceWin,
+    /// The current endpoint became unavailable
+    Failover,
+    /// The user picked the endpoint in manual mode
+    ManualSwitch,
+    /// A request failed over to another endpoint mid-flight
+    RequestFallback,
+    /// The tag filter excluded the current endpoint
+    TagFilter,
+    /// A config reload removed the current endpoint
+    ConfigReload,
+    /// The current endpoint entered a maintenance window
+    Maintenance,
+    /// A failover drill, recorded without switching: `to` served the drill's
+    /// request, empty when no endpoint could
+    Drill,
+    /// Every endpoint of the current tier was down, so a less preferred tier took over
+    TierFailover,
+    /// A more preferred tier has a healthy endpoint again
+    TierFailback,
+}
+
+impl std::fmt::Display for SwitchReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SwitchReason::AutoRaceWin => write!(f, "auto"),
+            SwitchReason::Failover => write!(f, "failover"),
+            SwitchReason::ManualSwitch => write!(f, "manual"),
+            SwitchReason::RequestFallback => write!(f, "fallback"),
+            SwitchReason::TagFilter => write!(f, "tag filter"),
+            SwitchReason::ConfigReload => write!(f, "config reload"),
+            SwitchReason::Maintenance => write!(f, "maintenance"),
+            SwitchReason::Drill => write!(f, "drill"),
+            SwitchReason::TierFailover => write!(f, "tier failover"),
+            SwitchReason::TierFailback => write!(f, "tier failback"),
+        }
+    }
+}
+
+/// Why a tracked connection ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompletionReason {
+    /// The response body was forwarded in full
+    Completed,
+    /// The client went away before the body ended
+    ClientDisconnected,
+    /// The upstream failed mid-body, or every endpoint tried failed to answer
+    UpstreamError,
+    /// No response headers arrived within the request timeout
+    RequestTimeout,
+    /// The body was still streaming when the total timeout ran out
+    BodyTimeout,
+    /// No body data arrived within the stream idle timeout
+    StreamStalled,
+    /// No activity for a while, most likely an interrupted client
+    Abandoned,
+    /// Still open past the stale connection limit
+    Stale,
+    /// Closed by the proxy shutting down
+    Shutdown,
+}
+
+impl std::fmt::Display for CompletionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompletionReason::Completed => write!(f, "completed"),
+            CompletionReason::ClientDisconnected => write!(f, "client disconnected"),
+            CompletionReason::UpstreamError => write!(f, "upstream error"),
+            CompletionReason::RequestTimeout => write!(f, "request timeout"),
+            CompletionReason::BodyTimeout => write!(f, "body timeout"),
+            CompletionReason::StreamStalled => write!(f, "stream stalled"),
+            CompletionReason::Abandoned => write!(f, "abandoned"),
+            CompletionReason::Stale => write!(f, "stale"),
+            CompletionReason::Shutdown => write!(f, "shutdown"),
+        }
+    }
+}
+
+/// A single entry in the endpoint switch history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwitchRecord {
+    pub timestamp: DateTime<Utc>,
+    pub from: String,
+    pub to: String,
+    pub from_latency: Option<u64>,
+    pub to_latency: Option<u64>,
+    pub reason: SwitchReason,
+    /// Manually selected although the endpoint was down, past the dashboard's
+    /// confirmation
+    #[serde(default)]
+    pub forced: bool,
+}
+
+/// A threshold alert from `[alerts]`, for one endpoint or the whole proxy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alert {
+    /// Name of the rule
+    pub rule: String,
+    pub metric: AlertMetric,
+    /// Endpoint id, `None` for `no_healthy_endpoint`
+    pub endpoint: Option<String>,
+    /// Latest value: percent for `error_rate`, milliseconds for `latency_p90`,
+    /// healthy endpoints for `no_healthy_endpoint`; `None` without data
+    pub value: Option<f64>,
+    pub threshold: Option<f64>,
+    pub for_seconds: u64,
+    /// When the condition started to hold
+    pub since: DateTime<Utc>,
+}
+
+impl Alert {
+    /// The condition in words, for logs
+    pub fn condition(&self) -> String {
+        let value = self.value.unwrap_or_default();
+        let threshold = self.threshold.unwrap_or_default();
+        match self.metric {
+            AlertMetric::ErrorRate => format!(
+                "error rate {value:.1}% (above {threshold}% over {}s)",
+                self.for_seconds
+            ),
+            AlertMetric::LatencyP90 => format!(
+                "p90 latency {value:.0}ms (above {threshold}ms over {}s)",
+                self.for_seconds
+            ),
+            AlertMetric::NoHealthyEndpoint => {
+                format!("no healthy endpoint for {}s", self.for_seconds)
+            }
+        }
+    }
+}
+
+/// Outcome of one health check cycle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthCycleSummary {
+    /// Cycles completed since startup, this one included; a gap between two
+    /// summaries means events were dropped
+    pub sequence: u64,
+    pub checked: usize,
+    pub healthy: usize,
+    pub failed: usize,
+    /// Fastest healthy endpoint of the cycle
+    pub best_endpoint: Option<String>,
+    pub best_latency_ms: Option<u64>,
+    /// Current endpoint once the cycle finished
+    pub current_endpoint: String,
+    pub current_latency_ms: Option<u64>,
+    /// Endpoint the cycle switched to, `None` when the current one was kept
+    pub switched_to: Option<String>,
+    pub switched_from: Option<String>,
+    pub duration_ms: u64,
+    pub finished_at: DateTime<Utc>,
+}
+
 /// Events that can occur in the proxy system
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -30,7 +192,22 @@ pub enum ProxyEvent {
     /// A new connection has started
     ConnectionStarted(ActiveConnection),
     /// A connection has completed
-    ConnectionCompleted(String), // connection_id
+    ConnectionCompleted {
+        id: ConnectionId,
+        reason: CompletionReason,
+        /// Time spent in each status, `None` for connections the proxy
+        /// cleaned up itself
+        phases: Option<PhaseDurations>,
+        /// Where the request's time went, `None` for connections the proxy
+        /// cleaned up itself
+        timings: Option<RequestTimings>,
+    },
+    /// Response body progress, sent at most once per second per connection
+    ConnectionProgress {
+        id: ConnectionId,
+        bytes_received: u64,
+        last_chunk_at: Instant,
+    },
     /// A new request has been received (for load tracking)
     RequestReceived {
         endpoint: String,
@@ -48,6 +225,8 @@ pub enum ProxyEvent {
         next_check_time: Instant, // 真正的下次检查时间
         load_level: LoadLevel,
         active_connections: u32,
+        /// What `actual_interval` was calculated from, `None` without dynamic scaling
+        interval_decision: Option<IntervalDecision>,
     },
     /// Health check cycle is actively running
     HealthCheckRunning {
@@ -56,14 +235,24 @@ pub enum ProxyEvent {
     },
     /// Health check cycle completed
     HealthCheckCompleted { duration: Duration },
+    /// What a finished health check cycle found, sent after its results are applied
+    HealthCycleSummary(HealthCycleSummary),
     /// Health check completed for an endpoint  
     HealthUpdate(EndpointStatus),
     /// Endpoint switch occurred
     EndpointSwitch {
         from: String,
         to: String,
-        from_latency: u64,
-        to_latency: u64,
+        from_latency: Option<u64>,
+        to_latency: Option<u64>,
+        reason: SwitchReason,
+    },
+    /// The endpoint left by a switch finished draining; `remaining` connections
+    /// were still active when the graceful switch timeout ended the drain
+    EndpointDrained {
+        endpoint: String,
+        remaining: u32,
+        elapsed: Duration,
     },
     /// Selection mode changed
     SelectionModeChanged { mode: SelectionMode },
@@ -76,10 +265,24 @@ pub enum ProxyEvent {
     ServerStarted { port: u16 },
     /// Configuration loaded
     ConfigLoaded { endpoint_count: usize },
+    /// The config file was read again and applied; `current` is the current
+    /// endpoint's URL afterwards
+    ConfigReloaded { diff: ConfigDiff, current: String },
+    /// The config file could not be reloaded; the running config is unchanged
+    ConfigReloadFailed { error: String },
     /// System health monitoring paused
     SystemPaused,
     /// System health monitoring resumed
     SystemResumed,
+    /// Automatic switching was frozen or unfrozen; health checks continue
+    SwitchingFrozen { frozen: bool },
+    /// A full health check cycle found every endpoint down, or not
+    AllEndpointsDown { down: bool },
+    /// Every endpoint of `group` is down after a full health check cycle, or
+    /// one recovered when `down` is false; sent once per change, not per endpoint
+    GroupDown { group: String, down: bool },
+    /// A threshold alert fired, or resolved when `firing` is false
+    AlertChanged { alert: Alert, firing: bool },
     /// Manual refresh/health check triggered
     ManualRefreshTriggered,
 }
@@ -87,21 +290,94 @@ pub enum ProxyEvent {
 /// Represents an active connection being tracked
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActiveConnection {
-    pub id: String,
+    pub id: ConnectionId,
+    /// URL the request was sent to
     pub endpoint: String,
+    /// Id of the endpoint at `endpoint`, which the tracker counts it under
+    pub endpoint_id: EndpointId,
     pub start_time: DateTime<Utc>,
     pub status: ConnectionStatus,
     pub request_info: Option<RequestInfo>,
+    /// Response body bytes forwarded so far
+    #[serde(default)]
+    pub bytes_received: u64,
+    /// When the connection entered each status, in order
+    #[serde(default)]
+    pub transitions: Vec<(ConnectionStatus, DateTime<Utc>)>,
 }
 
-/// Status of an active connection
+/// What is kept of a connection once it completes, for the recent requests
+/// history. Never holds headers or bodies.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletedConnection {
+    pub id: ConnectionId,
+    /// Endpoint that served it, after any fallback
+    pub endpoint: String,
+    pub method: Option<String>,
+    /// Request path without the query string
+    pub path: Option<String>,
+    /// Status returned to the client, `None` when the proxy gave up on the
+    /// connection without one
+    pub status: Option<u16>,
+    pub reason: CompletionReason,
+    pub start_time: DateTime<Utc>,
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub phases: PhaseDurations,
+}
+
+/// Status of an active connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ConnectionStatus {
+    /// Tracked, first upstream attempt not sent yet
     Connecting,
+    /// Waiting for response headers, across retries and fallback endpoints
     Processing,
+    /// Streaming the response body to the client
     Finishing,
 }
 
+/// Time a connection spent in each [`ConnectionStatus`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseDurations {
+    pub connecting_ms: u64,
+    pub processing_ms: u64,
+    pub finishing_ms: u64,
+}
+
+impl PhaseDurations {
+    fn add(&mut self, status: ConnectionStatus, ms: u64) {
+        let phase = match status {
+            ConnectionStatus::Connecting => &mut self.connecting_ms,
+            ConnectionStatus::Processing => &mut self.processing_ms,
+            ConnectionStatus::Finishing => &mut self.finishing_ms,
+        };
+        *phase += ms;
+    }
+}
+
+/// Where a proxied request's time went, in microseconds. The proxy's own
+/// share is [`RequestTimings::overhead_us`]; the rest is the upstream's.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestTimings {
+    /// Accepting the request to dispatching it upstream: buffering the body
+    /// and reading the shared state
+    pub preprocessing_us: u64,
+    /// Dispatch to the upstream's response headers, across retries and fallback
+    pub upstream_headers_us: u64,
+    /// Response headers to the end of the upstream body
+    pub body_transfer_us: u64,
+    /// End of the upstream body to the response handed to the client connection
+    pub response_write_us: u64,
+}
+
+impl RequestTimings {
+    /// Time spent in the proxy rather than waiting for the upstream
+    pub fn overhead_us(&self) -> u64 {
+        self.preprocessing_us + self.response_write_us
+    }
+}
+
 /// Optional request information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequestInfo {
@@ -111,13 +387,22 @@ pub struct RequestInfo {
 }
 
 impl ActiveConnection {
-    pub fn new(id: String, endpoint: String) -> Self {
+    pub fn new(
+        id: ConnectionId,
+        endpoint: String,
+        endpoint_id: EndpointId,
+        request_info: Option<RequestInfo>,
+    ) -> Self {
+        let start_time = Utc::now();
         Self {
             id,
             endpoint,
-            start_time: Utc::now(),
+            endpoint_id,
+            start_time,
             status: ConnectionStatus::Connecting,
-            request_info: None,
+            request_info,
+            bytes_received: 0,
+            transitions: vec![(ConnectionStatus::Connecting, start_time)],
         }
     }
 
@@ -127,8 +412,37 @@ impl ActiveConnection {
     }
 
     pub fn update_status(&mut self, status: ConnectionStatus) {
+        if self.status != status {
+            self.transitions.push((status, Utc::now()));
+        }
         self.status = status;
     }
+
+    /// Time spent in each status so far
+    pub fn phase_durations(&self) -> PhaseDurations {
+        let now = Utc::now();
+        let mut phases = PhaseDurations::default();
+        for (i, (status, entered)) in self.transitions.iter().enumerate() {
+            let left = self.transitions.get(i + 1).map_or(now, |(_, next)| *next);
+            phases.add(*status, (left - *entered).num_milliseconds().max(0) as u64);
+        }
+        phases
+    }
+
+    /// Summary of this connection as it completes
+    pub fn completed(&self, reason: CompletionReason, status: Option<u16>) -> CompletedConnection {
+        CompletedConnection {
+            id: self.id.clone(),
+            endpoint: self.endpoint.clone(),
+            method: self.request_info.as_ref().map(|info| info.method.clone()),
+            path: self.request_info.as_ref().map(|info| info.path.clone()),
+            status,
+            reason,
+            start_time: self.start_time,
+            duration_ms: self.duration(),
+            phases: self.phase_durations(),
+        }
+    }
 }
 
 impl std::fmt::Display for ConnectionStatus {
@@ -0,0 +1,271 @@
+//! Health of each group as a whole, summed from its endpoints.
+//!
+//! With several endpoints per group, whether a group can serve at all matters
+//! more than any single endpoint. [`summarize`] only reads what it is given,
+//! so `/status` and anything else can share it.
+
+use crate::endpoint::{EndpointId, EndpointIndex};
+use crate::health::{CheckState, EndpointStatus};
+use crate::metrics::CounterSnapshot;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// One group's endpoints taken together
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GroupHealth {
+    pub name: String,
+    pub endpoints: usize,
+    /// Passed their last check and not in maintenance
+    pub healthy: usize,
+    /// Not checked yet or being checked
+    pub pending: usize,
+    /// Lowest latency among the healthy endpoints, with its endpoint id
+    pub best_latency_ms: Option<u64>,
+    pub best_endpoint: Option<String>,
+    pub active_connections: u32,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Estimated cost of requests and health checks in micro-dollars
+    pub cost_micros: u64,
+    /// Every endpoint failed or is in maintenance, nothing is pending
+    pub down: bool,
+}
+
+/// Summary of every group in config order. `connections` is keyed by endpoint
+/// URL, `counters` by endpoint id; endpoints missing from either count zero.
+pub fn summarize(
+    index: &EndpointIndex,
+    statuses: &HashMap<EndpointId, EndpointStatus>,
+    connections: &HashMap<EndpointId, u32>,
+    counters: &BTreeMap<String, CounterSnapshot>,
+) -> Vec<GroupHealth> {
+    let mut groups: Vec<GroupHealth> = Vec::new();
+    for endpoint in index.iter() {
+        if groups
+            .last()
+            .is_none_or(|group| group.name != endpoint.group)
+        {
+            groups.push(GroupHealth {
+                name: endpoint.group.clone(),
+                ..GroupHealth::default()
+            });
+        }
+        let Some(group) = groups.last_mut() else {
+            continue;
+        };
+        group.endpoints += 1;
+
+        let status = statuses.get(&endpoint.id);
+        let in_maintenance = status.is_some_and(|status| status.maintenance_until.is_some());
+        match status.map(|status| status.state) {
+            _ if in_maintenance => {}
+            Some(CheckState::Healthy) => {
+                group.healthy += 1;
+                let latency = status.and_then(|status| status.latency);
+                if latency
+                    .is_some_and(|latency| group.best_latency_ms.is_none_or(|best| latency < best))
+                {
+                    group.best_latency_ms = latency;
+                    group.best_endpoint = Some(endpoint.id.to_string());
+                }
+            }
+            None | Some(CheckState::Unchecked | CheckState::Checking) => group.pending += 1,
+            Some(CheckState::Unhealthy) => {}
+        }
+
+        group.active_connections += connections.get(&endpoint.id).copied().unwrap_or(0);
+        if let Some(counters) = counters.get(endpoint.id.as_str()) {
+            group.input_tokens += counters.input_tokens;
+            group.output_tokens += counters.output_tokens;
+            group.cost_micros += counters.request_cost_micros + counters.health_check_cost_micros;
+        }
+    }
+    for group in &mut groups {
+        group.down = group.healthy == 0 && group.pending == 0;
+    }
+    groups
+}
+
+/// Groups of `groups` whose down state is not the one in `was_down`, with
+/// whether they are down now. Each outage is thus reported once for the group
+/// rather than once per endpoint, and a group that recovers once.
+pub fn down_changes(was_down: &BTreeSet<String>, groups: &[GroupHealth]) -> Vec<(String, bool)> {
+    groups
+        .iter()
+        .filter(|group| group.down != was_down.contains(&group.name))
+        .map(|group| (group.name.clone(), group.down))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_support::{config, TWO_GROUPS};
+
+    /// `TWO_GROUPS` with `gamma` and `delta` joining `alpha` in the main group
+    fn three_in_main() -> Config {
+        config(&TWO_GROUPS.replace(
+            r#"endpoints = [{ url = "http://127.0.0.1:19001", name = "alpha" }]"#,
+            r#"endpoints = [{ url = "http://127.0.0.1:19001", name = "alpha" }, { url = "http://127.0.0.1:19003", name = "gamma" }, { url = "http://127.0.0.1:19004", name = "delta" }]"#,
+        ))
+    }
+
+    fn healthy(latency: u64) -> EndpointStatus {
+        EndpointStatus::new_available(String::new(), latency, None)
+    }
+
+    fn unhealthy() -> EndpointStatus {
+        EndpointStatus::new_unavailable(String::new(), "down".to_string())
+    }
+
+    fn in_maintenance(mut status: EndpointStatus) -> EndpointStatus {
+        status.maintenance_until = Some(chrono::Utc::now());
+        status
+    }
+
+    fn statuses(of: Vec<(&str, EndpointStatus)>) -> HashMap<EndpointId, EndpointStatus> {
+        of.into_iter()
+            .map(|(id, status)| (EndpointId::new(id), status))
+            .collect()
+    }
+
+    fn summarize_statuses(
+        config: &Config,
+        statuses: &HashMap<EndpointId, EndpointStatus>,
+    ) -> Vec<GroupHealth> {
+        summarize(
+            config.endpoint_index(),
+            statuses,
+            &HashMap::new(),
+            &BTreeMap::new(),
+        )
+    }
+
+    #[test]
+    fn groups_are_summarized_in_config_order() {
+        let config = three_in_main();
+        let statuses = statuses(vec![
+            ("alpha", healthy(400)),
+            ("gamma", healthy(150)),
+            ("delta", unhealthy()),
+            ("beta", healthy(90)),
+        ]);
+        let connections = HashMap::from([
+            (EndpointId::new("alpha"), 2),
+            (EndpointId::new("gamma"), 3),
+            (EndpointId::new("beta"), 1),
+        ]);
+        let counters = BTreeMap::from([
+            (
+                "alpha".to_string(),
+                CounterSnapshot {
+                    input_tokens: 100,
+                    output_tokens: 10,
+                    request_cost_micros: 500,
+                    health_check_cost_micros: 5,
+                    ..CounterSnapshot::default()
+                },
+            ),
+            (
+                "delta".to_string(),
+                CounterSnapshot {
+                    input_tokens: 20,
+                    output_tokens: 2,
+                    request_cost_micros: 50,
+                    ..CounterSnapshot::default()
+                },
+            ),
+        ]);
+
+        let groups = summarize(config.endpoint_index(), &statuses, &connections, &counters);
+        assert_eq!(
+            groups[0],
+            GroupHealth {
+                name: "main".to_string(),
+                endpoints: 3,
+                healthy: 2,
+                pending: 0,
+                best_latency_ms: Some(150),
+                best_endpoint: Some("gamma".to_string()),
+                active_connections: 5,
+                input_tokens: 120,
+                output_tokens: 12,
+                cost_micros: 555,
+                down: false,
+            }
+        );
+        assert_eq!(groups[1].name, "backup");
+        assert_eq!((groups[1].healthy, groups[1].endpoints), (1, 1));
+        assert_eq!(groups[1].best_endpoint.as_deref(), Some("beta"));
+        assert_eq!(groups[1].active_connections, 1);
+        assert_eq!(groups[1].input_tokens, 0);
+        assert_eq!(groups.len(), 2);
+    }
+
+    #[test]
+    fn endpoints_in_maintenance_count_as_neither_healthy_nor_pending() {
+        let config = three_in_main();
+        let statuses = statuses(vec![
+            ("alpha", in_maintenance(healthy(100))),
+            ("gamma", unhealthy()),
+            (
+                "delta",
+                in_maintenance(EndpointStatus::new_unchecked(String::new())),
+            ),
+        ]);
+        let main = &summarize_statuses(&config, &statuses)[0];
+        assert_eq!((main.healthy, main.pending), (0, 0));
+        assert_eq!(main.best_latency_ms, None);
+        assert!(main.down);
+    }
+
+    #[test]
+    fn group_is_not_down_while_an_endpoint_is_pending() {
+        let config = three_in_main();
+        let mut statuses = statuses(vec![
+            ("alpha", unhealthy()),
+            ("gamma", unhealthy()),
+            ("delta", EndpointStatus::new_checking(String::new())),
+        ]);
+        let main = &summarize_statuses(&config, &statuses)[0];
+        assert_eq!((main.healthy, main.pending), (0, 1));
+        assert!(!main.down);
+
+        // An endpoint without a status has not been checked either
+        statuses.remove(&EndpointId::new("delta"));
+        assert!(!summarize_statuses(&config, &statuses)[0].down);
+
+        statuses.insert(EndpointId::new("delta"), unhealthy());
+        assert!(summarize_statuses(&config, &statuses)[0].down);
+    }
+
+    #[test]
+    fn a_group_going_down_is_reported_once_until_it_recovers() {
+        let config = three_in_main();
+        let all_down = statuses(vec![
+            ("alpha", unhealthy()),
+            ("gamma", unhealthy()),
+            ("delta", unhealthy()),
+            ("beta", healthy(100)),
+        ]);
+        let mut was_down = BTreeSet::new();
+
+        let groups = summarize_statuses(&config, &all_down);
+        assert_eq!(
+            down_changes(&was_down, &groups),
+            [("main".to_string(), true)]
+        );
+        was_down.insert("main".to_string());
+        // Still down after the next cycle: nothing new to report
+        assert_eq!(down_changes(&was_down, &groups), []);
+
+        let mut recovered = all_down.clone();
+        recovered.insert(EndpointId::new("gamma"), healthy(200));
+        let groups = summarize_statuses(&config, &recovered);
+        assert_eq!(
+            down_changes(&was_down, &groups),
+            [("main".to_string(), false)]
+        );
+    }
+}
@@ -1,10 +1,17 @@
-use crate::config::Config;
+use crate::config::{Config, HealthCheckMeasure};
+use crate::health_client::{request_phases, HealthCheckClient};
 use crate::logging::*;
-use chrono::{DateTime, Utc};
+use crate::token_check::{send_token_request, TOKEN_CHECK_PATH};
+use chrono::{DateTime, Local, Timelike, Utc};
+use hyper::StatusCode;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::VecDeque;
-use std::process::{Command, Stdio};
-use std::time::Instant;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
 
 /// Health check implementation optimized for minimal token consumption.
 ///
@@ -12,22 +19,61 @@ use std::time::Instant;
 /// - Input: "<don't-reply>" - explicit instruction to not respond (2 tokens)
 /// - Tools: Completely disabled via --disallowed-tools "*" (saves potential tool call overhead)
 /// - System prompt: Forces ultra-brief response "Respond with only 'ok'. Be extremely brief."
-/// - Model: Using cheapest model (claude-3-5-haiku-20241022)
+/// - Model: Using cheapest model (claude-3-5-haiku-20241022) unless `health_check.model` says otherwise
 /// - Output: Expected single word "ok" (~1 token)
 ///
 /// Total cost per health check: ~5-10 tokens (2 input + 1 output + system prompt overhead)
 /// This is a 80-90% reduction from typical interactive usage.
+///
+/// In `http` mode a check is a `count_tokens` request instead: no CLI, 0 tokens,
+/// but it only tests connectivity and auth.
 // Constants for health check
-const FAILED_ENDPOINT_LATENCY: u64 = 999_999;
 const DEFAULT_LATENCY_HISTORY_SIZE: usize = 20;
 
+/// Weight of a new measurement in its hour's moving average
+const HOURLY_PROFILE_ALPHA: f64 = 0.2;
+
 // Ultra-minimal health check prompt for token optimization
-const MINIMAL_HEALTH_PROMPT: &str = "<don't-reply>";
+pub(crate) const MINIMAL_HEALTH_PROMPT: &str = "<don't-reply>";
+
+/// Lowercase CLI output fragments that mean the endpoint rejected the token
+const AUTH_FAILURE_MARKERS: &[&str] = &[
+    "api error: 401",
+    "401 unauthorized",
+    "authentication_error",
+    "invalid api key",
+    "invalid bearer token",
+    "oauth token has expired",
+];
+
+/// Cheapest model, used for health checks and the smoke test request
+pub(crate) const HEALTH_CHECK_MODEL: &str = "claude-3-5-haiku-20241022";
+
+/// System prompt forcing the shortest possible reply
+pub(crate) const HEALTH_CHECK_SYSTEM_PROMPT: &str = "Respond with only 'ok'. Be extremely brief.";
+
+/// What a health check asks an endpoint, see [`Config::health_probe`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HealthProbe {
+    pub model: String,
+    pub prompt: String,
+    pub system_prompt: String,
+}
 
-// Alternative: Pure HTTP health check (0 tokens) - uncomment to use
-// This bypasses Claude entirely and just tests HTTP connectivity + auth
-#[allow(dead_code)]
-const USE_HTTP_HEALTH_CHECK: bool = false;
+/// Where the time of an HTTP check went, for display only: decisions use the
+/// total latency
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyPhases {
+    /// DNS lookup, `None` when the check reused a connection
+    pub resolve_ms: Option<u64>,
+    /// TCP connect, `None` when the check reused a connection
+    pub connect_ms: Option<u64>,
+    /// TLS handshake, `None` when the check reused a connection or the
+    /// endpoint is plain HTTP
+    pub tls_ms: Option<u64>,
+    /// From the connection being ready to the response headers
+    pub first_byte_ms: u64,
+}
 
 /// Represents a single latency measurement with timestamp
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +81,9 @@ pub struct LatencyMeasurement {
     pub timestamp: DateTime<Utc>,
     pub latency: Option<u64>, // None indicates failure
     pub error: Option<String>,
+    /// Phases of a passing HTTP check, `None` for CLI checks
+    #[serde(default)]
+    pub phases: Option<LatencyPhases>,
 }
 
 /// Rolling history of latency measurements for sparkline rendering
@@ -63,6 +112,7 @@ impl LatencyHistory {
             timestamp: Utc::now(),
             latency,
             error,
+            phases: None,
         };
 
         self.measurements.push_back(measurement);
@@ -73,19 +123,29 @@ impl LatencyHistory {
         }
     }
 
+    /// Attach `phases` to the most recent measurement
+    pub fn set_latest_phases(&mut self, phases: Option<LatencyPhases>) {
+        if let Some(latest) = self.measurements.back_mut() {
+            latest.phases = phases;
+        }
+    }
+
     /// Get all measurements in chronological order (oldest first)
     pub fn get_measurements(&self) -> &VecDeque<LatencyMeasurement> {
         &self.measurements
     }
 
     /// Get the most recent measurement
-    #[allow(dead_code)]
     pub fn get_latest(&self) -> Option<&LatencyMeasurement> {
         self.measurements.back()
     }
 
+    /// Get the latency of the most recent successful measurement
+    pub fn last_successful_latency(&self) -> Option<u64> {
+        self.measurements.iter().rev().find_map(|m| m.latency)
+    }
+
     /// Calculate average latency (excluding failures)
-    #[allow(dead_code)]
     pub fn average_latency(&self) -> Option<u64> {
         let valid_latencies: Vec<u64> =
             self.measurements.iter().filter_map(|m| m.latency).collect();
@@ -115,78 +175,223 @@ impl Default for LatencyHistory {
     }
 }
 
+/// Exponential moving average of successful latencies for each local hour of
+/// the day, so endpoints that are only fast at certain times can be told apart
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HourlyLatencyProfile {
+    // Boxed to keep `EndpointStatus` small inside events
+    buckets: Box<[Option<f64>; 24]>,
+}
+
+impl HourlyLatencyProfile {
+    /// Fold a successful latency into the bucket of the hour `at` falls in
+    pub fn record(&mut self, at: DateTime<Local>, latency: u64) {
+        let bucket = &mut self.buckets[at.hour() as usize];
+        let latency = latency as f64;
+        *bucket = Some(match *bucket {
+            Some(average) => average + HOURLY_PROFILE_ALPHA * (latency - average),
+            None => latency,
+        });
+    }
+
+    /// Typical latency in the hour `at` falls in, `None` before any measurement
+    pub fn latency_at(&self, at: DateTime<Local>) -> Option<u64> {
+        self.buckets[at.hour() as usize].map(|average| average.round() as u64)
+    }
+
+    /// Typical latency for each hour from 0 to 23
+    pub fn hours(&self) -> [Option<u64>; 24] {
+        self.buckets
+            .map(|bucket| bucket.map(|average| average.round() as u64))
+    }
+}
+
+/// Where an endpoint's health stands, from its checks and live requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckState {
+    /// Not checked since the proxy started or the endpoint was added
+    Unchecked,
+    /// A check is running, the earlier verdict no longer counts
+    Checking,
+    /// The last check passed
+    Healthy,
+    /// The last check, or a live request since, failed
+    Unhealthy,
+}
+
+impl CheckState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CheckState::Unchecked => "unchecked",
+            CheckState::Checking => "checking",
+            CheckState::Healthy => "healthy",
+            CheckState::Unhealthy => "unhealthy",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EndpointStatus {
     pub endpoint: String,
-    pub latency: u64,
-    pub available: bool,
+    /// Latency of the last check, `None` when it failed or hasn't completed
+    #[serde(with = "latency_compat")]
+    pub latency: Option<u64>,
+    pub state: CheckState,
+    /// Why the endpoint is unhealthy or in maintenance
     pub error: Option<String>,
     pub last_check: DateTime<Utc>,
+    /// Time until the health check printed its first output, `None` when it failed
+    #[serde(default)]
+    pub ttfb_ms: Option<u64>,
     /// Latency history for sparkline rendering
     #[serde(default)]
     pub latency_history: LatencyHistory,
+    /// Typical latency by hour of day, built from the same measurements
+    #[serde(default)]
+    pub hourly_profile: HourlyLatencyProfile,
+    /// Latency of the last HTTP check that had to open a connection
+    #[serde(default)]
+    pub cold_latency_ms: Option<u64>,
+    /// Latency of the last HTTP check that reused an open connection
+    #[serde(default)]
+    pub warm_latency_ms: Option<u64>,
+    /// The last check failed because the endpoint rejected the group's token
+    #[serde(default)]
+    pub auth_failed: bool,
+    /// End of the maintenance window the endpoint is in; it is neither checked
+    /// nor used until then
+    #[serde(default)]
+    pub maintenance_until: Option<DateTime<Utc>>,
 }
 
 impl EndpointStatus {
+    /// An endpoint no check has looked at yet
+    pub fn new_unchecked(endpoint: String) -> Self {
+        Self {
+            state: CheckState::Unchecked,
+            ..Self::new_checking(endpoint)
+        }
+    }
+
     pub fn new_unavailable(endpoint: String, error: String) -> Self {
         let mut history = LatencyHistory::new_default();
         history.add_measurement(None, Some(error.clone()));
 
         Self {
             endpoint,
-            latency: FAILED_ENDPOINT_LATENCY,
-            available: false,
+            latency: None,
+            state: CheckState::Unhealthy,
             error: Some(error),
             last_check: Utc::now(),
+            ttfb_ms: None,
             latency_history: history,
+            hourly_profile: HourlyLatencyProfile::default(),
+            cold_latency_ms: None,
+            warm_latency_ms: None,
+            auth_failed: false,
+            maintenance_until: None,
         }
     }
 
     pub fn new_checking(endpoint: String) -> Self {
         Self {
             endpoint,
-            latency: 0,
-            available: false,
-            error: None, // 关键：no error表示checking状态
+            latency: None,
+            state: CheckState::Checking,
+            error: None,
             last_check: Utc::now(),
+            ttfb_ms: None,
             latency_history: LatencyHistory::new_default(),
+            hourly_profile: HourlyLatencyProfile::default(),
+            cold_latency_ms: None,
+            warm_latency_ms: None,
+            auth_failed: false,
+            maintenance_until: None,
         }
     }
 
-    pub fn new_available(endpoint: String, latency: u64) -> Self {
+    pub fn new_available(endpoint: String, latency: u64, ttfb_ms: Option<u64>) -> Self {
         let mut history = LatencyHistory::new_default();
         history.add_measurement(Some(latency), None);
 
         Self {
             endpoint,
-            latency,
-            available: true,
+            latency: Some(latency),
+            state: CheckState::Healthy,
             error: None,
             last_check: Utc::now(),
+            ttfb_ms,
             latency_history: history,
+            hourly_profile: HourlyLatencyProfile::default(),
+            cold_latency_ms: None,
+            warm_latency_ms: None,
+            auth_failed: false,
+            maintenance_until: None,
         }
     }
 
+    /// The last check passed and nothing failed since
+    pub fn is_available(&self) -> bool {
+        self.state == CheckState::Healthy
+    }
+
+    /// Record a failure outside a check, e.g. of a live request
+    pub fn mark_unhealthy(&mut self, error: String) {
+        self.state = CheckState::Unhealthy;
+        self.error = Some(error);
+    }
+
+    /// Last latency measured by a successful health check, if any
+    pub fn last_known_latency(&self) -> Option<u64> {
+        if self.is_available() {
+            self.latency
+        } else {
+            self.latency_history.last_successful_latency()
+        }
+    }
+
+    /// Latency of the last HTTP check of the kind `measure` names, or any
+    /// check's latency until one of that kind passed
+    pub fn measured_latency(&self, measure: HealthCheckMeasure) -> Option<u64> {
+        let latency = self.latency?;
+        let measured = match measure {
+            HealthCheckMeasure::Warm => self.warm_latency_ms,
+            HealthCheckMeasure::Cold => self.cold_latency_ms,
+        };
+        Some(measured.unwrap_or(latency))
+    }
+
     /// Update the status with new health check results
     pub fn update_with_check_result(&mut self, latency: Option<u64>, error: Option<String>) {
         self.last_check = Utc::now();
 
-        if let Some(lat) = latency {
-            self.latency = lat;
-            self.available = true;
+        self.latency = latency;
+        if latency.is_some() {
+            self.state = CheckState::Healthy;
             self.error = None;
         } else {
-            self.latency = 999999;
-            self.available = false;
+            self.state = CheckState::Unhealthy;
             self.error = error.clone();
         }
 
         // Add to history
+        if let Some(latency) = latency {
+            self.hourly_profile.record(Local::now(), latency);
+        }
         self.latency_history.add_measurement(latency, error);
     }
 }
 
-pub fn check_endpoint_health(endpoint: &str, config: &Config, auth_token: &str) -> EndpointStatus {
+/// Check `endpoint` by running the Claude CLI at `claude_binary` against it,
+/// asking what `probe` holds
+pub fn check_endpoint_health(
+    endpoint: &str,
+    config: &Config,
+    auth_token: &str,
+    claude_binary: &Path,
+    probe: &HealthProbe,
+) -> EndpointStatus {
     let start = Instant::now();
 
     log_health_start(endpoint);
@@ -199,20 +404,21 @@ pub fn check_endpoint_health(endpoint: &str, config: &Config, auth_token: &str)
 
     // Spawn a thread to run the command
     let endpoint_clone = endpoint.to_string();
-    let claude_path = config.health_check.claude_binary_path.clone();
+    let claude_path = claude_binary.to_path_buf();
     let auth_token_clone = auth_token.to_string();
+    let probe = probe.clone();
 
     std::thread::spawn(move || {
         let result = Command::new(&claude_path)
             .args([
                 "-p",
-                MINIMAL_HEALTH_PROMPT, // 最短提示要求不回复
+                &probe.prompt, // Shortest prompt, asking for no reply
                 "--model",
-                "claude-3-5-haiku-20241022", // 最便宜模型
+                &probe.model, // Cheapest model
                 "--disallowed-tools",
-                "*", // 禁用所有工具 (关键优化)
+                "*", // Disable every tool (the main saving)
                 "--append-system-prompt",
-                "Respond with only 'ok'. Be extremely brief.", // 强制简短回复
+                &probe.system_prompt, // Force a short reply
             ])
             .env("ANTHROPIC_BASE_URL", &endpoint_clone)
             .env("ANTHROPIC_AUTH_TOKEN", &auth_token_clone)
@@ -221,7 +427,8 @@ pub fn check_endpoint_health(endpoint: &str, config: &Config, auth_token: &str)
             .current_dir("/tmp")
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
-            .output();
+            .spawn()
+            .and_then(|child| read_check_output(child, start));
         let _ = tx.send(result);
     });
 
@@ -248,18 +455,37 @@ pub fn check_endpoint_health(endpoint: &str, config: &Config, auth_token: &str)
 
     match result {
         Ok(output) => {
+            let auth_failed = is_auth_failure(&output.stdout) || is_auth_failure(&output.stderr);
             if output.status.success() && !output.stdout.is_empty() {
+                if let Err(error_msg) =
+                    validate_output(&output.stdout, &config.health_check.expected_output)
+                {
+                    log_health_failed(endpoint, &error_msg);
+                    let mut status =
+                        EndpointStatus::new_unavailable(endpoint.to_string(), error_msg);
+                    status.auth_failed = auth_failed;
+                    return status;
+                }
                 log_health_success(endpoint, latency);
-                EndpointStatus::new_available(endpoint.to_string(), latency)
+                let ttfb_ms = output.first_byte.map(|d| d.as_millis() as u64);
+                EndpointStatus::new_available(endpoint.to_string(), latency, ttfb_ms)
             } else {
-                let error_msg = if output.stderr.is_empty() {
+                // The CLI prints some errors, like a rejected key, on stdout
+                let error_output = if output.stderr.is_empty() {
+                    &output.stdout
+                } else {
+                    &output.stderr
+                };
+                let error_msg = if error_output.is_empty() {
                     "No output from claude command".to_string()
                 } else {
-                    String::from_utf8_lossy(&output.stderr).trim().to_string()
+                    String::from_utf8_lossy(error_output).trim().to_string()
                 };
 
                 log_health_failed(endpoint, &error_msg);
-                EndpointStatus::new_unavailable(endpoint.to_string(), error_msg)
+                let mut status = EndpointStatus::new_unavailable(endpoint.to_string(), error_msg);
+                status.auth_failed = auth_failed;
+                status
             }
         }
         Err(e) => {
@@ -270,6 +496,140 @@ pub fn check_endpoint_health(endpoint: &str, config: &Config, auth_token: &str)
     }
 }
 
+/// Check `endpoint` with a `count_tokens` request over `client` asking what
+/// `probe` holds, for the HTTP health check mode. Any 2xx answer is healthy;
+/// 401 and 403 mean the token was rejected. The latency is also recorded as
+/// cold or warm, by whether the request had to open a connection, and split
+/// into its phases on the check's measurement.
+pub async fn check_endpoint_http(
+    client: &HealthCheckClient,
+    endpoint: &str,
+    config: &Config,
+    auth_token: &str,
+    probe: &HealthProbe,
+) -> EndpointStatus {
+    let start = Instant::now();
+
+    log_health_start(endpoint);
+
+    let timeout = Duration::from_secs(config.health_check.timeout_seconds);
+    let result = send_token_request(client, endpoint, auth_token, probe, timeout).await;
+    let answered = Instant::now();
+    let latency = answered.duration_since(start).as_millis() as u64;
+
+    match result {
+        Ok(response) if response.status().is_success() => {
+            let phases = request_phases(&response, start, answered);
+            // The connection only goes back to the pool once the body is read
+            let _ =
+                tokio::time::timeout(timeout, hyper::body::to_bytes(response.into_body())).await;
+            log_health_success(endpoint, latency);
+            let mut status = EndpointStatus::new_available(endpoint.to_string(), latency, None);
+            status.latency_history.set_latest_phases(Some(phases));
+            if phases.connect_ms.is_some() {
+                status.cold_latency_ms = Some(latency);
+            } else {
+                status.warm_latency_ms = Some(latency);
+            }
+            status
+        }
+        Ok(response) => {
+            let status = response.status();
+            let error_msg = format!("{TOKEN_CHECK_PATH} answered {status}");
+            log_health_failed(endpoint, &error_msg);
+            let mut endpoint_status =
+                EndpointStatus::new_unavailable(endpoint.to_string(), error_msg);
+            endpoint_status.auth_failed =
+                matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN);
+            endpoint_status
+        }
+        Err(error_msg) => {
+            log_health_failed(endpoint, &error_msg);
+            EndpointStatus::new_unavailable(endpoint.to_string(), error_msg)
+        }
+    }
+}
+
+/// Output of the health check command, with when stdout produced its first byte
+struct CheckOutput {
+    status: std::process::ExitStatus,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    first_byte: Option<Duration>,
+}
+
+/// First line of `claude_binary --version`, `None` if it cannot be run
+pub fn claude_version(claude_binary: &Path) -> Option<String> {
+    let output = Command::new(claude_binary)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = stdout.lines().next()?.trim();
+    (output.status.success() && !version.is_empty()).then(|| version.to_string())
+}
+
+/// Collect the command's output, timing the first stdout byte from `start`.
+/// Stdout is read on its own thread so a chatty stderr can't block it.
+fn read_check_output(mut child: Child, start: Instant) -> std::io::Result<CheckOutput> {
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let reader = std::thread::spawn(move || -> std::io::Result<(Vec<u8>, Option<Duration>)> {
+        let mut output = Vec::new();
+        let mut first_byte = None;
+        let mut chunk = [0u8; 1024];
+        loop {
+            let read = stdout.read(&mut chunk)?;
+            if read == 0 {
+                return Ok((output, first_byte));
+            }
+            first_byte.get_or_insert_with(|| start.elapsed());
+            output.extend_from_slice(&chunk[..read]);
+        }
+    });
+
+    let output = child.wait_with_output()?;
+    let (stdout, first_byte) = reader
+        .join()
+        .map_err(|_| std::io::Error::other("stdout reader panicked"))??;
+
+    Ok(CheckOutput {
+        status: output.status,
+        stdout,
+        stderr: output.stderr,
+        first_byte,
+    })
+}
+
+/// Reject output that doesn't contain `expected`, e.g. an HTML error page a
+/// relay returned while the CLI still exited successfully
+/// Whether failed CLI output says the endpoint rejected the auth token, as
+/// opposed to being down
+fn is_auth_failure(output: &[u8]) -> bool {
+    let output = String::from_utf8_lossy(output).to_lowercase();
+    AUTH_FAILURE_MARKERS
+        .iter()
+        .any(|marker| output.contains(marker))
+}
+
+/// Fingerprint of a token value, to notice when it changes without keeping it
+pub fn token_fingerprint(token: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    token.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn validate_output(stdout: &[u8], expected: &str) -> Result<(), String> {
+    let output = String::from_utf8_lossy(stdout);
+    let output = output.trim();
+    if output.to_lowercase().contains(&expected.to_lowercase()) {
+        return Ok(());
+    }
+
+    let preview: String = output.chars().take(80).collect();
+    Err(format!("Unexpected health check output: {preview}"))
+}
+
 #[allow(dead_code)]
 pub fn find_best_endpoint(
     statuses: &std::collections::HashMap<String, EndpointStatus>,
@@ -281,9 +641,14 @@ pub fn find_best_endpoint(
 
     // Find the best available endpoint
     for status in statuses.values() {
-        if status.available && status.latency < best_latency {
-            best_latency = status.latency;
-            best_endpoint = Some(status.endpoint.clone());
+        if !status.is_available() {
+            continue;
+        }
+        if let Some(latency) = status.latency {
+            if latency < best_latency {
+                best_latency = latency;
+                best_endpoint = Some(status.endpoint.clone());
+            }
         }
     }
 
@@ -292,10 +657,11 @@ pub fn find_best_endpoint(
         if new_endpoint != current_endpoint {
             let current_latency = statuses
                 .get(current_endpoint)
-                .map(|s| s.latency)
-                .unwrap_or(u64::MAX);
+                .filter(|s| s.is_available())
+                .and_then(|s| s.latency);
 
-            if best_latency + switch_threshold_ms < current_latency {
+            // An unmeasured current endpoint is always worth leaving
+            if current_latency.is_none_or(|current| best_latency + switch_threshold_ms < current) {
                 return best_endpoint;
             }
         }
@@ -303,3 +669,125 @@ pub fn find_best_endpoint(
 
     None
 }
+
+/// Serde representation of `EndpointStatus::latency`.
+///
+/// Unknown latency is written as `null`. Older `/status` payloads reported failed
+/// endpoints with a 999999ms latency, which is read back as unknown.
+mod latency_compat {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    const LEGACY_FAILED_LATENCY: u64 = 999_999;
+
+    pub fn serialize<S: Serializer>(
+        latency: &Option<u64>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        latency.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u64>, D::Error> {
+        let latency = Option::<u64>::deserialize(deserializer)?;
+        Ok(latency.filter(|&ms| ms < LEGACY_FAILED_LATENCY))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2026, 1, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn hourly_profile_starts_empty() {
+        let profile = HourlyLatencyProfile::default();
+        assert_eq!(profile.hours(), [None; 24]);
+        assert_eq!(profile.latency_at(at(5, 12, 0)), None);
+    }
+
+    #[test]
+    fn hourly_profile_averages_within_an_hour() {
+        let mut profile = HourlyLatencyProfile::default();
+        profile.record(at(5, 9, 0), 100);
+        assert_eq!(profile.latency_at(at(5, 9, 30)), Some(100));
+
+        // Each measurement moves the average a fifth of the way towards it
+        profile.record(at(5, 9, 20), 200);
+        assert_eq!(profile.latency_at(at(5, 9, 59)), Some(120));
+        profile.record(at(5, 9, 40), 200);
+        assert_eq!(profile.latency_at(at(5, 9, 0)), Some(136));
+    }
+
+    #[test]
+    fn hourly_profile_rounds_the_average() {
+        let mut profile = HourlyLatencyProfile::default();
+        profile.record(at(5, 9, 0), 100);
+        profile.record(at(5, 9, 1), 103);
+        // 100.6
+        assert_eq!(profile.latency_at(at(5, 9, 0)), Some(101));
+        profile.record(at(5, 9, 2), 99);
+        // 100.28
+        assert_eq!(profile.latency_at(at(5, 9, 0)), Some(100));
+    }
+
+    #[test]
+    fn hourly_profile_keeps_hours_apart_and_days_together() {
+        let mut profile = HourlyLatencyProfile::default();
+        profile.record(at(5, 23, 59), 900);
+        profile.record(at(6, 0, 0), 100);
+        profile.record(at(6, 14, 0), 500);
+        // The same hour a day later folds into the same bucket
+        profile.record(at(7, 14, 0), 1000);
+
+        let mut expected = [None; 24];
+        expected[0] = Some(100);
+        expected[14] = Some(600);
+        expected[23] = Some(900);
+        assert_eq!(profile.hours(), expected);
+        assert_eq!(profile.latency_at(at(8, 14, 45)), Some(600));
+        assert_eq!(profile.latency_at(at(8, 13, 59)), None);
+    }
+
+    #[test]
+    fn hourly_profile_survives_a_round_trip() {
+        let mut status = EndpointStatus::new_checking("https://a.example.com".to_string());
+        status.hourly_profile.record(at(5, 3, 0), 250);
+        status.hourly_profile.record(at(5, 3, 10), 300);
+        status.hourly_profile.record(at(5, 22, 0), 80);
+
+        let json = serde_json::to_string(&status).unwrap();
+        let restored: EndpointStatus = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            restored.hourly_profile.hours(),
+            status.hourly_profile.hours()
+        );
+
+        // Saved before the profile existed
+        let mut old = serde_json::to_value(&status).unwrap();
+        old.as_object_mut().unwrap().remove("hourly_profile");
+        let restored: EndpointStatus = serde_json::from_value(old).unwrap();
+        assert_eq!(restored.hourly_profile.hours(), [None; 24]);
+    }
+
+    #[test]
+    fn measured_latency_uses_the_configured_kind_once_known() {
+        let mut status = EndpointStatus::new_available("https://a.example".to_string(), 300, None);
+        assert_eq!(status.measured_latency(HealthCheckMeasure::Warm), Some(300));
+        assert_eq!(status.measured_latency(HealthCheckMeasure::Cold), Some(300));
+
+        status.cold_latency_ms = Some(300);
+        status.warm_latency_ms = Some(80);
+        assert_eq!(status.measured_latency(HealthCheckMeasure::Warm), Some(80));
+        assert_eq!(status.measured_latency(HealthCheckMeasure::Cold), Some(300));
+
+        status.update_with_check_result(None, Some("down".to_string()));
+        assert_eq!(status.measured_latency(HealthCheckMeasure::Warm), None);
+    }
+}
@@ -0,0 +1,238 @@
+//! HTTP client of the HTTP health check mode. The orchestrator keeps one for
+//! its whole life, so checks reuse the connections earlier checks opened.
+//! Connections are dialed in stages, resolve then connect then TLS, and every
+//! response carries how long each stage of its connection took.
+
+use crate::health::LatencyPhases;
+use hyper::client::connect::{Connected, Connection};
+use hyper::service::Service;
+use hyper::{Body, Client, Response, Uri};
+use hyper_tls::{HttpsConnector, MaybeHttpsStream};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Client the HTTP health checks share
+pub(crate) type HealthCheckClient = Client<TimedConnector>;
+
+pub(crate) fn health_check_client() -> HealthCheckClient {
+    Client::builder().build(TimedConnector {
+        inner: HttpsConnector::new_with_connector(StagedConnector),
+    })
+}
+
+/// Where the time went between sending a request at `sent` and its
+/// `response` arriving at `answered`. Connection stages are only set when
+/// the request had to open its connection.
+pub(crate) fn request_phases(
+    response: &Response<Body>,
+    sent: Instant,
+    answered: Instant,
+) -> LatencyPhases {
+    match response.extensions().get::<ConnectTiming>() {
+        Some(timing) if timing.connected_at >= sent => LatencyPhases {
+            resolve_ms: Some(millis(timing.resolve)),
+            connect_ms: Some(millis(timing.connect)),
+            tls_ms: timing.tls.map(millis),
+            first_byte_ms: millis(answered.saturating_duration_since(timing.connected_at)),
+        },
+        _ => LatencyPhases {
+            first_byte_ms: millis(answered.saturating_duration_since(sent)),
+            ..LatencyPhases::default()
+        },
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+/// How the connection a response came over was opened
+#[derive(Debug, Clone, Copy)]
+struct ConnectTiming {
+    connected_at: Instant,
+    resolve: Duration,
+    connect: Duration,
+    /// `None` over plain HTTP
+    tls: Option<Duration>,
+}
+
+/// HTTPS connector timing the TLS handshake of each connection it opens
+#[derive(Clone)]
+pub(crate) struct TimedConnector {
+    inner: HttpsConnector<StagedConnector>,
+}
+
+impl Service<Uri> for TimedConnector {
+    type Response = TimedStream;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<TimedStream, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connecting = self.inner.call(uri);
+        Box::pin(async move {
+            let inner = connecting.await?;
+            let connected_at = Instant::now();
+            let tcp = match &inner {
+                MaybeHttpsStream::Http(tcp) => tcp,
+                MaybeHttpsStream::Https(tls) => tls.get_ref().get_ref().get_ref(),
+            };
+            let timing = ConnectTiming {
+                connected_at,
+                resolve: tcp.resolve,
+                connect: tcp.connect,
+                tls: matches!(inner, MaybeHttpsStream::Https(_))
+                    .then(|| connected_at.saturating_duration_since(tcp.connected_at)),
+            };
+            Ok(TimedStream { inner, timing })
+        })
+    }
+}
+
+/// Opens TCP connections with the DNS lookup and the connect timed apart
+#[derive(Clone)]
+pub(crate) struct StagedConnector;
+
+impl Service<Uri> for StagedConnector {
+    type Response = TimedTcp;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<TimedTcp>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        Box::pin(async move {
+            let host = uri
+                .host()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "URL has no host"))?
+                .trim_matches(|c| c == '[' || c == ']')
+                .to_string();
+            let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+                Some("https") => 443,
+                _ => 80,
+            });
+
+            let start = Instant::now();
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+                .await?
+                .collect();
+            let resolved = Instant::now();
+
+            let mut last_error = None;
+            for address in addresses {
+                match TcpStream::connect(address).await {
+                    Ok(stream) => {
+                        stream.set_nodelay(true)?;
+                        let connected_at = Instant::now();
+                        return Ok(TimedTcp {
+                            stream,
+                            resolve: resolved - start,
+                            connect: connected_at - resolved,
+                            connected_at,
+                        });
+                    }
+                    Err(e) => last_error = Some(e),
+                }
+            }
+            Err(last_error.unwrap_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("{host} has no address"))
+            }))
+        })
+    }
+}
+
+/// A TCP connection of [`StagedConnector`] with the time each stage took
+pub(crate) struct TimedTcp {
+    stream: TcpStream,
+    resolve: Duration,
+    connect: Duration,
+    connected_at: Instant,
+}
+
+impl Connection for TimedTcp {
+    fn connected(&self) -> Connected {
+        self.stream.connected()
+    }
+}
+
+impl AsyncRead for TimedTcp {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TimedTcp {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+/// A connection of [`TimedConnector`], handing its [`ConnectTiming`] to every
+/// response it carries
+pub(crate) struct TimedStream {
+    inner: MaybeHttpsStream<TimedTcp>,
+    timing: ConnectTiming,
+}
+
+impl Connection for TimedStream {
+    fn connected(&self) -> Connected {
+        self.inner.connected().extra(self.timing)
+    }
+}
+
+impl AsyncRead for TimedStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TimedStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
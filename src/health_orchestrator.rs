@@ -1,20 +1,164 @@
-use crate::config::Config;
+use crate::config::{Config, ListenerConfig};
 use crate::connection_tracker::SharedConnectionTracker;
-use crate::dynamic_health::DynamicHealthChecker;
-use crate::events::{ProxyEvent, SelectionMode};
-use crate::health::{self, EndpointStatus};
-use crate::proxy::SharedState;
-use futures::future;
-use std::sync::{Arc, Mutex};
+use crate::dynamic_health::{DynamicHealthChecker, IntervalDecision, LoadLevel};
+use crate::env_file;
+use crate::events::{
+    CompletionReason, HealthCycleSummary, ProxyEvent, SelectionMode, SwitchReason,
+};
+use crate::group_health;
+use crate::health::{self, CheckState, EndpointStatus};
+use crate::health_client::{health_check_client, HealthCheckClient};
+use crate::i18n;
+use crate::lock::{LockExt, RwLockExt};
+use crate::logging::{
+    console_line, log_all_endpoints_down, log_auth_rejected, log_auth_token_changed,
+    log_config_reload_failed, log_config_reloaded, log_endpoint_reset, log_group_down,
+    log_group_recovered, log_group_token_found, log_health_cycle_summary, log_maintenance_ended,
+    log_maintenance_started, log_standby_changed, log_startup_check_failed,
+};
+use crate::proxy::{ListenerChanges, ProxyState, SharedState};
+use crate::selection;
+use crate::token_check::check_tokens;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
+/// How often maintenance windows are checked for starting or ending
+const MAINTENANCE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Commands to control the health orchestrator
 #[derive(Debug, Clone)]
 pub enum OrchestratorCommand {
     Pause,
     Resume,
     ManualRefresh,
+    /// Re-check only the endpoints that are currently unavailable
+    RefreshFailed,
+    FreezeSwitching,
+    UnfreezeSwitching,
+    /// Read the config file again and apply it without a restart
+    ReloadConfig,
+    /// Clear the error state and latency history of the endpoint at this URL,
+    /// then check it right away
+    ResetEndpoint {
+        endpoint: String,
+        source: ResetSource,
+    },
+}
+
+/// What asked for an endpoint reset, named in its log line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetSource {
+    Dashboard,
+    AdminApi,
+}
+
+impl ResetSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ResetSource::Dashboard => "the dashboard",
+            ResetSource::AdminApi => "the admin API",
+        }
+    }
+}
+
+/// What the health loop is doing, published at cycle and command boundaries
+/// so `GET /admin/orchestrator` can tell a stalled loop from an idle one
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OrchestratorSnapshot {
+    pub paused: bool,
+    /// Interval the next cycle was scheduled with, after dynamic scaling
+    pub interval_seconds: u64,
+    /// Load level behind dynamic scaling, `None` without a connection tracker
+    pub load_level: Option<LoadLevel>,
+    /// Proxied requests per minute between the last two cycles
+    pub request_rate_per_minute: Option<f64>,
+    /// A cycle started and has not finished yet
+    pub cycle_running: bool,
+    pub last_cycle_started_at: Option<DateTime<Utc>>,
+    pub last_cycle_finished_at: Option<DateTime<Utc>>,
+    pub last_cycle_duration_ms: Option<u64>,
+    pub cycles_completed: u64,
+    /// When the next scheduled cycle is due
+    pub next_check_at: Option<DateTime<Utc>>,
+    /// Endpoints the last scheduled cycle skipped because they rejected their token
+    pub skipped_endpoints: usize,
+    pub last_command: Option<String>,
+    pub last_command_at: Option<DateTime<Utc>>,
+    /// Request total and time at the last finished cycle, for the request rate
+    #[serde(skip)]
+    request_baseline: Option<(u64, Instant)>,
+}
+
+/// Cloneable handle for controlling a running health orchestrator
+#[derive(Debug, Clone)]
+pub struct HealthOrchestratorHandle {
+    command_sender: mpsc::UnboundedSender<OrchestratorCommand>,
+    snapshot: Arc<RwLock<OrchestratorSnapshot>>,
+}
+
+impl HealthOrchestratorHandle {
+    /// The orchestrator's state as of its last cycle or command
+    pub fn snapshot(&self) -> OrchestratorSnapshot {
+        self.snapshot.read_or_recover().clone()
+    }
+
+    /// Send any command, for callers that decide which one at runtime
+    pub(crate) fn send(&self, command: OrchestratorCommand) {
+        let _ = self.command_sender.send(command);
+    }
+
+    /// Stop scheduled health checks until [`resume`](Self::resume) is called
+    pub fn pause(&self) {
+        let _ = self.command_sender.send(OrchestratorCommand::Pause);
+    }
+
+    /// Restart scheduled health checks after a pause
+    pub fn resume(&self) {
+        let _ = self.command_sender.send(OrchestratorCommand::Resume);
+    }
+
+    /// Check only the currently unavailable endpoints now
+    pub fn refresh_failed(&self) {
+        let _ = self.command_sender.send(OrchestratorCommand::RefreshFailed);
+    }
+
+    /// Keep health checks running but stop them from switching endpoints
+    pub fn freeze_switching(&self) {
+        let _ = self
+            .command_sender
+            .send(OrchestratorCommand::FreezeSwitching);
+    }
+
+    /// Let health checks switch endpoints again after a freeze
+    pub fn unfreeze_switching(&self) {
+        let _ = self
+            .command_sender
+            .send(OrchestratorCommand::UnfreezeSwitching);
+    }
+
+    /// Read the config file again and apply it, keeping runtime state
+    pub fn reload_config(&self) {
+        let _ = self.command_sender.send(OrchestratorCommand::ReloadConfig);
+    }
+
+    /// Check every endpoint now instead of waiting for the next cycle
+    pub fn refresh(&self) {
+        let _ = self.command_sender.send(OrchestratorCommand::ManualRefresh);
+    }
+
+    /// Give the endpoint at `endpoint` a clean slate and check it now
+    pub fn reset_endpoint(&self, endpoint: String, source: ResetSource) {
+        let _ = self
+            .command_sender
+            .send(OrchestratorCommand::ResetEndpoint { endpoint, source });
+    }
 }
 
 /// Modern health check orchestrator with clear separation of concerns
@@ -24,16 +168,23 @@ pub struct HealthCheckOrchestrator {
     event_sender: mpsc::UnboundedSender<ProxyEvent>,
     connection_tracker: Option<SharedConnectionTracker>,
     dynamic_checker: Option<DynamicHealthChecker>,
+    /// Inputs behind the interval last calculated, sent with the next cycle
+    interval_decision: Option<IntervalDecision>,
     dashboard_mode: bool,
     // Track if someone in current cycle has already won the race
-    cycle_winner_chosen: std::sync::Arc<std::sync::Mutex<bool>>,
+    cycle_winner_chosen: Arc<AtomicBool>,
     // System pause state
-    is_paused: Arc<Mutex<bool>>,
+    is_paused: Arc<AtomicBool>,
     // Command receiver for pause/resume/refresh
     command_receiver: mpsc::UnboundedReceiver<OrchestratorCommand>,
     // Command sender (for returning to caller)
-    #[allow(dead_code)]
     command_sender: mpsc::UnboundedSender<OrchestratorCommand>,
+    // Groups left out of checks until their token env var is set
+    groups_without_token: HashSet<String>,
+    // Published state, shared with every handle
+    snapshot: Arc<RwLock<OrchestratorSnapshot>>,
+    // Client of HTTP mode checks, kept so later checks reuse its connections
+    http_client: HealthCheckClient,
 }
 
 impl HealthCheckOrchestrator {
@@ -43,12 +194,17 @@ impl HealthCheckOrchestrator {
         event_sender: mpsc::UnboundedSender<ProxyEvent>,
         dashboard_mode: bool,
         connection_tracker: Option<SharedConnectionTracker>,
-    ) -> (Self, mpsc::UnboundedSender<OrchestratorCommand>) {
+    ) -> (Self, HealthOrchestratorHandle) {
         let dynamic_checker = connection_tracker
             .as_ref()
             .map(|_| DynamicHealthChecker::new(&config));
 
         let (command_sender, command_receiver) = mpsc::unbounded_channel();
+        let groups_without_token = config
+            .groups_without_token()
+            .into_iter()
+            .map(|group| group.name.clone())
+            .collect();
 
         let orchestrator = Self {
             config,
@@ -56,14 +212,46 @@ impl HealthCheckOrchestrator {
             event_sender,
             connection_tracker,
             dynamic_checker,
+            interval_decision: None,
             dashboard_mode,
-            cycle_winner_chosen: std::sync::Arc::new(std::sync::Mutex::new(false)),
-            is_paused: Arc::new(Mutex::new(false)),
+            cycle_winner_chosen: Arc::new(AtomicBool::new(false)),
+            is_paused: Arc::new(AtomicBool::new(false)),
             command_receiver,
             command_sender: command_sender.clone(),
+            groups_without_token,
+            snapshot: Arc::new(RwLock::new(OrchestratorSnapshot::default())),
+            http_client: health_check_client(),
         };
 
-        (orchestrator, command_sender)
+        let handle = orchestrator.handle();
+        (orchestrator, handle)
+    }
+
+    /// Another handle for controlling this orchestrator once it runs
+    pub fn handle(&self) -> HealthOrchestratorHandle {
+        HealthOrchestratorHandle {
+            command_sender: self.command_sender.clone(),
+            snapshot: self.snapshot.clone(),
+        }
+    }
+
+    /// Update the published snapshot
+    fn publish(&self, update: impl FnOnce(&mut OrchestratorSnapshot)) {
+        update(&mut self.snapshot.write_or_recover());
+    }
+
+    /// Publish the schedule the loop is about to wait on
+    fn publish_schedule(&self, next_check: tokio::time::Instant, interval: Duration) {
+        let paused = self.is_paused.load(Ordering::Relaxed);
+        let until_next = next_check.saturating_duration_since(tokio::time::Instant::now());
+        let load_level = self.dynamic_checker.as_ref().map(|c| c.get_load_level());
+        self.publish(|snapshot| {
+            snapshot.paused = paused;
+            snapshot.interval_seconds = interval.as_secs();
+            snapshot.load_level = load_level;
+            snapshot.next_check_at = (!paused)
+                .then(|| Utc::now() + chrono::Duration::from_std(until_next).unwrap_or_default());
+        });
     }
 
     /// Main orchestration loop - supports pause/resume and manual refresh
@@ -86,31 +274,45 @@ impl HealthCheckOrchestrator {
                     interval.tick().await;
 
                     // Quick cleanup for very recent interrupts (15 seconds)
-                    if let Ok(mut tracker_guard) = interrupt_tracker.lock() {
-                        let very_recent_abandoned = tracker_guard.cleanup_abandoned_connections(15);
-                        if !very_recent_abandoned.is_empty() {
-                            if !interrupt_dashboard_mode {
-                                println!(
-                                    "⚡ Fast cleanup: {} recently interrupted connections",
-                                    very_recent_abandoned.len()
-                                );
-                            }
-                            for connection_id in very_recent_abandoned {
-                                let _ = interrupt_sender
-                                    .send(ProxyEvent::ConnectionCompleted(connection_id));
-                            }
+                    let mut tracker_guard = interrupt_tracker.lock_or_recover();
+                    let very_recent_abandoned = tracker_guard.cleanup_abandoned_connections(15);
+                    if !very_recent_abandoned.is_empty() {
+                        if !interrupt_dashboard_mode {
+                            console_line(
+                                &i18n::console().fast_cleanup(very_recent_abandoned.len()),
+                            );
+                        }
+                        for connection_id in very_recent_abandoned {
+                            let _ = interrupt_sender.send(ProxyEvent::ConnectionCompleted {
+                                id: connection_id,
+                                reason: CompletionReason::Abandoned,
+                                phases: None,
+                                timings: None,
+                            });
                         }
                     }
                 }
             });
         }
 
+        let mut maintenance_tick = tokio::time::interval(MAINTENANCE_CHECK_INTERVAL);
+        maintenance_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
         loop {
+            self.publish_schedule(next_check, current_interval);
+
             // Handle commands and check pause state
             tokio::select! {
+                // Commands first, so a pause sent before startup skips the first cycle
+                biased;
+
                 // Handle orchestrator commands (pause/resume/manual refresh)
                 command = self.command_receiver.recv() => {
                     if let Some(cmd) = command {
+                        self.publish(|snapshot| {
+                            snapshot.last_command = Some(format!("{cmd:?}"));
+                            snapshot.last_command_at = Some(Utc::now());
+                        });
                         match cmd {
                             OrchestratorCommand::Pause => {
                                 self.handle_pause().await;
@@ -119,21 +321,61 @@ impl HealthCheckOrchestrator {
                                 self.handle_resume(&mut next_check, current_interval).await;
                             },
                             OrchestratorCommand::ManualRefresh => {
-                                self.handle_manual_refresh(&mut current_interval).await?;
+                                self.handle_manual_refresh(&mut current_interval, next_check).await?;
+                            }
+                            OrchestratorCommand::RefreshFailed => {
+                                self.handle_refresh_failed(&mut current_interval).await?;
+                            }
+                            OrchestratorCommand::FreezeSwitching => {
+                                self.set_switching_frozen(true);
+                            }
+                            OrchestratorCommand::UnfreezeSwitching => {
+                                self.set_switching_frozen(false);
+                            }
+                            OrchestratorCommand::ReloadConfig => {
+                                let changed = self.reload_config();
+                                self.recheck_changed_tokens().await;
+                                if changed {
+                                    // New endpoints and a replaced current one are checked right away
+                                    next_check = tokio::time::Instant::now();
+                                }
+                            }
+                            OrchestratorCommand::ResetEndpoint { endpoint, source } => {
+                                self.handle_reset_endpoint(&endpoint, source, &mut current_interval).await?;
                             }
                         }
                     }
                 }
 
+                // Start and end maintenance windows, checking endpoints that rejoin
+                _ = maintenance_tick.tick() => {
+                    let ended = self.update_maintenance();
+                    if !ended.is_empty()
+                        && !self.is_paused.load(Ordering::Relaxed)
+                        && !self.config.health_checks_disabled()
+                    {
+                        let check_interval = self.calculate_optimal_interval(&mut current_interval);
+                        let cycle_result = self.execute_health_cycle(check_interval, Some(&ended)).await;
+                        self.process_cycle_results(cycle_result).await?;
+                    }
+                }
+
                 // Regular health check cycle (only if not paused and time is reached)
                 _ = tokio::time::sleep_until(next_check) => {
-                    let is_paused = self.is_paused.lock().map(|guard| *guard).unwrap_or(true);
-                    if !is_paused {
+                    let is_paused = self.is_paused.load(Ordering::Relaxed);
+                    if self.config.health_checks_disabled() {
+                        // Nothing to run the checks with, look again after a reload
+                        next_check = tokio::time::Instant::now() + current_interval;
+                    } else if !is_paused {
+                        self.refresh_group_tokens();
+                        self.recheck_changed_tokens().await;
+
                         // Calculate optimal check interval
                         let check_interval = self.calculate_optimal_interval(&mut current_interval);
 
-                        // Execute health check cycle
-                        let cycle_result = self.execute_health_cycle(check_interval).await;
+                        // Execute health check cycle, skipping endpoints that rejected their token
+                        let only = self.scheduled_check_endpoints();
+                        let cycle_result = self.execute_health_cycle(check_interval, only.as_ref()).await;
 
                         // Handle cycle results and update state
                         self.process_cycle_results(cycle_result).await?;
@@ -143,6 +385,7 @@ impl HealthCheckOrchestrator {
 
                         // Schedule next check
                         next_check = tokio::time::Instant::now() + check_interval;
+                        self.update_all_down(next_check);
                     } else {
                         // If paused, just sleep a short time and check again
                         next_check = tokio::time::Instant::now() + Duration::from_secs(1);
@@ -152,12 +395,142 @@ impl HealthCheckOrchestrator {
         }
     }
 
+    /// Check the current endpoint once before the proxy accepts traffic.
+    ///
+    /// When it is down, the other endpoints Auto mode may switch to are checked
+    /// in parallel and the fastest healthy one becomes current, so the first
+    /// requests don't start on a dead endpoint and work through the fallbacks.
+    pub async fn run_startup_check(&self) {
+        self.update_maintenance();
+        let (current, eligible) = {
+            let state_guard = self.state.lock_or_recover();
+            let eligible: Vec<String> = state_guard
+                .auto_switch_endpoints()
+                .into_iter()
+                .filter(|endpoint| !state_guard.in_maintenance(endpoint))
+                .collect();
+            (state_guard.current_url().to_string(), eligible)
+        };
+        let all_endpoints = self.config.get_all_endpoints_legacy();
+
+        if let Some((auth_token, _, group)) = all_endpoints
+            .iter()
+            .find(|(_, endpoint, _)| endpoint.url == current && eligible.contains(&endpoint.url))
+        {
+            if self
+                .startup_check_endpoint(&current, auth_token, group)
+                .await
+                .is_available()
+            {
+                return;
+            }
+        }
+
+        let checks: Vec<_> = all_endpoints
+            .iter()
+            .filter(|(_, endpoint, _)| endpoint.url != current && eligible.contains(&endpoint.url))
+            .map(|(auth_token, endpoint, group)| {
+                self.startup_check_endpoint(&endpoint.url, auth_token, group)
+            })
+            .collect();
+        let max_concurrent = self.config.health_check.max_concurrent_checks.max(1);
+        let results: Vec<EndpointStatus> = stream::iter(checks)
+            .buffer_unordered(max_concurrent)
+            .collect()
+            .await;
+
+        let Some(best) = results
+            .into_iter()
+            .filter(|status| status.is_available())
+            .min_by_key(|status| {
+                (
+                    self.config.tier_of(&status.endpoint),
+                    status.latency.unwrap_or(u64::MAX),
+                )
+            })
+        else {
+            if !self.dashboard_mode {
+                log_startup_check_failed(&current);
+            }
+            return;
+        };
+
+        let switch_info = {
+            let state_guard = self.state.lock_or_recover();
+            selection::switch_decision(&best, &state_guard, chrono::Local::now()).ok()
+        };
+        if let Some((from_endpoint, from_latency, to_latency, reason)) = switch_info {
+            self.perform_endpoint_switch(
+                &best,
+                from_endpoint,
+                from_latency,
+                to_latency,
+                reason,
+                &self.state,
+                &self.event_sender,
+            )
+            .await;
+        }
+    }
+
+    /// Run one health check and record it like a regular cycle would
+    async fn startup_check_endpoint(
+        &self,
+        endpoint: &str,
+        auth_token: &str,
+        group: &str,
+    ) -> EndpointStatus {
+        let endpoint_url = endpoint.to_string();
+        let auth_token = auth_token.to_string();
+        let config = self.config.clone();
+        let claude_binary = self.config.claude_binary(group);
+        let probe = self.config.health_probe(group);
+        let status = if config.http_health_checks() {
+            Ok(health::check_endpoint_http(
+                &self.http_client,
+                &endpoint_url,
+                &config,
+                &auth_token,
+                &probe,
+            )
+            .await)
+        } else {
+            tokio::task::spawn_blocking(move || {
+                health::check_endpoint_health(
+                    &endpoint_url,
+                    &config,
+                    &auth_token,
+                    &claude_binary,
+                    &probe,
+                )
+            })
+            .await
+        }
+        .unwrap_or_else(|e| {
+            health::EndpointStatus::new_unavailable(
+                endpoint.to_string(),
+                format!("Task error: {e}"),
+            )
+        });
+
+        let status = self
+            .merge_with_existing_status(&status, &self.state)
+            .await
+            .unwrap_or(status);
+        let _ = self
+            .event_sender
+            .send(ProxyEvent::HealthUpdate(status.clone()));
+        status
+    }
+
     /// Calculate optimal check interval based on current conditions
     fn calculate_optimal_interval(&mut self, current_interval: &mut Duration) -> Duration {
         if let (Some(ref mut checker), Some(ref tracker)) =
             (&mut self.dynamic_checker, &self.connection_tracker)
         {
-            let new_interval = checker.calculate_interval(tracker);
+            let (new_interval, decision) = checker.calculate_interval(tracker);
+            self.interval_decision = Some(decision);
+            self.state.lock_or_recover().health_interval = Some((new_interval, decision));
             let current_val = *current_interval;
             let load_level = checker.get_load_level();
 
@@ -168,10 +541,11 @@ impl HealthCheckOrchestrator {
             if should_update {
                 *current_interval = new_interval;
                 if !self.dashboard_mode {
-                    println!(
-                        "🔄 Health check interval adjusted to {}s (Load: {:?})",
-                        current_interval.as_secs(),
-                        load_level
+                    console_line(
+                        &i18n::console().interval_adjusted(
+                            current_interval.as_secs(),
+                            &format!("{load_level:?}"),
+                        ),
                     );
                 }
             }
@@ -182,64 +556,121 @@ impl HealthCheckOrchestrator {
         }
     }
 
-    /// Execute a complete health check cycle
-    async fn execute_health_cycle(&self, interval: Duration) -> HealthCycleResult {
+    /// Pick up tokens set since the last cycle, so their groups join this one
+    fn refresh_group_tokens(&mut self) {
+        if self.groups_without_token.is_empty() {
+            return;
+        }
+        env_file::reload(false);
+
+        let still_missing: HashSet<String> = self
+            .config
+            .groups_without_token()
+            .into_iter()
+            .map(|group| group.name.clone())
+            .collect();
+        if !self.dashboard_mode {
+            for group in &self.config.groups {
+                if self.groups_without_token.contains(&group.name)
+                    && !still_missing.contains(&group.name)
+                {
+                    log_group_token_found(&group.name, &group.auth_token_env);
+                }
+            }
+        }
+        self.groups_without_token = still_missing;
+    }
+
+    /// Run the pre-flight token check again for groups whose token changed,
+    /// was set or could not be verified last time, when `validate_tokens` is on
+    async fn recheck_changed_tokens(&self) {
+        if self.config.health_check.validate_tokens {
+            check_tokens(&self.config, &self.state, self.dashboard_mode, true).await;
+        }
+    }
+
+    /// Execute a complete health check cycle over every endpoint, or only
+    /// those in `only`
+    async fn execute_health_cycle(
+        &self,
+        interval: Duration,
+        only: Option<&HashSet<String>>,
+    ) -> HealthCycleResult {
         let cycle_start = Instant::now();
         let next_check_time = cycle_start + interval;
+        let previous_endpoint = self.state.lock_or_recover().current_url().to_string();
+        self.publish(|snapshot| {
+            snapshot.cycle_running = true;
+            snapshot.last_cycle_started_at = Some(Utc::now());
+        });
 
         // Reset race winner flag for this cycle
-        if let Ok(mut winner_chosen) = self.cycle_winner_chosen.lock() {
-            *winner_chosen = false;
-        }
+        self.cycle_winner_chosen.store(false, Ordering::Release);
 
         // Send cycle start event
         self.send_cycle_start_event(interval, next_check_time).await;
 
         // Mark all endpoints as checking (best effort)
-        let _ = self.mark_endpoints_as_checking().await;
+        let _ = self.mark_endpoints_as_checking(only).await;
 
         // Execute parallel health checks
-        let check_results = self.execute_parallel_checks(cycle_start).await;
+        let check_results = self.execute_parallel_checks(cycle_start, only).await;
 
         HealthCycleResult {
             start_time: cycle_start,
             results: check_results,
             duration: cycle_start.elapsed(),
+            previous_endpoint,
         }
     }
 
-    /// Execute health checks for all endpoints in parallel
-    async fn execute_parallel_checks(&self, cycle_start: Instant) -> Vec<EndpointStatus> {
-        let all_endpoints = self.config.get_all_endpoints_legacy();
+    /// Execute health checks for all endpoints (or those in `only`) in parallel
+    async fn execute_parallel_checks(
+        &self,
+        cycle_start: Instant,
+        only: Option<&HashSet<String>>,
+    ) -> Vec<EndpointStatus> {
+        let all_endpoints: Vec<_> = self
+            .config
+            .get_all_endpoints_legacy()
+            .into_iter()
+            .filter(|(_, endpoint_config, _)| {
+                only.is_none_or(|only| only.contains(&endpoint_config.url))
+            })
+            .collect();
+
+        // Checks run in batches, so the cycle may take several check timeouts
+        let timeout_duration = self.config.health_check_cycle_timeout(all_endpoints.len());
 
         // Send running event
         let _ = self.event_sender.send(ProxyEvent::HealthCheckRunning {
             started_at: cycle_start,
-            estimated_duration: Duration::from_secs(self.config.health_check.timeout_seconds + 5),
+            estimated_duration: timeout_duration,
         });
 
-        // Create parallel check tasks
+        // Create check tasks, at most max_concurrent_checks running at once
         let check_futures: Vec<_> = all_endpoints
             .iter()
-            .map(|(auth_token, endpoint_config, _)| {
+            .map(|(auth_token, endpoint_config, group)| {
                 self.create_endpoint_check_task(
                     auth_token,
                     endpoint_config.clone(),
+                    group,
                     self.cycle_winner_chosen.clone(),
                 )
             })
             .collect();
+        let max_concurrent = self.config.health_check.max_concurrent_checks.max(1);
+        let check_stream = stream::iter(check_futures)
+            .buffer_unordered(max_concurrent)
+            .collect::<Vec<_>>();
 
         // Execute with timeout
-        let timeout_duration = Duration::from_secs(self.config.health_check.timeout_seconds + 5);
-        match tokio::time::timeout(timeout_duration, future::join_all(check_futures)).await {
+        match tokio::time::timeout(timeout_duration, check_stream).await {
             Ok(results) => results.into_iter().flatten().collect(),
             Err(_) => {
                 if !self.dashboard_mode {
-                    println!(
-                        "⚠️  Health check cycle timed out after {}s",
-                        timeout_duration.as_secs()
-                    );
+                    console_line(&i18n::console().cycle_timed_out(timeout_duration.as_secs()));
                 }
                 Vec::new()
             }
@@ -251,34 +682,197 @@ impl HealthCheckOrchestrator {
         &self,
         auth_token: &str,
         endpoint_config: crate::config::EndpointConfig,
-        cycle_winner_chosen: std::sync::Arc<std::sync::Mutex<bool>>,
+        group: &str,
+        cycle_winner_chosen: Arc<AtomicBool>,
     ) -> Option<EndpointStatus> {
         let endpoint_url = endpoint_config.url.clone();
         let endpoint_url_clone = endpoint_url.clone(); // For error handling
+        let fingerprint = health::token_fingerprint(auth_token);
         let auth_token = auth_token.to_string();
         let config = self.config.clone();
         let state = self.state.clone();
         let event_sender = self.event_sender.clone();
         let dashboard_mode = self.dashboard_mode;
+        let claude_binary = self.config.claude_binary(group);
+        let probe = self.config.health_probe(group);
 
         // Spawn health check task
-        let check_result = tokio::task::spawn_blocking(move || {
-            health::check_endpoint_health(&endpoint_url, &config, &auth_token)
-        })
-        .await;
+        let check_result = if config.http_health_checks() {
+            Ok(health::check_endpoint_http(
+                &self.http_client,
+                &endpoint_url,
+                &config,
+                &auth_token,
+                &probe,
+            )
+            .await)
+        } else {
+            tokio::task::spawn_blocking(move || {
+                health::check_endpoint_health(
+                    &endpoint_url,
+                    &config,
+                    &auth_token,
+                    &claude_binary,
+                    &probe,
+                )
+            })
+            .await
+        };
 
         let new_status = check_result.unwrap_or_else(|e| {
             if !dashboard_mode {
-                println!("⚠️  Health check task error for {endpoint_url_clone}: {e}");
+                console_line(&i18n::console().check_task_error(&endpoint_url_clone, &e));
             }
             health::EndpointStatus::new_unavailable(endpoint_url_clone, format!("Task error: {e}"))
         });
+        // A window that started during the check makes its result meaningless
+        if state.lock_or_recover().in_maintenance(&new_status.endpoint) {
+            return None;
+        }
+        self.record_auth_result(&new_status, fingerprint);
 
         // Update state and check for race winner (first available wins)
         self.update_endpoint_state(&new_status, &state, &event_sender, cycle_winner_chosen)
             .await
     }
 
+    /// Make an endpoint that rejected its token sticky-unavailable for that
+    /// token, or clear it once a check gets past authentication
+    fn record_auth_result(&self, status: &EndpointStatus, fingerprint: u64) {
+        let mut state_guard = self.state.lock_or_recover();
+        let Some(id) = state_guard
+            .config
+            .endpoint_index()
+            .id_of(&status.endpoint)
+            .cloned()
+        else {
+            return;
+        };
+        if status.auth_failed {
+            let previous = state_guard.auth_rejected.insert(id, fingerprint);
+            if previous != Some(fingerprint) && !self.dashboard_mode {
+                log_auth_rejected(&status.endpoint);
+            }
+        } else {
+            state_guard.auth_rejected.remove(&id);
+        }
+    }
+
+    /// Endpoints a scheduled cycle checks: all of them except those in
+    /// maintenance and those that rejected their group's current token.
+    /// Endpoints whose token changed since are checked again. `None` when
+    /// nothing is skipped.
+    fn scheduled_check_endpoints(&self) -> Option<HashSet<String>> {
+        let endpoints = self.config.get_all_endpoints();
+        let mut state_guard = self.state.lock_or_recover();
+        let in_maintenance = maintenance_endpoints(&state_guard);
+        if state_guard.auth_rejected.is_empty() && in_maintenance.is_empty() {
+            self.publish(|snapshot| snapshot.skipped_endpoints = 0);
+            return None;
+        }
+
+        // URL and token of each endpoint by id
+        let tokens: HashMap<&str, (&str, &str)> = endpoints
+            .iter()
+            .map(|(token, endpoint, _)| (endpoint.id(), (endpoint.url.as_str(), token.as_str())))
+            .collect();
+        let dashboard_mode = self.dashboard_mode;
+        state_guard.auth_rejected.retain(|id, fingerprint| {
+            let endpoint = tokens.get(id.as_str());
+            let unchanged =
+                endpoint.is_some_and(|(_, token)| health::token_fingerprint(token) == *fingerprint);
+            if !unchanged && !dashboard_mode {
+                log_auth_token_changed(endpoint.map_or(id.as_str(), |(url, _)| url));
+            }
+            unchanged
+        });
+
+        let only: HashSet<String> = endpoints
+            .iter()
+            .filter(|(_, endpoint, _)| {
+                !state_guard
+                    .auth_rejected
+                    .keys()
+                    .any(|id| id.as_str() == endpoint.id())
+            })
+            .map(|(_, endpoint, _)| endpoint.url.clone())
+            .filter(|url| !in_maintenance.contains(url))
+            .collect();
+        let skipped = endpoints.len() - only.len();
+        self.publish(|snapshot| snapshot.skipped_endpoints = skipped);
+        Some(only)
+    }
+
+    /// Endpoints a manual refresh checks: all of them except those in
+    /// maintenance. `None` when no endpoint is in maintenance.
+    fn manual_check_endpoints(&self) -> Option<HashSet<String>> {
+        let state_guard = self.state.lock_or_recover();
+        let in_maintenance = maintenance_endpoints(&state_guard);
+        if in_maintenance.is_empty() {
+            return None;
+        }
+        Some(
+            self.config
+                .get_all_endpoints()
+                .into_iter()
+                .map(|(_, endpoint, _)| endpoint.url)
+                .filter(|url| !in_maintenance.contains(url))
+                .collect(),
+        )
+    }
+
+    /// Start and end maintenance windows. An endpoint entering one is taken
+    /// out of rotation without counting as a failure, and left if it is
+    /// current. Returns the endpoints whose window ended, to be checked now.
+    fn update_maintenance(&self) -> HashSet<String> {
+        let due = self.config.maintenance_due(Utc::now());
+        let mut updates = Vec::new();
+        let mut ended = HashSet::new();
+        let switch = {
+            let mut state_guard = self.state.lock_or_recover();
+            if due.is_empty() && maintenance_endpoints(&state_guard).is_empty() {
+                return ended;
+            }
+            for status in state_guard.endpoint_status.values_mut() {
+                let until = due.get(&status.endpoint).copied();
+                if status.maintenance_until == until {
+                    continue;
+                }
+                match until {
+                    Some(until) => {
+                        if status.maintenance_until.is_none() && !self.dashboard_mode {
+                            log_maintenance_started(&status.endpoint, until);
+                        }
+                        status.mark_unhealthy("Maintenance".to_string());
+                    }
+                    None => {
+                        if !self.dashboard_mode {
+                            log_maintenance_ended(&status.endpoint);
+                        }
+                        ended.insert(status.endpoint.clone());
+                    }
+                }
+                status.maintenance_until = until;
+                updates.push(status.clone());
+            }
+            state_guard.leave_maintenance_endpoint(self.dashboard_mode)
+        };
+
+        for status in updates {
+            let _ = self.event_sender.send(ProxyEvent::HealthUpdate(status));
+        }
+        if let Some(record) = switch {
+            let _ = self.event_sender.send(ProxyEvent::EndpointSwitch {
+                from: record.from,
+                to: record.to,
+                from_latency: record.from_latency,
+                to_latency: record.to_latency,
+                reason: record.reason,
+            });
+        }
+        ended
+    }
+
     /// Update endpoint state without switching (for batch processing)
     #[allow(dead_code)]
     async fn update_endpoint_state_only(
@@ -304,7 +898,7 @@ impl HealthCheckOrchestrator {
         new_status: &EndpointStatus,
         state: &SharedState,
         event_sender: &mpsc::UnboundedSender<ProxyEvent>,
-        cycle_winner_chosen: std::sync::Arc<std::sync::Mutex<bool>>,
+        cycle_winner_chosen: Arc<AtomicBool>,
     ) -> Option<EndpointStatus> {
         // Update state with preserved history - now handles state update internally
         let final_status = self.merge_with_existing_status(new_status, state).await?;
@@ -312,15 +906,34 @@ impl HealthCheckOrchestrator {
         // Send health update event
         let _ = event_sender.send(ProxyEvent::HealthUpdate(final_status.clone()));
 
-        // Check for race winner: first available endpoint wins
-        self.check_race_winner(&final_status, state, event_sender, cycle_winner_chosen)
-            .await;
+        // Check for race winner: first available endpoint wins, unless frozen
+        if !state.lock_or_recover().switching_frozen {
+            self.check_race_winner(&final_status, state, event_sender, cycle_winner_chosen)
+                .await;
+        }
 
         Some(final_status)
     }
 
     /// Process results from a completed health cycle
     async fn process_cycle_results(&self, cycle_result: HealthCycleResult) -> anyhow::Result<()> {
+        let requests = self.state.lock_or_recover().metrics.totals().requests;
+        let mut sequence = 0;
+        self.publish(|snapshot| {
+            let now = Instant::now();
+            if let Some((baseline, at)) = snapshot.request_baseline {
+                let minutes = now.duration_since(at).as_secs_f64() / 60.0;
+                snapshot.request_rate_per_minute =
+                    (minutes > 0.0).then(|| requests.saturating_sub(baseline) as f64 / minutes);
+            }
+            snapshot.request_baseline = Some((requests, now));
+            snapshot.cycle_running = false;
+            snapshot.last_cycle_finished_at = Some(Utc::now());
+            snapshot.last_cycle_duration_ms = Some(cycle_result.duration.as_millis() as u64);
+            snapshot.cycles_completed += 1;
+            sequence = snapshot.cycles_completed;
+        });
+
         // Send cycle completion event
         let _ = self.event_sender.send(ProxyEvent::HealthCheckCompleted {
             duration: cycle_result.duration,
@@ -328,110 +941,152 @@ impl HealthCheckOrchestrator {
 
         // Perform periodic cleanup of stale connections (5 minutes timeout)
         if let Some(ref tracker) = self.connection_tracker {
-            if let Ok(mut tracker_guard) = tracker.lock() {
-                let stale = tracker_guard.cleanup_stale_connections(300); // 5 minutes
-                if !stale.is_empty() {
-                    if !self.dashboard_mode {
-                        println!("🧹 Cleaned up {} stale connections", stale.len());
-                    }
-                    // Send cleanup events for stale connections
-                    for connection_id in stale {
-                        let _ = self
-                            .event_sender
-                            .send(ProxyEvent::ConnectionCompleted(connection_id));
-                    }
+            let mut tracker_guard = tracker.lock_or_recover();
+            let stale = tracker_guard.cleanup_stale_connections(300); // 5 minutes
+            if !stale.is_empty() {
+                if !self.dashboard_mode {
+                    console_line(&i18n::console().stale_cleaned(stale.len()));
+                }
+                // Send cleanup events for stale connections
+                for connection_id in stale {
+                    let _ = self.event_sender.send(ProxyEvent::ConnectionCompleted {
+                        id: connection_id,
+                        reason: CompletionReason::Stale,
+                        phases: None,
+                        timings: None,
+                    });
                 }
+            }
 
-                // Also cleanup abandoned connections (30 seconds of inactivity - for ESC/user interrupts)
-                let abandoned = tracker_guard.cleanup_abandoned_connections(30); // 30 seconds for faster cleanup
-                if !abandoned.is_empty() {
-                    if !self.dashboard_mode {
-                        println!(
-                            "🧹 Cleaned up {} abandoned connections (likely interrupted)",
-                            abandoned.len()
-                        );
-                    }
-                    // Send cleanup events for abandoned connections
-                    for connection_id in abandoned {
-                        let _ = self
-                            .event_sender
-                            .send(ProxyEvent::ConnectionCompleted(connection_id));
-                    }
+            // Also cleanup abandoned connections (30 seconds of inactivity - for ESC/user interrupts)
+            let abandoned = tracker_guard.cleanup_abandoned_connections(30); // 30 seconds for faster cleanup
+            if !abandoned.is_empty() {
+                if !self.dashboard_mode {
+                    console_line(&i18n::console().abandoned_cleaned(abandoned.len()));
                 }
+                // Send cleanup events for abandoned connections
+                for connection_id in abandoned {
+                    let _ = self.event_sender.send(ProxyEvent::ConnectionCompleted {
+                        id: connection_id,
+                        reason: CompletionReason::Abandoned,
+                        phases: None,
+                        timings: None,
+                    });
+                }
+            }
+        }
+
+        let standby = {
+            let mut state_guard = self.state.lock_or_recover();
+            state_guard
+                .update_standby()
+                .then(|| state_guard.standby_url().map(str::to_string))
+        };
+        if let Some(standby) = standby {
+            if !self.dashboard_mode {
+                log_standby_changed(standby.as_deref());
             }
         }
 
+        self.settle_tier().await;
+
+        let summary = {
+            let mut state_guard = self.state.lock_or_recover();
+            let summary = cycle_result.summary(sequence, &state_guard);
+            state_guard.last_cycle_summary = Some(summary.clone());
+            summary
+        };
+        let _ = self
+            .event_sender
+            .send(ProxyEvent::HealthCycleSummary(summary.clone()));
+
         if !self.dashboard_mode {
-            println!(
-                "✅ Health check completed in {}ms, found {} endpoints",
-                cycle_result.duration.as_millis(),
-                cycle_result.results.len()
-            );
+            if self.config.logging.cycle_summary {
+                log_health_cycle_summary(&summary);
+            } else {
+                console_line(
+                    &i18n::console().cycle_completed(summary.duration_ms, summary.checked),
+                );
+            }
         }
 
         Ok(())
     }
 
+    /// Move to the fastest healthy endpoint of the active tier when the
+    /// current endpoint is not in it: the race defers a less preferred tier
+    /// while a preferred one is still being checked, so a failover across
+    /// tiers is only decided once the cycle's results are in
+    async fn settle_tier(&self) {
+        let switch = {
+            let state_guard = self.state.lock_or_recover();
+            if selection::switching_blocked(&state_guard).is_some() {
+                return;
+            }
+            let Some(best) = selection::best_of_cycle(&state_guard).cloned() else {
+                return;
+            };
+            selection::switch_decision(&best, &state_guard, chrono::Local::now())
+                .ok()
+                .map(|decision| (best, decision))
+        };
+
+        if let Some((best, (from_endpoint, from_latency, to_latency, reason))) = switch {
+            self.perform_endpoint_switch(
+                &best,
+                from_endpoint,
+                from_latency,
+                to_latency,
+                reason,
+                &self.state,
+                &self.event_sender,
+            )
+            .await;
+        }
+    }
+
     /// Check if this endpoint wins the race (first available wins) - only in Auto mode
     async fn check_race_winner(
         &self,
         status: &EndpointStatus,
         state: &SharedState,
         event_sender: &mpsc::UnboundedSender<ProxyEvent>,
-        cycle_winner_chosen: std::sync::Arc<std::sync::Mutex<bool>>,
+        cycle_winner_chosen: Arc<AtomicBool>,
     ) {
-        // Only available endpoints can win the race
-        if !status.available {
-            return;
-        }
-
-        // Check selection mode from proxy state - skip auto-switching in manual mode
-        let is_auto_mode = {
-            if let Ok(state_guard) = state.lock() {
-                state_guard.selection_mode == SelectionMode::Auto
-            } else {
-                false // Default to no switching if lock fails
-            }
+        // Only available endpoints can win the race, and none in manual mode.
+        // Endpoints outside auto_switch_scope or the tag filter must not claim
+        // it either, or they would block an eligible endpoint from winning this
+        // cycle. A less preferred tier may only win once every endpoint of the
+        // preferred tiers failed; the end of the cycle settles it otherwise.
+        let may_claim = {
+            let state_guard = state.lock_or_recover();
+            state_guard.selection_mode == SelectionMode::Auto
+                && selection::race_eligibility(status, &state_guard).is_ok()
         };
-
-        if !is_auto_mode {
-            // In manual mode, don't perform automatic switching
+        if !may_claim {
             return;
         }
 
-        // Try to claim the race winner spot
-        let won_race = {
-            if let Ok(mut winner_chosen) = cycle_winner_chosen.lock() {
-                if !*winner_chosen {
-                    // This endpoint is first available one, it wins!
-                    *winner_chosen = true;
-                    true
-                } else {
-                    // Someone already won, this endpoint is too late
-                    false
-                }
-            } else {
-                false
-            }
-        };
+        // Try to claim the race winner spot: the first available endpoint wins
+        let won_race = cycle_winner_chosen
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok();
 
         // If this endpoint won the race, switch to it
         if won_race {
             let switch_info = {
-                let state_guard = match state.lock() {
-                    Ok(guard) => guard,
-                    Err(_) => return,
-                };
+                let state_guard = state.lock_or_recover();
 
-                self.calculate_switch_decision(status, &state_guard)
+                selection::switch_decision(status, &state_guard, chrono::Local::now()).ok()
             };
 
-            if let Some((from_endpoint, from_latency, to_latency)) = switch_info {
+            if let Some((from_endpoint, from_latency, to_latency, reason)) = switch_info {
                 self.perform_endpoint_switch(
                     status,
                     from_endpoint,
                     from_latency,
                     to_latency,
+                    reason,
                     state,
                     event_sender,
                 )
@@ -472,8 +1127,7 @@ impl HealthCheckOrchestrator {
         let active_connections = self
             .connection_tracker
             .as_ref()
-            .and_then(|t| t.lock().ok())
-            .map(|t| t.get_active_count())
+            .map(|t| t.lock_or_recover().get_active_count())
             .unwrap_or(0);
 
         let _ = self.event_sender.send(ProxyEvent::HealthCheckStarted {
@@ -481,37 +1135,42 @@ impl HealthCheckOrchestrator {
             next_check_time: next_check,
             load_level,
             active_connections,
+            interval_decision: self.interval_decision,
         });
     }
 
-    async fn mark_endpoints_as_checking(&self) -> anyhow::Result<()> {
-        let mut state_guard = self
-            .state
-            .lock()
-            .map_err(|e| anyhow::anyhow!("Failed to acquire state lock: {}", e))?;
+    async fn mark_endpoints_as_checking(
+        &self,
+        only: Option<&HashSet<String>>,
+    ) -> anyhow::Result<()> {
+        let mut state_guard = self.state.lock_or_recover();
 
         for (_, endpoint_config, _) in self.config.get_all_endpoints() {
-            if !state_guard
-                .endpoint_status
-                .contains_key(&endpoint_config.url)
-            {
-                let checking_status =
-                    health::EndpointStatus::new_checking(endpoint_config.url.clone());
-                state_guard
-                    .endpoint_status
-                    .insert(endpoint_config.url.clone(), checking_status.clone());
-                let _ = self
-                    .event_sender
-                    .send(ProxyEvent::HealthUpdate(checking_status));
-            } else if let Some(existing_status) =
-                state_guard.endpoint_status.get_mut(&endpoint_config.url)
-            {
-                existing_status.available = false;
-                existing_status.error = None;
-                let _ = self
-                    .event_sender
-                    .send(ProxyEvent::HealthUpdate(existing_status.clone()));
+            if only.is_some_and(|only| !only.contains(&endpoint_config.url)) {
+                continue;
             }
+            let Some(id) = state_guard
+                .config
+                .endpoint_index()
+                .id_of(&endpoint_config.url)
+                .cloned()
+            else {
+                continue;
+            };
+            let status = match state_guard.endpoint_status.entry(id) {
+                Entry::Vacant(entry) => entry.insert(health::EndpointStatus::new_checking(
+                    endpoint_config.url.clone(),
+                )),
+                Entry::Occupied(entry) => {
+                    let existing_status = entry.into_mut();
+                    existing_status.state = CheckState::Checking;
+                    existing_status.error = None;
+                    existing_status
+                }
+            };
+            let _ = self
+                .event_sender
+                .send(ProxyEvent::HealthUpdate(status.clone()));
         }
 
         Ok(())
@@ -522,32 +1181,55 @@ impl HealthCheckOrchestrator {
         new_status: &EndpointStatus,
         state: &SharedState,
     ) -> Option<EndpointStatus> {
-        let mut state_guard = state.lock().ok()?;
-
-        let updated_status =
-            if let Some(existing_status) = state_guard.endpoint_status.get(&new_status.endpoint) {
-                let mut updated = existing_status.clone();
-                if new_status.available {
-                    updated.update_with_check_result(Some(new_status.latency), None);
-                } else {
-                    updated.update_with_check_result(None, new_status.error.clone());
-                }
-                updated
+        let mut state_guard = state.lock_or_recover();
+        // An endpoint removed by a reload while it was being checked
+        let id = state_guard
+            .config
+            .endpoint_index()
+            .id_of(&new_status.endpoint)?
+            .clone();
+
+        let mut updated_status = if let Some(existing_status) = state_guard.endpoint_status.get(&id)
+        {
+            let mut updated = existing_status.clone();
+            if new_status.is_available() {
+                updated.update_with_check_result(new_status.latency, None);
             } else {
-                // First time seeing this endpoint - use new status but ensure it has the measurement
-                let mut first_time = new_status.clone();
-                if new_status.available {
-                    first_time.update_with_check_result(Some(new_status.latency), None);
-                } else {
-                    first_time.update_with_check_result(None, new_status.error.clone());
-                }
-                first_time
-            };
+                updated.update_with_check_result(None, new_status.error.clone());
+            }
+            updated.ttfb_ms = new_status.ttfb_ms;
+            updated.auth_failed = new_status.auth_failed;
+            updated.cold_latency_ms = new_status.cold_latency_ms.or(updated.cold_latency_ms);
+            updated.warm_latency_ms = new_status.warm_latency_ms.or(updated.warm_latency_ms);
+            updated.latency_history.set_latest_phases(
+                new_status
+                    .latency_history
+                    .get_latest()
+                    .and_then(|measurement| measurement.phases),
+            );
+            updated
+        } else {
+            // First time seeing this endpoint - use new status but ensure it has the measurement
+            let mut first_time = new_status.clone();
+            if new_status.is_available() {
+                first_time.update_with_check_result(new_status.latency, None);
+            } else {
+                first_time.update_with_check_result(None, new_status.error.clone());
+            }
+            first_time
+        };
+        // The history keeps what each check took, decisions what `measure` says
+        updated_status.latency =
+            updated_status.measured_latency(state_guard.config.health_check.measure);
+
+        state_guard
+            .metrics
+            .record_health_check(&new_status.endpoint);
 
         // Update the state with the merged status in the same lock scope
         state_guard
             .endpoint_status
-            .insert(updated_status.endpoint.clone(), updated_status.clone());
+            .insert(id, updated_status.clone());
 
         Some(updated_status)
     }
@@ -559,25 +1241,23 @@ impl HealthCheckOrchestrator {
         state: &SharedState,
         event_sender: &mpsc::UnboundedSender<ProxyEvent>,
     ) {
-        if !status.available {
+        if !status.is_available() {
             return;
         }
 
         let switch_info = {
-            let state_guard = match state.lock() {
-                Ok(guard) => guard,
-                Err(_) => return,
-            };
+            let state_guard = state.lock_or_recover();
 
-            self.calculate_switch_decision(status, &state_guard)
+            selection::switch_decision(status, &state_guard, chrono::Local::now()).ok()
         };
 
-        if let Some((from_endpoint, from_latency, to_latency)) = switch_info {
+        if let Some((from_endpoint, from_latency, to_latency, reason)) = switch_info {
             self.perform_endpoint_switch(
                 status,
                 from_endpoint,
                 from_latency,
                 to_latency,
+                reason,
                 state,
                 event_sender,
             )
@@ -585,96 +1265,38 @@ impl HealthCheckOrchestrator {
         }
     }
 
-    fn calculate_switch_decision(
-        &self,
-        status: &EndpointStatus,
-        state_guard: &crate::proxy::ProxyState,
-    ) -> Option<(String, u64, u64)> {
-        let current = &state_guard.current_endpoint;
-        let threshold = self.config.server.switch_threshold_ms;
-
-        // Only consider available endpoints for switching
-        if !status.available {
-            return None;
-        }
-
-        // Switch immediately if:
-        // 1. No current endpoint, OR
-        // 2. Current endpoint is not available, OR
-        // 3. This endpoint is significantly faster than current
-        if current.is_empty() {
-            Some((String::new(), 999999, status.latency))
-        } else if let Some(current_status) = state_guard.endpoint_status.get(current) {
-            if !current_status.available {
-                // Current is down, switch immediately
-                Some((current.clone(), current_status.latency, status.latency))
-            } else if current_status.latency.saturating_sub(status.latency) >= threshold {
-                // This endpoint is significantly faster than current
-                Some((current.clone(), current_status.latency, status.latency))
-            } else {
-                None
-            }
-        } else {
-            // Current endpoint has no status, switch to this one
-            Some((current.clone(), 999999, status.latency))
-        }
-    }
-
+    #[allow(clippy::too_many_arguments)]
     async fn perform_endpoint_switch(
         &self,
         status: &EndpointStatus,
         from_endpoint: String,
-        from_latency: u64,
-        to_latency: u64,
+        from_latency: Option<u64>,
+        to_latency: Option<u64>,
+        reason: SwitchReason,
         state: &SharedState,
         event_sender: &mpsc::UnboundedSender<ProxyEvent>,
     ) {
-        if let Ok(mut state_guard) = state.lock() {
-            if self.dashboard_mode {
-                state_guard.switch_endpoint_silent(status.endpoint.clone());
-            } else {
-                state_guard.switch_endpoint(status.endpoint.clone());
-            }
-
-            // Clean up orphaned connections after endpoint switch
-            if let Some(ref tracker) = self.connection_tracker {
-                if let Ok(mut tracker_guard) = tracker.lock() {
-                    let orphaned = tracker_guard.cleanup_orphaned_connections(&status.endpoint);
-                    if !orphaned.is_empty() {
-                        if !self.dashboard_mode {
-                            println!(
-                                "🧹 Cleaned up {} orphaned connections after endpoint switch",
-                                orphaned.len()
-                            );
-                        }
-                        // Send cleanup event for each orphaned connection
-                        for connection_id in orphaned {
-                            let _ =
-                                event_sender.send(ProxyEvent::ConnectionCompleted(connection_id));
-                        }
-                    }
-                }
-            }
-
-            let _ = event_sender.send(ProxyEvent::EndpointSwitch {
-                from: from_endpoint,
-                to: status.endpoint.clone(),
-                from_latency,
-                to_latency,
-            });
-        }
+        let mut state_guard = state.lock_or_recover();
+        // Connections on the previous endpoint stay tracked while it drains
+        state_guard.switch_endpoint(status.endpoint.clone(), reason, self.dashboard_mode);
+
+        let _ = event_sender.send(ProxyEvent::EndpointSwitch {
+            from: from_endpoint,
+            to: status.endpoint.clone(),
+            from_latency,
+            to_latency,
+            reason,
+        });
     }
 
     /// Handle system pause command
     async fn handle_pause(&self) {
-        if let Ok(mut is_paused) = self.is_paused.lock() {
-            *is_paused = true;
-        }
+        self.is_paused.store(true, Ordering::Relaxed);
 
         let _ = self.event_sender.send(ProxyEvent::SystemPaused);
 
         if !self.dashboard_mode {
-            println!("⏸️  Health monitoring paused - manual refresh available with 'R'");
+            console_line(i18n::console().monitoring_paused());
         }
     }
 
@@ -684,9 +1306,7 @@ impl HealthCheckOrchestrator {
         next_check: &mut tokio::time::Instant,
         _current_interval: Duration,
     ) {
-        if let Ok(mut is_paused) = self.is_paused.lock() {
-            *is_paused = false;
-        }
+        self.is_paused.store(false, Ordering::Relaxed);
 
         // Schedule immediate check on resume
         *next_check = tokio::time::Instant::now();
@@ -694,40 +1314,291 @@ impl HealthCheckOrchestrator {
         let _ = self.event_sender.send(ProxyEvent::SystemResumed);
 
         if !self.dashboard_mode {
-            println!("▶️  Health monitoring resumed");
+            console_line(i18n::console().monitoring_resumed());
+        }
+    }
+
+    /// Handle freeze/unfreeze commands; cycles keep running either way
+    fn set_switching_frozen(&self, frozen: bool) {
+        self.state.lock_or_recover().switching_frozen = frozen;
+
+        let _ = self
+            .event_sender
+            .send(ProxyEvent::SwitchingFrozen { frozen });
+
+        if !self.dashboard_mode {
+            if frozen {
+                console_line(i18n::console().auto_switching_frozen());
+            } else {
+                console_line(i18n::console().auto_switching_unfrozen());
+            }
+        }
+    }
+
+    /// Read the config file again and apply it to the proxy state. Returns
+    /// whether the endpoints changed, so a health check cycle should run now.
+    fn reload_config(&mut self) -> bool {
+        let Some(path) = self.config.source_path.clone() else {
+            self.report_reload_failure("the config was not loaded from a file".to_string());
+            return false;
+        };
+        let mut config = match Config::reload_from_file(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                self.report_reload_failure(format!("{e:#}"));
+                return false;
+            }
+        };
+
+        // New ports are served before removed ones close. When one cannot be
+        // bound the running listeners stay as they are, and so does their config.
+        let listeners = self.reconcile_listeners(&config.listeners());
+        if listeners.is_err() {
+            config.server.port = self.config.server.port;
+            config.server.listeners = self.config.server.listeners.clone();
+        }
+
+        let active = self
+            .connection_tracker
+            .as_ref()
+            .map(|tracker| {
+                tracker
+                    .lock_or_recover()
+                    .get_endpoint_distribution()
+                    .clone()
+            })
+            .unwrap_or_default();
+        let (mut diff, switch, current) = {
+            let mut state_guard = self.state.lock_or_recover();
+            let (diff, switch) =
+                state_guard.apply_config(config.clone(), &active, self.dashboard_mode);
+            (diff, switch, state_guard.current_url().to_string())
+        };
+        match listeners {
+            Ok(changes) => {
+                diff.listeners_opened = changes.opened;
+                diff.listeners_closed = changes.closed;
+            }
+            Err(error) => diff.listener_error = Some(error),
+        }
+        i18n::set_console_language(config.ui.language.clone());
+        self.config = config;
+        self.groups_without_token = self
+            .config
+            .groups_without_token()
+            .into_iter()
+            .map(|group| group.name.clone())
+            .collect();
+
+        if !self.dashboard_mode {
+            log_config_reloaded(&path.display().to_string(), &diff);
+        }
+        if let Some(record) = switch {
+            let _ = self.event_sender.send(ProxyEvent::EndpointSwitch {
+                from: record.from,
+                to: record.to,
+                from_latency: record.from_latency,
+                to_latency: record.to_latency,
+                reason: record.reason,
+            });
+        }
+        let changed = !diff.is_empty();
+        let _ = self
+            .event_sender
+            .send(ProxyEvent::ConfigReloaded { diff, current });
+        changed
+    }
+
+    /// Serve `desired` on the running server, if there is one
+    fn reconcile_listeners(&self, desired: &[ListenerConfig]) -> Result<ListenerChanges, String> {
+        let listeners = self.state.lock_or_recover().listeners.upgrade();
+        match listeners {
+            Some(listeners) => listeners.reconcile(desired),
+            None => Ok(ListenerChanges::default()),
+        }
+    }
+
+    fn report_reload_failure(&self, error: String) {
+        if !self.dashboard_mode {
+            log_config_reload_failed(&error);
         }
+        let _ = self
+            .event_sender
+            .send(ProxyEvent::ConfigReloadFailed { error });
     }
 
     /// Handle manual refresh command - can work in both paused and running states
     async fn handle_manual_refresh(
         &mut self,
         current_interval: &mut Duration,
+        next_check: tokio::time::Instant,
     ) -> anyhow::Result<()> {
+        if self.skip_disabled_checks() {
+            return Ok(());
+        }
         let _ = self.event_sender.send(ProxyEvent::ManualRefreshTriggered);
 
         if !self.dashboard_mode {
-            println!("🔄 Manual health check triggered...");
+            console_line(i18n::console().manual_check_started());
         }
 
+        self.refresh_group_tokens();
+
         // Calculate optimal check interval
         let check_interval = self.calculate_optimal_interval(current_interval);
 
-        // Execute health check cycle
-        let cycle_result = self.execute_health_cycle(check_interval).await;
+        // Execute health check cycle, leaving out endpoints in maintenance
+        let only = self.manual_check_endpoints();
+        let cycle_result = self
+            .execute_health_cycle(check_interval, only.as_ref())
+            .await;
 
         // Handle cycle results and update state
         self.process_cycle_results(cycle_result).await?;
 
         // Cleanup and prepare for next cycle
         self.prepare_next_cycle();
+        self.update_all_down(next_check);
 
         if !self.dashboard_mode {
-            println!("✅ Manual health check completed");
+            console_line(i18n::console().manual_check_completed());
         }
 
         Ok(())
     }
 
+    /// Reset the endpoint at `endpoint` and check it, unless it is in
+    /// maintenance. An endpoint no longer configured is ignored.
+    async fn handle_reset_endpoint(
+        &mut self,
+        endpoint: &str,
+        source: ResetSource,
+        current_interval: &mut Duration,
+    ) -> anyhow::Result<()> {
+        let Some(status) = self.state.lock_or_recover().reset_endpoint(endpoint) else {
+            return Ok(());
+        };
+        if !self.dashboard_mode {
+            log_endpoint_reset(&status.endpoint, source.as_str());
+        }
+        let in_maintenance = status.maintenance_until.is_some();
+        let only = HashSet::from([status.endpoint.clone()]);
+        let _ = self.event_sender.send(ProxyEvent::HealthUpdate(status));
+        if in_maintenance || self.skip_disabled_checks() {
+            return Ok(());
+        }
+
+        let check_interval = self.calculate_optimal_interval(current_interval);
+        let cycle_result = self.execute_health_cycle(check_interval, Some(&only)).await;
+        self.process_cycle_results(cycle_result).await
+    }
+
+    /// Handle the failed-only refresh command: re-check unavailable endpoints,
+    /// any of which may win the race if it recovers
+    async fn handle_refresh_failed(
+        &mut self,
+        current_interval: &mut Duration,
+    ) -> anyhow::Result<()> {
+        if self.skip_disabled_checks() {
+            return Ok(());
+        }
+        let failed = failed_endpoints(&self.state);
+        if failed.is_empty() {
+            if !self.dashboard_mode {
+                console_line(i18n::console().no_failed_endpoints());
+            }
+            return Ok(());
+        }
+
+        let _ = self.event_sender.send(ProxyEvent::ManualRefreshTriggered);
+
+        if !self.dashboard_mode {
+            console_line(&i18n::console().rechecking_failed(failed.len()));
+        }
+
+        let check_interval = self.calculate_optimal_interval(current_interval);
+        let failed: HashSet<String> = failed.into_iter().collect();
+        let cycle_result = self
+            .execute_health_cycle(check_interval, Some(&failed))
+            .await;
+        self.process_cycle_results(cycle_result).await?;
+        self.prepare_next_cycle();
+
+        Ok(())
+    }
+
+    /// Whether health checks are disabled for a missing Claude CLI, saying so
+    /// when a check was asked for
+    fn skip_disabled_checks(&self) -> bool {
+        let disabled = self.config.health_checks_disabled();
+        if disabled && !self.dashboard_mode {
+            console_line(i18n::console().health_checks_disabled());
+        }
+        disabled
+    }
+
+    /// After a full cycle, fail requests fast until `next_check` if it left
+    /// every endpoint down, or stop failing them fast if it did not, and
+    /// report the groups that went down or recovered
+    fn update_all_down(&self, next_check: tokio::time::Instant) {
+        let (all_down, was_down) = {
+            let mut state_guard = self.state.lock_or_recover();
+            let all_down = !state_guard.endpoint_status.is_empty()
+                && !state_guard
+                    .endpoint_status
+                    .values()
+                    .any(|status| status.is_available());
+            let was_down = std::mem::replace(
+                &mut state_guard.all_down_until,
+                all_down.then(|| next_check.into_std()),
+            )
+            .is_some();
+            (all_down, was_down)
+        };
+
+        let _ = self
+            .event_sender
+            .send(ProxyEvent::AllEndpointsDown { down: all_down });
+        if all_down && !was_down && !self.dashboard_mode {
+            log_all_endpoints_down(self.config.retry.fail_fast_when_all_down);
+        }
+        self.update_down_groups();
+    }
+
+    /// Send one [`ProxyEvent::GroupDown`] for each group whose endpoints all
+    /// went down, or that has a healthy or pending endpoint again
+    fn update_down_groups(&self) {
+        let changes = {
+            let mut state_guard = self.state.lock_or_recover();
+            let groups = group_health::summarize(
+                state_guard.config.endpoint_index(),
+                &state_guard.endpoint_status,
+                &HashMap::new(),
+                &BTreeMap::new(),
+            );
+            let changes = group_health::down_changes(&state_guard.down_groups, &groups);
+            state_guard.down_groups = groups
+                .into_iter()
+                .filter(|group| group.down)
+                .map(|group| group.name)
+                .collect::<BTreeSet<_>>();
+            changes
+        };
+
+        for (group, down) in changes {
+            if !self.dashboard_mode {
+                if down {
+                    log_group_down(&group);
+                } else {
+                    log_group_recovered(&group);
+                }
+            }
+            let _ = self
+                .event_sender
+                .send(ProxyEvent::GroupDown { group, down });
+        }
+    }
+
     /// Record a request for load tracking (reserved for future use)
     #[allow(dead_code)]
     pub fn record_request(&mut self) {
@@ -741,10 +1612,181 @@ impl HealthCheckOrchestrator {
     }
 }
 
+/// Endpoints currently inside a maintenance window
+fn maintenance_endpoints(state: &ProxyState) -> HashSet<String> {
+    state
+        .endpoint_status
+        .values()
+        .filter(|status| status.maintenance_until.is_some())
+        .map(|status| status.endpoint.clone())
+        .collect()
+}
+
+/// Configured endpoints whose last health check failed, or that have none
+/// yet, leaving out those in maintenance
+pub(crate) fn failed_endpoints(state: &SharedState) -> Vec<String> {
+    let state_guard = state.lock_or_recover();
+    state_guard
+        .config
+        .get_all_endpoints()
+        .into_iter()
+        .map(|(_, endpoint, _)| endpoint.url)
+        .filter(|url| !state_guard.in_maintenance(url))
+        .filter(|url| {
+            state_guard
+                .status_of(url)
+                .is_none_or(|status| !status.is_available())
+        })
+        .collect()
+}
+
 /// Result of a health check cycle
 struct HealthCycleResult {
     #[allow(dead_code)]
     start_time: Instant,
+    /// Outcome of every endpoint the cycle checked
     results: Vec<EndpointStatus>,
     duration: Duration,
+    /// Current endpoint when the cycle started
+    previous_endpoint: String,
+}
+
+impl HealthCycleResult {
+    /// Counts, the fastest endpoint and any switch, once the results are applied
+    fn summary(&self, sequence: u64, state: &ProxyState) -> HealthCycleSummary {
+        let healthy = self
+            .results
+            .iter()
+            .filter(|status| status.is_available())
+            .count();
+        let best = self
+            .results
+            .iter()
+            .filter(|status| status.is_available())
+            .filter_map(|status| Some((status.endpoint.clone(), status.latency?)))
+            .min_by_key(|(_, latency)| *latency);
+        let current = state.current_url().to_string();
+        let switched = current != self.previous_endpoint;
+
+        HealthCycleSummary {
+            sequence,
+            checked: self.results.len(),
+            healthy,
+            failed: self.results.len() - healthy,
+            best_latency_ms: best.as_ref().map(|(_, latency)| *latency),
+            best_endpoint: best.map(|(endpoint, _)| endpoint),
+            current_latency_ms: state.status_of(&current).and_then(|status| status.latency),
+            switched_to: switched.then(|| current.clone()),
+            switched_from: switched.then(|| self.previous_endpoint.clone()),
+            current_endpoint: current,
+            duration_ms: self.duration.as_millis() as u64,
+            finished_at: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoint::EndpointId;
+    use crate::test_support::{config, TWO_GROUPS};
+    use std::sync::Mutex;
+
+    /// Wait for the next event `matches` accepts, failing after a few seconds
+    async fn next_event(
+        events: &mut mpsc::UnboundedReceiver<ProxyEvent>,
+        matches: impl Fn(&ProxyEvent) -> bool,
+    ) -> ProxyEvent {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let event = events.recv().await.expect("orchestrator stopped");
+                if matches(&event) {
+                    return event;
+                }
+            }
+        })
+        .await
+        .expect("no matching event within 5s")
+    }
+
+    #[tokio::test]
+    async fn pause_and_resume_commands_are_announced() {
+        let mut config = config(TWO_GROUPS);
+        // Checks that finish at once, whatever CLI is installed
+        config.health_check.claude_binary_path = "/bin/true".to_string();
+        let state = Arc::new(Mutex::new(ProxyState::new(config.clone())));
+        let (event_sender, mut events) = mpsc::unbounded_channel();
+        let (orchestrator, handle) =
+            HealthCheckOrchestrator::new(config, state, event_sender, true, None);
+
+        // Sent before the loop starts, so the first cycle is skipped
+        handle.send(OrchestratorCommand::Pause);
+        let running = tokio::spawn(orchestrator.run());
+
+        let first = next_event(&mut events, |_| true).await;
+        assert!(matches!(first, ProxyEvent::SystemPaused), "{first:?}");
+        assert!(handle.snapshot().paused);
+        assert_eq!(handle.snapshot().last_command.as_deref(), Some("Pause"));
+
+        handle.send(OrchestratorCommand::Resume);
+        next_event(&mut events, |event| {
+            matches!(event, ProxyEvent::SystemResumed)
+        })
+        .await;
+        next_event(&mut events, |event| {
+            matches!(event, ProxyEvent::HealthCheckStarted { .. })
+        })
+        .await;
+
+        running.abort();
+    }
+
+    #[test]
+    fn a_group_going_down_is_announced_once() {
+        let config = config(TWO_GROUPS);
+        let state = Arc::new(Mutex::new(ProxyState::new(config.clone())));
+        let (event_sender, mut events) = mpsc::unbounded_channel();
+        let (orchestrator, _handle) =
+            HealthCheckOrchestrator::new(config, state.clone(), event_sender, true, None);
+        let set = |id: &str, status: EndpointStatus| {
+            state
+                .lock()
+                .unwrap()
+                .endpoint_status
+                .insert(EndpointId::new(id), status);
+        };
+        let mut group_events = || {
+            std::iter::from_fn(|| events.try_recv().ok())
+                .filter_map(|event| match event {
+                    ProxyEvent::GroupDown { group, down } => Some((group, down)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let down = || EndpointStatus::new_unavailable(String::new(), "down".to_string());
+
+        set("alpha", down());
+        set(
+            "beta",
+            EndpointStatus::new_available(String::new(), 100, None),
+        );
+        orchestrator.update_down_groups();
+        assert_eq!(group_events(), [("main".to_string(), true)]);
+
+        // Later cycles finding it still down stay quiet
+        orchestrator.update_down_groups();
+        assert_eq!(group_events(), []);
+        assert!(state.lock().unwrap().down_groups.contains("main"));
+
+        set(
+            "alpha",
+            EndpointStatus::new_available(String::new(), 100, None),
+        );
+        set("beta", down());
+        orchestrator.update_down_groups();
+        assert_eq!(
+            group_events(),
+            [("main".to_string(), false), ("backup".to_string(), true)]
+        );
+    }
 }
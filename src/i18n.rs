@@ -1,4 +1,8 @@
+use crate::dynamic_health::{IntervalBound, IntervalDecision, LoadLevel};
+use crate::events::SwitchReason;
+use crate::lock::RwLockExt;
 use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
 
 /// Supported languages
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -26,6 +30,20 @@ pub struct I18n {
     pub language: Language,
 }
 
+/// Language of console output, `ui.language` once main has loaded the config
+static CONSOLE_LANGUAGE: RwLock<Language> = RwLock::new(Language::En);
+
+/// Print console messages in `language` from now on
+pub fn set_console_language(language: Language) {
+    *CONSOLE_LANGUAGE.write_or_recover() = language;
+}
+
+/// Text resources in the console language, for messages printed outside the
+/// dashboard
+pub fn console() -> I18n {
+    I18n::new(CONSOLE_LANGUAGE.read_or_recover().clone())
+}
+
 impl I18n {
     pub fn new(language: Language) -> Self {
         Self { language }
@@ -46,6 +64,13 @@ impl I18n {
         }
     }
 
+    pub fn monitor_only_label(&self) -> &'static str {
+        match self.language {
+            Language::En => "🩺 Monitor only - no requests are proxied",
+            Language::Zh => "🩺 仅监控 - 不代理任何请求",
+        }
+    }
+
     // Status indicators
     pub fn status_monitoring(&self) -> &'static str {
         match self.language {
@@ -54,6 +79,13 @@ impl I18n {
         }
     }
 
+    pub fn status_starting(&self) -> &'static str {
+        match self.language {
+            Language::En => "⏳ Starting…",
+            Language::Zh => "⏳ 启动中…",
+        }
+    }
+
     pub fn status_paused(&self) -> &'static str {
         match self.language {
             Language::En => "⏸️  Health checks paused",
@@ -71,8 +103,8 @@ impl I18n {
 
     pub fn btn_manual_check(&self) -> &'static str {
         match self.language {
-            Language::En => "[R] Manual Check",
-            Language::Zh => "[R] 手动检查",
+            Language::En => "[r] Manual Check [R] Failed Only",
+            Language::Zh => "[r] 手动检查 [R] 仅失败端点",
         }
     }
 
@@ -90,6 +122,34 @@ impl I18n {
         }
     }
 
+    pub fn btn_freeze(&self) -> &'static str {
+        match self.language {
+            Language::En => "[F] Freeze",
+            Language::Zh => "[F] 冻结切换",
+        }
+    }
+
+    pub fn btn_unfreeze(&self) -> &'static str {
+        match self.language {
+            Language::En => "[F] Unfreeze",
+            Language::Zh => "[F] 解除冻结",
+        }
+    }
+
+    pub fn switching_frozen(&self) -> &'static str {
+        match self.language {
+            Language::En => "FROZEN",
+            Language::Zh => "已冻结",
+        }
+    }
+
+    pub fn shedding_load(&self) -> &'static str {
+        match self.language {
+            Language::En => "SHEDDING LOAD",
+            Language::Zh => "正在限流",
+        }
+    }
+
     pub fn btn_to_manual(&self) -> &'static str {
         match self.language {
             Language::En => "[M] Manual Mode",
@@ -104,6 +164,20 @@ impl I18n {
         }
     }
 
+    pub fn btn_history(&self) -> &'static str {
+        match self.language {
+            Language::En => "[H] History",
+            Language::Zh => "[H] 切换历史",
+        }
+    }
+
+    pub fn btn_tags(&self) -> &'static str {
+        match self.language {
+            Language::En => "[T] Tags",
+            Language::Zh => "[T] 标签",
+        }
+    }
+
     pub fn btn_browse_endpoints(&self) -> &'static str {
         match self.language {
             Language::En => " │ [↑↓] Browse Endpoints",
@@ -140,6 +214,266 @@ impl I18n {
         }
     }
 
+    pub fn manual_endpoint_removed(&self, endpoint: &str) -> String {
+        match self.language {
+            Language::En => {
+                format!(
+                    "Manually selected endpoint {endpoint} is no longer listed, switched to Auto"
+                )
+            }
+            Language::Zh => format!("手动选择的端点 {endpoint} 已不在列表中，已切换到自动模式"),
+        }
+    }
+
+    pub fn confirm_endpoint_reset(&self, endpoint: &str) -> String {
+        match self.language {
+            Language::En => format!(
+                "Reset {endpoint}? Press X again to clear its latency history and errors, any other key to cancel"
+            ),
+            Language::Zh => format!("重置 {endpoint}？再按 X 清除其延迟历史和错误，按其他键取消"),
+        }
+    }
+
+    pub fn confirm_forced_selection(&self, endpoint: &str) -> String {
+        match self.language {
+            Language::En => format!(
+                "{endpoint} is DOWN - press Enter again to force the switch, any other key to cancel"
+            ),
+            Language::Zh => format!("{endpoint} 已宕机 - 再按 Enter 强制切换，按其他键取消"),
+        }
+    }
+
+    pub fn pinned_to_unhealthy(&self, endpoint: &str) -> String {
+        match self.language {
+            Language::En => format!("Traffic is pinned to {endpoint}, which is DOWN"),
+            Language::Zh => format!("流量已手动固定到宕机的 {endpoint}"),
+        }
+    }
+
+    pub fn switch_forced(&self) -> &'static str {
+        match self.language {
+            Language::En => "forced",
+            Language::Zh => "强制",
+        }
+    }
+
+    pub fn endpoint_reset(&self, endpoint: &str) -> String {
+        match self.language {
+            Language::En => format!("Reset {endpoint}, checking it again"),
+            Language::Zh => format!("已重置 {endpoint}，正在重新检查"),
+        }
+    }
+
+    pub fn draining(&self, active: u32) -> String {
+        match self.language {
+            Language::En => format!("draining ({active})"),
+            Language::Zh => format!("排空中 ({active})"),
+        }
+    }
+
+    pub fn standby(&self) -> &'static str {
+        match self.language {
+            Language::En => "standby",
+            Language::Zh => "备用",
+        }
+    }
+
+    pub fn group_down(&self) -> &'static str {
+        match self.language {
+            Language::En => "all down",
+            Language::Zh => "全部不可用",
+        }
+    }
+
+    pub fn group_down_notice(&self, group: &str) -> String {
+        match self.language {
+            Language::En => format!("Every endpoint of group {group} is down"),
+            Language::Zh => format!("分组 {group} 的所有端点均不可用"),
+        }
+    }
+
+    pub fn group_recovered_notice(&self, group: &str) -> String {
+        match self.language {
+            Language::En => format!("Group {group} has a usable endpoint again"),
+            Language::Zh => format!("分组 {group} 已恢复可用端点"),
+        }
+    }
+
+    pub fn group_connections(&self, count: u32) -> String {
+        match self.language {
+            Language::En => format!("{count} active"),
+            Language::Zh => format!("{count} 个活动连接"),
+        }
+    }
+
+    pub fn not_checked(&self) -> &'static str {
+        match self.language {
+            Language::En => "not checked",
+            Language::Zh => "未检查",
+        }
+    }
+
+    pub fn no_token(&self) -> &'static str {
+        match self.language {
+            Language::En => "no token",
+            Language::Zh => "无令牌",
+        }
+    }
+
+    pub fn alert_banner(&self, rule: &str, endpoint: Option<&str>, more: usize) -> String {
+        let target = endpoint.map(|e| format!(" · {e}")).unwrap_or_default();
+        match (&self.language, more) {
+            (Language::En, 0) => format!("🔔 ALERT: {rule}{target}"),
+            (Language::En, _) => format!("🔔 ALERT: {rule}{target} (+{more} more)"),
+            (Language::Zh, 0) => format!("🔔 告警: {rule}{target}"),
+            (Language::Zh, _) => format!("🔔 告警: {rule}{target}（另有 {more} 条）"),
+        }
+    }
+
+    pub fn alert_resolved(&self, rule: &str, endpoint: Option<&str>) -> String {
+        let target = endpoint.map(|e| format!(" · {e}")).unwrap_or_default();
+        match self.language {
+            Language::En => format!("✅ Alert resolved: {rule}{target}"),
+            Language::Zh => format!("✅ 告警已恢复: {rule}{target}"),
+        }
+    }
+
+    pub fn all_endpoints_down_banner(&self, next_check_seconds: u64) -> String {
+        match self.language {
+            Language::En => format!(
+                "🚨 ALL ENDPOINTS DOWN — failing fast (next check in {next_check_seconds}s)"
+            ),
+            Language::Zh => {
+                format!("🚨 所有端点不可用 — 请求快速失败（{next_check_seconds}秒后检查）")
+            }
+        }
+    }
+
+    pub fn terminal_too_small(&self, width: u16, height: u16) -> String {
+        match self.language {
+            Language::En => format!("Terminal too small (need ≥{width}x{height})"),
+            Language::Zh => format!("终端窗口太小（至少需要 {width}x{height}）"),
+        }
+    }
+
+    pub fn config_reloaded(&self, added: usize, removed: usize, changed: usize) -> String {
+        match self.language {
+            Language::En => {
+                format!("Config reloaded: +{added} endpoints, −{removed}, {changed} changed")
+            }
+            Language::Zh => {
+                format!("配置已重载：新增 {added} 个端点，移除 {removed} 个，变更 {changed} 个")
+            }
+        }
+    }
+
+    pub fn cycle_switched(
+        &self,
+        sequence: u64,
+        endpoint: &str,
+        healthy: usize,
+        checked: usize,
+    ) -> String {
+        match self.language {
+            Language::En => {
+                format!("Cycle #{sequence} switched to {endpoint} ({healthy}/{checked} healthy)")
+            }
+            Language::Zh => {
+                format!("第 {sequence} 轮检查切换到 {endpoint}（{healthy}/{checked} 个健康）")
+            }
+        }
+    }
+
+    pub fn cycle_failures(&self, sequence: u64, failed: usize, checked: usize) -> String {
+        match self.language {
+            Language::En => format!("Cycle #{sequence}: {failed} of {checked} endpoints failed"),
+            Language::Zh => format!("第 {sequence} 轮检查：{checked} 个端点中 {failed} 个失败"),
+        }
+    }
+
+    pub fn listeners_not_changed(&self, error: &str) -> String {
+        match self.language {
+            Language::En => format!("Config reloaded, but ports unchanged: cannot bind {error}"),
+            Language::Zh => format!("配置已重载，但端口未变更：无法绑定 {error}"),
+        }
+    }
+
+    pub fn config_reload_failed(&self, error: &str) -> String {
+        match self.language {
+            Language::En => format!("Config not reloaded: {error}"),
+            Language::Zh => format!("配置重载失败：{error}"),
+        }
+    }
+
+    pub fn auth_rejected(&self, endpoint: &str) -> String {
+        match self.language {
+            Language::En => {
+                format!(
+                    "{endpoint} rejected the token, checks paused until it changes (R re-checks)"
+                )
+            }
+            Language::Zh => format!("{endpoint} 拒绝了令牌，令牌变更前暂停检查（按 R 重新检查）"),
+        }
+    }
+
+    pub fn diagnostics_saved(&self, path: &str) -> String {
+        match self.language {
+            Language::En => format!("Diagnostics saved to {path}"),
+            Language::Zh => format!("诊断信息已保存到 {path}"),
+        }
+    }
+
+    pub fn table_exported(&self, path: &str) -> String {
+        match self.language {
+            Language::En => format!("Endpoint table exported to {path}"),
+            Language::Zh => format!("端点表已导出到 {path}"),
+        }
+    }
+
+    pub fn table_export_failed(&self, error: &str) -> String {
+        match self.language {
+            Language::En => format!("Endpoint table not exported: {error}"),
+            Language::Zh => format!("端点表导出失败：{error}"),
+        }
+    }
+
+    pub fn diagnostics_failed(&self, error: &str) -> String {
+        match self.language {
+            Language::En => format!("Diagnostics not saved: {error}"),
+            Language::Zh => format!("诊断信息保存失败：{error}"),
+        }
+    }
+
+    pub fn no_token_selected(&self, token_env: &str) -> String {
+        match self.language {
+            Language::En => format!("{token_env} is not set; this endpoint can't be selected"),
+            Language::Zh => format!("{token_env} 未设置，无法选择此端点"),
+        }
+    }
+
+    pub fn maintenance_selected(&self, time: &str) -> String {
+        match self.language {
+            Language::En => format!("This endpoint is in maintenance until {time}"),
+            Language::Zh => format!("此端点维护中，{time} 后可选择"),
+        }
+    }
+
+    pub fn no_tags_configured(&self) -> &'static str {
+        match self.language {
+            Language::En => "No endpoint has tags; add tags = [...] to endpoints in config.toml",
+            Language::Zh => "没有端点配置标签，请在 config.toml 的端点中添加 tags = [...]",
+        }
+    }
+
+    pub fn tag_picker_title(&self) -> &'static str {
+        match self.language {
+            Language::En => {
+                "🏷 Tag filter - [↑↓] Move [Space] Toggle [C] Clear [Enter] Apply [T/Esc] Close"
+            }
+            Language::Zh => "🏷 标签筛选 - [↑↓] 移动 [空格] 选择 [C] 清除 [Enter] 应用 [T/Esc] 关闭",
+        }
+    }
+
     // Status text
     pub fn status_checking(&self) -> &'static str {
         match self.language {
@@ -169,6 +503,27 @@ impl I18n {
         }
     }
 
+    pub fn status_maintenance(&self) -> &'static str {
+        match self.language {
+            Language::En => "🔧",
+            Language::Zh => "🔧",
+        }
+    }
+
+    pub fn maintenance_until(&self, time: &str) -> String {
+        match self.language {
+            Language::En => format!("until {time}"),
+            Language::Zh => format!("维护至 {time}"),
+        }
+    }
+
+    pub fn error_auth(&self) -> &'static str {
+        match self.language {
+            Language::En => "AUTH",
+            Language::Zh => "认证",
+        }
+    }
+
     pub fn error_generic(&self) -> &'static str {
         match self.language {
             Language::En => "Error",
@@ -191,6 +546,27 @@ impl I18n {
         }
     }
 
+    pub fn retries_per_minute(&self, count: u64) -> String {
+        match self.language {
+            Language::En => format!("{count} retries/min"),
+            Language::Zh => format!("{count}次重试/分钟"),
+        }
+    }
+
+    pub fn overhead_p90(&self, overhead: &str) -> String {
+        match self.language {
+            Language::En => format!("overhead p90 {overhead}"),
+            Language::Zh => format!("代理开销 p90 {overhead}"),
+        }
+    }
+
+    pub fn estimated_cost(&self, requests: &str, health_checks: &str) -> String {
+        match self.language {
+            Language::En => format!("{requests} (+{health_checks} health checks)"),
+            Language::Zh => format!("{requests}（健康检查 +{health_checks}）"),
+        }
+    }
+
     pub fn health_next(&self, seconds: u64) -> String {
         match self.language {
             Language::En => format!("Next: {seconds}s"),
@@ -227,6 +603,60 @@ impl I18n {
         }
     }
 
+    /// How the health check interval was calculated, e.g.
+    /// "interval 8m = base 60s × 8.0 idle-34m"
+    pub fn interval_explained(&self, interval_secs: u64, decision: &IntervalDecision) -> String {
+        let zh = self.language == Language::Zh;
+        let interval = compact_duration(interval_secs);
+        let Some(factor) = decision.scaling_factor else {
+            return match (decision.dynamic, zh) {
+                (false, false) => format!("interval {interval} (fixed)"),
+                (false, true) => format!("间隔 {interval}（固定）"),
+                (true, false) => format!("interval {interval} = min at high load"),
+                (true, true) => format!("间隔 {interval} = 高负载取下限"),
+            };
+        };
+        let base = compact_duration(decision.base_seconds);
+        let rate = decision.request_rate;
+        let cause = match (decision.load_level, zh) {
+            (LoadLevel::Idle, false) => {
+                format!(
+                    "idle-{}",
+                    compact_duration(decision.idle_seconds.unwrap_or(0))
+                )
+            }
+            (LoadLevel::Idle, true) => {
+                format!(
+                    "空闲{}",
+                    compact_duration(decision.idle_seconds.unwrap_or(0))
+                )
+            }
+            (LoadLevel::Low, false) => format!("low {rate:.0} req/min"),
+            (LoadLevel::Low, true) => format!("低负载 {rate:.0} 请求/分钟"),
+            (_, false) => format!("medium {rate:.0} req/min"),
+            (_, true) => format!("中负载 {rate:.0} 请求/分钟"),
+        };
+        let clamp = match (decision.clamped, zh) {
+            (None, _) => String::new(),
+            (Some(IntervalBound::Min), false) => format!(" → min {interval}"),
+            (Some(IntervalBound::Min), true) => format!(" → 下限 {interval}"),
+            (Some(IntervalBound::Max), false) => format!(" → max {interval}"),
+            (Some(IntervalBound::Max), true) => format!(" → 上限 {interval}"),
+        };
+        if zh {
+            format!("间隔 {interval} = 基准 {base} × {factor:.1} {cause}{clamp}")
+        } else {
+            format!("interval {interval} = base {base} × {factor:.1} {cause}{clamp}")
+        }
+    }
+
+    pub fn health_check_model(&self, model: &str) -> String {
+        match self.language {
+            Language::En => format!("check model {model}"),
+            Language::Zh => format!("检查模型 {model}"),
+        }
+    }
+
     // Switch info
     pub fn switch_new_connection(&self) -> &'static str {
         match self.language {
@@ -235,7 +665,82 @@ impl I18n {
         }
     }
 
+    /// Coarse age of an event, e.g. "12m ago"
+    pub fn time_ago(&self, seconds: u64) -> String {
+        let (value, unit_en, unit_zh) = match seconds {
+            0..=59 => (seconds, "s", "秒"),
+            60..=3599 => (seconds / 60, "m", "分钟"),
+            _ => (seconds / 3600, "h", "小时"),
+        };
+        match self.language {
+            Language::En => format!("{value}{unit_en} ago"),
+            Language::Zh => format!("{value}{unit_zh}前"),
+        }
+    }
+
+    // Switch history popup
+    pub fn switch_history_title(&self, count: usize) -> String {
+        match self.language {
+            Language::En => format!("🔀 Recent switches ({count}) - [↑↓] Scroll [H/Esc] Close"),
+            Language::Zh => format!("🔀 最近切换 ({count}) - [↑↓] 滚动 [H/Esc] 关闭"),
+        }
+    }
+
+    pub fn switch_history_empty(&self) -> &'static str {
+        match self.language {
+            Language::En => "No endpoint switches yet",
+            Language::Zh => "暂无端点切换记录",
+        }
+    }
+
+    pub fn switch_reason(&self, reason: SwitchReason) -> &'static str {
+        match (self.language.clone(), reason) {
+            (Language::En, SwitchReason::AutoRaceWin) => "auto",
+            (Language::En, SwitchReason::Failover) => "failover",
+            (Language::En, SwitchReason::ManualSwitch) => "manual",
+            (Language::En, SwitchReason::RequestFallback) => "fallback",
+            (Language::En, SwitchReason::TagFilter) => "tag filter",
+            (Language::En, SwitchReason::ConfigReload) => "config reload",
+            (Language::En, SwitchReason::Maintenance) => "maintenance",
+            (Language::En, SwitchReason::Drill) => "drill",
+            (Language::En, SwitchReason::TierFailover) => "tier failover",
+            (Language::En, SwitchReason::TierFailback) => "tier failback",
+            (Language::Zh, SwitchReason::AutoRaceWin) => "自动",
+            (Language::Zh, SwitchReason::Failover) => "故障转移",
+            (Language::Zh, SwitchReason::ManualSwitch) => "手动",
+            (Language::Zh, SwitchReason::RequestFallback) => "请求回退",
+            (Language::Zh, SwitchReason::TagFilter) => "标签筛选",
+            (Language::Zh, SwitchReason::ConfigReload) => "配置重载",
+            (Language::Zh, SwitchReason::Maintenance) => "维护",
+            (Language::Zh, SwitchReason::Drill) => "演练",
+            (Language::Zh, SwitchReason::TierFailover) => "层级故障转移",
+            (Language::Zh, SwitchReason::TierFailback) => "层级回切",
+        }
+    }
+
     // Paused subtitle
+    pub fn pause_pending_subtitle(&self, pause: bool) -> &'static str {
+        match (&self.language, pause) {
+            (Language::En, true) => "⏳ Pausing health checks… waiting for the health checker",
+            (Language::En, false) => "⏳ Resuming health checks… waiting for the health checker",
+            (Language::Zh, true) => "⏳ 正在暂停健康检查… 等待健康检查器确认",
+            (Language::Zh, false) => "⏳ 正在恢复健康检查… 等待健康检查器确认",
+        }
+    }
+
+    pub fn pause_not_confirmed(&self, pause: bool, seconds: u64) -> String {
+        match (&self.language, pause) {
+            (Language::En, true) => {
+                format!("Pause not confirmed within {seconds}s, health checks may still be running")
+            }
+            (Language::En, false) => {
+                format!("Resume not confirmed within {seconds}s, health checks may still be paused")
+            }
+            (Language::Zh, true) => format!("{seconds} 秒内未确认暂停，健康检查可能仍在运行"),
+            (Language::Zh, false) => format!("{seconds} 秒内未确认恢复，健康检查可能仍处于暂停"),
+        }
+    }
+
     pub fn paused_subtitle(&self) -> &'static str {
         match self.language {
             Language::En => {
@@ -244,4 +749,869 @@ impl I18n {
             Language::Zh => "⏸️  健康检查已暂停 - 连接监控继续运行，自动切换已停止",
         }
     }
+
+    // Config loading and validation
+
+    pub fn loading_config(&self, path: &str) -> String {
+        match self.language {
+            Language::En => format!("📋 Loading configuration from: {path}"),
+            Language::Zh => format!("📋 正在加载配置文件: {path}"),
+        }
+    }
+
+    pub fn loading_env_file(&self, path: &str, provided: &[String]) -> String {
+        match self.language {
+            Language::En => format!(
+                "📋 Loading environment variables from {path}: {}",
+                if provided.is_empty() {
+                    "none new".to_string()
+                } else {
+                    provided.join(", ")
+                }
+            ),
+            Language::Zh => format!(
+                "📋 从 {path} 加载环境变量: {}",
+                if provided.is_empty() {
+                    "无新变量".to_string()
+                } else {
+                    provided.join(", ")
+                }
+            ),
+        }
+    }
+
+    pub fn config_read_failed(&self, error: &dyn std::fmt::Display) -> String {
+        match self.language {
+            Language::En => format!("❌ Failed to read config file: {error}\n💡 Make sure the file exists and is readable"),
+            Language::Zh => format!("❌ 无法读取配置文件: {error}\n💡 请确认文件存在且可读"),
+        }
+    }
+
+    pub fn config_parse_failed(&self, error: &dyn std::fmt::Display) -> String {
+        match self.language {
+            Language::En => format!("❌ Failed to parse config file: {error}\n💡 Check your TOML syntax. Common issues:\n  • Missing quotes around strings\n  • Invalid endpoint_groups structure\n  • See config.toml.template for examples"),
+            Language::Zh => format!("❌ 无法解析配置文件: {error}\n💡 请检查 TOML 语法，常见问题：\n  • 字符串缺少引号\n  • endpoint_groups 结构无效\n  • 参考 config.toml.template 中的示例"),
+        }
+    }
+
+    pub fn config_not_found(&self) -> &'static str {
+        match self.language {
+            Language::En => "❌ No configuration file found!\n💡 Please create a config.toml file. You can:\n🔧 Copy config.toml.template to config.toml\n📝 Update the auth token and Claude binary path\n⚡ The old 'endpoints' array format is no longer supported",
+            Language::Zh => "❌ 未找到配置文件！\n💡 请创建 config.toml 文件，可以：\n🔧 将 config.toml.template 复制为 config.toml\n📝 更新认证令牌和 Claude 程序路径\n⚡ 旧的 'endpoints' 数组格式已不再支持",
+        }
+    }
+
+    pub fn env_file_not_found(&self, path: &str) -> String {
+        match self.language {
+            Language::En => format!(
+                "❌ Env file {path} not found\n💡 Check --env-file or env_file in config.toml"
+            ),
+            Language::Zh => format!(
+                "❌ 未找到 env 文件 {path}\n💡 请检查 --env-file 参数或 config.toml 中的 env_file"
+            ),
+        }
+    }
+
+    pub fn env_file_read_failed(&self, path: &str, error: &dyn std::fmt::Display) -> String {
+        match self.language {
+            Language::En => format!(
+                "❌ Failed to read env file {path}: {error}\n💡 Check the path given by --env-file or env_file"
+            ),
+            Language::Zh => format!(
+                "❌ 无法读取 env 文件 {path}: {error}\n💡 请检查 --env-file 参数或 env_file 给出的路径"
+            ),
+        }
+    }
+
+    pub fn strict_token_rejected(&self, groups: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Token rejected for group {groups}\n💡 Fix the token, or start without --strict to only mark the group"),
+            Language::Zh => format!("❌ 分组 {groups} 的令牌被拒绝\n💡 请修正令牌，或不带 --strict 启动以仅标记该分组"),
+        }
+    }
+
+    pub fn no_groups(&self) -> &'static str {
+        match self.language {
+            Language::En => "❌ No endpoint groups configured!\n💡 Please use the modern groups format in your config.toml.\n📖 See config.toml.example for examples.\n🔗 Copy config.toml.example to config.toml and update AUTH_TOKEN in .env",
+            Language::Zh => "❌ 未配置任何端点分组！\n💡 请在 config.toml 中使用新的 groups 格式。\n📖 参考 config.toml.example 中的示例。\n🔗 将 config.toml.example 复制为 config.toml，并在 .env 中更新 AUTH_TOKEN",
+        }
+    }
+
+    pub fn group_no_endpoints(&self, group: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Group '{group}' has no endpoints configured"),
+            Language::Zh => format!("❌ 分组 '{group}' 未配置任何端点"),
+        }
+    }
+
+    pub fn group_missing_token_env(&self, group: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Group '{group}' is missing auth_token_env reference"),
+            Language::Zh => format!("❌ 分组 '{group}' 缺少 auth_token_env 设置"),
+        }
+    }
+
+    pub fn placeholder_token(&self, token_env: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Please replace the placeholder auth token in '{token_env}' environment variable with your real Claude auth token"),
+            Language::Zh => format!("❌ 请将环境变量 '{token_env}' 中的占位令牌替换为真实的 Claude 认证令牌"),
+        }
+    }
+
+    pub fn invalid_price(&self, group: &str, field: &str, price: f64) -> String {
+        match self.language {
+            Language::En => format!("❌ Group '{group}' has an invalid {field}: {price}"),
+            Language::Zh => format!("❌ 分组 '{group}' 的 {field} 无效: {price}"),
+        }
+    }
+
+    pub fn no_group_token(&self, checked: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ No group has its auth token set (checked: {checked}).\n💡 Please check your .env file or set the environment variables"),
+            Language::Zh => format!("❌ 没有任何分组设置了认证令牌（已检查: {checked}）。\n💡 请检查 .env 文件或设置这些环境变量"),
+        }
+    }
+
+    pub fn duplicate_endpoint_id(&self, id: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Endpoint id '{id}' is used by more than one endpoint\n💡 Give them different names or set a unique id = \"...\" on each"),
+            Language::Zh => format!("❌ 端点 id '{id}' 被多个端点使用\n💡 请为它们取不同的名称，或分别设置唯一的 id = \"...\""),
+        }
+    }
+
+    pub fn duplicate_endpoint_url(&self, url: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Endpoint URL '{url}' is configured more than once\n💡 URLs differing only in case, a trailing slash or the default port reach the same endpoint; keep one entry"),
+            Language::Zh => format!("❌ 端点 URL '{url}' 被配置了多次\n💡 仅大小写、末尾斜杠或默认端口不同的 URL 指向同一端点，请只保留一项"),
+        }
+    }
+
+    pub fn duplicate_listener_port(&self, port: u16) -> String {
+        match self.language {
+            Language::En => format!("❌ Port {port} is used by more than one listener\n💡 Give each [[server.listeners]] entry its own port, different from server.port"),
+            Language::Zh => format!("❌ 端口 {port} 被多个监听器使用\n💡 请为每个 [[server.listeners]] 设置不同于 server.port 的独立端口"),
+        }
+    }
+
+    pub fn unknown_listener_group(&self, port: u16, group: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Listener on port {port} refers to unknown group '{group}'\n💡 Use the name of one of the [[groups]]"),
+            Language::Zh => format!("❌ 端口 {port} 的监听器引用了不存在的分组 '{group}'\n💡 请使用 [[groups]] 中某个分组的名称"),
+        }
+    }
+
+    pub fn invalid_maintenance_timezone(&self, timezone: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Invalid maintenance_timezone '{timezone}' in [server]\n💡 Use \"local\", \"utc\" or an offset such as \"+08:00\""),
+            Language::Zh => format!("❌ [server] 中的 maintenance_timezone '{timezone}' 无效\n💡 请使用 \"local\"、\"utc\" 或 \"+08:00\" 这样的偏移"),
+        }
+    }
+
+    pub fn invalid_maintenance_window(
+        &self,
+        endpoint: &str,
+        window: &str,
+        problem: &str,
+    ) -> String {
+        match self.language {
+            Language::En => format!("❌ Endpoint '{endpoint}' has an invalid maintenance window '{window}': {problem}\n💡 Use maintenance = [{{ window = \"03:00-03:15\", days = [\"mon\", \"tue\"] }}]"),
+            Language::Zh => format!("❌ 端点 '{endpoint}' 的维护窗口 '{window}' 无效: {problem}\n💡 请使用 maintenance = [{{ window = \"03:00-03:15\", days = [\"mon\", \"tue\"] }}]"),
+        }
+    }
+
+    pub fn invalid_cache(&self, problem: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ [cache] is invalid: {problem}\n💡 Use paths = [\"/v1/models\"] with ttl_seconds = 300"),
+            Language::Zh => format!("❌ [cache] 配置无效: {problem}\n💡 请使用 paths = [\"/v1/models\"] 和 ttl_seconds = 300"),
+        }
+    }
+
+    pub fn invalid_alert_rule(&self, rule: &str, problem: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Alert rule '{rule}' is invalid: {problem}\n💡 Use [[alerts.rules]] with name = \"errors\", metric = \"error_rate\", above = 20, for_seconds = 300"),
+            Language::Zh => format!("❌ 告警规则 '{rule}' 无效: {problem}\n💡 请使用 [[alerts.rules]]，例如 name = \"errors\", metric = \"error_rate\", above = 20, for_seconds = 300"),
+        }
+    }
+
+    pub fn endpoint_context(&self, endpoint: &str) -> String {
+        match self.language {
+            Language::En => format!("endpoint '{endpoint}'"),
+            Language::Zh => format!("端点 '{endpoint}'"),
+        }
+    }
+
+    pub fn timeout_override_zero(&self, field: &str, owner: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ {field} of {owner} cannot be 0\n💡 Set how many seconds to wait, or remove it to use the default"),
+            Language::Zh => format!("❌ {owner} 的 {field} 不能为 0\n💡 请设置等待的秒数，或删除该项以使用默认值"),
+        }
+    }
+
+    pub fn long_request_timeout(&self, owner: &str, seconds: u64) -> String {
+        match self.language {
+            Language::En => format!("⚠️  request_timeout_seconds of {owner} is {seconds}s: a stuck request waits this long before failing over"),
+            Language::Zh => format!("⚠️  {owner} 的 request_timeout_seconds 为 {seconds} 秒：卡住的请求要等待这么久才会故障转移"),
+        }
+    }
+
+    pub fn health_check_text_empty(&self, field: &str, owner: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Health check {field} of {owner} cannot be empty\n💡 Set it, or remove it to use the default"),
+            Language::Zh => format!("❌ {owner} 的健康检查 {field} 不能为空\n💡 请填写，或删除该项以使用默认值"),
+        }
+    }
+
+    pub fn long_health_prompt(&self, owner: &str, length: usize) -> String {
+        match self.language {
+            Language::En => format!("⚠️  Health check prompt of {owner} is {length} characters: every check pays for it, keep it minimal"),
+            Language::Zh => format!("⚠️  {owner} 的健康检查 prompt 有 {length} 个字符：每次检查都要为它付费，请尽量精简"),
+        }
+    }
+
+    pub fn response_timeout_zero(&self, field: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ {field} under [server] cannot be 0\n💡 Set how many seconds to wait, such as 30 for response headers"),
+            Language::Zh => format!("❌ [server] 中的 {field} 不能为 0\n💡 请设置等待的秒数，例如响应头等待 30 秒"),
+        }
+    }
+
+    pub fn invalid_retry_status(&self, status: u16, field: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Invalid status {status} in {field} under [retry]\n💡 Only error statuses (400-599) can be retried or sent to a fallback"),
+            Language::Zh => format!("❌ [retry] 的 {field} 中的状态码 {status} 无效\n💡 只有错误状态码（400-599）可以重试或回退"),
+        }
+    }
+
+    pub fn admin_token_not_set(&self, token_env: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Environment variable '{token_env}' for the admin token is not set.\n💡 Set it or remove admin_token_env from [server]"),
+            Language::Zh => format!("❌ 管理令牌的环境变量 '{token_env}' 未设置。\n💡 请设置它，或从 [server] 中移除 admin_token_env"),
+        }
+    }
+
+    pub fn no_default_group(&self) -> &'static str {
+        match self.language {
+            Language::En => "⚠️  No default group specified, using first group as default",
+            Language::Zh => "⚠️  未指定默认分组，使用第一个分组作为默认分组",
+        }
+    }
+
+    pub fn multiple_default_groups(&self, groups: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Groups '{groups}' are all marked default = true.\n💡 Mark only one group as the default."),
+            Language::Zh => format!("❌ 分组 '{groups}' 都设置了 default = true。\n💡 只能将一个分组设为默认组。"),
+        }
+    }
+
+    pub fn multiple_default_endpoints(&self, group: &str, endpoints: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Endpoints '{endpoints}' in group '{group}' are all marked default = true.\n💡 Mark only one endpoint as the default."),
+            Language::Zh => format!("❌ 分组 '{group}' 中的端点 '{endpoints}' 都设置了 default = true。\n💡 只能将一个端点设为默认端点。"),
+        }
+    }
+
+    pub fn default_endpoint_outside_default_group(&self, endpoint: &str, group: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Endpoint '{endpoint}' is marked default = true, but its group '{group}' is not the default group.\n💡 Mark the endpoint default only in the group with default = true."),
+            Language::Zh => format!("❌ 端点 '{endpoint}' 设置了 default = true，但其分组 '{group}' 不是默认组。\n💡 只能在 default = true 的分组中设置默认端点。"),
+        }
+    }
+
+    pub fn duplicate_endpoint_name(&self, name: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Duplicate endpoint name '{name}' found.\n💡 Each endpoint must have a unique name across all groups."),
+            Language::Zh => format!("❌ 发现重复的端点名称 '{name}'。\n💡 所有分组中的端点名称必须唯一。"),
+        }
+    }
+
+    pub fn claude_binary_not_found(&self, path: &str, group: &str, searched: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Claude binary not found at: {path} (group '{group}', also searched PATH and {searched})\n💡 Please install Claude CLI or update the path in your config, or set health_check.http_fallback = true to check over HTTP, or health_check.allow_missing_claude_binary = true to start without health checks"),
+            Language::Zh => format!("❌ 未找到 Claude 程序: {path}（分组 '{group}'，已同时搜索 PATH 和 {searched}）\n💡 请安装 Claude CLI 或在配置中更新路径，或设置 health_check.http_fallback = true 改用 HTTP 检查，或设置 health_check.allow_missing_claude_binary = true 以在无健康检查的情况下启动"),
+        }
+    }
+
+    pub fn claude_binary_required(&self, path: &str, group: &str, searched: &str) -> String {
+        match self.language {
+            Language::En => format!("❌ Claude binary not found at: {path} (group '{group}', also searched PATH and {searched})\n💡 health_check.mode = \"claude_cli\" requires it: install Claude CLI, update the path in your config, or set the mode to \"auto\" or \"http\""),
+            Language::Zh => format!("❌ 未找到 Claude 程序: {path}（分组 '{group}'，已同时搜索 PATH 和 {searched}）\n💡 health_check.mode = \"claude_cli\" 需要它：请安装 Claude CLI、在配置中更新路径，或将模式设为 \"auto\" 或 \"http\""),
+        }
+    }
+
+    pub fn claude_binary_missing_allowed(&self, path: &str, group: &str, searched: &str) -> String {
+        match self.language {
+            Language::En => format!("⚠️  Claude binary not found at: {path} (group '{group}', also searched PATH and {searched})"),
+            Language::Zh => format!("⚠️  未找到 Claude 程序: {path}（分组 '{group}'，已同时搜索 PATH 和 {searched}）"),
+        }
+    }
+
+    pub fn http_health_checks_at_startup(&self) -> &'static str {
+        match self.language {
+            Language::En => "⚠️  Health checks fall back to HTTP (http_fallback = true): each check sends a count_tokens request instead of running the Claude CLI",
+            Language::Zh => "⚠️  健康检查改用 HTTP（http_fallback = true）：每次检查发送 count_tokens 请求，而不运行 Claude CLI",
+        }
+    }
+
+    pub fn health_checks_disabled_at_startup(&self) -> &'static str {
+        match self.language {
+            Language::En => "⚠️  Health checks are DISABLED (allow_missing_claude_binary = true): the default endpoint serves until a request fails over",
+            Language::Zh => "⚠️  健康检查已禁用（allow_missing_claude_binary = true）：由默认端点提供服务，直到请求回退",
+        }
+    }
+
+    pub fn claude_binary_used(&self, group: &str, path: &str) -> String {
+        match self.language {
+            Language::En => format!("🔧 Group '{group}' health checks use {path}"),
+            Language::Zh => format!("🔧 分组 '{group}' 的健康检查使用 {path}"),
+        }
+    }
+
+    pub fn storage_without_sqlite(&self) -> &'static str {
+        match self.language {
+            Language::En => "❌ [storage] is enabled but this binary was built without SQLite support\n💡 Rebuild with `cargo build --release --features sqlite` or set storage.enabled = false",
+            Language::Zh => "❌ 已启用 [storage]，但此程序编译时未包含 SQLite 支持\n💡 请使用 `cargo build --release --features sqlite` 重新编译，或设置 storage.enabled = false",
+        }
+    }
+
+    pub fn config_validated(&self) -> &'static str {
+        match self.language {
+            Language::En => "✅ Configuration validated successfully!",
+            Language::Zh => "✅ 配置校验通过！",
+        }
+    }
+
+    pub fn config_summary(&self, groups: usize, endpoints: usize) -> String {
+        match self.language {
+            Language::En => format!("🚀 Found {groups} groups with {endpoints} total endpoints"),
+            Language::Zh => format!("🚀 共 {groups} 个分组，{endpoints} 个端点"),
+        }
+    }
+
+    pub fn health_check_context_global(&self) -> &'static str {
+        match self.language {
+            Language::En => "global",
+            Language::Zh => "全局配置",
+        }
+    }
+
+    pub fn health_check_context_group(&self, group: &str) -> String {
+        match self.language {
+            Language::En => format!("group '{group}'"),
+            Language::Zh => format!("分组 '{group}'"),
+        }
+    }
+
+    pub fn interval_zero(&self, context: &str) -> String {
+        match self.language {
+            Language::En => format!("Health check interval cannot be 0 for {context}"),
+            Language::Zh => format!("{context} 的健康检查间隔不能为 0"),
+        }
+    }
+
+    pub fn timeout_zero(&self, context: &str) -> String {
+        match self.language {
+            Language::En => format!("Health check timeout cannot be 0 for {context}"),
+            Language::Zh => format!("{context} 的健康检查超时不能为 0"),
+        }
+    }
+
+    pub fn max_concurrent_checks_zero(&self, context: &str) -> String {
+        match self.language {
+            Language::En => format!("Health check max_concurrent_checks cannot be 0 for {context}"),
+            Language::Zh => format!("{context} 的健康检查 max_concurrent_checks 不能为 0"),
+        }
+    }
+
+    pub fn timeout_not_below_interval(&self, timeout: u64, interval: u64, context: &str) -> String {
+        match self.language {
+            Language::En => format!("Health check timeout ({timeout}s) should be less than interval ({interval}s) for {context}"),
+            Language::Zh => format!("{context} 的健康检查超时（{timeout}秒）应小于检查间隔（{interval}秒）"),
+        }
+    }
+
+    pub fn min_interval_zero(&self, context: &str) -> String {
+        match self.language {
+            Language::En => format!(
+                "Minimum interval cannot be 0 when dynamic scaling is enabled for {context}"
+            ),
+            Language::Zh => format!("{context} 启用动态调整时最小间隔不能为 0"),
+        }
+    }
+
+    pub fn min_interval_above_interval(&self, min: u64, interval: u64, context: &str) -> String {
+        match self.language {
+            Language::En => format!("Minimum interval ({min}s) cannot be greater than base interval ({interval}s) for {context}"),
+            Language::Zh => format!("{context} 的最小间隔（{min}秒）不能大于基础间隔（{interval}秒）"),
+        }
+    }
+
+    pub fn timeout_not_below_min_interval(&self, timeout: u64, min: u64, context: &str) -> String {
+        match self.language {
+            Language::En => format!("Health check timeout ({timeout}s) should be less than minimum interval ({min}s) for {context}"),
+            Language::Zh => format!("{context} 的健康检查超时（{timeout}秒）应小于最小间隔（{min}秒）"),
+        }
+    }
+
+    pub fn max_interval_below_interval(&self, max: u64, interval: u64, context: &str) -> String {
+        match self.language {
+            Language::En => format!("Maximum interval ({max}s) cannot be less than base interval ({interval}s) for {context}"),
+            Language::Zh => format!("{context} 的最大间隔（{max}秒）不能小于基础间隔（{interval}秒）"),
+        }
+    }
+
+    // Health orchestrator console messages
+
+    pub fn fast_cleanup(&self, count: usize) -> String {
+        match self.language {
+            Language::En => format!("⚡ Fast cleanup: {count} recently interrupted connections"),
+            Language::Zh => format!("⚡ 快速清理: {count} 个刚中断的连接"),
+        }
+    }
+
+    pub fn interval_adjusted(&self, seconds: u64, load: &str) -> String {
+        match self.language {
+            Language::En => {
+                format!("🔄 Health check interval adjusted to {seconds}s (Load: {load})")
+            }
+            Language::Zh => format!("🔄 健康检查间隔已调整为 {seconds}秒（负载: {load}）"),
+        }
+    }
+
+    pub fn cycle_timed_out(&self, seconds: u64) -> String {
+        match self.language {
+            Language::En => format!("⚠️  Health check cycle timed out after {seconds}s"),
+            Language::Zh => format!("⚠️  健康检查轮次在 {seconds}秒后超时"),
+        }
+    }
+
+    pub fn check_task_error(&self, endpoint: &str, error: &dyn std::fmt::Display) -> String {
+        match self.language {
+            Language::En => format!("⚠️  Health check task error for {endpoint}: {error}"),
+            Language::Zh => format!("⚠️  {endpoint} 的健康检查任务出错: {error}"),
+        }
+    }
+
+    pub fn stale_cleaned(&self, count: usize) -> String {
+        match self.language {
+            Language::En => format!("🧹 Cleaned up {count} stale connections"),
+            Language::Zh => format!("🧹 已清理 {count} 个过期连接"),
+        }
+    }
+
+    pub fn abandoned_cleaned(&self, count: usize) -> String {
+        match self.language {
+            Language::En => {
+                format!("🧹 Cleaned up {count} abandoned connections (likely interrupted)")
+            }
+            Language::Zh => format!("🧹 已清理 {count} 个废弃连接（可能已被中断）"),
+        }
+    }
+
+    pub fn cycle_completed(&self, millis: u64, count: usize) -> String {
+        match self.language {
+            Language::En => {
+                format!("✅ Health check completed in {millis}ms, found {count} endpoints")
+            }
+            Language::Zh => format!("✅ 健康检查完成，用时 {millis}ms，共 {count} 个端点"),
+        }
+    }
+
+    pub fn monitoring_paused(&self) -> &'static str {
+        match self.language {
+            Language::En => "⏸️  Health monitoring paused - manual refresh available with 'R'",
+            Language::Zh => "⏸️  健康监控已暂停 - 可按 'R' 手动刷新",
+        }
+    }
+
+    pub fn monitoring_resumed(&self) -> &'static str {
+        match self.language {
+            Language::En => "▶️  Health monitoring resumed",
+            Language::Zh => "▶️  健康监控已恢复",
+        }
+    }
+
+    pub fn auto_switching_frozen(&self) -> &'static str {
+        match self.language {
+            Language::En => "❄️  Auto-switching frozen - health checks continue",
+            Language::Zh => "❄️  自动切换已冻结 - 健康检查继续运行",
+        }
+    }
+
+    pub fn auto_switching_unfrozen(&self) -> &'static str {
+        match self.language {
+            Language::En => "🔥 Auto-switching unfrozen",
+            Language::Zh => "🔥 自动切换已解冻",
+        }
+    }
+
+    pub fn health_checks_disabled(&self) -> &'static str {
+        match self.language {
+            Language::En => {
+                "⚠️  Health check skipped: health checks are disabled, the Claude binary is missing"
+            }
+            Language::Zh => "⚠️  已跳过健康检查：缺少 Claude 程序，健康检查已禁用",
+        }
+    }
+
+    pub fn manual_check_started(&self) -> &'static str {
+        match self.language {
+            Language::En => "🔄 Manual health check triggered...",
+            Language::Zh => "🔄 已触发手动健康检查...",
+        }
+    }
+
+    pub fn manual_check_completed(&self) -> &'static str {
+        match self.language {
+            Language::En => "✅ Manual health check completed",
+            Language::Zh => "✅ 手动健康检查完成",
+        }
+    }
+
+    pub fn no_failed_endpoints(&self) -> &'static str {
+        match self.language {
+            Language::En => "✅ No failed endpoints to re-check",
+            Language::Zh => "✅ 没有需要重新检查的故障端点",
+        }
+    }
+
+    pub fn rechecking_failed(&self, count: usize) -> String {
+        match self.language {
+            Language::En => format!("🔄 Re-checking {count} failed endpoints..."),
+            Language::Zh => format!("🔄 正在重新检查 {count} 个故障端点..."),
+        }
+    }
+
+    // Shutdown messages
+
+    pub fn received_sigint(&self) -> &'static str {
+        match self.language {
+            Language::En => "📨 Received SIGINT (Ctrl+C), performing graceful shutdown...",
+            Language::Zh => "📨 收到 SIGINT（Ctrl+C），正在优雅关闭...",
+        }
+    }
+
+    pub fn received_sigterm(&self) -> &'static str {
+        match self.language {
+            Language::En => "📨 Received SIGTERM, performing graceful shutdown...",
+            Language::Zh => "📨 收到 SIGTERM，正在优雅关闭...",
+        }
+    }
+
+    pub fn cleaning_up_connections(&self, signal: &str) -> String {
+        match self.language {
+            Language::En => {
+                format!("🧹 Cleaning up all active connections due to {signal} signal...")
+            }
+            Language::Zh => format!("🧹 收到 {signal} 信号，正在清理所有活动连接..."),
+        }
+    }
+
+    pub fn active_connections_cleaned(&self, count: usize) -> String {
+        match self.language {
+            Language::En => format!("🧹 Cleaned up {count} active connections"),
+            Language::Zh => format!("🧹 已清理 {count} 个活动连接"),
+        }
+    }
+
+    pub fn shutdown_completed(&self) -> &'static str {
+        match self.language {
+            Language::En => "✅ Graceful shutdown completed",
+            Language::Zh => "✅ 优雅关闭完成",
+        }
+    }
+
+    pub fn emergency_cleanup(&self, reason: &str) -> String {
+        match self.language {
+            Language::En => format!("⚠️ Emergency connection cleanup triggered: {reason}"),
+            Language::Zh => format!("⚠️ 触发紧急连接清理: {reason}"),
+        }
+    }
+
+    pub fn emergency_cleanup_done(&self, count: usize) -> String {
+        match self.language {
+            Language::En => format!("🧹 Emergency cleanup: removed {count} connections"),
+            Language::Zh => format!("🧹 紧急清理: 已移除 {count} 个连接"),
+        }
+    }
+}
+
+/// Seconds as "45s", "8m" or "2h", for compact dashboard text
+fn compact_duration(seconds: u64) -> String {
+    match seconds {
+        0..=119 => format!("{seconds}s"),
+        120..=7199 => format!("{}m", seconds / 60),
+        _ => format!("{}h", seconds / 3600),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Argument values every text is rendered with, distinct enough to find
+    /// in the output
+    const MARKERS: [&str; 7] = ["ARG-A", "ARG-B", "ARG-C", "4711", "5822", "6933", "47.5"];
+
+    /// Symbols shown the same in every language
+    const UNTRANSLATED: [&str; 3] = ["status_available", "status_error", "status_maintenance"];
+
+    type Render = Box<dyn Fn(&I18n) -> String>;
+
+    /// Each text with the arguments to render it with, once per branch
+    macro_rules! catalog {
+        ($($key:ident($($arg:expr),*);)*) => {
+            vec![$((
+                stringify!($key),
+                Box::new(|text: &I18n| text.$key($($arg),*).to_string()) as Render,
+            ),)*]
+        };
+    }
+
+    fn decision(
+        dynamic: bool,
+        load_level: LoadLevel,
+        scaling_factor: Option<f64>,
+        clamped: Option<IntervalBound>,
+    ) -> IntervalDecision {
+        IntervalDecision {
+            dynamic,
+            load_level,
+            request_rate: 4711.0,
+            idle_seconds: Some(5822),
+            base_seconds: 60,
+            scaling_factor,
+            min_seconds: 30,
+            max_seconds: 900,
+            clamped,
+        }
+    }
+
+    fn catalog() -> Vec<(&'static str, Render)> {
+        use IntervalBound::{Max, Min};
+        use LoadLevel::{Idle, Low, Medium};
+        catalog! {
+            app_title();
+            proxy_label();
+            monitor_only_label();
+            status_monitoring();
+            status_starting();
+            status_paused();
+            btn_quit();
+            btn_manual_check();
+            btn_pause();
+            btn_resume();
+            btn_freeze();
+            btn_unfreeze();
+            switching_frozen();
+            shedding_load();
+            btn_to_manual();
+            btn_to_auto();
+            btn_history();
+            btn_tags();
+            btn_browse_endpoints();
+            btn_select_confirm();
+            mode_auto();
+            mode_manual();
+            mode_manual_indexed(4711);
+            manual_endpoint_removed("ARG-A");
+            confirm_endpoint_reset("ARG-A");
+            confirm_forced_selection("ARG-A");
+            pinned_to_unhealthy("ARG-A");
+            switch_forced();
+            endpoint_reset("ARG-A");
+            draining(4711);
+            standby();
+            group_down();
+            group_connections(4711);
+            group_down_notice("ARG-A");
+            group_recovered_notice("ARG-A");
+            not_checked();
+            no_token();
+            alert_banner("ARG-A", Some("ARG-B"), 0);
+            alert_banner("ARG-A", None, 4711);
+            alert_resolved("ARG-A", Some("ARG-B"));
+            alert_resolved("ARG-A", None);
+            all_endpoints_down_banner(4711);
+            terminal_too_small(4711, 5822);
+            config_reloaded(4711, 5822, 6933);
+            cycle_switched(4711, "ARG-A", 5822, 6933);
+            cycle_failures(4711, 5822, 6933);
+            listeners_not_changed("ARG-A");
+            config_reload_failed("ARG-A");
+            auth_rejected("ARG-A");
+            diagnostics_saved("ARG-A");
+            table_exported("ARG-A");
+            table_export_failed("ARG-A");
+            diagnostics_failed("ARG-A");
+            no_token_selected("ARG-A");
+            maintenance_selected("ARG-A");
+            no_tags_configured();
+            tag_picker_title();
+            status_checking();
+            status_available();
+            status_error();
+            error_timeout();
+            status_maintenance();
+            maintenance_until("ARG-A");
+            error_auth();
+            error_generic();
+            health_checking_with_time(4711);
+            health_ready();
+            retries_per_minute(4711);
+            overhead_p90("ARG-A");
+            estimated_cost("ARG-A", "ARG-B");
+            health_next(4711);
+            load_high(4711);
+            load_medium(4711);
+            load_low(4711);
+            load_idle();
+            interval_explained(4711, &decision(false, Idle, None, None));
+            interval_explained(4711, &decision(true, Idle, None, None));
+            interval_explained(4711, &decision(true, Idle, Some(47.5), None));
+            interval_explained(4711, &decision(true, Low, Some(47.5), Some(Min)));
+            interval_explained(4711, &decision(true, Medium, Some(47.5), Some(Max)));
+            health_check_model("ARG-A");
+            switch_new_connection();
+            time_ago(47);
+            time_ago(4711);
+            time_ago(58220);
+            switch_history_title(4711);
+            switch_history_empty();
+            switch_reason(SwitchReason::AutoRaceWin);
+            switch_reason(SwitchReason::Failover);
+            switch_reason(SwitchReason::ManualSwitch);
+            switch_reason(SwitchReason::RequestFallback);
+            switch_reason(SwitchReason::TagFilter);
+            switch_reason(SwitchReason::ConfigReload);
+            switch_reason(SwitchReason::Maintenance);
+            switch_reason(SwitchReason::Drill);
+            switch_reason(SwitchReason::TierFailover);
+            switch_reason(SwitchReason::TierFailback);
+            pause_pending_subtitle(true);
+            pause_pending_subtitle(false);
+            pause_not_confirmed(true, 4711);
+            pause_not_confirmed(false, 4711);
+            paused_subtitle();
+            loading_config("ARG-A");
+            loading_env_file("ARG-A", &["ARG-B".to_string(), "ARG-C".to_string()]);
+            loading_env_file("ARG-A", &[]);
+            config_read_failed(&"ARG-A");
+            config_parse_failed(&"ARG-A");
+            config_not_found();
+            env_file_not_found("ARG-A");
+            env_file_read_failed("ARG-A", &"ARG-B");
+            strict_token_rejected("ARG-A");
+            no_groups();
+            group_no_endpoints("ARG-A");
+            group_missing_token_env("ARG-A");
+            placeholder_token("ARG-A");
+            invalid_price("ARG-A", "ARG-B", 47.5);
+            no_group_token("ARG-A");
+            duplicate_endpoint_id("ARG-A");
+            duplicate_endpoint_url("ARG-A");
+            duplicate_listener_port(4711);
+            unknown_listener_group(4711, "ARG-A");
+            invalid_maintenance_timezone("ARG-A");
+            invalid_maintenance_window("ARG-A", "ARG-B", "ARG-C");
+            invalid_cache("ARG-A");
+            invalid_alert_rule("ARG-A", "ARG-B");
+            endpoint_context("ARG-A");
+            timeout_override_zero("ARG-A", "ARG-B");
+            long_request_timeout("ARG-A", 4711);
+            health_check_text_empty("ARG-A", "ARG-B");
+            long_health_prompt("ARG-A", 4711);
+            response_timeout_zero("ARG-A");
+            invalid_retry_status(4711, "ARG-A");
+            admin_token_not_set("ARG-A");
+            no_default_group();
+            multiple_default_groups("ARG-A");
+            multiple_default_endpoints("ARG-A", "ARG-B");
+            default_endpoint_outside_default_group("ARG-A", "ARG-B");
+            duplicate_endpoint_name("ARG-A");
+            claude_binary_not_found("ARG-A", "ARG-B", "ARG-C");
+            claude_binary_required("ARG-A", "ARG-B", "ARG-C");
+            claude_binary_missing_allowed("ARG-A", "ARG-B", "ARG-C");
+            http_health_checks_at_startup();
+            health_checks_disabled_at_startup();
+            claude_binary_used("ARG-A", "ARG-B");
+            storage_without_sqlite();
+            config_validated();
+            config_summary(4711, 5822);
+            health_check_context_global();
+            health_check_context_group("ARG-A");
+            interval_zero("ARG-A");
+            timeout_zero("ARG-A");
+            max_concurrent_checks_zero("ARG-A");
+            timeout_not_below_interval(4711, 5822, "ARG-A");
+            min_interval_zero("ARG-A");
+            min_interval_above_interval(4711, 5822, "ARG-A");
+            timeout_not_below_min_interval(4711, 5822, "ARG-A");
+            max_interval_below_interval(4711, 5822, "ARG-A");
+            fast_cleanup(4711);
+            interval_adjusted(4711, "ARG-A");
+            cycle_timed_out(4711);
+            check_task_error("ARG-A", &"ARG-B");
+            stale_cleaned(4711);
+            abandoned_cleaned(4711);
+            cycle_completed(4711, 5822);
+            monitoring_paused();
+            monitoring_resumed();
+            auto_switching_frozen();
+            auto_switching_unfrozen();
+            health_checks_disabled();
+            manual_check_started();
+            manual_check_completed();
+            no_failed_endpoints();
+            rechecking_failed(4711);
+            received_sigint();
+            received_sigterm();
+            cleaning_up_connections("ARG-A");
+            active_connections_cleaned(4711);
+            shutdown_completed();
+            emergency_cleanup("ARG-A");
+            emergency_cleanup_done(4711);
+        }
+    }
+
+    #[test]
+    fn catalog_lists_every_text() {
+        let source = include_str!("i18n.rs");
+        let catalog_impl = &source[source.find("impl I18n {").unwrap()..];
+        let catalog_impl = &catalog_impl[..catalog_impl.find("\n}\n").unwrap()];
+        let mut defined: Vec<&str> = catalog_impl
+            .lines()
+            .filter_map(|line| line.strip_prefix("    pub fn "))
+            .filter_map(|line| line.split('(').next())
+            .filter(|name| *name != "new")
+            .collect();
+        defined.sort_unstable();
+
+        let mut listed: Vec<&str> = catalog().iter().map(|(key, _)| *key).collect();
+        listed.sort_unstable();
+        listed.dedup();
+        assert_eq!(listed, defined);
+    }
+
+    #[test]
+    fn chinese_catalog_translates_every_text_with_its_arguments() {
+        let en = I18n::new(Language::En);
+        let zh = I18n::new(Language::Zh);
+        for (key, render) in catalog() {
+            let (en, zh) = (render(&en), render(&zh));
+            assert!(!zh.trim().is_empty(), "{key} is empty in Chinese");
+            if !UNTRANSLATED.contains(&key) {
+                assert!(
+                    zh.chars().any(|c| ('\u{4e00}'..='\u{9fff}').contains(&c)),
+                    "{key} is not translated: {zh}"
+                );
+            }
+            for marker in MARKERS {
+                assert_eq!(
+                    en.contains(marker),
+                    zh.contains(marker),
+                    "{key} shows {marker} in only one language:\n{en}\n{zh}"
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,72 @@
+//! Automatic endpoint switching proxy for the Claude API.
+//!
+//! The binary is a thin wrapper around this crate. To embed the proxy, load a
+//! [`Config`], build a [`ProxyServer`] and run it:
+//!
+//! ```no_run
+//! # fn example() -> anyhow::Result<()> {
+//! // Before the runtime starts: loading writes the env file to the environment
+//! let config = claude_zephyr::Config::load_default()?;
+//! tokio::runtime::Runtime::new()?.block_on(
+//!     claude_zephyr::ProxyServer::builder(config)
+//!         .port(8081)
+//!         .build()
+//!         .run(),
+//! )
+//! # }
+//! ```
+
+#![deny(clippy::await_holding_lock)]
+
+pub mod build_info;
+pub mod config;
+pub mod config_view;
+pub mod diagnose;
+pub mod drill;
+pub mod endpoint;
+pub mod events;
+pub mod monitor;
+pub mod report;
+pub mod smoke;
+
+mod alerts;
+mod body_log;
+mod connection_tracker;
+mod dashboard;
+mod dev_tools;
+mod drain;
+mod dynamic_health;
+mod endpoint_table;
+mod env_file;
+mod group_health;
+mod health;
+mod health_client;
+mod health_orchestrator;
+mod i18n;
+mod lock;
+mod logging;
+mod maintenance;
+mod metrics;
+mod proxy;
+mod selection;
+mod server;
+mod signal_handler;
+mod state_handoff;
+mod stats;
+mod storage;
+mod streaming;
+#[cfg(test)]
+mod test_support;
+mod token_check;
+mod usage;
+
+pub use config::Config;
+pub use dev_tools::test_health_check_timing;
+pub use events::ProxyEvent;
+pub use health::EndpointStatus;
+pub use health_orchestrator::HealthOrchestratorHandle;
+pub use i18n::set_console_language;
+pub use logging::{console_text, set_console_emoji, ConsoleFields};
+pub use metrics::{CounterSnapshot, ProxyMetrics, SharedMetrics};
+pub use proxy::{ProxyState, RunMode, SharedState};
+pub use server::{EventHook, ProxyServer, ProxyServerBuilder};
@@ -0,0 +1,49 @@
+use crate::logging::log_lock_poisoned;
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Locking that survives a panic on another task.
+///
+/// A panic while a guard is held poisons the mutex. Every shared value in the
+/// proxy is updated in single steps, so the data behind a poisoned lock is
+/// still usable: log loudly, clear the poison and carry on rather than
+/// treating the value as lost and silently falling back to a default.
+///
+/// Guards are never held across an `.await`; `clippy::await_holding_lock` is
+/// denied crate-wide to keep it that way.
+pub trait LockExt<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> LockExt<T> for Mutex<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(|poisoned| {
+            log_lock_poisoned(std::any::type_name::<T>());
+            self.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+}
+
+/// [`LockExt`] for values that are read far more often than replaced
+pub trait RwLockExt<T> {
+    fn read_or_recover(&self) -> RwLockReadGuard<'_, T>;
+    fn write_or_recover(&self) -> RwLockWriteGuard<'_, T>;
+}
+
+impl<T> RwLockExt<T> for RwLock<T> {
+    fn read_or_recover(&self) -> RwLockReadGuard<'_, T> {
+        self.read().unwrap_or_else(|poisoned| {
+            log_lock_poisoned(std::any::type_name::<T>());
+            self.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+
+    fn write_or_recover(&self) -> RwLockWriteGuard<'_, T> {
+        self.write().unwrap_or_else(|poisoned| {
+            log_lock_poisoned(std::any::type_name::<T>());
+            self.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+}
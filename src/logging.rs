@@ -1,97 +1,1046 @@
-use tracing::{error, info, warn};
+use crate::config::{Config, ConfigDiff, RequestTimeout};
+use crate::connection_tracker::ConnectionId;
+use crate::events::{Alert, HealthCycleSummary, SwitchReason};
+use crate::health::LatencyPhases;
+use crate::token_check::{TokenCheck, TokenValidity};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::fmt::format::{DefaultFields, Writer};
+use tracing_subscriber::fmt::FormatFields;
 
-/// Log categories for better visual distinction
+/// Log categories for better visual distinction, bracketed tags such as
+/// `[HEALTH]` when emoji are off
 pub mod log_cat {
-    pub const HEALTH: &str = "🏥";
-    pub const PROXY: &str = "🔄";
-    pub const SWITCH: &str = "🔀";
-    pub const SERVER: &str = "🚀";
-    pub const CONFIG: &str = "⚙️";
-    pub const ERROR: &str = "❌";
-    pub const SUCCESS: &str = "✅";
+    use super::pick;
+
+    pub fn health() -> &'static str {
+        pick("🏥", "[HEALTH]")
+    }
+    pub fn proxy() -> &'static str {
+        pick("🔄", "[PROXY]")
+    }
+    pub fn switch() -> &'static str {
+        pick("🔀", "[SWITCH]")
+    }
+    pub fn server() -> &'static str {
+        pick("🚀", "[SERVER]")
+    }
+    pub fn config() -> &'static str {
+        pick("⚙️", "[CONFIG]")
+    }
+    pub fn error() -> &'static str {
+        pick("❌", "[ERROR]")
+    }
+    pub fn success() -> &'static str {
+        pick("✅", "[OK]")
+    }
+}
+
+/// Emoji and symbols in console output, off with `logging.emoji = false` or
+/// `--no-emoji` for log pipelines that only take ASCII
+static EMOJI: AtomicBool = AtomicBool::new(true);
+
+/// Print emoji in console output from now on, or plain ASCII instead
+pub fn set_console_emoji(enabled: bool) {
+    EMOJI.store(enabled, Ordering::Relaxed);
+}
+
+/// `fancy` while emoji are on, `plain` otherwise
+fn pick(fancy: &'static str, plain: &'static str) -> &'static str {
+    if EMOJI.load(Ordering::Relaxed) {
+        fancy
+    } else {
+        plain
+    }
+}
+
+/// `text` as it should be printed: unchanged while emoji are on, otherwise
+/// with emoji dropped and arrows, dashes and box drawing spelled in ASCII.
+/// For messages written elsewhere, such as `i18n::console()` texts.
+pub fn console_text(text: &str) -> Cow<'_, str> {
+    if EMOJI.load(Ordering::Relaxed) || text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '→' | '⇒' => plain.push_str("->"),
+            '←' => plain.push_str("<-"),
+            '—' | '–' | '─' => plain.push('-'),
+            '═' => plain.push('='),
+            '╰' | '└' => plain.push('`'),
+            '│' => plain.push('|'),
+            '•' => plain.push('*'),
+            '…' => plain.push_str("..."),
+            '\u{FE0F}' | '\u{200D}' => {}
+            c if is_emoji(c) => {
+                while chars
+                    .next_if(|&c| c == '\u{FE0F}' || c == '\u{200D}')
+                    .is_some()
+                {}
+                // The space that set the emoji off goes with it
+                if plain.is_empty() || plain.ends_with([' ', '\n']) {
+                    chars.next_if_eq(&' ');
+                }
+            }
+            c => plain.push(c),
+        }
+    }
+    Cow::Owned(plain)
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c,
+        '\u{1F000}'..='\u{1FAFF}'
+        | '\u{2300}'..='\u{23FF}'
+        | '\u{2190}'..='\u{21FF}'
+        | '\u{2600}'..='\u{27BF}'
+        | '\u{2B00}'..='\u{2BFF}')
+}
+
+fn arrow() -> &'static str {
+    pick("→", "->")
+}
+
+fn maintenance() -> &'static str {
+    pick("🔧", "[MAINT]")
+}
+
+/// Print a console message to stdout, see [`console_text`]
+pub fn console_line(text: &str) {
+    println!("{}", console_text(text));
+}
+
+/// Print a console warning or notice to stderr, see [`console_text`]
+pub fn console_warning(text: &str) {
+    eprintln!("{}", console_text(text));
+}
+
+/// Padded display name per endpoint URL when `logging.use_endpoint_names` is on
+static ENDPOINT_LABELS: RwLock<Option<HashMap<String, String>>> = RwLock::new(None);
+
+/// Per-endpoint health check progress logs at debug while `logging.cycle_summary` is on
+static CYCLE_SUMMARY: AtomicBool = AtomicBool::new(false);
+
+/// Set up console formatting from `[logging]`, call before logging starts
+pub fn configure_console(config: &Config) {
+    let labels = config.logging.use_endpoint_names.then(|| {
+        let endpoints = config.get_all_endpoints();
+        let width = endpoints
+            .iter()
+            .map(|(_, endpoint, _)| endpoint.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        endpoints
+            .into_iter()
+            .map(|(_, endpoint, _)| (endpoint.url, format!("{:<width$}", endpoint.name)))
+            .collect()
+    });
+    *ENDPOINT_LABELS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = labels;
+    CYCLE_SUMMARY.store(config.logging.cycle_summary, Ordering::Relaxed);
+    set_console_emoji(config.logging.emoji);
+
+    let window = config.logging.duplicate_window_seconds;
+    *DUPLICATE_ERRORS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = (window > 0).then(|| DuplicateErrors {
+        window: Duration::from_secs(window),
+        seen: HashMap::new(),
+    });
+}
+
+/// The same failure for an endpoint, counted while it repeats within a window
+struct RepeatedError {
+    error: String,
+    window_start: Instant,
+    repeats: u32,
+}
+
+/// Failures logged per (message kind, endpoint), `None` when suppression is off
+struct DuplicateErrors {
+    window: Duration,
+    seen: HashMap<(&'static str, String), RepeatedError>,
+}
+
+static DUPLICATE_ERRORS: Mutex<Option<DuplicateErrors>> = Mutex::new(None);
+
+/// Whether a failure should be logged now. The first occurrence always is;
+/// identical ones within the window are only counted and reported as a
+/// summary once the window ends or the error changes.
+fn first_in_window(kind: &'static str, endpoint: &str, error: &str) -> bool {
+    let mut guard = DUPLICATE_ERRORS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let Some(duplicates) = guard.as_mut() else {
+        return true;
+    };
+
+    let window = duplicates.window;
+    duplicates.seen.retain(|(kind, endpoint), repeated| {
+        let open = repeated.window_start.elapsed() < window;
+        if !open {
+            log_repeated_error(kind, endpoint, repeated);
+        }
+        open
+    });
+
+    let key = (kind, endpoint.to_string());
+    if let Some(repeated) = duplicates.seen.get_mut(&key) {
+        if repeated.error == error {
+            repeated.repeats += 1;
+            return false;
+        }
+        log_repeated_error(kind, endpoint, repeated);
+    }
+    duplicates.seen.insert(
+        key,
+        RepeatedError {
+            error: error.to_string(),
+            window_start: Instant::now(),
+            repeats: 0,
+        },
+    );
+    true
+}
+
+fn log_repeated_error(kind: &str, endpoint: &str, repeated: &RepeatedError) {
+    if repeated.repeats > 0 {
+        warn!(
+            "{} {}: {} - {} (repeated {} times in {}s)",
+            log_cat::error(),
+            kind,
+            endpoint_label(endpoint),
+            repeated.error,
+            repeated.repeats,
+            repeated.window_start.elapsed().as_secs()
+        );
+    }
+}
+
+/// Endpoint as shown in logs: its padded display name if configured, else the URL
+fn endpoint_label(endpoint: &str) -> String {
+    ENDPOINT_LABELS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .and_then(|labels| labels.get(endpoint).cloned())
+        .unwrap_or_else(|| endpoint.to_string())
+}
+
+/// Field formatter for the headless console. Retry and fallback events,
+/// which carry their details as fields, read as a sentence like the other
+/// log lines; everything else is formatted as usual.
+#[derive(Debug, Default)]
+pub struct ConsoleFields;
+
+impl<'writer> FormatFields<'writer> for ConsoleFields {
+    fn format_fields<R: RecordFields>(
+        &self,
+        mut writer: Writer<'writer>,
+        fields: R,
+    ) -> fmt::Result {
+        let mut decision = DecisionFields::default();
+        fields.record(&mut decision);
+        match decision.sentence() {
+            Some(sentence) => writer.write_str(&sentence),
+            None => DefaultFields::new().format_fields(writer, fields),
+        }
+    }
+}
+
+/// Fields of a retry or fallback event, see [`log_retry_attempt`]
+#[derive(Default)]
+struct DecisionFields {
+    message: String,
+    endpoint: Option<String>,
+    from: Option<String>,
+    reason: Option<String>,
+    connection_id: Option<String>,
+    attempt: Option<u64>,
+    max_attempts: Option<u64>,
+    delay_ms: Option<u64>,
+    status: Option<u64>,
+}
+
+impl DecisionFields {
+    fn sentence(&self) -> Option<String> {
+        let endpoint = endpoint_label(self.endpoint.as_deref()?);
+        let reason = self.reason.as_deref().unwrap_or_default();
+        let sentence = match self.message.as_str() {
+            "retry attempt" => format!(
+                "{} Retry {}/{} for {} - {}",
+                log_cat::proxy(),
+                self.attempt?,
+                self.max_attempts?,
+                endpoint,
+                reason
+            ),
+            "retry delay" => format!(
+                "{} Waiting {}ms before retrying {}",
+                log_cat::proxy(),
+                self.delay_ms?,
+                endpoint
+            ),
+            "retry skipped" => format!(
+                "{} Status {} from {} skips the remaining retries",
+                log_cat::proxy(),
+                self.status?,
+                endpoint
+            ),
+            "retry succeeded" => format!(
+                "{} {} Request succeeded on retry {}: {}",
+                log_cat::proxy(),
+                log_cat::success(),
+                self.attempt?,
+                endpoint
+            ),
+            "retries exhausted" => format!(
+                "{} {} Retries exhausted after {} attempts: {} - {}",
+                log_cat::proxy(),
+                log_cat::error(),
+                self.attempt?,
+                endpoint,
+                reason
+            ),
+            "request failed" => format!(
+                "{} {} Request failed: {} - {}",
+                log_cat::proxy(),
+                log_cat::error(),
+                endpoint,
+                reason
+            ),
+            "fallback attempt" => format!(
+                "{} Falling back from {} {} {}",
+                log_cat::proxy(),
+                endpoint_label(self.from.as_deref()?),
+                arrow(),
+                endpoint
+            ),
+            "fallback succeeded" => format!(
+                "{} {} Fallback succeeded: {} {} {}",
+                log_cat::proxy(),
+                log_cat::success(),
+                endpoint_label(self.from.as_deref()?),
+                arrow(),
+                endpoint
+            ),
+            _ => return None,
+        };
+        Some(match &self.connection_id {
+            Some(connection_id) => format!("{sentence} ({connection_id})"),
+            None => sentence,
+        })
+    }
+}
+
+impl Visit for DecisionFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        let slot = match field.name() {
+            "endpoint" => &mut self.endpoint,
+            "from" => &mut self.from,
+            "reason" => &mut self.reason,
+            "connection_id" => &mut self.connection_id,
+            _ => return,
+        };
+        *slot = Some(value.to_string());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        let slot = match field.name() {
+            "attempt" => &mut self.attempt,
+            "max_attempts" => &mut self.max_attempts,
+            "delay_ms" => &mut self.delay_ms,
+            "status" => &mut self.status,
+            _ => return,
+        };
+        *slot = Some(value);
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        }
+    }
 }
 
 /// Health check related logs
 pub fn log_health_start(endpoint: &str) {
-    info!("{} Health check starting: {}", log_cat::HEALTH, endpoint);
+    if CYCLE_SUMMARY.load(Ordering::Relaxed) {
+        debug!(
+            "{} Health check starting: {}",
+            log_cat::health(),
+            endpoint_label(endpoint)
+        );
+    } else {
+        info!(
+            "{} Health check starting: {}",
+            log_cat::health(),
+            endpoint_label(endpoint)
+        );
+    }
 }
 
 pub fn log_health_success(endpoint: &str, latency: u64) {
+    if CYCLE_SUMMARY.load(Ordering::Relaxed) {
+        debug!(
+            "{} {} Endpoint healthy: {} ({}ms)",
+            log_cat::health(),
+            log_cat::success(),
+            endpoint_label(endpoint),
+            latency
+        );
+    } else {
+        info!(
+            "{} {} Endpoint healthy: {} ({}ms)",
+            log_cat::health(),
+            log_cat::success(),
+            endpoint_label(endpoint),
+            latency
+        );
+    }
+}
+
+pub fn log_health_failed(endpoint: &str, error: &str) {
+    if !first_in_window("Endpoint failed", endpoint, error) {
+        return;
+    }
+    error!(
+        "{} {} Endpoint failed: {} - {}",
+        log_cat::health(),
+        log_cat::error(),
+        endpoint_label(endpoint),
+        error
+    );
+}
+
+/// One line per health check cycle, e.g.
+/// `cycle #42 212ms — 9/12 healthy, best GLADOS 182ms, current MIRROR-EU 240ms, no switch`
+pub fn log_health_cycle_summary(cycle: &HealthCycleSummary) {
+    let best = cycle
+        .best_endpoint
+        .as_deref()
+        .zip(cycle.best_latency_ms)
+        .map(|(endpoint, latency)| {
+            format!(", best {} {latency}ms", endpoint_label(endpoint).trim_end())
+        })
+        .unwrap_or_default();
+    let switch = cycle
+        .switched_from
+        .as_deref()
+        .map(|from| format!("switched from {}", endpoint_label(from).trim_end()))
+        .unwrap_or_else(|| "no switch".to_string());
+    let summary = format!(
+        "cycle #{} {}ms {} {}/{} healthy{best}, current {} {}, {switch}",
+        cycle.sequence,
+        cycle.duration_ms,
+        pick("—", "-"),
+        cycle.healthy,
+        cycle.checked,
+        endpoint_label(&cycle.current_endpoint).trim_end(),
+        format_latency(cycle.current_latency_ms)
+    );
+    if cycle.healthy == 0 && cycle.checked > 0 {
+        warn!("{} {} {}", log_cat::health(), log_cat::error(), summary);
+    } else {
+        info!("{} {} {}", log_cat::health(), log_cat::success(), summary);
+    }
+}
+
+pub fn log_alert_fired(alert: &Alert) {
+    warn!(
+        "{} {} Alert {} firing{}: {}",
+        log_cat::health(),
+        log_cat::error(),
+        alert.rule,
+        alert_endpoint(alert),
+        alert.condition()
+    );
+}
+
+pub fn log_alert_resolved(alert: &Alert) {
+    info!(
+        "{} {} Alert {} resolved{}",
+        log_cat::health(),
+        log_cat::success(),
+        alert.rule,
+        alert_endpoint(alert)
+    );
+}
+
+fn alert_endpoint(alert: &Alert) -> String {
+    alert
+        .endpoint
+        .as_ref()
+        .map(|endpoint| format!(" on {endpoint}"))
+        .unwrap_or_default()
+}
+
+pub fn log_auth_rejected(endpoint: &str) {
+    warn!(
+        "{} {} Token rejected by {}, skipping its scheduled checks until the token changes or a manual refresh",
+        log_cat::health(),
+        log_cat::error(),
+        endpoint_label(endpoint)
+    );
+}
+
+pub fn log_auth_token_changed(endpoint: &str) {
+    info!(
+        "{} Token changed for {}, checking it again",
+        log_cat::health(),
+        endpoint_label(endpoint)
+    );
+}
+
+pub fn log_maintenance_started(endpoint: &str, until: chrono::DateTime<chrono::Utc>) {
+    info!(
+        "{} {} {} entered maintenance until {}, skipping its checks",
+        log_cat::health(),
+        maintenance(),
+        endpoint_label(endpoint),
+        until.with_timezone(&chrono::Local).format("%H:%M")
+    );
+}
+
+pub fn log_maintenance_ended(endpoint: &str) {
     info!(
-        "{} {} Endpoint healthy: {} ({}ms)",
-        log_cat::HEALTH,
-        log_cat::SUCCESS,
+        "{} {} {} left maintenance, checking it again",
+        log_cat::health(),
+        maintenance(),
+        endpoint_label(endpoint)
+    );
+}
+
+pub fn log_endpoint_reset(endpoint: &str, source: &str) {
+    info!(
+        "{} Reset {} from {}: latency history and errors cleared",
+        log_cat::health(),
+        endpoint_label(endpoint),
+        source
+    );
+}
+
+pub fn log_startup_check_failed(endpoint: &str) {
+    error!(
+        "{} {} No healthy endpoint found at startup, starting on {}",
+        log_cat::health(),
+        log_cat::error(),
+        endpoint_label(endpoint)
+    );
+}
+
+pub fn log_all_endpoints_down(fail_fast: bool) {
+    if fail_fast {
+        error!(
+            "{} {} All endpoints are down, failing requests fast until one recovers",
+            log_cat::health(),
+            log_cat::error()
+        );
+    } else {
+        error!(
+            "{} {} All endpoints are down",
+            log_cat::health(),
+            log_cat::error()
+        );
+    }
+}
+
+pub fn log_group_down(group: &str) {
+    error!(
+        "{} {} Every endpoint of group {} is down",
+        log_cat::health(),
+        log_cat::error(),
+        group
+    );
+}
+
+pub fn log_group_recovered(group: &str) {
+    info!(
+        "{} Group {} has a usable endpoint again",
+        log_cat::health(),
+        group
+    );
+}
+
+/// Proxy related logs
+pub fn log_proxy_request(endpoint: &str, request_bytes: usize, timeout: RequestTimeout) {
+    info!(
+        "{} Request {} {} ({} bytes, timeout {})",
+        log_cat::proxy(),
+        arrow(),
+        endpoint_label(endpoint),
+        request_bytes,
+        timeout
+    );
+}
+
+pub fn log_passthrough_request(endpoint: &str, method: &str, path: &str) {
+    info!(
+        "{} Passthrough {} {} ({} {})",
+        log_cat::proxy(),
+        arrow(),
+        endpoint_label(endpoint),
+        method,
+        path
+    );
+}
+
+pub fn log_cache_hit(endpoint: &str, method: &str, path: &str) {
+    info!(
+        "{} Cache hit for {} ({} {})",
+        log_cat::proxy(),
+        endpoint_label(endpoint),
+        method,
+        path
+    );
+}
+
+pub fn log_proxy_response(endpoint: &str, response_bytes: u64) {
+    info!(
+        "{} Response {} {} ({} bytes)",
+        log_cat::proxy(),
+        pick("←", "<-"),
+        endpoint_label(endpoint),
+        response_bytes
+    );
+}
+
+/// A request or response body at debug level, `shown` being its first
+/// `logging.body_max_bytes`
+pub fn log_body(connection_id: &ConnectionId, kind: &str, bytes: usize, shown: &str) {
+    let cut = if shown.len() < bytes { " ..." } else { "" };
+    debug!(
+        "{} {} body of {} ({} bytes): {}{}",
+        log_cat::proxy(),
+        kind,
+        connection_id,
+        bytes,
+        shown,
+        cut
+    );
+}
+
+/// A request or response body written to `path` at debug level
+pub fn log_body_file(connection_id: &ConnectionId, kind: &str, bytes: usize, path: &Path) {
+    debug!(
+        "{} {} body of {} ({} bytes) {} {}",
+        log_cat::proxy(),
+        kind,
+        connection_id,
+        bytes,
+        arrow(),
+        path.display()
+    );
+}
+
+pub fn log_body_file_error(dir: &Path, error: &std::io::Error) {
+    warn!(
+        "{} Failed to write a body file to {}: {}",
+        log_cat::error(),
+        dir.display(),
+        error
+    );
+}
+
+/// An upstream request failed, `connection_id` is `None` for requests the
+/// proxy does not track, such as passthrough calls
+pub fn log_proxy_error(endpoint: &str, error: &str, connection_id: Option<&ConnectionId>) {
+    if !first_in_window("Request failed", endpoint, error) {
+        return;
+    }
+    error!(
         endpoint,
-        latency
+        reason = error,
+        connection_id = connection_id.map(ConnectionId::as_str),
+        "request failed"
     );
 }
 
-pub fn log_health_failed(endpoint: &str, error: &str) {
+pub fn log_request_shed() {
+    if !first_in_window("Request failed fast", "all endpoints", "down") {
+        return;
+    }
+    error!(
+        "{} {} Request failed fast: all endpoints are down",
+        log_cat::proxy(),
+        log_cat::error()
+    );
+}
+
+pub fn log_request_over_in_flight_limit(limit: usize) {
+    if !first_in_window("Request shed", "in-flight limit", "reached") {
+        return;
+    }
+    warn!(
+        "{} Request shed: {} requests already in flight (server.max_in_flight_requests)",
+        log_cat::proxy(),
+        limit
+    );
+}
+
+pub fn log_failure_in_switch_grace(endpoint: &str) {
+    warn!(
+        "{} Failure on {} ignored: it was switched to moments ago",
+        log_cat::switch(),
+        endpoint_label(endpoint)
+    );
+}
+
+pub fn log_stream_stalled(endpoint: &str, idle_seconds: u64, bytes_received: u64) {
+    error!(
+        "{} {} Stream stalled: {} sent no data for {}s after {} bytes, aborting",
+        log_cat::proxy(),
+        log_cat::error(),
+        endpoint_label(endpoint),
+        idle_seconds,
+        bytes_received
+    );
+}
+
+/// Retry and fallback related logs. These carry their details as fields
+/// (`endpoint`, `attempt`, `max_attempts`, `delay_ms`, `status`, `reason`,
+/// `from`, `connection_id`) under a short fixed message, so structured
+/// subscribers can filter on them; [`ConsoleFields`] renders them as text.
+pub fn log_retry_attempt(
+    endpoint: &str,
+    attempt: u32,
+    max_attempts: u32,
+    reason: &str,
+    connection_id: &ConnectionId,
+) {
     warn!(
-        "{} {} Endpoint failed: {} - {}",
-        log_cat::HEALTH,
-        log_cat::ERROR,
         endpoint,
-        error
+        attempt,
+        max_attempts,
+        reason,
+        connection_id = connection_id.as_str(),
+        "retry attempt"
     );
 }
 
-/// Proxy related logs
-pub fn log_proxy_request(endpoint: &str) {
-    info!("{} Request → {}", log_cat::PROXY, endpoint);
+pub fn log_retry_delay(endpoint: &str, delay_ms: u64, connection_id: &ConnectionId) {
+    info!(
+        endpoint,
+        delay_ms,
+        connection_id = connection_id.as_str(),
+        "retry delay"
+    );
+}
+
+pub fn log_retry_skipped(endpoint: &str, status: u16, connection_id: &ConnectionId) {
+    warn!(
+        endpoint,
+        status,
+        connection_id = connection_id.as_str(),
+        "retry skipped"
+    );
+}
+
+pub fn log_retry_success(endpoint: &str, attempt: u32, connection_id: &ConnectionId) {
+    info!(
+        endpoint,
+        attempt,
+        connection_id = connection_id.as_str(),
+        "retry succeeded"
+    );
 }
 
-pub fn log_proxy_error(endpoint: &str, error: &str) {
+pub fn log_retry_exhausted(
+    endpoint: &str,
+    attempts: u32,
+    error: &str,
+    connection_id: &ConnectionId,
+) {
+    if !first_in_window("Retries exhausted", endpoint, error) {
+        return;
+    }
     error!(
-        "{} {} Request failed: {} - {}",
-        log_cat::PROXY,
-        log_cat::ERROR,
         endpoint,
-        error
+        attempt = attempts,
+        reason = error,
+        connection_id = connection_id.as_str(),
+        "retries exhausted"
     );
 }
 
-/// Switch related logs
-pub fn log_endpoint_switch(from: &str, to: &str, from_latency: u64, to_latency: u64) {
-    info!("{} ⚡ SWITCHING ENDPOINT ⚡", log_cat::SWITCH);
+pub fn log_request_rerouted(unavailable: &str, to: &str) {
+    info!(
+        "{} {} is down, sending the request to {} instead",
+        log_cat::proxy(),
+        endpoint_label(unavailable),
+        endpoint_label(to)
+    );
+}
+
+pub fn log_fallback_attempt(from: &str, to: &str, connection_id: &ConnectionId) {
+    warn!(
+        endpoint = to,
+        from,
+        connection_id = connection_id.as_str(),
+        "fallback attempt"
+    );
+}
+
+pub fn log_fallback_success(from: &str, to: &str, connection_id: &ConnectionId) {
     info!(
-        "{} From: {} ({}ms) → To: {} ({}ms)",
-        log_cat::SWITCH,
+        endpoint = to,
         from,
-        from_latency,
-        to,
-        to_latency
+        connection_id = connection_id.as_str(),
+        "fallback succeeded"
     );
+}
+
+pub fn log_drill_started(endpoint: &str) {
     info!(
-        "{} ╰─ Performance improvement: {}ms",
-        log_cat::SWITCH,
-        from_latency.saturating_sub(to_latency)
+        "{} Failover drill: treating {} as failed for one request",
+        log_cat::proxy(),
+        endpoint_label(endpoint)
     );
 }
 
+pub fn log_drill_result(endpoint: &str, served_by: Option<&str>, failover_ms: u64) {
+    match served_by {
+        Some(served_by) => info!(
+            "{} {} Failover drill passed: {} {} {} in {}ms",
+            log_cat::proxy(),
+            log_cat::success(),
+            endpoint_label(endpoint),
+            arrow(),
+            endpoint_label(served_by),
+            failover_ms
+        ),
+        None => error!(
+            "{} {} Failover drill failed: no fallback endpoint served the request for {}",
+            log_cat::proxy(),
+            log_cat::error(),
+            endpoint_label(endpoint)
+        ),
+    }
+}
+
+/// Switch related logs
+pub fn log_endpoint_switch(
+    from: &str,
+    to: &str,
+    from_latency: Option<u64>,
+    to_latency: Option<u64>,
+    reason: SwitchReason,
+) {
+    let bolt = pick("⚡ ", "");
+    info!(
+        "{} {bolt}SWITCHING ENDPOINT ({}) {}",
+        log_cat::switch(),
+        reason,
+        bolt.trim_end()
+    );
+    info!(
+        "{} From: {} ({}) {} To: {} ({})",
+        log_cat::switch(),
+        endpoint_label(from),
+        format_latency(from_latency),
+        arrow(),
+        endpoint_label(to),
+        format_latency(to_latency)
+    );
+    if let (Some(from_latency), Some(to_latency)) = (from_latency, to_latency) {
+        info!(
+            "{} {} Performance improvement: {}ms",
+            log_cat::switch(),
+            pick("╰─", "`-"),
+            from_latency.saturating_sub(to_latency)
+        );
+    }
+}
+
+/// Drain of a switched-away endpoint finished
+pub fn log_endpoint_drained(endpoint: &str, remaining: u32, elapsed: std::time::Duration) {
+    if remaining == 0 {
+        info!(
+            "{} Endpoint drained: {} ({}ms)",
+            log_cat::switch(),
+            endpoint_label(endpoint),
+            elapsed.as_millis()
+        );
+    } else {
+        warn!(
+            "{} Drain timed out: {} still has {} active connection(s) after {}ms",
+            log_cat::switch(),
+            endpoint_label(endpoint),
+            remaining,
+            elapsed.as_millis()
+        );
+    }
+}
+
+/// Format an optional latency for display, "n/a" when unknown
+pub fn format_latency(latency: Option<u64>) -> String {
+    match latency {
+        Some(ms) => format!("{ms}ms"),
+        None => "n/a".to_string(),
+    }
+}
+
+/// An HTTP check's phases, e.g. `DNS 4ms • connect 31ms • TLS 62ms • first byte 140ms`
+pub fn format_phases(phases: &LatencyPhases) -> String {
+    let first_byte = format!("first byte {}ms", phases.first_byte_ms);
+    let Some(connect) = phases.connect_ms else {
+        return format!("{first_byte} (reused connection)");
+    };
+    let mut parts = vec![
+        format!("DNS {}", format_latency(phases.resolve_ms)),
+        format!("connect {connect}ms"),
+    ];
+    if let Some(tls) = phases.tls_ms {
+        parts.push(format!("TLS {tls}ms"));
+    }
+    parts.push(first_byte);
+    parts.join(" • ")
+}
+
 /// Server related logs
 pub fn log_server_start(port: u16) {
-    info!("{} ══════════════════════════════════", log_cat::SERVER);
-    info!("{} 🎯 Claude Zephyr", log_cat::SERVER);
-    info!("{} ⚡ Server: http://localhost:{}", log_cat::SERVER, port);
+    let rule = pick(
+        "══════════════════════════════════",
+        "==================================",
+    );
+    info!("{} {}", log_cat::server(), rule);
+    info!("{} {}Claude Zephyr", log_cat::server(), pick("🎯 ", ""));
     info!(
-        "{} 📊 Status: http://localhost:{}/status",
-        log_cat::SERVER,
+        "{} {}Server: http://localhost:{}",
+        log_cat::server(),
+        pick("⚡ ", ""),
         port
     );
     info!(
-        "{} 🔍 Health: http://localhost:{}/health",
-        log_cat::SERVER,
+        "{} {}Status: http://localhost:{}/status",
+        log_cat::server(),
+        pick("📊 ", ""),
         port
     );
-    info!("{} ══════════════════════════════════", log_cat::SERVER);
+    info!(
+        "{} {}Health: http://localhost:{}/health",
+        log_cat::server(),
+        pick("🔍 ", ""),
+        port
+    );
+    info!("{} {}", log_cat::server(), rule);
+}
+
+/// An extra listener from `[[server.listeners]]`
+pub fn log_standby_changed(endpoint: Option<&str>) {
+    match endpoint {
+        Some(endpoint) => info!(
+            "{} Standby endpoint: {}, kept warm for fallback",
+            log_cat::health(),
+            endpoint_label(endpoint)
+        ),
+        None => info!(
+            "{} No standby endpoint: fallback has no healthy endpoint to go to",
+            log_cat::health()
+        ),
+    }
+}
+
+pub fn log_listener_start(url: &str, group: Option<&str>) {
+    info!(
+        "{} {}Listener: {} ({})",
+        log_cat::server(),
+        pick("🔌 ", ""),
+        url,
+        group.map_or_else(
+            || "all groups".to_string(),
+            |group| format!("group {group}")
+        )
+    );
+}
+
+/// `claude-zephyr monitor` runs the health checks with no listener
+pub fn log_monitor_start(endpoint_count: usize) {
+    info!(
+        "{} Monitoring {} endpoints, no requests are proxied",
+        log_cat::health(),
+        endpoint_count
+    );
 }
 
 pub fn log_server_error(error: &str) {
     error!(
         "{} {} Server error: {}",
-        log_cat::SERVER,
-        log_cat::ERROR,
+        log_cat::server(),
+        log_cat::error(),
+        error
+    );
+}
+
+pub fn log_lock_poisoned(value: &str) {
+    error!(
+        "{} A task panicked while holding the {} lock; recovered its last value",
+        log_cat::error(),
+        value
+    );
+}
+
+pub fn log_usage_report_error(error: &str) {
+    warn!(
+        "{} {} Usage report not saved: {}",
+        log_cat::server(),
+        log_cat::error(),
+        error
+    );
+}
+
+pub fn log_storage_error(error: &str) {
+    warn!(
+        "{} {} Request storage: {}",
+        log_cat::server(),
+        log_cat::error(),
+        error
+    );
+}
+
+pub fn log_state_handoff_saved(path: &Path) {
+    info!(
+        "{} {} State handoff snapshot written to {}",
+        log_cat::server(),
+        log_cat::success(),
+        path.display()
+    );
+}
+
+pub fn log_state_handoff_restored(path: &Path, sections: &[&str], discarded: &[String]) {
+    info!(
+        "{} {} Resumed state from {}: {}",
+        log_cat::server(),
+        log_cat::success(),
+        path.display(),
+        sections.join(", ")
+    );
+    if !discarded.is_empty() {
+        warn!(
+            "{} {} State handoff sections discarded: {}",
+            log_cat::server(),
+            log_cat::error(),
+            discarded.join(", ")
+        );
+    }
+}
+
+pub fn log_state_handoff_error(error: &str) {
+    warn!(
+        "{} {} State handoff: {}",
+        log_cat::server(),
+        log_cat::error(),
         error
     );
 }
@@ -100,16 +1049,108 @@ pub fn log_server_error(error: &str) {
 pub fn log_config_loaded(endpoint_count: usize) {
     info!(
         "{} Configuration loaded: {} endpoints",
-        log_cat::CONFIG,
+        log_cat::config(),
         endpoint_count
     );
 }
 
-pub fn log_config_error(error: &str) {
-    error!(
-        "{} {} Configuration error: {}",
-        log_cat::CONFIG,
-        log_cat::ERROR,
+pub fn log_group_missing_token(group: &str, token_env: &str, endpoint_count: usize) {
+    warn!(
+        "{} Group '{}' has no token: {} is not set, its {} endpoints stay out of rotation",
+        log_cat::config(),
+        group,
+        token_env,
+        endpoint_count
+    );
+}
+
+pub fn log_claude_binary(groups: &[&str], path: &std::path::Path, version: Option<&str>) {
+    info!(
+        "{} Claude CLI for {}: {} ({})",
+        log_cat::config(),
+        groups.join(", "),
+        path.display(),
+        version.unwrap_or("version unknown")
+    );
+}
+
+pub fn log_token_check(group: &str, check: &TokenCheck) {
+    match check.validity {
+        TokenValidity::Valid => info!(
+            "{} {} group {}: token valid",
+            log_cat::config(),
+            log_cat::success(),
+            group
+        ),
+        TokenValidity::Invalid => warn!(
+            "{} {} group {}: token invalid ({}) at {}",
+            log_cat::config(),
+            log_cat::error(),
+            group,
+            check.http_status.unwrap_or_default(),
+            endpoint_label(&check.endpoint)
+        ),
+        TokenValidity::Unknown => warn!(
+            "{} group {}: token not verified, {}",
+            log_cat::config(),
+            group,
+            check.detail.as_deref().unwrap_or("no endpoint answered")
+        ),
+    }
+}
+
+pub fn log_group_token_found(group: &str, token_env: &str) {
+    info!(
+        "{} {} Group '{}' rejoined: {} is now set",
+        log_cat::config(),
+        log_cat::success(),
+        group,
+        token_env
+    );
+}
+
+pub fn log_env_file_reloaded(path: &str, changed: &[String]) {
+    if changed.is_empty() {
+        info!("{} Reloaded {}: no changes", log_cat::config(), path);
+    } else {
+        info!(
+            "{} Reloaded {}: updated {}",
+            log_cat::config(),
+            path,
+            changed.join(", ")
+        );
+    }
+}
+
+pub fn log_config_reloaded(path: &str, diff: &ConfigDiff) {
+    if diff.listener_error.is_some() {
+        warn!(
+            "{} {} Reloaded {}: {}",
+            log_cat::config(),
+            log_cat::error(),
+            path,
+            diff
+        );
+    } else {
+        info!("{} Reloaded {}: {}", log_cat::config(), path, diff);
+    }
+}
+
+pub fn log_config_reload_failed(error: &str) {
+    warn!(
+        "{} {} Config not reloaded, keeping the running config: {}",
+        log_cat::config(),
+        log_cat::error(),
+        error
+    );
+}
+
+pub fn log_env_file_missing(path: &str, error: &str) {
+    warn!(
+        "{} {} Env file {} could not be read ({}); keeping the values loaded before",
+        log_cat::config(),
+        log_cat::error(),
+        path,
         error
     );
 }
@@ -135,7 +1176,7 @@ pub mod debug {
     pub fn log_health_check_details(endpoint: &str, timeout: u64) {
         debug!(
             "{} Health check details: {} (timeout: {}s)",
-            log_cat::HEALTH,
+            log_cat::health(),
             endpoint,
             timeout
         );
@@ -145,7 +1186,7 @@ pub mod debug {
     pub fn log_connection_count(endpoint: &str, count: u32) {
         debug!(
             "{} Active connections to {}: {}",
-            log_cat::PROXY,
+            log_cat::proxy(),
             endpoint,
             count
         );
@@ -153,7 +1194,160 @@ pub mod debug {
 
     #[allow(dead_code)]
     pub fn log_request_details(method: &str, path: &str, endpoint: &str) {
-        debug!("{} {} {} → {}", log_cat::PROXY, method, path, endpoint);
+        debug!("{} {} {} {} {}", log_cat::proxy(), method, path, arrow(), endpoint);
     }
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TimeoutSource;
+    use std::io;
+    use std::sync::Arc;
+
+    /// Held by tests that turn emoji off, which is a process-wide setting
+    static EMOJI_SETTING: Mutex<()> = Mutex::new(());
+
+    /// Log output collected in memory
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Everything a sample of the logging helpers writes, formatted the way
+    /// the binary formats console logs
+    fn log_everything() -> String {
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .fmt_fields(ConsoleFields)
+            .with_ansi(false)
+            .with_max_level(tracing::Level::TRACE)
+            .finish();
+        let id = crate::connection_tracker::generate_connection_id();
+        let (a, b) = ("https://a.example.com", "https://b.example.com");
+        tracing::subscriber::with_default(subscriber, || {
+            log_server_start(8080);
+            log_listener_start("http://127.0.0.1:8081", Some("main"));
+            log_monitor_start(2);
+            log_config_loaded(2);
+            log_health_start(a);
+            log_health_success(a, 120);
+            log_health_failed(b, "connection refused");
+            log_auth_rejected(b);
+            log_maintenance_started(a, chrono::Utc::now());
+            log_maintenance_ended(a);
+            log_all_endpoints_down(true);
+            log_proxy_request(
+                a,
+                42,
+                RequestTimeout {
+                    seconds: 30,
+                    source: TimeoutSource::Server,
+                },
+            );
+            log_proxy_response(a, 1024);
+            log_body(&id, "Request", 10, "{\"a\"");
+            log_body_file(&id, "Response", 10, Path::new("bodies/a.response.body"));
+            log_body_file_error(Path::new("bodies"), &io::Error::other("denied"));
+            log_proxy_error(a, "reset", Some(&id));
+            log_request_shed();
+            log_stream_stalled(a, 60, 10);
+            log_retry_attempt(a, 1, 3, "status 500", &id);
+            log_retry_delay(a, 500, &id);
+            log_retry_skipped(a, 400, &id);
+            log_retry_success(a, 2, &id);
+            log_retry_exhausted(a, 3, "status 500", &id);
+            log_request_rerouted(a, b);
+            log_fallback_attempt(a, b, &id);
+            log_fallback_success(a, b, &id);
+            log_endpoint_switch(a, b, Some(300), Some(120), SwitchReason::Failover);
+            log_endpoint_drained(a, 0, Duration::from_secs(3));
+            log_standby_changed(Some(b));
+            log_standby_changed(None);
+            log_server_error("bind failed");
+            log_config_reload_failed("bad toml");
+        });
+        let output = capture.0.lock().unwrap().clone();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn a_body_cut_at_body_max_bytes_is_marked() {
+        let output = log_everything();
+        assert!(output.contains("(10 bytes): {\"a\" ..."), "{output}");
+    }
+
+    #[test]
+    fn no_emoji_output_is_ascii_only() {
+        let _setting = EMOJI_SETTING.lock().unwrap_or_else(PoisonError::into_inner);
+        set_console_emoji(true);
+        let fancy = log_everything();
+        set_console_emoji(false);
+        let plain = log_everything();
+        set_console_emoji(true);
+
+        assert!(!fancy.is_ascii(), "{fancy}");
+        assert_eq!(fancy.lines().count(), plain.lines().count());
+        let offending: Vec<&str> = plain.lines().filter(|line| !line.is_ascii()).collect();
+        assert!(offending.is_empty(), "{offending:#?}");
+        assert!(plain.contains("[SERVER] =========="), "{plain}");
+        assert!(
+            plain.contains("[HEALTH] [ERROR] Endpoint failed"),
+            "{plain}"
+        );
+    }
+
+    #[test]
+    fn console_text_spells_symbols_in_ascii() {
+        let _setting = EMOJI_SETTING.lock().unwrap_or_else(PoisonError::into_inner);
+        set_console_emoji(false);
+        assert_eq!(console_text("✅ Ready → main"), "Ready -> main");
+        assert_eq!(console_text("⚙️ a — b…"), "a - b...");
+        assert_eq!(console_text("│ • ok"), "| * ok");
+        assert_eq!(console_text("plain"), "plain");
+        set_console_emoji(true);
+        assert_eq!(console_text("✅ Ready"), "✅ Ready");
+    }
+
+    #[test]
+    fn phases_show_the_connection_stages_only_when_one_was_opened() {
+        let opened = LatencyPhases {
+            resolve_ms: Some(4),
+            connect_ms: Some(31),
+            tls_ms: Some(62),
+            first_byte_ms: 140,
+        };
+        assert_eq!(
+            format_phases(&opened),
+            "DNS 4ms • connect 31ms • TLS 62ms • first byte 140ms"
+        );
+        let plain = LatencyPhases {
+            tls_ms: None,
+            ..opened
+        };
+        assert_eq!(
+            format_phases(&plain),
+            "DNS 4ms • connect 31ms • first byte 140ms"
+        );
+        let reused = LatencyPhases {
+            first_byte_ms: 90,
+            ..LatencyPhases::default()
+        };
+        assert_eq!(
+            format_phases(&reused),
+            "first byte 90ms (reused connection)"
+        );
+    }
+}
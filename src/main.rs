@@ -1,194 +1,183 @@
-mod config;
-mod connection_tracker;
-mod dashboard;
-mod dev_tools;
-mod dynamic_health;
-mod events;
-mod health;
-mod health_orchestrator;
-mod i18n;
-mod logging;
-mod proxy;
-mod signal_handler;
-
-use clap::Parser;
-use config::Config;
-use connection_tracker::{ConnectionTracker, SharedConnectionTracker};
-use dashboard::Dashboard;
-use events::ProxyEvent;
-use health_orchestrator::HealthCheckOrchestrator;
-use logging::*;
-use proxy::{ProxyState, SharedState};
-use signal_handler::GracefulShutdown;
-use std::sync::{Arc, Mutex};
-use tokio::sync::mpsc;
+use clap::{Parser, Subcommand};
+use claude_zephyr::build_info;
+use claude_zephyr::config_view::{self, ConfigCommand};
+use claude_zephyr::diagnose::{self, DiagnoseArgs};
+use claude_zephyr::drill::{self, DrillArgs};
+use claude_zephyr::monitor::{self, MonitorArgs};
+use claude_zephyr::report::{self, ReportArgs};
+use claude_zephyr::smoke::{self, SmokeArgs};
+use claude_zephyr::{Config, ProxyServer, RunMode};
+use std::io::IsTerminal;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "claude-zephyr")]
 #[command(about = "Automatic endpoint switching for Claude API")]
+#[command(version, long_version = build_info::LONG_VERSION)]
 struct Args {
     /// Run in headless mode (for development only)
-    #[arg(long, help = "Run in headless mode without TUI dashboard (development use)")]
+    #[arg(
+        long,
+        help = "Run in headless mode without TUI dashboard (development use)"
+    )]
     headless: bool,
 
     /// Run timing self-test
     #[arg(long, help = "Run health check timing self-test")]
     test_timing: bool,
+
+    /// Start serving without verifying the starting endpoint first
+    #[arg(long)]
+    skip_startup_check: bool,
+
+    /// Check group tokens before serving and refuse to start if one is rejected
+    #[arg(long)]
+    strict: bool,
+
+    /// Read group tokens from this file instead of `env_file` in the config or .env
+    #[arg(long, value_name = "PATH")]
+    env_file: Option<PathBuf>,
+
+    /// Resume from the runtime state snapshot at this path, and write it there
+    /// on SIGUSR2 or POST /admin/prepare-restart for the next process
+    #[arg(long, value_name = "PATH")]
+    state_handoff: Option<PathBuf>,
+
+    /// Print plain ASCII tags such as [HEALTH] instead of emoji in log output
+    #[arg(long)]
+    no_emoji: bool,
+
+    /// Log at debug level in headless mode, including request and response
+    /// bodies (see logging.body_max_bytes and logging.body_to_file)
+    #[arg(long)]
+    debug: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the daily usage summary recorded by the proxy
+    Report(ReportArgs),
+    /// Proxy one minimal request through a throwaway instance and report the result
+    Smoke(SmokeArgs),
+    /// Save a diagnostic snapshot of the running proxy for a problem report
+    Diagnose(DiagnoseArgs),
+    /// Check that fallback works by failing the running proxy's current
+    /// endpoint for one synthetic request
+    Drill(DrillArgs),
+    /// Run the health checks as a latency monitor, without proxying anything
+    Monitor(MonitorArgs),
+    /// Inspect the effective configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+/// Multithreaded runtime everything async runs on
+fn runtime() -> anyhow::Result<tokio::runtime::Runtime> {
+    Ok(tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?)
+}
+
+// Not `#[tokio::main]`: the env file is written to the process environment
+// while loading the config, which must happen before any other thread runs
+fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
+    // The report only reads the usage file, so it needs no configuration
+    if let Some(Command::Report(report_args)) = &args.command {
+        return report::print_report(report_args);
+    }
+
     // Run timing test if requested
     if args.test_timing {
-        return dev_tools::test_health_check_timing().await;
+        return runtime()?.block_on(claude_zephyr::test_health_check_timing());
     }
 
     // Initialize logging based on mode
-    if args.headless {
-        // Headless mode: enable beautiful logging
-        tracing_subscriber::fmt::init();
+    let console_logs = match &args.command {
+        Some(Command::Monitor(monitor_args)) => !monitor_args.dashboard,
+        _ => args.headless,
+    };
+    if console_logs {
+        // Headless mode: enable beautiful logging, colored only on a terminal
+        tracing_subscriber::fmt()
+            .fmt_fields(claude_zephyr::ConsoleFields)
+            .with_ansi(std::io::stdout().is_terminal())
+            .with_max_level(if args.debug {
+                tracing::Level::DEBUG
+            } else {
+                tracing::Level::INFO
+            })
+            .init();
     }
     // Dashboard mode: no console logging to avoid interfering with TUI
 
+    // Before the config is read, so loading messages are plain as well
+    if args.no_emoji {
+        claude_zephyr::set_console_emoji(false);
+    }
+
     // Load configuration
-    let config = Config::load_default().map_err(|e| {
-        if args.headless {
-            log_config_error(&format!("Failed to load configuration: {e}"));
+    let mut config = Config::load_default_with_env(args.env_file.as_deref()).map_err(|e| {
+        if console_logs {
+            tracing::error!(
+                "{}",
+                claude_zephyr::console_text(&format!(
+                    "⚙️ ❌ Configuration error: Failed to load configuration: {e}"
+                ))
+            );
         }
         eprintln!("Please create a config.toml file or ensure the auth token is properly set.");
+        if args.no_emoji {
+            return anyhow::anyhow!("{}", claude_zephyr::console_text(&format!("{e:#}")));
+        }
         e
     })?;
-
-    if args.headless {
-        let total_endpoints: usize = config.groups.iter().map(|g| g.endpoints.len()).sum();
-        log_config_loaded(total_endpoints);
+    if args.no_emoji {
+        config.logging.emoji = false;
     }
-
-    // Create connection tracker and event system
-    let connection_tracker = Arc::new(Mutex::new(ConnectionTracker::new()));
-    let (event_sender, event_receiver) = mpsc::unbounded_channel::<ProxyEvent>();
-
-    // Send initial config event
-    let total_endpoints: usize = config.groups.iter().map(|g| g.endpoints.len()).sum();
-    let _ = event_sender.send(ProxyEvent::ConfigLoaded {
-        endpoint_count: total_endpoints,
-    });
-
-    let state = Arc::new(Mutex::new(ProxyState::new(config.clone())));
-
-    // Check if headless mode is enabled (for development)
-    if args.headless {
-        // Run in headless mode (development use)
-        run_normal_mode(config, state, connection_tracker, event_sender).await
-    } else {
-        // Run in dashboard mode (default behavior)
-        run_with_dashboard(
-            config,
-            state,
-            connection_tracker,
-            event_sender,
-            event_receiver,
-        )
-        .await
+    claude_zephyr::set_console_language(config.ui.language.clone());
+    if args.skip_startup_check {
+        config.health_check.startup_check = false;
+    }
+    if args.strict {
+        config.health_check.validate_tokens = true;
+        config.health_check.strict_token_validation = true;
     }
-}
 
-async fn run_with_dashboard(
-    config: Config,
-    state: SharedState,
-    connection_tracker: SharedConnectionTracker,
-    event_sender: mpsc::UnboundedSender<ProxyEvent>,
-    event_receiver: mpsc::UnboundedReceiver<ProxyEvent>,
-) -> anyhow::Result<()> {
-    // Create dashboard before moving config into spawned tasks
-    let dashboard_interval = config.health_check_interval();
-    let mut dashboard = Dashboard::new(&config, dashboard_interval);
-
-    // Start health check orchestrator (dashboard mode - no console logs)
-    let health_state = state.clone();
-    let health_config = config.clone();
-    let health_sender = event_sender.clone();
-    let health_tracker = connection_tracker.clone();
-
-    let (health_orchestrator, orchestrator_command_sender) = HealthCheckOrchestrator::new(
-        health_config,
-        health_state,
-        health_sender,
-        true, // dashboard mode
-        Some(health_tracker),
-    );
-
-    tokio::spawn(async move {
-        if let Err(e) = health_orchestrator.run().await {
-            tracing::error!("Health check orchestrator error: {}", e);
-        }
-    });
-
-    // Start proxy server (dashboard mode - no console logs)
-    let proxy_sender = event_sender.clone();
-    let proxy_tracker = connection_tracker.clone();
-    let proxy_state = state.clone(); // Clone for proxy server
-    tokio::spawn(async move {
-        let _ = proxy::start_proxy_server_with_events_dashboard(
-            config,
-            proxy_state,
-            proxy_tracker,
-            proxy_sender,
-        )
-        .await;
-    });
-
-    // Run dashboard
-    dashboard
-        .run(
-            event_receiver,
-            connection_tracker,
-            state,
-            orchestrator_command_sender,
-        )
-        .await
+    runtime()?.block_on(run(args, config))
 }
 
-async fn run_normal_mode(
-    config: Config,
-    state: SharedState,
-    connection_tracker: SharedConnectionTracker,
-    event_sender: mpsc::UnboundedSender<ProxyEvent>,
-) -> anyhow::Result<()> {
-    // Create graceful shutdown handler
-    let shutdown_handler = GracefulShutdown::new(connection_tracker.clone(), event_sender.clone());
-
-    // Start health check orchestrator (normal mode - with console logs)
-    let health_state = state.clone();
-    let health_config = config.clone();
-    let health_sender = event_sender.clone();
-    let health_tracker = connection_tracker.clone();
-
-    let (health_orchestrator, _orchestrator_command_sender) = HealthCheckOrchestrator::new(
-        health_config,
-        health_state,
-        health_sender,
-        false, // normal mode
-        Some(health_tracker),
-    );
-
-    // Start health check orchestrator task
-    tokio::spawn(async move {
-        if let Err(e) = health_orchestrator.run().await {
-            tracing::error!("Health check orchestrator error: {}", e);
-        }
-    });
+/// Run the command `args` ask for with the loaded `config`
+async fn run(args: Args, config: Config) -> anyhow::Result<()> {
+    if let Some(Command::Smoke(smoke_args)) = &args.command {
+        return smoke::run_smoke(config, smoke_args).await;
+    }
+    if let Some(Command::Diagnose(diagnose_args)) = &args.command {
+        return diagnose::run_diagnose(&config, diagnose_args).await;
+    }
+    if let Some(Command::Drill(drill_args)) = &args.command {
+        return drill::run_drill(&config, drill_args).await;
+    }
+    if let Some(Command::Monitor(monitor_args)) = &args.command {
+        return monitor::run_monitor(config, monitor_args).await;
+    }
+    if let Some(Command::Config(config_command)) = &args.command {
+        return config_view::run_config(&config, config_command).await;
+    }
 
-    // Start proxy server with signal handling
-    tokio::select! {
-        result = proxy::start_proxy_server_with_events(config, state, connection_tracker, event_sender) => {
-            result
-        }
-        _ = shutdown_handler.wait_for_shutdown() => {
-            println!("✅ Graceful shutdown completed");
-            Ok(())
-        }
+    // Dashboard is the default; headless mode is for development
+    let mode = if args.headless {
+        RunMode::Normal
+    } else {
+        RunMode::Dashboard
+    };
+    let mut builder = ProxyServer::builder(config).mode(mode);
+    if let Some(path) = args.state_handoff {
+        builder = builder.state_handoff(path);
     }
+    builder.build().run().await
 }
@@ -0,0 +1,147 @@
+//! Recurring maintenance windows, during which an endpoint is taken out of
+//! rotation instead of being health checked and failed over from
+
+use crate::config::MaintenanceWindow;
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeZone, Utc, Weekday,
+};
+
+/// Days searched for the next start of a window, enough for a weekly one
+const LOOKAHEAD_DAYS: i64 = 8;
+
+/// Time zone maintenance windows are written in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaintenanceTimeZone {
+    /// The time zone of the machine running the proxy
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl MaintenanceTimeZone {
+    /// Parse `local`, `utc` or an offset such as `+08:00`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "local" => Some(Self::Local),
+            "utc" => FixedOffset::east_opt(0).map(Self::Fixed),
+            offset => offset.parse().ok().map(Self::Fixed),
+        }
+    }
+
+    fn to_naive(self, at: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Self::Local => at.with_timezone(&Local).naive_local(),
+            Self::Fixed(offset) => at.with_timezone(&offset).naive_local(),
+        }
+    }
+
+    /// `None` for a local time skipped by a daylight saving change
+    fn to_utc(self, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Local => Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|at| at.with_timezone(&Utc)),
+            Self::Fixed(offset) => offset
+                .from_local_datetime(&naive)
+                .single()
+                .map(|at| at.with_timezone(&Utc)),
+        }
+    }
+}
+
+/// A parsed [`MaintenanceWindow`]
+struct Schedule {
+    start: NaiveTime,
+    end: NaiveTime,
+    /// Days the window starts on, every day when empty
+    days: Vec<Weekday>,
+}
+
+impl Schedule {
+    fn parse(window: &MaintenanceWindow) -> Result<Self, String> {
+        let (start, end) = window
+            .window
+            .split_once('-')
+            .ok_or_else(|| "expected a start and end time like \"03:00-03:15\"".to_string())?;
+        let time = |value: &str| {
+            NaiveTime::parse_from_str(value.trim(), "%H:%M")
+                .map_err(|_| format!("'{}' is not a time like \"03:00\"", value.trim()))
+        };
+        let (start, end) = (time(start)?, time(end)?);
+        if start == end {
+            return Err("the window starts and ends at the same time".to_string());
+        }
+        let days = window
+            .days
+            .iter()
+            .map(|day| {
+                day.parse::<Weekday>()
+                    .map_err(|_| format!("'{day}' is not a day like \"mon\""))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { start, end, days })
+    }
+
+    /// Local start and end of the window starting on `date`, if it runs that
+    /// day. A window ending before it starts ends the next day.
+    fn occurrence(&self, date: NaiveDate) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        if !self.days.is_empty() && !self.days.contains(&date.weekday()) {
+            return None;
+        }
+        let end_date = if self.end > self.start {
+            date
+        } else {
+            date + Duration::days(1)
+        };
+        Some((date.and_time(self.start), end_date.and_time(self.end)))
+    }
+}
+
+/// Check that a window parses, describing the problem if it does not
+pub fn validate_window(window: &MaintenanceWindow) -> Result<(), String> {
+    Schedule::parse(window).map(|_| ())
+}
+
+fn schedules(windows: &[MaintenanceWindow]) -> impl Iterator<Item = Schedule> + '_ {
+    windows
+        .iter()
+        .filter_map(|window| Schedule::parse(window).ok())
+}
+
+/// End of the window `now` falls in, the latest one if windows overlap
+pub fn active_until(
+    windows: &[MaintenanceWindow],
+    time_zone: MaintenanceTimeZone,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let local = time_zone.to_naive(now);
+    let today = local.date();
+    schedules(windows)
+        .flat_map(|schedule| {
+            [today - Duration::days(1), today]
+                .into_iter()
+                .filter_map(move |date| schedule.occurrence(date))
+        })
+        .filter(|(start, end)| *start <= local && local < *end)
+        .filter_map(|(_, end)| time_zone.to_utc(end))
+        .max()
+}
+
+/// When the next window after `now` starts
+pub fn next_start(
+    windows: &[MaintenanceWindow],
+    time_zone: MaintenanceTimeZone,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let local = time_zone.to_naive(now);
+    let today = local.date();
+    schedules(windows)
+        .flat_map(|schedule| {
+            (0..LOOKAHEAD_DAYS)
+                .filter_map(move |day| schedule.occurrence(today + Duration::days(day)))
+        })
+        .filter(|(start, _)| *start > local)
+        .filter_map(|(start, _)| time_zone.to_utc(start))
+        .min()
+}
@@ -0,0 +1,974 @@
+use crate::config::Config;
+use crate::endpoint::{EndpointId, EndpointIndex};
+use crate::health::EndpointStatus;
+use crate::lock::RwLockExt;
+use crate::usage::{TokenUsage, HEALTH_CHECK_INPUT_TOKENS, HEALTH_CHECK_OUTPUT_TOKENS};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Width of the sliding window behind "retries in the last minute"
+const RECENT_WINDOW_SECONDS: u64 = 60;
+
+pub type SharedMetrics = Arc<ProxyMetrics>;
+
+/// Group prices converted to integer micro-dollars per million tokens
+#[derive(Debug, Clone, Copy, Default)]
+struct Pricing {
+    input_micros_per_mtok: u64,
+    output_micros_per_mtok: u64,
+}
+
+impl Pricing {
+    fn from_prices(input_price_per_mtok: f64, output_price_per_mtok: f64) -> Self {
+        Self {
+            input_micros_per_mtok: (input_price_per_mtok * 1_000_000.0).round() as u64,
+            output_micros_per_mtok: (output_price_per_mtok * 1_000_000.0).round() as u64,
+        }
+    }
+
+    fn is_free(&self) -> bool {
+        self.input_micros_per_mtok == 0 && self.output_micros_per_mtok == 0
+    }
+
+    /// Cost in micro-dollars, computed from exact token totals so nothing drifts
+    fn cost_micros(&self, input_tokens: u64, output_tokens: u64) -> u64 {
+        let micros = (input_tokens as u128 * self.input_micros_per_mtok as u128
+            + output_tokens as u128 * self.output_micros_per_mtok as u128)
+            / 1_000_000;
+        micros as u64
+    }
+}
+
+/// How a proxied request ended, and who a failure is attributed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestOutcome {
+    Success,
+    /// The request itself was rejected (4xx other than 408/429); forwarded as-is
+    /// and never held against the endpoint
+    ClientError,
+    /// 408, 429, 5xx or a proxy failure; retried and eligible for fallback
+    EndpointError,
+}
+
+impl RequestOutcome {
+    pub fn from_status(status: hyper::StatusCode) -> Self {
+        if status.is_server_error()
+            || status == hyper::StatusCode::REQUEST_TIMEOUT
+            || status == hyper::StatusCode::TOO_MANY_REQUESTS
+        {
+            RequestOutcome::EndpointError
+        } else if status.is_client_error() {
+            RequestOutcome::ClientError
+        } else {
+            RequestOutcome::Success
+        }
+    }
+}
+
+/// Per-endpoint request counters
+#[derive(Debug, Default)]
+struct EndpointCounters {
+    /// Last URL the endpoint had, which the counters are reported under
+    url: String,
+    requests: AtomicU64,
+    endpoint_errors: AtomicU64,
+    client_errors: AtomicU64,
+    ancillary_requests: AtomicU64,
+    switches: AtomicU64,
+    retry_attempts: AtomicU64,
+    retry_successes: AtomicU64,
+    retries_exhausted: AtomicU64,
+    stream_stalls: AtomicU64,
+    request_timeouts: AtomicU64,
+    body_timeouts: AtomicU64,
+    fallback_timeouts: AtomicU64,
+    input_tokens: AtomicU64,
+    output_tokens: AtomicU64,
+    health_checks: AtomicU64,
+    pricing: Pricing,
+}
+
+impl EndpointCounters {
+    fn snapshot(&self) -> CounterSnapshot {
+        let input_tokens = self.input_tokens.load(Ordering::Relaxed);
+        let output_tokens = self.output_tokens.load(Ordering::Relaxed);
+        let health_checks = self.health_checks.load(Ordering::Relaxed);
+        CounterSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            endpoint_errors: self.endpoint_errors.load(Ordering::Relaxed),
+            client_errors: self.client_errors.load(Ordering::Relaxed),
+            ancillary_requests: self.ancillary_requests.load(Ordering::Relaxed),
+            switches: self.switches.load(Ordering::Relaxed),
+            retry_attempts: self.retry_attempts.load(Ordering::Relaxed),
+            retry_successes: self.retry_successes.load(Ordering::Relaxed),
+            retries_exhausted: self.retries_exhausted.load(Ordering::Relaxed),
+            stream_stalls: self.stream_stalls.load(Ordering::Relaxed),
+            request_timeouts: self.request_timeouts.load(Ordering::Relaxed),
+            body_timeouts: self.body_timeouts.load(Ordering::Relaxed),
+            fallback_timeouts: self.fallback_timeouts.load(Ordering::Relaxed),
+            input_tokens,
+            output_tokens,
+            health_checks,
+            request_cost_micros: self.pricing.cost_micros(input_tokens, output_tokens),
+            health_check_cost_micros: self.pricing.cost_micros(
+                health_checks * HEALTH_CHECK_INPUT_TOKENS,
+                health_checks * HEALTH_CHECK_OUTPUT_TOKENS,
+            ),
+        }
+    }
+}
+
+/// Point-in-time copy of an endpoint's counters
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CounterSnapshot {
+    /// Proxied requests answered on behalf of the endpoint
+    pub requests: u64,
+    /// Requests that ended in a 408, 429, server error or proxy failure
+    pub endpoint_errors: u64,
+    /// Requests the upstream rejected with another 4xx, caused by the client
+    pub client_errors: u64,
+    /// Requests matching a passthrough rule, not counted in `requests`
+    pub ancillary_requests: u64,
+    /// Times the endpoint became the current one
+    pub switches: u64,
+    /// Retries sent after a failed attempt
+    pub retry_attempts: u64,
+    /// Requests that succeeded after at least one retry
+    pub retry_successes: u64,
+    /// Requests that failed on every attempt
+    pub retries_exhausted: u64,
+    /// Response streams aborted after going idle
+    pub stream_stalls: u64,
+    /// Requests that ended without response headers within the request timeout
+    pub request_timeouts: u64,
+    /// Response bodies cut off by the total timeout while still streaming
+    pub body_timeouts: u64,
+    /// Fallback attempts on the endpoint that got no response headers in time
+    pub fallback_timeouts: u64,
+    /// Tokens reported by upstream responses
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Health checks run against the endpoint
+    pub health_checks: u64,
+    /// Estimated cost of proxied requests in micro-dollars
+    pub request_cost_micros: u64,
+    /// Estimated cost of health checks in micro-dollars
+    pub health_check_cost_micros: u64,
+}
+
+impl CounterSnapshot {
+    /// Counters accumulated since an earlier snapshot of the same endpoint
+    pub fn since(&self, earlier: &CounterSnapshot) -> CounterSnapshot {
+        CounterSnapshot {
+            requests: self.requests.saturating_sub(earlier.requests),
+            endpoint_errors: self.endpoint_errors.saturating_sub(earlier.endpoint_errors),
+            client_errors: self.client_errors.saturating_sub(earlier.client_errors),
+            ancillary_requests: self
+                .ancillary_requests
+                .saturating_sub(earlier.ancillary_requests),
+            switches: self.switches.saturating_sub(earlier.switches),
+            retry_attempts: self.retry_attempts.saturating_sub(earlier.retry_attempts),
+            retry_successes: self.retry_successes.saturating_sub(earlier.retry_successes),
+            retries_exhausted: self
+                .retries_exhausted
+                .saturating_sub(earlier.retries_exhausted),
+            stream_stalls: self.stream_stalls.saturating_sub(earlier.stream_stalls),
+            request_timeouts: self
+                .request_timeouts
+                .saturating_sub(earlier.request_timeouts),
+            body_timeouts: self.body_timeouts.saturating_sub(earlier.body_timeouts),
+            fallback_timeouts: self
+                .fallback_timeouts
+                .saturating_sub(earlier.fallback_timeouts),
+            input_tokens: self.input_tokens.saturating_sub(earlier.input_tokens),
+            output_tokens: self.output_tokens.saturating_sub(earlier.output_tokens),
+            health_checks: self.health_checks.saturating_sub(earlier.health_checks),
+            request_cost_micros: self
+                .request_cost_micros
+                .saturating_sub(earlier.request_cost_micros),
+            health_check_cost_micros: self
+                .health_check_cost_micros
+                .saturating_sub(earlier.health_check_cost_micros),
+        }
+    }
+}
+
+impl std::ops::Add for CounterSnapshot {
+    type Output = CounterSnapshot;
+
+    fn add(self, other: CounterSnapshot) -> CounterSnapshot {
+        CounterSnapshot {
+            requests: self.requests + other.requests,
+            endpoint_errors: self.endpoint_errors + other.endpoint_errors,
+            client_errors: self.client_errors + other.client_errors,
+            ancillary_requests: self.ancillary_requests + other.ancillary_requests,
+            switches: self.switches + other.switches,
+            retry_attempts: self.retry_attempts + other.retry_attempts,
+            retry_successes: self.retry_successes + other.retry_successes,
+            retries_exhausted: self.retries_exhausted + other.retries_exhausted,
+            stream_stalls: self.stream_stalls + other.stream_stalls,
+            request_timeouts: self.request_timeouts + other.request_timeouts,
+            body_timeouts: self.body_timeouts + other.body_timeouts,
+            fallback_timeouts: self.fallback_timeouts + other.fallback_timeouts,
+            input_tokens: self.input_tokens + other.input_tokens,
+            output_tokens: self.output_tokens + other.output_tokens,
+            health_checks: self.health_checks + other.health_checks,
+            request_cost_micros: self.request_cost_micros + other.request_cost_micros,
+            health_check_cost_micros: self.health_check_cost_micros
+                + other.health_check_cost_micros,
+        }
+    }
+}
+
+/// Counters by endpoint id, with the index request URLs are resolved through
+#[derive(Debug, Default)]
+struct EndpointCounterMap {
+    index: EndpointIndex,
+    by_id: HashMap<EndpointId, EndpointCounters>,
+}
+
+impl EndpointCounterMap {
+    /// Counters of the endpoint at `url`, found by its last URL once a reload
+    /// removed it, so requests it still drains are counted
+    fn get(&self, url: &str) -> Option<&EndpointCounters> {
+        match self.index.id_of(url) {
+            Some(id) => self.by_id.get(id),
+            None => self.by_id.values().find(|counters| counters.url == url),
+        }
+    }
+}
+
+/// Event counter over the last minute, bucketed by second.
+///
+/// Buckets are reused as the clock wraps around, so the count is approximate
+/// when two threads race on a bucket rollover.
+#[derive(Debug)]
+struct RecentCounter {
+    seconds: [AtomicU64; RECENT_WINDOW_SECONDS as usize],
+    counts: [AtomicU64; RECENT_WINDOW_SECONDS as usize],
+}
+
+impl RecentCounter {
+    fn new() -> Self {
+        Self {
+            seconds: std::array::from_fn(|_| AtomicU64::new(0)),
+            counts: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    fn increment(&self) {
+        let now = unix_seconds();
+        let index = (now % RECENT_WINDOW_SECONDS) as usize;
+
+        // First event in this second: clear whatever the bucket held a minute ago
+        if self.seconds[index].swap(now, Ordering::Relaxed) != now {
+            self.counts[index].store(0, Ordering::Relaxed);
+        }
+        self.counts[index].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn total(&self) -> u64 {
+        let now = unix_seconds();
+        self.seconds
+            .iter()
+            .zip(&self.counts)
+            .filter(|(second, _)| {
+                now.saturating_sub(second.load(Ordering::Relaxed)) < RECENT_WINDOW_SECONDS
+            })
+            .map(|(_, count)| count.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+fn unix_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Connections from local clients to the proxy listener
+#[derive(Debug, Default)]
+struct LocalConnectionCounters {
+    open: AtomicU64,
+    rejected: AtomicU64,
+    header_timeouts: AtomicU64,
+    idle_closed: AtomicU64,
+}
+
+/// Point-in-time copy of the local listener counters
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct LocalConnectionSnapshot {
+    /// Connections currently open
+    pub open: u64,
+    /// Connections closed on accept because `max_concurrent_local_connections` was reached
+    pub rejected: u64,
+    /// Connections closed for not sending request headers in time
+    pub header_timeouts: u64,
+    /// Keep-alive connections closed after `idle_connection_timeout_seconds`
+    pub idle_closed: u64,
+}
+
+/// Proxied requests being handled, checked before a request is admitted
+#[derive(Debug, Default)]
+struct InFlightCounters {
+    active: AtomicUsize,
+    /// `server.max_in_flight_requests`, 0 means no limit
+    limit: AtomicUsize,
+    rejected: AtomicU64,
+}
+
+/// Point-in-time copy of the in-flight request counters
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct InFlightSnapshot {
+    /// Requests currently being handled
+    pub active: usize,
+    /// `server.max_in_flight_requests`, 0 means no limit
+    pub limit: usize,
+    /// Requests answered with 503 because the limit was reached
+    pub rejected: u64,
+}
+
+impl InFlightSnapshot {
+    /// Whether the next request would be rejected
+    pub fn at_limit(&self) -> bool {
+        self.limit > 0 && self.active >= self.limit
+    }
+}
+
+/// Holds one in-flight slot and releases it when dropped, so a request that
+/// ends any way, including its future being dropped, is no longer counted
+#[derive(Debug)]
+pub struct InFlightRequest {
+    metrics: SharedMetrics,
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        self.metrics
+            .in_flight
+            .active
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Lock-free proxy counters shared by the request path, /status, /metrics and
+/// the dashboard
+#[derive(Debug)]
+pub struct ProxyMetrics {
+    /// Counters per endpoint. Only a config reload takes the write lock, so
+    /// request paths never wait on each other.
+    endpoints: RwLock<EndpointCounterMap>,
+    recent_retries: RecentCounter,
+    /// Requests answered with 503 without trying any endpoint, because all were down
+    shed_requests: AtomicU64,
+    /// Cacheable requests answered from the response cache, and those that
+    /// had to fetch the response upstream
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    /// Events skipped for `/events` clients that fell behind
+    events_dropped: AtomicU64,
+    local_connections: LocalConnectionCounters,
+    in_flight: InFlightCounters,
+}
+
+impl ProxyMetrics {
+    pub fn new(config: &Config) -> Self {
+        let metrics = Self {
+            endpoints: RwLock::new(EndpointCounterMap::default()),
+            recent_retries: RecentCounter::new(),
+            shed_requests: AtomicU64::new(0),
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+            events_dropped: AtomicU64::new(0),
+            local_connections: LocalConnectionCounters::default(),
+            in_flight: InFlightCounters::default(),
+        };
+        metrics.reconfigure(config);
+        metrics
+    }
+
+    /// Match the counters to the endpoints in `config` by id: an endpoint whose
+    /// URL changed keeps its counts, a new one starts at zero and prices follow
+    /// the group. Removed endpoints keep their totals under their last URL,
+    /// until a configured endpoint takes that URL over.
+    pub fn reconfigure(&self, config: &Config) {
+        self.in_flight
+            .limit
+            .store(config.server.max_in_flight_requests, Ordering::Relaxed);
+        let mut endpoints = self.endpoints.write_or_recover();
+        endpoints.index = config.endpoint_index().clone();
+
+        for group in &config.groups {
+            let pricing =
+                Pricing::from_prices(group.input_price_per_mtok, group.output_price_per_mtok);
+            for endpoint in &group.endpoints {
+                let counters = endpoints
+                    .by_id
+                    .entry(EndpointId::new(endpoint.id()))
+                    .or_default();
+                counters.url.clone_from(&endpoint.url);
+                counters.pricing = pricing;
+            }
+        }
+        let EndpointCounterMap { index, by_id } = &mut *endpoints;
+        by_id.retain(|id, counters| {
+            index.get(id).is_some() || index.resolve(&counters.url).is_none()
+        });
+    }
+
+    /// Whether any group has a price configured
+    pub fn pricing_enabled(&self) -> bool {
+        self.endpoints
+            .read_or_recover()
+            .by_id
+            .values()
+            .any(|c| !c.pricing.is_free())
+    }
+
+    /// A proxied request was answered
+    pub fn record_request(&self, endpoint: &str, outcome: RequestOutcome) {
+        if let Some(counters) = self.endpoints.read_or_recover().get(endpoint) {
+            counters.requests.fetch_add(1, Ordering::Relaxed);
+            match outcome {
+                RequestOutcome::Success => {}
+                RequestOutcome::ClientError => {
+                    counters.client_errors.fetch_add(1, Ordering::Relaxed);
+                }
+                RequestOutcome::EndpointError => {
+                    counters.endpoint_errors.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// A request was failed fast while every endpoint was down
+    pub fn record_shed_request(&self) {
+        self.shed_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A cacheable request was answered from the cache, or fetched upstream
+    pub fn record_cache_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.cache_hits
+        } else {
+            &self.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// An `/events` client fell behind and skipped `dropped` events
+    pub fn record_events_dropped(&self, dropped: u64) {
+        self.events_dropped.fetch_add(dropped, Ordering::Relaxed);
+    }
+
+    /// Take an in-flight slot for a proxied request, or count a rejection and
+    /// return None when `server.max_in_flight_requests` are already running
+    pub fn try_admit_request(self: &Arc<Self>) -> Option<InFlightRequest> {
+        let counters = &self.in_flight;
+        let active = counters.active.fetch_add(1, Ordering::Relaxed);
+        let limit = counters.limit.load(Ordering::Relaxed);
+        if limit > 0 && active >= limit {
+            counters.active.fetch_sub(1, Ordering::Relaxed);
+            counters.rejected.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        Some(InFlightRequest {
+            metrics: Arc::clone(self),
+        })
+    }
+
+    /// A request matching a passthrough rule was forwarded to the endpoint
+    pub fn record_ancillary_request(&self, endpoint: &str) {
+        if let Some(counters) = self.endpoints.read_or_recover().get(endpoint) {
+            counters.ancillary_requests.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The endpoint became the current one
+    pub fn record_switch(&self, endpoint: &str) {
+        if let Some(counters) = self.endpoints.read_or_recover().get(endpoint) {
+            counters.switches.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// A failed attempt is about to be retried
+    pub fn record_retry_attempt(&self, endpoint: &str) {
+        if let Some(counters) = self.endpoints.read_or_recover().get(endpoint) {
+            counters.retry_attempts.fetch_add(1, Ordering::Relaxed);
+        }
+        self.recent_retries.increment();
+    }
+
+    /// A request succeeded after at least one retry
+    pub fn record_retry_success(&self, endpoint: &str) {
+        if let Some(counters) = self.endpoints.read_or_recover().get(endpoint) {
+            counters.retry_successes.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// A request failed on every attempt against the endpoint
+    pub fn record_retry_exhausted(&self, endpoint: &str) {
+        if let Some(counters) = self.endpoints.read_or_recover().get(endpoint) {
+            counters.retries_exhausted.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// A response stream was aborted for sending no data within the idle timeout
+    pub fn record_stream_stall(&self, endpoint: &str) {
+        if let Some(counters) = self.endpoints.read_or_recover().get(endpoint) {
+            counters.stream_stalls.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// A request got no response headers within the request timeout
+    pub fn record_request_timeout(&self, endpoint: &str) {
+        if let Some(counters) = self.endpoints.read_or_recover().get(endpoint) {
+            counters.request_timeouts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// A response body hit the total timeout while still streaming
+    pub fn record_body_timeout(&self, endpoint: &str) {
+        if let Some(counters) = self.endpoints.read_or_recover().get(endpoint) {
+            counters.body_timeouts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// A fallback attempt on the endpoint got no response headers in time
+    pub fn record_fallback_timeout(&self, endpoint: &str) {
+        if let Some(counters) = self.endpoints.read_or_recover().get(endpoint) {
+            counters.fallback_timeouts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Tokens reported by a response served by the endpoint
+    pub fn record_usage(&self, endpoint: &str, usage: TokenUsage) {
+        if let Some(counters) = self.endpoints.read_or_recover().get(endpoint) {
+            counters
+                .input_tokens
+                .fetch_add(usage.input_tokens, Ordering::Relaxed);
+            counters
+                .output_tokens
+                .fetch_add(usage.output_tokens, Ordering::Relaxed);
+        }
+    }
+
+    /// A health check ran against the endpoint
+    pub fn record_health_check(&self, endpoint: &str) {
+        if let Some(counters) = self.endpoints.read_or_recover().get(endpoint) {
+            counters.health_checks.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Add counts carried over from a previous process, by endpoint id. Costs
+    /// are not added, they follow from the tokens and the current prices.
+    pub fn restore_counters(&self, snapshots: &BTreeMap<String, CounterSnapshot>) {
+        let endpoints = self.endpoints.read_or_recover();
+        for (id, counters) in &endpoints.by_id {
+            let Some(snapshot) = snapshots.get(id.as_str()) else {
+                continue;
+            };
+            for (counter, value) in [
+                (&counters.requests, snapshot.requests),
+                (&counters.endpoint_errors, snapshot.endpoint_errors),
+                (&counters.client_errors, snapshot.client_errors),
+                (&counters.ancillary_requests, snapshot.ancillary_requests),
+                (&counters.switches, snapshot.switches),
+                (&counters.retry_attempts, snapshot.retry_attempts),
+                (&counters.retry_successes, snapshot.retry_successes),
+                (&counters.retries_exhausted, snapshot.retries_exhausted),
+                (&counters.stream_stalls, snapshot.stream_stalls),
+                (&counters.request_timeouts, snapshot.request_timeouts),
+                (&counters.body_timeouts, snapshot.body_timeouts),
+                (&counters.fallback_timeouts, snapshot.fallback_timeouts),
+                (&counters.input_tokens, snapshot.input_tokens),
+                (&counters.output_tokens, snapshot.output_tokens),
+                (&counters.health_checks, snapshot.health_checks),
+            ] {
+                counter.fetch_add(value, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Counters for every endpoint, sorted by URL
+    pub fn endpoint_snapshots(&self) -> BTreeMap<String, CounterSnapshot> {
+        self.endpoints
+            .read_or_recover()
+            .by_id
+            .values()
+            .map(|counters| (counters.url.clone(), counters.snapshot()))
+            .collect()
+    }
+
+    /// Counters for every endpoint, sorted by endpoint id, which stays the same
+    /// when a reload changes an endpoint's URL
+    pub fn snapshots_by_id(&self) -> BTreeMap<String, CounterSnapshot> {
+        self.endpoints
+            .read_or_recover()
+            .by_id
+            .iter()
+            .map(|(id, counters)| (id.to_string(), counters.snapshot()))
+            .collect()
+    }
+
+    /// Counters summed over all endpoints
+    pub fn totals(&self) -> CounterSnapshot {
+        self.endpoint_snapshots()
+            .into_values()
+            .fold(CounterSnapshot::default(), |total, s| total + s)
+    }
+
+    /// Retries sent across all endpoints during the last minute
+    pub fn retries_last_minute(&self) -> u64 {
+        self.recent_retries.total()
+    }
+
+    /// Requests failed fast while every endpoint was down
+    pub fn shed_requests(&self) -> u64 {
+        self.shed_requests.load(Ordering::Relaxed)
+    }
+
+    /// Cacheable requests answered from the cache and fetched upstream
+    pub fn cache_lookups(&self) -> (u64, u64) {
+        (
+            self.cache_hits.load(Ordering::Relaxed),
+            self.cache_misses.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Events skipped across all `/events` clients that fell behind
+    pub fn events_dropped(&self) -> u64 {
+        self.events_dropped.load(Ordering::Relaxed)
+    }
+
+    /// A local client connection was accepted
+    pub fn local_connection_opened(&self) {
+        self.local_connections.open.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// An accepted local client connection ended
+    pub fn local_connection_closed(&self) {
+        self.local_connections.open.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// A local connection was closed on accept because the limit was reached
+    pub fn record_local_connection_rejected(&self) {
+        self.local_connections
+            .rejected
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A local connection was closed before it sent complete request headers
+    pub fn record_header_timeout(&self) {
+        self.local_connections
+            .header_timeouts
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// An idle local keep-alive connection was closed
+    pub fn record_idle_connection_closed(&self) {
+        self.local_connections
+            .idle_closed
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn in_flight(&self) -> InFlightSnapshot {
+        let counters = &self.in_flight;
+        InFlightSnapshot {
+            active: counters.active.load(Ordering::Relaxed),
+            limit: counters.limit.load(Ordering::Relaxed),
+            rejected: counters.rejected.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn local_connections(&self) -> LocalConnectionSnapshot {
+        let counters = &self.local_connections;
+        LocalConnectionSnapshot {
+            open: counters.open.load(Ordering::Relaxed),
+            rejected: counters.rejected.load(Ordering::Relaxed),
+            header_timeouts: counters.header_timeouts.load(Ordering::Relaxed),
+            idle_closed: counters.idle_closed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Render counters and endpoint health in the Prometheus text exposition format
+pub fn render_prometheus(
+    metrics: &ProxyMetrics,
+    endpoint_status: &HashMap<EndpointId, EndpointStatus>,
+    current_endpoint: &str,
+) -> String {
+    let mut out = String::new();
+    let mut statuses: Vec<&EndpointStatus> = endpoint_status.values().collect();
+    statuses.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+
+    let _ = writeln!(
+        out,
+        "# HELP zephyr_endpoint_up Whether the last health check succeeded"
+    );
+    let _ = writeln!(out, "# TYPE zephyr_endpoint_up gauge");
+    for status in &statuses {
+        let _ = writeln!(
+            out,
+            "zephyr_endpoint_up{{endpoint=\"{}\"}} {}",
+            escape_label(&status.endpoint),
+            u8::from(status.is_available())
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP zephyr_endpoint_latency_ms Latency of the last successful health check"
+    );
+    let _ = writeln!(out, "# TYPE zephyr_endpoint_latency_ms gauge");
+    for status in &statuses {
+        if let Some(latency) = status.latency {
+            let _ = writeln!(
+                out,
+                "zephyr_endpoint_latency_ms{{endpoint=\"{}\"}} {}",
+                escape_label(&status.endpoint),
+                latency
+            );
+        }
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP zephyr_endpoint_current Whether requests are routed to the endpoint"
+    );
+    let _ = writeln!(out, "# TYPE zephyr_endpoint_current gauge");
+    for status in &statuses {
+        let _ = writeln!(
+            out,
+            "zephyr_endpoint_current{{endpoint=\"{}\"}} {}",
+            escape_label(&status.endpoint),
+            u8::from(status.endpoint == current_endpoint)
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP zephyr_endpoint_maintenance Whether the endpoint is inside a maintenance window"
+    );
+    let _ = writeln!(out, "# TYPE zephyr_endpoint_maintenance gauge");
+    for status in &statuses {
+        let _ = writeln!(
+            out,
+            "zephyr_endpoint_maintenance{{endpoint=\"{}\"}} {}",
+            escape_label(&status.endpoint),
+            u8::from(status.maintenance_until.is_some())
+        );
+    }
+
+    let counters = metrics.endpoint_snapshots();
+    let endpoint_counters: [(&str, &str, CounterField); 15] = [
+        (
+            "zephyr_requests_total",
+            "Proxied requests answered on behalf of the endpoint",
+            |s| s.requests,
+        ),
+        (
+            "zephyr_endpoint_errors_total",
+            "Requests that ended in a 408, 429, server error or proxy failure",
+            |s| s.endpoint_errors,
+        ),
+        (
+            "zephyr_client_errors_total",
+            "Requests the upstream rejected with another 4xx, not held against the endpoint",
+            |s| s.client_errors,
+        ),
+        (
+            "zephyr_ancillary_requests_total",
+            "Requests forwarded by a passthrough rule without retries or fallback",
+            |s| s.ancillary_requests,
+        ),
+        (
+            "zephyr_switches_total",
+            "Times the endpoint became the current one",
+            |s| s.switches,
+        ),
+        (
+            "zephyr_retry_attempts_total",
+            "Retries sent after a failed attempt",
+            |s| s.retry_attempts,
+        ),
+        (
+            "zephyr_retry_successes_total",
+            "Requests that succeeded after retrying",
+            |s| s.retry_successes,
+        ),
+        (
+            "zephyr_retry_exhausted_total",
+            "Requests that failed on every retry",
+            |s| s.retries_exhausted,
+        ),
+        (
+            "zephyr_stream_stalls_total",
+            "Response streams aborted after going idle",
+            |s| s.stream_stalls,
+        ),
+        (
+            "zephyr_request_timeouts_total",
+            "Requests that got no response headers within the request timeout",
+            |s| s.request_timeouts,
+        ),
+        (
+            "zephyr_body_timeouts_total",
+            "Response bodies cut off by the total timeout while still streaming",
+            |s| s.body_timeouts,
+        ),
+        (
+            "zephyr_fallback_timeouts_total",
+            "Fallback attempts that got no response headers in time",
+            |s| s.fallback_timeouts,
+        ),
+        (
+            "zephyr_input_tokens_total",
+            "Input tokens reported by upstream responses",
+            |s| s.input_tokens,
+        ),
+        (
+            "zephyr_output_tokens_total",
+            "Output tokens reported by upstream responses",
+            |s| s.output_tokens,
+        ),
+        (
+            "zephyr_health_checks_total",
+            "Health checks run against the endpoint",
+            |s| s.health_checks,
+        ),
+    ];
+    for (name, help, value) in endpoint_counters {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} counter");
+        for (endpoint, snapshot) in &counters {
+            let _ = writeln!(
+                out,
+                "{name}{{endpoint=\"{}\"}} {}",
+                escape_label(endpoint),
+                value(snapshot)
+            );
+        }
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP zephyr_cost_microdollars_total Estimated cost from group pricing, by request kind"
+    );
+    let _ = writeln!(out, "# TYPE zephyr_cost_microdollars_total counter");
+    for (endpoint, snapshot) in &counters {
+        for (kind, value) in [
+            ("requests", snapshot.request_cost_micros),
+            ("health_checks", snapshot.health_check_cost_micros),
+        ] {
+            let _ = writeln!(
+                out,
+                "zephyr_cost_microdollars_total{{endpoint=\"{}\",kind=\"{kind}\"}} {value}",
+                escape_label(endpoint)
+            );
+        }
+    }
+
+    let _ = writeln!(out, "# HELP zephyr_retries_last_minute Retries sent across all endpoints in the last 60 seconds");
+    let _ = writeln!(out, "# TYPE zephyr_retries_last_minute gauge");
+    let _ = writeln!(
+        out,
+        "zephyr_retries_last_minute {}",
+        metrics.retries_last_minute()
+    );
+
+    let _ = writeln!(out, "# HELP zephyr_shed_requests_total Requests failed fast without trying an endpoint because all were down");
+    let _ = writeln!(out, "# TYPE zephyr_shed_requests_total counter");
+    let _ = writeln!(
+        out,
+        "zephyr_shed_requests_total {}",
+        metrics.shed_requests()
+    );
+
+    let (cache_hits, cache_misses) = metrics.cache_lookups();
+    let _ = writeln!(
+        out,
+        "# HELP zephyr_cache_hits_total Cacheable requests answered from the response cache"
+    );
+    let _ = writeln!(out, "# TYPE zephyr_cache_hits_total counter");
+    let _ = writeln!(out, "zephyr_cache_hits_total {cache_hits}");
+    let _ = writeln!(
+        out,
+        "# HELP zephyr_cache_misses_total Cacheable requests whose response was fetched upstream"
+    );
+    let _ = writeln!(out, "# TYPE zephyr_cache_misses_total counter");
+    let _ = writeln!(out, "zephyr_cache_misses_total {cache_misses}");
+
+    let _ = writeln!(
+        out,
+        "# HELP zephyr_events_dropped_total Events skipped for /events clients that fell behind"
+    );
+    let _ = writeln!(out, "# TYPE zephyr_events_dropped_total counter");
+    let _ = writeln!(
+        out,
+        "zephyr_events_dropped_total {}",
+        metrics.events_dropped()
+    );
+
+    let in_flight = metrics.in_flight();
+    let _ = writeln!(
+        out,
+        "# HELP zephyr_in_flight_requests Proxied requests currently being handled"
+    );
+    let _ = writeln!(out, "# TYPE zephyr_in_flight_requests gauge");
+    let _ = writeln!(out, "zephyr_in_flight_requests {}", in_flight.active);
+    let _ = writeln!(out, "# HELP zephyr_in_flight_rejected_total Requests answered with 503 because max_in_flight_requests was reached");
+    let _ = writeln!(out, "# TYPE zephyr_in_flight_rejected_total counter");
+    let _ = writeln!(
+        out,
+        "zephyr_in_flight_rejected_total {}",
+        in_flight.rejected
+    );
+
+    let local = metrics.local_connections();
+    let _ = writeln!(
+        out,
+        "# HELP zephyr_local_connections Client connections currently open to the proxy listener"
+    );
+    let _ = writeln!(out, "# TYPE zephyr_local_connections gauge");
+    let _ = writeln!(out, "zephyr_local_connections {}", local.open);
+    for (name, help, value) in [
+        (
+            "zephyr_local_connections_rejected_total",
+            "Client connections closed on accept because max_concurrent_local_connections was reached",
+            local.rejected,
+        ),
+        (
+            "zephyr_local_header_timeouts_total",
+            "Client connections closed for not sending request headers within the header read timeout",
+            local.header_timeouts,
+        ),
+        (
+            "zephyr_local_idle_connections_closed_total",
+            "Idle client keep-alive connections closed by the proxy",
+            local.idle_closed,
+        ),
+    ] {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} counter");
+        let _ = writeln!(out, "{name} {value}");
+    }
+
+    out
+}
+
+/// Selects one counter out of a snapshot
+type CounterField = fn(&CounterSnapshot) -> u64;
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
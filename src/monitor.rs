@@ -0,0 +1,27 @@
+use crate::config::Config;
+use crate::proxy::RunMode;
+use crate::server::ProxyServer;
+
+/// Options for the `monitor` subcommand
+#[derive(clap::Args, Debug)]
+pub struct MonitorArgs {
+    /// Show the dashboard instead of console logs
+    #[arg(long)]
+    pub dashboard: bool,
+}
+
+/// Run the health checks as a live latency monitor, without binding a port
+/// or forwarding any request, until the dashboard quits or SIGINT/SIGTERM
+pub async fn run_monitor(config: Config, args: &MonitorArgs) -> anyhow::Result<()> {
+    let mode = if args.dashboard {
+        RunMode::Dashboard
+    } else {
+        RunMode::Normal
+    };
+    ProxyServer::builder(config)
+        .mode(mode)
+        .monitor_only()
+        .build()
+        .run()
+        .await
+}
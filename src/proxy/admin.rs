@@ -0,0 +1,1276 @@
+use crate::build_info;
+use crate::config_view::RedactedConfig;
+use crate::connection_tracker::{EventSender, SharedConnectionTracker};
+use crate::endpoint::EndpointId;
+use crate::endpoint_table::{self, EndpointRow};
+use crate::events::{ActiveConnection, CompletedConnection, ProxyEvent, SwitchRecord};
+use crate::group_health;
+use crate::health_orchestrator::{failed_endpoints, HealthOrchestratorHandle, ResetSource};
+use crate::lock::LockExt;
+use crate::maintenance;
+use crate::selection::{self, Exclusion, SwitchDecision};
+use crate::state_handoff;
+use chrono::Timelike;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use std::collections::{BTreeMap, HashMap};
+
+use super::fallback::listener_endpoint;
+use super::{ProxyState, RunMode, SharedState};
+
+/// Version of the `/diagnostics` body, bumped when its shape changes. The
+/// body without `schema_version`, with the raw `connection_durations`, is
+/// version 1.
+const DIAGNOSTICS_SCHEMA_VERSION: u32 = 2;
+
+pub(super) async fn diagnostics_handler(
+    req: Request<Body>,
+    connection_tracker: SharedConnectionTracker,
+) -> anyhow::Result<Response<Body>> {
+    let verbose = match query_param(&req, "verbose").as_deref() {
+        None | Some("false") => false,
+        Some("true") => true,
+        Some(value) => return invalid_request(&format!("Invalid verbose flag: {value}")),
+    };
+    let response_json = diagnostics_json(&connection_tracker, verbose);
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(response_json.to_string()))?)
+}
+
+/// Body of `/diagnostics`, with every active connection listed when `verbose`
+pub(crate) fn diagnostics_json(
+    connection_tracker: &SharedConnectionTracker,
+    verbose: bool,
+) -> serde_json::Value {
+    let (diagnostics, longest_connection_endpoint, mut connections) = {
+        let tracker_guard = connection_tracker.lock_or_recover();
+        let connections: Vec<ActiveConnection> = if verbose {
+            tracker_guard
+                .get_active_connections()
+                .values()
+                .cloned()
+                .collect()
+        } else {
+            Vec::new()
+        };
+        (
+            tracker_guard.get_connection_diagnostics(),
+            tracker_guard
+                .oldest_connection()
+                .map(|conn| conn.endpoint.clone()),
+            connections,
+        )
+    };
+
+    let mut body = serde_json::json!({
+        "schema_version": DIAGNOSTICS_SCHEMA_VERSION,
+        "connection_diagnostics": {
+            "total_active": diagnostics.total_active,
+            "endpoint_distribution": diagnostics.endpoint_counts,
+            "connection_durations_seconds": diagnostics.durations,
+            "endpoint_durations_seconds": diagnostics.endpoint_durations,
+            "completed_count": diagnostics.completed_count,
+            "peak_concurrent": diagnostics.peak_concurrent,
+            "longest_connection_endpoint": longest_connection_endpoint,
+            "phase_averages_ms": diagnostics.phase_averages,
+            "request_timings_us": diagnostics.request_timings,
+            "proxy_overhead_p90_us": diagnostics.overhead_p90_us,
+        }
+    });
+    if verbose {
+        // Oldest first, the likeliest to be stuck
+        connections.sort_by_key(|conn| conn.start_time);
+        let now = chrono::Utc::now();
+        body["connection_diagnostics"]["connections"] = connections
+            .iter()
+            .map(|conn| {
+                serde_json::json!({
+                    "id": conn.id,
+                    "endpoint": conn.endpoint,
+                    "duration_seconds": (now - conn.start_time).num_seconds().max(0),
+                })
+            })
+            .collect();
+    }
+    body
+}
+
+/// Reject admin requests without the configured bearer token.
+/// Returns `None` when the request may proceed.
+pub(super) fn check_admin_auth(req: &Request<Body>, state: &SharedState) -> Option<Response<Body>> {
+    let expected = state.lock_or_recover().config.get_admin_token()?;
+
+    let provided = req
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    if constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+        return None;
+    }
+
+    let error_json = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": "authentication_error",
+            "message": "Admin token required",
+        }
+    });
+    Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .header("content-type", "application/json")
+        .body(Body::from(error_json.to_string()))
+        .ok()
+}
+
+/// Compare secrets without leaking the position of the first mismatch
+pub(super) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Value of a query string parameter, percent-decoded
+pub(super) fn query_param(req: &Request<Body>, name: &str) -> Option<String> {
+    url::form_urlencoded::parse(req.uri().query()?.as_bytes())
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// `endpoint` spelled as in the config, so a filter matches however the URL
+/// is written; unknown endpoints are left as they are
+fn configured_url(state: &SharedState, endpoint: String) -> String {
+    state
+        .lock_or_recover()
+        .config
+        .endpoint_ref(&endpoint)
+        .map_or(endpoint, |configured| configured.url.clone())
+}
+
+pub(super) async fn connections_handler(
+    req: Request<Body>,
+    state: SharedState,
+    connection_tracker: SharedConnectionTracker,
+) -> anyhow::Result<Response<Body>> {
+    if let Some(rejection) = check_admin_auth(&req, &state) {
+        return Ok(rejection);
+    }
+
+    let endpoint_filter = query_param(&req, "endpoint").map(|e| configured_url(&state, e));
+
+    // Snapshot under the lock, serialize after releasing it
+    let mut connections: Vec<ActiveConnection> = connection_tracker
+        .lock_or_recover()
+        .get_active_connections()
+        .values()
+        .filter(|conn| {
+            endpoint_filter
+                .as_ref()
+                .is_none_or(|endpoint| &conn.endpoint == endpoint)
+        })
+        .cloned()
+        .collect();
+    connections.sort_by_key(|conn| std::cmp::Reverse(conn.start_time)); // Newest first
+
+    let mut by_status: HashMap<String, usize> = HashMap::new();
+    let mut by_endpoint: HashMap<String, usize> = HashMap::new();
+    for conn in &connections {
+        *by_status.entry(format!("{:?}", conn.status)).or_insert(0) += 1;
+        *by_endpoint.entry(conn.endpoint.clone()).or_insert(0) += 1;
+    }
+
+    let entries: Vec<serde_json::Value> = connections
+        .iter()
+        .map(|conn| {
+            serde_json::json!({
+                "id": conn.id,
+                "endpoint": conn.endpoint,
+                "status": conn.status,
+                "method": conn.request_info.as_ref().map(|info| &info.method),
+                "path": conn.request_info.as_ref().map(|info| &info.path),
+                "start_time": conn.start_time,
+                "duration_ms": conn.duration(),
+                "bytes_received": conn.bytes_received,
+            })
+        })
+        .collect();
+
+    let response_json = serde_json::json!({
+        "count": entries.len(),
+        "by_status": by_status,
+        "by_endpoint": by_endpoint,
+        "connections": entries,
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&response_json)?))?)
+}
+
+/// `GET /connections/recent`: the last completed requests, newest first
+pub(super) async fn recent_connections_handler(
+    req: Request<Body>,
+    state: SharedState,
+    connection_tracker: SharedConnectionTracker,
+) -> anyhow::Result<Response<Body>> {
+    if let Some(rejection) = check_admin_auth(&req, &state) {
+        return Ok(rejection);
+    }
+
+    let endpoint_filter = query_param(&req, "endpoint").map(|e| configured_url(&state, e));
+
+    // Snapshot under the lock, serialize after releasing it
+    let (capacity, requests) = {
+        let tracker = connection_tracker.lock_or_recover();
+        let requests: Vec<CompletedConnection> = tracker
+            .recent_connections()
+            .filter(|conn| {
+                endpoint_filter
+                    .as_ref()
+                    .is_none_or(|endpoint| &conn.endpoint == endpoint)
+            })
+            .cloned()
+            .collect();
+        (tracker.recent_capacity(), requests)
+    };
+
+    let response_json = serde_json::json!({
+        "count": requests.len(),
+        "capacity": capacity,
+        "requests": requests,
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&response_json)?))?)
+}
+
+pub(super) async fn status_handler(
+    req: Request<Body>,
+    state: SharedState,
+    connection_tracker: Option<SharedConnectionTracker>,
+) -> anyhow::Result<Response<Body>> {
+    let query = match StatusQuery::from_request(&req) {
+        Ok(query) => query,
+        Err(message) => return invalid_request(&message),
+    };
+    let status_info = status_json(&state, connection_tracker.as_ref(), &query);
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&status_info)?))?)
+}
+
+/// Version of the `/status` body, bumped when its shape changes. The body
+/// without `schema_version`, still served by `?format=legacy`, is version 1.
+const STATUS_SCHEMA_VERSION: u32 = 2;
+
+/// Sections `?fields=` selects from, each a group of top-level `/status` keys
+const STATUS_SECTIONS: &[&str] = &["runtime", "endpoints", "connections", "counters", "config"];
+
+/// Section a top-level `/status` key belongs to, `None` for keys always included
+fn status_section(key: &str) -> Option<&'static str> {
+    match key {
+        "schema_version" | "timestamp" => None,
+        "endpoints" | "groups" => Some("endpoints"),
+        "total_active_connections" | "endpoint_connections" => Some("connections"),
+        "request_counters" | "cost" => Some("counters"),
+        "config" | "auto_switch_endpoints" | "listeners" => Some("config"),
+        _ => Some("runtime"),
+    }
+}
+
+/// What `/status` returns, from its query string
+#[derive(Debug, Default)]
+pub(crate) struct StatusQuery {
+    /// Sections to include, every one when `None`
+    fields: Option<Vec<String>>,
+    /// Only list endpoints of this group
+    group: Option<String>,
+    /// Only list endpoints that are (or are not) available
+    available: Option<bool>,
+    /// The version 1 body, endpoints keyed by id, without filters
+    legacy: bool,
+}
+
+impl StatusQuery {
+    fn from_request(req: &Request<Body>) -> Result<Self, String> {
+        let fields = query_param(req, "fields")
+            .map(|fields| {
+                fields
+                    .split(',')
+                    .map(|field| field.trim().to_string())
+                    .filter(|field| !field.is_empty())
+                    .map(|field| {
+                        if STATUS_SECTIONS.contains(&field.as_str()) {
+                            Ok(field)
+                        } else {
+                            Err(format!(
+                                "Unknown status field: {field} (expected {})",
+                                STATUS_SECTIONS.join(", ")
+                            ))
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        let available = query_param(req, "available")
+            .map(|value| match value.as_str() {
+                "true" => Ok(true),
+                "false" => Ok(false),
+                _ => Err(format!("Invalid available filter: {value}")),
+            })
+            .transpose()?;
+        let legacy = match query_param(req, "format").as_deref() {
+            None | Some("v2") => false,
+            Some("legacy") => true,
+            Some(format) => return Err(format!("Unknown status format: {format}")),
+        };
+
+        Ok(Self {
+            fields,
+            group: query_param(req, "group"),
+            available,
+            legacy,
+        })
+    }
+
+    fn includes(&self, key: &str) -> bool {
+        match (status_section(key), &self.fields) {
+            (Some(section), Some(fields)) => fields.iter().any(|field| field == section),
+            _ => true,
+        }
+    }
+}
+
+/// Failover tiers in the order Auto mode prefers them, with their groups and
+/// healthy endpoints; the tier of the current endpoint is `active`
+fn tiers_json(state: &ProxyState) -> Vec<serde_json::Value> {
+    let mut tiers: BTreeMap<u8, Vec<&str>> = BTreeMap::new();
+    for group in &state.config.groups {
+        tiers.entry(group.priority).or_default().push(&group.name);
+    }
+    let current_tier = state.tier_of(state.current_url());
+    tiers
+        .into_iter()
+        .map(|(priority, groups)| {
+            let healthy = state
+                .config
+                .endpoint_index()
+                .iter()
+                .filter(|endpoint| groups.contains(&endpoint.group.as_str()))
+                .filter(|endpoint| {
+                    state
+                        .endpoint_status
+                        .get(&endpoint.id)
+                        .is_some_and(|status| {
+                            status.is_available() && status.maintenance_until.is_none()
+                        })
+                })
+                .count();
+            serde_json::json!({
+                "priority": priority,
+                "groups": groups,
+                "healthy_endpoints": healthy,
+                "active": priority == current_tier,
+            })
+        })
+        .collect()
+}
+
+/// Body of `/status`
+pub(crate) fn status_json(
+    state: &SharedState,
+    connection_tracker: Option<&SharedConnectionTracker>,
+    query: &StatusQuery,
+) -> serde_json::Value {
+    let state_guard = state.lock_or_recover();
+
+    // Get connection info from tracker if available, otherwise use old system for backwards compatibility
+    let (total_active_connections, endpoint_distribution, endpoint_connections) =
+        if let Some(tracker) = connection_tracker {
+            let tracker_guard = tracker.lock_or_recover();
+            (
+                tracker_guard.get_active_count(),
+                tracker_guard.get_endpoint_distribution().clone(),
+                tracker_guard.connections_by_url(),
+            )
+        } else {
+            (0, HashMap::new(), BTreeMap::new())
+        };
+
+    let mut auth_rejected: Vec<&str> = state_guard
+        .auth_rejected
+        .keys()
+        .filter_map(|id| state_guard.config.endpoint_index().get(id))
+        .map(|endpoint| endpoint.url.as_str())
+        .collect();
+    auth_rejected.sort();
+    let endpoints = if query.legacy {
+        serde_json::Value::Object(endpoints_json(&state_guard))
+    } else {
+        serde_json::Value::Array(endpoints_array(&state_guard, query))
+    };
+
+    let mut groups = group_health::summarize(
+        state_guard.config.endpoint_index(),
+        &state_guard.endpoint_status,
+        &endpoint_distribution,
+        &state_guard.metrics.snapshots_by_id(),
+    );
+    if let Some(name) = &query.group {
+        groups.retain(|group| &group.name == name);
+    }
+
+    let counter_totals = state_guard.metrics.totals();
+    let mut status = serde_json::json!({
+        "schema_version": STATUS_SCHEMA_VERSION,
+        "current_endpoint": state_guard.current_url(),
+        "current_endpoint_reason": state_guard.current_endpoint_reason,
+        "current_endpoint_since": state_guard.current_endpoint_since,
+        "grace_until": state_guard
+            .grace_until
+            .filter(|_| state_guard.in_switch_grace(chrono::Utc::now())),
+        "switching_frozen": state_guard.switching_frozen,
+        "standby_endpoint": state_guard.standby_endpoint,
+        "current_tier": state_guard.tier_of(state_guard.current_url()),
+        "tiers": tiers_json(&state_guard),
+        "last_health_cycle": state_guard.last_cycle_summary,
+        "failing_fast": state_guard.fail_fast_retry_after().is_some(),
+        "active_alerts": state_guard.active_alerts,
+        "health_check_mode": state_guard.config.health_check_mode_name(),
+        "health_interval": state_guard.health_interval.map(|(interval, decision)| {
+            serde_json::json!({
+                "seconds": interval.as_secs(),
+                "decision": decision,
+            })
+        }),
+        "total_active_connections": total_active_connections,
+        "endpoint_connections": endpoint_connections,
+        "endpoints": endpoints,
+        "groups": groups,
+        "request_counters": {
+            "total": counter_totals,
+            "retries_last_minute": state_guard.metrics.retries_last_minute(),
+            "shed_requests": state_guard.metrics.shed_requests(),
+            "cache": {
+                "hits": state_guard.metrics.cache_lookups().0,
+                "misses": state_guard.metrics.cache_lookups().1,
+            },
+            "event_stream": {
+                "clients": state_guard.event_stream.receiver_count(),
+                "dropped": state_guard.metrics.events_dropped(),
+            },
+            "in_flight": state_guard.metrics.in_flight(),
+            "local_connections": state_guard.metrics.local_connections(),
+            "endpoints": state_guard.metrics.endpoint_snapshots(),
+        },
+        "cost": {
+            "currency": state_guard.config.ui.currency,
+            "pricing_enabled": state_guard.metrics.pricing_enabled(),
+            "requests_micros": counter_totals.request_cost_micros,
+            "health_checks_micros": counter_totals.health_check_cost_micros,
+            "total_micros": counter_totals.request_cost_micros + counter_totals.health_check_cost_micros,
+        },
+        "timestamp": chrono::Utc::now(),
+        "config": {
+            "port": state_guard.config.server.port,
+            "switch_threshold_ms": state_guard.config.server.switch_threshold_ms,
+            "health_check_interval_seconds": state_guard.config.health_check.interval_seconds,
+            "fallback_policy": state_guard.config.retry.fallback,
+            "retry_on_status": state_guard.config.retry.retry_on_status,
+            "fallback_on_status": state_guard.config.retry.fallback_on_status,
+            "auto_switch_scope": state_guard.config.server.auto_switch_scope,
+            "prefer_hourly_profile": state_guard.config.server.prefer_hourly_profile,
+            "maintenance_timezone": state_guard.config.server.maintenance_timezone,
+            "passthrough_rules": state_guard.config.routing.passthrough,
+            "cache": state_guard.config.cache,
+        },
+        "auto_switch_endpoints": state_guard.auto_switch_endpoints(),
+        "listeners": listeners_json(&state_guard),
+        "tag_filter": tag_filter_json(&state_guard),
+        "draining": draining_json(&state_guard, &endpoint_distribution),
+        "auth_rejected_endpoints": auth_rejected,
+        "groups_without_token": groups_without_token_json(&state_guard),
+        "token_checks": state_guard.token_checks.iter().collect::<BTreeMap<_, _>>(),
+    });
+
+    if let Some(fields) = status.as_object_mut() {
+        if query.legacy {
+            fields.remove("schema_version");
+        } else {
+            fields.retain(|key, _| query.includes(key));
+        }
+    }
+    status
+}
+
+/// Endpoint status sorted by group then name, each with its id, name, group and
+/// URL (`endpoint`), filtered by the query's `group` and `available`
+fn endpoints_array(state: &ProxyState, query: &StatusQuery) -> Vec<serde_json::Value> {
+    let mut entries: Vec<(&str, &str, serde_json::Value)> = state
+        .config
+        .groups
+        .iter()
+        .filter(|group| query.group.as_ref().is_none_or(|name| &group.name == name))
+        .flat_map(|group| {
+            group
+                .endpoints
+                .iter()
+                .map(move |endpoint| (group, endpoint))
+        })
+        .filter_map(|(group, endpoint)| {
+            let status = state.status_of(&endpoint.url)?;
+            if query
+                .available
+                .is_some_and(|available| status.is_available() != available)
+            {
+                return None;
+            }
+            let mut value = serde_json::to_value(status).unwrap_or_default();
+            if let Some(fields) = value.as_object_mut() {
+                fields.insert("id".to_string(), serde_json::json!(endpoint.id()));
+                fields.insert(
+                    "available".to_string(),
+                    serde_json::json!(status.is_available()),
+                );
+                fields.insert("name".to_string(), serde_json::json!(endpoint.name));
+                fields.insert("group".to_string(), serde_json::json!(group.name));
+                fields.insert(
+                    "maintenance".to_string(),
+                    serde_json::json!(endpoint.maintenance),
+                );
+                fields.insert(
+                    "next_maintenance_at".to_string(),
+                    serde_json::json!(maintenance::next_start(
+                        &endpoint.maintenance,
+                        state.config.maintenance_timezone(),
+                        chrono::Utc::now(),
+                    )),
+                );
+            }
+            Some((group.name.as_str(), endpoint.name.as_str(), value))
+        })
+        .collect();
+    entries.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+    entries.into_iter().map(|(_, _, value)| value).collect()
+}
+
+/// Endpoint status keyed by id, each carrying both its id and its URL, as in
+/// the legacy `/status` body
+pub(super) fn endpoints_json(state: &ProxyState) -> serde_json::Map<String, serde_json::Value> {
+    state
+        .endpoint_status
+        .iter()
+        .map(|(id, status)| {
+            let mut value = serde_json::to_value(status).unwrap_or_default();
+            if let Some(fields) = value.as_object_mut() {
+                fields.insert("id".to_string(), serde_json::json!(id));
+                fields.insert(
+                    "available".to_string(),
+                    serde_json::json!(status.is_available()),
+                );
+            }
+            (id.to_string(), value)
+        })
+        .collect()
+}
+
+/// Endpoints draining after a switch, with their remaining connections, by URL
+pub(super) fn draining_json(
+    state: &ProxyState,
+    endpoint_connections: &HashMap<EndpointId, u32>,
+) -> Vec<serde_json::Value> {
+    let mut draining: Vec<_> = state.draining.iter().collect();
+    draining.sort_by(|(_, a), (_, b)| a.url.cmp(&b.url));
+    draining
+        .into_iter()
+        .map(|(id, drain)| {
+            serde_json::json!({
+                "endpoint": drain.url,
+                "active_connections": endpoint_connections.get(id).copied().unwrap_or(0),
+                "elapsed_ms": drain.started.elapsed().as_millis() as u64,
+            })
+        })
+        .collect()
+}
+
+/// Groups kept out of rotation until their token env var is set
+pub(super) fn groups_without_token_json(state: &ProxyState) -> Vec<serde_json::Value> {
+    state
+        .config
+        .groups_without_token()
+        .into_iter()
+        .map(|group| {
+            let endpoints: Vec<&str> = group
+                .endpoints
+                .iter()
+                .map(|endpoint| endpoint.url.as_str())
+                .collect();
+            serde_json::json!({
+                "group": group.name,
+                "auth_token_env": group.auth_token_env,
+                "endpoints": endpoints,
+            })
+        })
+        .collect()
+}
+
+/// Every local listener, with the group it is limited to and the endpoint
+/// its requests go to now
+fn listeners_json(state: &ProxyState) -> Vec<serde_json::Value> {
+    state
+        .config
+        .listeners()
+        .into_iter()
+        .map(|listener| {
+            let endpoint = listener_endpoint(state, listener.group.as_deref());
+            serde_json::json!({
+                "url": listener.url(),
+                "port": listener.port,
+                "bind_address": listener.bind_address,
+                "group": listener.group,
+                "endpoint": endpoint,
+            })
+        })
+        .collect()
+}
+
+/// Active tag filter and the endpoints it leaves out
+pub(super) fn tag_filter_json(state: &ProxyState) -> serde_json::Value {
+    let excluded: Vec<String> = state
+        .config
+        .get_all_endpoints()
+        .into_iter()
+        .map(|(_, endpoint, _)| endpoint.url)
+        .filter(|endpoint| !state.matches_tag_filter(endpoint))
+        .collect();
+    serde_json::json!({
+        "tags": state.tag_filter,
+        "excluded_endpoints": excluded,
+    })
+}
+
+#[derive(serde::Deserialize)]
+pub(super) struct TagFilterRequest {
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// `POST /admin/filter {"tags": [...]}` sets the tag filter, an empty list clears it
+pub(super) async fn filter_handler(
+    req: Request<Body>,
+    state: SharedState,
+    event_sender: EventSender,
+    mode: RunMode,
+) -> anyhow::Result<Response<Body>> {
+    if let Some(rejection) = check_admin_auth(&req, &state) {
+        return Ok(rejection);
+    }
+
+    if req.method() != Method::POST {
+        return Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "POST")
+            .body(Body::empty())?);
+    }
+
+    let body = hyper::body::to_bytes(req.into_body()).await?;
+    let request: TagFilterRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return invalid_request(&format!("Invalid tag filter: {e}")),
+    };
+
+    let (response_json, record) = {
+        let mut state_guard = state.lock_or_recover();
+        let known_tags = state_guard.config.all_tags();
+        let unknown: Vec<&str> = request
+            .tags
+            .iter()
+            .filter(|tag| !known_tags.contains(tag))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            return invalid_request(&format!("Unknown tags: {}", unknown.join(", ")));
+        }
+
+        let record = state_guard.set_tag_filter(request.tags, mode.is_silent());
+        let response_json = serde_json::json!({
+            "current_endpoint": state_guard.current_url(),
+            "tag_filter": tag_filter_json(&state_guard),
+        });
+        (response_json, record)
+    };
+
+    if let Some(record) = record {
+        let _ = event_sender.send(ProxyEvent::EndpointSwitch {
+            from: record.from,
+            to: record.to,
+            from_latency: record.from_latency,
+            to_latency: record.to_latency,
+            reason: record.reason,
+        });
+    }
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&response_json)?))?)
+}
+
+/// Body of `POST /admin/freeze`
+#[derive(serde::Deserialize)]
+pub(super) struct FreezeRequest {
+    frozen: bool,
+}
+
+pub(super) async fn freeze_handler(
+    req: Request<Body>,
+    state: SharedState,
+    event_sender: EventSender,
+) -> anyhow::Result<Response<Body>> {
+    if let Some(rejection) = check_admin_auth(&req, &state) {
+        return Ok(rejection);
+    }
+
+    if req.method() != Method::POST {
+        return Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "POST")
+            .body(Body::empty())?);
+    }
+
+    let body = hyper::body::to_bytes(req.into_body()).await?;
+    let request: FreezeRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return invalid_request(&format!("Invalid freeze request: {e}")),
+    };
+
+    let response_json = {
+        let mut state_guard = state.lock_or_recover();
+        state_guard.switching_frozen = request.frozen;
+        serde_json::json!({
+            "current_endpoint": state_guard.current_url(),
+            "switching_frozen": state_guard.switching_frozen,
+        })
+    };
+    let _ = event_sender.send(ProxyEvent::SwitchingFrozen {
+        frozen: request.frozen,
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&response_json)?))?)
+}
+
+pub(super) async fn refresh_handler(
+    req: Request<Body>,
+    state: SharedState,
+    orchestrator: HealthOrchestratorHandle,
+) -> anyhow::Result<Response<Body>> {
+    if let Some(rejection) = check_admin_auth(&req, &state) {
+        return Ok(rejection);
+    }
+
+    if req.method() != Method::POST {
+        return Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "POST")
+            .body(Body::empty())?);
+    }
+
+    let scope = url::form_urlencoded::parse(req.uri().query().unwrap_or("").as_bytes())
+        .find(|(key, _)| key == "scope")
+        .map(|(_, value)| value.into_owned())
+        .unwrap_or_else(|| "all".to_string());
+    let response_json = match scope.as_str() {
+        "all" => {
+            orchestrator.refresh();
+            serde_json::json!({ "scope": scope })
+        }
+        "failed" => {
+            orchestrator.refresh_failed();
+            serde_json::json!({
+                "scope": scope,
+                "endpoints": failed_endpoints(&state),
+            })
+        }
+        _ => return invalid_request(&format!("Unknown refresh scope: {scope}")),
+    };
+
+    Ok(Response::builder()
+        .status(StatusCode::ACCEPTED)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&response_json)?))?)
+}
+
+/// `POST /admin/endpoints/{id}/reset`: clear the endpoint's latency history,
+/// last error and token rejection, then check it right away
+pub(super) async fn endpoint_reset_handler(
+    req: Request<Body>,
+    state: SharedState,
+    orchestrator: HealthOrchestratorHandle,
+) -> anyhow::Result<Response<Body>> {
+    if let Some(rejection) = check_admin_auth(&req, &state) {
+        return Ok(rejection);
+    }
+
+    let Some(raw_id) = req
+        .uri()
+        .path()
+        .strip_prefix("/admin/endpoints/")
+        .and_then(|rest| rest.strip_suffix("/reset"))
+    else {
+        return Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())?);
+    };
+    if req.method() != Method::POST {
+        return Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "POST")
+            .body(Body::empty())?);
+    }
+
+    // A lone path segment parses as one key, percent-decoded
+    let id = url::form_urlencoded::parse(raw_id.as_bytes())
+        .next()
+        .map(|(id, _)| id.into_owned())
+        .unwrap_or_default();
+    let endpoint = state
+        .lock_or_recover()
+        .config
+        .endpoint_index()
+        .get(&EndpointId::new(&id))
+        .cloned();
+    let Some(endpoint) = endpoint else {
+        let error_json = serde_json::json!({
+            "type": "error",
+            "error": {
+                "type": "not_found_error",
+                "message": format!("No endpoint with id '{id}'"),
+            }
+        });
+        return Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .header("content-type", "application/json")
+            .body(Body::from(error_json.to_string()))?);
+    };
+
+    orchestrator.reset_endpoint(endpoint.url.clone(), ResetSource::AdminApi);
+    let response_json = serde_json::json!({
+        "id": endpoint.id,
+        "endpoint": endpoint.url,
+        "name": endpoint.name,
+    });
+    Ok(Response::builder()
+        .status(StatusCode::ACCEPTED)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&response_json)?))?)
+}
+
+/// 400 response in the Anthropic API error format
+pub(super) fn invalid_request(message: &str) -> anyhow::Result<Response<Body>> {
+    let error_json = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": "invalid_request_error",
+            "message": message,
+        }
+    });
+    Ok(Response::builder()
+        .status(StatusCode::BAD_REQUEST)
+        .header("content-type", "application/json")
+        .body(Body::from(error_json.to_string()))?)
+}
+
+/// `POST /admin/prepare-restart` writes the state handoff snapshot for the
+/// process replacing this one and keeps serving
+pub(super) async fn prepare_restart_handler(
+    req: Request<Body>,
+    state: SharedState,
+) -> anyhow::Result<Response<Body>> {
+    if let Some(rejection) = check_admin_auth(&req, &state) {
+        return Ok(rejection);
+    }
+
+    if req.method() != Method::POST {
+        return Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "POST")
+            .body(Body::empty())?);
+    }
+
+    let path = state.lock_or_recover().state_handoff.clone();
+    let result = match &path {
+        Some(path) => state_handoff::save(&state, path).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "api_error",
+                format!("{e:#}"),
+            )
+        }),
+        None => Err((
+            StatusCode::CONFLICT,
+            "invalid_request_error",
+            "Start the proxy with --state-handoff <path> to prepare a restart".to_string(),
+        )),
+    };
+
+    let (status, response_json) = match result {
+        Ok(report) => (
+            StatusCode::OK,
+            serde_json::json!({
+                "path": path,
+                "sections": report.sections,
+            }),
+        ),
+        Err((status, error_type, message)) => (
+            status,
+            serde_json::json!({
+                "type": "error",
+                "error": {
+                    "type": error_type,
+                    "message": message,
+                }
+            }),
+        ),
+    };
+
+    Ok(Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&response_json)?))?)
+}
+
+/// `GET /admin/simulate-selection`: what Auto mode would decide from the
+/// current health results, computed by the orchestrator's own decision
+/// functions without switching
+pub(super) async fn simulate_selection_handler(
+    req: Request<Body>,
+    state: SharedState,
+) -> anyhow::Result<Response<Body>> {
+    if let Some(rejection) = check_admin_auth(&req, &state) {
+        return Ok(rejection);
+    }
+
+    if req.method() != Method::GET {
+        return Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "GET")
+            .body(Body::empty())?);
+    }
+
+    let response_json = simulate_selection_json(&state.lock_or_recover());
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&response_json)?))?)
+}
+
+fn simulate_selection_json(state: &ProxyState) -> serde_json::Value {
+    let now = chrono::Local::now();
+    let blocked_by = selection::switching_blocked(state);
+
+    // The race goes to the first eligible endpoint to answer, which with the
+    // last results is the fastest one
+    let mut by_latency: Vec<_> = state
+        .config
+        .endpoint_index()
+        .iter()
+        .filter_map(|endpoint| state.endpoint_status.get(&endpoint.id))
+        .filter(|status| status.is_available())
+        .collect();
+    by_latency.sort_by_key(|status| status.latency.unwrap_or(u64::MAX));
+    let race_winner = by_latency
+        .into_iter()
+        .find(|status| selection::race_eligibility(status, state).is_ok());
+    let race_decision = race_winner.map(|status| selection::switch_decision(status, state, now));
+
+    let cycle_best = selection::best_of_cycle(state);
+    let cycle_decision = cycle_best.map(|status| selection::switch_decision(status, state, now));
+
+    // The race switches during the cycle, the end of the cycle only when the
+    // race left the current endpoint outside the active tier
+    let decision = match (&race_decision, &cycle_decision) {
+        _ if blocked_by.is_some() => None,
+        (Some(decision @ Ok(_)), _) => Some(("first_available", race_winner, decision)),
+        (_, Some(decision @ Ok(_))) => Some(("best_of_cycle", cycle_best, decision)),
+        _ => None,
+    };
+
+    let endpoints: Vec<serde_json::Value> = state
+        .config
+        .endpoint_index()
+        .iter()
+        .map(|endpoint| {
+            let status = state.endpoint_status.get(&endpoint.id);
+            let excluded = match status {
+                None => Some(Exclusion::Unavailable),
+                Some(status) => selection::race_eligibility(status, state)
+                    .and_then(|()| selection::switch_decision(status, state, now).map(|_| ()))
+                    .err(),
+            };
+            serde_json::json!({
+                "id": endpoint.id,
+                "endpoint": endpoint.url,
+                "name": endpoint.name,
+                "tier": state.tier_of(&endpoint.url),
+                "available": status.is_some_and(|status| status.is_available()),
+                "latency": status.and_then(|status| status.latency),
+                "hourly_latency": status.and_then(|status| status.hourly_profile.latency_at(now)),
+                "excluded": excluded,
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "current_endpoint": state.current_url(),
+        "current_tier": state.tier_of(state.current_url()),
+        "switch_threshold_ms": state.config.server.switch_threshold_ms,
+        "blocked_by": blocked_by,
+        "decision": decision.map(|(strategy, status, decision)| {
+            let mut json = decision_json(status.map(|status| status.endpoint.as_str()), decision);
+            json["strategy"] = serde_json::json!(strategy);
+            json
+        }),
+        "strategies": {
+            "first_available": {
+                "candidate": race_winner.map(|status| &status.endpoint),
+                "decision": race_decision.as_ref().map(|decision| {
+                    decision_json(race_winner.map(|status| status.endpoint.as_str()), decision)
+                }),
+            },
+            "best_of_cycle": {
+                "candidate": cycle_best.map(|status| &status.endpoint),
+                "decision": cycle_decision.as_ref().map(|decision| {
+                    decision_json(cycle_best.map(|status| status.endpoint.as_str()), decision)
+                }),
+            },
+        },
+        "endpoints": endpoints,
+    })
+}
+
+fn decision_json(
+    to: Option<&str>,
+    decision: &Result<SwitchDecision, Exclusion>,
+) -> serde_json::Value {
+    match decision {
+        Ok((from, from_latency, to_latency, reason)) => serde_json::json!({
+            "switch": true,
+            "from": from,
+            "to": to,
+            "from_latency": from_latency,
+            "to_latency": to_latency,
+            "reason": reason,
+        }),
+        Err(exclusion) => serde_json::json!({
+            "switch": false,
+            "stay_reason": exclusion,
+        }),
+    }
+}
+
+/// `GET /admin/orchestrator`: the health loop's published state, for telling a
+/// stalled loop from an idle one
+pub(super) async fn orchestrator_handler(
+    req: Request<Body>,
+    state: SharedState,
+    orchestrator: HealthOrchestratorHandle,
+) -> anyhow::Result<Response<Body>> {
+    if let Some(rejection) = check_admin_auth(&req, &state) {
+        return Ok(rejection);
+    }
+
+    let mut response_json = serde_json::to_value(orchestrator.snapshot())?;
+    if let Some(fields) = response_json.as_object_mut() {
+        fields.insert(
+            "timestamp".to_string(),
+            serde_json::json!(chrono::Utc::now()),
+        );
+    }
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&response_json)?))?)
+}
+
+pub(super) async fn switches_handler(state: SharedState) -> anyhow::Result<Response<Body>> {
+    let response_json = switches_json(&state);
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&response_json)?))?)
+}
+
+/// Body of `/switches`: the recent switches, newest first
+pub(crate) fn switches_json(state: &SharedState) -> serde_json::Value {
+    // Copy the history out so serialization doesn't hold the state lock
+    let switches: Vec<SwitchRecord> = state
+        .lock_or_recover()
+        .switch_history
+        .iter()
+        .rev()
+        .cloned()
+        .collect();
+
+    serde_json::json!({
+        "count": switches.len(),
+        "switches": switches,
+    })
+}
+
+pub(super) async fn profile_handler(state: SharedState) -> anyhow::Result<Response<Body>> {
+    let endpoints: Vec<serde_json::Value> = {
+        let guard = state.lock_or_recover();
+        guard
+            .config
+            .get_all_endpoints()
+            .into_iter()
+            .map(|(_, configured, _)| {
+                let hours = guard
+                    .status_of(&configured.url)
+                    .map(|status| status.hourly_profile.hours())
+                    .unwrap_or_default();
+                serde_json::json!({
+                    "id": configured.id(),
+                    "endpoint": configured.url,
+                    "name": configured.name,
+                    "hourly_latency_ms": hours,
+                })
+            })
+            .collect()
+    };
+
+    let response_json = serde_json::json!({
+        "current_hour": chrono::Local::now().hour(),
+        "endpoints": endpoints,
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&response_json)?))?)
+}
+
+/// `GET /health/history`: each endpoint's recorded checks, oldest first, with
+/// the phases of passing HTTP checks
+pub(super) async fn history_handler(state: SharedState) -> anyhow::Result<Response<Body>> {
+    let endpoints: Vec<serde_json::Value> = {
+        let guard = state.lock_or_recover();
+        guard
+            .config
+            .get_all_endpoints()
+            .into_iter()
+            .map(|(_, configured, _)| {
+                let checks = guard
+                    .status_of(&configured.url)
+                    .map(|status| status.latency_history.get_measurements().clone())
+                    .unwrap_or_default();
+                serde_json::json!({
+                    "id": configured.id(),
+                    "endpoint": configured.url,
+                    "name": configured.name,
+                    "checks": checks,
+                })
+            })
+            .collect()
+    };
+
+    let response_json = serde_json::json!({ "endpoints": endpoints });
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&response_json)?))?)
+}
+
+/// `GET /status/export.csv`: the dashboard's endpoint table as CSV
+pub(super) async fn export_csv_handler(state: SharedState) -> anyhow::Result<Response<Body>> {
+    let csv = {
+        let guard = state.lock_or_recover();
+        let counters = guard.metrics.endpoint_snapshots();
+        let rows: Vec<EndpointRow> = guard
+            .config
+            .endpoint_index()
+            .iter()
+            .map(|endpoint| {
+                EndpointRow::new(
+                    endpoint,
+                    guard.endpoint_status.get(&endpoint.id),
+                    counters.get(&endpoint.url).copied().unwrap_or_default(),
+                    guard.config.missing_token_env(&endpoint.url).is_some(),
+                )
+            })
+            .collect();
+        endpoint_table::to_csv(&rows, &guard.config.ui.currency)
+    };
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/csv; charset=utf-8")
+        .body(Body::from(csv))?)
+}
+
+pub(super) async fn version_handler(state: SharedState) -> anyhow::Result<Response<Body>> {
+    let response_json = {
+        let guard = state.lock_or_recover();
+        serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "git_commit": build_info::GIT_COMMIT,
+            "build_timestamp": build_info::BUILD_TIMESTAMP,
+            "rustc_version": build_info::RUSTC_VERSION,
+            "features": build_info::features(),
+            "config_path": guard.config.source_path,
+            "group_count": guard.config.groups.len(),
+            "endpoint_count": guard.config.get_all_endpoints().len(),
+            "uptime_seconds": guard.started_at.elapsed().as_secs(),
+        })
+    };
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&response_json)?))?)
+}
+
+pub(super) async fn config_handler(state: SharedState) -> anyhow::Result<Response<Body>> {
+    let view = RedactedConfig::new(&state.lock_or_recover().config)?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&view)?))?)
+}
+
+pub(super) async fn metrics_handler(state: SharedState) -> anyhow::Result<Response<Body>> {
+    let body = {
+        let guard = state.lock_or_recover();
+        crate::metrics::render_prometheus(
+            &guard.metrics,
+            &guard.endpoint_status,
+            guard.current_url(),
+        )
+    };
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/plain; version=0.0.4")
+        .body(Body::from(body))?)
+}
+
+pub(super) async fn health_handler() -> anyhow::Result<Response<Body>> {
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from("OK"))?)
+}
@@ -0,0 +1,216 @@
+//! In-memory cache of responses to the GET requests `[cache]` names, such as
+//! `/v1/models`, so tooling that polls them does not go upstream every time.
+//!
+//! Responses are kept per group, since each group authenticates with its own
+//! token, and never for a group that forwards the client's Authorization
+//! header, which would hand one client's response to another.
+
+use crate::lock::LockExt;
+use hyper::body::{Bytes, HttpBody};
+use hyper::header::{
+    HeaderName, HeaderValue, ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_LENGTH, ETAG,
+    IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, TRANSFER_ENCODING,
+};
+use hyper::{Body, HeaderMap, Response, StatusCode};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Response header telling the client where the response came from
+pub(super) const CACHE_HEADER: &str = "x-zephyr-cache";
+
+/// Entries kept at most; the oldest is dropped to make room
+const MAX_ENTRIES: usize = 256;
+
+/// How a cacheable request was answered, sent as [`CACHE_HEADER`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum CacheOutcome {
+    /// Served from a fresh entry without an upstream call
+    Hit,
+    /// A stale entry the upstream confirmed unchanged with a 304
+    Revalidated,
+    /// Fetched upstream
+    Miss,
+    /// Not looked up, the group forwards the client's credentials
+    Bypass,
+}
+
+impl CacheOutcome {
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            CacheOutcome::Hit => "HIT",
+            CacheOutcome::Revalidated => "REVALIDATED",
+            CacheOutcome::Miss => "MISS",
+            CacheOutcome::Bypass => "BYPASS",
+        }
+    }
+}
+
+/// What a cached response is stored under
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct CacheKey {
+    pub(super) method: String,
+    /// Path with the query string
+    pub(super) path: String,
+    pub(super) group: String,
+    /// The client's Accept-Encoding, as the stored body may be compressed
+    pub(super) accept_encoding: Option<String>,
+}
+
+impl CacheKey {
+    pub(super) fn new(method: &str, path: &str, group: &str, headers: &HeaderMap) -> Self {
+        Self {
+            method: method.to_string(),
+            path: path.to_string(),
+            group: group.to_string(),
+            accept_encoding: headers
+                .get(ACCEPT_ENCODING)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    stored_at: Instant,
+}
+
+/// Cached responses shared by every listener
+#[derive(Debug, Default)]
+pub(super) struct ResponseCache {
+    entries: Mutex<HashMap<CacheKey, CachedResponse>>,
+}
+
+/// Result of looking a request up
+pub(super) enum Lookup {
+    /// A fresh response to send as is
+    Fresh(Response<Body>),
+    /// A stale response, with the validators to send upstream
+    Stale(HeaderMap),
+    Missing,
+}
+
+impl ResponseCache {
+    pub(super) fn lookup(&self, key: &CacheKey, ttl: Duration) -> Lookup {
+        let entries = self.entries.lock_or_recover();
+        let Some(entry) = entries.get(key) else {
+            return Lookup::Missing;
+        };
+        if entry.stored_at.elapsed() < ttl {
+            return Lookup::Fresh(entry.response(CacheOutcome::Hit));
+        }
+        let mut validators = HeaderMap::new();
+        if let Some(etag) = entry.headers.get(ETAG) {
+            validators.insert(IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(modified) = entry.headers.get(LAST_MODIFIED) {
+            validators.insert(IF_MODIFIED_SINCE, modified.clone());
+        }
+        Lookup::Stale(validators)
+    }
+
+    /// Restart the entry's lifetime after the upstream answered 304, returning
+    /// the response to send, `None` when the entry is gone
+    pub(super) fn revalidate(&self, key: &CacheKey) -> Option<Response<Body>> {
+        let mut entries = self.entries.lock_or_recover();
+        let entry = entries.get_mut(key)?;
+        entry.stored_at = Instant::now();
+        Some(entry.response(CacheOutcome::Revalidated))
+    }
+
+    /// Keep a successful upstream response of at most `max_body_bytes`,
+    /// returning the response to forward either way
+    pub(super) async fn store(
+        &self,
+        key: CacheKey,
+        response: Response<Body>,
+        max_body_bytes: usize,
+    ) -> Response<Body> {
+        let headers = response.headers();
+        let no_store = headers
+            .get(CACHE_CONTROL)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| {
+                let value = value.to_ascii_lowercase();
+                value.contains("no-store") || value.contains("private")
+            });
+        let too_large = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok())
+            .is_some_and(|length| length > max_body_bytes);
+        if response.status() != StatusCode::OK || no_store || too_large {
+            return with_outcome(response, CacheOutcome::Miss);
+        }
+
+        let (parts, body) = response.into_parts();
+        let body = match read_limited(body, max_body_bytes).await {
+            Ok(body) => body,
+            Err(body) => {
+                return with_outcome(Response::from_parts(parts, body), CacheOutcome::Miss);
+            }
+        };
+        let entry = CachedResponse {
+            status: parts.status,
+            headers: parts.headers,
+            body,
+            stored_at: Instant::now(),
+        };
+        let response = entry.response(CacheOutcome::Miss);
+
+        let mut entries = self.entries.lock_or_recover();
+        if entries.len() >= MAX_ENTRIES && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.stored_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, entry);
+        response
+    }
+}
+
+impl CachedResponse {
+    fn response(&self, outcome: CacheOutcome) -> Response<Body> {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response.headers_mut().remove(TRANSFER_ENCODING);
+        response
+            .headers_mut()
+            .insert(CONTENT_LENGTH, HeaderValue::from(self.body.len()));
+        with_outcome(response, outcome)
+    }
+}
+
+/// `response` marked with how the cache answered it
+pub(super) fn with_outcome(mut response: Response<Body>, outcome: CacheOutcome) -> Response<Body> {
+    response.headers_mut().insert(
+        HeaderName::from_static(CACHE_HEADER),
+        HeaderValue::from_static(outcome.as_str()),
+    );
+    response
+}
+
+/// Read `body` whole when it is at most `max` bytes, otherwise hand back a
+/// body that still yields every byte, for forwarding
+async fn read_limited(mut body: Body, max: usize) -> Result<Bytes, Body> {
+    let mut buffered = Vec::new();
+    while let Some(chunk) = body.data().await {
+        match chunk {
+            Ok(chunk) if buffered.len() + chunk.len() <= max => buffered.extend_from_slice(&chunk),
+            chunk => {
+                let read = futures::stream::iter([Ok(Bytes::from(buffered)), chunk]);
+                return Err(Body::wrap_stream(futures::StreamExt::chain(read, body)));
+            }
+        }
+    }
+    Ok(Bytes::from(buffered))
+}
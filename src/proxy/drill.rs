@@ -0,0 +1,230 @@
+use crate::connection_tracker::{EventSender, SharedConnectionTracker};
+use crate::drill::DrillReport;
+use crate::lock::LockExt;
+use crate::logging::*;
+use crate::smoke::minimal_request_body;
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+use super::admin::{check_admin_auth, constant_time_eq};
+use super::handler::{proxy_handler, too_many_in_flight};
+use super::retry::HttpsClient;
+use super::{ProxyState, RunMode, SharedState};
+
+/// Header carrying the drill token on the drill's own request
+pub(super) const DRILL_HEADER: &str = "x-zephyr-drill";
+
+/// A failover drill in progress
+#[derive(Debug)]
+pub(crate) struct ActiveDrill {
+    /// Random token on the drill's request, so no other request can pass
+    /// itself off as part of the drill
+    token: String,
+    /// Endpoint that answered the drill's request
+    served_by: Option<String>,
+    /// Fallback endpoints the drill's request was sent to
+    candidates_tried: u32,
+}
+
+/// Whether `token` belongs to the running drill
+pub(super) fn is_drill_request(state: &ProxyState, token: &HeaderValue) -> bool {
+    state
+        .drill
+        .as_ref()
+        .is_some_and(|drill| constant_time_eq(drill.token.as_bytes(), token.as_bytes()))
+}
+
+/// Record how the drill's request went, `served_by` being `None` when no
+/// fallback endpoint served it
+pub(super) fn record_drill_attempt(
+    state: &mut ProxyState,
+    served_by: Option<&str>,
+    candidates_tried: u32,
+) {
+    if let Some(drill) = &mut state.drill {
+        drill.served_by = served_by.map(str::to_string);
+        drill.candidates_tried = candidates_tried;
+    }
+}
+
+/// Unpredictable token for one drill
+fn drill_token() -> String {
+    let part = || RandomState::new().build_hasher().finish();
+    format!("{:016x}{:016x}", part(), part())
+}
+
+/// `POST /admin/drill`: treat the current endpoint as failed for one
+/// synthetic request sent through the normal proxy path, and report whether
+/// a fallback endpoint served it. Real requests are not affected.
+pub(super) async fn drill_handler(
+    req: Request<Body>,
+    state: SharedState,
+    client: HttpsClient,
+    connection_tracker: SharedConnectionTracker,
+    event_sender: EventSender,
+    mode: RunMode,
+) -> anyhow::Result<Response<Body>> {
+    if let Some(rejection) = check_admin_auth(&req, &state) {
+        return Ok(rejection);
+    }
+
+    if req.method() != Method::POST {
+        return Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header("allow", "POST")
+            .body(Body::empty())?);
+    }
+
+    let token = drill_token();
+    let endpoint = {
+        let mut state_guard = state.lock_or_recover();
+        if state_guard.drill.is_some() {
+            let error_json = serde_json::json!({
+                "type": "error",
+                "error": {
+                    "type": "invalid_request_error",
+                    "message": "A failover drill is already running",
+                }
+            });
+            return Ok(Response::builder()
+                .status(StatusCode::CONFLICT)
+                .header("content-type", "application/json")
+                .body(Body::from(error_json.to_string()))?);
+        }
+        state_guard.drill = Some(ActiveDrill {
+            token: token.clone(),
+            served_by: None,
+            candidates_tried: 0,
+        });
+        state_guard.current_url().to_string()
+    };
+
+    // Run to completion even if the caller goes away, so the drill is
+    // always cleared and recorded
+    let report = tokio::spawn(run_drill(
+        token,
+        endpoint,
+        state,
+        client,
+        connection_tracker,
+        event_sender,
+        mode,
+    ))
+    .await?;
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string_pretty(&report)?))?)
+}
+
+async fn run_drill(
+    token: String,
+    endpoint: String,
+    state: SharedState,
+    client: HttpsClient,
+    connection_tracker: SharedConnectionTracker,
+    event_sender: EventSender,
+    mode: RunMode,
+) -> DrillReport {
+    if !mode.is_silent() {
+        log_drill_started(&endpoint);
+    }
+
+    let start = Instant::now();
+    let (status, failover_ms) = match send_drill_request(
+        token,
+        &state,
+        client,
+        connection_tracker,
+        event_sender,
+        mode,
+    )
+    .await
+    {
+        Ok((status, failover)) => (status, failover.as_millis() as u64),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            start.elapsed().as_millis() as u64,
+        ),
+    };
+
+    let report = {
+        let mut state_guard = state.lock_or_recover();
+        let drill = state_guard.drill.take();
+        let (served_by, candidates_tried) = drill
+            .map(|drill| (drill.served_by, drill.candidates_tried))
+            .unwrap_or_default();
+        let served_by = served_by.filter(|_| status.is_success());
+        state_guard.record_drill(&endpoint, served_by.as_deref());
+        DrillReport {
+            passed: served_by
+                .as_ref()
+                .is_some_and(|served_by| *served_by != endpoint),
+            drilled_endpoint: endpoint,
+            served_by,
+            status: status.as_u16(),
+            candidates_tried,
+            failover_ms,
+            fallback_policy: state_guard.config.retry.fallback,
+        }
+    };
+
+    if !mode.is_silent() {
+        log_drill_result(
+            &report.drilled_endpoint,
+            report.served_by.as_deref(),
+            report.failover_ms,
+        );
+    }
+    report
+}
+
+/// Send the drill's request through the proxy handler, returning its status
+/// and how long the response headers took
+async fn send_drill_request(
+    token: String,
+    state: &SharedState,
+    client: HttpsClient,
+    connection_tracker: SharedConnectionTracker,
+    event_sender: EventSender,
+    mode: RunMode,
+) -> anyhow::Result<(StatusCode, Duration)> {
+    let probe = state.lock_or_recover().config.health_check.probe();
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/v1/messages")
+        .header(CONTENT_TYPE, "application/json")
+        .header("anthropic-version", "2023-06-01")
+        .header(DRILL_HEADER, token)
+        .body(Body::from(minimal_request_body(&probe).to_string()))?;
+
+    let start = Instant::now();
+    // The drill counts against the in-flight limit like any client request
+    let metrics = state.lock_or_recover().metrics.clone();
+    let response = match metrics.try_admit_request() {
+        Some(in_flight) => {
+            proxy_handler(
+                request,
+                state.clone(),
+                client,
+                connection_tracker,
+                event_sender,
+                mode,
+                None,
+                in_flight,
+            )
+            .await?
+        }
+        None => too_many_in_flight(metrics.in_flight().limit)?,
+    };
+    let elapsed = start.elapsed();
+
+    // Read the body to the end so the drill's connection completes
+    let status = response.status();
+    hyper::body::to_bytes(response.into_body()).await?;
+    Ok((status, elapsed))
+}
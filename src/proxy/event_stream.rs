@@ -0,0 +1,547 @@
+//! `GET /events`: every [`ProxyEvent`] as Server-Sent Events, so a notifier
+//! can follow the proxy without linking against the crate.
+//!
+//! Events reach clients through a broadcast channel the event fan-out feeds.
+//! A client that falls more than [`EVENT_STREAM_CAPACITY`] events behind
+//! skips the oldest ones and is told how many with an `EventsDropped` event.
+
+use crate::config::ConfigDiff;
+use crate::connection_tracker::ConnectionId;
+use crate::dynamic_health::{IntervalDecision, LoadLevel};
+use crate::events::{
+    ActiveConnection, Alert, CompletionReason, HealthCycleSummary, PhaseDurations, ProxyEvent,
+    RequestTimings, SelectionMode, SwitchReason,
+};
+use crate::health::{CheckState, EndpointStatus};
+use crate::lock::LockExt;
+use crate::metrics::ProxyMetrics;
+use chrono::{DateTime, Utc};
+use hyper::body::Bytes;
+use hyper::{Body, Request, Response, StatusCode};
+use serde::Serialize;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::{
+    self,
+    error::{RecvError, TryRecvError},
+};
+
+use super::admin::{check_admin_auth, invalid_request, query_param};
+use super::SharedState;
+
+/// Events kept for clients that fall behind before the oldest are dropped
+pub(crate) const EVENT_STREAM_CAPACITY: usize = 1024;
+
+/// A comment is sent this often so idle streams stay open and a client that
+/// went away is noticed
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Frames already queued are sent together up to about this size
+const MAX_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Event categories `?types=` accepts besides event names
+const CATEGORIES: &[&str] = &[
+    "connection",
+    "load",
+    "health",
+    "switch",
+    "config",
+    "system",
+    "alert",
+];
+
+/// Sender the event fan-out feeds `/events` clients from
+pub(crate) type EventStream = broadcast::Sender<ProxyEvent>;
+
+pub(crate) fn new_event_stream() -> EventStream {
+    broadcast::channel(EVENT_STREAM_CAPACITY).0
+}
+
+/// A [`ProxyEvent`] as sent to `/events` clients, named after its variant.
+/// Instants become wall-clock times and durations milliseconds.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+enum EventView<'a> {
+    ConnectionStarted(&'a ActiveConnection),
+    ConnectionCompleted {
+        id: &'a ConnectionId,
+        reason: CompletionReason,
+        phases: Option<PhaseDurations>,
+        timings: Option<RequestTimings>,
+    },
+    ConnectionProgress {
+        id: &'a ConnectionId,
+        bytes_received: u64,
+        last_chunk_at: DateTime<Utc>,
+    },
+    RequestReceived {
+        endpoint: &'a str,
+        received_at: DateTime<Utc>,
+    },
+    LoadLevelUpdated {
+        load_level: LoadLevel,
+        request_rate: f64,
+        active_connections: u32,
+    },
+    HealthCheckStarted {
+        interval_ms: u64,
+        next_check_at: DateTime<Utc>,
+        load_level: LoadLevel,
+        active_connections: u32,
+        interval_decision: Option<IntervalDecision>,
+    },
+    HealthCheckRunning {
+        started_at: DateTime<Utc>,
+        estimated_duration_ms: u64,
+    },
+    HealthCheckCompleted {
+        duration_ms: u64,
+    },
+    HealthCycleSummary(&'a HealthCycleSummary),
+    /// The endpoint's verdict without its latency history
+    HealthUpdate {
+        endpoint: &'a str,
+        state: CheckState,
+        available: bool,
+        latency_ms: Option<u64>,
+        ttfb_ms: Option<u64>,
+        error: Option<&'a str>,
+        auth_failed: bool,
+        last_check: DateTime<Utc>,
+        maintenance_until: Option<DateTime<Utc>>,
+    },
+    EndpointSwitch {
+        from: &'a str,
+        to: &'a str,
+        from_latency_ms: Option<u64>,
+        to_latency_ms: Option<u64>,
+        reason: SwitchReason,
+    },
+    EndpointDrained {
+        endpoint: &'a str,
+        remaining: u32,
+        elapsed_ms: u64,
+    },
+    SelectionModeChanged {
+        mode: SelectionMode,
+    },
+    ManualEndpointSelected {
+        endpoint: &'a str,
+        endpoint_index: usize,
+    },
+    ServerStarted {
+        port: u16,
+    },
+    ConfigLoaded {
+        endpoint_count: usize,
+    },
+    ConfigReloaded {
+        diff: &'a ConfigDiff,
+        current: &'a str,
+    },
+    ConfigReloadFailed {
+        error: &'a str,
+    },
+    SystemPaused,
+    SystemResumed,
+    SwitchingFrozen {
+        frozen: bool,
+    },
+    AllEndpointsDown {
+        down: bool,
+    },
+    GroupDown {
+        group: &'a str,
+        down: bool,
+    },
+    AlertChanged {
+        alert: &'a Alert,
+        firing: bool,
+    },
+    ManualRefreshTriggered,
+    /// Not a [`ProxyEvent`]: the client fell behind and missed `dropped` events
+    EventsDropped {
+        dropped: u64,
+    },
+}
+
+/// Every event name, for checking `?types=`
+const EVENT_NAMES: &[&str] = &[
+    "ConnectionStarted",
+    "ConnectionCompleted",
+    "ConnectionProgress",
+    "RequestReceived",
+    "LoadLevelUpdated",
+    "HealthCheckStarted",
+    "HealthCheckRunning",
+    "HealthCheckCompleted",
+    "HealthCycleSummary",
+    "HealthUpdate",
+    "EndpointSwitch",
+    "EndpointDrained",
+    "SelectionModeChanged",
+    "ManualEndpointSelected",
+    "ServerStarted",
+    "ConfigLoaded",
+    "ConfigReloaded",
+    "ConfigReloadFailed",
+    "SystemPaused",
+    "SystemResumed",
+    "SwitchingFrozen",
+    "AllEndpointsDown",
+    "GroupDown",
+    "AlertChanged",
+    "ManualRefreshTriggered",
+    "EventsDropped",
+];
+
+impl<'a> EventView<'a> {
+    fn new(event: &'a ProxyEvent) -> Self {
+        match event {
+            ProxyEvent::ConnectionStarted(connection) => EventView::ConnectionStarted(connection),
+            ProxyEvent::ConnectionCompleted {
+                id,
+                reason,
+                phases,
+                timings,
+            } => EventView::ConnectionCompleted {
+                id,
+                reason: *reason,
+                phases: *phases,
+                timings: *timings,
+            },
+            ProxyEvent::ConnectionProgress {
+                id,
+                bytes_received,
+                last_chunk_at,
+            } => EventView::ConnectionProgress {
+                id,
+                bytes_received: *bytes_received,
+                last_chunk_at: wall_clock(*last_chunk_at),
+            },
+            ProxyEvent::RequestReceived {
+                endpoint,
+                timestamp,
+            } => EventView::RequestReceived {
+                endpoint,
+                received_at: wall_clock(*timestamp),
+            },
+            ProxyEvent::LoadLevelUpdated {
+                load_level,
+                request_rate,
+                active_connections,
+            } => EventView::LoadLevelUpdated {
+                load_level: *load_level,
+                request_rate: *request_rate,
+                active_connections: *active_connections,
+            },
+            ProxyEvent::HealthCheckStarted {
+                actual_interval,
+                next_check_time,
+                load_level,
+                active_connections,
+                interval_decision,
+            } => EventView::HealthCheckStarted {
+                interval_ms: millis(*actual_interval),
+                next_check_at: wall_clock(*next_check_time),
+                load_level: *load_level,
+                active_connections: *active_connections,
+                interval_decision: *interval_decision,
+            },
+            ProxyEvent::HealthCheckRunning {
+                started_at,
+                estimated_duration,
+            } => EventView::HealthCheckRunning {
+                started_at: wall_clock(*started_at),
+                estimated_duration_ms: millis(*estimated_duration),
+            },
+            ProxyEvent::HealthCheckCompleted { duration } => EventView::HealthCheckCompleted {
+                duration_ms: millis(*duration),
+            },
+            ProxyEvent::HealthCycleSummary(summary) => EventView::HealthCycleSummary(summary),
+            ProxyEvent::HealthUpdate(status) => health_update(status),
+            ProxyEvent::EndpointSwitch {
+                from,
+                to,
+                from_latency,
+                to_latency,
+                reason,
+            } => EventView::EndpointSwitch {
+                from,
+                to,
+                from_latency_ms: *from_latency,
+                to_latency_ms: *to_latency,
+                reason: *reason,
+            },
+            ProxyEvent::EndpointDrained {
+                endpoint,
+                remaining,
+                elapsed,
+            } => EventView::EndpointDrained {
+                endpoint,
+                remaining: *remaining,
+                elapsed_ms: millis(*elapsed),
+            },
+            ProxyEvent::SelectionModeChanged { mode } => {
+                EventView::SelectionModeChanged { mode: *mode }
+            }
+            ProxyEvent::ManualEndpointSelected {
+                endpoint,
+                endpoint_index,
+            } => EventView::ManualEndpointSelected {
+                endpoint,
+                endpoint_index: *endpoint_index,
+            },
+            ProxyEvent::ServerStarted { port } => EventView::ServerStarted { port: *port },
+            ProxyEvent::ConfigLoaded { endpoint_count } => EventView::ConfigLoaded {
+                endpoint_count: *endpoint_count,
+            },
+            ProxyEvent::ConfigReloaded { diff, current } => {
+                EventView::ConfigReloaded { diff, current }
+            }
+            ProxyEvent::ConfigReloadFailed { error } => EventView::ConfigReloadFailed { error },
+            ProxyEvent::SystemPaused => EventView::SystemPaused,
+            ProxyEvent::SystemResumed => EventView::SystemResumed,
+            ProxyEvent::SwitchingFrozen { frozen } => {
+                EventView::SwitchingFrozen { frozen: *frozen }
+            }
+            ProxyEvent::AllEndpointsDown { down } => EventView::AllEndpointsDown { down: *down },
+            ProxyEvent::GroupDown { group, down } => EventView::GroupDown { group, down: *down },
+            ProxyEvent::AlertChanged { alert, firing } => EventView::AlertChanged {
+                alert,
+                firing: *firing,
+            },
+            ProxyEvent::ManualRefreshTriggered => EventView::ManualRefreshTriggered,
+        }
+    }
+
+    /// Name of the event, the `event:` field of its frame
+    fn name(&self) -> &'static str {
+        match self {
+            EventView::ConnectionStarted(_) => "ConnectionStarted",
+            EventView::ConnectionCompleted { .. } => "ConnectionCompleted",
+            EventView::ConnectionProgress { .. } => "ConnectionProgress",
+            EventView::RequestReceived { .. } => "RequestReceived",
+            EventView::LoadLevelUpdated { .. } => "LoadLevelUpdated",
+            EventView::HealthCheckStarted { .. } => "HealthCheckStarted",
+            EventView::HealthCheckRunning { .. } => "HealthCheckRunning",
+            EventView::HealthCheckCompleted { .. } => "HealthCheckCompleted",
+            EventView::HealthCycleSummary(_) => "HealthCycleSummary",
+            EventView::HealthUpdate { .. } => "HealthUpdate",
+            EventView::EndpointSwitch { .. } => "EndpointSwitch",
+            EventView::EndpointDrained { .. } => "EndpointDrained",
+            EventView::SelectionModeChanged { .. } => "SelectionModeChanged",
+            EventView::ManualEndpointSelected { .. } => "ManualEndpointSelected",
+            EventView::ServerStarted { .. } => "ServerStarted",
+            EventView::ConfigLoaded { .. } => "ConfigLoaded",
+            EventView::ConfigReloaded { .. } => "ConfigReloaded",
+            EventView::ConfigReloadFailed { .. } => "ConfigReloadFailed",
+            EventView::SystemPaused => "SystemPaused",
+            EventView::SystemResumed => "SystemResumed",
+            EventView::SwitchingFrozen { .. } => "SwitchingFrozen",
+            EventView::AllEndpointsDown { .. } => "AllEndpointsDown",
+            EventView::GroupDown { .. } => "GroupDown",
+            EventView::AlertChanged { .. } => "AlertChanged",
+            EventView::ManualRefreshTriggered => "ManualRefreshTriggered",
+            EventView::EventsDropped { .. } => "EventsDropped",
+        }
+    }
+
+    /// Category `?types=` can select the event by
+    fn category(&self) -> &'static str {
+        match self {
+            EventView::ConnectionStarted(_)
+            | EventView::ConnectionCompleted { .. }
+            | EventView::ConnectionProgress { .. }
+            | EventView::RequestReceived { .. } => "connection",
+            EventView::LoadLevelUpdated { .. } => "load",
+            EventView::HealthCheckStarted { .. }
+            | EventView::HealthCheckRunning { .. }
+            | EventView::HealthCheckCompleted { .. }
+            | EventView::HealthCycleSummary(_)
+            | EventView::HealthUpdate { .. }
+            | EventView::AllEndpointsDown { .. }
+            | EventView::GroupDown { .. }
+            | EventView::ManualRefreshTriggered => "health",
+            EventView::EndpointSwitch { .. }
+            | EventView::EndpointDrained { .. }
+            | EventView::SelectionModeChanged { .. }
+            | EventView::ManualEndpointSelected { .. }
+            | EventView::SwitchingFrozen { .. } => "switch",
+            EventView::ConfigLoaded { .. }
+            | EventView::ConfigReloaded { .. }
+            | EventView::ConfigReloadFailed { .. } => "config",
+            EventView::ServerStarted { .. }
+            | EventView::SystemPaused
+            | EventView::SystemResumed
+            | EventView::EventsDropped { .. } => "system",
+            EventView::AlertChanged { .. } => "alert",
+        }
+    }
+
+    /// Append the SSE frame: the event name and its JSON with the time it was sent
+    fn write_frame(&self, frames: &mut String) {
+        #[derive(Serialize)]
+        struct Stamped<'a, 'b> {
+            timestamp: DateTime<Utc>,
+            #[serde(flatten)]
+            event: &'b EventView<'a>,
+        }
+        let data = serde_json::to_string(&Stamped {
+            timestamp: Utc::now(),
+            event: self,
+        })
+        .unwrap_or_default();
+        let _ = write!(frames, "event: {}\ndata: {data}\n\n", self.name());
+    }
+}
+
+fn health_update(status: &EndpointStatus) -> EventView<'_> {
+    EventView::HealthUpdate {
+        endpoint: &status.endpoint,
+        state: status.state,
+        available: status.is_available(),
+        latency_ms: status.latency,
+        ttfb_ms: status.ttfb_ms,
+        error: status.error.as_deref(),
+        auth_failed: status.auth_failed,
+        last_check: status.last_check,
+        maintenance_until: status.maintenance_until,
+    }
+}
+
+/// Wall-clock time of a monotonic `instant`, in the past or the future
+fn wall_clock(instant: Instant) -> DateTime<Utc> {
+    let now = Instant::now();
+    if instant <= now {
+        Utc::now() - chrono::Duration::from_std(now - instant).unwrap_or_default()
+    } else {
+        Utc::now() + chrono::Duration::from_std(instant - now).unwrap_or_default()
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+/// Event names and categories from `?types=`, `None` for every event
+struct TypeFilter(Option<Vec<String>>);
+
+impl TypeFilter {
+    fn parse(types: Option<&str>) -> Result<Self, String> {
+        let Some(types) = types else {
+            return Ok(TypeFilter(None));
+        };
+        let mut wanted = Vec::new();
+        for name in types
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            let known = CATEGORIES.contains(&name)
+                || EVENT_NAMES
+                    .iter()
+                    .any(|event| event.eq_ignore_ascii_case(name));
+            if !known {
+                return Err(format!(
+                    "Unknown event type: {name} (use a category: {}, or an event name such as EndpointSwitch)",
+                    CATEGORIES.join(", ")
+                ));
+            }
+            wanted.push(name.to_ascii_lowercase());
+        }
+        Ok(TypeFilter(Some(wanted)))
+    }
+
+    fn allows(&self, event: &EventView) -> bool {
+        let Some(wanted) = &self.0 else {
+            return true;
+        };
+        wanted
+            .iter()
+            .any(|name| name == event.category() || name.eq_ignore_ascii_case(event.name()))
+    }
+}
+
+/// Append the frame for what the client received, unless the filter skips
+/// it; false once the event stream closed
+fn write_received(
+    frames: &mut String,
+    received: Result<ProxyEvent, RecvError>,
+    filter: &TypeFilter,
+    metrics: &ProxyMetrics,
+) -> bool {
+    match received {
+        Ok(event) => {
+            let view = EventView::new(&event);
+            if filter.allows(&view) {
+                view.write_frame(frames);
+            }
+        }
+        Err(RecvError::Lagged(dropped)) => {
+            metrics.record_events_dropped(dropped);
+            EventView::EventsDropped { dropped }.write_frame(frames);
+        }
+        Err(RecvError::Closed) => return false,
+    }
+    true
+}
+
+pub(super) async fn events_handler(
+    req: Request<Body>,
+    state: SharedState,
+) -> anyhow::Result<Response<Body>> {
+    if let Some(rejection) = check_admin_auth(&req, &state) {
+        return Ok(rejection);
+    }
+    let filter = match TypeFilter::parse(query_param(&req, "types").as_deref()) {
+        Ok(filter) => filter,
+        Err(message) => return invalid_request(&message),
+    };
+
+    let (mut events, metrics) = {
+        let state_guard = state.lock_or_recover();
+        (
+            state_guard.event_stream.subscribe(),
+            state_guard.metrics.clone(),
+        )
+    };
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        // The first tick is immediate, so the client sees the stream open
+        let mut keepalive = tokio::time::interval(KEEPALIVE_INTERVAL);
+        loop {
+            let mut frames = String::new();
+            tokio::select! {
+                event = events.recv() => {
+                    if !write_received(&mut frames, event, &filter, &metrics) {
+                        break;
+                    }
+                }
+                _ = keepalive.tick() => frames.push_str(": keepalive\n\n"),
+            }
+            // Events that queued up meanwhile go out in the same chunk, so a
+            // burst does not cost a write per event
+            while frames.len() < MAX_CHUNK_BYTES {
+                let event = match events.try_recv() {
+                    Ok(event) => Ok(event),
+                    Err(TryRecvError::Lagged(dropped)) => Err(RecvError::Lagged(dropped)),
+                    Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+                };
+                write_received(&mut frames, event, &filter, &metrics);
+            }
+            if frames.is_empty() {
+                continue;
+            }
+            if sender.send_data(Bytes::from(frames)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/event-stream")
+        .header("cache-control", "no-cache")
+        .body(body)?)
+}
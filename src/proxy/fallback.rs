@@ -0,0 +1,537 @@
+use crate::config::FallbackPolicy;
+use crate::connection_tracker::{ConnectionId, EventSender, SharedConnectionTracker};
+use crate::events::{ProxyEvent, SelectionMode, SwitchReason};
+use crate::health::{CheckState, EndpointStatus};
+use crate::lock::LockExt;
+use crate::logging::*;
+use hyper::{Body, Response};
+
+use super::retry::{
+    send_upstream_attempt, HttpsClient, RequestTemplate, ResolvedEndpoint, UpstreamError,
+};
+use super::{ProxyState, SharedState};
+
+/// What is known about a fallback candidate's health, most promising first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum CandidateHealth {
+    Healthy,
+    /// Never checked, or being checked without an earlier failure
+    Unknown,
+    /// Failed its last check or a live request
+    Failed,
+}
+
+impl CandidateHealth {
+    fn of(status: Option<&EndpointStatus>) -> Self {
+        let Some(status) = status else {
+            return Self::Unknown;
+        };
+        match status.state {
+            CheckState::Healthy => Self::Healthy,
+            CheckState::Unhealthy => Self::Failed,
+            // A running check has no verdict yet, the history keeps the last one
+            CheckState::Checking
+                if status
+                    .latency_history
+                    .get_latest()
+                    .is_some_and(|measurement| measurement.latency.is_none()) =>
+            {
+                Self::Failed
+            }
+            CheckState::Checking | CheckState::Unchecked => Self::Unknown,
+        }
+    }
+}
+
+/// An endpoint the fallback policy allows, with what is known about its health
+pub(super) struct EndpointCandidate {
+    pub(super) endpoint: ResolvedEndpoint,
+    pub(super) health: CandidateHealth,
+    pub(super) latency: Option<u64>,
+    /// Failover tier of the endpoint's group, lower is preferred
+    pub(super) tier: u8,
+}
+
+/// Order candidates by tier, and within a tier the healthy endpoints by
+/// latency first, then those not checked yet, then those that failed as a
+/// last resort. Equally ranked endpoints keep their order.
+pub(super) fn rank_candidates(mut candidates: Vec<EndpointCandidate>) -> Vec<EndpointCandidate> {
+    candidates.sort_by_key(|candidate| {
+        (
+            candidate.tier,
+            candidate.health,
+            candidate.latency.unwrap_or(u64::MAX),
+        )
+    });
+    candidates
+}
+
+/// Fallback candidates for a request that failed on `failed_endpoint`, in the
+/// order they should be tried. `only_group` limits them to one group, for
+/// requests from a listener restricted to it.
+pub(super) fn fallback_candidates(
+    state: &ProxyState,
+    failed_endpoint: &str,
+    only_group: Option<&str>,
+) -> Vec<ResolvedEndpoint> {
+    let failed_group = state
+        .config
+        .group_of(failed_endpoint)
+        .map(|group| group.name.as_str());
+
+    ranked_endpoints(state, failed_endpoint, |group| {
+        only_group.is_none_or(|only| only == group)
+            && match state.config.retry.fallback {
+                FallbackPolicy::Off => false,
+                FallbackPolicy::SameGroup => failed_group == Some(group),
+                FallbackPolicy::All => true,
+            }
+    })
+}
+
+/// Endpoint for a request from a listener limited to `listener_group`, the
+/// current endpoint for one serving every group
+pub(super) fn listener_endpoint(
+    state: &ProxyState,
+    listener_group: Option<&str>,
+) -> Option<String> {
+    match listener_group {
+        Some(group) => group_endpoint(state, group),
+        None => Some(state.current_url().to_string()),
+    }
+}
+
+/// Where to send a request bound for `endpoint` when the proxy already knows
+/// that endpoint is down: the first available endpoint its fallback would
+/// try. Read in the same lock as `endpoint`, so a request arriving between a
+/// health check marking the current endpoint failed and the switch away from
+/// it goes straight to the alternative instead of spending its retries.
+/// `None` when `endpoint` is not known to be down or nothing else is up.
+pub(super) fn reroute_unavailable(
+    state: &ProxyState,
+    endpoint: &str,
+    listener_group: Option<&str>,
+) -> Option<ResolvedEndpoint> {
+    // Not yet checked or being checked is not known to be down
+    if state
+        .status_of(endpoint)
+        .is_none_or(|status| status.state != CheckState::Unhealthy)
+    {
+        return None;
+    }
+    fallback_candidates(state, endpoint, listener_group)
+        .into_iter()
+        .find(|candidate| {
+            state
+                .status_of(&candidate.url)
+                .is_some_and(|status| status.is_available())
+        })
+}
+
+/// Endpoint for a request arriving on a listener restricted to `group`: the
+/// current endpoint when it is in the group, else the group's best endpoint.
+/// `None` when the group has no endpoint that can be used.
+fn group_endpoint(state: &ProxyState, group: &str) -> Option<String> {
+    let current_group = state.config.group_of(state.current_url());
+    if current_group.is_some_and(|current| current.name == group) {
+        return Some(state.current_url().to_string());
+    }
+    ranked_endpoints(state, "", |candidate| candidate == group)
+        .into_iter()
+        .next()
+        .map(|endpoint| endpoint.url)
+}
+
+/// Endpoints other than `excluded` in the groups `group_allowed` accepts,
+/// leaving out those filtered, draining, in maintenance or rejecting their
+/// token, ranked by [`rank_candidates`]
+fn ranked_endpoints(
+    state: &ProxyState,
+    excluded: &str,
+    group_allowed: impl Fn(&str) -> bool,
+) -> Vec<ResolvedEndpoint> {
+    let candidates = state
+        .config
+        .get_all_endpoints()
+        .into_iter()
+        .filter(|(_, endpoint, _)| endpoint.url != excluded)
+        .filter(|(_, endpoint, _)| state.matches_tag_filter(&endpoint.url))
+        .filter(|(_, endpoint, _)| !state.is_draining(&endpoint.url))
+        .filter(|(_, endpoint, _)| !state.in_maintenance(&endpoint.url))
+        .filter(|(auth_token, endpoint, _)| !state.is_auth_rejected(&endpoint.url, auth_token))
+        .filter(|(_, _, group)| group_allowed(group))
+        .map(|(auth_token, endpoint, _)| {
+            let status = state.status_of(&endpoint.url);
+            let health = CandidateHealth::of(status);
+            let latency = status.and_then(|s| s.latency);
+            let tier = state.tier_of(&endpoint.url);
+            EndpointCandidate {
+                endpoint: ResolvedEndpoint {
+                    auth_mode: state.config.auth_mode_for(&endpoint.url),
+                    timeout: state.config.request_timeout_for(&endpoint.url),
+                    url: endpoint.url,
+                    auth_token,
+                },
+                health,
+                latency,
+                tier,
+            }
+        })
+        .collect();
+
+    rank_candidates(candidates)
+        .into_iter()
+        .map(|candidate| candidate.endpoint)
+        .collect()
+}
+
+/// Mark an endpoint unavailable after a failed live request. An endpoint in
+/// maintenance is expected to fail and is left as it is.
+pub fn mark_endpoint_failed(state: &SharedState, endpoint: &str, error: String) {
+    let mut state_guard = state.lock_or_recover();
+    if let Some(status) = state_guard
+        .status_of_mut(endpoint)
+        .filter(|status| status.maintenance_until.is_none())
+    {
+        status.mark_unhealthy(error);
+        status.last_check = chrono::Utc::now();
+    }
+}
+
+/// Try the request once on every other endpoint until one of them succeeds
+pub(super) async fn try_with_fallback_endpoints(
+    client: &HttpsClient,
+    template: &RequestTemplate,
+    failed_endpoint: &str,
+    state: &SharedState,
+    only_group: Option<&str>,
+    connection_id: &ConnectionId,
+    silent_mode: bool,
+) -> Option<(String, Response<Body>)> {
+    let (candidates, retry_config) = {
+        let state_guard = state.lock_or_recover();
+        (
+            fallback_candidates(&state_guard, failed_endpoint, only_group),
+            state_guard.config.retry.clone(),
+        )
+    };
+
+    for candidate in candidates {
+        let endpoint = candidate.url.clone();
+        if !silent_mode {
+            log_fallback_attempt(failed_endpoint, &endpoint, connection_id);
+        }
+
+        match send_upstream_attempt(client, template, &candidate, &retry_config).await {
+            Ok(resp) => {
+                if !silent_mode {
+                    log_fallback_success(failed_endpoint, &endpoint, connection_id);
+                }
+                return Some((endpoint, resp));
+            }
+            Err(err) => {
+                if !silent_mode {
+                    log_proxy_error(&endpoint, &err.describe(), Some(connection_id));
+                }
+                if err.marks_endpoint_failed() {
+                    mark_endpoint_failed(state, &endpoint, err.describe());
+                }
+                if matches!(err, UpstreamError::Timeout(_)) {
+                    state
+                        .lock_or_recover()
+                        .metrics
+                        .record_fallback_timeout(&endpoint);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Record that a request was served by a fallback endpoint: re-attribute the
+/// connection, which was announced with `RequestReceived` once already and is
+/// not counted again for the load, and, in auto mode with `make_current`,
+/// make the fallback endpoint current
+pub(super) fn handle_fallback_success(
+    state: &SharedState,
+    connection_tracker: &SharedConnectionTracker,
+    event_sender: &EventSender,
+    connection_id: &ConnectionId,
+    fallback_endpoint: &str,
+    make_current: bool,
+    silent_mode: bool,
+) {
+    let fallback_id = state
+        .lock_or_recover()
+        .config
+        .endpoint_index()
+        .key_of(fallback_endpoint);
+    connection_tracker.lock_or_recover().reassign_connection(
+        connection_id,
+        fallback_endpoint,
+        &fallback_id,
+    );
+
+    let record = {
+        let mut state_guard = state.lock_or_recover();
+        // A manual selection or frozen switching is the user's choice, so only
+        // this request moves
+        if make_current
+            && state_guard.selection_mode == SelectionMode::Auto
+            && !state_guard.switching_frozen
+        {
+            state_guard.switch_endpoint(
+                fallback_endpoint.to_string(),
+                SwitchReason::RequestFallback,
+                silent_mode,
+            )
+        } else {
+            None
+        }
+    };
+
+    if let Some(record) = record {
+        let _ = event_sender.send(ProxyEvent::EndpointSwitch {
+            from: record.from,
+            to: record.to,
+            from_latency: record.from_latency,
+            to_latency: record.to_latency,
+            reason: record.reason,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoint::EndpointId;
+    use crate::health::token_fingerprint;
+    use crate::proxy::Drain;
+    use crate::test_support::{config, TWO_GROUPS};
+
+    const ALPHA: &str = "http://127.0.0.1:19001";
+    const BETA: &str = "http://127.0.0.1:19002";
+    const GAMMA: &str = "http://127.0.0.1:19003";
+    const DELTA: &str = "http://127.0.0.1:19004";
+
+    /// `main` with alpha, gamma (tagged `eu`) and delta, `backup` with beta
+    fn state() -> ProxyState {
+        let toml = TWO_GROUPS.replace(
+            r#"endpoints = [{ url = "http://127.0.0.1:19001", name = "alpha" }]"#,
+            r#"endpoints = [
+    { url = "http://127.0.0.1:19001", name = "alpha" },
+    { url = "http://127.0.0.1:19003", name = "gamma", tags = ["eu"] },
+    { url = "http://127.0.0.1:19004", name = "delta" },
+]"#,
+        );
+        let mut state = ProxyState::new(config(&toml));
+        for (url, latency) in [(ALPHA, 100), (BETA, 50), (GAMMA, 300), (DELTA, 200)] {
+            set_status(
+                &mut state,
+                url,
+                EndpointStatus::new_available(url.to_string(), latency, None),
+            );
+        }
+        state
+    }
+
+    fn set_status(state: &mut ProxyState, url: &str, status: EndpointStatus) {
+        *state.status_of_mut(url).unwrap() = status;
+    }
+
+    fn urls(endpoints: Vec<ResolvedEndpoint>) -> Vec<String> {
+        endpoints.into_iter().map(|endpoint| endpoint.url).collect()
+    }
+
+    fn candidate(
+        url: &str,
+        health: CandidateHealth,
+        latency: Option<u64>,
+        tier: u8,
+    ) -> EndpointCandidate {
+        EndpointCandidate {
+            endpoint: ResolvedEndpoint {
+                url: url.to_string(),
+                ..ResolvedEndpoint::default()
+            },
+            health,
+            latency,
+            tier,
+        }
+    }
+
+    fn ranked(candidates: Vec<EndpointCandidate>) -> Vec<String> {
+        rank_candidates(candidates)
+            .into_iter()
+            .map(|candidate| candidate.endpoint.url)
+            .collect()
+    }
+
+    #[test]
+    fn healthy_candidates_rank_first_by_latency() {
+        let order = ranked(vec![
+            candidate("failed", CandidateHealth::Failed, None, 1),
+            candidate("unknown", CandidateHealth::Unknown, None, 1),
+            candidate("slow", CandidateHealth::Healthy, Some(300), 1),
+            candidate("fast", CandidateHealth::Healthy, Some(50), 1),
+        ]);
+        assert_eq!(order, ["fast", "slow", "unknown", "failed"]);
+    }
+
+    #[test]
+    fn tier_ranks_before_health() {
+        let order = ranked(vec![
+            candidate("tier2-healthy", CandidateHealth::Healthy, Some(10), 2),
+            candidate("tier1-failed", CandidateHealth::Failed, None, 1),
+            candidate("tier1-healthy", CandidateHealth::Healthy, Some(900), 1),
+        ]);
+        assert_eq!(order, ["tier1-healthy", "tier1-failed", "tier2-healthy"]);
+    }
+
+    #[test]
+    fn equally_ranked_candidates_keep_their_order() {
+        let order = ranked(vec![
+            candidate("first", CandidateHealth::Unknown, None, 1),
+            candidate("second", CandidateHealth::Unknown, None, 1),
+            candidate("third", CandidateHealth::Unknown, None, 1),
+        ]);
+        assert_eq!(order, ["first", "second", "third"]);
+    }
+
+    #[test]
+    fn candidate_health_follows_the_last_verdict() {
+        let url = ALPHA.to_string();
+        assert_eq!(CandidateHealth::of(None), CandidateHealth::Unknown);
+        let unchecked = EndpointStatus::new_unchecked(url.clone());
+        assert_eq!(
+            CandidateHealth::of(Some(&unchecked)),
+            CandidateHealth::Unknown
+        );
+        let checking = EndpointStatus::new_checking(url.clone());
+        assert_eq!(
+            CandidateHealth::of(Some(&checking)),
+            CandidateHealth::Unknown
+        );
+        let healthy = EndpointStatus::new_available(url.clone(), 80, None);
+        assert_eq!(
+            CandidateHealth::of(Some(&healthy)),
+            CandidateHealth::Healthy
+        );
+        let mut failed = EndpointStatus::new_unavailable(url, "down".to_string());
+        assert_eq!(CandidateHealth::of(Some(&failed)), CandidateHealth::Failed);
+        // Checked again after failing: still failed until the check passes
+        failed.state = CheckState::Checking;
+        assert_eq!(CandidateHealth::of(Some(&failed)), CandidateHealth::Failed);
+    }
+
+    #[test]
+    fn fallback_tries_other_endpoints_healthy_and_fastest_first() {
+        let mut state = state();
+        set_status(
+            &mut state,
+            DELTA,
+            EndpointStatus::new_unavailable(DELTA.to_string(), "down".to_string()),
+        );
+        assert_eq!(
+            urls(fallback_candidates(&state, ALPHA, None)),
+            [BETA, GAMMA, DELTA]
+        );
+    }
+
+    #[test]
+    fn fallback_prefers_the_failed_endpoints_tier() {
+        let mut state = state();
+        state.config.groups[1].priority = 2;
+        assert_eq!(
+            urls(fallback_candidates(&state, ALPHA, None)),
+            [DELTA, GAMMA, BETA]
+        );
+    }
+
+    #[test]
+    fn fallback_skips_draining_maintenance_and_rejected_endpoints() {
+        let mut state = state();
+        state
+            .draining
+            .insert(EndpointId::new("gamma"), Drain::new(GAMMA));
+        state.status_of_mut(DELTA).unwrap().maintenance_until = Some(chrono::Utc::now());
+        state.auth_rejected.insert(
+            EndpointId::new("beta"),
+            token_fingerprint("test-token-backup"),
+        );
+        assert!(fallback_candidates(&state, ALPHA, None).is_empty());
+
+        // A rejection of an older token no longer counts
+        state
+            .auth_rejected
+            .insert(EndpointId::new("beta"), token_fingerprint("rotated"));
+        assert_eq!(urls(fallback_candidates(&state, ALPHA, None)), [BETA]);
+    }
+
+    #[test]
+    fn fallback_follows_the_tag_filter() {
+        let mut state = state();
+        state.tag_filter = vec!["eu".to_string()];
+        assert_eq!(urls(fallback_candidates(&state, ALPHA, None)), [GAMMA]);
+    }
+
+    #[test]
+    fn fallback_policy_limits_the_groups() {
+        let mut state = state();
+        state.config.retry.fallback = FallbackPolicy::SameGroup;
+        assert_eq!(
+            urls(fallback_candidates(&state, ALPHA, None)),
+            [DELTA, GAMMA]
+        );
+        assert!(fallback_candidates(&state, BETA, None).is_empty());
+
+        state.config.retry.fallback = FallbackPolicy::Off;
+        assert!(fallback_candidates(&state, ALPHA, None).is_empty());
+    }
+
+    #[test]
+    fn fallback_stays_in_the_listeners_group() {
+        let state = state();
+        assert_eq!(
+            urls(fallback_candidates(&state, ALPHA, Some("backup"))),
+            [BETA]
+        );
+        assert!(fallback_candidates(&state, BETA, Some("backup")).is_empty());
+    }
+
+    #[test]
+    fn unavailable_endpoint_is_rerouted_to_the_first_available_candidate() {
+        let mut state = state();
+        assert!(reroute_unavailable(&state, ALPHA, None).is_none());
+
+        set_status(
+            &mut state,
+            ALPHA,
+            EndpointStatus::new_unavailable(ALPHA.to_string(), "down".to_string()),
+        );
+        set_status(
+            &mut state,
+            BETA,
+            EndpointStatus::new_checking(BETA.to_string()),
+        );
+        let rerouted = reroute_unavailable(&state, ALPHA, None).unwrap();
+        assert_eq!(rerouted.url, DELTA);
+        assert_eq!(rerouted.auth_token, "test-token-main");
+    }
+
+    #[test]
+    fn endpoint_not_yet_judged_is_not_rerouted() {
+        let mut state = state();
+        for status in [
+            EndpointStatus::new_checking(ALPHA.to_string()),
+            EndpointStatus {
+                state: CheckState::Unchecked,
+                ..EndpointStatus::new_checking(ALPHA.to_string())
+            },
+        ] {
+            set_status(&mut state, ALPHA, status);
+            assert!(reroute_unavailable(&state, ALPHA, None).is_none());
+        }
+    }
+}
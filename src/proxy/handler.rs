@@ -0,0 +1,661 @@
+use crate::body_log::{self, BodyKind};
+use crate::config::{AuthMode, Config, FallbackPolicy};
+use crate::connection_tracker::{
+    generate_connection_id, ConnectionId, EventSender, SharedConnectionTracker,
+};
+use crate::env_file;
+use crate::events::{
+    ActiveConnection, CompletionReason, ConnectionStatus, ProxyEvent, RequestInfo, RequestTimings,
+};
+use crate::lock::LockExt;
+use crate::logging::*;
+use crate::metrics::{InFlightRequest, RequestOutcome};
+use crate::storage::RequestRecord;
+use crate::streaming::{ProgressBody, StreamLimits};
+use crate::usage::TokenUsage;
+use hyper::header::{IF_MODIFIED_SINCE, IF_NONE_MATCH};
+use hyper::{Body, Request, Response, StatusCode};
+use std::time::{Duration, Instant};
+
+use super::cache::{with_outcome, CacheKey, CacheOutcome, Lookup, ResponseCache};
+
+use super::drill::{is_drill_request, record_drill_attempt, DRILL_HEADER};
+use super::fallback::{
+    handle_fallback_success, listener_endpoint, mark_endpoint_failed, reroute_unavailable,
+    try_with_fallback_endpoints,
+};
+use super::retry::{
+    retry_request, send_upstream_attempt, HttpsClient, RequestTemplate, ResolvedEndpoint,
+    UpstreamError,
+};
+use super::{RunMode, SharedState};
+
+/// Retry-After sent with a request rejected over the in-flight limit; slots
+/// free up as soon as running requests finish
+const IN_FLIGHT_RETRY_AFTER_SECONDS: u64 = 1;
+
+/// Unified connection cleanup function to ensure proper cleanup in all exit paths,
+/// returning the connection if it was still active
+pub(super) async fn cleanup_connection_on_exit(
+    connection_id: &ConnectionId,
+    connection_tracker: &SharedConnectionTracker,
+    event_sender: &EventSender,
+    reason: CompletionReason,
+    status: StatusCode,
+    timings: RequestTimings,
+) -> Option<ActiveConnection> {
+    let mut tracker = connection_tracker.lock_or_recover();
+    let connection = tracker.complete_connection(connection_id, reason, Some(status.as_u16()))?;
+    tracker.record_timings(&connection.endpoint_id, timings);
+    let _ = event_sender.send(ProxyEvent::ConnectionCompleted {
+        id: connection_id.clone(),
+        reason,
+        phases: Some(connection.phase_durations()),
+        timings: Some(timings),
+    });
+    Some(connection)
+}
+
+/// Body streaming limits for a response from `endpoint`, its body timeout
+/// resolved like [`Config::body_timeout_for`]
+pub(super) fn stream_limits_for(config: &Config, endpoint: &str) -> StreamLimits {
+    let idle_seconds = config.server.stream_idle_timeout_seconds;
+    StreamLimits {
+        total_timeout: config.body_timeout_for(endpoint).duration(),
+        idle_timeout: (idle_seconds > 0).then(|| std::time::Duration::from_secs(idle_seconds)),
+        stall_action: config.server.stream_stall_action,
+    }
+}
+
+/// Proxy a request holding an `in_flight` slot, which is released when the
+/// request completes
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn proxy_handler(
+    req: Request<Body>,
+    state: SharedState,
+    client: HttpsClient,
+    connection_tracker: SharedConnectionTracker,
+    event_sender: EventSender,
+    mode: RunMode,
+    listener_group: Option<&str>,
+    in_flight: InFlightRequest,
+) -> anyhow::Result<Response<Body>> {
+    let accepted_at = Instant::now();
+    let silent_mode = mode.is_silent();
+
+    // Generate unique connection ID
+    let connection_id = generate_connection_id();
+
+    // Get the current endpoint and how to authenticate to it for this request,
+    // or an available endpoint in its place when it is known to be down
+    let (upstream, upstream_id, rerouted_from, is_current, retry_config, metrics) = {
+        let state_guard = state.lock_or_recover();
+        if let Some(retry_after) = state_guard.fail_fast_retry_after() {
+            state_guard.metrics.record_shed_request();
+            drop(state_guard);
+            if !silent_mode {
+                log_request_shed();
+            }
+            return all_endpoints_down(retry_after);
+        }
+        let Some(endpoint) = listener_endpoint(&state_guard, listener_group) else {
+            return no_group_endpoint(listener_group);
+        };
+        let (upstream, rerouted_from) =
+            match reroute_unavailable(&state_guard, &endpoint, listener_group) {
+                Some(alternative) => (alternative, Some(endpoint)),
+                None => (
+                    ResolvedEndpoint::resolve(&state_guard.config, &endpoint),
+                    None,
+                ),
+            };
+        let is_current = state_guard.is_current(&upstream.url);
+        let upstream_id = state_guard.config.endpoint_index().key_of(&upstream.url);
+
+        (
+            upstream,
+            upstream_id,
+            rerouted_from,
+            is_current,
+            state_guard.config.retry.clone(),
+            state_guard.metrics.clone(),
+        )
+    };
+
+    let endpoint_for_request = upstream.url.clone();
+    if let Some(unavailable) = &rerouted_from {
+        if !silent_mode {
+            log_request_rerouted(unavailable, &endpoint_for_request);
+        }
+    }
+
+    // Buffer the request so it can be replayed for retries and fallback
+    let mut template = match RequestTemplate::buffer(req).await {
+        Ok(template) => template,
+        Err(e) => return unreadable_body(e),
+    };
+    // Only the running drill's own request skips its endpoint; the header is
+    // never forwarded
+    let drill = template
+        .headers
+        .remove(DRILL_HEADER)
+        .is_some_and(|token| is_drill_request(&state.lock_or_recover(), &token));
+    if !drill
+        && upstream.auth_mode == AuthMode::Passthrough
+        && template.client_authorization.is_none()
+    {
+        return missing_client_authorization(&endpoint_for_request);
+    }
+    let request_info = RequestInfo {
+        method: template.method.to_string(),
+        path: template.path().to_string(),
+        user_agent: template
+            .headers
+            .get(hyper::header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+    };
+
+    // Start connection tracking and set to processing in single lock acquisition
+    let active_connection = {
+        let mut tracker = connection_tracker.lock_or_recover();
+        let connection = tracker.start_connection(
+            connection_id.clone(),
+            endpoint_for_request.clone(),
+            upstream_id,
+            Some(request_info),
+        );
+        tracker.update_connection_status(&connection_id, ConnectionStatus::Processing);
+        connection
+    };
+
+    // Send connection started event
+    let _ = event_sender.send(ProxyEvent::ConnectionStarted(active_connection));
+
+    // Send request received event for load tracking
+    let _ = event_sender.send(ProxyEvent::RequestReceived {
+        endpoint: endpoint_for_request.clone(),
+        timestamp: std::time::Instant::now(),
+    });
+
+    // Log proxy request only if not in silent mode
+    if !silent_mode {
+        log_proxy_request(&endpoint_for_request, template.body.len(), upstream.timeout);
+        if body_log::enabled() {
+            let logging = state.lock_or_recover().config.logging.clone();
+            body_log::record(&logging, &connection_id, BodyKind::Request, &template.body);
+        }
+    }
+
+    // Forward request with retries - This will block for the entire duration of the AI response
+    // For AI responses, this await can take 30+ seconds for long content generation
+    let dispatched_at = Instant::now();
+    let primary = if drill {
+        Err(UpstreamError::Drill)
+    } else {
+        retry_request(
+            &client,
+            &template,
+            &upstream,
+            &retry_config,
+            &metrics,
+            &connection_id,
+            silent_mode,
+        )
+        .await
+    };
+
+    let (served_by, upstream) = match primary {
+        Ok(resp) => (endpoint_for_request.clone(), Ok(resp)),
+        Err(err) => {
+            if !silent_mode {
+                log_proxy_error(&endpoint_for_request, &err.describe(), Some(&connection_id));
+            }
+
+            // Right after a switch, a failure may only be the new connection's
+            // setup cost, so it takes a few before the endpoint is judged. A
+            // drill's failure is not real and is not counted at all.
+            let excused = !drill
+                && state
+                    .lock_or_recover()
+                    .excuse_failure_in_grace(&endpoint_for_request, chrono::Utc::now());
+            if excused && !silent_mode {
+                log_failure_in_switch_grace(&endpoint_for_request);
+            }
+
+            // Mark the endpoint we actually used as failed
+            if err.marks_endpoint_failed() && !excused {
+                mark_endpoint_failed(&state, &endpoint_for_request, err.describe());
+            }
+
+            let fallback = if retry_config.fallback == FallbackPolicy::Off {
+                None
+            } else {
+                try_with_fallback_endpoints(
+                    &client,
+                    &template,
+                    &endpoint_for_request,
+                    &state,
+                    listener_group,
+                    &connection_id,
+                    silent_mode,
+                )
+                .await
+            };
+
+            // A listener restricted to a group may be served by an endpoint
+            // other than the current one, whose failure moves nothing
+            match fallback {
+                Some((fallback_endpoint, resp)) => {
+                    handle_fallback_success(
+                        &state,
+                        &connection_tracker,
+                        &event_sender,
+                        &connection_id,
+                        &fallback_endpoint,
+                        !excused && !drill && is_current,
+                        silent_mode,
+                    );
+                    (fallback_endpoint, Ok(resp))
+                }
+                None => (endpoint_for_request.clone(), Err(err)),
+            }
+        }
+    };
+
+    // Body transfer and response write are filled in once the body ends
+    let timings = RequestTimings {
+        preprocessing_us: dispatched_at.duration_since(accepted_at).as_micros() as u64,
+        upstream_headers_us: dispatched_at.elapsed().as_micros() as u64,
+        ..RequestTimings::default()
+    };
+
+    if drill {
+        let served = upstream.is_ok().then_some(served_by.as_str());
+        record_drill_attempt(&mut state.lock_or_recover(), served, template.attempts());
+    }
+
+    // A final server error response is still forwarded to the client as-is
+    let upstream = match upstream {
+        Err(UpstreamError::Status(resp)) => Ok(resp),
+        other => other,
+    };
+
+    // Handle all possible outcomes with unified cleanup
+    let result = match upstream {
+        Ok(resp) => {
+            // Response headers received, but AI might still be generating content.
+            // The connection is Finishing while the body streams through and
+            // completes when the body ends or the client goes away
+            connection_tracker
+                .lock_or_recover()
+                .update_connection_status(&connection_id, ConnectionStatus::Finishing);
+            let (parts, body) = resp.into_parts();
+            metrics.record_request(&served_by, RequestOutcome::from_status(parts.status));
+            if parts.status == StatusCode::UNAUTHORIZED {
+                // The token may have been rotated in the env file since it was read
+                env_file::reload(false);
+            }
+            // A fallback's response streams under that endpoint's limits
+            let stream_limits = stream_limits_for(&state.lock_or_recover().config, &served_by);
+            let body = ProgressBody::new(
+                body,
+                &parts,
+                template.retries(),
+                connection_id,
+                served_by,
+                state,
+                connection_tracker,
+                event_sender,
+                stream_limits,
+                timings,
+                in_flight,
+                silent_mode,
+            );
+            Ok(Response::from_parts(parts, body.into_body()))
+        }
+        Err(err) => {
+            // Primary endpoint and every fallback the policy allows failed
+            metrics.record_request(&served_by, RequestOutcome::EndpointError);
+            let (status, reason) = match err {
+                UpstreamError::Timeout(_) => {
+                    metrics.record_request_timeout(&served_by);
+                    (
+                        StatusCode::GATEWAY_TIMEOUT,
+                        CompletionReason::RequestTimeout,
+                    )
+                }
+                _ => (StatusCode::BAD_GATEWAY, CompletionReason::UpstreamError),
+            };
+
+            let completed = cleanup_connection_on_exit(
+                &connection_id,
+                &connection_tracker,
+                &event_sender,
+                reason,
+                status,
+                timings,
+            )
+            .await;
+            if let Some(connection) = completed {
+                state
+                    .lock_or_recover()
+                    .record_request(RequestRecord::completed(
+                        &connection,
+                        status.as_u16(),
+                        template.retries(),
+                        0,
+                        TokenUsage::default(),
+                    ));
+            }
+
+            let scope = match retry_config.fallback {
+                FallbackPolicy::Off => "Endpoint failed with fallback disabled",
+                FallbackPolicy::SameGroup => "All endpoints in the group failed",
+                FallbackPolicy::All => "All endpoints failed",
+            };
+            let error_json = serde_json::json!({
+                "type": "error",
+                "error": {
+                    "type": "proxy_error",
+                    "message": format!(
+                        "{} (fallback: {}); last error from {}: {}",
+                        scope,
+                        retry_config.fallback.as_str(),
+                        served_by,
+                        err.describe()
+                    ),
+                    "fallback_policy": retry_config.fallback,
+                }
+            });
+
+            Ok(Response::builder()
+                .status(status)
+                .header("content-type", "application/json")
+                .body(Body::from(error_json.to_string()))?)
+        }
+    };
+
+    result
+}
+
+/// Forward a request matching a passthrough rule or a `[cache]` path to the
+/// current endpoint with a single attempt: no retries, no fallback and no
+/// connection tracking, and a failure does not mark the endpoint unavailable.
+/// A cacheable request is answered from `cache` while its entry is fresh.
+pub(super) async fn passthrough_handler(
+    req: Request<Body>,
+    state: SharedState,
+    client: HttpsClient,
+    mode: RunMode,
+    listener_group: Option<&str>,
+    cache: &ResponseCache,
+) -> anyhow::Result<Response<Body>> {
+    let (upstream, group, cache_config, retry_config, metrics) = {
+        let state_guard = state.lock_or_recover();
+        let Some(endpoint) = listener_endpoint(&state_guard, listener_group) else {
+            return no_group_endpoint(listener_group);
+        };
+        let config = &state_guard.config;
+        let group = config
+            .endpoint_index()
+            .resolve(&endpoint)
+            .map(|endpoint| endpoint.group.clone())
+            .unwrap_or_default();
+        (
+            ResolvedEndpoint::resolve(config, &endpoint),
+            group,
+            config
+                .cache
+                .is_cacheable(req.method().as_str(), req.uri().path())
+                .then(|| config.cache.clone()),
+            config.retry.clone(),
+            state_guard.metrics.clone(),
+        )
+    };
+
+    let mut template = match RequestTemplate::buffer(req).await {
+        Ok(template) => template,
+        Err(e) => return unreadable_body(e),
+    };
+    let endpoint = upstream.url.as_str();
+    if upstream.auth_mode == AuthMode::Passthrough && template.client_authorization.is_none() {
+        return missing_client_authorization(endpoint);
+    }
+
+    // A response fetched with the client's credentials is never shared
+    let bypass_cache = cache_config.is_some() && upstream.auth_mode != AuthMode::Inject;
+    let cache_key = cache_config.filter(|_| !bypass_cache).map(|cache_config| {
+        let key = CacheKey::new(
+            template.method.as_str(),
+            &template.path_and_query,
+            &group,
+            &template.headers,
+        );
+        (key, cache_config)
+    });
+    let mut revalidating = false;
+    if let Some((key, cache_config)) = &cache_key {
+        match cache.lookup(key, Duration::from_secs(cache_config.ttl_seconds)) {
+            Lookup::Fresh(response) => {
+                metrics.record_cache_lookup(true);
+                if !mode.is_silent() {
+                    log_cache_hit(endpoint, template.method.as_str(), template.path());
+                }
+                return Ok(response);
+            }
+            Lookup::Stale(validators) => {
+                let client_conditional = template.headers.contains_key(IF_NONE_MATCH)
+                    || template.headers.contains_key(IF_MODIFIED_SINCE);
+                if !client_conditional && !validators.is_empty() {
+                    template.headers.extend(validators);
+                    revalidating = true;
+                }
+            }
+            Lookup::Missing => {}
+        }
+    }
+
+    if !mode.is_silent() {
+        log_passthrough_request(endpoint, template.method.as_str(), template.path());
+    }
+    metrics.record_ancillary_request(endpoint);
+
+    match send_upstream_attempt(&client, &template, &upstream, &retry_config).await {
+        Ok(resp) | Err(UpstreamError::Status(resp)) => {
+            if resp.status() == StatusCode::UNAUTHORIZED {
+                env_file::reload(false);
+            }
+            let Some((key, cache_config)) = cache_key else {
+                if bypass_cache {
+                    return Ok(with_outcome(resp, CacheOutcome::Bypass));
+                }
+                return Ok(resp);
+            };
+            if revalidating && resp.status() == StatusCode::NOT_MODIFIED {
+                if let Some(cached) = cache.revalidate(&key) {
+                    metrics.record_cache_lookup(true);
+                    return Ok(cached);
+                }
+            }
+            metrics.record_cache_lookup(false);
+            Ok(cache.store(key, resp, cache_config.max_body_bytes).await)
+        }
+        Err(err) => {
+            if !mode.is_silent() {
+                log_proxy_error(endpoint, &err.describe(), None);
+            }
+            let status = match err {
+                UpstreamError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+                _ => StatusCode::BAD_GATEWAY,
+            };
+            let error_json = serde_json::json!({
+                "type": "error",
+                "error": {
+                    "type": "proxy_error",
+                    "message": format!("Passthrough request to {} failed: {}", endpoint, err.describe()),
+                }
+            });
+            Ok(Response::builder()
+                .status(status)
+                .header("content-type", "application/json")
+                .body(Body::from(error_json.to_string()))?)
+        }
+    }
+}
+
+/// 503 response for a request from a listener whose group has no endpoint
+/// that can serve it
+fn no_group_endpoint(listener_group: Option<&str>) -> anyhow::Result<Response<Body>> {
+    let error_json = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": "proxy_error",
+            "message": format!(
+                "No usable endpoint in group '{}', the only group this port serves",
+                listener_group.unwrap_or_default()
+            ),
+        }
+    });
+    Ok(Response::builder()
+        .status(StatusCode::SERVICE_UNAVAILABLE)
+        .header("content-type", "application/json")
+        .body(Body::from(error_json.to_string()))?)
+}
+
+/// 401 response for a request without an Authorization header to an endpoint
+/// whose group passes the client's header through
+fn missing_client_authorization(endpoint: &str) -> anyhow::Result<Response<Body>> {
+    let error_json = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": "authentication_error",
+            "message": format!(
+                "{endpoint} uses the client's credentials (auth_mode = \"passthrough\") and the request has no Authorization header"
+            ),
+        }
+    });
+    Ok(Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .header("content-type", "application/json")
+        .body(Body::from(error_json.to_string()))?)
+}
+
+/// 503 for a request failed fast while every endpoint is down, telling the
+/// client to come back after the next health check cycle
+fn all_endpoints_down(retry_after: std::time::Duration) -> anyhow::Result<Response<Body>> {
+    let retry_after_seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    let error_json = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": "proxy_error",
+            "message": format!(
+                "All endpoints are down; failing fast until the next health check in {retry_after_seconds}s"
+            ),
+            "retry_after_seconds": retry_after_seconds,
+        }
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::SERVICE_UNAVAILABLE)
+        .header("content-type", "application/json")
+        .header(hyper::header::RETRY_AFTER, retry_after_seconds)
+        .body(Body::from(error_json.to_string()))?)
+}
+
+/// 503 for a request over `server.max_in_flight_requests`, sent without
+/// reading its body
+pub(super) fn too_many_in_flight(limit: usize) -> anyhow::Result<Response<Body>> {
+    let error_json = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": "proxy_error",
+            "message": format!(
+                "Proxy is shedding load: {limit} requests are already in flight"
+            ),
+            "retry_after_seconds": IN_FLIGHT_RETRY_AFTER_SECONDS,
+        }
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::SERVICE_UNAVAILABLE)
+        .header("content-type", "application/json")
+        .header(hyper::header::RETRY_AFTER, IN_FLIGHT_RETRY_AFTER_SECONDS)
+        .body(Body::from(error_json.to_string()))?)
+}
+
+/// 400 response for a request body that could not be read
+fn unreadable_body(e: hyper::Error) -> anyhow::Result<Response<Body>> {
+    Ok(Response::builder()
+        .status(StatusCode::BAD_REQUEST)
+        .body(Body::from(format!("Failed to read request body: {e}")))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TimeoutSource;
+    use crate::test_support::{config, TWO_GROUPS};
+
+    const ALPHA: &str = "http://127.0.0.1:19001";
+    const BETA: &str = "http://127.0.0.1:19002";
+
+    fn body_timeout(config: &Config, endpoint: &str) -> (Duration, TimeoutSource) {
+        (
+            stream_limits_for(config, endpoint).total_timeout,
+            config.body_timeout_for(endpoint).source,
+        )
+    }
+
+    #[test]
+    fn body_timeout_prefers_endpoint_over_group_over_server() {
+        let mut config = config(TWO_GROUPS);
+        config.server.response_body_timeout_seconds = 600;
+        assert_eq!(
+            body_timeout(&config, ALPHA),
+            (Duration::from_secs(600), TimeoutSource::Server)
+        );
+
+        config.groups[0].response_body_timeout_seconds = Some(900);
+        assert_eq!(
+            body_timeout(&config, ALPHA),
+            (Duration::from_secs(900), TimeoutSource::Group)
+        );
+
+        config.groups[0].endpoints[0].response_body_timeout_seconds = Some(1200);
+        assert_eq!(
+            body_timeout(&config, ALPHA),
+            (Duration::from_secs(1200), TimeoutSource::Endpoint)
+        );
+
+        // Another group's overrides do not apply
+        assert_eq!(
+            body_timeout(&config, BETA),
+            (Duration::from_secs(600), TimeoutSource::Server)
+        );
+    }
+
+    #[test]
+    fn body_timeout_ignores_header_timeout_overrides() {
+        let mut config = config(TWO_GROUPS);
+        config.server.response_body_timeout_seconds = 600;
+        config.groups[0].request_timeout_seconds = Some(30);
+        config.groups[0].endpoints[0].request_timeout_seconds = Some(10);
+        assert_eq!(
+            body_timeout(&config, ALPHA),
+            (Duration::from_secs(600), TimeoutSource::Server)
+        );
+        assert_eq!(config.request_timeout_for(ALPHA).seconds, 10);
+    }
+
+    #[test]
+    fn body_timeout_of_an_unknown_endpoint_is_the_server_setting() {
+        let mut config = config(TWO_GROUPS);
+        config.server.response_body_timeout_seconds = 600;
+        config.groups[0].response_body_timeout_seconds = Some(900);
+        assert_eq!(
+            body_timeout(&config, "http://127.0.0.1:19003"),
+            (Duration::from_secs(600), TimeoutSource::Server)
+        );
+    }
+}
@@ -0,0 +1,782 @@
+use crate::config::{Config, ConfigDiff};
+use crate::dynamic_health::IntervalDecision;
+use crate::endpoint::EndpointId;
+use crate::events::{Alert, HealthCycleSummary, SelectionMode, SwitchReason, SwitchRecord};
+use crate::health::{token_fingerprint, CheckState, EndpointStatus};
+use crate::logging::*;
+use crate::metrics::{ProxyMetrics, SharedMetrics};
+use crate::storage::{RequestRecord, RequestRecordSender};
+use crate::token_check::TokenCheck;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+mod admin;
+mod cache;
+mod drill;
+mod event_stream;
+mod fallback;
+mod handler;
+mod retry;
+mod server;
+mod standby;
+
+pub(crate) use admin::{diagnostics_json, status_json, switches_json, StatusQuery};
+pub(crate) use drill::ActiveDrill;
+pub(crate) use event_stream::EventStream;
+pub use fallback::mark_endpoint_failed;
+pub use server::start_proxy_server;
+pub(crate) use server::{ListenerChanges, Listeners};
+
+pub type SharedState = Arc<Mutex<ProxyState>>;
+
+/// Number of endpoint switches kept for /switches and the dashboard timeline
+const SWITCH_HISTORY_SIZE: usize = 100;
+
+/// Failed requests on the endpoint just switched to, within its grace period,
+/// before it is marked failed: the first ones also pay for new connections
+const GRACE_FAILURE_THRESHOLD: u32 = 2;
+
+/// An endpoint left by a switch or removed by a reload, finishing the requests
+/// it still has in flight
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drain {
+    /// URL the draining requests were sent to
+    pub url: String,
+    pub started: Instant,
+}
+
+impl Drain {
+    fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            started: Instant::now(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ProxyState {
+    pub config: Config,
+    /// Health of every configured endpoint, see [`ProxyState::status_of`]
+    pub endpoint_status: HashMap<EndpointId, EndpointStatus>,
+    /// Endpoint requests are routed to, see [`ProxyState::current_url`]
+    pub current_endpoint: EndpointId,
+    /// Why the current endpoint was chosen, `None` for the starting endpoint
+    pub current_endpoint_reason: Option<SwitchReason>,
+    /// When the current endpoint was chosen
+    pub current_endpoint_since: chrono::DateTime<chrono::Utc>,
+    /// End of the current endpoint's grace period after a switch, `None` for
+    /// the starting endpoint or when `switch_grace_seconds` is 0
+    pub grace_until: Option<chrono::DateTime<chrono::Utc>>,
+    /// Failed requests on the current endpoint during its grace period
+    pub grace_failures: u32,
+    pub selection_mode: SelectionMode,
+    /// Most recent endpoint switches, oldest first
+    pub switch_history: VecDeque<SwitchRecord>,
+    /// Lock-free counters, clone the handle out instead of holding the state lock
+    pub metrics: SharedMetrics,
+    /// Only endpoints with one of these tags are auto-selected or used for
+    /// fallback; empty means no filter
+    pub tag_filter: Vec<String>,
+    /// Endpoints left by a switch or removed by a reload that are finishing
+    /// their in-flight requests
+    pub draining: HashMap<EndpointId, Drain>,
+    /// Endpoints whose last health check was rejected for the token, with the
+    /// fingerprint of that token. Scheduled checks and fallback skip them until
+    /// the group's token changes or a manual refresh checks them again.
+    pub auth_rejected: HashMap<EndpointId, u64>,
+    /// Last pre-flight token check of each group by name, when
+    /// `validate_tokens` is on
+    pub token_checks: HashMap<String, TokenCheck>,
+    /// Endpoint kept warm for the next fallback when `server.warm_standby` is
+    /// on, recomputed after every health check cycle
+    pub standby_endpoint: Option<EndpointId>,
+    /// Outcome of the last finished health check cycle
+    pub last_cycle_summary: Option<HealthCycleSummary>,
+    /// Health checks keep running but never switch the current endpoint
+    pub switching_frozen: bool,
+    /// Threshold alerts firing as of the last evaluation
+    pub active_alerts: Vec<Alert>,
+    /// Last health check interval the orchestrator calculated and what from,
+    /// `None` until the first cycle or without a connection tracker
+    pub health_interval: Option<(Duration, IntervalDecision)>,
+    /// When the next health check cycle is due, set while the last full cycle
+    /// found every endpoint down
+    pub all_down_until: Option<Instant>,
+    /// Groups the last full cycle found entirely down, see [`ProxyEvent::GroupDown`]
+    pub down_groups: BTreeSet<String>,
+    /// When this proxy instance was created, for uptime
+    pub started_at: Instant,
+    /// Writer for completed request records, set when `[storage]` is enabled
+    pub(crate) request_records: Option<RequestRecordSender>,
+    /// Events for `/events` clients, fed by the server's event fan-out
+    pub(crate) event_stream: EventStream,
+    /// Failover drill in progress, at most one at a time
+    pub(crate) drill: Option<ActiveDrill>,
+    /// Listeners of the running server, reconciled with the config on reload
+    pub(crate) listeners: Weak<Listeners>,
+    /// Where SIGUSR2 and `/admin/prepare-restart` write the state handoff
+    /// snapshot, set by `--state-handoff`
+    pub(crate) state_handoff: Option<PathBuf>,
+}
+
+impl ProxyState {
+    pub fn new(config: Config) -> Self {
+        let current_endpoint = starting_endpoint(&config);
+
+        let mut endpoint_status = HashMap::new();
+        let metrics = Arc::new(ProxyMetrics::new(&config));
+
+        // Initialize all endpoints as unavailable
+        for (_, endpoint, _) in config.get_all_endpoints() {
+            let Some(id) = config.endpoint_index().id_of(&endpoint.url) else {
+                continue;
+            };
+            endpoint_status.insert(
+                id.clone(),
+                EndpointStatus::new_unchecked(endpoint.url.clone()),
+            );
+        }
+
+        Self {
+            config,
+            endpoint_status,
+            current_endpoint,
+            current_endpoint_reason: None,
+            current_endpoint_since: chrono::Utc::now(),
+            grace_until: None,
+            grace_failures: 0,
+            selection_mode: SelectionMode::Auto, // Start with auto mode
+            switch_history: VecDeque::with_capacity(SWITCH_HISTORY_SIZE),
+            metrics,
+            tag_filter: Vec::new(),
+            draining: HashMap::new(),
+            auth_rejected: HashMap::new(),
+            token_checks: HashMap::new(),
+            standby_endpoint: None,
+            last_cycle_summary: None,
+            switching_frozen: false,
+            active_alerts: Vec::new(),
+            health_interval: None,
+            all_down_until: None,
+            down_groups: BTreeSet::new(),
+            started_at: Instant::now(),
+            request_records: None,
+            event_stream: event_stream::new_event_stream(),
+            drill: None,
+            listeners: Weak::new(),
+            state_handoff: None,
+        }
+    }
+
+    /// Queue a completed request for storage under its endpoint's id, if
+    /// storage is enabled
+    pub(crate) fn record_request(&self, mut record: RequestRecord) {
+        if let Some(sender) = &self.request_records {
+            if let Some(id) = self.config.endpoint_id(&record.endpoint) {
+                record.endpoint = id.to_string();
+            }
+            let _ = sender.send(record);
+        }
+    }
+
+    /// Time until the next health check cycle while requests should fail fast:
+    /// the last full cycle found every endpoint down and none has recovered since
+    pub fn fail_fast_retry_after(&self) -> Option<Duration> {
+        if !self.config.retry.fail_fast_when_all_down {
+            return None;
+        }
+        let next_check = self.all_down_until?;
+        if self
+            .endpoint_status
+            .values()
+            .any(|status| status.is_available())
+        {
+            return None;
+        }
+        Some(next_check.saturating_duration_since(Instant::now()))
+    }
+
+    /// Whether the current endpoint is within its grace period at `now`
+    pub fn in_switch_grace(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.grace_until.is_some_and(|until| now < until)
+    }
+
+    /// Count a failed request on `endpoint` at `now` and decide whether it is
+    /// excused: it hit the endpoint switched to within the grace period, and
+    /// fewer than [`GRACE_FAILURE_THRESHOLD`] requests have failed there since.
+    /// An excused failure neither marks the endpoint failed nor switches away.
+    pub fn excuse_failure_in_grace(
+        &mut self,
+        endpoint: &str,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> bool {
+        if !self.is_current(endpoint) || !self.in_switch_grace(now) {
+            return false;
+        }
+        self.grace_failures += 1;
+        self.grace_failures < GRACE_FAILURE_THRESHOLD
+    }
+
+    /// URL requests to the current endpoint are sent to
+    pub fn current_url(&self) -> &str {
+        self.url_of(&self.current_endpoint)
+    }
+
+    /// Whether the endpoint at `url` is the current one, however the URL is spelled
+    pub fn is_current(&self, url: &str) -> bool {
+        self.config.endpoint_index().id_of(url) == Some(&self.current_endpoint)
+    }
+
+    /// URL of the standby endpoint, see [`ProxyState::standby_endpoint`]
+    pub fn standby_url(&self) -> Option<&str> {
+        self.standby_endpoint.as_ref().map(|id| self.url_of(id))
+    }
+
+    /// Configured URL of the endpoint `id`, or the id itself for one that is
+    /// no longer configured
+    fn url_of<'a>(&'a self, id: &'a EndpointId) -> &'a str {
+        self.config
+            .endpoint_index()
+            .get(id)
+            .map_or(id.as_str(), |endpoint| endpoint.url.as_str())
+    }
+
+    /// Health of the endpoint at `url`, however the URL is spelled
+    pub fn status_of(&self, url: &str) -> Option<&EndpointStatus> {
+        let id = self.config.endpoint_index().id_of(url)?;
+        self.endpoint_status.get(id)
+    }
+
+    pub fn status_of_mut(&mut self, url: &str) -> Option<&mut EndpointStatus> {
+        let id = self.config.endpoint_index().id_of(url)?;
+        self.endpoint_status.get_mut(id)
+    }
+
+    /// Give the endpoint at `url` a clean slate: its latency history, last
+    /// error and token rejection are cleared and it is shown as checking until
+    /// the next check decides its availability. The hourly profile and a
+    /// running maintenance window are kept. Returns the new status, `None` for
+    /// an endpoint that is not configured.
+    pub fn reset_endpoint(&mut self, url: &str) -> Option<EndpointStatus> {
+        let id = self.config.endpoint_index().id_of(url)?.clone();
+        let status = self.endpoint_status.get_mut(&id)?;
+        let mut reset = EndpointStatus::new_checking(status.endpoint.clone());
+        reset.hourly_profile = std::mem::take(&mut status.hourly_profile);
+        if status.maintenance_until.is_some() {
+            reset.maintenance_until = status.maintenance_until;
+            reset.error.clone_from(&status.error);
+        }
+        *status = reset.clone();
+
+        self.auth_rejected.remove(&id);
+        if id == self.current_endpoint {
+            self.grace_failures = 0;
+        }
+        Some(reset)
+    }
+
+    /// Whether `endpoint` is draining after a switch and must not get new requests
+    pub fn is_draining(&self, endpoint: &str) -> bool {
+        self.config
+            .endpoint_index()
+            .id_of(endpoint)
+            .is_some_and(|id| self.draining.contains_key(id))
+    }
+
+    /// Whether `endpoint` rejected `auth_token` in its last health check
+    pub fn is_auth_rejected(&self, endpoint: &str, auth_token: &str) -> bool {
+        self.config
+            .endpoint_index()
+            .id_of(endpoint)
+            .and_then(|id| self.auth_rejected.get(id))
+            == Some(&token_fingerprint(auth_token))
+    }
+
+    /// Whether `endpoint` is inside one of its maintenance windows
+    pub fn in_maintenance(&self, endpoint: &str) -> bool {
+        self.status_of(endpoint)
+            .is_some_and(|status| status.maintenance_until.is_some())
+    }
+
+    /// Leave a current endpoint that entered maintenance for the fastest
+    /// healthy endpoint it may switch to, unless switching is frozen. Without
+    /// one, the next health check cycle fails over from it.
+    pub fn leave_maintenance_endpoint(&mut self, quiet: bool) -> Option<SwitchRecord> {
+        if self.switching_frozen || !self.in_maintenance(self.current_url()) {
+            return None;
+        }
+
+        let candidates = match self.selection_mode {
+            SelectionMode::Auto => self.auto_switch_endpoints(),
+            SelectionMode::Manual => self
+                .config
+                .get_all_endpoints()
+                .into_iter()
+                .map(|(_, endpoint, _)| endpoint.url)
+                .filter(|url| self.matches_tag_filter(url))
+                .collect(),
+        };
+        let best = candidates
+            .into_iter()
+            .filter_map(|endpoint| {
+                let status = self.status_of(&endpoint)?;
+                (status.is_available() && status.maintenance_until.is_none())
+                    .then_some((endpoint, status.latency))
+            })
+            .min_by_key(|(endpoint, latency)| {
+                (self.tier_of(endpoint), latency.unwrap_or(u64::MAX))
+            })?
+            .0;
+        self.switch_endpoint(best, SwitchReason::Maintenance, quiet)
+    }
+
+    /// Whether `endpoint` passes the tag filter; every endpoint does without one
+    pub fn matches_tag_filter(&self, endpoint: &str) -> bool {
+        self.tag_filter.is_empty() || self.config.endpoint_has_any_tag(endpoint, &self.tag_filter)
+    }
+
+    /// Pick the standby endpoint again, `None` unless `server.warm_standby` is
+    /// on. Returns whether it changed.
+    pub fn update_standby(&mut self) -> bool {
+        let standby = if self.config.server.warm_standby {
+            standby::standby_candidate(self)
+        } else {
+            None
+        };
+        let changed = standby != self.standby_endpoint;
+        self.standby_endpoint = standby;
+        changed
+    }
+
+    /// Failover tier of `endpoint`, lower is preferred
+    pub fn tier_of(&self, endpoint: &str) -> u8 {
+        self.config.tier_of(endpoint)
+    }
+
+    /// Most preferred tier with a healthy endpoint Auto mode may switch to
+    pub fn active_tier(&self) -> Option<u8> {
+        self.auto_switch_endpoints()
+            .iter()
+            .filter(|endpoint| {
+                self.status_of(endpoint).is_some_and(|status| {
+                    status.is_available() && status.maintenance_until.is_none()
+                })
+            })
+            .map(|endpoint| self.tier_of(endpoint))
+            .min()
+    }
+
+    /// Whether a tier preferred over `endpoint`'s still has an endpoint that
+    /// is healthy or being checked, so `endpoint` must not take over yet
+    pub fn preferred_tier_may_serve(&self, endpoint: &str) -> bool {
+        let tier = self.tier_of(endpoint);
+        self.auto_switch_endpoints()
+            .iter()
+            .filter(|candidate| self.tier_of(candidate) < tier)
+            .filter_map(|candidate| self.status_of(candidate))
+            .any(|status| {
+                status.maintenance_until.is_none()
+                    && matches!(status.state, CheckState::Healthy | CheckState::Checking)
+            })
+    }
+
+    /// Endpoints Auto mode may switch to: within `auto_switch_scope` and the tag filter
+    pub fn auto_switch_endpoints(&self) -> Vec<String> {
+        self.config
+            .auto_switch_endpoints(self.current_url())
+            .into_iter()
+            .filter(|endpoint| self.matches_tag_filter(endpoint))
+            .collect()
+    }
+
+    /// Replace the tag filter, an empty list clears it.
+    ///
+    /// In Auto mode an excluded current endpoint is left right away for the
+    /// fastest healthy endpoint that passes; without one, the next health check
+    /// cycle moves it.
+    pub fn set_tag_filter(&mut self, tags: Vec<String>, quiet: bool) -> Option<SwitchRecord> {
+        self.tag_filter = tags;
+        if self.selection_mode != SelectionMode::Auto || self.matches_tag_filter(self.current_url())
+        {
+            return None;
+        }
+
+        let best = self
+            .auto_switch_endpoints()
+            .into_iter()
+            .filter_map(|endpoint| {
+                let status = self.status_of(&endpoint)?;
+                status.is_available().then_some((endpoint, status.latency))
+            })
+            .min_by_key(|(endpoint, latency)| {
+                (self.tier_of(endpoint), latency.unwrap_or(u64::MAX))
+            })?
+            .0;
+        self.switch_endpoint(best, SwitchReason::TagFilter, quiet)
+    }
+
+    /// Replace the configuration after a reload, keeping the runtime state of
+    /// every endpoint still configured. Endpoints are matched by id, so one
+    /// whose URL changed keeps its health, counters and drain under the new URL.
+    ///
+    /// New endpoints start out checking. A removed endpoint with requests still
+    /// in `active` drains like one left by a switch. A removed current endpoint
+    /// is left right away for the fastest healthy endpoint, or the starting
+    /// endpoint of the new config without one.
+    pub fn apply_config(
+        &mut self,
+        config: Config,
+        active: &HashMap<EndpointId, u32>,
+        quiet: bool,
+    ) -> (ConfigDiff, Option<SwitchRecord>) {
+        let diff = self.config.diff_endpoints(&config);
+        let old_index = self.config.endpoint_index().clone();
+        let new_index = config.endpoint_index().clone();
+
+        let mut endpoint_status = HashMap::new();
+        for endpoint in new_index.iter() {
+            let status = match self.endpoint_status.remove(&endpoint.id) {
+                Some(mut status) => {
+                    status.endpoint.clone_from(&endpoint.url);
+                    status
+                }
+                None => EndpointStatus::new_unchecked(endpoint.url.clone()),
+            };
+            endpoint_status.insert(endpoint.id.clone(), status);
+        }
+        self.endpoint_status = endpoint_status;
+        self.auth_rejected
+            .retain(|id, _| new_index.get(id).is_some());
+
+        for (id, drain) in &mut self.draining {
+            if let Some(endpoint) = new_index.get(id) {
+                drain.url.clone_from(&endpoint.url);
+            }
+        }
+        for endpoint in old_index.iter() {
+            if new_index.get(&endpoint.id).is_none()
+                && active.get(&endpoint.id).is_some_and(|count| *count > 0)
+            {
+                self.draining
+                    .entry(endpoint.id.clone())
+                    .or_insert_with(|| Drain::new(&endpoint.url));
+            }
+        }
+
+        self.token_checks
+            .retain(|group, _| config.groups.iter().any(|g| &g.name == group));
+
+        self.config = config;
+        self.metrics.reconfigure(&self.config);
+
+        let switch = match new_index.get(&self.current_endpoint) {
+            Some(_) => None,
+            None => {
+                let next = self
+                    .config
+                    .get_all_endpoints()
+                    .into_iter()
+                    .map(|(_, endpoint, _)| endpoint.url)
+                    .filter(|url| self.matches_tag_filter(url))
+                    .filter_map(|url| {
+                        let status = self.status_of(&url)?;
+                        status.is_available().then_some((url, status.latency))
+                    })
+                    .min_by_key(|(url, latency)| (self.tier_of(url), latency.unwrap_or(u64::MAX)))
+                    .map(|(url, _)| url)
+                    .unwrap_or_else(|| starting_url(&self.config));
+                self.switch_endpoint(next, SwitchReason::ConfigReload, quiet)
+            }
+        };
+
+        (diff, switch)
+    }
+
+    /// Switch the active endpoint, recording the reason in the switch history.
+    /// The previous endpoint drains until the drain monitor finishes it.
+    ///
+    /// `quiet` suppresses the console log (dashboard mode). Returns the recorded
+    /// switch, or `None` if `new_endpoint` is already current.
+    pub fn switch_endpoint(
+        &mut self,
+        new_endpoint: String,
+        reason: SwitchReason,
+        quiet: bool,
+    ) -> Option<SwitchRecord> {
+        if self.is_current(&new_endpoint) {
+            return None;
+        }
+
+        let record = self.record_switch(&new_endpoint, reason);
+        if !quiet {
+            log_endpoint_switch(
+                &record.from,
+                &record.to,
+                record.from_latency,
+                record.to_latency,
+                reason,
+            );
+        }
+        let new_id = self.config.endpoint_index().key_of(&new_endpoint);
+        self.draining.remove(&new_id);
+        self.current_endpoint_reason = Some(reason);
+        self.current_endpoint_since = record.timestamp;
+        let grace = Duration::from_secs(self.config.server.switch_grace_seconds);
+        self.grace_until = (!grace.is_zero())
+            .then(|| chrono::Duration::from_std(grace).ok())
+            .flatten()
+            .and_then(|grace| record.timestamp.checked_add_signed(grace));
+        self.grace_failures = 0;
+        let previous_url = self.current_url().to_string();
+        let previous = std::mem::replace(&mut self.current_endpoint, new_id);
+        self.draining.insert(previous, Drain::new(&previous_url));
+
+        Some(record)
+    }
+
+    /// Manually switch to `new_endpoint` although it is down, marked as forced
+    /// in the switch history
+    pub(crate) fn force_switch_endpoint(
+        &mut self,
+        new_endpoint: String,
+        quiet: bool,
+    ) -> Option<SwitchRecord> {
+        let mut record = self.switch_endpoint(new_endpoint, SwitchReason::ManualSwitch, quiet)?;
+        record.forced = true;
+        if let Some(last) = self.switch_history.back_mut() {
+            last.forced = true;
+        }
+        Some(record)
+    }
+
+    /// Append a switch from the current endpoint to the bounded history
+    fn record_switch(&mut self, new_endpoint: &str, reason: SwitchReason) -> SwitchRecord {
+        let record = self.switch_record(self.current_url(), new_endpoint, reason);
+        self.metrics.record_switch(new_endpoint);
+        self.push_switch_history(record.clone());
+        record
+    }
+
+    /// Add a failover drill away from `endpoint` to the history without
+    /// switching or counting a switch
+    pub(crate) fn record_drill(&mut self, endpoint: &str, served_by: Option<&str>) {
+        let record =
+            self.switch_record(endpoint, served_by.unwrap_or_default(), SwitchReason::Drill);
+        self.push_switch_history(record);
+    }
+
+    fn switch_record(&self, from: &str, to: &str, reason: SwitchReason) -> SwitchRecord {
+        // Use the last latency a health check actually measured, even for endpoints
+        // that are failing right now
+        let latency = |endpoint: &str| {
+            self.status_of(endpoint)
+                .and_then(|s| s.last_known_latency())
+        };
+        SwitchRecord {
+            timestamp: chrono::Utc::now(),
+            from: from.to_string(),
+            to: to.to_string(),
+            from_latency: latency(from),
+            to_latency: latency(to),
+            reason,
+            forced: false,
+        }
+    }
+
+    /// Switch history carried over from a previous process, oldest first
+    pub(crate) fn restore_switch_history(&mut self, records: Vec<SwitchRecord>) {
+        for record in records {
+            self.push_switch_history(record);
+        }
+    }
+
+    fn push_switch_history(&mut self, record: SwitchRecord) {
+        self.switch_history.push_back(record);
+        while self.switch_history.len() > SWITCH_HISTORY_SIZE {
+            self.switch_history.pop_front();
+        }
+    }
+}
+
+/// The default endpoint, or the first one with a token
+fn starting_endpoint(config: &Config) -> EndpointId {
+    config.endpoint_index().key_of(&starting_url(config))
+}
+
+/// URL of [`starting_endpoint`]
+fn starting_url(config: &Config) -> String {
+    if let Some((_, default_endpoint)) = config.get_default_endpoint() {
+        default_endpoint.url.clone()
+    } else {
+        config
+            .get_all_endpoints()
+            .first()
+            .map(|(_, endpoint, _)| endpoint.url.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Whether the proxy runs alongside the dashboard, which owns the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunMode {
+    /// Console logs for every request, switch and error
+    Normal,
+    /// No console output; the dashboard shows everything through events
+    Dashboard,
+}
+
+impl RunMode {
+    /// Whether console logging is suppressed
+    pub fn is_silent(self) -> bool {
+        self == RunMode::Dashboard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoint::EndpointIndex;
+    use crate::test_support::{config, TWO_GROUPS};
+
+    const ALPHA: &str = "http://127.0.0.1:19001";
+    const BETA: &str = "http://127.0.0.1:19002";
+
+    fn without_backup() -> Config {
+        let mut config = config(TWO_GROUPS);
+        config.groups.truncate(1);
+        config.endpoint_index = EndpointIndex::new(&config.groups).unwrap();
+        config
+    }
+
+    #[test]
+    fn current_endpoint_follows_a_url_change() {
+        let mut state = ProxyState::new(config(TWO_GROUPS));
+        assert_eq!(state.current_endpoint.as_str(), "alpha");
+
+        let moved = config(&TWO_GROUPS.replace("19001", "19011"));
+        let (_, switch) = state.apply_config(moved, &HashMap::new(), true);
+        assert!(switch.is_none());
+        assert_eq!(state.current_url(), "http://127.0.0.1:19011");
+    }
+
+    #[test]
+    fn drain_follows_a_url_change() {
+        let mut state = ProxyState::new(config(TWO_GROUPS));
+        state.switch_endpoint(BETA.to_string(), SwitchReason::ManualSwitch, true);
+        assert!(state.is_draining(ALPHA));
+        assert!(state.is_draining("HTTP://127.0.0.1:19001/"));
+
+        let moved = config(&TWO_GROUPS.replace("19001", "19011"));
+        state.apply_config(moved, &HashMap::new(), true);
+        assert!(state.is_draining("http://127.0.0.1:19011"));
+        assert_eq!(
+            state.draining[&EndpointId::new("alpha")].url,
+            "http://127.0.0.1:19011"
+        );
+    }
+
+    #[test]
+    fn removed_endpoint_drains_only_with_active_requests() {
+        let beta = EndpointId::new("beta");
+
+        let mut state = ProxyState::new(config(TWO_GROUPS));
+        state.apply_config(without_backup(), &HashMap::new(), true);
+        assert!(!state.draining.contains_key(&beta));
+
+        let mut state = ProxyState::new(config(TWO_GROUPS));
+        let active = HashMap::from([(beta.clone(), 1)]);
+        state.apply_config(without_backup(), &active, true);
+        assert_eq!(state.draining[&beta].url, BETA);
+    }
+
+    #[test]
+    fn switching_back_ends_the_drain() {
+        let mut state = ProxyState::new(config(TWO_GROUPS));
+        state.switch_endpoint(BETA.to_string(), SwitchReason::ManualSwitch, true);
+        state.switch_endpoint(ALPHA.to_string(), SwitchReason::ManualSwitch, true);
+        assert!(!state.is_draining(ALPHA));
+        assert!(state.is_draining(BETA));
+        assert!(state.is_current(ALPHA));
+    }
+
+    /// A state just switched to beta, with a 30s grace period, and when the
+    /// switch happened
+    fn switched_to_beta() -> (ProxyState, chrono::DateTime<chrono::Utc>) {
+        let mut config = config(TWO_GROUPS);
+        config.server.switch_grace_seconds = 30;
+        let mut state = ProxyState::new(config);
+        let record = state
+            .switch_endpoint(BETA.to_string(), SwitchReason::Failover, true)
+            .unwrap();
+        (state, record.timestamp)
+    }
+
+    fn seconds(seconds: i64) -> chrono::Duration {
+        chrono::Duration::seconds(seconds)
+    }
+
+    #[test]
+    fn no_grace_before_the_first_switch() {
+        let mut state = ProxyState::new(config(TWO_GROUPS));
+        assert!(!state.in_switch_grace(chrono::Utc::now()));
+        assert!(!state.excuse_failure_in_grace(ALPHA, chrono::Utc::now()));
+    }
+
+    #[test]
+    fn failures_inside_the_grace_period_are_excused_up_to_the_threshold() {
+        let (mut state, switched_at) = switched_to_beta();
+        let inside = switched_at + seconds(29);
+        assert!(state.in_switch_grace(inside));
+
+        assert!(state.excuse_failure_in_grace(BETA, switched_at + seconds(1)));
+        // The second failure is judged, even inside the grace period
+        assert!(!state.excuse_failure_in_grace(BETA, inside));
+        assert!(!state.excuse_failure_in_grace(BETA, inside));
+        assert_eq!(state.grace_failures, GRACE_FAILURE_THRESHOLD + 1);
+    }
+
+    #[test]
+    fn failures_after_the_grace_period_are_not_excused() {
+        let (mut state, switched_at) = switched_to_beta();
+        for after in [switched_at + seconds(30), switched_at + seconds(3600)] {
+            assert!(!state.in_switch_grace(after));
+            assert!(!state.excuse_failure_in_grace(BETA, after));
+        }
+        assert_eq!(state.grace_failures, 0);
+    }
+
+    #[test]
+    fn only_the_endpoint_switched_to_is_excused() {
+        let (mut state, switched_at) = switched_to_beta();
+        assert!(!state.excuse_failure_in_grace(ALPHA, switched_at + seconds(1)));
+        assert_eq!(state.grace_failures, 0);
+    }
+
+    #[test]
+    fn each_switch_starts_a_new_grace_period() {
+        let (mut state, switched_at) = switched_to_beta();
+        assert!(state.excuse_failure_in_grace(BETA, switched_at + seconds(1)));
+        assert!(!state.excuse_failure_in_grace(BETA, switched_at + seconds(2)));
+
+        let record = state
+            .switch_endpoint(ALPHA.to_string(), SwitchReason::Failover, true)
+            .unwrap();
+        assert_eq!(state.grace_failures, 0);
+        assert!(state.excuse_failure_in_grace(ALPHA, record.timestamp + seconds(1)));
+    }
+
+    #[test]
+    fn zero_grace_disables_the_grace_period() {
+        let mut config = config(TWO_GROUPS);
+        config.server.switch_grace_seconds = 0;
+        let mut state = ProxyState::new(config);
+        let record = state
+            .switch_endpoint(BETA.to_string(), SwitchReason::Failover, true)
+            .unwrap();
+        assert!(state.grace_until.is_none());
+        assert!(!state.excuse_failure_in_grace(BETA, record.timestamp));
+    }
+}
@@ -0,0 +1,657 @@
+use crate::config::{AuthMode, Config, RequestTimeout, RetryConfig};
+use crate::connection_tracker::ConnectionId;
+use crate::logging::*;
+use crate::metrics::{ProxyMetrics, RequestOutcome};
+use hyper::body::Bytes;
+use hyper::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONNECTION, HOST};
+use hyper::{Body, Client, Method, Request, Response, StatusCode, Uri, Version};
+use hyper_tls::HttpsConnector;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+pub(super) type HttpsClient = Client<HttpsConnector<hyper::client::HttpConnector>>;
+
+/// Headers that only apply to the connection they arrive on and are never
+/// forwarded upstream, besides any the client's `connection` header names
+const HOP_BY_HOP_HEADERS: [&str; 8] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "upgrade",
+];
+
+/// An endpoint a request can be sent to, with how to authenticate to it
+#[derive(Debug, Clone, Default)]
+pub(super) struct ResolvedEndpoint {
+    pub(super) url: String,
+    /// Token of the endpoint's group
+    pub(super) auth_token: String,
+    pub(super) auth_mode: AuthMode,
+    /// How long an attempt waits for the response headers
+    pub(super) timeout: RequestTimeout,
+}
+
+impl ResolvedEndpoint {
+    /// `url` with the credentials its group configures, none for an unknown
+    /// endpoint
+    pub(super) fn resolve(config: &Config, url: &str) -> Self {
+        let auth_token = config
+            .get_all_endpoints()
+            .into_iter()
+            .find(|(_, endpoint, _)| endpoint.url == url)
+            .map(|(token, _, _)| token)
+            .unwrap_or_default();
+        Self {
+            url: url.to_string(),
+            auth_token,
+            auth_mode: config.auth_mode_for(url),
+            timeout: config.request_timeout_for(url),
+        }
+    }
+}
+
+/// Buffered copy of an incoming request that can be replayed against any endpoint
+pub(super) struct RequestTemplate {
+    pub(super) method: Method,
+    pub(super) version: Version,
+    /// Client headers without `host` and `authorization`, which every attempt
+    /// sets for its endpoint
+    pub(super) headers: HeaderMap,
+    /// The client's own `authorization` header, sent instead of the group
+    /// token by endpoints whose group's [`AuthMode`] allows it
+    pub(super) client_authorization: Option<HeaderValue>,
+    pub(super) path_and_query: String,
+    /// Reference counted, so attempts share it instead of copying the body
+    pub(super) body: Bytes,
+    /// Upstream attempts made so far, on any endpoint
+    attempts: AtomicU32,
+}
+
+impl RequestTemplate {
+    /// Buffer an incoming request so it can be sent more than once
+    pub(super) async fn buffer(req: Request<Body>) -> Result<Self, hyper::Error> {
+        let (mut parts, body) = req.into_parts();
+        let body = hyper::body::to_bytes(body).await?;
+        parts.headers.remove(HOST);
+        let client_authorization = parts.headers.remove(AUTHORIZATION);
+        Ok(Self {
+            path_and_query: parts
+                .uri
+                .path_and_query()
+                .map(|x| x.as_str().to_string())
+                .unwrap_or_default(),
+            method: parts.method,
+            version: parts.version,
+            headers: parts.headers,
+            client_authorization,
+            body,
+            attempts: AtomicU32::new(0),
+        })
+    }
+
+    /// Request path without the query string
+    pub(super) fn path(&self) -> &str {
+        self.path_and_query
+            .split_once('?')
+            .map_or(self.path_and_query.as_str(), |(path, _)| path)
+    }
+
+    /// Upstream attempts made so far, on any endpoint
+    pub(super) fn attempts(&self) -> u32 {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    /// Attempts beyond the first, across retries and fallback endpoints
+    pub(super) fn retries(&self) -> u32 {
+        self.attempts().saturating_sub(1)
+    }
+}
+
+/// Build the request one attempt sends to `endpoint`. This is the only place
+/// upstream requests are made, so the primary endpoint, its retries and the
+/// fallback endpoints all get the same treatment: the path is appended to the
+/// endpoint URL, `host` is set to the URL's authority, hop-by-hop headers are
+/// dropped and `authorization` follows the group's [`AuthMode`].
+pub(super) fn build_upstream_request(
+    template: &RequestTemplate,
+    endpoint: &ResolvedEndpoint,
+) -> anyhow::Result<Request<Body>> {
+    let uri: Uri = format!(
+        "{}{}",
+        endpoint.url.trim_end_matches('/'),
+        template.path_and_query
+    )
+    .parse()
+    .map_err(|e| anyhow::anyhow!("Invalid URI: {}", e))?;
+    let host = uri
+        .authority()
+        .ok_or_else(|| anyhow::anyhow!("Endpoint URL has no host: {}", endpoint.url))?
+        .as_str()
+        .parse()?;
+
+    let mut headers = template.headers.clone();
+    let named_by_connection: Vec<String> = headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+    for name in HOP_BY_HOP_HEADERS
+        .iter()
+        .copied()
+        .chain(named_by_connection.iter().map(String::as_str))
+    {
+        headers.remove(name);
+    }
+    headers.insert(HOST, host);
+
+    // Forward the client's Authorization header or add one with the auth
+    // token from config, as the endpoint's group wants
+    let client_authorization = match endpoint.auth_mode {
+        AuthMode::Inject => None,
+        AuthMode::Passthrough => Some(
+            template
+                .client_authorization
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("No client Authorization header to pass through"))?,
+        ),
+        AuthMode::PreferClient => template.client_authorization.clone(),
+    };
+    if let Some(auth_header) = client_authorization {
+        headers.insert(AUTHORIZATION, auth_header);
+    } else if !endpoint.auth_token.is_empty() {
+        let auth_value = format!("Bearer {}", endpoint.auth_token);
+        if let Ok(auth_header) = auth_value.parse() {
+            headers.insert(AUTHORIZATION, auth_header);
+        }
+    }
+
+    let mut request = Request::new(Body::from(template.body.clone()));
+    *request.method_mut() = template.method.clone();
+    *request.uri_mut() = uri;
+    *request.version_mut() = template.version;
+    *request.headers_mut() = headers;
+    Ok(request)
+}
+
+/// Why an upstream attempt did not produce a usable response
+pub(super) enum UpstreamError {
+    /// Connection-level failure
+    Http(hyper::Error),
+    /// No response headers within the endpoint's request timeout
+    Timeout(RequestTimeout),
+    /// The endpoint answered with a status that is retried or sent to a
+    /// fallback, see [`status_action`]
+    Status(Response<Body>),
+    /// The request could not be built for this endpoint
+    Build(anyhow::Error),
+    /// Not sent: a failover drill treats the endpoint as failed
+    Drill,
+}
+
+impl UpstreamError {
+    pub(super) fn describe(&self) -> String {
+        match self {
+            UpstreamError::Http(e) => format!("HTTP error: {e}"),
+            UpstreamError::Timeout(timeout) => {
+                format!("No response headers: timed out after {timeout}")
+            }
+            UpstreamError::Status(resp) => format!("Upstream status {}", resp.status()),
+            UpstreamError::Build(e) => format!("Request build error: {e}"),
+            UpstreamError::Drill => "Treated as failed for a failover drill".to_string(),
+        }
+    }
+
+    /// Whether the failure says the endpoint itself is unreachable
+    pub(super) fn marks_endpoint_failed(&self) -> bool {
+        matches!(self, UpstreamError::Http(_) | UpstreamError::Timeout(_))
+    }
+}
+
+/// What to do with an upstream response, decided by its status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum StatusAction {
+    /// Forward the response to the client as it is
+    Return,
+    /// Retry on the same endpoint with backoff, then fall back
+    Retry,
+    /// Skip the remaining retries and go straight to fallback
+    Fallback,
+}
+
+/// Classify `status` by the `[retry]` status lists. Without
+/// `retry_on_status`, the statuses that blame the endpoint (408, 429 and
+/// server errors) are retried.
+pub(super) fn status_action(status: StatusCode, retry_config: &RetryConfig) -> StatusAction {
+    let code = status.as_u16();
+    if retry_config.fallback_on_status.contains(&code) {
+        return StatusAction::Fallback;
+    }
+    let retried = match &retry_config.retry_on_status {
+        Some(statuses) => statuses.contains(&code),
+        None => RequestOutcome::from_status(status) == RequestOutcome::EndpointError,
+    };
+    if retried {
+        StatusAction::Retry
+    } else {
+        StatusAction::Return
+    }
+}
+
+/// Exponential backoff delay before retry number `attempt` (1-based)
+pub(super) fn calculate_backoff_delay(
+    attempt: u32,
+    retry_config: &RetryConfig,
+) -> std::time::Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    let delay = retry_config.base_delay_ms.saturating_mul(1u64 << exponent);
+    std::time::Duration::from_millis(delay.min(retry_config.max_delay_ms))
+}
+
+/// Send a single attempt to `endpoint`, treating responses that are retried
+/// or sent to a fallback as failures. Every other response, such as a 4xx
+/// that is the client's fault, is returned for forwarding. Only the wait for
+/// the response headers is bounded by the endpoint's timeout; the body is limited
+/// while it streams, see [`crate::streaming::StreamLimits`].
+pub(super) async fn send_upstream_attempt(
+    client: &HttpsClient,
+    template: &RequestTemplate,
+    endpoint: &ResolvedEndpoint,
+    retry_config: &RetryConfig,
+) -> Result<Response<Body>, UpstreamError> {
+    let request = build_upstream_request(template, endpoint).map_err(UpstreamError::Build)?;
+    template.attempts.fetch_add(1, Ordering::Relaxed);
+
+    match tokio::time::timeout(endpoint.timeout.duration(), client.request(request)).await {
+        Ok(Ok(resp)) if status_action(resp.status(), retry_config) != StatusAction::Return => {
+            Err(UpstreamError::Status(resp))
+        }
+        Ok(Ok(resp)) => Ok(resp),
+        Ok(Err(e)) => Err(UpstreamError::Http(e)),
+        Err(_) => Err(UpstreamError::Timeout(endpoint.timeout)),
+    }
+}
+
+/// Send a request to one endpoint, retrying failures with exponential backoff.
+/// A failure here always happened before any of the response reached the
+/// client, so the request is safe to replay.
+pub(super) async fn retry_request(
+    client: &HttpsClient,
+    template: &RequestTemplate,
+    upstream: &ResolvedEndpoint,
+    retry_config: &RetryConfig,
+    metrics: &ProxyMetrics,
+    connection_id: &ConnectionId,
+    silent_mode: bool,
+) -> Result<Response<Body>, UpstreamError> {
+    let endpoint = upstream.url.as_str();
+    let max_attempts = retry_config.max_retries + 1;
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+
+        match send_upstream_attempt(client, template, upstream, retry_config).await {
+            Ok(resp) => {
+                if attempt > 1 {
+                    metrics.record_retry_success(endpoint);
+                    if !silent_mode {
+                        log_retry_success(endpoint, attempt - 1, connection_id);
+                    }
+                }
+                return Ok(resp);
+            }
+            Err(UpstreamError::Build(e)) => return Err(UpstreamError::Build(e)),
+            Err(UpstreamError::Status(resp))
+                if status_action(resp.status(), retry_config) == StatusAction::Fallback =>
+            {
+                if !silent_mode {
+                    log_retry_skipped(endpoint, resp.status().as_u16(), connection_id);
+                }
+                return Err(UpstreamError::Status(resp));
+            }
+            Err(err) if attempt >= max_attempts => {
+                if max_attempts > 1 {
+                    metrics.record_retry_exhausted(endpoint);
+                    if !silent_mode {
+                        log_retry_exhausted(endpoint, attempt, &err.describe(), connection_id);
+                    }
+                }
+                return Err(err);
+            }
+            Err(err) => {
+                let delay = calculate_backoff_delay(attempt, retry_config);
+                metrics.record_retry_attempt(endpoint);
+                if !silent_mode {
+                    log_retry_attempt(
+                        endpoint,
+                        attempt,
+                        max_attempts - 1,
+                        &err.describe(),
+                        connection_id,
+                    );
+                    log_retry_delay(endpoint, delay.as_millis() as u64, connection_id);
+                }
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn backoff(base_delay_ms: u64, max_delay_ms: u64) -> RetryConfig {
+        RetryConfig {
+            base_delay_ms,
+            max_delay_ms,
+            ..RetryConfig::default()
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_with_each_retry() {
+        let config = backoff(100, 60_000);
+        let delays: Vec<_> = (1..=5)
+            .map(|attempt| calculate_backoff_delay(attempt, &config))
+            .collect();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1600].map(Duration::from_millis)
+        );
+    }
+
+    #[test]
+    fn backoff_is_capped_at_the_max_delay() {
+        let config = backoff(100, 500);
+        assert_eq!(
+            calculate_backoff_delay(3, &config),
+            Duration::from_millis(400)
+        );
+        assert_eq!(
+            calculate_backoff_delay(4, &config),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            calculate_backoff_delay(64, &config),
+            Duration::from_millis(500)
+        );
+        // The exponent is clamped, so huge attempt numbers cannot overflow
+        assert_eq!(
+            calculate_backoff_delay(u32::MAX, &config),
+            Duration::from_millis(500)
+        );
+        let config = backoff(u64::MAX / 2, u64::MAX);
+        assert_eq!(
+            calculate_backoff_delay(20, &config),
+            Duration::from_millis(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn backoff_stays_between_base_and_max() {
+        for config in [backoff(100, 5_000), backoff(1, 1), backoff(1_000, 300)] {
+            let floor = config.base_delay_ms.min(config.max_delay_ms);
+            let mut previous = Duration::ZERO;
+            for attempt in 0..=40 {
+                let delay = calculate_backoff_delay(attempt, &config);
+                assert!(
+                    delay >= Duration::from_millis(floor)
+                        && delay <= Duration::from_millis(config.max_delay_ms),
+                    "attempt {attempt}: {delay:?} outside {floor}..={}ms",
+                    config.max_delay_ms
+                );
+                assert!(
+                    delay >= previous,
+                    "attempt {attempt} waits less than the one before"
+                );
+                previous = delay;
+            }
+        }
+    }
+
+    #[test]
+    fn endpoint_errors_are_retried_by_default() {
+        let config = RetryConfig::default();
+        for code in [408, 429, 500, 502, 503, 504, 529] {
+            let status = StatusCode::from_u16(code).unwrap();
+            assert_eq!(
+                status_action(status, &config),
+                StatusAction::Retry,
+                "{code}"
+            );
+        }
+    }
+
+    #[test]
+    fn other_statuses_are_returned_by_default() {
+        let config = RetryConfig::default();
+        for code in [200, 201, 204, 304, 400, 401, 403, 404, 413, 422] {
+            let status = StatusCode::from_u16(code).unwrap();
+            assert_eq!(
+                status_action(status, &config),
+                StatusAction::Return,
+                "{code}"
+            );
+        }
+    }
+
+    fn status_lists(retry_on_status: Option<&[u16]>, fallback_on_status: &[u16]) -> RetryConfig {
+        RetryConfig {
+            retry_on_status: retry_on_status.map(<[u16]>::to_vec),
+            fallback_on_status: fallback_on_status.to_vec(),
+            ..RetryConfig::default()
+        }
+    }
+
+    fn action(code: u16, config: &RetryConfig) -> StatusAction {
+        status_action(StatusCode::from_u16(code).unwrap(), config)
+    }
+
+    #[test]
+    fn status_decision_matrix() {
+        use StatusAction::{Fallback, Retry, Return};
+        let retry_list: &[u16] = &[500, 502, 503, 504];
+        let configs = [
+            ("defaults", status_lists(None, &[])),
+            ("fallback only", status_lists(None, &[529, 503])),
+            ("retry only", status_lists(Some(retry_list), &[])),
+            ("both", status_lists(Some(retry_list), &[529, 503])),
+            ("retry nothing", status_lists(Some(&[]), &[])),
+        ];
+        // Expected action per config above, in the same order
+        let matrix: [(u16, [StatusAction; 5]); 9] = [
+            (200, [Return, Return, Return, Return, Return]),
+            (400, [Return, Return, Return, Return, Return]),
+            (408, [Retry, Retry, Return, Return, Return]),
+            (429, [Retry, Retry, Return, Return, Return]),
+            (500, [Retry, Retry, Retry, Retry, Return]),
+            (502, [Retry, Retry, Retry, Retry, Return]),
+            (503, [Retry, Fallback, Retry, Fallback, Return]),
+            (504, [Retry, Retry, Retry, Retry, Return]),
+            (529, [Retry, Fallback, Return, Fallback, Return]),
+        ];
+        for (code, expected) in matrix {
+            for ((name, config), expected) in configs.iter().zip(expected) {
+                assert_eq!(action(code, config), expected, "{code} with {name}");
+            }
+        }
+    }
+
+    #[test]
+    fn fallback_list_wins_over_retry_list() {
+        let config = status_lists(Some(&[503]), &[503]);
+        assert_eq!(action(503, &config), StatusAction::Fallback);
+    }
+
+    #[test]
+    fn listed_statuses_need_not_be_errors() {
+        let config = status_lists(Some(&[404]), &[409]);
+        assert_eq!(action(404, &config), StatusAction::Retry);
+        assert_eq!(action(409, &config), StatusAction::Fallback);
+    }
+
+    async fn template(client_authorization: Option<&str>) -> RequestTemplate {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri("http://127.0.0.1:8080/v1/messages?beta=true")
+            .header(HOST, "127.0.0.1:8080")
+            .header("anthropic-version", "2023-06-01")
+            .header(CONNECTION, "keep-alive, x-session-hint")
+            .header("keep-alive", "timeout=5")
+            .header("x-session-hint", "abc")
+            .header("te", "trailers")
+            .header("proxy-authorization", "Basic cHJveHk=");
+        if let Some(authorization) = client_authorization {
+            request = request.header(AUTHORIZATION, authorization);
+        }
+        RequestTemplate::buffer(request.body(Body::from("{}")).unwrap())
+            .await
+            .unwrap()
+    }
+
+    fn endpoint(url: &str, auth_mode: AuthMode) -> ResolvedEndpoint {
+        ResolvedEndpoint {
+            url: url.to_string(),
+            auth_token: "group-token".to_string(),
+            auth_mode,
+            timeout: RequestTimeout::default(),
+        }
+    }
+
+    fn authorization(request: &Request<Body>) -> Option<&str> {
+        request
+            .headers()
+            .get(AUTHORIZATION)
+            .map(|value| value.to_str().unwrap())
+    }
+
+    #[tokio::test]
+    async fn upstream_request_keeps_the_endpoints_port_and_path() {
+        let template = template(None).await;
+        for (url, uri, host) in [
+            (
+                "https://api.example.com",
+                "https://api.example.com/v1/messages?beta=true",
+                "api.example.com",
+            ),
+            (
+                "https://relay.example.com:8443/claude/",
+                "https://relay.example.com:8443/claude/v1/messages?beta=true",
+                "relay.example.com:8443",
+            ),
+            (
+                "http://10.0.0.5:3000/api",
+                "http://10.0.0.5:3000/api/v1/messages?beta=true",
+                "10.0.0.5:3000",
+            ),
+        ] {
+            let request =
+                build_upstream_request(&template, &endpoint(url, AuthMode::Inject)).unwrap();
+            assert_eq!(request.uri(), uri, "{url}");
+            assert_eq!(request.headers()[HOST], host, "{url}");
+            assert_eq!(request.method(), Method::POST);
+        }
+    }
+
+    #[tokio::test]
+    async fn upstream_request_drops_hop_by_hop_headers() {
+        let template = template(None).await;
+        let request = build_upstream_request(
+            &template,
+            &endpoint("https://api.example.com", AuthMode::Inject),
+        )
+        .unwrap();
+        let headers = request.headers();
+        for dropped in [
+            "connection",
+            "keep-alive",
+            "te",
+            "proxy-authorization",
+            "x-session-hint",
+        ] {
+            assert!(!headers.contains_key(dropped), "{dropped} was forwarded");
+        }
+        assert_eq!(headers["anthropic-version"], "2023-06-01");
+    }
+
+    #[tokio::test]
+    async fn upstream_authorization_follows_the_auth_mode() {
+        let url = "https://api.example.com";
+        let with_client = template(Some("Bearer client-key")).await;
+        let without_client = template(None).await;
+
+        let cases = [
+            (AuthMode::Inject, &with_client, Some("Bearer group-token")),
+            (
+                AuthMode::Inject,
+                &without_client,
+                Some("Bearer group-token"),
+            ),
+            (
+                AuthMode::Passthrough,
+                &with_client,
+                Some("Bearer client-key"),
+            ),
+            (
+                AuthMode::PreferClient,
+                &with_client,
+                Some("Bearer client-key"),
+            ),
+            (
+                AuthMode::PreferClient,
+                &without_client,
+                Some("Bearer group-token"),
+            ),
+        ];
+        for (mode, template, expected) in cases {
+            let request = build_upstream_request(template, &endpoint(url, mode)).unwrap();
+            assert_eq!(authorization(&request), expected, "{mode:?}");
+        }
+
+        assert!(
+            build_upstream_request(&without_client, &endpoint(url, AuthMode::Passthrough)).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_endpoint_gets_no_token() {
+        let template = template(None).await;
+        let unknown = ResolvedEndpoint {
+            url: "https://api.example.com".to_string(),
+            ..ResolvedEndpoint::default()
+        };
+        let request = build_upstream_request(&template, &unknown).unwrap();
+        assert_eq!(authorization(&request), None);
+    }
+
+    #[tokio::test]
+    async fn endpoint_without_a_host_is_rejected() {
+        let template = template(None).await;
+        assert!(
+            build_upstream_request(&template, &endpoint("not a url", AuthMode::Inject)).is_err()
+        );
+    }
+
+    #[test]
+    fn only_unreachable_endpoints_are_marked_failed() {
+        let timeout = UpstreamError::Timeout(RequestTimeout::default());
+        assert!(timeout.marks_endpoint_failed());
+        let status = UpstreamError::Status(
+            Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .body(Body::empty())
+                .unwrap(),
+        );
+        assert!(!status.marks_endpoint_failed());
+        assert!(!UpstreamError::Build(anyhow::anyhow!("no host")).marks_endpoint_failed());
+        assert!(!UpstreamError::Drill.marks_endpoint_failed());
+    }
+}
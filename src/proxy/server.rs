@@ -0,0 +1,630 @@
+use crate::config::{Config, ListenerConfig};
+use crate::connection_tracker::{EventSender, SharedConnectionTracker};
+use crate::events::ProxyEvent;
+use crate::health_orchestrator::HealthOrchestratorHandle;
+use crate::lock::{LockExt, RwLockExt};
+use crate::logging::*;
+use crate::metrics::SharedMetrics;
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Client, Method, Request, Response, StatusCode};
+use hyper_tls::HttpsConnector;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{watch, Semaphore};
+use tracing::{debug, error};
+
+use super::admin::{
+    config_handler, connections_handler, diagnostics_handler, endpoint_reset_handler,
+    export_csv_handler, filter_handler, freeze_handler, health_handler, history_handler,
+    metrics_handler, orchestrator_handler, prepare_restart_handler, profile_handler,
+    recent_connections_handler, refresh_handler, simulate_selection_handler, status_handler,
+    switches_handler, version_handler,
+};
+use super::cache::ResponseCache;
+use super::drill::drill_handler;
+use super::event_stream::events_handler;
+use super::handler::{passthrough_handler, proxy_handler, too_many_in_flight};
+use super::retry::HttpsClient;
+use super::standby::keep_standby_warm;
+use super::{RunMode, SharedState};
+
+/// What the connections of every listener share
+#[derive(Clone)]
+struct ServerContext {
+    state: SharedState,
+    client: HttpsClient,
+    connection_tracker: SharedConnectionTracker,
+    event_sender: EventSender,
+    orchestrator: HealthOrchestratorHandle,
+    mode: RunMode,
+    metrics: SharedMetrics,
+    cache: Arc<ResponseCache>,
+    http: Http,
+    header_timeout: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    /// One pool for all listeners, so the limit covers the whole proxy
+    connection_slots: Option<Arc<Semaphore>>,
+}
+
+/// Group a listener is limited to, replaced in place when a reload changes it
+type ListenerGroup = Arc<RwLock<Option<Arc<str>>>>;
+
+/// The listeners being served, reconciled with the config on every reload
+pub(crate) struct Listeners {
+    context: ServerContext,
+    running: Mutex<Vec<RunningListener>>,
+}
+
+struct RunningListener {
+    config: ListenerConfig,
+    group: ListenerGroup,
+    /// Set to true to stop accepting and drain the listener's connections
+    closing: watch::Sender<bool>,
+}
+
+/// Listener URLs a reload started and stopped serving on
+#[derive(Debug, Default)]
+pub(crate) struct ListenerChanges {
+    pub opened: Vec<String>,
+    pub closed: Vec<String>,
+}
+
+impl Listeners {
+    /// Serve `desired` instead of the running listeners. New addresses are
+    /// bound and served before removed ones close, so the proxy keeps
+    /// accepting throughout. If any new address cannot be bound, nothing
+    /// changes and the error is returned.
+    pub(crate) fn reconcile(&self, desired: &[ListenerConfig]) -> Result<ListenerChanges, String> {
+        let mut running = self.running.lock_or_recover();
+        let address = |config: &ListenerConfig| SocketAddr::new(config.bind_address, config.port);
+
+        // Bind everything new first; dropping them on an error closes them again
+        let mut bound = Vec::new();
+        for config in desired {
+            if running
+                .iter()
+                .any(|r| address(&r.config) == address(config))
+            {
+                continue;
+            }
+            let listener = std::net::TcpListener::bind(address(config))
+                .and_then(|listener| {
+                    listener.set_nonblocking(true)?;
+                    TcpListener::from_std(listener)
+                })
+                .map_err(|e| format!("{} ({e})", config.url()))?;
+            bound.push((listener, config.clone()));
+        }
+
+        let mut changes = ListenerChanges::default();
+        for (listener, config) in bound {
+            changes.opened.push(config.url());
+            running.push(self.serve(listener, config));
+        }
+
+        // A listener kept on the same address only changes the group it serves
+        for listener in running.iter_mut() {
+            if let Some(config) = desired
+                .iter()
+                .find(|config| address(config) == address(&listener.config))
+            {
+                *listener.group.write_or_recover() = config.group.as_deref().map(Arc::from);
+                listener.config = config.clone();
+            }
+        }
+
+        running.retain(|listener| {
+            let keep = desired
+                .iter()
+                .any(|config| address(config) == address(&listener.config));
+            if !keep {
+                changes.closed.push(listener.config.url());
+                let _ = listener.closing.send(true);
+            }
+            keep
+        });
+        Ok(changes)
+    }
+
+    /// Start accepting on a bound listener
+    fn serve(&self, listener: TcpListener, config: ListenerConfig) -> RunningListener {
+        let group = Arc::new(RwLock::new(config.group.as_deref().map(Arc::from)));
+        let (closing, closing_receiver) = watch::channel(false);
+        tokio::spawn(accept_loop(
+            listener,
+            group.clone(),
+            closing_receiver,
+            self.context.clone(),
+        ));
+        RunningListener {
+            config,
+            group,
+            closing,
+        }
+    }
+
+    fn close_all(&self) {
+        for listener in self.running.lock_or_recover().drain(..) {
+            let _ = listener.closing.send(true);
+        }
+    }
+}
+
+/// Closes every listener when the server future is dropped
+struct CloseListenersOnDrop(Arc<Listeners>);
+
+impl Drop for CloseListenersOnDrop {
+    fn drop(&mut self) {
+        self.0.close_all();
+    }
+}
+
+/// Start the proxy server with event and connection tracking, on
+/// `server.port` and every `[[server.listeners]]` entry
+pub async fn start_proxy_server(
+    config: Config,
+    state: SharedState,
+    connection_tracker: SharedConnectionTracker,
+    event_sender: EventSender,
+    orchestrator: HealthOrchestratorHandle,
+    mode: RunMode,
+) -> anyhow::Result<()> {
+    let https = HttpsConnector::new();
+    let client = Client::builder()
+        .pool_idle_timeout(std::time::Duration::from_secs(30))
+        .pool_max_idle_per_host(4)
+        .build::<_, hyper::Body>(https);
+
+    tokio::spawn(keep_standby_warm(client.clone(), state.clone()));
+
+    // Send server started event before creating the service
+    let _ = event_sender.send(ProxyEvent::ServerStarted {
+        port: config.server.port,
+    });
+
+    // Bind every listener before serving any, so a taken port fails startup
+    let mut listeners = Vec::new();
+    for listener_config in config.listeners() {
+        let addr = SocketAddr::new(listener_config.bind_address, listener_config.port);
+        match TcpListener::bind(addr).await {
+            Ok(listener) => listeners.push((listener, listener_config)),
+            Err(e) => {
+                if !mode.is_silent() {
+                    log_server_error(&format!("{} ({e})", listener_config.url()));
+                }
+                return Err(anyhow::anyhow!(
+                    "Server error: {} ({})",
+                    e,
+                    listener_config.url()
+                ));
+            }
+        }
+    }
+
+    let mut http = Http::new();
+    let header_timeout = (config.server.http1_header_read_timeout_ms > 0)
+        .then(|| Duration::from_millis(config.server.http1_header_read_timeout_ms));
+    if let Some(timeout) = header_timeout {
+        http.http1_header_read_timeout(timeout);
+    }
+    let idle_timeout = (config.server.idle_connection_timeout_seconds > 0)
+        .then(|| Duration::from_secs(config.server.idle_connection_timeout_seconds));
+    let connection_slots = (config.server.max_concurrent_local_connections > 0).then(|| {
+        Arc::new(Semaphore::new(
+            config.server.max_concurrent_local_connections,
+        ))
+    });
+
+    if !mode.is_silent() {
+        log_server_start(config.server.port);
+        for listener in &config.server.listeners {
+            log_listener_start(&listener.url(), listener.group.as_deref());
+        }
+    }
+
+    let metrics = state.lock_or_recover().metrics.clone();
+    let context = ServerContext {
+        state: state.clone(),
+        client,
+        connection_tracker,
+        event_sender,
+        orchestrator,
+        mode,
+        metrics,
+        cache: Arc::new(ResponseCache::default()),
+        http,
+        header_timeout,
+        idle_timeout,
+        connection_slots,
+    };
+    let served = Arc::new(Listeners {
+        context,
+        running: Mutex::new(Vec::new()),
+    });
+    for (listener, config) in listeners {
+        let running = served.serve(listener, config);
+        served.running.lock_or_recover().push(running);
+    }
+    state.lock_or_recover().listeners = Arc::downgrade(&served);
+
+    // Listeners come and go with reloads; the server runs until dropped
+    let _close = CloseListenersOnDrop(served);
+    std::future::pending::<()>().await;
+    Ok(())
+}
+
+/// Accept and serve local connections on one listener until it closes.
+/// Requests arriving on it are limited to `group` when one is set.
+async fn accept_loop(
+    listener: TcpListener,
+    group: ListenerGroup,
+    mut closing: watch::Receiver<bool>,
+    context: ServerContext,
+) {
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            // Dropping the listener refuses new connections on its port
+            _ = closing.changed() => return,
+        };
+        let stream = match accepted {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                // Usually out of file descriptors; give open connections a moment to close
+                error!("Accept error: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let permit = match &context.connection_slots {
+            Some(slots) => match slots.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    context.metrics.record_local_connection_rejected();
+                    debug!("Local connection limit reached, closing new connection");
+                    continue;
+                }
+            },
+            None => None,
+        };
+
+        let activity = Arc::new(ConnectionActivity::new());
+        let service = {
+            let context = context.clone();
+            let group = group.clone();
+            let activity = activity.clone();
+
+            service_fn(move |req| {
+                let context = context.clone();
+                let group = group.clone();
+                let activity = activity.clone();
+
+                async move {
+                    let _request = activity.request_started();
+                    let group = group.read_or_recover().clone();
+                    match handle_request(req, context, group.as_deref()).await {
+                        Ok(response) => Ok::<Response<Body>, hyper::Error>(response),
+                        Err(e) => {
+                            error!("Request error: {}", e);
+                            Ok(Response::builder()
+                                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                                .body(Body::from("Internal server error"))
+                                .unwrap())
+                        }
+                    }
+                }
+            })
+        };
+
+        let connection = context
+            .http
+            .serve_connection(
+                TrackedStream {
+                    inner: stream,
+                    activity: activity.clone(),
+                },
+                service,
+            )
+            .with_upgrades();
+        let metrics = context.metrics.clone();
+        let header_timeout = context.header_timeout;
+        let idle_timeout = context.idle_timeout;
+        let mut closing = closing.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            metrics.local_connection_opened();
+            tokio::pin!(connection);
+            // Closed by the proxy rather than by a timeout or the client
+            let mut closed_by_proxy = false;
+            let result = loop {
+                let idle_wait = activity.until_idle(idle_timeout);
+                tokio::select! {
+                    result = connection.as_mut() => break result,
+                    _ = sleep_if_some(idle_wait) => {
+                        if activity.is_idle(idle_timeout) {
+                            // Finishes a response still being written, then closes
+                            closed_by_proxy = true;
+                            metrics.record_idle_connection_closed();
+                            connection.as_mut().graceful_shutdown();
+                            break connection.as_mut().await;
+                        }
+                    }
+                    // Like an endpoint drain, the listener closing never cuts
+                    // a request in flight; the connection closes after it
+                    _ = closing.changed() => {
+                        closed_by_proxy = true;
+                        connection.as_mut().graceful_shutdown();
+                        break connection.as_mut().await;
+                    }
+                }
+            };
+            metrics.local_connection_closed();
+            let header_timed_out = match &result {
+                Err(e) => is_header_timeout(e, &activity, header_timeout),
+                // Hyper closes a connection that timed out before sending a
+                // single byte without an error
+                Ok(()) => {
+                    header_timeout.is_some() && !closed_by_proxy && activity.silent_until_closed()
+                }
+            };
+            if header_timed_out {
+                metrics.record_header_timeout();
+            }
+            if let Err(e) = result {
+                debug!("Local connection error: {}", e);
+            }
+        });
+    }
+}
+
+/// Sleep for `wait`, or forever without one
+async fn sleep_if_some(wait: Option<Duration>) {
+    match wait {
+        Some(wait) => tokio::time::sleep(wait).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Whether `error` ended the connection because the client took longer than
+/// `header_timeout` to send a request head. Hyper's own header timeout error
+/// has no [`hyper::Error::is_timeout`] source, so an error on a connection
+/// that had been waiting that long for a head counts as one too.
+fn is_header_timeout(
+    error: &hyper::Error,
+    activity: &ConnectionActivity,
+    header_timeout: Option<Duration>,
+) -> bool {
+    error.is_timeout() || header_timeout.is_some_and(|limit| activity.waited_for_head(limit))
+}
+
+/// When a local connection last carried traffic and how many of its requests
+/// are being handled
+struct ConnectionActivity {
+    opened: Instant,
+    /// Milliseconds after `opened` of the last read or write
+    last_activity_ms: AtomicU64,
+    /// Milliseconds after `opened` since which hyper has been reading the next
+    /// request head: the end of the last request or response write
+    head_wait_since_ms: AtomicU64,
+    in_flight: AtomicUsize,
+    /// The client sent at least one byte
+    received_data: AtomicBool,
+    /// The client closed its side of the connection
+    peer_closed: AtomicBool,
+}
+
+impl ConnectionActivity {
+    fn new() -> Self {
+        Self {
+            opened: Instant::now(),
+            last_activity_ms: AtomicU64::new(0),
+            head_wait_since_ms: AtomicU64::new(0),
+            in_flight: AtomicUsize::new(0),
+            received_data: AtomicBool::new(false),
+            peer_closed: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether the connection ended without the client sending anything or
+    /// closing it, which leaves only the proxy's timeouts as the cause
+    fn silent_until_closed(&self) -> bool {
+        !self.received_data.load(Ordering::Relaxed) && !self.peer_closed.load(Ordering::Relaxed)
+    }
+
+    fn touch(&self) {
+        let elapsed = self.opened.elapsed().as_millis() as u64;
+        self.last_activity_ms.store(elapsed, Ordering::Relaxed);
+    }
+
+    /// Restart the wait for a request head after the proxy sent something.
+    /// Reads do not restart it, as a head trickling in is still one head.
+    fn restart_head_wait(&self) {
+        let elapsed = self.opened.elapsed().as_millis() as u64;
+        self.head_wait_since_ms.store(elapsed, Ordering::Relaxed);
+    }
+
+    /// Whether no request is in flight and the next head has been awaited
+    /// for at least `limit`
+    fn waited_for_head(&self, limit: Duration) -> bool {
+        let since = Duration::from_millis(self.head_wait_since_ms.load(Ordering::Relaxed));
+        self.in_flight.load(Ordering::Relaxed) == 0
+            && self.opened.elapsed().saturating_sub(since) >= limit
+    }
+
+    fn idle_for(&self) -> Duration {
+        let last = Duration::from_millis(self.last_activity_ms.load(Ordering::Relaxed));
+        self.opened.elapsed().saturating_sub(last)
+    }
+
+    /// How long to wait before checking the idle timeout again, `None` when
+    /// connections never time out
+    fn until_idle(&self, idle_timeout: Option<Duration>) -> Option<Duration> {
+        let limit = idle_timeout?;
+        Some(
+            limit
+                .saturating_sub(self.idle_for())
+                .max(Duration::from_secs(1)),
+        )
+    }
+
+    /// Whether the connection has had no request in flight and no traffic for
+    /// the idle timeout
+    fn is_idle(&self, idle_timeout: Option<Duration>) -> bool {
+        idle_timeout.is_some_and(|limit| {
+            self.in_flight.load(Ordering::Relaxed) == 0 && self.idle_for() >= limit
+        })
+    }
+
+    /// Count a request as in flight until the returned guard is dropped
+    fn request_started(self: &Arc<Self>) -> InFlightRequest {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlightRequest(self.clone())
+    }
+}
+
+struct InFlightRequest(Arc<ConnectionActivity>);
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.0.touch();
+        self.0.restart_head_wait();
+    }
+}
+
+/// Local client socket that records its traffic in a [`ConnectionActivity`]
+struct TrackedStream {
+    inner: TcpStream,
+    activity: Arc<ConnectionActivity>,
+}
+
+impl AsyncRead for TrackedStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let wanted = buf.remaining() > 0;
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if matches!(poll, Poll::Ready(Ok(()))) {
+            if buf.filled().len() > before {
+                self.activity.received_data.store(true, Ordering::Relaxed);
+                self.activity.touch();
+            } else if wanted {
+                self.activity.peer_closed.store(true, Ordering::Relaxed);
+            }
+        }
+        poll
+    }
+}
+
+impl AsyncWrite for TrackedStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if matches!(poll, Poll::Ready(Ok(written)) if written > 0) {
+            self.activity.touch();
+            self.activity.restart_head_wait();
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// Route a request from a listener limited to `listener_group`, when it is
+async fn handle_request(
+    req: Request<Body>,
+    context: ServerContext,
+    listener_group: Option<&str>,
+) -> anyhow::Result<Response<Body>> {
+    let ServerContext {
+        state,
+        client,
+        connection_tracker,
+        event_sender,
+        orchestrator,
+        mode,
+        metrics,
+        cache,
+        ..
+    } = context;
+    match req.uri().path() {
+        "/status" => status_handler(req, state, Some(connection_tracker.clone())).await,
+        "/diagnostics" => diagnostics_handler(req, connection_tracker.clone()).await,
+        "/switches" => switches_handler(state).await,
+        "/events" => events_handler(req, state).await,
+        "/metrics" => metrics_handler(state).await,
+        "/connections" => connections_handler(req, state, connection_tracker).await,
+        "/connections/recent" => recent_connections_handler(req, state, connection_tracker).await,
+        "/admin/filter" => filter_handler(req, state, event_sender, mode).await,
+        "/admin/freeze" => freeze_handler(req, state, event_sender).await,
+        "/admin/refresh" => refresh_handler(req, state, orchestrator).await,
+        "/admin/orchestrator" => orchestrator_handler(req, state, orchestrator).await,
+        "/admin/simulate-selection" => simulate_selection_handler(req, state).await,
+        "/admin/prepare-restart" => prepare_restart_handler(req, state).await,
+        "/admin/drill" => {
+            drill_handler(req, state, client, connection_tracker, event_sender, mode).await
+        }
+        path if path.starts_with("/admin/endpoints/") => {
+            endpoint_reset_handler(req, state, orchestrator).await
+        }
+        "/health" => health_handler().await,
+        "/health/profile" => profile_handler(state).await,
+        "/health/history" => history_handler(state).await,
+        "/status/export.csv" => export_csv_handler(state).await,
+        "/version" => version_handler(state).await,
+        "/config" => config_handler(state).await,
+        path if is_passthrough(&state, req.method(), path) => {
+            passthrough_handler(req, state, client, mode, listener_group, &cache).await
+        }
+        _ => {
+            // Checked before anything else so an overloaded proxy sheds
+            // requests without locking state or reading their bodies
+            let Some(in_flight) = metrics.try_admit_request() else {
+                let limit = metrics.in_flight().limit;
+                if !mode.is_silent() {
+                    log_request_over_in_flight_limit(limit);
+                }
+                return too_many_in_flight(limit);
+            };
+            proxy_handler(
+                req,
+                state,
+                client,
+                connection_tracker,
+                event_sender,
+                mode,
+                listener_group,
+                in_flight,
+            )
+            .await
+        }
+    }
+}
+
+/// Whether the request matches one of the `[routing]` passthrough rules or is
+/// a GET on a `[cache]` path
+fn is_passthrough(state: &SharedState, method: &Method, path: &str) -> bool {
+    let config = &state.lock_or_recover().config;
+    config.routing.is_passthrough(method.as_str(), path)
+        || config.cache.is_cacheable(method.as_str(), path)
+}
@@ -0,0 +1,81 @@
+use crate::endpoint::EndpointId;
+use crate::lock::LockExt;
+use hyper::{Body, Method, Request};
+use std::time::{Duration, Instant};
+
+use super::fallback::fallback_candidates;
+use super::retry::HttpsClient;
+use super::{ProxyState, SharedState};
+
+/// How often the standby connection is used, below the client pool's 30s idle
+/// timeout so the pooled connection is never dropped
+const WARM_INTERVAL: Duration = Duration::from_secs(20);
+
+/// How often the loop looks for a new standby endpoint
+const STANDBY_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Give up on a warm request after this long
+const WARM_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The healthy endpoint a request failing on the current one would fall back
+/// to first, `None` when fallback has nowhere healthy to go
+pub(crate) fn standby_candidate(state: &ProxyState) -> Option<EndpointId> {
+    fallback_candidates(state, state.current_url(), None)
+        .into_iter()
+        .next()
+        .filter(|endpoint| {
+            state
+                .status_of(&endpoint.url)
+                .is_some_and(|status| status.is_available())
+        })
+        .map(|endpoint| state.config.endpoint_index().key_of(&endpoint.url))
+}
+
+/// Keep a pooled connection to the standby endpoint open, so the first
+/// fallback request does not pay for a TCP and TLS handshake. The `HEAD`
+/// requests carry no token and are left out of every counter.
+pub(super) async fn keep_standby_warm(client: HttpsClient, state: SharedState) {
+    let mut last_warmed: Option<(String, Instant)> = None;
+    let mut poll = tokio::time::interval(STANDBY_POLL_INTERVAL);
+    poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        poll.tick().await;
+        let standby = {
+            let state_guard = state.lock_or_recover();
+            state_guard
+                .standby_endpoint
+                .as_ref()
+                .filter(|standby| **standby != state_guard.current_endpoint)
+                .and_then(|_| state_guard.standby_url())
+                .map(str::to_string)
+        };
+        let Some(standby) = standby else {
+            last_warmed = None;
+            continue;
+        };
+        let due = last_warmed
+            .as_ref()
+            .is_none_or(|(endpoint, at)| *endpoint != standby || at.elapsed() >= WARM_INTERVAL);
+        if due {
+            warm(&client, &standby).await;
+            last_warmed = Some((standby, Instant::now()));
+        }
+    }
+}
+
+/// Send a `HEAD` request to `endpoint` and read the response, leaving its
+/// connection in the client pool. Failures are ignored: health checks decide
+/// whether the endpoint is usable.
+async fn warm(client: &HttpsClient, endpoint: &str) {
+    let Ok(request) = Request::builder()
+        .method(Method::HEAD)
+        .uri(endpoint)
+        .body(Body::empty())
+    else {
+        return;
+    };
+    if let Ok(Ok(response)) = tokio::time::timeout(WARM_TIMEOUT, client.request(request)).await {
+        let _ = hyper::body::to_bytes(response.into_body()).await;
+    }
+}
@@ -0,0 +1,476 @@
+use crate::lock::LockExt;
+use crate::logging::log_usage_report_error;
+use crate::metrics::{CounterSnapshot, SharedMetrics};
+use crate::storage::{load_totals, RequestTotals, DEFAULT_STORAGE_FILE};
+use anyhow::Context;
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Usage file written next to config.toml
+pub const DEFAULT_USAGE_FILE: &str = "usage.json";
+
+/// How often the running counters are folded into the usage file
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Days kept in the usage file before the oldest are dropped
+const RETENTION_DAYS: u64 = 90;
+
+/// Version 2 keys endpoints by id, version 1 by URL
+const USAGE_FILE_VERSION: u32 = 2;
+
+pub type SharedUsageAggregator = Arc<Mutex<UsageAggregator>>;
+
+/// Usage of one endpoint over one day
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageCounts {
+    pub requests: u64,
+    pub errors: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Estimated request cost in micro-dollars
+    pub cost_micros: u64,
+    pub health_checks: u64,
+    /// Estimated health check cost in micro-dollars
+    pub health_check_cost_micros: u64,
+    /// Times the endpoint became the current one
+    pub switches: u64,
+}
+
+impl UsageCounts {
+    fn add(&mut self, other: &UsageCounts) {
+        self.requests += other.requests;
+        self.errors += other.errors;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cost_micros += other.cost_micros;
+        self.health_checks += other.health_checks;
+        self.health_check_cost_micros += other.health_check_cost_micros;
+        self.switches += other.switches;
+    }
+
+    fn is_empty(&self) -> bool {
+        self.requests == 0 && self.health_checks == 0 && self.switches == 0
+    }
+
+    /// Percentage of requests that failed
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 * 100.0 / self.requests as f64
+        }
+    }
+}
+
+impl From<CounterSnapshot> for UsageCounts {
+    fn from(snapshot: CounterSnapshot) -> Self {
+        Self {
+            requests: snapshot.requests,
+            errors: snapshot.endpoint_errors,
+            input_tokens: snapshot.input_tokens,
+            output_tokens: snapshot.output_tokens,
+            cost_micros: snapshot.request_cost_micros,
+            health_checks: snapshot.health_checks,
+            health_check_cost_micros: snapshot.health_check_cost_micros,
+            switches: snapshot.switches,
+        }
+    }
+}
+
+/// Contents of the usage file: per-endpoint counters keyed by local date
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UsageReport {
+    version: u32,
+    /// Currency symbol configured when the costs were recorded
+    #[serde(default)]
+    currency: String,
+    #[serde(default)]
+    days: BTreeMap<NaiveDate, BTreeMap<String, UsageCounts>>,
+}
+
+impl Default for UsageReport {
+    fn default() -> Self {
+        Self {
+            version: USAGE_FILE_VERSION,
+            currency: String::new(),
+            days: BTreeMap::new(),
+        }
+    }
+}
+
+impl UsageReport {
+    /// Read the usage file, an absent file is an empty report
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse usage file {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => {
+                Err(e).with_context(|| format!("Failed to read usage file {}", path.display()))
+            }
+        }
+    }
+
+    /// Write through a temp file and rename so readers never see a partial file
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+
+        fs::write(&temp_path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+        fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to replace usage file {}", path.display()))
+    }
+
+    /// Move counters recorded under a URL by older versions to the id of the
+    /// endpoint configured at that URL. URLs no longer configured stay as they are.
+    fn migrate_to_ids(&mut self, endpoint_ids: &HashMap<String, String>) {
+        if self.version >= USAGE_FILE_VERSION {
+            return;
+        }
+        for endpoints in self.days.values_mut() {
+            for (url, counts) in std::mem::take(endpoints) {
+                let key = endpoint_ids.get(&url).cloned().unwrap_or(url);
+                endpoints.entry(key).or_default().add(&counts);
+            }
+        }
+        self.version = USAGE_FILE_VERSION;
+    }
+
+    fn prune(&mut self, today: NaiveDate) {
+        if let Some(oldest) = today.checked_sub_days(chrono::Days::new(RETENTION_DAYS)) {
+            self.days.retain(|date, _| *date > oldest);
+        }
+    }
+
+    /// Per-endpoint totals for the dates in `from..=to`
+    fn totals(&self, from: NaiveDate, to: NaiveDate) -> BTreeMap<String, UsageCounts> {
+        let mut totals: BTreeMap<String, UsageCounts> = BTreeMap::new();
+        for endpoints in self.days.range(from..=to).map(|(_, endpoints)| endpoints) {
+            for (endpoint, counts) in endpoints {
+                totals.entry(endpoint.clone()).or_default().add(counts);
+            }
+        }
+        totals
+    }
+}
+
+/// Folds the proxy's running counters into per-day totals and persists them.
+///
+/// Counters since the previous flush are credited to the local date at flush
+/// time, so a flush just after midnight books the last minute of the previous
+/// day onto the new one.
+pub struct UsageAggregator {
+    path: PathBuf,
+    metrics: SharedMetrics,
+    /// Counters by endpoint id as of the previous flush
+    baseline: BTreeMap<String, CounterSnapshot>,
+    report: UsageReport,
+}
+
+impl UsageAggregator {
+    pub fn new(
+        path: PathBuf,
+        metrics: SharedMetrics,
+        endpoint_ids: HashMap<String, String>,
+        currency: String,
+    ) -> Self {
+        let mut report = UsageReport::load(&path).unwrap_or_else(|e| {
+            // Keep the unreadable file around instead of overwriting it
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(".bak");
+            let _ = fs::rename(&path, &backup);
+            log_usage_report_error(&format!("{e:#}, starting a new usage file"));
+            UsageReport::default()
+        });
+        report.currency = currency;
+        report.migrate_to_ids(&endpoint_ids);
+
+        Self {
+            path,
+            baseline: metrics.snapshots_by_id(),
+            metrics,
+            report,
+        }
+    }
+
+    /// Add the counters accumulated since the last flush to today and save
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        let today = Local::now().date_naive();
+        let current = self.metrics.snapshots_by_id();
+
+        for (id, snapshot) in &current {
+            let delta = match self.baseline.get(id) {
+                Some(previous) => snapshot.since(previous),
+                None => *snapshot,
+            };
+            let counts = UsageCounts::from(delta);
+            if !counts.is_empty() {
+                self.report
+                    .days
+                    .entry(today)
+                    .or_default()
+                    .entry(id.clone())
+                    .or_default()
+                    .add(&counts);
+            }
+        }
+        self.baseline = current;
+
+        self.report.prune(today);
+        self.report.save(&self.path)
+    }
+}
+
+/// Start the background task that flushes usage to disk every minute
+pub fn spawn_usage_aggregator(
+    metrics: SharedMetrics,
+    endpoint_ids: HashMap<String, String>,
+    currency: String,
+) -> SharedUsageAggregator {
+    let aggregator = Arc::new(Mutex::new(UsageAggregator::new(
+        PathBuf::from(DEFAULT_USAGE_FILE),
+        metrics,
+        endpoint_ids,
+        currency,
+    )));
+
+    let task_aggregator = aggregator.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        // The first tick fires immediately and there is nothing to flush yet
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            flush_usage(&task_aggregator);
+        }
+    });
+
+    aggregator
+}
+
+/// Persist usage now, used on the timer and at shutdown
+pub fn flush_usage(aggregator: &SharedUsageAggregator) {
+    let mut aggregator = aggregator.lock_or_recover();
+    if let Err(e) = aggregator.flush() {
+        log_usage_report_error(&format!("{e:#}"));
+    }
+}
+
+/// Options for the `report` subcommand
+#[derive(clap::Args, Debug)]
+pub struct ReportArgs {
+    /// Day to report on (YYYY-MM-DD), defaults to today
+    #[arg(long, conflicts_with = "last")]
+    date: Option<NaiveDate>,
+
+    /// Report on the last N days including today, e.g. 7d
+    #[arg(long, value_parser = parse_days)]
+    last: Option<u64>,
+
+    /// Print JSON instead of a table
+    #[arg(long)]
+    json: bool,
+
+    /// Usage file written by the proxy
+    #[arg(long, default_value = DEFAULT_USAGE_FILE)]
+    file: PathBuf,
+
+    /// Read the request records in this SQLite file ([storage]) instead of the
+    /// usage file
+    #[arg(
+        long,
+        value_name = "PATH",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_STORAGE_FILE
+    )]
+    from_db: Option<PathBuf>,
+}
+
+fn parse_days(value: &str) -> Result<u64, String> {
+    let days: u64 = value
+        .strip_suffix('d')
+        .unwrap_or(value)
+        .parse()
+        .map_err(|_| format!("expected a number of days such as 7d, got '{value}'"))?;
+    if days == 0 {
+        return Err("the number of days must be at least 1".to_string());
+    }
+    Ok(days)
+}
+
+/// Print the usage summary for the `report` subcommand
+pub fn print_report(args: &ReportArgs) -> anyhow::Result<()> {
+    let today = Local::now().date_naive();
+    let (from, to) = match (args.date, args.last) {
+        (Some(date), _) => (date, date),
+        (None, Some(days)) => (
+            today
+                .checked_sub_days(chrono::Days::new(days - 1))
+                .unwrap_or(NaiveDate::MIN),
+            today,
+        ),
+        (None, None) => (today, today),
+    };
+
+    if let Some(path) = &args.from_db {
+        return print_db_report(path, from, to, args.json);
+    }
+
+    let report = UsageReport::load(&args.file)?;
+    let endpoints = report.totals(from, to);
+    let mut total = UsageCounts::default();
+    for counts in endpoints.values() {
+        total.add(counts);
+    }
+
+    if args.json {
+        let summary = serde_json::json!({
+            "from": from,
+            "to": to,
+            "currency": report.currency,
+            "endpoints": endpoints,
+            "total": total,
+        });
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+
+    if from == to {
+        println!("Usage for {from}");
+    } else {
+        println!("Usage for {from} to {to}");
+    }
+
+    if endpoints.is_empty() {
+        println!("No usage recorded.");
+        return Ok(());
+    }
+
+    let currency = if report.currency.is_empty() {
+        "$"
+    } else {
+        report.currency.as_str()
+    };
+    let width = endpoints
+        .keys()
+        .map(|endpoint| endpoint.len())
+        .max()
+        .unwrap_or(0)
+        .max("Endpoint".len());
+
+    println!(
+        "{:<width$}  {:>9}  {:>7}  {:>12}  {:>12}  {:>12}  {:>8}",
+        "Endpoint", "Requests", "Errors", "Input tok", "Output tok", "Cost", "Switches"
+    );
+    let rows = endpoints
+        .iter()
+        .map(|(endpoint, counts)| (endpoint.as_str(), counts))
+        .chain(std::iter::once(("Total", &total)));
+    for (endpoint, counts) in rows {
+        println!(
+            "{:<width$}  {:>9}  {:>6.1}%  {:>12}  {:>12}  {:>12}  {:>8}",
+            endpoint,
+            counts.requests,
+            counts.error_rate(),
+            counts.input_tokens,
+            counts.output_tokens,
+            format_cost(currency, counts.cost_micros),
+            counts.switches,
+        );
+    }
+    println!(
+        "Health checks: {} (estimated {})",
+        total.health_checks,
+        format_cost(currency, total.health_check_cost_micros)
+    );
+
+    Ok(())
+}
+
+/// Print the requests stored in the SQLite file between local dates `from` and `to`
+fn print_db_report(path: &Path, from: NaiveDate, to: NaiveDate, json: bool) -> anyhow::Result<()> {
+    let endpoints = load_totals(
+        path,
+        local_midnight(from),
+        local_midnight(to + chrono::Days::new(1)),
+    )?;
+    let mut total = RequestTotals::default();
+    for counts in endpoints.values() {
+        total.add(counts);
+    }
+
+    if json {
+        let summary = serde_json::json!({
+            "from": from,
+            "to": to,
+            "endpoints": endpoints,
+            "total": total,
+        });
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+
+    if from == to {
+        println!("Requests for {from}");
+    } else {
+        println!("Requests for {from} to {to}");
+    }
+
+    if endpoints.is_empty() {
+        println!("No requests recorded.");
+        return Ok(());
+    }
+
+    let width = endpoints
+        .keys()
+        .map(|endpoint| endpoint.len())
+        .max()
+        .unwrap_or(0)
+        .max("Endpoint".len());
+
+    println!(
+        "{:<width$}  {:>9}  {:>7}  {:>12}  {:>12}  {:>8}  {:>9}",
+        "Endpoint", "Requests", "Errors", "Input tok", "Output tok", "Retries", "Avg time"
+    );
+    let rows = endpoints
+        .iter()
+        .map(|(endpoint, counts)| (endpoint.as_str(), counts))
+        .chain(std::iter::once(("Total", &total)));
+    for (endpoint, counts) in rows {
+        println!(
+            "{:<width$}  {:>9}  {:>6.1}%  {:>12}  {:>12}  {:>8}  {:>7}ms",
+            endpoint,
+            counts.requests,
+            counts.error_rate(),
+            counts.input_tokens,
+            counts.output_tokens,
+            counts.retries,
+            counts.average_duration_ms(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Start of a local day as a UTC instant
+fn local_midnight(date: NaiveDate) -> DateTime<Utc> {
+    date.and_time(NaiveTime::MIN)
+        .and_local_timezone(Local)
+        .earliest()
+        .map_or_else(
+            || date.and_time(NaiveTime::MIN).and_utc(),
+            |midnight| midnight.to_utc(),
+        )
+}
+
+fn format_cost(currency: &str, micros: u64) -> String {
+    format!("{currency}{:.4}", micros as f64 / 1_000_000.0)
+}
@@ -0,0 +1,195 @@
+//! Auto mode's switching decisions as pure functions over the proxy state.
+//!
+//! The health orchestrator makes its switches through these, and
+//! `/admin/simulate-selection` runs the same functions against the current
+//! state without switching, so a preview always matches what the next health
+//! check cycle would do with the same results.
+
+use crate::events::{SelectionMode, SwitchReason};
+use crate::health::EndpointStatus;
+use crate::proxy::ProxyState;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+/// A switch Auto mode would make: from the current endpoint with its latency,
+/// to the candidate's latency, and why
+pub(crate) type SwitchDecision = (String, Option<u64>, Option<u64>, SwitchReason);
+
+/// Why an endpoint would not become current
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Exclusion {
+    /// Already the current endpoint
+    Current,
+    /// Its last check failed, or it has not been checked yet
+    Unavailable,
+    /// Inside a maintenance window
+    Maintenance,
+    /// The tag filter leaves it out
+    TagFilter,
+    /// Outside `auto_switch_scope`, or its group has no token
+    OutOfScope,
+    /// A preferred tier has an endpoint that is healthy or still being checked
+    PreferredTier,
+    /// In a less preferred tier than the healthy current endpoint
+    LowerTier,
+    /// Not faster than the current endpoint by `switch_threshold_ms`, nor at
+    /// this hour with `prefer_hourly_profile`
+    BelowThreshold,
+}
+
+/// Whether `status` may become current from Auto mode at all, before it is
+/// compared with the current endpoint
+pub(crate) fn eligibility(status: &EndpointStatus, state: &ProxyState) -> Result<(), Exclusion> {
+    let endpoint = status.endpoint.as_str();
+    if status.maintenance_until.is_some() {
+        Err(Exclusion::Maintenance)
+    } else if !status.is_available() {
+        Err(Exclusion::Unavailable)
+    } else if !state.matches_tag_filter(endpoint) {
+        Err(Exclusion::TagFilter)
+    } else if !state.auto_switch_endpoints().iter().any(|e| e == endpoint) {
+        Err(Exclusion::OutOfScope)
+    } else {
+        Ok(())
+    }
+}
+
+/// Whether `status` may claim a cycle's race: the first eligible endpoint to
+/// answer does, unless a preferred tier is still in play
+pub(crate) fn race_eligibility(
+    status: &EndpointStatus,
+    state: &ProxyState,
+) -> Result<(), Exclusion> {
+    eligibility(status, state)?;
+    if state.preferred_tier_may_serve(&status.endpoint) {
+        return Err(Exclusion::PreferredTier);
+    }
+    Ok(())
+}
+
+/// The switch Auto mode makes when `status` is offered against the current
+/// endpoint, or why it stays
+pub(crate) fn switch_decision(
+    status: &EndpointStatus,
+    state: &ProxyState,
+    now: DateTime<Local>,
+) -> Result<SwitchDecision, Exclusion> {
+    let current = state.current_url();
+    let threshold = state.config.server.switch_threshold_ms;
+
+    // Only consider available endpoints for switching
+    if !status.is_available() {
+        return Err(Exclusion::Unavailable);
+    }
+    if state.is_current(&status.endpoint) {
+        return Err(Exclusion::Current);
+    }
+
+    // Switch immediately if:
+    // 1. No current endpoint, OR
+    // 2. Current endpoint is not available, OR
+    // 3. This endpoint is significantly faster than current
+    if current.is_empty() {
+        return Ok((String::new(), None, status.latency, SwitchReason::Failover));
+    }
+    if !state.matches_tag_filter(current) {
+        // The filter excludes the current endpoint, leave it regardless of latency
+        return Ok((
+            current.to_string(),
+            state.status_of(current).and_then(|s| s.latency),
+            status.latency,
+            SwitchReason::TagFilter,
+        ));
+    }
+    let Some(current_status) = state.status_of(current) else {
+        // Current endpoint has no status, switch to this one
+        return Ok((
+            current.to_string(),
+            None,
+            status.latency,
+            SwitchReason::Failover,
+        ));
+    };
+
+    let tier = state.tier_of(&status.endpoint);
+    let current_tier = state.tier_of(current);
+    let reason = if tier < current_tier {
+        // A preferred tier is healthy again, return to it regardless of latency
+        SwitchReason::TierFailback
+    } else if !current_status.is_available() {
+        // Current is down, switch immediately
+        if tier > current_tier {
+            SwitchReason::TierFailover
+        } else {
+            SwitchReason::Failover
+        }
+    } else if tier > current_tier {
+        // A healthy tier is never left for a faster, less preferred one
+        return Err(Exclusion::LowerTier);
+    } else if is_faster_by(status.latency, current_status.latency, threshold) {
+        // This endpoint is significantly faster than current
+        SwitchReason::AutoRaceWin
+    } else if state.config.server.prefer_hourly_profile
+        && !is_faster_by(current_status.latency, status.latency, threshold)
+        && is_faster_by(
+            status.hourly_profile.latency_at(now),
+            current_status.hourly_profile.latency_at(now),
+            threshold,
+        )
+    {
+        // Measurements are a tie, but this endpoint is usually faster at this hour
+        SwitchReason::AutoRaceWin
+    } else {
+        return Err(Exclusion::BelowThreshold);
+    };
+
+    Ok((
+        current.to_string(),
+        current_status.latency,
+        status.latency,
+        reason,
+    ))
+}
+
+/// Endpoint the end of a cycle moves to when the current endpoint is not a
+/// healthy member of the active tier: the fastest healthy endpoint of that
+/// tier. `None` when the current endpoint is where it should be.
+pub(crate) fn best_of_cycle(state: &ProxyState) -> Option<&EndpointStatus> {
+    let active_tier = state.active_tier()?;
+    let current = state.current_url();
+    let current_healthy = state
+        .status_of(current)
+        .is_some_and(|status| status.is_available() && status.maintenance_until.is_none());
+    if current_healthy && state.tier_of(current) == active_tier {
+        return None;
+    }
+    state
+        .auto_switch_endpoints()
+        .iter()
+        .filter(|endpoint| state.tier_of(endpoint) == active_tier)
+        .filter_map(|endpoint| state.status_of(endpoint))
+        .filter(|status| status.is_available() && status.maintenance_until.is_none())
+        .min_by_key(|status| status.latency.unwrap_or(u64::MAX))
+}
+
+/// Why Auto mode makes no switch at all right now, `None` when it may
+pub(crate) fn switching_blocked(state: &ProxyState) -> Option<&'static str> {
+    if state.selection_mode != SelectionMode::Auto {
+        Some("manual_mode")
+    } else if state.switching_frozen {
+        Some("switching_frozen")
+    } else {
+        None
+    }
+}
+
+/// Whether `candidate` beats `current` by at least `threshold` ms.
+/// An unmeasured current endpoint is beaten by any measured candidate.
+fn is_faster_by(candidate: Option<u64>, current: Option<u64>, threshold: u64) -> bool {
+    match (candidate, current) {
+        (Some(candidate), Some(current)) => current.saturating_sub(candidate) >= threshold,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
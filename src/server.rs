@@ -0,0 +1,448 @@
+use crate::alerts::run_alert_evaluator;
+use crate::config::Config;
+use crate::connection_tracker::{
+    ConnectionTracker, EventReceiver, EventSender, SharedConnectionTracker,
+};
+use crate::dashboard::Dashboard;
+use crate::drain::run_drain_monitor;
+use crate::events::ProxyEvent;
+use crate::health::claude_version;
+use crate::health_orchestrator::{HealthCheckOrchestrator, HealthOrchestratorHandle};
+use crate::i18n;
+use crate::lock::LockExt;
+use crate::logging::{
+    configure_console, console_line, console_text, log_claude_binary, log_config_loaded,
+    log_group_missing_token, log_monitor_start, log_state_handoff_error,
+    log_state_handoff_restored, log_storage_error,
+};
+use crate::proxy::{self, ProxyState, RunMode, SharedState};
+use crate::report::{flush_usage, spawn_usage_aggregator, SharedUsageAggregator};
+use crate::signal_handler::{reload_on_sighup, save_state_on_sigusr2, GracefulShutdown};
+use crate::state_handoff;
+use crate::storage::spawn_storage_writer;
+use crate::token_check::check_tokens;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Callback invoked for every [`ProxyEvent`] the proxy emits
+pub type EventHook = Arc<dyn Fn(&ProxyEvent) + Send + Sync>;
+
+/// Builder for a [`ProxyServer`], created by [`ProxyServer::builder`]
+pub struct ProxyServerBuilder {
+    config: Config,
+    port: Option<u16>,
+    state: Option<SharedState>,
+    event_hook: Option<EventHook>,
+    mode: RunMode,
+    record_usage: bool,
+    state_handoff: Option<PathBuf>,
+    monitor_only: bool,
+}
+
+impl ProxyServerBuilder {
+    /// Listen on this port instead of `server.port` from the config
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Share an existing proxy state instead of creating one from the config
+    pub fn state(mut self, state: SharedState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Call `hook` for every event, in addition to the dashboard if enabled
+    pub fn event_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&ProxyEvent) + Send + Sync + 'static,
+    {
+        self.event_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Run with the terminal dashboard or with console logs (the default)
+    pub fn mode(mut self, mode: RunMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Persist daily usage to the usage file (the default) and, with `[storage]`
+    /// enabled, request records; off for throwaway runs
+    pub fn record_usage(mut self, record_usage: bool) -> Self {
+        self.record_usage = record_usage;
+        self
+    }
+
+    /// Resume from the state handoff snapshot at `path` if there is one, and
+    /// write it there on SIGUSR2 or `POST /admin/prepare-restart`
+    pub fn state_handoff(mut self, path: PathBuf) -> Self {
+        self.state_handoff = Some(path);
+        self
+    }
+
+    /// Run the health checks without the proxy: no port is bound and no
+    /// request is forwarded, for watching endpoint latency on its own
+    pub fn monitor_only(mut self) -> Self {
+        self.monitor_only = true;
+        self
+    }
+
+    pub fn build(self) -> ProxyServer {
+        let mut config = self.config;
+        if let Some(port) = self.port {
+            config.server.port = port;
+        }
+
+        let state = self
+            .state
+            .unwrap_or_else(|| Arc::new(Mutex::new(ProxyState::new(config.clone()))));
+        if let Some(path) = self.state_handoff {
+            resume_state(&state, path, self.mode.is_silent());
+        }
+        let connection_tracker = Arc::new(Mutex::new(ConnectionTracker::new(
+            config.ui.recent_requests_size,
+        )));
+        let (event_sender, event_receiver) = mpsc::unbounded_channel::<ProxyEvent>();
+
+        let total_endpoints: usize = config.groups.iter().map(|g| g.endpoints.len()).sum();
+        let _ = event_sender.send(ProxyEvent::ConfigLoaded {
+            endpoint_count: total_endpoints,
+        });
+
+        let mode = self.mode;
+        // Without traffic there is no load to adapt the check interval to
+        let (orchestrator, orchestrator_handle) = HealthCheckOrchestrator::new(
+            config.clone(),
+            state.clone(),
+            event_sender.clone(),
+            mode.is_silent(),
+            (!self.monitor_only).then(|| connection_tracker.clone()),
+        );
+
+        ProxyServer {
+            config,
+            state,
+            connection_tracker,
+            event_sender,
+            event_receiver,
+            event_hook: self.event_hook,
+            mode,
+            record_usage: self.record_usage && !self.monitor_only,
+            monitor_only: self.monitor_only,
+            orchestrator,
+            orchestrator_handle,
+        }
+    }
+}
+
+/// The proxy server together with its health orchestrator.
+///
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// let config = claude_zephyr::Config::load_default()?;
+/// let server = claude_zephyr::ProxyServer::builder(config)
+///     .port(8081)
+///     .event_hook(|event| println!("{event:?}"))
+///     .build();
+/// let health = server.health_orchestrator();
+/// health.refresh();
+/// server.run_until(tokio::signal::ctrl_c()).await
+/// # }
+/// ```
+pub struct ProxyServer {
+    config: Config,
+    state: SharedState,
+    connection_tracker: SharedConnectionTracker,
+    event_sender: EventSender,
+    event_receiver: EventReceiver,
+    event_hook: Option<EventHook>,
+    mode: RunMode,
+    record_usage: bool,
+    monitor_only: bool,
+    orchestrator: HealthCheckOrchestrator,
+    orchestrator_handle: HealthOrchestratorHandle,
+}
+
+impl ProxyServer {
+    pub fn builder(config: Config) -> ProxyServerBuilder {
+        ProxyServerBuilder {
+            config,
+            port: None,
+            state: None,
+            event_hook: None,
+            mode: RunMode::Normal,
+            record_usage: true,
+            state_handoff: None,
+            monitor_only: false,
+        }
+    }
+
+    /// Port the server listens on
+    pub fn port(&self) -> u16 {
+        self.config.server.port
+    }
+
+    /// Shared proxy state: current endpoint, endpoint health and counters
+    pub fn state(&self) -> SharedState {
+        self.state.clone()
+    }
+
+    /// Handle to pause, resume or refresh health checks
+    pub fn health_orchestrator(&self) -> HealthOrchestratorHandle {
+        self.orchestrator_handle.clone()
+    }
+
+    /// Run until the dashboard quits, or until SIGINT/SIGTERM without it
+    pub async fn run(self) -> anyhow::Result<()> {
+        if self.mode == RunMode::Dashboard {
+            return self.run_with_dashboard().await;
+        }
+
+        let shutdown_handler =
+            GracefulShutdown::new(self.connection_tracker.clone(), self.event_sender.clone());
+        self.run_until(async move { shutdown_handler.wait_for_shutdown().await })
+            .await?;
+        console_line(i18n::console().shutdown_completed());
+        Ok(())
+    }
+
+    /// Serve without the dashboard until `shutdown` completes; console logs
+    /// stay off in [`RunMode::Dashboard`]
+    pub async fn run_until<F>(mut self, shutdown: F) -> anyhow::Result<()>
+    where
+        F: Future,
+    {
+        if !self.mode.is_silent() {
+            configure_console(&self.config);
+            let total_endpoints: usize = self.config.groups.iter().map(|g| g.endpoints.len()).sum();
+            log_config_loaded(total_endpoints);
+            for group in self.config.groups_without_token() {
+                log_group_missing_token(&group.name, &group.auth_token_env, group.endpoints.len());
+            }
+            if !self.config.http_health_checks() {
+                self.log_claude_binaries();
+            }
+        }
+        self.validate_tokens().await?;
+
+        let usage_aggregator = self.start_usage_aggregator();
+        self.start_request_storage();
+        let event_receiver = self.start_event_fanout();
+        drop(event_receiver);
+
+        let server = serve(
+            self.orchestrator,
+            self.config,
+            self.state,
+            self.connection_tracker,
+            self.event_sender,
+            self.mode,
+            self.monitor_only,
+        );
+
+        let result = tokio::select! {
+            result = server => result,
+            _ = shutdown => Ok(()),
+        };
+
+        if let Some(aggregator) = &usage_aggregator {
+            flush_usage(aggregator);
+        }
+        result
+    }
+
+    async fn run_with_dashboard(mut self) -> anyhow::Result<()> {
+        self.validate_tokens().await?;
+        let usage_aggregator = self.start_usage_aggregator();
+        self.start_request_storage();
+        let event_receiver = self.start_event_fanout();
+
+        // Create dashboard before moving config into spawned tasks
+        let dashboard_interval = self.config.health_check_interval();
+        let mut dashboard = Dashboard::new(&self.config, dashboard_interval);
+        if self.monitor_only {
+            dashboard.set_monitor_only();
+        }
+
+        // Start health checks and the proxy (dashboard mode - no console logs)
+        tokio::spawn(serve(
+            self.orchestrator,
+            self.config,
+            self.state.clone(),
+            self.connection_tracker.clone(),
+            self.event_sender.clone(),
+            RunMode::Dashboard,
+            self.monitor_only,
+        ));
+
+        // Run dashboard
+        let result = dashboard
+            .run(
+                event_receiver,
+                self.connection_tracker,
+                self.state,
+                self.orchestrator_handle,
+            )
+            .await;
+
+        if let Some(aggregator) = &usage_aggregator {
+            flush_usage(aggregator);
+        }
+        result
+    }
+
+    /// Check every group's token when `validate_tokens` is on. A rejected
+    /// token only marks its group, unless `strict_token_validation` makes it
+    /// fatal.
+    async fn validate_tokens(&self) -> anyhow::Result<()> {
+        let health_check = &self.config.health_check;
+        if !health_check.validate_tokens {
+            return Ok(());
+        }
+        let checks = check_tokens(&self.config, &self.state, self.mode.is_silent(), false).await;
+        let invalid: Vec<String> = checks
+            .iter()
+            .filter(|(_, check)| check.is_invalid())
+            .map(|(group, check)| format!("{group} ({})", check.http_status.unwrap_or_default()))
+            .collect();
+        if health_check.strict_token_validation && !invalid.is_empty() {
+            let error = i18n::console().strict_token_rejected(&invalid.join(", "));
+            anyhow::bail!("{}", console_text(&error));
+        }
+        Ok(())
+    }
+
+    /// Log each Claude CLI health checks will run, with its `--version`, once
+    fn log_claude_binaries(&self) {
+        let mut binaries: BTreeMap<PathBuf, Vec<&str>> = BTreeMap::new();
+        for group in &self.config.groups {
+            binaries
+                .entry(self.config.claude_binary(&group.name))
+                .or_default()
+                .push(&group.name);
+        }
+        for (path, groups) in binaries {
+            log_claude_binary(&groups, &path, claude_version(&path).as_deref());
+        }
+    }
+
+    fn start_usage_aggregator(&self) -> Option<SharedUsageAggregator> {
+        if !self.record_usage {
+            return None;
+        }
+        let metrics = self.state.lock_or_recover().metrics.clone();
+        Some(spawn_usage_aggregator(
+            metrics,
+            self.config.endpoint_ids(),
+            self.config.ui.currency.clone(),
+        ))
+    }
+
+    /// Record completed requests to the SQLite file when `[storage]` enables it
+    fn start_request_storage(&self) {
+        if !self.record_usage || !self.config.storage.enabled {
+            return;
+        }
+        match spawn_storage_writer(&self.config.storage, &self.config.endpoint_ids()) {
+            Ok(sender) => self.state.lock_or_recover().request_records = Some(sender),
+            Err(e) => log_storage_error(&format!("{e:#}")),
+        }
+    }
+
+    /// Pass events to `/events` clients and through the hook, returning the
+    /// receiver for the dashboard
+    fn start_event_fanout(&mut self) -> EventReceiver {
+        let (_, placeholder) = mpsc::unbounded_channel();
+        let mut receiver = std::mem::replace(&mut self.event_receiver, placeholder);
+        let event_stream = self.state.lock_or_recover().event_stream.clone();
+        let hook = self.event_hook.clone();
+
+        let (forward_sender, forward_receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                if event_stream.receiver_count() > 0 {
+                    let _ = event_stream.send(event.clone());
+                }
+                if let Some(hook) = &hook {
+                    hook(&event);
+                }
+                // Nobody listens without the dashboard, which is fine
+                let _ = forward_sender.send(event);
+            }
+        });
+        forward_receiver
+    }
+}
+
+/// Restore the state handoff snapshot at `path` into `state`, and remember
+/// the path for the next snapshot. A snapshot that cannot be read is skipped
+/// and the proxy starts cold.
+fn resume_state(state: &SharedState, path: PathBuf, silent: bool) {
+    let mut state_guard = state.lock_or_recover();
+    match state_handoff::restore(&mut state_guard, &path) {
+        Ok(Some(report)) if !silent => {
+            log_state_handoff_restored(&path, &report.sections, &report.discarded)
+        }
+        Ok(_) => {}
+        Err(e) if !silent => log_state_handoff_error(&format!("{e:#}, starting without it")),
+        Err(_) => {}
+    }
+    state_guard.state_handoff = Some(path);
+}
+
+/// Verify the starting endpoint if configured, then run health checks and the
+/// proxy, or only the health checks when `monitor_only`. The listener is only
+/// bound once the startup check has finished.
+async fn serve(
+    orchestrator: HealthCheckOrchestrator,
+    config: Config,
+    state: SharedState,
+    connection_tracker: SharedConnectionTracker,
+    event_sender: EventSender,
+    mode: RunMode,
+    monitor_only: bool,
+) -> anyhow::Result<()> {
+    if config.health_check.startup_check && !config.health_checks_disabled() {
+        orchestrator.run_startup_check().await;
+    }
+
+    let orchestrator_handle = orchestrator.handle();
+    tokio::spawn(reload_on_sighup(orchestrator_handle.clone()));
+    tokio::spawn(save_state_on_sigusr2(state.clone(), mode.is_silent()));
+    tokio::spawn(run_alert_evaluator(
+        state.clone(),
+        event_sender.clone(),
+        mode.is_silent(),
+    ));
+    if monitor_only {
+        if !mode.is_silent() {
+            log_monitor_start(config.endpoint_index().iter().count());
+        }
+        return orchestrator.run().await;
+    }
+    tokio::spawn(run_drain_monitor(
+        state.clone(),
+        connection_tracker.clone(),
+        event_sender.clone(),
+        mode.is_silent(),
+    ));
+    tokio::spawn(async move {
+        if let Err(e) = orchestrator.run().await {
+            tracing::error!("Health check orchestrator error: {}", e);
+        }
+    });
+
+    proxy::start_proxy_server(
+        config,
+        state,
+        connection_tracker,
+        event_sender,
+        orchestrator_handle,
+        mode,
+    )
+    .await
+}
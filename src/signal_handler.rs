@@ -1,5 +1,11 @@
 use crate::connection_tracker::SharedConnectionTracker;
-use crate::events::ProxyEvent;
+use crate::env_file;
+use crate::events::{CompletionReason, ProxyEvent};
+use crate::health_orchestrator::HealthOrchestratorHandle;
+use crate::i18n;
+use crate::lock::LockExt;
+use crate::logging::console_line;
+use crate::proxy::SharedState;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -27,11 +33,11 @@ impl GracefulShutdown {
     pub async fn wait_for_shutdown(&self) {
         tokio::select! {
             _ = tokio::signal::ctrl_c() => {
-                println!("📨 Received SIGINT (Ctrl+C), performing graceful shutdown...");
+                console_line(i18n::console().received_sigint());
                 self.perform_graceful_shutdown("SIGINT").await;
             }
             _ = self.wait_for_sigterm() => {
-                println!("📨 Received SIGTERM, performing graceful shutdown...");
+                console_line(i18n::console().received_sigterm());
                 self.perform_graceful_shutdown("SIGTERM").await;
             }
         }
@@ -58,32 +64,29 @@ impl GracefulShutdown {
         // Set shutdown flag
         self.shutdown_flag.store(true, Ordering::Relaxed);
 
-        println!("🧹 Cleaning up all active connections due to {signal} signal...");
+        console_line(&i18n::console().cleaning_up_connections(signal));
 
         // Force cleanup all connections
-        let cleaned_connections = {
-            if let Ok(mut tracker) = self.connection_tracker.lock() {
-                tracker.force_cleanup_all_connections()
-            } else {
-                Vec::new()
-            }
-        };
+        let cleaned_connections = self
+            .connection_tracker
+            .lock_or_recover()
+            .force_cleanup_all_connections();
 
         if !cleaned_connections.is_empty() {
-            println!(
-                "🧹 Cleaned up {} active connections",
-                cleaned_connections.len()
-            );
+            console_line(&i18n::console().active_connections_cleaned(cleaned_connections.len()));
 
             // Send cleanup events for all connections
             for connection_id in cleaned_connections {
-                let _ = self
-                    .event_sender
-                    .send(ProxyEvent::ConnectionCompleted(connection_id));
+                let _ = self.event_sender.send(ProxyEvent::ConnectionCompleted {
+                    id: connection_id,
+                    reason: CompletionReason::Shutdown,
+                    phases: None,
+                    timings: None,
+                });
             }
         }
 
-        println!("✅ Graceful shutdown completed");
+        console_line(i18n::console().shutdown_completed());
     }
 
     /// Check if shutdown has been requested (reserved for future use)
@@ -100,24 +103,151 @@ pub async fn emergency_connection_cleanup(
     event_sender: &mpsc::UnboundedSender<ProxyEvent>,
     reason: &str,
 ) {
-    println!("⚠️ Emergency connection cleanup triggered: {reason}");
+    console_line(&i18n::console().emergency_cleanup(reason));
 
-    let cleaned_connections = {
-        if let Ok(mut tracker) = connection_tracker.lock() {
-            tracker.force_cleanup_all_connections()
-        } else {
-            return;
-        }
-    };
+    let cleaned_connections = connection_tracker
+        .lock_or_recover()
+        .force_cleanup_all_connections();
 
     if !cleaned_connections.is_empty() {
-        println!(
-            "🧹 Emergency cleanup: removed {} connections",
-            cleaned_connections.len()
-        );
+        console_line(&i18n::console().emergency_cleanup_done(cleaned_connections.len()));
 
         for connection_id in cleaned_connections {
-            let _ = event_sender.send(ProxyEvent::ConnectionCompleted(connection_id));
+            let _ = event_sender.send(ProxyEvent::ConnectionCompleted {
+                id: connection_id,
+                reason: CompletionReason::Shutdown,
+                phases: None,
+                timings: None,
+            });
+        }
+    }
+}
+
+/// Re-read the env file and then the config file on every SIGHUP, so rotated
+/// tokens and endpoint changes apply without a restart (Unix only)
+#[cfg(unix)]
+pub async fn reload_on_sighup(orchestrator: HealthOrchestratorHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let Ok(mut sighup) = signal(SignalKind::hangup()) else {
+        return;
+    };
+    while sighup.recv().await.is_some() {
+        env_file::reload(true);
+        orchestrator.reload_config();
+    }
+}
+
+/// For non-Unix systems there is no SIGHUP
+#[cfg(not(unix))]
+pub async fn reload_on_sighup(_orchestrator: HealthOrchestratorHandle) {}
+
+/// Write the state handoff snapshot on every SIGUSR2 when `--state-handoff`
+/// is set, and keep serving (Unix only)
+#[cfg(unix)]
+pub async fn save_state_on_sigusr2(state: SharedState, silent: bool) {
+    use crate::logging::{log_state_handoff_error, log_state_handoff_saved};
+    use crate::state_handoff;
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let Some(path) = state.lock_or_recover().state_handoff.clone() else {
+        return;
+    };
+    let Ok(mut sigusr2) = signal(SignalKind::user_defined2()) else {
+        return;
+    };
+    while sigusr2.recv().await.is_some() {
+        match state_handoff::save(&state, &path) {
+            Ok(_) if !silent => log_state_handoff_saved(&path),
+            Ok(_) => {}
+            Err(e) if !silent => log_state_handoff_error(&format!("{e:#}")),
+            Err(_) => {}
+        }
+    }
+}
+
+/// For non-Unix systems there is no SIGUSR2
+#[cfg(not(unix))]
+pub async fn save_state_on_sigusr2(_state: SharedState, _silent: bool) {}
+
+/// Job control signals the dashboard has to react to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalSignal {
+    /// SIGTSTP: give the terminal back to the shell, then stop
+    Suspend,
+    /// SIGCONT: the process was continued and the screen may be stale
+    Resume,
+}
+
+/// Listens for SIGTSTP and SIGCONT while the dashboard owns the terminal.
+/// Once SIGTSTP has a handler the kernel no longer stops the process, so
+/// [`stop_process`] has to be called after the terminal is restored.
+pub struct TerminalSignals {
+    #[cfg(unix)]
+    suspend: Option<tokio::signal::unix::Signal>,
+    #[cfg(unix)]
+    resume: Option<tokio::signal::unix::Signal>,
+}
+
+impl TerminalSignals {
+    pub fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            Self {
+                suspend: signal(SignalKind::from_raw(libc::SIGTSTP)).ok(),
+                resume: signal(SignalKind::from_raw(libc::SIGCONT)).ok(),
+            }
+        }
+        #[cfg(not(unix))]
+        Self {}
+    }
+
+    /// Wait for the next job control signal (never resolves on non-Unix systems)
+    pub async fn recv(&mut self) -> TerminalSignal {
+        #[cfg(unix)]
+        {
+            async fn next(signal: &mut Option<tokio::signal::unix::Signal>) {
+                if let Some(signal) = signal {
+                    if signal.recv().await.is_some() {
+                        return;
+                    }
+                }
+                std::future::pending().await
+            }
+            tokio::select! {
+                _ = next(&mut self.suspend) => TerminalSignal::Suspend,
+                _ = next(&mut self.resume) => TerminalSignal::Resume,
+            }
         }
+        #[cfg(not(unix))]
+        std::future::pending().await
+    }
+}
+
+/// Ask for a suspend the way the shell would. Raw mode turns Ctrl+Z into a
+/// plain key press, so the dashboard forwards it here.
+#[cfg(unix)]
+pub fn request_suspend() {
+    // SAFETY: raise only delivers a signal to the calling process
+    unsafe {
+        libc::raise(libc::SIGTSTP);
     }
 }
+
+/// For non-Unix systems there is no job control
+#[cfg(not(unix))]
+pub fn request_suspend() {}
+
+/// Stop the process until the shell continues it. Returns after `fg`.
+#[cfg(unix)]
+pub fn stop_process() {
+    // SAFETY: raise only delivers a signal to the calling process
+    unsafe {
+        libc::raise(libc::SIGSTOP);
+    }
+}
+
+/// For non-Unix systems there is no job control
+#[cfg(not(unix))]
+pub fn stop_process() {}
@@ -0,0 +1,206 @@
+use crate::config::{Config, FallbackPolicy};
+use crate::events::{SelectionMode, SwitchReason};
+use crate::health::HealthProbe;
+use crate::lock::LockExt;
+use crate::proxy::RunMode;
+use crate::server::ProxyServer;
+use crate::usage::{TokenUsage, UsageParser};
+use hyper::{Body, Client, Method, Request, StatusCode};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// How often to retry connecting while the instance runs its startup check
+const LISTEN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Options for the `smoke` subcommand
+#[derive(clap::Args, Debug)]
+pub struct SmokeArgs {
+    /// Send the request to this endpoint (URL or name) without fallback
+    #[arg(long)]
+    endpoint: Option<String>,
+
+    /// Print JSON instead of text
+    #[arg(long)]
+    json: bool,
+}
+
+/// Outcome of the smoke request
+#[derive(Debug, serde::Serialize)]
+struct SmokeResult {
+    /// Endpoint that answered, after any fallback
+    endpoint: String,
+    name: Option<String>,
+    /// Endpoint the request was first sent to, when fallback moved it elsewhere
+    fallback_from: Option<String>,
+    status: u16,
+    latency_ms: u64,
+    #[serde(flatten)]
+    usage: TokenUsage,
+}
+
+/// Start a throwaway proxy on a free port, send one minimal request through it
+/// and report who answered. Fails if the request could not be served.
+pub async fn run_smoke(mut config: Config, args: &SmokeArgs) -> anyhow::Result<()> {
+    let forced = match &args.endpoint {
+        Some(endpoint) => {
+            let url = config
+                .get_all_endpoints()
+                .into_iter()
+                .map(|(_, configured, _)| configured)
+                .find(|configured| &configured.url == endpoint || &configured.name == endpoint)
+                .map(|configured| configured.url)
+                .ok_or_else(|| anyhow::anyhow!("Unknown endpoint: {endpoint}"))?;
+            // Keep the request on the chosen endpoint no matter how it fares
+            config.health_check.startup_check = false;
+            config.retry.fallback = FallbackPolicy::Off;
+            Some(url)
+        }
+        None => None,
+    };
+    // A forced endpoint is asked what its group's health checks ask
+    let probe = match forced.as_deref().and_then(|url| config.group_of(url)) {
+        Some(group) => config.health_probe(&group.name),
+        None => config.health_check.probe(),
+    };
+
+    let port = std::net::TcpListener::bind(("127.0.0.1", 0))?
+        .local_addr()?
+        .port();
+    let server = ProxyServer::builder(config.clone())
+        .port(port)
+        .mode(RunMode::Dashboard)
+        .record_usage(false)
+        .build();
+    // The startup check still runs, but no scheduled health check cycle
+    server.health_orchestrator().pause();
+
+    let state = server.state();
+    if let Some(url) = forced {
+        let mut state_guard = state.lock_or_recover();
+        state_guard.current_endpoint = state_guard.config.endpoint_index().key_of(&url);
+        state_guard.current_endpoint_reason = Some(SwitchReason::ManualSwitch);
+        state_guard.selection_mode = SelectionMode::Manual;
+    }
+    let metrics = state.lock_or_recover().metrics.clone();
+
+    let (stop_sender, stop_receiver) = oneshot::channel::<()>();
+    let mut server_task = tokio::spawn(server.run_until(stop_receiver));
+
+    let request = async {
+        wait_for_listener(port).await;
+        let starting_endpoint = state.lock_or_recover().current_url().to_string();
+        let before = metrics.endpoint_snapshots();
+        let (status, latency, usage) = send_smoke_request(port, &probe).await?;
+
+        // The only request this instance proxied was credited to whoever answered it
+        let endpoint = metrics
+            .endpoint_snapshots()
+            .into_iter()
+            .find(|(url, snapshot)| {
+                before
+                    .get(url)
+                    .is_none_or(|earlier| snapshot.since(earlier).requests > 0)
+            })
+            .map(|(url, _)| url)
+            .unwrap_or_else(|| starting_endpoint.clone());
+
+        anyhow::Ok(SmokeResult {
+            name: config
+                .get_all_endpoints()
+                .into_iter()
+                .find(|(_, configured, _)| configured.url == endpoint)
+                .map(|(_, configured, _)| configured.name),
+            fallback_from: (endpoint != starting_endpoint).then_some(starting_endpoint),
+            endpoint,
+            status: status.as_u16(),
+            latency_ms: latency.as_millis() as u64,
+            usage,
+        })
+    };
+
+    let result = tokio::select! {
+        result = request => result,
+        server_result = &mut server_task => {
+            return Err(match server_result {
+                Ok(Err(e)) => e,
+                _ => anyhow::anyhow!("Smoke test proxy stopped unexpectedly"),
+            });
+        }
+    }?;
+
+    let _ = stop_sender.send(());
+    let _ = server_task.await;
+
+    print_result(&result, args.json)?;
+    if !StatusCode::from_u16(result.status).is_ok_and(|status| status.is_success()) {
+        anyhow::bail!("No endpoint could serve the smoke request");
+    }
+    Ok(())
+}
+
+/// Wait until the instance accepts connections, which it does only once the
+/// startup check has finished
+async fn wait_for_listener(port: u16) {
+    while tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .is_err()
+    {
+        tokio::time::sleep(LISTEN_POLL_INTERVAL).await;
+    }
+}
+
+/// The health check prompt as a Messages API request body
+pub(crate) fn minimal_request_body(probe: &HealthProbe) -> serde_json::Value {
+    serde_json::json!({
+        "model": probe.model,
+        "max_tokens": 16,
+        "system": probe.system_prompt,
+        "messages": [{ "role": "user", "content": probe.prompt }],
+    })
+}
+
+/// Send the health check prompt as a Messages API request through the proxy
+async fn send_smoke_request(
+    port: u16,
+    probe: &HealthProbe,
+) -> anyhow::Result<(StatusCode, Duration, TokenUsage)> {
+    let body = minimal_request_body(probe);
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(format!("http://127.0.0.1:{port}/v1/messages"))
+        .header("content-type", "application/json")
+        .header("anthropic-version", "2023-06-01")
+        .body(Body::from(serde_json::to_vec(&body)?))?;
+
+    let start = Instant::now();
+    let response = Client::new().request(request).await?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    let latency = start.elapsed();
+
+    let mut parser = UsageParser::new(false);
+    parser.feed(&body);
+    Ok((status, latency, parser.finish()))
+}
+
+fn print_result(result: &SmokeResult, json: bool) -> anyhow::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(result)?);
+        return Ok(());
+    }
+
+    match &result.name {
+        Some(name) => println!("Endpoint: {name} ({})", result.endpoint),
+        None => println!("Endpoint: {}", result.endpoint),
+    }
+    if let Some(from) = &result.fallback_from {
+        println!("Fallback: from {from}");
+    }
+    println!("Status:   {}", result.status);
+    println!("Latency:  {}ms", result.latency_ms);
+    println!(
+        "Tokens:   {} in / {} out",
+        result.usage.input_tokens, result.usage.output_tokens
+    );
+    Ok(())
+}
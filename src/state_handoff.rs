@@ -0,0 +1,416 @@
+//! Runtime state handed from a running proxy to the one replacing it.
+//!
+//! With `--state-handoff <path>`, SIGUSR2 or `POST /admin/prepare-restart`
+//! write a snapshot of the runtime state to `path` while the proxy keeps
+//! serving, and a proxy started with the same flag resumes from it. Each
+//! section carries its own schema version, so a binary that changed one
+//! section's layout discards just that section and keeps the rest warm.
+
+use crate::endpoint::EndpointId;
+use crate::events::{SelectionMode, SwitchReason, SwitchRecord};
+use crate::health::EndpointStatus;
+use crate::lock::LockExt;
+use crate::metrics::CounterSnapshot;
+use crate::proxy::{ProxyState, SharedState};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const ENDPOINTS: &str = "endpoints";
+const SELECTION: &str = "selection";
+const EXCLUSIONS: &str = "exclusions";
+const COUNTERS: &str = "counters";
+const SWITCH_HISTORY: &str = "switch_history";
+
+/// Schema version of each section; bump one when its layout changes
+const ENDPOINTS_VERSION: u32 = 2;
+const SELECTION_VERSION: u32 = 1;
+const EXCLUSIONS_VERSION: u32 = 1;
+const COUNTERS_VERSION: u32 = 1;
+const SWITCH_HISTORY_VERSION: u32 = 1;
+
+/// The file as written: sections stay raw JSON until their version is checked
+#[derive(Debug, Serialize, Deserialize)]
+struct SnapshotFile {
+    written_at: DateTime<Utc>,
+    /// Version of the proxy that wrote the file
+    written_by: String,
+    sections: BTreeMap<String, Section>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Section {
+    version: u32,
+    data: serde_json::Value,
+}
+
+/// How the current endpoint was chosen, with the current endpoint by id so a
+/// reload that changed its URL in between does not lose it
+#[derive(Debug, Serialize, Deserialize)]
+struct SelectionSection {
+    mode: SelectionMode,
+    current_endpoint: Option<String>,
+    current_endpoint_reason: Option<SwitchReason>,
+    current_endpoint_since: DateTime<Utc>,
+    switching_frozen: bool,
+    tag_filter: Vec<String>,
+}
+
+/// What was written or restored
+#[derive(Debug, Default, Serialize)]
+pub struct HandoffReport {
+    /// Sections written or restored
+    pub sections: Vec<&'static str>,
+    /// Sections in the file this binary cannot read, with why
+    pub discarded: Vec<String>,
+}
+
+/// Write the runtime state to `path`, replacing it atomically. The state
+/// lock is only held while the snapshot is serialized.
+pub fn save(state: &SharedState, path: &Path) -> anyhow::Result<HandoffReport> {
+    let contents = serde_json::to_vec_pretty(&snapshot(&state.lock_or_recover())?)?;
+
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    fs::write(&temporary, contents)
+        .with_context(|| format!("Failed to write {}", temporary.display()))?;
+    fs::rename(&temporary, path)
+        .with_context(|| format!("Failed to replace {}", path.display()))?;
+
+    Ok(HandoffReport {
+        sections: vec![ENDPOINTS, SELECTION, EXCLUSIONS, COUNTERS, SWITCH_HISTORY],
+        discarded: Vec::new(),
+    })
+}
+
+fn snapshot(state: &ProxyState) -> anyhow::Result<SnapshotFile> {
+    let endpoints: BTreeMap<&str, &EndpointStatus> = state
+        .endpoint_status
+        .iter()
+        .map(|(id, status)| (id.as_str(), status))
+        .collect();
+    let selection = SelectionSection {
+        mode: state.selection_mode,
+        current_endpoint: Some(state.current_endpoint.to_string()),
+        current_endpoint_reason: state.current_endpoint_reason,
+        current_endpoint_since: state.current_endpoint_since,
+        switching_frozen: state.switching_frozen,
+        tag_filter: state.tag_filter.clone(),
+    };
+    let exclusions: BTreeMap<&str, u64> = state
+        .auth_rejected
+        .iter()
+        .map(|(id, fingerprint)| (id.as_str(), *fingerprint))
+        .collect();
+
+    let mut sections = BTreeMap::new();
+    let mut add = |name: &str, version: u32, data| {
+        sections.insert(name.to_string(), Section { version, data });
+    };
+    add(
+        ENDPOINTS,
+        ENDPOINTS_VERSION,
+        serde_json::to_value(endpoints)?,
+    );
+    add(
+        SELECTION,
+        SELECTION_VERSION,
+        serde_json::to_value(selection)?,
+    );
+    add(
+        EXCLUSIONS,
+        EXCLUSIONS_VERSION,
+        serde_json::to_value(exclusions)?,
+    );
+    add(
+        COUNTERS,
+        COUNTERS_VERSION,
+        serde_json::to_value(state.metrics.snapshots_by_id())?,
+    );
+    add(
+        SWITCH_HISTORY,
+        SWITCH_HISTORY_VERSION,
+        serde_json::to_value(&state.switch_history)?,
+    );
+
+    Ok(SnapshotFile {
+        written_at: Utc::now(),
+        written_by: env!("CARGO_PKG_VERSION").to_string(),
+        sections,
+    })
+}
+
+/// Restore the snapshot at `path` into a freshly created state, then remove
+/// the file so a later start cannot resume from stale state. `None` when
+/// there is no snapshot.
+///
+/// Endpoints are matched by id; state of endpoints no longer configured is
+/// dropped.
+pub fn restore(state: &mut ProxyState, path: &Path) -> anyhow::Result<Option<HandoffReport>> {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let file: SnapshotFile = serde_json::from_slice(&contents)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+
+    let mut report = HandoffReport::default();
+    let mut sections = file.sections;
+
+    let endpoints: Option<BTreeMap<String, EndpointStatus>> =
+        take(&mut sections, ENDPOINTS, ENDPOINTS_VERSION, &mut report);
+    if let Some(endpoints) = endpoints {
+        for (id, mut status) in endpoints {
+            let id = EndpointId::new(&id);
+            let Some(endpoint) = state.config.endpoint_index().get(&id) else {
+                continue;
+            };
+            status.endpoint = endpoint.url.clone();
+            state.endpoint_status.insert(id, status);
+        }
+        report.sections.push(ENDPOINTS);
+    }
+
+    let selection: Option<SelectionSection> =
+        take(&mut sections, SELECTION, SELECTION_VERSION, &mut report);
+    if let Some(selection) = selection {
+        let current = selection
+            .current_endpoint
+            .map(|id| EndpointId::new(&id))
+            .filter(|id| state.config.endpoint_index().get(id).is_some());
+        if let Some(current) = current {
+            state.current_endpoint = current;
+            state.current_endpoint_reason = selection.current_endpoint_reason;
+            state.current_endpoint_since = selection.current_endpoint_since;
+        }
+        state.selection_mode = selection.mode;
+        state.switching_frozen = selection.switching_frozen;
+        let known_tags = state.config.all_tags();
+        state.tag_filter = selection
+            .tag_filter
+            .into_iter()
+            .filter(|tag| known_tags.contains(tag))
+            .collect();
+        report.sections.push(SELECTION);
+    }
+
+    let exclusions: Option<BTreeMap<String, u64>> =
+        take(&mut sections, EXCLUSIONS, EXCLUSIONS_VERSION, &mut report);
+    if let Some(exclusions) = exclusions {
+        for (id, fingerprint) in exclusions {
+            let id = EndpointId::new(&id);
+            if state.config.endpoint_index().get(&id).is_some() {
+                state.auth_rejected.insert(id, fingerprint);
+            }
+        }
+        report.sections.push(EXCLUSIONS);
+    }
+
+    let counters: Option<BTreeMap<String, CounterSnapshot>> =
+        take(&mut sections, COUNTERS, COUNTERS_VERSION, &mut report);
+    if let Some(counters) = counters {
+        state.metrics.restore_counters(&counters);
+        report.sections.push(COUNTERS);
+    }
+
+    let switch_history: Option<Vec<SwitchRecord>> = take(
+        &mut sections,
+        SWITCH_HISTORY,
+        SWITCH_HISTORY_VERSION,
+        &mut report,
+    );
+    if let Some(switch_history) = switch_history {
+        state.restore_switch_history(switch_history);
+        report.sections.push(SWITCH_HISTORY);
+    }
+
+    report.discarded.extend(
+        sections
+            .into_keys()
+            .map(|name| format!("{name} (unknown section)")),
+    );
+    Ok(Some(report))
+}
+
+/// Remove the section `name` and parse it, or record why it was discarded:
+/// a version other than `version`, or data that no longer parses
+fn take<T: DeserializeOwned>(
+    sections: &mut BTreeMap<String, Section>,
+    name: &str,
+    version: u32,
+    report: &mut HandoffReport,
+) -> Option<T> {
+    let section = sections.remove(name)?;
+    if section.version != version {
+        report.discarded.push(format!(
+            "{name} (version {}, expected {version})",
+            section.version
+        ));
+        return None;
+    }
+    serde_json::from_value(section.data)
+        .map_err(|e| report.discarded.push(format!("{name} ({e})")))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::health::CheckState;
+    use crate::metrics::RequestOutcome;
+    use crate::test_support::{config, TWO_GROUPS};
+    use std::sync::{Arc, Mutex};
+
+    const ALPHA: &str = "http://127.0.0.1:19001";
+    const BETA: &str = "http://127.0.0.1:19002";
+
+    /// `TWO_GROUPS` with `beta` tagged `eu`
+    fn tagged_config() -> crate::config::Config {
+        config(&TWO_GROUPS.replace(r#"name = "beta" }"#, r#"name = "beta", tags = ["eu"] }"#))
+    }
+
+    /// A path of its own for each test's snapshot
+    fn snapshot_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("zephyr-handoff-{}-{name}.json", std::process::id()))
+    }
+
+    /// A state with something in every section
+    fn busy_state() -> SharedState {
+        let mut state = ProxyState::new(tagged_config());
+        let beta = state
+            .endpoint_status
+            .get_mut(&EndpointId::new("beta"))
+            .unwrap();
+        beta.state = CheckState::Healthy;
+        beta.latency = Some(120);
+        beta.latency_history.add_measurement(Some(120), None);
+        beta.latency_history
+            .add_measurement(None, Some("timeout".to_string()));
+        state.selection_mode = SelectionMode::Manual;
+        state.switch_endpoint(BETA.to_string(), SwitchReason::ManualSwitch, true);
+        state.switching_frozen = true;
+        state.tag_filter = vec!["eu".to_string()];
+        state.auth_rejected.insert(EndpointId::new("alpha"), 42);
+        state.metrics.record_request(ALPHA, RequestOutcome::Success);
+        state
+            .metrics
+            .record_request(ALPHA, RequestOutcome::EndpointError);
+        Arc::new(Mutex::new(state))
+    }
+
+    fn json<T: Serialize>(value: &T) -> serde_json::Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    #[test]
+    fn every_section_survives_a_round_trip() {
+        let path = snapshot_path("round-trip");
+        let saved = busy_state();
+        let report = save(&saved, &path).unwrap();
+        assert_eq!(report.sections.len(), 5);
+
+        let mut restored = ProxyState::new(tagged_config());
+        let report = restore(&mut restored, &path).unwrap().unwrap();
+        assert_eq!(
+            report.sections,
+            [ENDPOINTS, SELECTION, EXCLUSIONS, COUNTERS, SWITCH_HISTORY]
+        );
+        assert!(report.discarded.is_empty(), "{:?}", report.discarded);
+        assert!(!path.exists(), "the snapshot is removed once restored");
+
+        let saved = saved.lock().unwrap();
+        let id = |id: &str| EndpointId::new(id);
+        assert_eq!(
+            json(&restored.endpoint_status[&id("beta")]),
+            json(&saved.endpoint_status[&id("beta")])
+        );
+        assert_eq!(restored.current_endpoint, id("beta"));
+        assert_eq!(
+            restored.current_endpoint_reason,
+            Some(SwitchReason::ManualSwitch)
+        );
+        assert_eq!(
+            restored.current_endpoint_since,
+            saved.current_endpoint_since
+        );
+        assert_eq!(restored.selection_mode, SelectionMode::Manual);
+        assert!(restored.switching_frozen);
+        assert_eq!(restored.tag_filter, ["eu"]);
+        assert_eq!(restored.auth_rejected.get(&id("alpha")), Some(&42));
+        let counters = restored.metrics.snapshots_by_id();
+        assert_eq!(counters["alpha"].requests, 2);
+        assert_eq!(counters["alpha"].endpoint_errors, 1);
+        assert_eq!(json(&restored.switch_history), json(&saved.switch_history));
+    }
+
+    #[test]
+    fn missing_snapshot_is_a_cold_start() {
+        let mut state = ProxyState::new(config(TWO_GROUPS));
+        let path = snapshot_path("missing");
+        assert!(restore(&mut state, &path).unwrap().is_none());
+    }
+
+    #[test]
+    fn section_of_another_version_is_discarded_alone() {
+        let path = snapshot_path("version");
+        save(&busy_state(), &path).unwrap();
+        let mut file: SnapshotFile = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        file.sections.get_mut(SELECTION).unwrap().version = SELECTION_VERSION + 1;
+        file.sections.insert(
+            "future".to_string(),
+            Section {
+                version: 1,
+                data: serde_json::Value::Null,
+            },
+        );
+        fs::write(&path, serde_json::to_vec(&file).unwrap()).unwrap();
+
+        let mut state = ProxyState::new(tagged_config());
+        let report = restore(&mut state, &path).unwrap().unwrap();
+        assert_eq!(
+            report.sections,
+            [ENDPOINTS, EXCLUSIONS, COUNTERS, SWITCH_HISTORY]
+        );
+        assert_eq!(
+            report.discarded,
+            [
+                "selection (version 2, expected 1)".to_string(),
+                "future (unknown section)".to_string()
+            ]
+        );
+        assert_eq!(state.current_endpoint, EndpointId::new("alpha"));
+        assert_eq!(state.selection_mode, SelectionMode::Auto);
+        assert_eq!(state.auth_rejected.len(), 1);
+    }
+
+    #[test]
+    fn state_of_removed_endpoints_is_dropped() {
+        let path = snapshot_path("removed");
+        save(&busy_state(), &path).unwrap();
+
+        // beta is gone from the new config, and so is its tag
+        let toml = TWO_GROUPS.replace(r#"name = "beta""#, r#"name = "gamma""#);
+        let mut state = ProxyState::new(config(&toml));
+        restore(&mut state, &path).unwrap().unwrap();
+
+        assert!(!state.endpoint_status.contains_key(&EndpointId::new("beta")));
+        assert_eq!(
+            state.endpoint_status[&EndpointId::new("gamma")].state,
+            CheckState::Unchecked
+        );
+        assert_eq!(state.current_endpoint, EndpointId::new("alpha"));
+        assert!(state.tag_filter.is_empty());
+        // Still configured, so still excluded
+        assert_eq!(
+            state.auth_rejected.get(&EndpointId::new("alpha")),
+            Some(&42)
+        );
+    }
+}
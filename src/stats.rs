@@ -0,0 +1,108 @@
+//! Summary statistics shared by the diagnostics, the dashboard and alerts.
+
+use serde::Serialize;
+
+/// Nearest-rank percentile of sorted, non-empty values
+pub(crate) fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+/// Count, range, median, 90th percentile and mean of a set of values, all 0
+/// when there are none
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Summary {
+    pub count: usize,
+    pub min: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub max: u64,
+    pub mean: f64,
+}
+
+impl Summary {
+    pub fn of(values: impl IntoIterator<Item = u64>) -> Self {
+        let mut values: Vec<u64> = values.into_iter().collect();
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_unstable();
+        Self {
+            count: values.len(),
+            min: values[0],
+            p50: percentile(&values, 50),
+            p90: percentile(&values, 90),
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<u64>() as f64 / values.len() as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_is_the_nearest_rank() {
+        let values: Vec<u64> = (1..=10).collect();
+        assert_eq!(percentile(&values, 0), 1);
+        assert_eq!(percentile(&values, 10), 1);
+        assert_eq!(percentile(&values, 11), 2);
+        assert_eq!(percentile(&values, 50), 5);
+        assert_eq!(percentile(&values, 90), 9);
+        assert_eq!(percentile(&values, 91), 10);
+        assert_eq!(percentile(&values, 100), 10);
+    }
+
+    #[test]
+    fn percentile_of_a_single_value_is_that_value() {
+        for percent in [0, 1, 50, 99, 100] {
+            assert_eq!(percentile(&[42], percent), 42);
+        }
+    }
+
+    #[test]
+    fn summary_of_nothing_is_all_zero() {
+        assert_eq!(Summary::of([]), Summary::default());
+        assert_eq!(Summary::of([]).count, 0);
+        assert_eq!(Summary::of([]).mean, 0.0);
+    }
+
+    #[test]
+    fn summary_of_one_value() {
+        assert_eq!(
+            Summary::of([7]),
+            Summary {
+                count: 1,
+                min: 7,
+                p50: 7,
+                p90: 7,
+                max: 7,
+                mean: 7.0,
+            }
+        );
+    }
+
+    #[test]
+    fn summary_does_not_need_sorted_values() {
+        assert_eq!(
+            Summary::of([900, 100, 500, 300, 200, 800, 400, 1000, 700, 600]),
+            Summary {
+                count: 10,
+                min: 100,
+                p50: 500,
+                p90: 900,
+                max: 1000,
+                mean: 550.0,
+            }
+        );
+    }
+
+    #[test]
+    fn summary_keeps_duplicates_and_a_fractional_mean() {
+        let summary = Summary::of([1, 1, 2]);
+        assert_eq!(summary.count, 3);
+        assert_eq!((summary.min, summary.p50, summary.max), (1, 1, 2));
+        assert!((summary.mean - 4.0 / 3.0).abs() < 1e-9, "{}", summary.mean);
+    }
+}
@@ -0,0 +1,345 @@
+use crate::config::StorageConfig;
+use crate::events::ActiveConnection;
+use crate::usage::TokenUsage;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tokio::sync::mpsc;
+
+/// SQLite file written next to config.toml
+pub const DEFAULT_STORAGE_FILE: &str = "requests.db";
+
+pub type RequestRecordSender = mpsc::UnboundedSender<RequestRecord>;
+
+/// One completed proxied request
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub struct RequestRecord {
+    /// When the request arrived
+    pub timestamp: DateTime<Utc>,
+    /// Id of the endpoint that served it, after any fallback
+    pub endpoint: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub duration_ms: u64,
+    /// Upstream attempts beyond the first, across retries and fallback
+    pub retries: u32,
+    /// Response body bytes forwarded to the client
+    pub bytes: u64,
+    pub usage: TokenUsage,
+}
+
+impl RequestRecord {
+    /// Record for a tracked connection that just completed, under the
+    /// endpoint's URL until [`ProxyState`](crate::proxy::ProxyState) maps it to its id
+    pub fn completed(
+        connection: &ActiveConnection,
+        status: u16,
+        retries: u32,
+        bytes: u64,
+        usage: TokenUsage,
+    ) -> Self {
+        let (method, path) = connection
+            .request_info
+            .as_ref()
+            .map(|info| (info.method.clone(), info.path.clone()))
+            .unwrap_or_default();
+        Self {
+            timestamp: connection.start_time,
+            endpoint: connection.endpoint.clone(),
+            method,
+            path,
+            status,
+            duration_ms: connection.duration(),
+            retries,
+            bytes,
+            usage,
+        }
+    }
+}
+
+/// Stored requests of one endpoint over a time range
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct RequestTotals {
+    pub requests: u64,
+    /// Requests answered with a 408, 429 or 5xx status, by the upstream or the
+    /// proxy; other 4xx are the client's and not counted
+    pub errors: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub retries: u64,
+    pub total_duration_ms: u64,
+}
+
+impl RequestTotals {
+    pub fn add(&mut self, other: &RequestTotals) {
+        self.requests += other.requests;
+        self.errors += other.errors;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.retries += other.retries;
+        self.total_duration_ms += other.total_duration_ms;
+    }
+
+    /// Percentage of requests that failed
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 * 100.0 / self.requests as f64
+        }
+    }
+
+    pub fn average_duration_ms(&self) -> u64 {
+        self.total_duration_ms
+            .checked_div(self.requests)
+            .unwrap_or_default()
+    }
+}
+
+/// Open the SQLite file and start the thread writing records to it. Records
+/// are queued on an unbounded channel, so sending never waits for the disk.
+///
+/// A file written before endpoints had ids has its URLs replaced by the ids in
+/// `endpoint_ids` on first open.
+#[cfg(feature = "sqlite")]
+pub fn spawn_storage_writer(
+    config: &StorageConfig,
+    endpoint_ids: &HashMap<String, String>,
+) -> anyhow::Result<RequestRecordSender> {
+    let connection = sqlite::open(&config.path, endpoint_ids)?;
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let retention_days = config.retention_days;
+    std::thread::Builder::new()
+        .name("request-storage".to_string())
+        .spawn(move || sqlite::run_writer(connection, receiver, retention_days))?;
+    Ok(sender)
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn spawn_storage_writer(
+    _config: &StorageConfig,
+    _endpoint_ids: &HashMap<String, String>,
+) -> anyhow::Result<RequestRecordSender> {
+    anyhow::bail!("Request storage needs a build with `--features sqlite`")
+}
+
+/// Per-endpoint totals of the requests that arrived in `from..to`
+#[cfg(feature = "sqlite")]
+pub fn load_totals(
+    path: &Path,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> anyhow::Result<BTreeMap<String, RequestTotals>> {
+    sqlite::load_totals(path, from, to)
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn load_totals(
+    _path: &Path,
+    _from: DateTime<Utc>,
+    _to: DateTime<Utc>,
+) -> anyhow::Result<BTreeMap<String, RequestTotals>> {
+    anyhow::bail!("Reading request storage needs a build with `--features sqlite`")
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::{RequestRecord, RequestTotals};
+    use crate::logging::log_storage_error;
+    use anyhow::Context;
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use rusqlite::{params, Connection, OpenFlags};
+    use std::collections::{BTreeMap, HashMap};
+    use std::path::Path;
+    use std::time::{Duration, Instant};
+    use tokio::sync::mpsc;
+
+    /// Most records written in one transaction
+    const BATCH_SIZE: usize = 256;
+
+    /// `user_version` of a file whose `endpoint` column holds ids
+    const SCHEMA_VERSION: i64 = 1;
+
+    /// How often records past the retention are deleted
+    const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS requests (
+            id INTEGER PRIMARY KEY,
+            timestamp TEXT NOT NULL,
+            endpoint TEXT NOT NULL,
+            method TEXT NOT NULL,
+            path TEXT NOT NULL,
+            status INTEGER NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            retries INTEGER NOT NULL,
+            bytes INTEGER NOT NULL,
+            input_tokens INTEGER NOT NULL,
+            output_tokens INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS requests_timestamp ON requests (timestamp);
+    ";
+
+    /// Timestamps are stored as fixed-width UTC text so they sort and compare
+    /// as strings
+    fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+        timestamp.to_rfc3339_opts(SecondsFormat::Millis, true)
+    }
+
+    pub(super) fn open(
+        path: &Path,
+        endpoint_ids: &HashMap<String, String>,
+    ) -> anyhow::Result<Connection> {
+        let mut connection = Connection::open(path)
+            .with_context(|| format!("Failed to open request storage {}", path.display()))?;
+        // Lets `report --from-db` read while the proxy writes
+        connection
+            .pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        connection
+            .execute_batch(SCHEMA)
+            .with_context(|| format!("Failed to create tables in {}", path.display()))?;
+        migrate_to_ids(&mut connection, endpoint_ids)
+            .with_context(|| format!("Failed to migrate {}", path.display()))?;
+        Ok(connection)
+    }
+
+    /// Replace endpoint URLs recorded by older versions with the ids of the
+    /// endpoints configured at those URLs, once per file
+    fn migrate_to_ids(
+        connection: &mut Connection,
+        endpoint_ids: &HashMap<String, String>,
+    ) -> anyhow::Result<()> {
+        let version: i64 = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version >= SCHEMA_VERSION {
+            return Ok(());
+        }
+
+        let transaction = connection.transaction()?;
+        {
+            let mut statement =
+                transaction.prepare("UPDATE requests SET endpoint = ?1 WHERE endpoint = ?2")?;
+            for (url, id) in endpoint_ids {
+                statement.execute(params![id, url])?;
+            }
+        }
+        transaction.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Write records as they arrive, batching whatever queued up behind the
+    /// first one, until every sender is gone
+    pub(super) fn run_writer(
+        mut connection: Connection,
+        mut receiver: mpsc::UnboundedReceiver<RequestRecord>,
+        retention_days: u64,
+    ) {
+        let mut last_prune: Option<Instant> = None;
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+
+        loop {
+            if retention_days > 0 && last_prune.is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL) {
+                if let Err(e) = prune(&connection, retention_days) {
+                    log_storage_error(&format!("{e:#}"));
+                }
+                last_prune = Some(Instant::now());
+            }
+
+            let Some(record) = receiver.blocking_recv() else {
+                break;
+            };
+            batch.push(record);
+            while batch.len() < BATCH_SIZE {
+                match receiver.try_recv() {
+                    Ok(record) => batch.push(record),
+                    Err(_) => break,
+                }
+            }
+
+            if let Err(e) = insert_batch(&mut connection, &batch) {
+                log_storage_error(&format!("{e:#}"));
+            }
+            batch.clear();
+        }
+    }
+
+    fn insert_batch(connection: &mut Connection, batch: &[RequestRecord]) -> anyhow::Result<()> {
+        let transaction = connection.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached(
+                "INSERT INTO requests (timestamp, endpoint, method, path, status, duration_ms,
+                     retries, bytes, input_tokens, output_tokens)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )?;
+            for record in batch {
+                statement.execute(params![
+                    format_timestamp(record.timestamp),
+                    record.endpoint,
+                    record.method,
+                    record.path,
+                    record.status,
+                    record.duration_ms,
+                    record.retries,
+                    record.bytes,
+                    record.usage.input_tokens,
+                    record.usage.output_tokens,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn prune(connection: &Connection, retention_days: u64) -> anyhow::Result<()> {
+        let Some(cutoff) = Utc::now().checked_sub_days(chrono::Days::new(retention_days)) else {
+            return Ok(());
+        };
+        connection.execute(
+            "DELETE FROM requests WHERE timestamp < ?1",
+            params![format_timestamp(cutoff)],
+        )?;
+        Ok(())
+    }
+
+    pub(super) fn load_totals(
+        path: &Path,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> anyhow::Result<BTreeMap<String, RequestTotals>> {
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Failed to open request storage {}", path.display()))?;
+        let mut statement = connection.prepare(
+            "SELECT endpoint, COUNT(*), SUM(status >= 500 OR status IN (408, 429)),
+                 SUM(input_tokens), SUM(output_tokens), SUM(retries), SUM(duration_ms)
+             FROM requests
+             WHERE timestamp >= ?1 AND timestamp < ?2
+             GROUP BY endpoint",
+        )?;
+        let rows = statement.query_map(
+            params![format_timestamp(from), format_timestamp(to)],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    RequestTotals {
+                        requests: row.get(1)?,
+                        errors: row.get(2)?,
+                        input_tokens: row.get(3)?,
+                        output_tokens: row.get(4)?,
+                        retries: row.get(5)?,
+                        total_duration_ms: row.get(6)?,
+                    },
+                ))
+            },
+        )?;
+
+        let mut totals = BTreeMap::new();
+        for row in rows {
+            let (endpoint, counts) = row?;
+            totals.insert(endpoint, counts);
+        }
+        Ok(totals)
+    }
+}
@@ -0,0 +1,371 @@
+use crate::body_log::{self, BodyKind};
+use crate::config::StreamStallAction;
+use crate::connection_tracker::{ConnectionId, EventSender, SharedConnectionTracker};
+use crate::events::{CompletionReason, ProxyEvent, RequestTimings};
+use crate::lock::LockExt;
+use crate::logging::{
+    log_failure_in_switch_grace, log_proxy_error, log_proxy_response, log_stream_stalled,
+};
+use crate::metrics::InFlightRequest;
+use crate::proxy::{mark_endpoint_failed, SharedState};
+use crate::storage::RequestRecord;
+use crate::usage::UsageParser;
+use futures::Stream;
+use hyper::body::Bytes;
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::http::response::Parts;
+use hyper::Body;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::time::Sleep;
+
+/// Minimum gap between two progress events for the same connection
+const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_secs(1);
+
+type StreamError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Timeouts applied while a response body streams to the client
+#[derive(Debug, Clone, Copy)]
+pub struct StreamLimits {
+    /// Upper bound for the whole body transfer, from the response headers on
+    pub total_timeout: Duration,
+    /// Longest allowed gap between two chunks, `None` to never abort
+    pub idle_timeout: Option<Duration>,
+    pub stall_action: StreamStallAction,
+}
+
+/// Forwards an upstream response body to the client chunk by chunk.
+///
+/// Counts the bytes passing through, reports them to the dashboard as
+/// `ConnectionProgress` events, records token usage for cost estimates,
+/// aborts streams that stall or run past the total timeout, and completes the
+/// tracked connection when the body ends, fails, or the client goes away.
+pub struct ProgressBody {
+    inner: Body,
+    connection_id: ConnectionId,
+    endpoint: String,
+    state: SharedState,
+    connection_tracker: SharedConnectionTracker,
+    event_sender: EventSender,
+    silent_mode: bool,
+    limits: StreamLimits,
+    is_sse: bool,
+    /// Response status and upstream retries, for the stored request record
+    status: u16,
+    retries: u32,
+    usage: UsageParser,
+    bytes_received: u64,
+    /// Length the upstream announced, `None` for a chunked body
+    content_length: Option<u64>,
+    /// Timings up to the response headers, completed when the body ends
+    timings: RequestTimings,
+    headers_at: Instant,
+    /// When the upstream body ended; the connection completes once hyper has
+    /// written the end of the response and drops the body
+    upstream_done_at: Option<Instant>,
+    last_chunk_at: Instant,
+    last_event_at: Option<Instant>,
+    deadline: Pin<Box<Sleep>>,
+    idle_deadline: Option<Pin<Box<Sleep>>>,
+    outcome: Option<CompletionReason>,
+    /// Slot under `server.max_in_flight_requests`, released with the connection
+    in_flight: Option<InFlightRequest>,
+    /// The body so far, kept while debug logs show bodies
+    captured: Option<Vec<u8>>,
+}
+
+impl ProgressBody {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        inner: Body,
+        parts: &Parts,
+        retries: u32,
+        connection_id: ConnectionId,
+        endpoint: String,
+        state: SharedState,
+        connection_tracker: SharedConnectionTracker,
+        event_sender: EventSender,
+        limits: StreamLimits,
+        timings: RequestTimings,
+        in_flight: InFlightRequest,
+        silent_mode: bool,
+    ) -> Self {
+        let is_sse = parts
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        let content_length = parts
+            .headers
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+
+        Self {
+            inner,
+            connection_id,
+            endpoint,
+            state,
+            connection_tracker,
+            event_sender,
+            silent_mode,
+            limits,
+            is_sse,
+            status: parts.status.as_u16(),
+            retries,
+            usage: UsageParser::new(is_sse),
+            bytes_received: 0,
+            content_length,
+            timings,
+            headers_at: Instant::now(),
+            // An empty body has nothing to poll
+            upstream_done_at: (content_length == Some(0)).then(Instant::now),
+            last_chunk_at: Instant::now(),
+            last_event_at: None,
+            deadline: Box::pin(tokio::time::sleep(limits.total_timeout)),
+            idle_deadline: limits
+                .idle_timeout
+                .map(|idle| Box::pin(tokio::time::sleep(idle))),
+            outcome: None,
+            in_flight: Some(in_flight),
+            captured: (!silent_mode && body_log::enabled()).then(Vec::new),
+        }
+    }
+
+    /// Wrap into a hyper body that streams straight to the client
+    pub fn into_body(self) -> Body {
+        Body::wrap_stream(self)
+    }
+
+    fn record_chunk(&mut self, chunk: &Bytes) {
+        let now = Instant::now();
+        self.bytes_received += chunk.len() as u64;
+        self.usage.feed(chunk);
+        if let Some(captured) = &mut self.captured {
+            captured.extend_from_slice(chunk);
+        }
+        self.last_chunk_at = now;
+        // hyper writes the last bytes and drops the body without polling for
+        // its end, so the upstream is done once the announced length is in
+        if self.content_length == Some(self.bytes_received) {
+            self.upstream_done_at = Some(now);
+        }
+
+        if let (Some(idle_deadline), Some(idle)) =
+            (self.idle_deadline.as_mut(), self.limits.idle_timeout)
+        {
+            idle_deadline
+                .as_mut()
+                .reset(tokio::time::Instant::now() + idle);
+        }
+
+        let due = self
+            .last_event_at
+            .is_none_or(|last| now.duration_since(last) >= PROGRESS_EVENT_INTERVAL);
+        if due {
+            self.last_event_at = Some(now);
+            self.connection_tracker
+                .lock_or_recover()
+                .update_bytes_received(&self.connection_id, self.bytes_received);
+            let _ = self.event_sender.send(ProxyEvent::ConnectionProgress {
+                id: self.connection_id.clone(),
+                bytes_received: self.bytes_received,
+                last_chunk_at: now,
+            });
+        }
+    }
+
+    /// Request timings with the body transfer and, for a body that ended,
+    /// the response write
+    fn timings(&self) -> RequestTimings {
+        let upstream_end = self.upstream_done_at.unwrap_or(self.last_chunk_at);
+        RequestTimings {
+            body_transfer_us: upstream_end.duration_since(self.headers_at).as_micros() as u64,
+            response_write_us: self
+                .upstream_done_at
+                .map_or(0, |done| done.elapsed().as_micros() as u64),
+            ..self.timings
+        }
+    }
+
+    /// Release the tracked connection exactly once
+    fn finish(&mut self, outcome: CompletionReason) {
+        if self.outcome.is_some() {
+            return;
+        }
+        self.outcome = Some(outcome);
+        self.in_flight = None;
+
+        // Drop the upstream body so an aborted upstream connection is closed now
+        if outcome != CompletionReason::Completed {
+            self.inner = Body::empty();
+        }
+
+        // A body cut short is not a response worth logging as forwarded
+        if outcome == CompletionReason::Completed && !self.silent_mode {
+            log_proxy_response(&self.endpoint, self.bytes_received);
+        }
+        if let Some(body) = self.captured.take() {
+            let logging = self.state.lock_or_recover().config.logging.clone();
+            body_log::record(&logging, &self.connection_id, BodyKind::Response, &body);
+        }
+
+        // Tokens already generated are billed even if the stream was cut short
+        let usage = self.usage.finish();
+        if !usage.is_empty() {
+            self.state
+                .lock_or_recover()
+                .metrics
+                .record_usage(&self.endpoint, usage);
+        }
+
+        let timings = self.timings();
+        let completed = {
+            let mut tracker = self.connection_tracker.lock_or_recover();
+            let completed =
+                tracker.complete_connection(&self.connection_id, outcome, Some(self.status));
+            if let Some(connection) = &completed {
+                tracker.record_timings(&connection.endpoint_id, timings);
+            }
+            completed
+        };
+        if let Some(connection) = completed {
+            let _ = self.event_sender.send(ProxyEvent::ConnectionCompleted {
+                id: self.connection_id.clone(),
+                reason: outcome,
+                phases: Some(connection.phase_durations()),
+                timings: Some(timings),
+            });
+            self.state
+                .lock_or_recover()
+                .record_request(RequestRecord::completed(
+                    &connection,
+                    self.status,
+                    self.retries,
+                    self.bytes_received,
+                    usage,
+                ));
+        }
+    }
+
+    fn fail(&mut self, outcome: CompletionReason, message: &str) {
+        if !self.silent_mode {
+            log_proxy_error(&self.endpoint, message, Some(&self.connection_id));
+        }
+        self.finish(outcome);
+    }
+
+    /// Abort a stream that has gone quiet and count it against the endpoint
+    fn stall(&mut self) -> Option<Result<Bytes, StreamError>> {
+        let idle_seconds = self.last_chunk_at.elapsed().as_secs();
+        if !self.silent_mode {
+            log_stream_stalled(&self.endpoint, idle_seconds, self.bytes_received);
+        }
+        let error = format!("Stream stalled: no data for {idle_seconds}s");
+        let excused = self
+            .state
+            .lock_or_recover()
+            .excuse_failure_in_grace(&self.endpoint, chrono::Utc::now());
+        if excused {
+            if !self.silent_mode {
+                log_failure_in_switch_grace(&self.endpoint);
+            }
+        } else {
+            mark_endpoint_failed(&self.state, &self.endpoint, error.clone());
+        }
+        self.state
+            .lock_or_recover()
+            .metrics
+            .record_stream_stall(&self.endpoint);
+        self.finish(CompletionReason::StreamStalled);
+
+        match self.limits.stall_action {
+            StreamStallAction::ErrorEvent if self.is_sse => {
+                Some(Ok(Bytes::from(sse_error_event(&error))))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Terminal SSE event in the shape of an Anthropic streaming error
+fn sse_error_event(message: &str) -> String {
+    let data = serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": "proxy_error",
+            "message": message,
+        }
+    });
+    format!("event: error\ndata: {data}\n\n")
+}
+
+impl Stream for ProgressBody {
+    type Item = Result<Bytes, StreamError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if this.outcome.is_some() || this.upstream_done_at.is_some() {
+            return Poll::Ready(None);
+        }
+
+        if this.deadline.as_mut().poll(cx).is_ready() {
+            this.state
+                .lock_or_recover()
+                .metrics
+                .record_body_timeout(&this.endpoint);
+            // The status line is already sent, so the client sees the body
+            // cut off rather than a 504
+            let error = format!(
+                "Body consumption timeout: response body not finished within {}s",
+                this.limits.total_timeout.as_secs()
+            );
+            this.fail(CompletionReason::BodyTimeout, &error);
+            return Poll::Ready(Some(Err(error.into())));
+        }
+
+        match Pin::new(&mut this.inner).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                this.record_chunk(&chunk);
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(Some(Err(e))) => {
+                this.fail(
+                    CompletionReason::UpstreamError,
+                    &format!("Body consumption error: {e}"),
+                );
+                Poll::Ready(Some(Err(Box::new(e))))
+            }
+            Poll::Ready(None) => {
+                // The AI has finished generating and transmitting; the
+                // connection completes when hyper drops the body
+                this.upstream_done_at = Some(Instant::now());
+                Poll::Ready(None)
+            }
+            Poll::Pending => {
+                let stalled = this
+                    .idle_deadline
+                    .as_mut()
+                    .is_some_and(|idle_deadline| idle_deadline.as_mut().poll(cx).is_ready());
+                if stalled {
+                    Poll::Ready(this.stall())
+                } else {
+                    Poll::Pending
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ProgressBody {
+    fn drop(&mut self) {
+        // Without an upstream end, the client disconnected before the body ended
+        if self.upstream_done_at.is_some() {
+            self.finish(CompletionReason::Completed);
+        } else {
+            self.finish(CompletionReason::ClientDisconnected);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Fixtures shared by the unit tests
+
+use crate::config::Config;
+use std::sync::Once;
+
+/// Tokens of the [`TWO_GROUPS`] groups, so their endpoints are usable
+pub(crate) const TEST_TOKENS: [(&str, &str); 2] = [
+    ("ZEPHYR_TEST_TOKEN_MAIN", "test-token-main"),
+    ("ZEPHYR_TEST_TOKEN_BACKUP", "test-token-backup"),
+];
+
+/// A config with two single-endpoint groups on the loopback address, `main`
+/// being the default. Tests append sections or override fields after parsing.
+pub(crate) const TWO_GROUPS: &str = r#"
+[server]
+port = 18080
+
+[[groups]]
+name = "main"
+auth_token_env = "ZEPHYR_TEST_TOKEN_MAIN"
+default = true
+endpoints = [{ url = "http://127.0.0.1:19001", name = "alpha" }]
+
+[[groups]]
+name = "backup"
+auth_token_env = "ZEPHYR_TEST_TOKEN_BACKUP"
+endpoints = [{ url = "http://127.0.0.1:19002", name = "beta" }]
+
+[health_check]
+interval_seconds = 60
+timeout_seconds = 5
+claude_binary_path = "/nonexistent/claude"
+allow_missing_claude_binary = true
+"#;
+
+/// Parse `toml` as a config file, without the validation a load runs. The
+/// [`TEST_TOKENS`] are set first.
+pub(crate) fn config(toml: &str) -> Config {
+    static TOKENS: Once = Once::new();
+    TOKENS.call_once(|| {
+        for (name, value) in TEST_TOKENS {
+            std::env::set_var(name, value);
+        }
+    });
+    Config::parse(toml).expect("test config parses")
+}
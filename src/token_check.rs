@@ -0,0 +1,204 @@
+//! Pre-flight token validation: one cheap authenticated request per group, so
+//! a mistyped or expired token shows up as "token invalid" instead of every
+//! health check failing with a CLI error.
+
+use crate::config::{Config, Group};
+use crate::health::{token_fingerprint, HealthProbe};
+use crate::lock::LockExt;
+use crate::logging::log_token_check;
+use crate::proxy::SharedState;
+use chrono::{DateTime, Utc};
+use hyper::client::connect::Connect;
+use hyper::{Body, Client, Method, Request, Response, StatusCode};
+use hyper_tls::HttpsConnector;
+use serde::Serialize;
+use std::time::Duration;
+
+/// Counts tokens without generating anything, so it costs nothing
+pub(crate) const TOKEN_CHECK_PATH: &str = "/v1/messages/count_tokens";
+
+/// What a group's endpoints said about its token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenValidity {
+    /// An endpoint accepted the token
+    Valid,
+    /// An endpoint answered 401 or 403
+    Invalid,
+    /// No endpoint could be reached, or one answered something that says
+    /// nothing about the token
+    Unknown,
+}
+
+/// Result of the last token check for one group
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenCheck {
+    pub validity: TokenValidity,
+    /// Status the endpoint answered, `None` when none answered
+    pub http_status: Option<u16>,
+    /// Endpoint that answered, or the last one tried
+    pub endpoint: String,
+    /// Why the result is unknown
+    pub detail: Option<String>,
+    pub checked_at: DateTime<Utc>,
+    /// Fingerprint of the token checked, to check again once it changes
+    #[serde(skip)]
+    pub fingerprint: u64,
+}
+
+impl TokenCheck {
+    pub fn is_invalid(&self) -> bool {
+        self.validity == TokenValidity::Invalid
+    }
+}
+
+/// Check the token of every group that has one and record the results in the
+/// state, by group name. With `only_changed`, groups whose token is the one
+/// already checked are skipped unless that check was inconclusive. Returns the
+/// groups checked.
+pub(crate) async fn check_tokens(
+    config: &Config,
+    state: &SharedState,
+    silent: bool,
+    only_changed: bool,
+) -> Vec<(String, TokenCheck)> {
+    let groups: Vec<(&Group, String)> = {
+        let state_guard = state.lock_or_recover();
+        config
+            .groups
+            .iter()
+            .filter(|group| !group.endpoints.is_empty())
+            .filter_map(|group| group.auth_token().map(|token| (group, token)))
+            .filter(|(group, token)| {
+                !only_changed
+                    || state_guard
+                        .token_checks
+                        .get(&group.name)
+                        .is_none_or(|check| {
+                            check.fingerprint != token_fingerprint(token)
+                                || check.validity == TokenValidity::Unknown
+                        })
+            })
+            .collect()
+    };
+    if groups.is_empty() {
+        return Vec::new();
+    }
+
+    let client = Client::builder().build::<_, Body>(HttpsConnector::new());
+    let timeout = Duration::from_secs(config.health_check.timeout_seconds);
+    let results: Vec<(String, TokenCheck)> =
+        futures::future::join_all(groups.iter().map(|(group, token)| {
+            let client = &client;
+            let probe = config.health_probe(&group.name);
+            async move {
+                let check = check_group_token(client, group, token, &probe, timeout).await;
+                (group.name.clone(), check)
+            }
+        }))
+        .await;
+
+    let mut state_guard = state.lock_or_recover();
+    for (group, check) in &results {
+        let previous = state_guard
+            .token_checks
+            .insert(group.clone(), check.clone());
+        // An endpoint that stays unreachable is checked every cycle, log it once
+        let repeated = previous.is_some_and(|previous| {
+            previous.fingerprint == check.fingerprint
+                && previous.validity == check.validity
+                && previous.http_status == check.http_status
+        });
+        if !silent && !repeated {
+            log_token_check(group, check);
+        }
+    }
+    results
+}
+
+/// Try the group's endpoints in order until one answers
+async fn check_group_token(
+    client: &Client<HttpsConnector<hyper::client::HttpConnector>>,
+    group: &Group,
+    token: &str,
+    probe: &HealthProbe,
+    timeout: Duration,
+) -> TokenCheck {
+    let mut check = TokenCheck {
+        validity: TokenValidity::Unknown,
+        http_status: None,
+        endpoint: String::new(),
+        detail: None,
+        checked_at: Utc::now(),
+        fingerprint: token_fingerprint(token),
+    };
+    for endpoint in &group.endpoints {
+        check.endpoint = endpoint.url.clone();
+        let status = match send_token_check(client, &endpoint.url, token, probe, timeout).await {
+            Ok(status) => status,
+            Err(e) => {
+                check.detail = Some(e);
+                continue;
+            }
+        };
+        check.http_status = Some(status.as_u16());
+        check.validity = match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => TokenValidity::Invalid,
+            status if status.is_success() => TokenValidity::Valid,
+            _ => TokenValidity::Unknown,
+        };
+        check.detail = (check.validity == TokenValidity::Unknown)
+            .then(|| format!("{TOKEN_CHECK_PATH} answered {status}"));
+        break;
+    }
+    check.checked_at = Utc::now();
+    check
+}
+
+/// Send one `count_tokens` request, returning the status it was answered with
+pub(crate) async fn send_token_check(
+    client: &Client<HttpsConnector<hyper::client::HttpConnector>>,
+    endpoint: &str,
+    token: &str,
+    probe: &HealthProbe,
+    timeout: Duration,
+) -> Result<StatusCode, String> {
+    send_token_request(client, endpoint, token, probe, timeout)
+        .await
+        .map(|response| response.status())
+}
+
+/// Send one `count_tokens` request over `client`, returning the response
+/// once its headers arrived
+pub(crate) async fn send_token_request<C>(
+    client: &Client<C>,
+    endpoint: &str,
+    token: &str,
+    probe: &HealthProbe,
+    timeout: Duration,
+) -> Result<Response<Body>, String>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    let body = serde_json::json!({
+        "model": probe.model,
+        "messages": [{ "role": "user", "content": probe.prompt }],
+    });
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(format!(
+            "{}{TOKEN_CHECK_PATH}",
+            endpoint.trim_end_matches('/')
+        ))
+        .header("authorization", format!("Bearer {token}"))
+        .header("content-type", "application/json")
+        .header("anthropic-version", "2023-06-01")
+        .body(Body::from(body.to_string()))
+        .map_err(|e| format!("Invalid request: {e}"))?;
+
+    match tokio::time::timeout(timeout, client.request(request)).await {
+        Ok(Ok(response)) => Ok(response),
+        Ok(Err(e)) => Err(format!("HTTP error: {e}")),
+        Err(_) => Err(format!("No answer within {}s", timeout.as_secs())),
+    }
+}
@@ -0,0 +1,106 @@
+use serde::Serialize;
+
+/// Largest non-streaming body kept around to read its `usage` block
+const MAX_JSON_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+/// Estimated tokens consumed by one CLI health check (see `health.rs`)
+pub const HEALTH_CHECK_INPUT_TOKENS: u64 = 8;
+pub const HEALTH_CHECK_OUTPUT_TOKENS: u64 = 1;
+
+/// Tokens reported by the upstream for one response
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn is_empty(&self) -> bool {
+        self.input_tokens == 0 && self.output_tokens == 0
+    }
+}
+
+/// Reads token usage out of a response body as it streams past.
+///
+/// Streaming responses report input tokens in `message_start` and a running
+/// output total in `message_delta`; plain JSON responses carry a single
+/// top-level `usage` object.
+#[derive(Debug)]
+pub struct UsageParser {
+    is_sse: bool,
+    /// Incomplete SSE line carried over to the next chunk
+    line_buffer: Vec<u8>,
+    json_body: Vec<u8>,
+    json_overflow: bool,
+    usage: TokenUsage,
+}
+
+impl UsageParser {
+    pub fn new(is_sse: bool) -> Self {
+        Self {
+            is_sse,
+            line_buffer: Vec::new(),
+            json_body: Vec::new(),
+            json_overflow: false,
+            usage: TokenUsage::default(),
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        if self.is_sse {
+            self.feed_sse(chunk);
+        } else if !self.json_overflow {
+            if self.json_body.len() + chunk.len() > MAX_JSON_BODY_BYTES {
+                self.json_overflow = true;
+                self.json_body = Vec::new();
+            } else {
+                self.json_body.extend_from_slice(chunk);
+            }
+        }
+    }
+
+    /// Usage seen so far; a truncated stream still reports what it got
+    pub fn finish(&mut self) -> TokenUsage {
+        if !self.is_sse && !self.json_overflow {
+            if let Ok(body) = serde_json::from_slice::<serde_json::Value>(&self.json_body) {
+                self.merge(body.get("usage"));
+            }
+            self.json_body = Vec::new();
+        }
+        self.usage
+    }
+
+    fn feed_sse(&mut self, chunk: &[u8]) {
+        self.line_buffer.extend_from_slice(chunk);
+
+        while let Some(newline) = self.line_buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.line_buffer.drain(..=newline).collect();
+            let Some(data) = line.strip_prefix(b"data:") else {
+                continue;
+            };
+            // Only events that can carry usage are worth a JSON parse
+            if !data.windows(7).any(|w| w == b"\"usage\"") {
+                continue;
+            }
+            if let Ok(event) = serde_json::from_slice::<serde_json::Value>(data) {
+                match event.get("type").and_then(|t| t.as_str()) {
+                    Some("message_start") => {
+                        self.merge(event.get("message").and_then(|m| m.get("usage")))
+                    }
+                    Some("message_delta") => self.merge(event.get("usage")),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Counts in later events are running totals, so keep the largest seen
+    fn merge(&mut self, usage: Option<&serde_json::Value>) {
+        let Some(usage) = usage else {
+            return;
+        };
+        let field = |name: &str| usage.get(name).and_then(|v| v.as_u64()).unwrap_or(0);
+        self.usage.input_tokens = self.usage.input_tokens.max(field("input_tokens"));
+        self.usage.output_tokens = self.usage.output_tokens.max(field("output_tokens"));
+    }
+}
@@ -0,0 +1,314 @@
+//! Fixtures for the integration tests: a scriptable upstream standing in for
+//! an Anthropic-compatible endpoint, and a proxy started on a free port.
+
+#![allow(dead_code)]
+
+use claude_zephyr::{
+    Config, HealthOrchestratorHandle, ProxyServer, ProxyServerBuilder, RunMode, SharedState,
+};
+use hyper::body::Bytes;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, HeaderMap, Method, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::{SocketAddr, TcpListener};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Environment variable every test group reads its token from
+pub const TOKEN_ENV: &str = "ZEPHYR_IT_TOKEN";
+pub const TOKEN: &str = "it-token";
+
+/// Body of a successful Messages API response
+pub const MESSAGE_BODY: &str = r#"{"id":"msg_mock","type":"message","content":[{"type":"text","text":"ok"}],"usage":{"input_tokens":3,"output_tokens":1}}"#;
+
+/// A request as the upstream received it
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    pub method: Method,
+    pub path: String,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+/// An HTTP server on a loopback port answering every request with `respond`
+pub struct MockUpstream {
+    pub url: String,
+    requests: Arc<Mutex<Vec<ReceivedRequest>>>,
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl MockUpstream {
+    pub fn start<F, Fut>(respond: F) -> Self
+    where
+        F: Fn(ReceivedRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response<Body>> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock upstream");
+        let addr = listener.local_addr().expect("mock upstream address");
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let respond = Arc::new(respond);
+
+        let received = Arc::clone(&requests);
+        let make_service = make_service_fn(move |_| {
+            let received = Arc::clone(&received);
+            let respond = Arc::clone(&respond);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                    let received = Arc::clone(&received);
+                    let respond = Arc::clone(&respond);
+                    async move {
+                        let (parts, body) = req.into_parts();
+                        let request = ReceivedRequest {
+                            method: parts.method,
+                            path: parts
+                                .uri
+                                .path_and_query()
+                                .map_or_else(|| "/".to_string(), |p| p.to_string()),
+                            headers: parts.headers,
+                            body: hyper::body::to_bytes(body).await.unwrap_or_default(),
+                        };
+                        received.lock().unwrap().push(request.clone());
+                        Ok::<_, Infallible>(respond(request).await)
+                    }
+                }))
+            }
+        });
+
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        let server = Server::from_tcp(listener)
+            .expect("mock upstream listener")
+            .serve(make_service)
+            .with_graceful_shutdown(async {
+                let _ = stopped.await;
+            });
+        tokio::spawn(server);
+
+        Self {
+            url: format!("http://{addr}"),
+            requests,
+            _shutdown: shutdown,
+        }
+    }
+
+    /// Answer every request with a Messages API response and `status`
+    pub fn with_status(status: StatusCode) -> Self {
+        Self::start(move |_| async move { json_response(status, MESSAGE_BODY) })
+    }
+
+    pub fn ok() -> Self {
+        Self::with_status(StatusCode::OK)
+    }
+
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn request_count(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+}
+
+pub fn json_response(status: StatusCode, body: &'static str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(body))
+        .expect("mock response")
+}
+
+/// A port nothing listens on right now
+pub fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .expect("free port")
+}
+
+/// Config TOML with one group per upstream URL, each with a single endpoint:
+/// group `g<i>` with endpoint `e<i>`, the first group the default. Each group
+/// is a less preferred tier than the one before, so the health check race
+/// never moves the proxy off the first endpoint while it is being checked.
+/// `server` goes into the `[server]` table and `extra` after everything else.
+/// Health checks run [`stub_claude`], so every endpoint passes them.
+pub fn config_toml(port: u16, upstreams: &[&str], server: &str, extra: &str) -> String {
+    let mut toml = format!("[server]\nport = {port}\n{server}\n");
+    for (i, url) in upstreams.iter().enumerate() {
+        toml.push_str(&format!(
+            "\n[[groups]]\nname = \"g{i}\"\nauth_token_env = \"{TOKEN_ENV}\"\npriority = {}\n{}endpoints = [{{ url = \"{url}\", name = \"e{i}\" }}]\n",
+            i + 1,
+            if i == 0 { "default = true\n" } else { "" },
+        ));
+    }
+    toml.push_str(&format!(
+        "\n[health_check]\ninterval_seconds = 60\ntimeout_seconds = 5\nclaude_binary_path = \"{}\"\n",
+        stub_claude().display()
+    ));
+    toml.push_str(extra);
+    toml
+}
+
+/// A stand-in for the Claude CLI that answers every health check with `ok`
+/// without contacting the endpoint, so the checks neither depend on a CLI
+/// being installed nor show up in the requests an upstream received
+pub fn stub_claude() -> PathBuf {
+    static STUB: Once = Once::new();
+    let path = std::env::temp_dir().join(format!("zephyr-it-{}-claude", std::process::id()));
+    STUB.call_once(|| {
+        std::fs::write(&path, "#!/bin/sh\necho ok\n").expect("write stub claude");
+        let mut permissions = std::fs::metadata(&path).expect("stub claude").permissions();
+        permissions.set_mode(0o755);
+        std::fs::set_permissions(&path, permissions).expect("make stub claude executable");
+    });
+    path
+}
+
+/// Load `toml` the way the binary loads a config file
+pub fn load_config(toml: &str) -> Config {
+    load_config_file(&write_config(toml))
+}
+
+/// Load the config file at `path`, which a reload reads again
+pub fn load_config_file(path: &Path) -> Config {
+    std::env::set_var(TOKEN_ENV, TOKEN);
+    Config::load_from_file(path).expect("load test config")
+}
+
+/// Write `toml` as `config.toml` in a directory of its own, returning its path
+pub fn write_config(toml: &str) -> PathBuf {
+    let path = config_dir().join("config.toml");
+    std::fs::write(&path, toml).expect("write test config");
+    path
+}
+
+/// A fresh directory for one config file and whatever sits next to it
+fn config_dir() -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "zephyr-it-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::create_dir_all(&dir).expect("create test config dir");
+    dir
+}
+
+/// A proxy running in the background until dropped
+pub struct TestProxy {
+    pub port: u16,
+    pub state: SharedState,
+    pub orchestrator: HealthOrchestratorHandle,
+    _stop: oneshot::Sender<()>,
+}
+
+impl TestProxy {
+    /// Start a proxy for `config` on `config.server.port`, returning once it
+    /// accepts connections
+    pub async fn start(config: Config) -> Self {
+        Self::start_with(ProxyServer::builder(config)).await
+    }
+
+    /// Start the proxy `builder` describes, silent and without recording usage
+    pub async fn start_with(builder: ProxyServerBuilder) -> Self {
+        Self::run(builder.mode(RunMode::Dashboard).record_usage(false).build()).await
+    }
+
+    /// Start a proxy for `config` with its health checks paused from the
+    /// start, so only requests move it off its starting endpoint. Set
+    /// `startup_check = false`, which runs before the pause is read.
+    pub async fn start_paused(config: Config) -> Self {
+        Self::start_paused_with(ProxyServer::builder(config).mode(RunMode::Dashboard)).await
+    }
+
+    /// [`TestProxy::start_paused`] for the proxy `builder` describes, in the
+    /// mode it sets, without recording usage
+    pub async fn start_paused_with(builder: ProxyServerBuilder) -> Self {
+        let server = builder.record_usage(false).build();
+        server.health_orchestrator().pause();
+        Self::run(server).await
+    }
+
+    async fn run(server: ProxyServer) -> Self {
+        let port = server.port();
+        let state = server.state();
+        let orchestrator = server.health_orchestrator();
+        let (stop, stopped) = oneshot::channel::<()>();
+        tokio::spawn(server.run_until(async {
+            let _ = stopped.await;
+        }));
+        wait_for_port(port).await;
+        Self {
+            port,
+            state,
+            orchestrator,
+            _stop: stop,
+        }
+    }
+
+    /// Wait until the health loop has finished more than `cycles` cycles,
+    /// returning how many it has
+    pub async fn wait_for_cycles(&self, cycles: u64) -> u64 {
+        for _ in 0..400 {
+            let completed = self.orchestrator.snapshot().cycles_completed;
+            if completed > cycles {
+                return completed;
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        panic!("no health check cycle after cycle {cycles}");
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}{path}", self.port)
+    }
+
+    /// GET one of the proxy's own pages
+    pub async fn get(&self, path: &str) -> (StatusCode, String) {
+        let response = hyper::Client::new()
+            .get(self.url(path).parse().expect("proxy url"))
+            .await
+            .expect("proxy answers");
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap_or_default();
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// POST a Messages API request through the proxy
+    pub async fn send_message(&self) -> (StatusCode, Bytes) {
+        send_message_to(self.port).await.expect("proxy answers")
+    }
+}
+
+/// POST a Messages API request to a proxy listening on `port`
+pub async fn send_message_to(port: u16) -> hyper::Result<(StatusCode, Bytes)> {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(format!("http://127.0.0.1:{port}/v1/messages"))
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"model":"m","max_tokens":1,"messages":[]}"#))
+        .expect("request");
+    let response = hyper::Client::new().request(request).await?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body())
+        .await
+        .unwrap_or_default();
+    Ok((status, body))
+}
+
+/// Wait until something accepts connections on `port`
+pub async fn wait_for_port(port: u16) {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    for _ in 0..200 {
+        if tokio::net::TcpStream::connect(addr).await.is_ok() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    panic!("nothing listens on port {port}");
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::{config_toml, free_port, load_config, write_config, TestProxy, TOKEN, TOKEN_ENV};
+use hyper::StatusCode;
+use std::process::Command;
+
+/// A config whose token also sits in an endpoint path and query string
+fn config_with_token_in_urls(port: u16) -> String {
+    config_toml(
+        port,
+        &[
+            &format!("http://127.0.0.1:9/{TOKEN}/v1"),
+            &format!("http://127.0.0.1:9/v1?key={TOKEN}"),
+        ],
+        "",
+        "",
+    )
+}
+
+#[tokio::test]
+async fn config_route_shows_no_token() {
+    let port = free_port();
+    let proxy = TestProxy::start(load_config(&config_with_token_in_urls(port))).await;
+
+    let (status, view) = proxy.get("/config").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(!view.contains(TOKEN), "token leaked:\n{view}");
+    assert!(view.contains(r#""auth_token": "<set>""#), "{view}");
+    assert!(view.contains("http://127.0.0.1:9/[REDACTED]/v1"), "{view}");
+}
+
+#[test]
+fn config_show_prints_no_token() {
+    let path = write_config(&config_with_token_in_urls(free_port()));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_claude-zephyr"))
+        .args(["config", "show"])
+        .current_dir(path.parent().unwrap())
+        .env(TOKEN_ENV, TOKEN)
+        .output()
+        .expect("run config show");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stdout}\n{stderr}");
+    assert!(!stdout.contains(TOKEN), "token leaked:\n{stdout}");
+    assert!(!stderr.contains(TOKEN), "token leaked:\n{stderr}");
+    assert!(stdout.contains(r#""auth_token": "<set>""#), "{stdout}");
+    assert!(stdout.contains("v1?key=[REDACTED]"), "{stdout}");
+}
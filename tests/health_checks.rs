@@ -0,0 +1,119 @@
+mod common;
+
+use claude_zephyr::{ProxyEvent, ProxyServer};
+use common::{config_toml, free_port, load_config, MockUpstream, TestProxy};
+use hyper::StatusCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn health_checks_continue_after_a_panic_holding_the_state_lock() {
+    let upstream = MockUpstream::ok();
+    let port = free_port();
+    let proxy = TestProxy::start(load_config(&config_toml(port, &[&upstream.url], "", ""))).await;
+    let cycles = proxy.wait_for_cycles(0).await;
+
+    // A handler panicking mid-update poisons the state lock
+    let state = proxy.state.clone();
+    let panicked = std::thread::spawn(move || {
+        let _guard = state.lock().unwrap();
+        panic!("handler panicked holding the state lock");
+    })
+    .join();
+    assert!(panicked.is_err());
+    assert!(proxy.state.is_poisoned());
+
+    proxy.orchestrator.refresh();
+    proxy.wait_for_cycles(cycles).await;
+    let (status, _) = proxy.send_message().await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn health_checks_continue_after_the_event_hook_panics() {
+    let upstream = MockUpstream::ok();
+    let port = free_port();
+    let hook_panicked = Arc::new(AtomicBool::new(false));
+    let builder = ProxyServer::builder(load_config(&config_toml(port, &[&upstream.url], "", "")))
+        .event_hook({
+            let hook_panicked = hook_panicked.clone();
+            move |event| {
+                if matches!(event, ProxyEvent::HealthUpdate(_))
+                    && !hook_panicked.swap(true, Ordering::Relaxed)
+                {
+                    panic!("event hook panicked on a health update");
+                }
+            }
+        });
+    let proxy = TestProxy::start_with(builder).await;
+    let cycles = proxy.wait_for_cycles(0).await;
+    assert!(hook_panicked.load(Ordering::Relaxed));
+
+    proxy.orchestrator.refresh();
+    proxy.wait_for_cycles(cycles).await;
+}
+
+#[tokio::test]
+async fn http_mode_checks_endpoints_with_a_count_tokens_request() {
+    let healthy = MockUpstream::ok();
+    let rejecting = MockUpstream::with_status(StatusCode::UNAUTHORIZED);
+    let port = free_port();
+    let toml = config_toml(port, &[&healthy.url, &rejecting.url], "", "")
+        .replace("[health_check]\n", "[health_check]\nmode = \"http\"\n");
+    let proxy = TestProxy::start(load_config(&toml)).await;
+    proxy.wait_for_cycles(0).await;
+
+    let requests = healthy.requests();
+    assert!(!requests.is_empty());
+    assert!(requests
+        .iter()
+        .all(|request| request.path == "/v1/messages/count_tokens"));
+
+    {
+        let state = proxy.state.lock().unwrap();
+        let healthy_status = state.status_of(&healthy.url).unwrap();
+        assert!(healthy_status.is_available(), "{healthy_status:?}");
+        let rejected_status = state.status_of(&rejecting.url).unwrap();
+        assert!(!rejected_status.is_available());
+        assert!(rejected_status.auth_failed, "{rejected_status:?}");
+    }
+
+    let (status, body) = proxy.get("/status").await;
+    assert_eq!(status, StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["health_check_mode"], "http");
+}
+
+#[tokio::test]
+async fn http_mode_reuses_its_connection_and_times_each_phase() {
+    let upstream = MockUpstream::ok();
+    let port = free_port();
+    let toml = config_toml(port, &[&upstream.url], "", "")
+        .replace("[health_check]\n", "[health_check]\nmode = \"http\"\n");
+    let proxy = TestProxy::start(load_config(&toml)).await;
+    let cycles = proxy.wait_for_cycles(0).await;
+    proxy.orchestrator.refresh();
+    proxy.wait_for_cycles(cycles).await;
+
+    {
+        let state = proxy.state.lock().unwrap();
+        let status = state.status_of(&upstream.url).unwrap();
+        assert!(status.cold_latency_ms.is_some(), "{status:?}");
+        assert!(status.warm_latency_ms.is_some(), "{status:?}");
+        assert_eq!(status.latency, status.warm_latency_ms);
+    }
+
+    let (status, body) = proxy.get("/health/history").await;
+    assert_eq!(status, StatusCode::OK);
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let checks = body["endpoints"][0]["checks"].as_array().unwrap();
+    let phases: Vec<&serde_json::Value> = checks.iter().map(|check| &check["phases"]).collect();
+    // The first check dials the mock upstream over plain HTTP, later ones
+    // reuse its connection
+    assert!(phases[0]["resolve_ms"].is_u64(), "{checks:?}");
+    assert!(phases[0]["connect_ms"].is_u64(), "{checks:?}");
+    assert!(phases[0]["tls_ms"].is_null(), "{checks:?}");
+    let last = phases.last().unwrap();
+    assert!(last["connect_ms"].is_null(), "{checks:?}");
+    assert!(last["first_byte_ms"].is_u64(), "{checks:?}");
+}
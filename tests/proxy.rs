@@ -0,0 +1,443 @@
+mod common;
+
+use claude_zephyr::CounterSnapshot;
+use common::{
+    config_toml, free_port, json_response, load_config, stub_claude, MockUpstream, TestProxy,
+    MESSAGE_BODY, TOKEN, TOKEN_ENV,
+};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+#[tokio::test]
+async fn forwards_a_request_to_the_current_endpoint() {
+    let upstream = MockUpstream::ok();
+    let port = free_port();
+    let proxy = TestProxy::start(load_config(&config_toml(port, &[&upstream.url], "", ""))).await;
+
+    let (status, body) = proxy.send_message().await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, MESSAGE_BODY);
+    let requests = upstream.requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request.method, Method::POST);
+    assert_eq!(request.path, "/v1/messages");
+    assert_eq!(
+        request.headers["authorization"],
+        format!("Bearer {TOKEN}").as_str()
+    );
+    assert_eq!(
+        request.headers["host"],
+        upstream.url.trim_start_matches("http://")
+    );
+    assert_eq!(
+        request.body,
+        r#"{"model":"m","max_tokens":1,"messages":[]}"#
+    );
+}
+
+/// Read from `stream` until the proxy closes it, returning how long that took
+async fn time_until_closed(stream: &mut TcpStream) -> Duration {
+    let started = Instant::now();
+    let mut buf = [0u8; 256];
+    loop {
+        match tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf)).await {
+            Ok(Ok(0) | Err(_)) => return started.elapsed(),
+            Ok(Ok(_)) => continue,
+            Err(_) => panic!("connection still open after 5s"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn header_read_timeout_closes_silent_and_slow_connections() {
+    let upstream = MockUpstream::ok();
+    let port = free_port();
+    let config = config_toml(
+        port,
+        &[&upstream.url],
+        "http1_header_read_timeout_ms = 300",
+        "",
+    );
+    let proxy = TestProxy::start(load_config(&config)).await;
+
+    // Connected but never sending a byte
+    let mut silent = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let waited = time_until_closed(&mut silent).await;
+    assert!(
+        waited >= Duration::from_millis(250),
+        "closed after {waited:?}"
+    );
+
+    // Starting a request head but never finishing it
+    let mut slow = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    slow.write_all(b"GET /status HTTP/1.1\r\n").await.unwrap();
+    let waited = time_until_closed(&mut slow).await;
+    assert!(
+        waited >= Duration::from_millis(250),
+        "closed after {waited:?}"
+    );
+
+    // A connection that sends its head in time is served
+    let (status, metrics) = proxy.get("/metrics").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(
+        metrics
+            .lines()
+            .any(|line| line == "zephyr_local_header_timeouts_total 2"),
+        "{metrics}"
+    );
+    assert_eq!(upstream.request_count(), 0);
+}
+
+#[tokio::test]
+async fn fallback_statuses_skip_the_retries() {
+    let overloaded = MockUpstream::with_status(StatusCode::from_u16(529).unwrap());
+    let backup = MockUpstream::ok();
+    let port = free_port();
+    let config = config_toml(
+        port,
+        &[&overloaded.url, &backup.url],
+        "",
+        "\n[retry]\nmax_retries = 2\nbase_delay_ms = 1\nfallback_on_status = [529]\n",
+    );
+    let proxy = TestProxy::start(load_config(&config)).await;
+
+    let (status, _) = proxy.send_message().await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(overloaded.request_count(), 1);
+    assert_eq!(backup.request_count(), 1);
+}
+
+#[tokio::test]
+async fn retry_statuses_are_retried_before_falling_back() {
+    let failing = MockUpstream::with_status(StatusCode::INTERNAL_SERVER_ERROR);
+    let backup = MockUpstream::ok();
+    let port = free_port();
+    let config = config_toml(
+        port,
+        &[&failing.url, &backup.url],
+        "",
+        "\n[retry]\nmax_retries = 2\nbase_delay_ms = 1\nretry_on_status = [500]\n",
+    );
+    let proxy = TestProxy::start(load_config(&config)).await;
+
+    let (status, _) = proxy.send_message().await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(failing.request_count(), 3);
+    assert_eq!(backup.request_count(), 1);
+}
+
+/// Timeouts of a second for the response headers and for the body
+const SHORT_TIMEOUTS: &str =
+    "response_header_timeout_seconds = 1\nresponse_body_timeout_seconds = 1";
+
+#[tokio::test]
+async fn slow_headers_are_retried_and_end_in_a_gateway_timeout() {
+    let upstream = MockUpstream::start(|_| async {
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        json_response(StatusCode::OK, MESSAGE_BODY)
+    });
+    let port = free_port();
+    let config = config_toml(
+        port,
+        &[&upstream.url],
+        SHORT_TIMEOUTS,
+        "\n[retry]\nmax_retries = 1\nbase_delay_ms = 1\n",
+    );
+    let proxy = TestProxy::start(load_config(&config)).await;
+
+    let started = Instant::now();
+    let (status, _) = proxy.send_message().await;
+
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+    assert!(
+        started.elapsed() < Duration::from_secs(3),
+        "{:?}",
+        started.elapsed()
+    );
+    assert_eq!(upstream.request_count(), 2);
+    let counters = proxy.state.lock().unwrap().metrics.snapshots_by_id()["e0"];
+    assert_eq!(counters.request_timeouts, 1);
+    assert_eq!(counters.body_timeouts, 0);
+}
+
+#[tokio::test]
+async fn slow_body_is_cut_off_without_a_retry() {
+    // Headers at once, then a chunk every 200ms for four seconds
+    let upstream = MockUpstream::start(|_| async {
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            for _ in 0..20 {
+                if sender.send_data("data: {}\n\n".into()).await.is_err() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+        });
+        Response::builder()
+            .header("content-type", "text/event-stream")
+            .body(body)
+            .unwrap()
+    });
+    let port = free_port();
+    let config = config_toml(
+        port,
+        &[&upstream.url],
+        SHORT_TIMEOUTS,
+        "\n[retry]\nmax_retries = 1\nbase_delay_ms = 1\n",
+    );
+    let proxy = TestProxy::start(load_config(&config)).await;
+
+    let started = Instant::now();
+    let (status, body) = proxy.send_message().await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(
+        started.elapsed() < Duration::from_secs(3),
+        "{:?}",
+        started.elapsed()
+    );
+    assert!(body.len() < 20 * "data: {}\n\n".len(), "{body:?}");
+    assert_eq!(upstream.request_count(), 1);
+    let counters = proxy.state.lock().unwrap().metrics.snapshots_by_id()["e0"];
+    assert_eq!(counters.body_timeouts, 1);
+    assert_eq!(counters.request_timeouts, 0);
+}
+
+/// POST a Messages API request through the proxy on `port`, tagged with
+/// whether it was sent after the current endpoint was marked down
+async fn send_tagged(port: u16, after_switch: bool) -> StatusCode {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(format!("http://127.0.0.1:{port}/v1/messages"))
+        .header("content-type", "application/json")
+        .header("x-after-switch", after_switch.to_string())
+        .body(Body::from(r#"{"model":"m","max_tokens":1,"messages":[]}"#))
+        .unwrap();
+    let response = hyper::Client::new().request(request).await.unwrap();
+    response.status()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn requests_after_the_current_endpoint_goes_down_skip_it() {
+    let current = MockUpstream::ok();
+    let backup = MockUpstream::ok();
+    let port = free_port();
+    let config = config_toml(port, &[&current.url, &backup.url], "", "");
+    let proxy = TestProxy::start(load_config(&config)).await;
+    // The first cycle would mark the endpoint up again
+    proxy.wait_for_cycles(0).await;
+
+    // The first 50 requests may still be in flight when the endpoint is
+    // marked down, the other 150 are sent after it
+    let mut requests = Vec::new();
+    for i in 0..200 {
+        if i == 50 {
+            proxy
+                .state
+                .lock()
+                .unwrap()
+                .status_of_mut(&current.url)
+                .unwrap()
+                .mark_unhealthy("down".to_string());
+        }
+        requests.push(tokio::spawn(send_tagged(port, i >= 50)));
+    }
+    for request in requests {
+        assert_eq!(request.await.unwrap(), StatusCode::OK);
+    }
+
+    assert_eq!(current.request_count() + backup.request_count(), 200);
+    let late = current
+        .requests()
+        .iter()
+        .filter(|request| request.headers["x-after-switch"] == "true")
+        .count();
+    assert_eq!(
+        late, 0,
+        "requests sent after the switch reached the dead endpoint"
+    );
+    let rerouted = backup
+        .requests()
+        .iter()
+        .filter(|request| request.headers["x-after-switch"] == "true")
+        .count();
+    assert_eq!(rerouted, 150);
+}
+
+/// Config whose current endpoint `dead` shares group `g0` with `sibling`,
+/// with `other` in group `g1`, for a proxy started with
+/// [`TestProxy::start_paused`]
+fn fallback_config(port: u16, dead: &str, sibling: &str, other: &str, policy: &str) -> String {
+    format!(
+        r#"[server]
+port = {port}
+
+[[groups]]
+name = "g0"
+auth_token_env = "{TOKEN_ENV}"
+default = true
+endpoints = [{{ url = "{dead}", name = "dead" }}, {{ url = "{sibling}", name = "sibling" }}]
+
+[[groups]]
+name = "g1"
+auth_token_env = "{TOKEN_ENV}"
+endpoints = [{{ url = "{other}", name = "other" }}]
+
+[health_check]
+interval_seconds = 60
+timeout_seconds = 5
+claude_binary_path = "{claude}"
+startup_check = false
+
+[retry]
+max_retries = 0
+base_delay_ms = 1
+fallback = "{policy}"
+"#,
+        claude = stub_claude().display()
+    )
+}
+
+/// URL of a loopback port nothing listens on
+fn dead_url() -> String {
+    format!("http://127.0.0.1:{}", free_port())
+}
+
+/// The `fallback_policy` of the proxy's error response
+fn fallback_policy(body: &[u8]) -> String {
+    let error: serde_json::Value = serde_json::from_slice(body).expect("error JSON");
+    error["error"]["fallback_policy"]
+        .as_str()
+        .expect("fallback_policy")
+        .to_string()
+}
+
+#[tokio::test]
+async fn fallback_off_returns_the_primary_error() {
+    let (sibling, other) = (MockUpstream::ok(), MockUpstream::ok());
+    let port = free_port();
+    let config = fallback_config(port, &dead_url(), &sibling.url, &other.url, "off");
+    let proxy = TestProxy::start_paused(load_config(&config)).await;
+
+    let (status, body) = proxy.send_message().await;
+
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(fallback_policy(&body), "off");
+    assert_eq!(sibling.request_count(), 0);
+    assert_eq!(other.request_count(), 0);
+}
+
+#[tokio::test]
+async fn fallback_in_the_same_group_stays_in_the_group() {
+    let (sibling, other) = (MockUpstream::ok(), MockUpstream::ok());
+    let port = free_port();
+    let config = fallback_config(port, &dead_url(), &sibling.url, &other.url, "same_group");
+    let proxy = TestProxy::start_paused(load_config(&config)).await;
+
+    let (status, body) = proxy.send_message().await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, MESSAGE_BODY);
+    assert_eq!(sibling.request_count(), 1);
+    assert_eq!(other.request_count(), 0);
+}
+
+#[tokio::test]
+async fn fallback_in_the_same_group_never_leaves_it() {
+    let other = MockUpstream::ok();
+    let port = free_port();
+    let config = fallback_config(port, &dead_url(), &dead_url(), &other.url, "same_group");
+    let proxy = TestProxy::start_paused(load_config(&config)).await;
+
+    let (status, body) = proxy.send_message().await;
+
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(fallback_policy(&body), "same_group");
+    assert_eq!(other.request_count(), 0);
+}
+
+#[tokio::test]
+async fn fallback_to_all_reaches_other_groups() {
+    let other = MockUpstream::ok();
+    let port = free_port();
+    let config = fallback_config(port, &dead_url(), &dead_url(), &other.url, "all");
+    let proxy = TestProxy::start_paused(load_config(&config)).await;
+
+    let (status, body) = proxy.send_message().await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, MESSAGE_BODY);
+    assert_eq!(other.request_count(), 1);
+}
+
+/// What a proxy did with a request its current endpoint answers with `code`:
+/// the status the client got, the requests the endpoint and the backup
+/// received, and the endpoint's counters
+async fn answer_with(code: u16, fallback: &str) -> (StatusCode, usize, usize, CounterSnapshot) {
+    let primary = MockUpstream::with_status(StatusCode::from_u16(code).unwrap());
+    let backup = MockUpstream::ok();
+    let port = free_port();
+    let config = config_toml(
+        port,
+        &[&primary.url, &backup.url],
+        "",
+        &format!("\n[retry]\nmax_retries = 1\nbase_delay_ms = 1\nfallback = \"{fallback}\"\n"),
+    )
+    .replace(
+        "[health_check]\n",
+        "[health_check]\nstartup_check = false\n",
+    );
+    let proxy = TestProxy::start_paused(load_config(&config)).await;
+
+    let (status, _) = proxy.send_message().await;
+
+    let counters = proxy.state.lock().unwrap().metrics.snapshots_by_id()["e0"];
+    (
+        status,
+        primary.request_count(),
+        backup.request_count(),
+        counters,
+    )
+}
+
+#[tokio::test]
+async fn client_errors_are_forwarded_without_retry_or_fallback() {
+    for code in [400, 401] {
+        let (status, primary, backup, counters) = answer_with(code, "all").await;
+        assert_eq!(status.as_u16(), code);
+        assert_eq!((primary, backup), (1, 0), "{code}");
+        assert_eq!(counters.client_errors, 1, "{code}");
+        assert_eq!(counters.endpoint_errors, 0, "{code}");
+        assert_eq!(counters.retry_attempts, 0, "{code}");
+    }
+}
+
+#[tokio::test]
+async fn endpoint_errors_are_retried_then_fall_back() {
+    for code in [429, 503] {
+        let (status, primary, backup, counters) = answer_with(code, "all").await;
+        assert_eq!(status, StatusCode::OK, "{code}");
+        assert_eq!((primary, backup), (2, 1), "{code}");
+        assert_eq!(counters.retry_attempts, 1, "{code}");
+        assert_eq!(counters.retries_exhausted, 1, "{code}");
+        assert_eq!(counters.client_errors, 0, "{code}");
+    }
+}
+
+#[tokio::test]
+async fn endpoint_errors_count_against_the_endpoint() {
+    for code in [429, 503] {
+        let (status, primary, backup, counters) = answer_with(code, "off").await;
+        assert_eq!(status.as_u16(), code);
+        assert_eq!((primary, backup), (2, 0), "{code}");
+        assert_eq!(counters.endpoint_errors, 1, "{code}");
+        assert_eq!(counters.client_errors, 0, "{code}");
+    }
+}
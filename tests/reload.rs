@@ -0,0 +1,77 @@
+mod common;
+
+use common::{
+    config_toml, free_port, load_config_file, send_message_to, wait_for_port, write_config,
+    MockUpstream, TestProxy,
+};
+use hyper::StatusCode;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+/// Wait until nothing accepts connections on `port` any more
+async fn wait_for_refusal(port: u16) {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    for _ in 0..200 {
+        if TcpStream::connect(addr).await.is_err() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    panic!("port {port} still accepts connections");
+}
+
+#[tokio::test]
+async fn reload_moves_the_proxy_to_the_new_port() {
+    let upstream = MockUpstream::ok();
+    let (old_port, new_port) = (free_port(), free_port());
+    let path = write_config(&config_toml(old_port, &[&upstream.url], "", ""));
+    let proxy = TestProxy::start(load_config_file(&path)).await;
+    assert_eq!(proxy.send_message().await.0, StatusCode::OK);
+
+    std::fs::write(&path, config_toml(new_port, &[&upstream.url], "", "")).unwrap();
+    proxy.orchestrator.reload_config();
+
+    wait_for_port(new_port).await;
+    let (status, _) = send_message_to(new_port).await.expect("new port answers");
+    assert_eq!(status, StatusCode::OK);
+    wait_for_refusal(old_port).await;
+    assert!(send_message_to(old_port).await.is_err());
+    assert_eq!(upstream.request_count(), 2);
+}
+
+#[tokio::test]
+async fn reload_keeps_the_old_port_when_the_new_one_is_taken() {
+    let upstream = MockUpstream::ok();
+    let old_port = free_port();
+    let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let taken_port = taken.local_addr().unwrap().port();
+    let path = write_config(&config_toml(old_port, &[&upstream.url], "", ""));
+    let proxy = TestProxy::start(load_config_file(&path)).await;
+
+    // The rest of the new config still applies
+    let reloaded = config_toml(taken_port, &[&upstream.url], "switch_grace_seconds = 7", "");
+    std::fs::write(&path, reloaded).unwrap();
+    proxy.orchestrator.reload_config();
+    for _ in 0..200 {
+        if proxy
+            .state
+            .lock()
+            .unwrap()
+            .config
+            .server
+            .switch_grace_seconds
+            == 7
+        {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+
+    let (status, _) = proxy.send_message().await;
+    assert_eq!(status, StatusCode::OK);
+    let state = proxy.state.lock().unwrap();
+    assert_eq!(state.config.server.switch_grace_seconds, 7);
+    assert_eq!(state.config.server.port, old_port);
+    drop(taken);
+}
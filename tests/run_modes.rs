@@ -0,0 +1,147 @@
+mod common;
+
+use claude_zephyr::{ConsoleFields, ProxyEvent, ProxyServer, RunMode};
+use common::{config_toml, free_port, load_config, MockUpstream, TestProxy};
+use hyper::body::Bytes;
+use hyper::StatusCode;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Log output collected in memory
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// What a proxy did for the requests of [`serve_with_fallback`]
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    responses: Vec<(StatusCode, Bytes)>,
+    overloaded_requests: usize,
+    backup_requests: usize,
+    current_endpoint: String,
+    /// Names of the events the proxy emitted, in order
+    events: Vec<String>,
+}
+
+/// Name of an event's variant
+fn event_name(event: &ProxyEvent) -> String {
+    format!("{event:?}")
+        .chars()
+        .take_while(char::is_ascii_alphanumeric)
+        .collect()
+}
+
+/// Send three requests through a proxy in `mode` whose current endpoint
+/// answers 529, so the first falls back and moves the proxy to the backup.
+/// Returns what the proxy did and what it logged.
+async fn serve_with_fallback(mode: RunMode) -> (Outcome, String) {
+    let overloaded = MockUpstream::with_status(StatusCode::from_u16(529).unwrap());
+    let backup = MockUpstream::ok();
+    let port = free_port();
+    let toml = config_toml(
+        port,
+        &[&overloaded.url, &backup.url],
+        "",
+        "\n[retry]\nmax_retries = 2\nbase_delay_ms = 1\nfallback_on_status = [529]\n",
+    )
+    .replace(
+        "[health_check]\n",
+        "[health_check]\nstartup_check = false\n",
+    );
+
+    let logs = Capture::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .fmt_fields(ConsoleFields)
+        .with_ansi(false)
+        .finish();
+    // Every task runs on this thread in a current-thread runtime
+    let _logging = tracing::subscriber::set_default(subscriber);
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let builder = ProxyServer::builder(load_config(&toml))
+        .mode(mode)
+        .event_hook({
+            let events = Arc::clone(&events);
+            move |event| events.lock().unwrap().push(event_name(event))
+        });
+    let proxy = TestProxy::start_paused_with(builder).await;
+
+    let mut responses = Vec::new();
+    for _ in 0..3 {
+        responses.push(proxy.send_message().await);
+    }
+    // The endpoint switched away from drains last, within a drain poll
+    for _ in 0..100 {
+        if events
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|e| e == "EndpointDrained")
+        {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let current = proxy.state.lock().unwrap().current_url().to_string();
+    let outcome = Outcome {
+        responses,
+        overloaded_requests: overloaded.request_count(),
+        backup_requests: backup.request_count(),
+        // The URLs differ between runs, the endpoint names do not
+        current_endpoint: if current == backup.url {
+            "e1".to_string()
+        } else {
+            current
+        },
+        events: std::mem::take(&mut *events.lock().unwrap()),
+    };
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    (outcome, logs)
+}
+
+/// [`serve_with_fallback`] in a runtime of its own, which ends the tasks the
+/// proxy leaves behind before the next one logs
+fn serve_with_fallback_in(mode: RunMode) -> (Outcome, String) {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(serve_with_fallback(mode))
+}
+
+#[test]
+fn run_modes_differ_only_in_console_output() {
+    let (normal, normal_logs) = serve_with_fallback_in(RunMode::Normal);
+    let (dashboard, dashboard_logs) = serve_with_fallback_in(RunMode::Dashboard);
+
+    assert_eq!(normal, dashboard);
+    assert!(normal
+        .responses
+        .iter()
+        .all(|(status, _)| *status == StatusCode::OK));
+    assert_eq!((normal.overloaded_requests, normal.backup_requests), (1, 3));
+    assert_eq!(normal.current_endpoint, "e1");
+    assert!(normal.events.iter().any(|event| event == "EndpointSwitch"));
+    assert_eq!(normal.events.last().unwrap(), "EndpointDrained");
+
+    assert!(normal_logs.contains("Falling back"), "{normal_logs}");
+    assert!(
+        normal_logs.contains("SWITCHING ENDPOINT (fallback)"),
+        "{normal_logs}"
+    );
+    assert_eq!(dashboard_logs, "");
+}